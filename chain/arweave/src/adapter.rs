@@ -1,4 +1,6 @@
-use crate::{data_source::DataSource, Chain};
+use crate::codec;
+use crate::data_source::{DataSource, TagFilter};
+use crate::Chain;
 use graph::blockchain as bc;
 use graph::prelude::*;
 use sha2::{Digest, Sha256};
@@ -47,6 +49,11 @@ pub(crate) struct ArweaveTransactionFilter {
     owners_pubkey: HashSet<Vec<u8>>,
     owners_sha: HashSet<Vec<u8>>,
     match_all: bool,
+    /// Whether some transaction handler does not filter by tags, so that
+    /// the tags of a transaction do not matter
+    untagged: bool,
+    /// The tag filters of the transaction handlers that filter by tags
+    tag_filters: HashSet<Vec<TagFilter>>,
 }
 
 impl ArweaveTransactionFilter {
@@ -62,13 +69,36 @@ impl ArweaveTransactionFilter {
         self.owners_pubkey.contains(owner) || self.owners_sha.contains(&sha256(owner))
     }
 
+    /// Whether a transaction with `tags` is wanted by some transaction
+    /// handler. This only considers tags; the owner is checked by `matches`
+    pub fn matches_tags(&self, tags: &[codec::Tag]) -> bool {
+        self.untagged
+            || self
+                .tag_filters
+                .iter()
+                .any(|filter| filter.iter().all(|tag| tag.matches(tags)))
+    }
+
     pub fn from_data_sources<'a>(iter: impl IntoIterator<Item = &'a DataSource>) -> Self {
-        let owners: Vec<Vec<u8>> = iter
+        let data_sources: Vec<&DataSource> = iter
             .into_iter()
             .filter(|data_source| {
                 data_source.source.owner.is_some()
                     && !data_source.mapping.transaction_handlers.is_empty()
             })
+            .collect();
+
+        let handlers = data_sources
+            .iter()
+            .flat_map(|ds| ds.mapping.transaction_handlers.iter());
+        let untagged = handlers.clone().any(|handler| handler.tags.is_empty());
+        let tag_filters = handlers
+            .filter(|handler| !handler.tags.is_empty())
+            .map(|handler| handler.tags.clone())
+            .collect();
+
+        let owners: Vec<Vec<u8>> = data_sources
+            .into_iter()
             .map(|ds| match &ds.source.owner {
                 Some(str) if MATCH_ALL_WILDCARD.eq(str) => MATCH_ALL_WILDCARD.as_bytes().to_owned(),
                 owner => base64_url::decode(&owner.clone().unwrap_or_default()).unwrap_or_default(),
@@ -94,6 +124,8 @@ impl ArweaveTransactionFilter {
             match_all,
             owners_pubkey: HashSet::from_iter(owners_pubkey),
             owners_sha: HashSet::from_iter(owners_sha),
+            untagged,
+            tag_filters,
         }
     }
 
@@ -102,11 +134,15 @@ impl ArweaveTransactionFilter {
             owners_pubkey,
             owners_sha,
             match_all,
+            untagged,
+            tag_filters,
         } = self;
 
         owners_pubkey.extend(other.owners_pubkey);
         owners_sha.extend(other.owners_sha);
         *match_all = *match_all || other.match_all;
+        *untagged = *untagged || other.untagged;
+        tag_filters.extend(other.tag_filters);
     }
 }
 
@@ -144,7 +180,8 @@ mod test {
 
     use graph::{prelude::Link, semver::Version};
 
-    use crate::data_source::{DataSource, Mapping, Source, TransactionHandler};
+    use crate::codec::Tag;
+    use crate::data_source::{DataSource, Mapping, Source, TagFilter, TransactionHandler};
 
     use super::{ArweaveTransactionFilter, MATCH_ALL_WILDCARD};
 
@@ -234,6 +271,42 @@ mod test {
         assert_eq!(true, filter.matches(ARWEAVE_SHA_EXAMPLE.as_bytes()))
     }
 
+    #[test]
+    fn transaction_filter_tags() {
+        let tag = |name: &str, value: &str| Tag {
+            name: name.as_bytes().to_vec(),
+            value: value.as_bytes().to_vec(),
+        };
+
+        let mut ds = new_datasource(Some(MATCH_ALL_WILDCARD.into()), 10);
+        ds.mapping.transaction_handlers[0].tags = vec![
+            TagFilter {
+                name: "App-Name".into(),
+                value: Some("SmartWeaveContract".into()),
+            },
+            TagFilter {
+                name: "Contract-Src".into(),
+                value: None,
+            },
+        ];
+
+        let filter = ArweaveTransactionFilter::from_data_sources(vec![&ds]);
+        assert!(filter.matches_tags(&[
+            tag("Contract-Src", "abc"),
+            tag("App-Name", "SmartWeaveContract"),
+        ]));
+        assert!(!filter.matches_tags(&[tag("App-Name", "SmartWeaveContract")]));
+        assert!(!filter.matches_tags(&[tag("App-Name", "ArDrive"), tag("Contract-Src", "abc")]));
+        assert!(!filter.matches_tags(&[]));
+
+        // A handler without tags makes the tags of a transaction irrelevant
+        let mut filter = filter;
+        filter.extend(ArweaveTransactionFilter::from_data_sources(vec![
+            &new_datasource(Some("owner".into()), 10),
+        ]));
+        assert!(filter.matches_tags(&[]));
+    }
+
     fn new_datasource(owner: Option<String>, start_block: i32) -> DataSource {
         DataSource {
             kind: "".into(),
@@ -247,6 +320,7 @@ mod test {
                 block_handlers: vec![],
                 transaction_handlers: vec![TransactionHandler {
                     handler: "my_handler".into(),
+                    tags: vec![],
                 }],
                 runtime: Arc::new(vec![]),
                 link: Link { link: "".into() },
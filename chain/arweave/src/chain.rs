@@ -210,7 +210,9 @@ impl TriggersAdapterTrait<Chain> for TriggersAdapter {
             .clone()
             .txs
             .into_iter()
-            .filter(|tx| transaction_filter.matches(&tx.owner))
+            .filter(|tx| {
+                transaction_filter.matches(&tx.owner) && transaction_filter.matches_tags(&tx.tags)
+            })
             .map(|tx| trigger::TransactionWithBlockPtr {
                 tx: Arc::new(tx),
                 block: shared_block.clone(),
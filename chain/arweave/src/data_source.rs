@@ -1,6 +1,6 @@
 use graph::anyhow::Context;
 use graph::blockchain::{Block, TriggerWithHandler};
-use graph::components::store::StoredDynamicDataSource;
+use graph::components::store::{EntityType, StoredDynamicDataSource};
 use graph::data::subgraph::DataSourceContext;
 use graph::prelude::SubgraphManifestValidationError;
 use graph::{
@@ -15,6 +15,7 @@ use graph::{
 use std::sync::Arc;
 
 use crate::chain::Chain;
+use crate::codec;
 use crate::trigger::ArweaveTrigger;
 
 pub const ARWEAVE_KIND: &str = "arweave";
@@ -63,10 +64,11 @@ impl blockchain::DataSource<Chain> for DataSource {
                 Some(handler) => &handler.handler,
                 None => return Ok(None),
             },
-            // A transaction trigger matches if a transaction handler is present.
-            ArweaveTrigger::Transaction(_) => match self.handler_for_transaction() {
-                Some(handler) => &handler.handler,
-                None => return Ok(None),
+            // A transaction trigger matches if a transaction handler is present
+            // and the transaction has the tags that the handler filters by.
+            ArweaveTrigger::Transaction(tx) => match self.handler_for_transaction() {
+                Some(handler) if handler.matches_tags(&tx.tx.tags) => &handler.handler,
+                _ => return Ok(None),
             },
         };
 
@@ -160,6 +162,14 @@ impl blockchain::DataSource<Chain> for DataSource {
         if self.mapping.transaction_handlers.len() > 1 {
             errors.push(anyhow!("data source has duplicated transaction handlers"));
         }
+        for handler in &self.mapping.transaction_handlers {
+            if handler.tags.iter().any(|tag| tag.name.is_empty()) {
+                errors.push(anyhow!(
+                    "transaction handler `{}` can't filter by tags with an empty name",
+                    handler.handler
+                ));
+            }
+        }
 
         errors
     }
@@ -171,6 +181,20 @@ impl blockchain::DataSource<Chain> for DataSource {
     fn runtime(&self) -> Option<Arc<Vec<u8>>> {
         Some(self.mapping.runtime.cheap_clone())
     }
+
+    fn handler_names(&self) -> Vec<&str> {
+        self.mapping.handler_names()
+    }
+
+    fn declared_entities(&self) -> Option<Vec<EntityType>> {
+        Some(
+            self.mapping
+                .entities
+                .iter()
+                .map(|entity| EntityType::new(entity.clone()))
+                .collect(),
+        )
+    }
 }
 
 impl DataSource {
@@ -301,6 +325,10 @@ impl blockchain::DataSourceTemplate<Chain> for DataSourceTemplate {
     fn manifest_idx(&self) -> u32 {
         unreachable!("arweave does not support dynamic data sources")
     }
+
+    fn handler_names(&self) -> Vec<&str> {
+        self.mapping.handler_names()
+    }
 }
 
 #[derive(Clone, Debug, Default, Hash, Eq, PartialEq, Deserialize)]
@@ -361,6 +389,20 @@ pub struct Mapping {
     pub link: Link,
 }
 
+impl Mapping {
+    pub fn handler_names(&self) -> Vec<&str> {
+        self.block_handlers
+            .iter()
+            .map(|handler| handler.handler.as_str())
+            .chain(
+                self.transaction_handlers
+                    .iter()
+                    .map(|handler| handler.handler.as_str()),
+            )
+            .collect()
+    }
+}
+
 #[derive(Clone, Debug, Hash, Eq, PartialEq, Deserialize)]
 pub struct MappingBlockHandler {
     pub handler: String,
@@ -369,6 +411,36 @@ pub struct MappingBlockHandler {
 #[derive(Clone, Debug, Hash, Eq, PartialEq, Deserialize)]
 pub struct TransactionHandler {
     pub handler: String,
+    /// The tags that a transaction must have for the handler to be called
+    #[serde(default)]
+    pub tags: Vec<TagFilter>,
+}
+
+impl TransactionHandler {
+    /// Whether `tags` include all the tags that the handler filters by
+    pub(crate) fn matches_tags(&self, tags: &[codec::Tag]) -> bool {
+        self.tags.iter().all(|filter| filter.matches(tags))
+    }
+}
+
+/// A tag that a transaction must have. If `value` is not set, the
+/// transaction only needs to have a tag with that name
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Deserialize)]
+pub struct TagFilter {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+impl TagFilter {
+    pub(crate) fn matches(&self, tags: &[codec::Tag]) -> bool {
+        tags.iter().any(|tag| {
+            tag.name == self.name.as_bytes()
+                && self
+                    .value
+                    .as_ref()
+                    .map_or(true, |value| tag.value == value.as_bytes())
+        })
+    }
 }
 
 #[derive(Clone, Debug, Hash, Eq, PartialEq, Deserialize)]
@@ -1,9 +1,11 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use prost::Message;
 use prost_types::Any;
 
-use crate::{data_source::DataSource, Chain};
+use crate::codec;
+use crate::data_source::{DataSource, EventAttributeFilter};
+use crate::Chain;
 use graph::blockchain as bc;
 use graph::firehose::EventTypeFilter;
 use graph::prelude::*;
@@ -14,6 +16,7 @@ const EVENT_TYPE_FILTER_TYPE_URL: &str =
 #[derive(Clone, Debug, Default)]
 pub struct TriggerFilter {
     pub(crate) event_type_filter: CosmosEventTypeFilter,
+    pub(crate) message_type_filter: CosmosMessageTypeFilter,
     pub(crate) block_filter: CosmosBlockFilter,
 }
 
@@ -21,6 +24,8 @@ impl bc::TriggerFilter<Chain> for TriggerFilter {
     fn extend<'a>(&mut self, data_sources: impl Iterator<Item = &'a DataSource> + Clone) {
         self.event_type_filter
             .extend_from_data_sources(data_sources.clone());
+        self.message_type_filter
+            .extend_from_data_sources(data_sources.clone());
         self.block_filter.extend_from_data_sources(data_sources);
     }
 
@@ -43,6 +48,13 @@ impl bc::TriggerFilter<Chain> for TriggerFilter {
             return vec![];
         }
 
+        // The Firehose filter drops transactions that do not emit one of
+        // the event types, and with them the messages that message
+        // handlers need to see
+        if !self.message_type_filter.message_types.is_empty() {
+            return vec![];
+        }
+
         let filter = EventTypeFilter {
             event_types: Vec::from_iter(self.event_type_filter.event_types),
         };
@@ -59,16 +71,60 @@ pub type EventType = String;
 #[derive(Clone, Debug, Default)]
 pub(crate) struct CosmosEventTypeFilter {
     pub event_types: HashSet<EventType>,
+    /// The attribute filters of the handlers of each event type. Event
+    /// types that have a handler without attribute filters are not in
+    /// here since all their events are triggers. The Firehose filter can
+    /// only select events by type, and the attributes are checked when
+    /// the triggers of a block are collected
+    pub attribute_filters: HashMap<EventType, Vec<Vec<EventAttributeFilter>>>,
 }
 
 impl CosmosEventTypeFilter {
-    pub(crate) fn matches(&self, event_type: &EventType) -> bool {
-        self.event_types.contains(event_type)
+    pub(crate) fn matches(&self, event: &codec::Event) -> bool {
+        if !self.event_types.contains(&event.event_type) {
+            return false;
+        }
+        match self.attribute_filters.get(&event.event_type) {
+            None => true,
+            Some(filters) => filters
+                .iter()
+                .any(|filter| filter.iter().all(|attribute| attribute.matches(event))),
+        }
     }
 
     fn extend_from_data_sources<'a>(&mut self, data_sources: impl Iterator<Item = &'a DataSource>) {
-        self.event_types.extend(
-            data_sources.flat_map(|data_source| data_source.events().map(ToString::to_string)),
+        for handler in data_sources.flat_map(|data_source| &data_source.mapping.event_handlers) {
+            let known = !self.event_types.insert(handler.event.clone());
+            if handler.attributes.is_empty() {
+                self.attribute_filters.remove(&handler.event);
+            } else if !known || self.attribute_filters.contains_key(&handler.event) {
+                self.attribute_filters
+                    .entry(handler.event.clone())
+                    .or_default()
+                    .push(handler.attributes.clone());
+            }
+        }
+    }
+}
+
+/// The type URLs of the transaction messages that message handlers are
+/// interested in. Messages can not be selected by Firehose, and are
+/// filtered when the triggers of a block are collected
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CosmosMessageTypeFilter {
+    pub message_types: HashSet<String>,
+}
+
+impl CosmosMessageTypeFilter {
+    pub(crate) fn matches(&self, message: &Any) -> bool {
+        self.message_types.contains(&message.type_url)
+    }
+
+    fn extend_from_data_sources<'a>(&mut self, data_sources: impl Iterator<Item = &'a DataSource>) {
+        self.message_types.extend(
+            data_sources
+                .flat_map(|data_source| &data_source.mapping.message_handlers)
+                .map(|handler| handler.message.clone()),
         );
     }
 }
@@ -105,6 +161,11 @@ mod test {
                 TriggerFilter::test_new(false, &["event_1", "event_2", "event_3"]),
                 Some(event_type_filter_with(&["event_1", "event_2", "event_3"])),
             ),
+            (
+                TriggerFilter::test_new(false, &["event_1"])
+                    .with_message_types(&["/cosmos.bank.v1beta1.MsgSend"]),
+                None,
+            ),
         ];
 
         for (trigger_filter, expected_filter) in cases {
@@ -130,12 +191,20 @@ mod test {
             TriggerFilter {
                 event_type_filter: CosmosEventTypeFilter {
                     event_types: event_types.iter().map(ToString::to_string).collect(),
+                    attribute_filters: HashMap::new(),
                 },
+                message_type_filter: CosmosMessageTypeFilter::default(),
                 block_filter: CosmosBlockFilter {
                     trigger_every_block,
                 },
             }
         }
+
+        pub(crate) fn with_message_types(mut self, message_types: &[&str]) -> TriggerFilter {
+            self.message_type_filter.message_types =
+                message_types.iter().map(ToString::to_string).collect();
+            self
+        }
     }
 
     fn event_type_filter_with(event_types: &[&str]) -> EventTypeFilter {
@@ -258,13 +258,19 @@ impl TriggersAdapterTrait<Chain> for TriggersAdapter {
             let mut triggers: Vec<_> = Vec::new();
             if let Some(tx) = tx_result.tx.clone() {
                 if let Some(tx_body) = tx.body {
-                    triggers.extend(tx_body.messages.into_iter().map(|message| {
-                        CosmosTrigger::with_message(
-                            message,
-                            header_only_block.clone(),
-                            build_tx_context(&tx_result),
-                        )
-                    }));
+                    triggers.extend(
+                        tx_body
+                            .messages
+                            .into_iter()
+                            .filter(|message| filter.message_type_filter.matches(message))
+                            .map(|message| {
+                                CosmosTrigger::with_message(
+                                    message,
+                                    header_only_block.clone(),
+                                    build_tx_context(&tx_result),
+                                )
+                            }),
+                    );
                 }
             }
             triggers.push(CosmosTrigger::with_transaction(
@@ -303,7 +309,7 @@ fn filter_event_trigger(
     tx_context: Option<codec::TransactionContext>,
     origin: EventOrigin,
 ) -> Option<CosmosTrigger> {
-    if filter.event_type_filter.matches(&event.event_type) {
+    if filter.event_type_filter.matches(&event) {
         Some(CosmosTrigger::with_event(
             event,
             block.clone(),
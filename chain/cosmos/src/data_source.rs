@@ -219,6 +219,20 @@ impl blockchain::DataSource<Chain> for DataSource {
     fn runtime(&self) -> Option<Arc<Vec<u8>>> {
         Some(self.mapping.runtime.cheap_clone())
     }
+
+    fn handler_names(&self) -> Vec<&str> {
+        self.mapping.handler_names()
+    }
+
+    fn declared_entities(&self) -> Option<Vec<EntityType>> {
+        Some(
+            self.mapping
+                .entities
+                .iter()
+                .map(|entity| EntityType::new(entity.clone()))
+                .collect(),
+        )
+    }
 }
 
 impl DataSource {
@@ -277,20 +291,13 @@ impl DataSource {
                     event_type_matches
                 }
             })
+            .filter(|handler| handler.matches_attributes(event))
             .cloned()
     }
 
     pub(crate) fn has_block_handler(&self) -> bool {
         !self.mapping.block_handlers.is_empty()
     }
-
-    /// Return an iterator over all event types from event handlers.
-    pub(crate) fn events(&self) -> impl Iterator<Item = &str> {
-        self.mapping
-            .event_handlers
-            .iter()
-            .map(|handler| handler.event.as_str())
-    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
@@ -370,6 +377,10 @@ impl blockchain::DataSourceTemplate<Chain> for DataSourceTemplate {
     fn manifest_idx(&self) -> u32 {
         unimplemented!("{}", TEMPLATE_ERROR);
     }
+
+    fn handler_names(&self) -> Vec<&str> {
+        unimplemented!("{}", TEMPLATE_ERROR);
+    }
 }
 
 #[derive(Clone, Debug, Default, Hash, Eq, PartialEq, Deserialize)]
@@ -440,6 +451,30 @@ pub struct Mapping {
     pub link: Link,
 }
 
+impl Mapping {
+    pub fn handler_names(&self) -> Vec<&str> {
+        self.block_handlers
+            .iter()
+            .map(|handler| handler.handler.as_str())
+            .chain(
+                self.event_handlers
+                    .iter()
+                    .map(|handler| handler.handler.as_str()),
+            )
+            .chain(
+                self.transaction_handlers
+                    .iter()
+                    .map(|handler| handler.handler.as_str()),
+            )
+            .chain(
+                self.message_handlers
+                    .iter()
+                    .map(|handler| handler.handler.as_str()),
+            )
+            .collect()
+    }
+}
+
 #[derive(Clone, Debug, Hash, Eq, PartialEq, Deserialize)]
 pub struct MappingBlockHandler {
     pub handler: String,
@@ -450,6 +485,37 @@ pub struct MappingEventHandler {
     pub event: String,
     pub origin: Option<EventOrigin>,
     pub handler: String,
+    /// The attributes that an event must have for the handler to be called
+    #[serde(default)]
+    pub attributes: Vec<EventAttributeFilter>,
+}
+
+impl MappingEventHandler {
+    /// Whether `event` has all the attributes that the handler filters by
+    pub(crate) fn matches_attributes(&self, event: &codec::Event) -> bool {
+        self.attributes.iter().all(|filter| filter.matches(event))
+    }
+}
+
+/// An attribute that an event must have. Keys and values are compared with
+/// the attributes as they appear in the block. If `value` is not set, the
+/// event only needs to have an attribute with that key
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Deserialize)]
+pub struct EventAttributeFilter {
+    pub key: String,
+    pub value: Option<String>,
+}
+
+impl EventAttributeFilter {
+    pub(crate) fn matches(&self, event: &codec::Event) -> bool {
+        event.attributes.iter().any(|attribute| {
+            attribute.key == self.key
+                && self
+                    .value
+                    .as_ref()
+                    .map_or(true, |value| *value == attribute.value)
+        })
+    }
 }
 
 #[derive(Clone, Debug, Hash, Eq, PartialEq, Deserialize)]
@@ -507,7 +573,81 @@ fn duplicate_url_type(message: &str) -> Error {
 mod tests {
     use super::*;
 
-    use graph::blockchain::DataSource as _;
+    use graph::blockchain::{DataSource as _, TriggerFilter as _};
+
+    use crate::adapter::TriggerFilter;
+
+    #[test]
+    fn test_event_attribute_filters() {
+        fn event(event_type: &str, attributes: &[(&str, &str)]) -> codec::Event {
+            codec::Event {
+                event_type: event_type.to_string(),
+                attributes: attributes
+                    .iter()
+                    .map(|(key, value)| codec::EventAttribute {
+                        key: key.to_string(),
+                        value: value.to_string(),
+                        index: true,
+                    })
+                    .collect(),
+            }
+        }
+
+        let mut transfer = MappingEventHandler::with_origin("transfer", None);
+        transfer.attributes = vec![
+            EventAttributeFilter {
+                key: "recipient".to_string(),
+                value: Some("alice".to_string()),
+            },
+            EventAttributeFilter {
+                key: "amount".to_string(),
+                value: None,
+            },
+        ];
+        let data_source = DataSource::with_event_handlers(vec![
+            transfer,
+            MappingEventHandler::with_origin("message", None),
+        ]);
+        let mut filter = TriggerFilter::default();
+        filter.extend(vec![&data_source].into_iter());
+        let filter = filter.event_type_filter;
+
+        let cases = [
+            (
+                event("transfer", &[("recipient", "alice"), ("amount", "1")]),
+                true,
+            ),
+            (
+                event("transfer", &[("recipient", "bob"), ("amount", "1")]),
+                false,
+            ),
+            (event("transfer", &[("recipient", "alice")]), false),
+            (event("message", &[]), true),
+            (event("coin_spent", &[]), false),
+        ];
+        for (event, matches) in cases {
+            assert_eq!(matches, filter.matches(&event), "{:?}", event);
+            assert_eq!(
+                matches,
+                data_source
+                    .handler_for_event(&event, EventOrigin::DeliverTx)
+                    .is_some(),
+                "{:?}",
+                event
+            );
+        }
+
+        // A handler without attribute filters makes all events of its type
+        // triggers
+        let other = DataSource::with_event_handlers(vec![MappingEventHandler::with_origin(
+            "transfer", None,
+        )]);
+        let mut filter = TriggerFilter::default();
+        filter.extend(vec![&data_source, &other].into_iter());
+        assert!(filter
+            .event_type_filter
+            .matches(&event("transfer", &[("recipient", "bob")])));
+    }
 
     #[test]
     fn test_event_handlers_origin_validation() {
@@ -665,6 +805,7 @@ mod tests {
                 event: event_type.to_string(),
                 origin,
                 handler: "handler".to_string(),
+                attributes: vec![],
             }
         }
     }
@@ -19,7 +19,10 @@ use web3::types::{Address, Log, H256};
 use graph::prelude::*;
 use graph::{
     blockchain as bc,
-    components::metrics::{CounterVec, GaugeVec, HistogramVec},
+    components::metrics::{
+        resources::{DeploymentResources, RESOURCE_USAGE},
+        CounterVec, GaugeVec, HistogramVec,
+    },
     petgraph::{self, graphmap::GraphMap},
 };
 
@@ -52,8 +55,11 @@ pub enum EthereumContractCallError {
     EncodingError(ethabi::Error),
     #[error("call error: {0}")]
     Web3Error(web3::Error),
+    /// The call reverted for the reason in the first field. The second
+    /// field holds the data that the contract reverted with; it is empty if
+    /// the provider did not return it
     #[error("call reverted: {0}")]
-    Revert(String),
+    Revert(String, Vec<u8>),
     #[error("ethereum node took too long to perform call")]
     Timeout,
 }
@@ -784,6 +790,7 @@ pub struct SubgraphEthRpcMetrics {
     request_duration: GaugeVec,
     errors: CounterVec,
     deployment: String,
+    resources: Arc<DeploymentResources>,
 }
 
 impl SubgraphEthRpcMetrics {
@@ -806,6 +813,7 @@ impl SubgraphEthRpcMetrics {
             request_duration,
             errors,
             deployment: subgraph_hash.into(),
+            resources: RESOURCE_USAGE.deployment(subgraph_hash),
         }
     }
 
@@ -813,6 +821,7 @@ impl SubgraphEthRpcMetrics {
         self.request_duration
             .with_label_values(&[&self.deployment, method, provider])
             .set(duration);
+        self.resources.add_rpc(Duration::from_secs_f64(duration));
     }
 
     pub fn add_error(&self, method: &str, provider: &str) {
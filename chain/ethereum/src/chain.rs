@@ -447,7 +447,9 @@ impl Blockchain for Chain {
                         .component_logger("EthereumFirehoseBlockIngestor", None),
                     self.name.clone(),
                 );
-                let ingestor = ingestor.with_transforms(vec![Transforms::EthereumHeaderOnly]);
+                let ingestor = ingestor
+                    .with_transforms(vec![Transforms::EthereumHeaderOnly])
+                    .with_cleanup(ENV_VARS.cleanup_blocks);
 
                 Box::new(ingestor)
             }
@@ -578,6 +580,24 @@ impl Block for BlockFinality {
 
 pub struct DummyDataSourceTemplate;
 
+/// The triggers that `filter` selects in `block`. This needs no provider
+/// since the block already contains its receipts and calls
+pub fn parse_triggers(
+    logger: &Logger,
+    block: EthereumBlockWithCalls,
+    filter: &TriggerFilter,
+) -> Result<BlockWithTriggers<Chain>, Error> {
+    let mut triggers = Vec::new();
+    triggers.append(&mut parse_log_triggers(&filter.log, &block.ethereum_block));
+    triggers.append(&mut parse_call_triggers(&filter.call, &block)?);
+    triggers.append(&mut parse_block_triggers(&filter.block, &block));
+    Ok(BlockWithTriggers::new(
+        BlockFinality::NonFinal(block),
+        triggers,
+        logger,
+    ))
+}
+
 pub struct TriggersAdapter {
     logger: Logger,
     ethrpc_metrics: Arc<SubgraphEthRpcMetrics>,
@@ -624,10 +644,10 @@ impl TriggersAdapterTrait<Chain> for TriggersAdapter {
         )
         .await?;
 
-        match &block {
+        let block_number = block.number() as BlockNumber;
+        match block {
             BlockFinality::Final(_) => {
                 let adapter = self.chain_client.rpc()?.cheapest_with(&self.capabilities)?;
-                let block_number = block.number() as BlockNumber;
                 let blocks = blocks_with_triggers(
                     adapter,
                     logger.clone(),
@@ -642,16 +662,7 @@ impl TriggersAdapterTrait<Chain> for TriggersAdapter {
                 assert!(blocks.len() == 1);
                 Ok(blocks.into_iter().next().unwrap())
             }
-            BlockFinality::NonFinal(full_block) => {
-                let mut triggers = Vec::new();
-                triggers.append(&mut parse_log_triggers(
-                    &filter.log,
-                    &full_block.ethereum_block,
-                ));
-                triggers.append(&mut parse_call_triggers(&filter.call, full_block)?);
-                triggers.append(&mut parse_block_triggers(&filter.block, full_block));
-                Ok(BlockWithTriggers::new(block, triggers, logger))
-            }
+            BlockFinality::NonFinal(full_block) => parse_triggers(logger, full_block, filter),
         }
     }
 
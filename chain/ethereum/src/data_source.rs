@@ -1,7 +1,7 @@
 use anyhow::{anyhow, Error};
 use anyhow::{ensure, Context};
 use graph::blockchain::TriggerWithHandler;
-use graph::components::store::StoredDynamicDataSource;
+use graph::components::store::{EntityType, StoredDynamicDataSource};
 use graph::data_source::CausalityRegion;
 use graph::prelude::ethabi::ethereum_types::H160;
 use graph::prelude::ethabi::StateMutability;
@@ -286,6 +286,20 @@ impl blockchain::DataSource<Chain> for DataSource {
     fn runtime(&self) -> Option<Arc<Vec<u8>>> {
         Some(self.mapping.runtime.cheap_clone())
     }
+
+    fn handler_names(&self) -> Vec<&str> {
+        self.mapping.handler_names()
+    }
+
+    fn declared_entities(&self) -> Option<Vec<EntityType>> {
+        Some(
+            self.mapping
+                .entities
+                .iter()
+                .map(|entity| EntityType::new(entity.clone()))
+                .collect(),
+        )
+    }
 }
 
 impl DataSource {
@@ -880,6 +894,10 @@ impl blockchain::DataSourceTemplate<Chain> for DataSourceTemplate {
     fn manifest_idx(&self) -> u32 {
         self.manifest_idx
     }
+
+    fn handler_names(&self) -> Vec<&str> {
+        self.mapping.handler_names()
+    }
 }
 
 #[derive(Clone, Debug, Default, Hash, Eq, PartialEq, Deserialize)]
@@ -914,6 +932,23 @@ pub struct Mapping {
 }
 
 impl Mapping {
+    pub fn handler_names(&self) -> Vec<&str> {
+        self.block_handlers
+            .iter()
+            .map(|handler| handler.handler.as_str())
+            .chain(
+                self.call_handlers
+                    .iter()
+                    .map(|handler| handler.handler.as_str()),
+            )
+            .chain(
+                self.event_handlers
+                    .iter()
+                    .map(|handler| handler.handler.as_str()),
+            )
+            .collect()
+    }
+
     pub fn requires_archive(&self) -> anyhow::Result<bool> {
         calls_host_fn(&self.runtime, "ethereum.call")
     }
@@ -83,6 +83,16 @@ pub struct EnvVars {
     /// The time to wait between polls when using polling block ingestor.
     /// The value is set in millis and the default is 1000.
     pub ingestor_polling_interval: Duration,
+    /// Run every `ethereum.call` that mappings make against a second
+    /// provider as well and report calls whose results differ.
+    ///
+    /// Set by the flag `GRAPH_ETH_CALL_VERIFY`. Off by default.
+    pub eth_call_verification: bool,
+    /// Fail the subgraph with a deterministic error when the results of a
+    /// verified call differ instead of only reporting it.
+    ///
+    /// Set by the flag `GRAPH_ETH_CALL_VERIFY_FAIL`. Off by default.
+    pub eth_call_verification_fail: bool,
 }
 
 // This does not print any values avoid accidentally leaking any sensitive env vars
@@ -124,6 +134,8 @@ impl From<Inner> for EnvVars {
             target_triggers_per_block_range: x.target_triggers_per_block_range,
             genesis_block_number: x.genesis_block_number,
             ingestor_polling_interval: Duration::from_millis(x.ingestor_polling_interval),
+            eth_call_verification: x.eth_call_verification.0,
+            eth_call_verification_fail: x.eth_call_verification_fail.0,
         }
     }
 }
@@ -171,4 +183,8 @@ struct Inner {
     genesis_block_number: u64,
     #[envconfig(from = "ETHEREUM_POLLING_INTERVAL", default = "1000")]
     ingestor_polling_interval: u64,
+    #[envconfig(from = "GRAPH_ETH_CALL_VERIFY", default = "false")]
+    eth_call_verification: EnvVarBoolean,
+    #[envconfig(from = "GRAPH_ETH_CALL_VERIFY_FAIL", default = "false")]
+    eth_call_verification_fail: EnvVarBoolean,
 }
@@ -437,7 +437,7 @@ impl EthereumAdapter {
         let retry_log_message = format!("eth_call RPC call for block {}", block_ptr);
         retry(retry_log_message, &logger)
             .when(|result| match result {
-                Ok(_) | Err(EthereumContractCallError::Revert(_)) => false,
+                Ok(_) | Err(EthereumContractCallError::Revert(..)) => false,
                 Err(_) => true,
             })
             .limit(ENV_VARS.request_retries)
@@ -527,7 +527,15 @@ impl EthereumAdapter {
                             if geth_execution_errors
                                 .any(|e| rpc_error.message.to_lowercase().contains(e)) =>
                         {
-                            Err(EthereumContractCallError::Revert(rpc_error.message))
+                            // Geth returns the data the contract reverted
+                            // with as a hex string
+                            let data = rpc_error
+                                .data
+                                .as_ref()
+                                .and_then(|data| data.as_str())
+                                .and_then(|data| hex::decode(data.trim_start_matches("0x")).ok())
+                                .unwrap_or_default();
+                            Err(EthereumContractCallError::Revert(rpc_error.message, data))
                         }
 
                         // Check for Parity revert.
@@ -544,18 +552,19 @@ impl EthereumAdapter {
                                         || data == PARITY_OUT_OF_GAS
                                         || data == XDAI_REVERT =>
                                 {
+                                    let payload = if data.starts_with(PARITY_REVERT_PREFIX) {
+                                        hex::decode(data.trim_start_matches(PARITY_REVERT_PREFIX))
+                                            .unwrap_or_default()
+                                    } else {
+                                        vec![]
+                                    };
                                     let reason = if data == PARITY_BAD_INSTRUCTION_FE {
                                         PARITY_BAD_INSTRUCTION_FE.to_owned()
                                     } else {
-                                        let payload = data.trim_start_matches(PARITY_REVERT_PREFIX);
-                                        hex::decode(payload)
-                                            .ok()
-                                            .and_then(|payload| {
-                                                as_solidity_revert_with_reason(&payload)
-                                            })
+                                        as_solidity_revert_with_reason(&payload)
                                             .unwrap_or("no reason".to_owned())
                                     };
-                                    Err(EthereumContractCallError::Revert(reason))
+                                    Err(EthereumContractCallError::Revert(reason, payload))
                                 }
 
                                 // The VM execution error was not identified as a revert.
@@ -1247,12 +1256,18 @@ impl EthereumAdapterTrait for EthereumAdapter {
                     // We got a `0x` response. For old Geth, this can mean a revert. It can also be
                     // that the contract actually returned an empty response. A view call is meant
                     // to return something, so we treat empty responses the same as reverts.
-                    Err(EthereumContractCallError::Revert("empty response".into()))
+                    Err(EthereumContractCallError::Revert(
+                        "empty response".into(),
+                        vec![],
+                    ))
                 } else {
                     // Decode failures are reverts. The reasoning is that if Solidity fails to
                     // decode an argument, that's a revert, so the same goes for the output.
                     call.function.decode_output(&output).map_err(|e| {
-                        EthereumContractCallError::Revert(format!("failed to decode output: {}", e))
+                        EthereumContractCallError::Revert(
+                            format!("failed to decode output: {}", e),
+                            vec![],
+                        )
                     })
                 }
             }),
@@ -2,6 +2,7 @@ use crate::{chain::BlockFinality, EthereumAdapter, EthereumAdapterTrait, ENV_VAR
 use graph::{
     blockchain::{BlockHash, BlockIngestor, BlockPtr, IngestorError},
     cheap_clone::CheapClone,
+    components::metrics::ingestors::{IngestorHealth, INGESTOR_HEALTH},
     prelude::{
         async_trait, error, ethabi::ethereum_types::H256, info, tokio, trace, warn, ChainStore,
         Error, EthereumBlockWithCalls, Future01CompatExt, LogCode, Logger,
@@ -16,6 +17,7 @@ pub struct PollingBlockIngestor {
     chain_store: Arc<dyn ChainStore>,
     polling_interval: Duration,
     network_name: String,
+    health: Arc<IngestorHealth>,
 }
 
 impl PollingBlockIngestor {
@@ -27,6 +29,8 @@ impl PollingBlockIngestor {
         polling_interval: Duration,
         network_name: String,
     ) -> Result<PollingBlockIngestor, Error> {
+        let health = INGESTOR_HEALTH.chain(&network_name);
+        health.set_provider(eth_adapter.provider());
         Ok(PollingBlockIngestor {
             logger,
             ancestor_count,
@@ -34,6 +38,7 @@ impl PollingBlockIngestor {
             chain_store,
             polling_interval,
             network_name,
+            health,
         })
     }
 
@@ -147,6 +152,10 @@ impl PollingBlockIngestor {
         while let Some(hash) = missing_block_hash {
             missing_block_hash = self.ingest_block(&hash).await?;
         }
+
+        if let Some(head) = self.chain_store.cheap_clone().chain_head_ptr().await? {
+            self.health.observe_block(head, Some(latest_block.number));
+        }
         Ok(())
     }
 
@@ -212,6 +221,7 @@ impl BlockIngestor for PollingBlockIngestor {
                         self.logger,
                         "Trying again after block polling failed: {}", err
                     );
+                    self.health.observe_error(&err.to_string());
                 }
                 Ok(()) => (),
             }
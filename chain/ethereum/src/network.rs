@@ -1,12 +1,12 @@
 use anyhow::{anyhow, bail};
 use graph::cheap_clone::CheapClone;
-use graph::endpoint::EndpointMetrics;
+use graph::endpoint::{EndpointMetrics, ProviderHealth};
 use graph::firehose::{AvailableCapacity, SubgraphLimit};
 use graph::prelude::rand::seq::IteratorRandom;
 use graph::prelude::rand::{self, Rng};
 use std::cmp::Ordering;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
 pub use graph::impl_slog_value;
 use graph::prelude::Error;
@@ -44,16 +44,28 @@ impl EthereumNetworkAdapter {
     pub fn provider(&self) -> &str {
         self.adapter.provider()
     }
+
+    pub fn health(&self) -> ProviderHealth {
+        self.endpoint_metrics.health(&self.provider().into())
+    }
 }
 
+/// The adapters for one network. Clones share their adapters so that
+/// replacing them, e.g., when the configuration is reloaded, affects every
+/// user of the network
 #[derive(Debug, Clone)]
 pub struct EthereumNetworkAdapters {
-    pub adapters: Vec<EthereumNetworkAdapter>,
-    call_only_adapters: Vec<EthereumNetworkAdapter>,
+    inner: Arc<RwLock<Adapters>>,
     // Percentage of request that should be used to retest errored adapters.
     retest_percent: f64,
 }
 
+#[derive(Debug, Default)]
+struct Adapters {
+    adapters: Vec<EthereumNetworkAdapter>,
+    call_only_adapters: Vec<EthereumNetworkAdapter>,
+}
+
 impl Default for EthereumNetworkAdapters {
     fn default() -> Self {
         Self::new(None)
@@ -63,33 +75,66 @@ impl Default for EthereumNetworkAdapters {
 impl EthereumNetworkAdapters {
     pub fn new(retest_percent: Option<f64>) -> Self {
         Self {
-            adapters: vec![],
-            call_only_adapters: vec![],
+            inner: Arc::new(RwLock::new(Adapters::default())),
             retest_percent: retest_percent.unwrap_or(DEFAULT_ADAPTER_ERROR_RETEST_PERCENT),
         }
     }
 
     pub fn push_adapter(&mut self, adapter: EthereumNetworkAdapter) {
+        let mut inner = self.inner.write().unwrap();
         if adapter.is_call_only() {
-            self.call_only_adapters.push(adapter);
+            inner.call_only_adapters.push(adapter);
         } else {
-            self.adapters.push(adapter);
+            inner.adapters.push(adapter);
         }
     }
+
+    /// Replace the adapters with those of `other` for this network and all
+    /// its clones. Callers that already hold an adapter keep using it
+    pub fn replace(&self, other: &EthereumNetworkAdapters) {
+        let (adapters, call_only_adapters) = {
+            let other = other.inner.read().unwrap();
+            (other.adapters.clone(), other.call_only_adapters.clone())
+        };
+        let mut inner = self.inner.write().unwrap();
+        inner.adapters = adapters;
+        inner.call_only_adapters = call_only_adapters;
+    }
+
+    /// The adapters that are not call-only
+    pub fn adapters(&self) -> Vec<EthereumNetworkAdapter> {
+        self.inner.read().unwrap().adapters.clone()
+    }
+
+    /// All adapters, including the ones that can only be used for calls
+    pub fn all_adapters(&self) -> Vec<EthereumNetworkAdapter> {
+        let inner = self.inner.read().unwrap();
+        inner
+            .adapters
+            .iter()
+            .chain(inner.call_only_adapters.iter())
+            .cloned()
+            .collect()
+    }
+
     pub fn all_cheapest_with(
         &self,
         required_capabilities: &NodeCapabilities,
-    ) -> impl Iterator<Item = &EthereumNetworkAdapter> + '_ {
-        let cheapest_sufficient_capability = self
+    ) -> Vec<EthereumNetworkAdapter> {
+        let inner = self.inner.read().unwrap();
+        let cheapest_sufficient_capability = inner
             .adapters
             .iter()
             .find(|adapter| &adapter.capabilities >= required_capabilities)
             .map(|adapter| &adapter.capabilities);
 
-        self.adapters
+        inner
+            .adapters
             .iter()
-            .filter(move |adapter| Some(&adapter.capabilities) == cheapest_sufficient_capability)
+            .filter(|adapter| Some(&adapter.capabilities) == cheapest_sufficient_capability)
             .filter(|adapter| adapter.get_capacity() > AvailableCapacity::Unavailable)
+            .cloned()
+            .collect()
     }
 
     pub fn cheapest_with(
@@ -97,9 +142,8 @@ impl EthereumNetworkAdapters {
         required_capabilities: &NodeCapabilities,
     ) -> Result<Arc<EthereumAdapter>, Error> {
         let retest_rng: f64 = (&mut rand::thread_rng()).gen();
-        let cheapest = self
-            .all_cheapest_with(required_capabilities)
-            .choose_multiple(&mut rand::thread_rng(), 3);
+        let adapters = self.all_cheapest_with(required_capabilities);
+        let cheapest = adapters.iter().choose_multiple(&mut rand::thread_rng(), 3);
         let cheapest = cheapest.iter();
 
         // If request falls below the retest threshold, use this request to try and
@@ -123,19 +167,53 @@ impl EthereumNetworkAdapters {
         ))
     }
 
+    /// An adapter with at least `required_capabilities` whose provider is
+    /// not `provider`, including call-only adapters. Used to check results
+    /// that `provider` returned against an independent provider
+    pub fn other_than(
+        &self,
+        provider: &str,
+        required_capabilities: &NodeCapabilities,
+    ) -> Option<Arc<EthereumAdapter>> {
+        self.all_adapters()
+            .into_iter()
+            .filter(|adapter| adapter.provider() != provider)
+            .filter(|adapter| &adapter.capabilities >= required_capabilities)
+            .filter(|adapter| adapter.get_capacity() > AvailableCapacity::Unavailable)
+            .min_by_key(|adapter| adapter.current_error_count())
+            .map(|adapter| adapter.adapter)
+    }
+
     pub fn cheapest(&self) -> Option<Arc<EthereumAdapter>> {
         // EthereumAdapters are sorted by their NodeCapabilities when the EthereumNetworks
         // struct is instantiated so they do not need to be sorted here
-        self.adapters
+        self.inner
+            .read()
+            .unwrap()
+            .adapters
             .first()
             .map(|ethereum_network_adapter| ethereum_network_adapter.adapter.clone())
     }
 
     pub fn remove(&mut self, provider: &str) {
-        self.adapters
+        self.inner
+            .write()
+            .unwrap()
+            .adapters
             .retain(|adapter| adapter.adapter.provider() != provider);
     }
 
+    fn sort(&mut self) {
+        self.inner.write().unwrap().adapters.sort_by(|a, b| {
+            a.capabilities
+                .partial_cmp(&b.capabilities)
+                // We can't define a total ordering over node capabilities,
+                // so incomparable items are considered equal and end up
+                // near each other.
+                .unwrap_or(Ordering::Equal)
+        })
+    }
+
     pub fn call_or_cheapest(
         &self,
         capabilities: Option<&NodeCapabilities>,
@@ -154,11 +232,12 @@ impl EthereumNetworkAdapters {
     }
 
     pub fn call_only_adapter(&self) -> anyhow::Result<Option<Arc<EthereumAdapter>>> {
-        if self.call_only_adapters.is_empty() {
+        let inner = self.inner.read().unwrap();
+        if inner.call_only_adapters.is_empty() {
             return Ok(None);
         }
 
-        let adapters = self
+        let adapters = inner
             .call_only_adapters
             .iter()
             .min_by_key(|x| Arc::strong_count(&x.adapter))
@@ -229,8 +308,8 @@ impl EthereumNetworks {
             .iter()
             .flat_map(|(network_name, network_adapters)| {
                 network_adapters
-                    .adapters
-                    .iter()
+                    .adapters()
+                    .into_iter()
                     .map(move |network_adapter| {
                         (
                             network_name.clone(),
@@ -244,14 +323,7 @@ impl EthereumNetworks {
 
     pub fn sort(&mut self) {
         for adapters in self.networks.values_mut() {
-            adapters.adapters.sort_by(|a, b| {
-                a.capabilities
-                    .partial_cmp(&b.capabilities)
-                    // We can't define a total ordering over node capabilities,
-                    // so incomparable items are considered equal and end up
-                    // near each other.
-                    .unwrap_or(Ordering::Equal)
-            })
+            adapters.sort()
         }
     }
 
@@ -380,7 +452,7 @@ mod tests {
             .await,
         );
 
-        let mut adapters = {
+        let adapters = {
             let mut ethereum_networks = EthereumNetworks::new(metrics);
             ethereum_networks.insert(
                 chain.clone(),
@@ -437,7 +509,7 @@ mod tests {
 
         // Check empty falls back to call only
         {
-            adapters.call_only_adapters = vec![];
+            adapters.inner.write().unwrap().call_only_adapters = vec![];
             let adapter = adapters
                 .call_or_cheapest(Some(&NodeCapabilities {
                     archive: true,
@@ -687,7 +759,7 @@ mod tests {
                 SubgraphLimit::Unlimited
             };
 
-            no_retest_adapters.adapters.push(EthereumNetworkAdapter {
+            no_retest_adapters.push_adapter(EthereumNetworkAdapter {
                 endpoint_metrics: metrics.clone(),
                 capabilities: NodeCapabilities {
                     archive: true,
@@ -696,17 +768,15 @@ mod tests {
                 adapter: adapter.clone(),
                 limit: limit.clone(),
             });
-            always_retest_adapters
-                .adapters
-                .push(EthereumNetworkAdapter {
-                    endpoint_metrics: metrics.clone(),
-                    capabilities: NodeCapabilities {
-                        archive: true,
-                        traces: false,
-                    },
-                    adapter,
-                    limit,
-                });
+            always_retest_adapters.push_adapter(EthereumNetworkAdapter {
+                endpoint_metrics: metrics.clone(),
+                capabilities: NodeCapabilities {
+                    archive: true,
+                    traces: false,
+                },
+                adapter,
+                limit,
+            });
         });
 
         assert_eq!(
@@ -755,7 +825,7 @@ mod tests {
         metrics.report_for_test(&Provider::from(error_provider.clone()), false);
 
         let mut no_retest_adapters = EthereumNetworkAdapters::new(Some(0f64));
-        no_retest_adapters.adapters.push(EthereumNetworkAdapter {
+        no_retest_adapters.push_adapter(EthereumNetworkAdapter {
             endpoint_metrics: metrics.clone(),
             capabilities: NodeCapabilities {
                 archive: true,
@@ -777,24 +847,22 @@ mod tests {
         );
 
         let mut always_retest_adapters = EthereumNetworkAdapters::new(Some(1f64));
-        always_retest_adapters
-            .adapters
-            .push(EthereumNetworkAdapter {
-                endpoint_metrics: metrics.clone(),
-                capabilities: NodeCapabilities {
-                    archive: true,
-                    traces: false,
-                },
-                adapter: fake_adapter(
-                    &logger,
-                    &no_error_provider,
-                    &provider_metrics,
-                    &metrics,
-                    false,
-                )
-                .await,
-                limit: SubgraphLimit::Unlimited,
-            });
+        always_retest_adapters.push_adapter(EthereumNetworkAdapter {
+            endpoint_metrics: metrics.clone(),
+            capabilities: NodeCapabilities {
+                archive: true,
+                traces: false,
+            },
+            adapter: fake_adapter(
+                &logger,
+                &no_error_provider,
+                &provider_metrics,
+                &metrics,
+                false,
+            )
+            .await,
+            limit: SubgraphLimit::Unlimited,
+        });
         assert_eq!(
             always_retest_adapters
                 .cheapest_with(&NodeCapabilities {
@@ -807,7 +875,7 @@ mod tests {
         );
 
         let mut no_available_adapter = EthereumNetworkAdapters::default();
-        no_available_adapter.adapters.push(EthereumNetworkAdapter {
+        no_available_adapter.push_adapter(EthereumNetworkAdapter {
             endpoint_metrics: metrics.clone(),
             capabilities: NodeCapabilities {
                 archive: true,
@@ -1,3 +1,4 @@
+use super::revert::CallRevert;
 use super::runtime_adapter::UnresolvedContractCall;
 use crate::trigger::{
     EthereumBlockData, EthereumCallData, EthereumEventData, EthereumTransactionData,
@@ -15,8 +16,8 @@ use graph::{
 };
 use graph_runtime_derive::AscType;
 use graph_runtime_wasm::asc_abi::class::{
-    Array, AscAddress, AscBigInt, AscEnum, AscH160, AscString, AscWrapped, EthereumValueKind,
-    Uint8Array,
+    Array, AscAddress, AscBigInt, AscEnum, AscEnumArray, AscH160, AscString, AscWrapped,
+    EthereumValueKind, Uint8Array,
 };
 use semver::Version;
 
@@ -272,6 +273,30 @@ impl AscIndexId for AscEthereumTransaction_0_0_6 {
     const INDEX_ASC_TYPE_ID: IndexForAscTypeId = IndexForAscTypeId::EthereumTransaction;
 }
 
+/// Introduced in API Version 0.0.8, this is the same as [`AscEthereumTransaction_0_0_6`] with
+/// the EIP-1559 fee fields and the transaction type added. All of them are `null` for
+/// transactions that do not have them.
+#[repr(C)]
+#[derive(AscType)]
+pub(crate) struct AscEthereumTransaction_0_0_8 {
+    pub hash: AscPtr<AscH256>,
+    pub index: AscPtr<AscBigInt>,
+    pub from: AscPtr<AscH160>,
+    pub to: AscPtr<AscH160>,
+    pub value: AscPtr<AscBigInt>,
+    pub gas_limit: AscPtr<AscBigInt>,
+    pub gas_price: AscPtr<AscBigInt>,
+    pub input: AscPtr<Uint8Array>,
+    pub nonce: AscPtr<AscBigInt>,
+    pub max_fee_per_gas: AscPtr<AscBigInt>,
+    pub max_priority_fee_per_gas: AscPtr<AscBigInt>,
+    pub transaction_type: AscPtr<AscBigInt>,
+}
+
+impl AscIndexId for AscEthereumTransaction_0_0_8 {
+    const INDEX_ASC_TYPE_ID: IndexForAscTypeId = IndexForAscTypeId::EthereumTransaction;
+}
+
 #[repr(C)]
 #[derive(AscType)]
 pub(crate) struct AscEthereumEvent<T, B>
@@ -340,6 +365,30 @@ impl AscIndexId for AscEthereumTransactionReceipt {
     const INDEX_ASC_TYPE_ID: IndexForAscTypeId = IndexForAscTypeId::TransactionReceipt;
 }
 
+/// Introduced in API Version 0.0.8, this is the same as [`AscEthereumTransactionReceipt`] with
+/// the transaction type and the effective gas price added.
+#[repr(C)]
+#[derive(AscType)]
+pub(crate) struct AscEthereumTransactionReceipt_0_0_8 {
+    pub transaction_hash: AscPtr<AscH256>,
+    pub transaction_index: AscPtr<AscBigInt>,
+    pub block_hash: AscPtr<AscH256>,
+    pub block_number: AscPtr<AscBigInt>,
+    pub cumulative_gas_used: AscPtr<AscBigInt>,
+    pub gas_used: AscPtr<AscBigInt>,
+    pub contract_address: AscPtr<AscAddress>,
+    pub logs: AscPtr<AscLogArray>,
+    pub status: AscPtr<AscBigInt>,
+    pub root: AscPtr<AscH256>,
+    pub logs_bloom: AscPtr<AscH2048>,
+    pub transaction_type: AscPtr<AscBigInt>,
+    pub effective_gas_price: AscPtr<AscBigInt>,
+}
+
+impl AscIndexId for AscEthereumTransactionReceipt_0_0_8 {
+    const INDEX_ASC_TYPE_ID: IndexForAscTypeId = IndexForAscTypeId::TransactionReceipt;
+}
+
 /// Introduced in API Version 0.0.7, this is the same as [`AscEthereumEvent`] with an added
 /// `receipt` field.
 #[repr(C)]
@@ -363,6 +412,29 @@ impl AscIndexId for AscEthereumEvent_0_0_7<AscEthereumTransaction_0_0_6, AscEthe
     const INDEX_ASC_TYPE_ID: IndexForAscTypeId = IndexForAscTypeId::EthereumEvent;
 }
 
+/// Introduced in API Version 0.0.8, this is the same as [`AscEthereumEvent_0_0_7`] with the
+/// receipt in the layout of [`AscEthereumTransactionReceipt_0_0_8`].
+#[repr(C)]
+#[derive(AscType)]
+pub(crate) struct AscEthereumEvent_0_0_8<T, B>
+where
+    T: AscType,
+    B: AscType,
+{
+    pub address: AscPtr<AscAddress>,
+    pub log_index: AscPtr<AscBigInt>,
+    pub transaction_log_index: AscPtr<AscBigInt>,
+    pub log_type: AscPtr<AscString>,
+    pub block: AscPtr<B>,
+    pub transaction: AscPtr<T>,
+    pub params: AscPtr<AscLogParamArray>,
+    pub receipt: AscPtr<AscEthereumTransactionReceipt_0_0_8>,
+}
+
+impl AscIndexId for AscEthereumEvent_0_0_8<AscEthereumTransaction_0_0_8, AscEthereumBlock_0_0_6> {
+    const INDEX_ASC_TYPE_ID: IndexForAscTypeId = IndexForAscTypeId::EthereumEvent;
+}
+
 #[repr(C)]
 #[derive(AscType)]
 pub(crate) struct AscLogParam {
@@ -545,6 +617,49 @@ impl ToAscObj<AscEthereumTransaction_0_0_6> for EthereumTransactionData {
     }
 }
 
+impl ToAscObj<AscEthereumTransaction_0_0_8> for EthereumTransactionData {
+    fn to_asc_obj<H: AscHeap + ?Sized>(
+        &self,
+        heap: &mut H,
+        gas: &GasCounter,
+    ) -> Result<AscEthereumTransaction_0_0_8, HostExportError> {
+        let AscEthereumTransaction_0_0_6 {
+            hash,
+            index,
+            from,
+            to,
+            value,
+            gas_limit,
+            gas_price,
+            input,
+            nonce,
+        } = self.to_asc_obj(heap, gas)?;
+        Ok(AscEthereumTransaction_0_0_8 {
+            hash,
+            index,
+            from,
+            to,
+            value,
+            gas_limit,
+            gas_price,
+            input,
+            nonce,
+            max_fee_per_gas: self
+                .max_fee_per_gas
+                .map(|fee| asc_new(heap, &BigInt::from_unsigned_u256(&fee), gas))
+                .unwrap_or(Ok(AscPtr::null()))?,
+            max_priority_fee_per_gas: self
+                .max_priority_fee_per_gas
+                .map(|fee| asc_new(heap, &BigInt::from_unsigned_u256(&fee), gas))
+                .unwrap_or(Ok(AscPtr::null()))?,
+            transaction_type: self
+                .transaction_type
+                .map(|tx_type| asc_new(heap, &BigInt::from(tx_type), gas))
+                .unwrap_or(Ok(AscPtr::null()))?,
+        })
+    }
+}
+
 impl<T, B> ToAscObj<AscEthereumEvent<T, B>> for EthereumEventData
 where
     T: AscType + AscIndexId,
@@ -618,6 +733,47 @@ where
     }
 }
 
+impl<T, B> ToAscObj<AscEthereumEvent_0_0_8<T, B>>
+    for (EthereumEventData, Option<&TransactionReceipt>)
+where
+    T: AscType + AscIndexId,
+    B: AscType + AscIndexId,
+    EthereumTransactionData: ToAscObj<T>,
+    EthereumBlockData: ToAscObj<B>,
+{
+    fn to_asc_obj<H: AscHeap + ?Sized>(
+        &self,
+        heap: &mut H,
+        gas: &GasCounter,
+    ) -> Result<AscEthereumEvent_0_0_8<T, B>, HostExportError> {
+        let (event_data, optional_receipt) = self;
+        let AscEthereumEvent {
+            address,
+            log_index,
+            transaction_log_index,
+            log_type,
+            block,
+            transaction,
+            params,
+        } = event_data.to_asc_obj(heap, gas)?;
+        let receipt = if let Some(receipt_data) = optional_receipt {
+            asc_new(heap, receipt_data, gas)?
+        } else {
+            AscPtr::null()
+        };
+        Ok(AscEthereumEvent_0_0_8 {
+            address,
+            log_index,
+            transaction_log_index,
+            log_type,
+            block,
+            transaction,
+            params,
+            receipt,
+        })
+    }
+}
+
 impl ToAscObj<AscEthereumLog> for Log {
     fn to_asc_obj<H: AscHeap + ?Sized>(
         &self,
@@ -709,6 +865,49 @@ impl ToAscObj<AscEthereumTransactionReceipt> for &TransactionReceipt {
     }
 }
 
+impl ToAscObj<AscEthereumTransactionReceipt_0_0_8> for &TransactionReceipt {
+    fn to_asc_obj<H: AscHeap + ?Sized>(
+        &self,
+        heap: &mut H,
+        gas: &GasCounter,
+    ) -> Result<AscEthereumTransactionReceipt_0_0_8, HostExportError> {
+        let AscEthereumTransactionReceipt {
+            transaction_hash,
+            transaction_index,
+            block_hash,
+            block_number,
+            cumulative_gas_used,
+            gas_used,
+            contract_address,
+            logs,
+            status,
+            root,
+            logs_bloom,
+        } = self.to_asc_obj(heap, gas)?;
+        Ok(AscEthereumTransactionReceipt_0_0_8 {
+            transaction_hash,
+            transaction_index,
+            block_hash,
+            block_number,
+            cumulative_gas_used,
+            gas_used,
+            contract_address,
+            logs,
+            status,
+            root,
+            logs_bloom,
+            transaction_type: self
+                .transaction_type
+                .map(|tx_type| asc_new(heap, &BigInt::from(tx_type), gas))
+                .unwrap_or(Ok(AscPtr::null()))?,
+            effective_gas_price: self
+                .effective_gas_price
+                .map(|price| asc_new(heap, &BigInt::from_unsigned_u256(&price), gas))
+                .unwrap_or(Ok(AscPtr::null()))?,
+        })
+    }
+}
+
 impl ToAscObj<AscEthereumCall> for EthereumCallData {
     fn to_asc_obj<H: AscHeap + ?Sized>(
         &self,
@@ -769,6 +968,98 @@ impl ToAscObj<AscEthereumCall_0_0_3<AscEthereumTransaction_0_0_6, AscEthereumBlo
     }
 }
 
+impl ToAscObj<AscEthereumCall_0_0_3<AscEthereumTransaction_0_0_8, AscEthereumBlock_0_0_6>>
+    for EthereumCallData
+{
+    fn to_asc_obj<H: AscHeap + ?Sized>(
+        &self,
+        heap: &mut H,
+        gas: &GasCounter,
+    ) -> Result<
+        AscEthereumCall_0_0_3<AscEthereumTransaction_0_0_8, AscEthereumBlock_0_0_6>,
+        HostExportError,
+    > {
+        Ok(AscEthereumCall_0_0_3 {
+            to: asc_new(heap, &self.to, gas)?,
+            from: asc_new(heap, &self.from, gas)?,
+            block: asc_new(heap, &self.block, gas)?,
+            transaction: asc_new(heap, &self.transaction, gas)?,
+            inputs: asc_new(heap, &self.inputs, gas)?,
+            outputs: asc_new(heap, &self.outputs, gas)?,
+        })
+    }
+}
+
+/// The result of `ethereum.tryCall`. Exactly one of `value` and `revert`
+/// is set
+#[repr(C)]
+#[derive(AscType)]
+pub(crate) struct AscEthereumCallResult {
+    pub value: AscEnumArray<EthereumValueKind>,
+    pub revert: AscPtr<AscEthereumCallRevert>,
+}
+
+impl AscIndexId for AscEthereumCallResult {
+    const INDEX_ASC_TYPE_ID: IndexForAscTypeId = IndexForAscTypeId::EthereumCallResult;
+}
+
+/// Why a call reverted. `error` and `params` are only set if the revert
+/// data could be decoded as a standard error or a custom error from the
+/// ABI of the contract
+#[repr(C)]
+#[derive(AscType)]
+pub(crate) struct AscEthereumCallRevert {
+    pub reason: AscPtr<AscString>,
+    pub data: AscPtr<Uint8Array>,
+    pub error: AscPtr<AscString>,
+    pub params: AscPtr<AscLogParamArray>,
+}
+
+impl AscIndexId for AscEthereumCallRevert {
+    const INDEX_ASC_TYPE_ID: IndexForAscTypeId = IndexForAscTypeId::EthereumCallRevert;
+}
+
+impl ToAscObj<AscEthereumCallResult> for Result<Vec<ethabi::Token>, CallRevert> {
+    fn to_asc_obj<H: AscHeap + ?Sized>(
+        &self,
+        heap: &mut H,
+        gas: &GasCounter,
+    ) -> Result<AscEthereumCallResult, HostExportError> {
+        Ok(match self {
+            Ok(tokens) => AscEthereumCallResult {
+                value: asc_new(heap, tokens.as_slice(), gas)?,
+                revert: AscPtr::null(),
+            },
+            Err(revert) => AscEthereumCallResult {
+                value: AscPtr::null(),
+                revert: asc_new(heap, revert, gas)?,
+            },
+        })
+    }
+}
+
+impl ToAscObj<AscEthereumCallRevert> for CallRevert {
+    fn to_asc_obj<H: AscHeap + ?Sized>(
+        &self,
+        heap: &mut H,
+        gas: &GasCounter,
+    ) -> Result<AscEthereumCallRevert, HostExportError> {
+        let (error, params) = match &self.decoded {
+            Some(decoded) => (
+                asc_new(heap, decoded.name.as_str(), gas)?,
+                asc_new(heap, &decoded.params, gas)?,
+            ),
+            None => (AscPtr::null(), AscPtr::null()),
+        };
+        Ok(AscEthereumCallRevert {
+            reason: asc_new(heap, self.reason.as_str(), gas)?,
+            data: asc_new(heap, self.data.as_slice(), gas)?,
+            error,
+            params,
+        })
+    }
+}
+
 impl ToAscObj<AscLogParam> for ethabi::LogParam {
     fn to_asc_obj<H: AscHeap + ?Sized>(
         &self,
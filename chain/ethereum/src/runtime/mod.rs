@@ -1,4 +1,5 @@
 pub use runtime_adapter::RuntimeAdapter;
 
 pub mod abi;
+mod revert;
 pub mod runtime_adapter;
@@ -0,0 +1,167 @@
+//! Decoding of the data that a contract call reverted with. Besides the
+//! standard `Error(string)` and `Panic(uint256)` that Solidity uses for
+//! `require` and failed assertions, contracts can revert with custom errors
+//! that their ABI declares, e.g. `error InsufficientBalance(uint256
+//! available, uint256 required)`. The revert data of those starts with the
+//! selector of the error, followed by its ABI encoded parameters, just like
+//! the input of a function call.
+use graph::prelude::ethabi::{self, Contract, LogParam, ParamType};
+
+/// A contract call that reverted
+#[derive(Clone, Debug, PartialEq)]
+pub struct CallRevert {
+    /// The reason that the provider gave for the revert
+    pub reason: String,
+    /// The data that the contract reverted with. Empty if the provider did
+    /// not return it
+    pub data: Vec<u8>,
+    pub decoded: Option<DecodedRevert>,
+}
+
+impl CallRevert {
+    /// A revert with `reason` and `data` of a call to `contract`
+    pub fn new(contract: &Contract, reason: String, data: Vec<u8>) -> Self {
+        let decoded = DecodedRevert::decode(contract, &data);
+        CallRevert {
+            reason,
+            data,
+            decoded,
+        }
+    }
+
+    /// The decoded error if there is one, and the reason from the provider
+    /// otherwise
+    pub fn describe(&self) -> String {
+        match &self.decoded {
+            Some(decoded) => decoded.describe(),
+            None => self.reason.clone(),
+        }
+    }
+}
+
+/// A revert that was decoded with the ABI of the contract
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedRevert {
+    /// The name of the error, e.g. `Error` or `InsufficientBalance`
+    pub name: String,
+    pub params: Vec<LogParam>,
+}
+
+impl DecodedRevert {
+    /// Decode `data` as one of the standard errors or as one of the custom
+    /// errors in `contract`. Returns `None` if `data` does not match any of
+    /// them
+    pub fn decode(contract: &Contract, data: &[u8]) -> Option<Self> {
+        if data.len() < 4 {
+            return None;
+        }
+        let (selector, payload) = data.split_at(4);
+
+        let standard = [
+            ("Error", vec![("message", ParamType::String)]),
+            ("Panic", vec![("code", ParamType::Uint(256))]),
+        ];
+        let standard = standard.into_iter().map(|(name, inputs)| {
+            let inputs = inputs
+                .into_iter()
+                .map(|(name, kind)| (name.to_string(), kind))
+                .collect();
+            (name.to_string(), inputs)
+        });
+        let custom = contract.errors().map(|error| {
+            let inputs = error
+                .inputs
+                .iter()
+                .map(|param| (param.name.clone(), param.kind.clone()))
+                .collect();
+            (error.name.clone(), inputs)
+        });
+
+        standard
+            .chain(custom)
+            .find_map(|(name, inputs): (String, Vec<(String, ParamType)>)| {
+                let kinds: Vec<_> = inputs.iter().map(|(_, kind)| kind.clone()).collect();
+                if &ethabi::short_signature(&name, &kinds)[..] != selector {
+                    return None;
+                }
+                let tokens = ethabi::decode(&kinds, payload).ok()?;
+                let params = inputs
+                    .into_iter()
+                    .zip(tokens)
+                    .map(|((name, _), value)| LogParam { name, value })
+                    .collect();
+                Some(DecodedRevert { name, params })
+            })
+    }
+
+    /// A description of the revert for log messages, e.g.,
+    /// `InsufficientBalance(available: 1, required: 2)`
+    pub fn describe(&self) -> String {
+        let params: Vec<_> = self
+            .params
+            .iter()
+            .map(|param| format!("{}: {}", param.name, param.value))
+            .collect();
+        format!("{}({})", self.name, params.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use graph::prelude::ethabi::{Token, Uint};
+
+    use super::*;
+
+    const ABI: &str = r#"[
+        {
+            "type": "error",
+            "name": "InsufficientBalance",
+            "inputs": [
+                { "name": "available", "type": "uint256" },
+                {
+                    "name": "owners",
+                    "type": "tuple[]",
+                    "components": [
+                        { "name": "owner", "type": "address" },
+                        { "name": "tags", "type": "string[]" }
+                    ]
+                }
+            ]
+        }
+    ]"#;
+
+    fn revert_data(name: &str, kinds: &[ParamType], tokens: &[Token]) -> Vec<u8> {
+        let mut data = ethabi::short_signature(name, kinds).to_vec();
+        data.extend(ethabi::encode(tokens));
+        data
+    }
+
+    #[test]
+    fn decode_reverts() {
+        let contract = Contract::load(ABI.as_bytes()).unwrap();
+
+        let data = revert_data(
+            "Error",
+            &[ParamType::String],
+            &[Token::String("nope".into())],
+        );
+        let revert = DecodedRevert::decode(&contract, &data).unwrap();
+        assert_eq!("Error(message: nope)", revert.describe());
+
+        let owners = Token::Array(vec![Token::Tuple(vec![
+            Token::Address([7u8; 20].into()),
+            Token::Array(vec![Token::String("a".into()), Token::String("b".into())]),
+        ])]);
+        let kinds = contract.errors().next().unwrap().inputs.iter();
+        let kinds: Vec<_> = kinds.map(|param| param.kind.clone()).collect();
+        let tokens = vec![Token::Uint(Uint::from(5u64)), owners.clone()];
+        let data = revert_data("InsufficientBalance", &kinds, &tokens);
+        let revert = DecodedRevert::decode(&contract, &data).unwrap();
+        assert_eq!("InsufficientBalance", revert.name);
+        assert_eq!("owners", revert.params[1].name);
+        assert_eq!(owners, revert.params[1].value);
+
+        assert_eq!(None, DecodedRevert::decode(&contract, &[1, 2, 3, 4]));
+        assert_eq!(None, DecodedRevert::decode(&contract, &[]));
+    }
+}
@@ -4,25 +4,31 @@ use crate::data_source::MappingABI;
 use crate::{
     capabilities::NodeCapabilities, network::EthereumNetworkAdapters, Chain, DataSource,
     EthereumAdapter, EthereumAdapterTrait, EthereumContractCall, EthereumContractCallError,
+    ENV_VARS,
 };
-use anyhow::{Context, Error};
+use anyhow::{anyhow, Context, Error};
 use blockchain::HostFn;
+use graph::prelude::lazy_static;
 use graph::runtime::gas::Gas;
 use graph::runtime::{AscIndexId, IndexForAscTypeId};
 use graph::{
     blockchain::{self, BlockPtr, HostFnCtx},
     cheap_clone::CheapClone,
+    components::metrics::CounterVec,
     prelude::{
         ethabi::{self, Address, Token},
-        EthereumCallCache, Future01CompatExt,
+        CachedEthereumCall, EthereumCallCache, Future01CompatExt, MetricsRegistry,
     },
     runtime::{asc_get, asc_new, AscPtr, HostExportError},
     semver::Version,
-    slog::{info, trace, Logger},
+    slog::{error, info, trace, warn, Logger},
 };
-use graph_runtime_wasm::asc_abi::class::{AscEnumArray, EthereumValueKind};
+use graph_runtime_wasm::asc_abi::class::{AscEnumArray, AscString, EthereumValueKind, Uint8Array};
 
-use super::abi::{AscUnresolvedContractCall, AscUnresolvedContractCall_0_0_4};
+use super::abi::{
+    AscEthereumCallResult, AscUnresolvedContractCall, AscUnresolvedContractCall_0_0_4,
+};
+use super::revert::CallRevert;
 
 // When making an ethereum call, the maximum ethereum gas is ETH_CALL_GAS which is 50 million. One
 // unit of Ethereum gas is at least 100ns according to these benchmarks [1], so 1000 of our gas. In
@@ -34,9 +40,79 @@ use super::abi::{AscUnresolvedContractCall, AscUnresolvedContractCall_0_0_4};
 // [1] - https://www.sciencedirect.com/science/article/abs/pii/S0166531620300900
 pub const ETHEREUM_CALL: Gas = Gas::new(5_000_000_000);
 
+/// The address of the ENS registry, which is the same on mainnet and the
+/// test networks that ENS is deployed on
+const ENS_REGISTRY: &str = "00000000000C2E074eC69A0dFb2997BA6C7d2e1e";
+
+/// The functions of the ENS registry and of resolvers that are needed for
+/// forward resolution
+const ENS_ABI: &str = r#"[
+    {
+        "type": "function",
+        "name": "resolver",
+        "inputs": [{ "name": "node", "type": "bytes32" }],
+        "outputs": [{ "name": "", "type": "address" }],
+        "stateMutability": "view"
+    },
+    {
+        "type": "function",
+        "name": "addr",
+        "inputs": [{ "name": "node", "type": "bytes32" }],
+        "outputs": [{ "name": "", "type": "address" }],
+        "stateMutability": "view"
+    }
+]"#;
+
+lazy_static! {
+    static ref ENS_CONTRACT: ethabi::Contract =
+        ethabi::Contract::load(ENS_ABI.as_bytes()).expect("the ENS ABI is valid");
+}
+
 pub struct RuntimeAdapter {
     pub eth_adapters: Arc<EthereumNetworkAdapters>,
     pub call_cache: Arc<dyn EthereumCallCache>,
+    verification_mismatches: CounterVec,
+}
+
+impl RuntimeAdapter {
+    pub fn new(
+        eth_adapters: Arc<EthereumNetworkAdapters>,
+        call_cache: Arc<dyn EthereumCallCache>,
+        registry: Arc<MetricsRegistry>,
+    ) -> Self {
+        let verification_mismatches = registry
+            .global_counter_vec(
+                "eth_call_verification_mismatches",
+                "Counts the contract calls for which two providers returned different results",
+                &["provider", "verifier"],
+            )
+            .unwrap();
+        Self {
+            eth_adapters,
+            call_cache,
+            verification_mismatches,
+        }
+    }
+
+    /// The provider that calls made through `eth_adapter` are checked
+    /// against, or `None` if calls are not verified or there is no other
+    /// provider that could run them
+    fn verifier(
+        eth_adapters: &EthereumNetworkAdapters,
+        eth_adapter: &EthereumAdapter,
+        capabilities: &NodeCapabilities,
+        mismatches: &CounterVec,
+    ) -> Option<CallVerifier> {
+        if !ENV_VARS.eth_call_verification {
+            return None;
+        }
+        eth_adapters
+            .other_than(eth_adapter.provider(), capabilities)
+            .map(|adapter| CallVerifier {
+                adapter,
+                mismatches: mismatches.clone(),
+            })
+    }
 }
 
 impl blockchain::RuntimeAdapter<Chain> for RuntimeAdapter {
@@ -44,67 +120,204 @@ impl blockchain::RuntimeAdapter<Chain> for RuntimeAdapter {
         let abis = ds.mapping.abis.clone();
         let call_cache = self.call_cache.cheap_clone();
         let eth_adapters = self.eth_adapters.cheap_clone();
+        let mismatches = self.verification_mismatches.clone();
         let archive = ds.mapping.requires_archive()?;
+        let capabilities = NodeCapabilities {
+            archive,
+            traces: false,
+        };
 
         let ethereum_call = HostFn {
             name: "ethereum.call",
             func: Arc::new(move |ctx, wasm_ptr| {
                 // Ethereum calls should prioritise call-only adapters if one is available.
-                let eth_adapter = eth_adapters.call_or_cheapest(Some(&NodeCapabilities {
-                    archive,
-                    traces: false,
-                }))?;
-                ethereum_call(&eth_adapter, call_cache.cheap_clone(), ctx, wasm_ptr, &abis)
-                    .map(|ptr| ptr.wasm_ptr())
+                let eth_adapter = eth_adapters.call_or_cheapest(Some(&capabilities))?;
+                let verifier =
+                    Self::verifier(&eth_adapters, &eth_adapter, &capabilities, &mismatches);
+                ethereum_call(
+                    &eth_adapter,
+                    call_cache.cheap_clone(),
+                    verifier.as_ref(),
+                    ctx,
+                    wasm_ptr,
+                    &abis,
+                )
+                .map(|ptr| ptr.wasm_ptr())
+            }),
+        };
+
+        let abis = ds.mapping.abis.clone();
+        let eth_adapters = self.eth_adapters.cheap_clone();
+        let call_cache = self.call_cache.cheap_clone();
+        let mismatches = self.verification_mismatches.clone();
+        let ethereum_try_call = HostFn {
+            name: "ethereum.tryCall",
+            func: Arc::new(move |ctx, wasm_ptr| {
+                let eth_adapter = eth_adapters.call_or_cheapest(Some(&capabilities))?;
+                let verifier =
+                    Self::verifier(&eth_adapters, &eth_adapter, &capabilities, &mismatches);
+                ethereum_try_call(
+                    &eth_adapter,
+                    call_cache.cheap_clone(),
+                    verifier.as_ref(),
+                    ctx,
+                    wasm_ptr,
+                    &abis,
+                )
+                .map(|ptr| ptr.wasm_ptr())
+            }),
+        };
+
+        let eth_adapters = self.eth_adapters.cheap_clone();
+        let call_cache = self.call_cache.cheap_clone();
+        let mismatches = self.verification_mismatches.clone();
+        let ens_resolve = HostFn {
+            name: "ens.resolve",
+            func: Arc::new(move |ctx, wasm_ptr| {
+                let eth_adapter = eth_adapters.call_or_cheapest(Some(&capabilities))?;
+                let verifier =
+                    Self::verifier(&eth_adapters, &eth_adapter, &capabilities, &mismatches);
+                ens_resolve(
+                    &eth_adapter,
+                    call_cache.cheap_clone(),
+                    verifier.as_ref(),
+                    ctx,
+                    wasm_ptr,
+                )
+                .map(|ptr| ptr.wasm_ptr())
             }),
         };
 
-        Ok(vec![ethereum_call])
+        Ok(vec![ethereum_call, ethereum_try_call, ens_resolve])
     }
 }
 
+/// function ens.resolve(name: string): Address | null
+///
+/// Resolves `name` to an address at the current block through the resolver
+/// that the ENS registry has for it. Returns `null` if the name has no
+/// resolver or the resolver has no address for it
+fn ens_resolve(
+    eth_adapter: &EthereumAdapter,
+    call_cache: Arc<dyn EthereumCallCache>,
+    verifier: Option<&CallVerifier>,
+    ctx: HostFnCtx<'_>,
+    wasm_ptr: u32,
+) -> Result<AscPtr<Uint8Array>, HostExportError> {
+    let name: String = asc_get::<_, AscString, _>(ctx.heap, wasm_ptr.into(), &ctx.gas)?;
+    let node = Token::FixedBytes(graph::util::ens::namehash(&name).to_vec());
+    let registry: Address = ENS_REGISTRY
+        .parse()
+        .expect("the ENS registry address is valid");
+
+    let mut address = registry;
+    for (contract_name, function_name) in [("ENSRegistry", "resolver"), ("Resolver", "addr")] {
+        ctx.gas.consume_host_fn(ETHEREUM_CALL)?;
+        let call = EthereumContractCall {
+            address,
+            block_ptr: ctx.block_ptr.cheap_clone(),
+            function: ENS_CONTRACT
+                .function(function_name)
+                .expect("the ENS ABI has the function")
+                .clone(),
+            args: vec![node.clone()],
+        };
+        let tokens = match execute_call(
+            eth_adapter,
+            call_cache.cheap_clone(),
+            verifier,
+            &ctx.logger,
+            call,
+            &ENS_CONTRACT,
+            contract_name,
+            function_name,
+        )? {
+            Ok(tokens) => tokens,
+            Err(_) => return Ok(AscPtr::null()),
+        };
+        address = match tokens.into_iter().next().and_then(Token::into_address) {
+            Some(address) if !address.is_zero() => address,
+            _ => return Ok(AscPtr::null()),
+        };
+    }
+
+    Ok(asc_new(ctx.heap, &address, &ctx.gas)?)
+}
+
 /// function ethereum.call(call: SmartContractCall): Array<Token> | null
 fn ethereum_call(
     eth_adapter: &EthereumAdapter,
     call_cache: Arc<dyn EthereumCallCache>,
+    verifier: Option<&CallVerifier>,
     ctx: HostFnCtx<'_>,
     wasm_ptr: u32,
     abis: &[Arc<MappingABI>],
 ) -> Result<AscEnumArray<EthereumValueKind>, HostExportError> {
+    let result = call_from_wasm(eth_adapter, call_cache, verifier, &ctx, wasm_ptr, abis)?;
+    match result {
+        Ok(tokens) => Ok(asc_new(ctx.heap, tokens.as_slice(), &ctx.gas)?),
+        Err(_) => Ok(AscPtr::null()),
+    }
+}
+
+/// function ethereum.tryCall(call: SmartContractCall): EthereumCallResult
+///
+/// Like `ethereum.call`, but if the call reverts, the result says why. The
+/// revert data is decoded as one of the custom errors in the ABI of the
+/// contract if possible
+fn ethereum_try_call(
+    eth_adapter: &EthereumAdapter,
+    call_cache: Arc<dyn EthereumCallCache>,
+    verifier: Option<&CallVerifier>,
+    ctx: HostFnCtx<'_>,
+    wasm_ptr: u32,
+    abis: &[Arc<MappingABI>],
+) -> Result<AscPtr<AscEthereumCallResult>, HostExportError> {
+    let result = call_from_wasm(eth_adapter, call_cache, verifier, &ctx, wasm_ptr, abis)?;
+    Ok(asc_new(ctx.heap, &result, &ctx.gas)?)
+}
+
+/// Read the call at `wasm_ptr` from the mapping and run it
+fn call_from_wasm(
+    eth_adapter: &EthereumAdapter,
+    call_cache: Arc<dyn EthereumCallCache>,
+    verifier: Option<&CallVerifier>,
+    ctx: &HostFnCtx<'_>,
+    wasm_ptr: u32,
+    abis: &[Arc<MappingABI>],
+) -> Result<Result<Vec<Token>, CallRevert>, HostExportError> {
     ctx.gas.consume_host_fn(ETHEREUM_CALL)?;
 
     // For apiVersion >= 0.0.4 the call passed from the mapping includes the
     // function signature; subgraphs using an apiVersion < 0.0.4 don't pass
     // the signature along with the call.
     let call: UnresolvedContractCall = if ctx.heap.api_version() >= Version::new(0, 0, 4) {
-        asc_get::<_, AscUnresolvedContractCall_0_0_4, _>(ctx.heap, wasm_ptr.into(), &ctx.gas)?
+        asc_get::<_, AscUnresolvedContractCall_0_0_4, _>(&*ctx.heap, wasm_ptr.into(), &ctx.gas)?
     } else {
-        asc_get::<_, AscUnresolvedContractCall, _>(ctx.heap, wasm_ptr.into(), &ctx.gas)?
+        asc_get::<_, AscUnresolvedContractCall, _>(&*ctx.heap, wasm_ptr.into(), &ctx.gas)?
     };
 
-    let result = eth_call(
+    eth_call(
         eth_adapter,
         call_cache,
+        verifier,
         &ctx.logger,
         &ctx.block_ptr,
         call,
         abis,
-    )?;
-    match result {
-        Some(tokens) => Ok(asc_new(ctx.heap, tokens.as_slice(), &ctx.gas)?),
-        None => Ok(AscPtr::null()),
-    }
+    )
 }
 
-/// Returns `Ok(None)` if the call was reverted.
+/// Returns `Ok(Err(revert))` if the call was reverted.
 fn eth_call(
     eth_adapter: &EthereumAdapter,
     call_cache: Arc<dyn EthereumCallCache>,
+    verifier: Option<&CallVerifier>,
     logger: &Logger,
     block_ptr: &BlockPtr,
     unresolved_call: UnresolvedContractCall,
     abis: &[Arc<MappingABI>],
-) -> Result<Option<Vec<Token>>, HostExportError> {
+) -> Result<Result<Vec<Token>, CallRevert>, HostExportError> {
     let start_time = Instant::now();
 
     // Obtain the path to the contract ABI
@@ -165,16 +378,86 @@ fn eth_call(
         args: unresolved_call.function_args.clone(),
     };
 
+    let result = execute_call(
+        eth_adapter,
+        call_cache,
+        verifier,
+        logger,
+        call,
+        &contract,
+        &unresolved_call.contract_name,
+        &unresolved_call.function_name,
+    );
+
+    trace!(logger, "Contract call finished";
+              "address" => &unresolved_call.contract_address.to_string(),
+              "contract" => &unresolved_call.contract_name,
+              "function" => &unresolved_call.function_name,
+              "function_signature" => &unresolved_call.function_signature,
+              "time" => format!("{}ms", start_time.elapsed().as_millis()));
+
+    result
+}
+
+/// Run `call`, returning `Ok(Err(revert))` if the call was reverted. Revert
+/// data is decoded with the ABI in `contract`. The result is checked
+/// against the provider of `verifier` if there is one. The contract and
+/// function name are only used for error messages
+fn execute_call(
+    eth_adapter: &EthereumAdapter,
+    call_cache: Arc<dyn EthereumCallCache>,
+    verifier: Option<&CallVerifier>,
+    logger: &Logger,
+    call: EthereumContractCall,
+    contract: &ethabi::Contract,
+    contract_name: &str,
+    function_name: &str,
+) -> Result<Result<Vec<Token>, CallRevert>, HostExportError> {
+    let result = execute_call_inner(
+        eth_adapter,
+        call_cache,
+        logger,
+        call.clone(),
+        contract,
+        contract_name,
+        function_name,
+    )?;
+    if let Some(verifier) = verifier {
+        verifier.verify(
+            eth_adapter.provider(),
+            logger,
+            call,
+            contract_name,
+            function_name,
+            &result,
+        )?;
+    }
+    Ok(result)
+}
+
+fn execute_call_inner(
+    eth_adapter: &EthereumAdapter,
+    call_cache: Arc<dyn EthereumCallCache>,
+    logger: &Logger,
+    call: EthereumContractCall,
+    contract: &ethabi::Contract,
+    contract_name: &str,
+    function_name: &str,
+) -> Result<Result<Vec<Token>, CallRevert>, HostExportError> {
     // Run Ethereum call in tokio runtime
     let logger1 = logger.clone();
     let call_cache = call_cache.clone();
-    let result = match graph::block_on(
+    match graph::block_on(
             eth_adapter.contract_call(&logger1, call, call_cache).compat()
         ) {
-            Ok(tokens) => Ok(Some(tokens)),
-            Err(EthereumContractCallError::Revert(reason)) => {
-                info!(logger, "Contract call reverted"; "reason" => reason);
-                Ok(None)
+            Ok(tokens) => Ok(Ok(tokens)),
+            Err(EthereumContractCallError::Revert(reason, data)) => {
+                let revert = CallRevert::new(contract, reason, data);
+                info!(logger, "Contract call reverted";
+                      "contract" => contract_name,
+                      "function" => function_name,
+                      "reason" => revert.describe());
+                Ok(Err(revert))
             }
 
             // Any error reported by the Ethereum node could be due to the block no longer being on
@@ -182,34 +465,112 @@ fn eth_call(
             // subgraph due to a transient error such as a reorg.
             Err(EthereumContractCallError::Web3Error(e)) => Err(HostExportError::PossibleReorg(anyhow::anyhow!(
                 "Ethereum node returned an error when calling function \"{}\" of contract \"{}\": {}",
-                unresolved_call.function_name,
-                unresolved_call.contract_name,
+                function_name,
+                contract_name,
                 e
             ))),
 
             // Also retry on timeouts.
             Err(EthereumContractCallError::Timeout) => Err(HostExportError::PossibleReorg(anyhow::anyhow!(
                 "Ethereum node did not respond when calling function \"{}\" of contract \"{}\"",
-                unresolved_call.function_name,
-                unresolved_call.contract_name,
+                function_name,
+                contract_name,
             ))),
 
             Err(e) => Err(HostExportError::Unknown(anyhow::anyhow!(
                 "Failed to call function \"{}\" of contract \"{}\": {}",
-                unresolved_call.function_name,
-                unresolved_call.contract_name,
+                function_name,
+                contract_name,
                 e
             ))),
+        }
+}
+
+/// A second provider that the results of contract calls are checked
+/// against to detect providers that return wrong results
+struct CallVerifier {
+    adapter: Arc<EthereumAdapter>,
+    mismatches: CounterVec,
+}
+
+impl CallVerifier {
+    /// Run `call` against the provider of the verifier and compare its
+    /// result with `result`, which `provider` returned. Calls that fail on
+    /// the verifier are only logged since the verifier might just be
+    /// unavailable
+    fn verify(
+        &self,
+        provider: &str,
+        logger: &Logger,
+        call: EthereumContractCall,
+        contract_name: &str,
+        function_name: &str,
+        result: &Result<Vec<Token>, CallRevert>,
+    ) -> Result<(), HostExportError> {
+        // Reverts count as the same result no matter why the call reverted
+        let result = result.as_ref().ok();
+        let block = call.block_ptr.number;
+        let expected = match graph::block_on(
+            self.adapter
+                .contract_call(logger, call, Arc::new(NoCallCache))
+                .compat(),
+        ) {
+            Ok(tokens) => Some(tokens),
+            Err(EthereumContractCallError::Revert(..)) => None,
+            Err(e) => {
+                warn!(logger, "Could not verify contract call";
+                      "contract" => contract_name,
+                      "function" => function_name,
+                      "verifier" => self.adapter.provider(),
+                      "error" => e.to_string());
+                return Ok(());
+            }
         };
+        if expected.as_ref() == result {
+            return Ok(());
+        }
 
-    trace!(logger, "Contract call finished";
-              "address" => &unresolved_call.contract_address.to_string(),
-              "contract" => &unresolved_call.contract_name,
-              "function" => &unresolved_call.function_name,
-              "function_signature" => &unresolved_call.function_signature,
-              "time" => format!("{}ms", start_time.elapsed().as_millis()));
+        self.mismatches
+            .with_label_values(&[provider, self.adapter.provider()])
+            .inc();
+        error!(logger, "Providers returned different results for contract call";
+               "contract" => contract_name,
+               "function" => function_name,
+               "block" => block,
+               "provider" => provider,
+               "result" => format!("{:?}", result),
+               "verifier" => self.adapter.provider(),
+               "verifier_result" => format!("{:?}", expected));
+        if ENV_VARS.eth_call_verification_fail {
+            return Err(HostExportError::Deterministic(anyhow!(
+                "providers `{}` and `{}` returned different results when calling function \"{}\" of contract \"{}\" at block {}",
+                provider,
+                self.adapter.provider(),
+                function_name,
+                contract_name,
+                block
+            )));
+        }
+        Ok(())
+    }
+}
 
-    result
+/// A call cache that never has anything in it so that verified calls
+/// always go to the provider, and that does not remember their results
+struct NoCallCache;
+
+impl EthereumCallCache for NoCallCache {
+    fn get_call(&self, _: Address, _: &[u8], _: BlockPtr) -> Result<Option<Vec<u8>>, Error> {
+        Ok(None)
+    }
+
+    fn get_calls_in_block(&self, _: BlockPtr) -> Result<Vec<CachedEthereumCall>, Error> {
+        Ok(vec![])
+    }
+
+    fn set_call(&self, _: Address, _: &[u8], _: BlockPtr, _: &[u8]) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -8,6 +8,7 @@ use web3::RequestId;
 use graph::prelude::*;
 use graph::url::Url;
 use std::future::Future;
+use std::time::Instant;
 
 /// Abstraction over the different web3 transports.
 #[derive(Clone, Debug)]
@@ -99,10 +100,11 @@ impl web3::Transport for Transport {
                 };
                 let out = async move {
                     let labels = labels;
+                    let start = Instant::now();
                     let out = client.send(id, request).await;
                     match out {
-                        Ok(_) => metrics.success(&labels),
-                        Err(_) => metrics.failure(&labels),
+                        Ok(_) => metrics.success(&labels, start.elapsed()),
+                        Err(_) => metrics.failure(&labels, start.elapsed()),
                     }
 
                     out
@@ -2,6 +2,7 @@ use graph::blockchain::TriggerData;
 use graph::data::subgraph::API_VERSION_0_0_2;
 use graph::data::subgraph::API_VERSION_0_0_6;
 use graph::data::subgraph::API_VERSION_0_0_7;
+use graph::data::subgraph::API_VERSION_0_0_8;
 use graph::prelude::ethabi::ethereum_types::H160;
 use graph::prelude::ethabi::ethereum_types::H256;
 use graph::prelude::ethabi::ethereum_types::U128;
@@ -34,9 +35,11 @@ use crate::runtime::abi::AscEthereumCall;
 use crate::runtime::abi::AscEthereumCall_0_0_3;
 use crate::runtime::abi::AscEthereumEvent;
 use crate::runtime::abi::AscEthereumEvent_0_0_7;
+use crate::runtime::abi::AscEthereumEvent_0_0_8;
 use crate::runtime::abi::AscEthereumTransaction_0_0_1;
 use crate::runtime::abi::AscEthereumTransaction_0_0_2;
 use crate::runtime::abi::AscEthereumTransaction_0_0_6;
+use crate::runtime::abi::AscEthereumTransaction_0_0_8;
 
 // ETHDEP: This should be defined in only one place.
 type LightEthereumBlock = Block<Transaction>;
@@ -135,7 +138,17 @@ impl ToAscPtr for MappingTrigger {
                     log_type: log.log_type.clone(),
                     params,
                 };
-                if api_version >= API_VERSION_0_0_7 {
+                if api_version >= API_VERSION_0_0_8 {
+                    asc_new::<
+                        AscEthereumEvent_0_0_8<
+                            AscEthereumTransaction_0_0_8,
+                            AscEthereumBlock_0_0_6,
+                        >,
+                        _,
+                        _,
+                    >(heap, &(ethereum_event_data, receipt.as_deref()), gas)?
+                    .erase()
+                } else if api_version >= API_VERSION_0_0_7 {
                     asc_new::<
                         AscEthereumEvent_0_0_7<
                             AscEthereumTransaction_0_0_6,
@@ -183,7 +196,14 @@ impl ToAscPtr for MappingTrigger {
                     inputs,
                     outputs,
                 };
-                if heap.api_version() >= Version::new(0, 0, 6) {
+                if heap.api_version() >= API_VERSION_0_0_8 {
+                    asc_new::<
+                        AscEthereumCall_0_0_3<AscEthereumTransaction_0_0_8, AscEthereumBlock_0_0_6>,
+                        _,
+                        _,
+                    >(heap, &call, gas)?
+                    .erase()
+                } else if heap.api_version() >= Version::new(0, 0, 6) {
                     asc_new::<
                         AscEthereumCall_0_0_3<AscEthereumTransaction_0_0_6, AscEthereumBlock_0_0_6>,
                         _,
@@ -388,6 +408,9 @@ pub struct EthereumTransactionData {
     pub gas_price: U256,
     pub input: Bytes,
     pub nonce: U256,
+    pub max_fee_per_gas: Option<U256>,
+    pub max_priority_fee_per_gas: Option<U256>,
+    pub transaction_type: Option<U64>,
 }
 
 impl From<&'_ Transaction> for EthereumTransactionData {
@@ -405,6 +428,9 @@ impl From<&'_ Transaction> for EthereumTransactionData {
             gas_price: tx.gas_price.unwrap_or(U256::zero()), // EIP-1559 made this optional.
             input: tx.input.0.clone(),
             nonce: tx.nonce,
+            max_fee_per_gas: tx.max_fee_per_gas,
+            max_priority_fee_per_gas: tx.max_priority_fee_per_gas,
+            transaction_type: tx.transaction_type,
         }
     }
 }
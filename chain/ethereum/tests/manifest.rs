@@ -7,13 +7,13 @@ use graph::data::subgraph::{SPEC_VERSION_0_0_4, SPEC_VERSION_0_0_7};
 use graph::data_source::DataSourceTemplate;
 use graph::prelude::{
     anyhow, async_trait, serde_yaml, tokio, DeploymentHash, Entity, Link, Logger, SubgraphManifest,
-    SubgraphManifestValidationError, UnvalidatedSubgraphManifest,
+    SubgraphManifestValidationError, UnvalidatedSubgraphManifest, ENV_VARS,
 };
 use graph::{
     blockchain::NodeCapabilities as _,
     components::{
         link_resolver::{JsonValueStream, LinkResolver as LinkResolverTrait},
-        store::EntityType,
+        store::{EntityType, PruneReporter, PruneRequest},
     },
     data::subgraph::SubgraphFeature,
 };
@@ -50,6 +50,14 @@ impl LinkResolverTrait for TextResolver {
         Box::new(self.clone())
     }
 
+    fn with_max_retries(&self, _max_retries: usize) -> Box<dyn LinkResolverTrait> {
+        Box::new(self.clone())
+    }
+
+    fn with_max_file_size(&self, _max_file_size: usize) -> Box<dyn LinkResolverTrait> {
+        Box::new(self.clone())
+    }
+
     async fn cat(&self, _logger: &Logger, link: &Link) -> Result<Vec<u8>, anyhow::Error> {
         self.texts
             .get(&link.link)
@@ -178,6 +186,104 @@ specVersion: 0.0.2
     assert_eq!(12345, graft.block);
 }
 
+#[tokio::test]
+async fn handler_limits_manifest() {
+    const YAML: &str = "
+dataSources: []
+schema:
+  file:
+    /: /ipfs/Qmschema
+handlerLimits:
+  maxGas: 1000
+  timeout: 5
+  ipfsTimeout: 10
+  maxIpfsFileSize: 1024
+specVersion: 0.0.2
+";
+
+    let manifest = resolve_manifest(YAML, SPEC_VERSION_0_0_4).await;
+
+    assert_eq!(Some(1000), manifest.handler_limits.max_gas);
+    assert_eq!(Some(5), manifest.handler_limits.timeout);
+    assert_eq!(Some(10), manifest.handler_limits.ipfs_timeout);
+    assert_eq!(Some(1024), manifest.handler_limits.max_ipfs_file_size);
+    // Lowering the limits is always allowed
+    assert_eq!(1000, manifest.handler_limits.max_gas());
+    assert_eq!(1024, manifest.handler_limits.max_ipfs_file_size());
+    assert!(manifest.handler_limits.exceeded_ceilings().is_empty());
+
+    // Raising them above the ceiling is not allowed
+    let yaml = YAML.replace("maxGas: 1000", &format!("maxGas: {}", u64::MAX));
+    let manifest = resolve_manifest(&yaml, SPEC_VERSION_0_0_4).await;
+    assert_eq!(
+        vec![format!(
+            "maxGas {} is above {}",
+            u64::MAX,
+            ENV_VARS.max_gas_per_handler_ceiling
+        )],
+        manifest.handler_limits.exceeded_ceilings()
+    );
+
+    let yaml = YAML.replace("maxIpfsFileSize: 1024", "maxIpfsFileSize: 1000000000000");
+    let manifest = resolve_manifest(&yaml, SPEC_VERSION_0_0_4).await;
+    assert_eq!(
+        vec![format!(
+            "maxIpfsFileSize 1000000000000 is above {}",
+            ENV_VARS.mappings.max_ipfs_file_bytes_ceiling
+        )],
+        manifest.handler_limits.exceeded_ceilings()
+    );
+
+    let yaml = YAML.replace("ipfsTimeout: 10", "ipfsTimeout: 1000000");
+    let manifest = resolve_manifest(&yaml, SPEC_VERSION_0_0_4).await;
+    assert_eq!(
+        vec![format!(
+            "ipfsTimeout 1000000s is above {}s",
+            ENV_VARS.mappings.ipfs_timeout_ceiling.as_secs()
+        )],
+        manifest.handler_limits.exceeded_ceilings()
+    );
+}
+
+#[test]
+fn handler_limits_above_ceiling_are_rejected() {
+    const YAML: &str = "
+dataSources: []
+schema:
+  file:
+    /: /ipfs/Qmschema
+handlerLimits:
+  maxGas: 1000
+specVersion: 0.0.2
+";
+    test_store::run_test_sequentially(|store| async move {
+        let store = store.subgraph_store();
+        let exceeds_ceilings = |yaml: String| {
+            let store = store.clone();
+            async move {
+                resolve_unvalidated(&yaml)
+                    .await
+                    .validate(store, true)
+                    .await
+                    .expect_err("Validation must fail")
+                    .into_iter()
+                    .any(|e| {
+                        matches!(
+                            e,
+                            SubgraphManifestValidationError::HandlerLimitsExceedCeilings(_)
+                        )
+                    })
+            }
+        };
+
+        assert!(!exceeds_ceilings(YAML.to_string()).await);
+        let yaml = YAML.replace("maxGas: 1000", &format!("maxGas: {}", u64::MAX));
+        assert!(exceeds_ceilings(yaml).await);
+        let yaml = YAML.replace("maxGas: 1000", "maxIpfsFileSize: 1000000000000");
+        assert!(exceeds_ceilings(yaml).await);
+    });
+}
+
 #[test]
 fn graft_failed_subgraph() {
     const YAML: &str = "
@@ -360,6 +466,87 @@ specVersion: 0.0.2
     })
 }
 
+#[test]
+fn graft_pruned_base_manifest() {
+    const YAML: &str = "
+dataSources: []
+schema:
+  file:
+    /: /ipfs/Qmschema
+graft:
+  base: Qmbase
+  block: 0
+specVersion: 0.0.2
+";
+
+    struct Progress;
+    impl PruneReporter for Progress {}
+
+    test_store::run_test_sequentially(|store| async move {
+        let subgraph_store = store.subgraph_store();
+
+        let subgraph = DeploymentHash::new("Qmbase").unwrap();
+        let deployment = test_store::create_test_subgraph(&subgraph, GQL_SCHEMA).await;
+        test_store::transact_and_wait(
+            &subgraph_store,
+            &deployment,
+            test_store::BLOCKS[3].clone(),
+            vec![],
+        )
+        .await
+        .unwrap();
+
+        // Keep 2 blocks of history, which makes block 1 the earliest block
+        let req = PruneRequest::new(&deployment, 2, 1, 0, 3).unwrap();
+        subgraph_store
+            .prune(Box::new(Progress), &deployment, req)
+            .await
+            .unwrap();
+
+        let unvalidated = resolve_unvalidated(YAML).await;
+        let msg = unvalidated
+            .validate(subgraph_store.clone(), true)
+            .await
+            .expect_err("Validation must fail")
+            .into_iter()
+            .find(|e| matches!(e, SubgraphManifestValidationError::GraftBaseInvalid(_)))
+            .expect("There must be a GraftBaseInvalid error")
+            .to_string();
+        assert_eq!(
+            "the graft base is invalid: failed to graft onto `Qmbase` \
+            at block 0 since it has been pruned and only has data starting at block 1. \
+            You can graft it starting at block 1 or later, or deploy with \
+            `graft_at_earliest_block` to graft at that block",
+            msg
+        );
+
+        // Grafting at the earliest block of the base works
+        let mut unvalidated = resolve_unvalidated(YAML).await;
+        let moved = unvalidated
+            .graft_at_earliest_block(subgraph_store.clone())
+            .await
+            .unwrap();
+        assert_eq!(Some(0), moved);
+        assert_eq!(
+            Some(1),
+            unvalidated
+                .manifest()
+                .graft
+                .as_ref()
+                .map(|graft| graft.block)
+        );
+        assert!(
+            !unvalidated
+                .validate(subgraph_store, true)
+                .await
+                .expect_err("Validation must fail")
+                .into_iter()
+                .any(|e| matches!(&e, SubgraphManifestValidationError::GraftBaseInvalid(_))),
+            "There shouldn't be a GraftBaseInvalid error"
+        );
+    })
+}
+
 #[tokio::test]
 async fn parse_call_handlers() {
     const YAML: &str = "
@@ -403,6 +590,43 @@ specVersion: 0.0.2
     assert_eq!(true, required_capabilities.traces);
 }
 
+#[tokio::test]
+async fn parse_subgraph_data_source() {
+    const YAML: &str = "
+dataSources:
+  - kind: subgraph
+    name: Base
+    source:
+      address: Qmbase
+      startBlock: 100
+    mapping:
+      apiVersion: 0.0.7
+      language: wasm/assemblyscript
+      entities:
+        - Thing
+      file:
+        /: /ipfs/Qmmapping
+      handlers:
+        - handler: handleThing
+          entity: Thing
+schema:
+  file:
+    /: /ipfs/Qmschema
+specVersion: 0.0.2
+";
+
+    let manifest = resolve_manifest(YAML, SPEC_VERSION_0_0_4).await;
+    let ds = manifest.data_sources[0].as_subgraph().unwrap();
+
+    assert_eq!("Qmbase", ds.source.address.as_str());
+    assert_eq!(100, ds.source.start_block);
+    assert_eq!(
+        vec!["handleThing"],
+        manifest.data_sources[0].handler_names()
+    );
+    assert!(ds.validate().is_empty());
+}
+
 #[test]
 fn undeclared_grafting_feature_causes_feature_validation_error() {
     const YAML: &str = "
@@ -504,6 +728,51 @@ schema:
     });
 }
 
+#[test]
+fn skip_timeout_requires_non_fatal_errors() {
+    const YAML: &str = "
+specVersion: 0.0.4
+features:
+  - nonFatalErrors
+handlerLimits:
+  skipTimeout: 5
+dataSources: []
+schema:
+  file:
+    /: /ipfs/Qmschema
+";
+    test_store::run_test_sequentially(|store| async move {
+        let store = store.subgraph_store();
+        let skip_timeout_error = |yaml: String| {
+            let store = store.clone();
+            async move {
+                resolve_unvalidated(&yaml)
+                    .await
+                    .validate(store, true)
+                    .await
+                    .expect_err("Validation must fail")
+                    .into_iter()
+                    .any(|e| {
+                        matches!(
+                            e,
+                            SubgraphManifestValidationError::SkipTimeoutWithoutNonFatalErrors
+                        )
+                    })
+            }
+        };
+
+        assert!(!skip_timeout_error(YAML.to_string()).await);
+        assert!(skip_timeout_error(YAML.replace("  - nonFatalErrors", "  - grafting")).await);
+
+        let manifest = resolve_manifest(YAML, SPEC_VERSION_0_0_4).await;
+        assert_eq!(Some(5), manifest.handler_limits.skip_timeout);
+        assert_eq!(
+            Some(Duration::from_secs(5)),
+            manifest.handler_limits.skip_timeout()
+        );
+    });
+}
+
 #[test]
 fn declared_full_text_search_feature_causes_no_feature_validation_errors() {
     const YAML: &str = "
@@ -143,9 +143,10 @@ impl NearReceiptFilter {
         // prefix [a] and suffix [c,d] would produce [a,c], [a,d]
         // prefix [] and suffix [c, d] would produce [None, c], [None, d]
         // prefix [a,b] and suffix [] would produce [a, None], [b, None]
+        // prefix [] and suffix [] would match every account and is ignored
         let partial_accounts: Vec<(Option<String>, Option<String>)> = sources
             .iter()
-            .filter(|s| s.partial_accounts.is_some())
+            .filter(|s| s.partial_accounts.as_ref().map_or(false, |p| !p.is_empty()))
             .flat_map(|s| {
                 let partials = s.partial_accounts.as_ref().unwrap();
 
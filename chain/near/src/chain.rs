@@ -420,9 +420,10 @@ mod test {
     use crate::{
         adapter::{NearReceiptFilter, TriggerFilter},
         codec::{
-            self, execution_outcome, receipt, Block, BlockHeader, DataReceiver, ExecutionOutcome,
-            ExecutionOutcomeWithId, IndexerExecutionOutcomeWithReceipt, IndexerShard,
-            ReceiptAction, SuccessValueExecutionStatus,
+            self, action, execution_outcome, receipt, Action, Block, BlockHeader, DataReceiver,
+            ExecutionOutcome, ExecutionOutcomeWithId, FunctionCallAction,
+            IndexerExecutionOutcomeWithReceipt, IndexerShard, ReceiptAction,
+            SuccessValueExecutionStatus,
         },
         data_source::{DataSource, Mapping, PartialAccounts, ReceiptHandler, NEAR_KIND},
         trigger::{NearTrigger, ReceiptWithOutcome},
@@ -553,6 +554,12 @@ mod test {
                     (Some("b".to_string()), None),
                 ]),
             },
+            Case {
+                name: "no prefix && no suffix".into(),
+                account: Some("x.near".to_string()),
+                partial_accounts: Some(PartialAccounts::default()),
+                expected: HashSet::new(),
+            },
         ];
 
         for case in cases.into_iter() {
@@ -736,6 +743,54 @@ mod test {
         }
     }
 
+    #[test]
+    fn receipt_handler_method_filter() {
+        let account = "x.near".to_string();
+        let call = |method_name: &str, args: &str| {
+            let block = Arc::new(new_success_block(11, &account));
+            let mut receipt = new_receipt_with_outcome(&account, block.clone());
+            receipt.receipt.receipt = Some(receipt::Receipt::Action(ReceiptAction {
+                actions: vec![Action {
+                    action: Some(action::Action::FunctionCall(FunctionCallAction {
+                        method_name: method_name.to_string(),
+                        args: args.as_bytes().to_vec(),
+                        ..Default::default()
+                    })),
+                }],
+                ..Default::default()
+            }));
+            (block, Arc::new(receipt))
+        };
+
+        let mut ds = new_data_source(Some(account.clone()), None);
+        ds.mapping.receipt_handlers[0].method_names =
+            vec!["ft_transfer".to_string(), "ft_transfer_call".to_string()];
+        ds.mapping.receipt_handlers[0].args_prefix = Some(r#"{"receiver_id":"a"#.to_string());
+
+        let logger = Logger::root(slog::Discard, o!());
+        let cases = vec![
+            ("ft_transfer", r#"{"receiver_id":"a.near"}"#, true),
+            ("ft_transfer_call", r#"{"receiver_id":"ab.near"}"#, true),
+            ("ft_transfer", r#"{"receiver_id":"b.near"}"#, false),
+            ("storage_deposit", r#"{"receiver_id":"a.near"}"#, false),
+        ];
+        for (method_name, args, matches) in cases {
+            let (block, receipt) = call(method_name, args);
+            let res = ds
+                .match_and_decode(&NearTrigger::Receipt(receipt), &block, &logger)
+                .expect("unable to process block");
+            assert_eq!(matches, res.is_some(), "{} {}", method_name, args);
+        }
+
+        // Receipts without function calls never match a method filter
+        let block = Arc::new(new_success_block(11, &account));
+        let receipt = Arc::new(new_receipt_with_outcome(&account, block.clone()));
+        let res = ds
+            .match_and_decode(&NearTrigger::Receipt(receipt), &block, &logger)
+            .expect("unable to process block");
+        assert!(res.is_none());
+    }
+
     #[tokio::test]
     async fn test_trigger_filter_empty() {
         let account1: String = "account1".into();
@@ -876,6 +931,8 @@ mod test {
                 block_handlers: vec![],
                 receipt_handlers: vec![ReceiptHandler {
                     handler: "asdsa".to_string(),
+                    method_names: vec![],
+                    args_prefix: None,
                 }],
                 runtime: Arc::new(vec![]),
                 link: Link::default(),
@@ -1,6 +1,6 @@
 use graph::anyhow::Context;
 use graph::blockchain::{Block, TriggerWithHandler};
-use graph::components::store::StoredDynamicDataSource;
+use graph::components::store::{EntityType, StoredDynamicDataSource};
 use graph::data::subgraph::DataSourceContext;
 use graph::prelude::SubgraphManifestValidationError;
 use graph::{
@@ -15,6 +15,7 @@ use graph::{
 use std::sync::Arc;
 
 use crate::chain::Chain;
+use crate::codec;
 use crate::trigger::{NearTrigger, ReceiptWithOutcome};
 
 pub const NEAR_KIND: &str = "near";
@@ -90,7 +91,10 @@ impl blockchain::DataSource<Chain> for DataSource {
                 return true;
             }
 
-            if let Some(partial_accounts) = &ds.source.accounts {
+            // Empty partial accounts do not match anything, just like in
+            // the receipt filter
+            let partial_accounts = ds.source.accounts.as_ref().filter(|a| !a.is_empty());
+            if let Some(partial_accounts) = partial_accounts {
                 let matches_prefix = if partial_accounts.prefixes.is_empty() {
                     true
                 } else {
@@ -132,8 +136,8 @@ impl blockchain::DataSource<Chain> for DataSource {
                 }
 
                 match self.handler_for_receipt() {
-                    Some(handler) => &handler.handler,
-                    None => return Ok(None),
+                    Some(handler) if handler.matches(&receipt.receipt) => &handler.handler,
+                    _ => return Ok(None),
                 }
             }
         };
@@ -248,6 +252,14 @@ impl blockchain::DataSource<Chain> for DataSource {
         if self.mapping.receipt_handlers.len() > 1 {
             errors.push(anyhow!("data source has duplicated receipt handlers"));
         }
+        for handler in &self.mapping.receipt_handlers {
+            if handler.method_names.iter().any(|name| name.is_empty()) {
+                errors.push(anyhow!(
+                    "receipt handler `{}` can't have empty method names",
+                    handler.handler
+                ));
+            }
+        }
 
         errors
     }
@@ -259,6 +271,20 @@ impl blockchain::DataSource<Chain> for DataSource {
     fn runtime(&self) -> Option<Arc<Vec<u8>>> {
         Some(self.mapping.runtime.cheap_clone())
     }
+
+    fn handler_names(&self) -> Vec<&str> {
+        self.mapping.handler_names()
+    }
+
+    fn declared_entities(&self) -> Option<Vec<EntityType>> {
+        Some(
+            self.mapping
+                .entities
+                .iter()
+                .map(|entity| EntityType::new(entity.clone()))
+                .collect(),
+        )
+    }
 }
 
 impl DataSource {
@@ -387,6 +413,10 @@ impl blockchain::DataSourceTemplate<Chain> for DataSourceTemplate {
     fn manifest_idx(&self) -> u32 {
         unreachable!("near does not support dynamic data sources")
     }
+
+    fn handler_names(&self) -> Vec<&str> {
+        self.mapping.handler_names()
+    }
 }
 
 #[derive(Clone, Debug, Default, Hash, Eq, PartialEq, Deserialize)]
@@ -447,14 +477,63 @@ pub struct Mapping {
     pub link: Link,
 }
 
+impl Mapping {
+    pub fn handler_names(&self) -> Vec<&str> {
+        self.block_handlers
+            .iter()
+            .map(|handler| handler.handler.as_str())
+            .chain(
+                self.receipt_handlers
+                    .iter()
+                    .map(|handler| handler.handler.as_str()),
+            )
+            .collect()
+    }
+}
+
 #[derive(Clone, Debug, Hash, Eq, PartialEq, Deserialize)]
 pub struct MappingBlockHandler {
     pub handler: String,
 }
 
 #[derive(Clone, Debug, Hash, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ReceiptHandler {
     pub(crate) handler: String,
+    /// If not empty, the handler is only called for receipts with a
+    /// function call to one of these methods
+    #[serde(default)]
+    pub(crate) method_names: Vec<String>,
+    /// If set, the handler is only called for receipts with a function
+    /// call whose arguments start with this string
+    #[serde(default)]
+    pub(crate) args_prefix: Option<String>,
+}
+
+impl ReceiptHandler {
+    /// Whether the handler should be called for `receipt`. Receipts are
+    /// filtered here, before the mapping is called, since the Firehose
+    /// receipt filter can only select receipts by their receiver
+    pub(crate) fn matches(&self, receipt: &codec::Receipt) -> bool {
+        if self.method_names.is_empty() && self.args_prefix.is_none() {
+            return true;
+        }
+
+        let actions = match &receipt.receipt {
+            Some(codec::receipt::Receipt::Action(action)) => &action.actions,
+            _ => return false,
+        };
+        actions.iter().any(|action| match &action.action {
+            Some(codec::action::Action::FunctionCall(call)) => {
+                (self.method_names.is_empty() || self.method_names.contains(&call.method_name))
+                    && self
+                        .args_prefix
+                        .as_ref()
+                        .map_or(true, |prefix| call.args.starts_with(prefix.as_bytes()))
+            }
+            _ => false,
+        })
+    }
 }
 
 #[derive(Clone, Debug, Hash, Eq, PartialEq, Deserialize, Default)]
@@ -1,16 +1,18 @@
 use std::sync::Arc;
+use std::time::Duration;
 
-use anyhow::{anyhow, Error};
+use anyhow::{anyhow, Context, Error};
 use graph::{
     blockchain,
     cheap_clone::CheapClone,
     components::link_resolver::LinkResolver,
-    prelude::{async_trait, BlockNumber, DataSourceTemplateInfo, Link},
+    prelude::{async_trait, reqwest, BlockNumber, DataSourceTemplateInfo, Link, ENV_VARS},
     slog::Logger,
 };
 
 use prost::Message;
 use serde::Deserialize;
+use sha2::{Digest, Sha256};
 
 use crate::{chain::Chain, Block, TriggerData};
 
@@ -19,6 +21,9 @@ pub const SUBSTREAMS_KIND: &str = "substreams";
 const DYNAMIC_DATA_SOURCE_ERROR: &str = "Substreams do not support dynamic data sources";
 const TEMPLATE_ERROR: &str = "Substreams do not support templates";
 
+/// How long fetching a package from a URL or the registry may take
+const PACKAGE_FETCH_TIMEOUT: Duration = Duration::from_secs(120);
+
 const ALLOWED_MAPPING_KIND: [&str; 1] = ["substreams/graph-entities"];
 
 #[derive(Clone, Debug, PartialEq)]
@@ -76,6 +81,10 @@ impl blockchain::DataSource<Chain> for DataSource {
         None
     }
 
+    fn handler_names(&self) -> Vec<&str> {
+        vec![]
+    }
+
     // match_and_decode only seems to be used on the default trigger processor which substreams
     // bypasses so it should be fine to leave it unimplemented.
     fn match_and_decode(
@@ -168,7 +177,7 @@ impl blockchain::UnresolvedDataSource<Chain> for UnresolvedDataSource {
         logger: &Logger,
         _manifest_idx: u32,
     ) -> Result<DataSource, Error> {
-        let content = resolver.cat(logger, &self.source.package.file).await?;
+        let content = self.source.package.content(resolver, logger).await?;
 
         let package = graph::substreams::Package::decode(content.as_ref())?;
 
@@ -229,10 +238,110 @@ pub struct UnresolvedSource {
 
 #[derive(Clone, Debug, Default, Hash, Eq, PartialEq, Deserialize)]
 #[serde(rename_all = "camelCase")]
-/// The unresolved Package section of the manifest.
+/// The unresolved Package section of the manifest. The package comes from
+/// exactly one of `file`, which is on IPFS, `url` or `registry`.
 pub struct UnresolvedPackage {
     pub module_name: String,
-    pub file: Link,
+    #[serde(default)]
+    pub file: Option<Link>,
+    /// An `https` URL of the `.spkg` file
+    #[serde(default)]
+    pub url: Option<String>,
+    /// The name of the package in the Substreams registry, like
+    /// `streamingfast/uniswap-v3-v0.2.8`
+    #[serde(default)]
+    pub registry: Option<String>,
+    /// The checksum `sha256:<hex>` of the `.spkg` file. It is required for
+    /// packages from a URL or the registry since their content can change
+    #[serde(default)]
+    pub checksum: Option<String>,
+}
+
+impl UnresolvedPackage {
+    /// The content of the `.spkg` file, which is checked against the
+    /// checksum if there is one
+    async fn content(
+        &self,
+        resolver: &Arc<dyn LinkResolver>,
+        logger: &Logger,
+    ) -> Result<Vec<u8>, Error> {
+        let content = match (&self.file, &self.url, &self.registry) {
+            (Some(file), None, None) => resolver.cat(logger, file).await?,
+            (None, Some(url), None) => {
+                if !url.starts_with("https://") {
+                    return Err(anyhow!(
+                        "the URL {} of a Substreams package must use https",
+                        url
+                    ));
+                }
+                self.fetch(url).await?
+            }
+            (None, None, Some(name)) => {
+                let url = format!(
+                    "{}/{}.spkg",
+                    ENV_VARS.substreams_registry_url.trim_end_matches('/'),
+                    name
+                );
+                self.fetch(&url).await?
+            }
+            _ => {
+                return Err(anyhow!(
+                    "the Substreams package of module {} must have exactly one of \
+                     `file`, `url` or `registry`",
+                    self.module_name
+                ))
+            }
+        };
+
+        if let Some(checksum) = &self.checksum {
+            let expected = parse_checksum(checksum)?;
+            let actual = Sha256::digest(&content);
+            if actual.as_slice() != expected.as_slice() {
+                return Err(anyhow!(
+                    "the Substreams package of module {} has checksum sha256:{}, expected {}",
+                    self.module_name,
+                    hex::encode(actual),
+                    checksum
+                ));
+            }
+        }
+        Ok(content)
+    }
+
+    async fn fetch(&self, url: &str) -> Result<Vec<u8>, Error> {
+        if self.checksum.is_none() {
+            return Err(anyhow!(
+                "the Substreams package at {} needs a `checksum` since it is not on IPFS",
+                url
+            ));
+        }
+        let res = reqwest::Client::new()
+            .get(url)
+            .timeout(PACKAGE_FETCH_TIMEOUT)
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .with_context(|| format!("failed to fetch Substreams package from {}", url))?;
+        let content = res
+            .bytes()
+            .await
+            .with_context(|| format!("failed to fetch Substreams package from {}", url))?;
+        Ok(content.to_vec())
+    }
+}
+
+/// Parse a checksum `sha256:<hex>`
+fn parse_checksum(checksum: &str) -> Result<Vec<u8>, Error> {
+    checksum
+        .strip_prefix("sha256:")
+        .and_then(|hex| hex::decode(hex).ok())
+        .filter(|hash| hash.len() == 32)
+        .ok_or_else(|| {
+            anyhow!(
+                "invalid Substreams package checksum {}, it must have the form sha256:<hex>",
+                checksum
+            )
+        })
 }
 
 #[derive(Debug, Clone, Default, Deserialize)]
@@ -256,6 +365,10 @@ impl blockchain::DataSourceTemplate<Chain> for NoopDataSourceTemplate {
     fn manifest_idx(&self) -> u32 {
         todo!()
     }
+
+    fn handler_names(&self) -> Vec<&str> {
+        unimplemented!("{}", TEMPLATE_ERROR);
+    }
 }
 
 #[async_trait]
@@ -284,6 +397,7 @@ mod test {
         substreams::{Module, Modules, Package},
     };
     use prost::Message;
+    use sha2::{Digest, Sha256};
 
     use crate::{DataSource, Mapping, UnresolvedDataSource, UnresolvedMapping, SUBSTREAMS_KIND};
 
@@ -297,9 +411,10 @@ mod test {
             source: crate::UnresolvedSource {
                 package: crate::UnresolvedPackage {
                     module_name: "output".into(),
-                    file: Link {
+                    file: Some(Link {
                         link: "/ipfs/QmbHnhUFZa6qqqRyubUYhXntox1TCBxqryaBM1iNGqVJzT".into(),
-                    },
+                    }),
+                    ..Default::default()
                 },
             },
             mapping: UnresolvedMapping {
@@ -334,6 +449,51 @@ mod test {
         assert_eq!(ds, expected);
     }
 
+    #[tokio::test]
+    async fn package_checksum() {
+        let link_resolver: Arc<dyn LinkResolver> = Arc::new(NoopLinkResolver {});
+        let logger = Logger::root(Discard, o!());
+        let checksum = format!(
+            "sha256:{}",
+            hex::encode(Sha256::digest(gen_package().encode_to_vec()))
+        );
+        let package = |checksum: Option<String>| crate::UnresolvedPackage {
+            module_name: "output".into(),
+            file: Some(Link {
+                link: "/ipfs/QmbHnhUFZa6qqqRyubUYhXntox1TCBxqryaBM1iNGqVJzT".into(),
+            }),
+            checksum,
+            ..Default::default()
+        };
+
+        assert!(package(None).content(&link_resolver, &logger).await.is_ok());
+        assert!(package(Some(checksum))
+            .content(&link_resolver, &logger)
+            .await
+            .is_ok());
+        let wrong = format!("sha256:{}", hex::encode([0u8; 32]));
+        assert!(package(Some(wrong))
+            .content(&link_resolver, &logger)
+            .await
+            .is_err());
+        assert!(package(Some("md5:abcd".into()))
+            .content(&link_resolver, &logger)
+            .await
+            .is_err());
+
+        // A package can only come from one place, and needs a checksum if
+        // it is not on IPFS
+        let mut both = package(None);
+        both.registry = Some("streamingfast/uniswap-v3-v0.2.8".into());
+        assert!(both.content(&link_resolver, &logger).await.is_err());
+        let unpinned = crate::UnresolvedPackage {
+            module_name: "output".into(),
+            url: Some("https://example.com/uniswap-v3.spkg".into()),
+            ..Default::default()
+        };
+        assert!(unpinned.content(&link_resolver, &logger).await.is_err());
+    }
+
     #[test]
     fn data_source_validation() {
         let mut ds = gen_data_source();
@@ -450,6 +610,14 @@ mod test {
             unimplemented!()
         }
 
+        fn with_max_retries(&self, _max_retries: usize) -> Box<dyn LinkResolver> {
+            Box::new(NoopLinkResolver {})
+        }
+
+        fn with_max_file_size(&self, _max_file_size: usize) -> Box<dyn LinkResolver> {
+            Box::new(NoopLinkResolver {})
+        }
+
         async fn cat(&self, _logger: &Logger, _link: &Link) -> Result<Vec<u8>, Error> {
             Ok(gen_package().encode_to_vec())
         }
@@ -7,11 +7,12 @@ use graph::{
         store::{DeploymentLocator, EntityKey, EntityType, SubgraphFork},
         subgraph::{MappingError, ProofOfIndexingEvent, SharedProofOfIndexing},
     },
-    data::store::scalar::Bytes,
+    data::store::{scalar::Bytes, EntityValidationError},
+    data::subgraph::schema::SubgraphError,
     data_source::{self, CausalityRegion},
     prelude::{
-        anyhow, async_trait, BigDecimal, BigInt, BlockHash, BlockNumber, BlockState, Entity,
-        RuntimeHostBuilder, Value,
+        anyhow, async_trait, BigDecimal, BigInt, BlockHash, BlockNumber, BlockState,
+        DeploymentHash, Entity, RuntimeHostBuilder, Value,
     },
     slog::Logger,
     substreams::Modules,
@@ -174,6 +175,9 @@ where
         _subgraph_metrics: &Arc<graph::prelude::SubgraphInstanceMetrics>,
         _instrument: bool,
     ) -> Result<BlockState<Chain>, MappingError> {
+        // Process all changes like one handler so that an invalid entity
+        // discards all changes of the block
+        state.enter_handler();
         for entity_change in block.changes.entity_changes.iter() {
             match entity_change.operation() {
                 Operation::Unset => {
@@ -214,7 +218,15 @@ where
                         logger,
                     );
 
-                    state.entity_cache.set(key, Entity::from(data))?;
+                    if let Err(e) = state.entity_cache.set(key, Entity::from(data)) {
+                        let error = deterministic_error(
+                            &self.locator.hash,
+                            blockchain::Block::ptr(block.as_ref()),
+                            e,
+                        )?;
+                        state.exit_handler_and_discard_changes_due_to_error(error);
+                        return Ok(state);
+                    }
                 }
                 Operation::Delete => {
                     let entity_type: &str = &entity_change.entity;
@@ -239,11 +251,32 @@ where
                 }
             }
         }
+        state.exit_handler();
 
         Ok(state)
     }
 }
 
+/// Turn an error from setting an entity into a deterministic subgraph error
+/// if the entity does not match the schema, which will happen again every
+/// time the block is processed. Any other error is passed on as is
+fn deterministic_error(
+    subgraph_id: &DeploymentHash,
+    block_ptr: BlockPtr,
+    e: Error,
+) -> Result<SubgraphError, MappingError> {
+    match e.downcast::<EntityValidationError>() {
+        Ok(e) => Ok(SubgraphError {
+            subgraph_id: subgraph_id.clone(),
+            message: e.to_string(),
+            block_ptr: Some(block_ptr),
+            handler: None,
+            deterministic: true,
+        }),
+        Err(e) => Err(MappingError::Unknown(e)),
+    }
+}
+
 fn decode_value(value: &crate::codec::value::Typed) -> Result<Value, MappingError> {
     use codec::value::Typed;
 
@@ -285,14 +318,22 @@ fn decode_value(value: &crate::codec::value::Typed) -> Result<Value, MappingErro
 
 #[cfg(test)]
 mod test {
-    use std::{ops::Add, str::FromStr};
+    use std::{ops::Add, str::FromStr, sync::Arc};
 
     use crate::codec::value::Typed;
     use crate::codec::{Array, Value};
-    use crate::trigger::decode_value;
+    use crate::trigger::{decode_value, deterministic_error};
     use graph::{
+        blockchain::BlockPtr,
+        components::{
+            store::{EmptyStore, EntityKey},
+            subgraph::MappingError,
+        },
         data::store::scalar::Bytes,
-        prelude::{BigDecimal, BigInt, Value as GraphValue},
+        prelude::{
+            anyhow, BigDecimal, BigInt, DeploymentHash, Entity, EntityCache, Schema,
+            Value as GraphValue,
+        },
     };
 
     #[test]
@@ -409,4 +450,28 @@ mod test {
             assert_eq!(case.expected_value, value, "failed case: {}", case.name)
         }
     }
+
+    #[test]
+    fn invalid_entities_are_deterministic_errors() {
+        let id = DeploymentHash::new("substreams").unwrap();
+        let schema =
+            Schema::parse("type User @entity { id: ID!, name: String! }", id.clone()).unwrap();
+        let mut cache = EntityCache::new(Arc::new(EmptyStore::new(Arc::new(schema))));
+        let block_ptr = BlockPtr::from((vec![0xff; 32], 1i32));
+
+        let mut user = Entity::new();
+        user.set("id", "bob");
+        let err = cache
+            .set(
+                EntityKey::data("User".to_string(), "alex".to_string()),
+                user,
+            )
+            .unwrap_err();
+        let error = deterministic_error(&id, block_ptr.clone(), err).unwrap();
+        assert!(error.deterministic);
+        assert_eq!(Some(block_ptr.clone()), error.block_ptr);
+
+        let err = deterministic_error(&id, block_ptr, anyhow!("connection lost")).unwrap_err();
+        assert!(matches!(err, MappingError::Unknown(_)));
+    }
 }
@@ -1,4 +1,6 @@
-use std::sync::{Arc, Mutex};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::Duration;
 
 use anyhow::anyhow;
@@ -12,26 +14,30 @@ use lru_time_cache::LruCache;
 use serde_json::Value;
 
 use graph::{
+    components::link_resolver::IpfsCache,
     ipfs_client::{IpfsClient, StatApi},
     prelude::{LinkResolver as LinkResolverTrait, *},
 };
 
 fn retry_policy<I: Send + Sync>(
     always_retry: bool,
+    max_retries: Option<usize>,
     op: &'static str,
     logger: &Logger,
 ) -> RetryConfigNoTimeout<I, graph::prelude::reqwest::Error> {
     // Even if retries were not requested, networking errors are still retried until we either get
-    // a valid HTTP response or a timeout.
+    // a valid HTTP response or a timeout, or until `max_retries` is reached if there is one.
     if always_retry {
         retry(op, logger).no_limit()
     } else {
-        retry(op, logger)
-            .no_limit()
-            .when(|res: &Result<_, reqwest::Error>| match res {
-                Ok(_) => false,
-                Err(e) => !(e.is_status() || e.is_timeout()),
-            })
+        let config = retry(op, logger).when(|res: &Result<_, reqwest::Error>| match res {
+            Ok(_) => false,
+            Err(e) => !(e.is_status() || e.is_timeout()),
+        });
+        match max_retries {
+            Some(max_retries) => config.limit(max_retries),
+            None => config.no_limit(),
+        }
     }
     .no_timeout() // The timeout should be set in the internal future.
 }
@@ -55,6 +61,7 @@ async fn select_fastest_client_with_stat(
     path: String,
     timeout: Duration,
     do_retry: bool,
+    max_retries: Option<usize>,
 ) -> Result<(u64, Arc<IpfsClient>), Error> {
     let mut err: Option<Error> = None;
 
@@ -64,7 +71,7 @@ async fn select_fastest_client_with_stat(
         .map(|(i, c)| {
             let c = c.cheap_clone();
             let path = path.clone();
-            retry_policy(do_retry, "IPFS stat", &logger).run(move || {
+            retry_policy(do_retry, max_retries, "IPFS stat", &logger).run(move || {
                 let path = path.clone();
                 let c = c.cheap_clone();
                 async move {
@@ -106,12 +113,27 @@ fn restrict_file_size(path: &str, size: u64, max_file_bytes: usize) -> Result<()
     Ok(())
 }
 
+/// Subgraphs that are deployed from files on disk rather than from IPFS
+#[derive(Default)]
+struct LocalSubgraphs {
+    /// The manifests of the subgraphs by deployment hash
+    manifests: HashMap<String, Vec<u8>>,
+    /// The files that `file://` links may point to. Other files can not be
+    /// read so that manifests from IPFS can not read files from the node
+    files: HashSet<PathBuf>,
+}
+
 #[derive(Clone)]
 pub struct LinkResolver {
     clients: Arc<Vec<Arc<IpfsClient>>>,
     cache: Arc<Mutex<LruCache<String, Vec<u8>>>>,
+    /// The cache that is shared with other nodes, if there is one
+    store_cache: Option<Arc<dyn IpfsCache>>,
+    local: Arc<RwLock<LocalSubgraphs>>,
     timeout: Duration,
     retry: bool,
+    max_retries: Option<usize>,
+    max_file_size: usize,
     env_vars: Arc<EnvVars>,
 }
 
@@ -122,11 +144,78 @@ impl LinkResolver {
             cache: Arc::new(Mutex::new(LruCache::with_capacity(
                 env_vars.mappings.max_ipfs_cache_size as usize,
             ))),
+            store_cache: None,
+            local: Arc::new(RwLock::new(LocalSubgraphs::default())),
             timeout: env_vars.mappings.ipfs_timeout,
             retry: false,
+            max_retries: None,
+            max_file_size: env_vars.mappings.max_ipfs_file_bytes,
             env_vars,
         }
     }
+
+    /// Look up files that are not in memory in `store_cache` before
+    /// fetching them from IPFS, and add the files that were fetched to it
+    pub fn with_store_cache(mut self, store_cache: Arc<dyn IpfsCache>) -> Self {
+        self.store_cache = Some(store_cache);
+        self
+    }
+
+    /// Serve `manifest` as the manifest of the deployment `hash` and allow
+    /// `file://` links to `files`, so that subgraphs can be deployed from
+    /// disk without IPFS
+    pub fn add_local_subgraph(
+        &self,
+        hash: &DeploymentHash,
+        manifest: Vec<u8>,
+        files: impl IntoIterator<Item = PathBuf>,
+    ) {
+        let mut local = self.local.write().unwrap();
+        local.manifests.insert(hash.to_string(), manifest);
+        local.files.extend(files);
+    }
+
+    fn cat_local_file(&self, path: &str) -> Result<Vec<u8>, Error> {
+        let path = Path::new(path);
+        if !self.local.read().unwrap().files.contains(path) {
+            return Err(anyhow!(
+                "file {} is not a file of a local subgraph",
+                path.display()
+            ));
+        }
+        let data = std::fs::read(path)
+            .with_context(|| format!("failed to read file {}", path.display()))?;
+        restrict_file_size(
+            &path.to_string_lossy(),
+            data.len() as u64,
+            self.max_file_size,
+        )?;
+        Ok(data)
+    }
+
+    fn cache_in_memory(&self, logger: &Logger, path: &str, data: &[u8]) {
+        // Only cache files if they are not too large
+        if data.len() <= self.env_vars.mappings.max_ipfs_cache_file_size {
+            let mut cache = self.cache.lock().unwrap();
+            if !cache.contains_key(path) {
+                cache.insert(path.to_owned(), data.to_vec());
+            }
+        } else {
+            debug!(logger, "File too large for cache";
+                        "path" => path,
+                        "size" => data.len()
+            );
+        }
+    }
+
+    /// The clients to send a request to. Nodes that could not be reached
+    /// recently are left out as long as there are others
+    fn healthy_clients(&self) -> Arc<Vec<Arc<IpfsClient>>> {
+        Arc::new(IpfsClient::healthy(
+            &self.clients,
+            self.env_vars.mappings.ipfs_unhealthy_backoff,
+        ))
+    }
 }
 
 impl Debug for LinkResolver {
@@ -134,6 +223,8 @@ impl Debug for LinkResolver {
         f.debug_struct("LinkResolver")
             .field("timeout", &self.timeout)
             .field("retry", &self.retry)
+            .field("max_retries", &self.max_retries)
+            .field("max_file_size", &self.max_file_size)
             .field("env_vars", &self.env_vars)
             .finish()
     }
@@ -159,34 +250,64 @@ impl LinkResolverTrait for LinkResolver {
         Box::new(s)
     }
 
-    /// Supports links of the form `/ipfs/ipfs_hash` or just `ipfs_hash`.
+    fn with_max_retries(&self, max_retries: usize) -> Box<dyn LinkResolverTrait> {
+        let mut s = self.cheap_clone();
+        s.max_retries = Some(max_retries);
+        Box::new(s)
+    }
+
+    fn with_max_file_size(&self, max_file_size: usize) -> Box<dyn LinkResolverTrait> {
+        let mut s = self.cheap_clone();
+        s.max_file_size = max_file_size;
+        Box::new(s)
+    }
+
+    /// Supports links of the form `/ipfs/ipfs_hash` or just `ipfs_hash`,
+    /// and `file://` links to the files of local subgraphs.
     async fn cat(&self, logger: &Logger, link: &Link) -> Result<Vec<u8>, Error> {
+        if let Some(path) = link.link.strip_prefix("file://") {
+            return self.cat_local_file(path);
+        }
+
         // Discard the `/ipfs/` prefix (if present) to get the hash.
         let path = link.link.trim_start_matches("/ipfs/").to_owned();
 
+        if let Some(manifest) = self.local.read().unwrap().manifests.get(&path) {
+            return Ok(manifest.clone());
+        }
+
         if let Some(data) = self.cache.lock().unwrap().get(&path) {
             trace!(logger, "IPFS cache hit"; "hash" => &path);
             return Ok(data.clone());
         }
         trace!(logger, "IPFS cache miss"; "hash" => &path);
 
+        if let Some(store_cache) = &self.store_cache {
+            if let Some(data) = store_cache.get(&path).await {
+                trace!(logger, "IPFS store cache hit"; "hash" => &path);
+                restrict_file_size(&path, data.len() as u64, self.max_file_size)?;
+                self.cache_in_memory(logger, &path, &data);
+                return Ok(data);
+            }
+        }
+
         let (size, client) = select_fastest_client_with_stat(
-            self.clients.cheap_clone(),
+            self.healthy_clients(),
             logger.cheap_clone(),
             StatApi::Files,
             path.clone(),
             self.timeout,
             self.retry,
+            self.max_retries,
         )
         .await?;
 
-        let max_cache_file_size = self.env_vars.mappings.max_ipfs_cache_file_size;
-        let max_file_size = self.env_vars.mappings.max_ipfs_file_bytes;
+        let max_file_size = self.max_file_size;
         restrict_file_size(&path, size, max_file_size)?;
 
         let req_path = path.clone();
         let timeout = self.timeout;
-        let data = retry_policy(self.retry, "ipfs.cat", logger)
+        let data = retry_policy(self.retry, self.max_retries, "ipfs.cat", logger)
             .run(move || {
                 let path = req_path.clone();
                 let client = client.clone();
@@ -197,17 +318,9 @@ impl LinkResolverTrait for LinkResolver {
         // The size reported by `files/stat` is not guaranteed to be exact, so check the limit again.
         restrict_file_size(&path, data.len() as u64, max_file_size)?;
 
-        // Only cache files if they are not too large
-        if data.len() <= max_cache_file_size {
-            let mut cache = self.cache.lock().unwrap();
-            if !cache.contains_key(&path) {
-                cache.insert(path.clone(), data.clone());
-            }
-        } else {
-            debug!(logger, "File too large for cache";
-                        "path" => path,
-                        "size" => data.len()
-            );
+        self.cache_in_memory(logger, &path, &data);
+        if let Some(store_cache) = &self.store_cache {
+            store_cache.insert(path, data.clone()).await;
         }
 
         Ok(data)
@@ -216,20 +329,20 @@ impl LinkResolverTrait for LinkResolver {
     async fn get_block(&self, logger: &Logger, link: &Link) -> Result<Vec<u8>, Error> {
         trace!(logger, "IPFS block get"; "hash" => &link.link);
         let (size, client) = select_fastest_client_with_stat(
-            self.clients.cheap_clone(),
+            self.healthy_clients(),
             logger.cheap_clone(),
             StatApi::Block,
             link.link.clone(),
             self.timeout,
             self.retry,
+            self.max_retries,
         )
         .await?;
 
-        let max_file_size = self.env_vars.mappings.max_ipfs_file_bytes;
-        restrict_file_size(&link.link, size, max_file_size)?;
+        restrict_file_size(&link.link, size, self.max_file_size)?;
 
         let link = link.link.clone();
-        let data = retry_policy(self.retry, "ipfs.getBlock", logger)
+        let data = retry_policy(self.retry, self.max_retries, "ipfs.getBlock", logger)
             .run(move || {
                 let link = link.clone();
                 let client = client.clone();
@@ -248,12 +361,13 @@ impl LinkResolverTrait for LinkResolver {
         let path = link.link.trim_start_matches("/ipfs/");
 
         let (size, client) = select_fastest_client_with_stat(
-            self.clients.cheap_clone(),
+            self.healthy_clients(),
             logger.cheap_clone(),
             StatApi::Files,
             path.to_string(),
             self.timeout,
             self.retry,
+            self.max_retries,
         )
         .await?;
 
@@ -353,6 +467,43 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn with_max_file_size() {
+        let file: &[u8] = &[0u8; 201];
+        let client = IpfsClient::localhost();
+        let resolver = super::LinkResolver::new(vec![client.clone()], Default::default());
+        let limited = resolver.with_max_file_size(200);
+
+        let logger = Logger::root(slog::Discard, o!());
+
+        let link = Link {
+            link: client.add(file.into()).await.unwrap().hash,
+        };
+        assert_eq!(
+            file.to_vec(),
+            LinkResolver::cat(&resolver, &logger, &link).await.unwrap()
+        );
+        let err = limited.cat(&logger, &link).await.unwrap_err();
+        assert!(err.to_string().contains("It can be at most 200 bytes"));
+    }
+
+    #[tokio::test]
+    async fn with_max_retries() {
+        // Nothing listens on this port, so every attempt fails with a
+        // networking error that is retried
+        let client = IpfsClient::new("http://127.0.0.1:1").unwrap();
+        let resolver = super::LinkResolver::new(vec![client], Default::default())
+            .with_max_retries(1)
+            .with_timeout(Duration::from_secs(1));
+
+        let logger = Logger::root(slog::Discard, o!());
+        let link = Link {
+            link: "QmTkzDwWqPbnAh5YiV5VwcTLnGdwSNsNTn2aDxdXBFca7D".to_owned(),
+        };
+        let res = tokio::time::timeout(Duration::from_secs(30), resolver.cat(&logger, &link)).await;
+        assert!(res.expect("retries are limited").is_err());
+    }
+
     async fn json_round_trip(text: &'static str, env_vars: EnvVars) -> Result<Vec<Value>, Error> {
         let client = IpfsClient::localhost();
         let resolver = super::LinkResolver::new(vec![client.clone()], Arc::new(env_vars));
@@ -403,4 +554,46 @@ mod tests {
             values.unwrap()
         );
     }
+
+    #[tokio::test]
+    async fn local_subgraph_files() {
+        let dir = std::env::temp_dir().join(format!("local-subgraph-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let schema = dir.join("schema.graphql");
+        let other = dir.join("other.txt");
+        std::fs::write(&schema, "type Thing @entity { id: ID! }").unwrap();
+        std::fs::write(&other, "secret").unwrap();
+
+        let resolver = super::LinkResolver::new(vec![], Arc::new(EnvVars::default()));
+        let hash = DeploymentHash::new("local1234").unwrap();
+        resolver.add_local_subgraph(&hash, b"manifest".to_vec(), vec![schema.clone()]);
+
+        let logger = Logger::root(slog::Discard, o!());
+        assert_eq!(
+            b"manifest".to_vec(),
+            LinkResolver::cat(&resolver, &logger, &hash.to_ipfs_link())
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            b"type Thing @entity { id: ID! }".to_vec(),
+            LinkResolver::cat(
+                &resolver,
+                &logger,
+                &Link::from(format!("file://{}", schema.display()))
+            )
+            .await
+            .unwrap()
+        );
+        // Files that no local subgraph references can not be read
+        assert!(LinkResolver::cat(
+            &resolver,
+            &logger,
+            &Link::from(format!("file://{}", other.display()))
+        )
+        .await
+        .is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,332 @@
+use anyhow::{anyhow, Error};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use graph::{
+    data_source::offchain::ArweaveTxId,
+    prelude::{reqwest, serde_json, CheapClone},
+    url::Url,
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::Duration;
+use tower::{buffer::Buffer, ServiceBuilder, ServiceExt};
+
+const CLOUDFLARE_TIMEOUT: u16 = 524;
+const GATEWAY_TIMEOUT: u16 = 504;
+
+/// The size of the chunks that Arweave splits transaction data into
+const MAX_CHUNK_SIZE: usize = 256 * 1024;
+/// The minimum size of the last chunk of transaction data
+const MIN_CHUNK_SIZE: usize = 32 * 1024;
+/// How large a transaction header can be in addition to the data that
+/// headers of format 1 transactions include
+const MAX_HEADER_SIZE: u64 = 1024 * 1024;
+
+pub type ArweaveService = Buffer<ArweaveTxId, BoxFuture<'static, Result<Option<Bytes>, Error>>>;
+
+pub fn arweave_service(
+    client: reqwest::Client,
+    gateways: Vec<Url>,
+    max_file_size: u64,
+    timeout: Duration,
+    concurrency_and_rate_limit: u16,
+) -> ArweaveService {
+    let arweave = ArweaveServiceInner {
+        client,
+        gateways: Arc::new(gateways),
+        max_file_size,
+        timeout,
+    };
+
+    let svc = ServiceBuilder::new()
+        .rate_limit(concurrency_and_rate_limit.into(), Duration::from_secs(1))
+        .concurrency_limit(concurrency_and_rate_limit as usize)
+        .service_fn(move |req| arweave.cheap_clone().call_inner(req))
+        .boxed();
+
+    // The `Buffer` makes it so the rate and concurrency limit are shared among clones.
+    Buffer::new(svc, 1)
+}
+
+/// Parse the gateway URLs from `GRAPH_ARWEAVE_GATEWAYS`. Transactions are
+/// resolved relative to the gateway URL, which therefore needs to end in
+/// a `/`
+pub fn arweave_gateways(gateways: &[String]) -> Result<Vec<Url>, Error> {
+    gateways
+        .iter()
+        .map(|gateway| {
+            let gateway = if gateway.ends_with('/') {
+                gateway.to_string()
+            } else {
+                format!("{}/", gateway)
+            };
+            Url::parse(&gateway).map_err(|e| anyhow!("invalid Arweave gateway {}: {}", gateway, e))
+        })
+        .collect()
+}
+
+#[derive(Clone)]
+struct ArweaveServiceInner {
+    client: reqwest::Client,
+    gateways: Arc<Vec<Url>>,
+    max_file_size: u64,
+    timeout: Duration,
+}
+
+impl CheapClone for ArweaveServiceInner {
+    fn cheap_clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            gateways: self.gateways.cheap_clone(),
+            max_file_size: self.max_file_size,
+            timeout: self.timeout,
+        }
+    }
+}
+
+/// The fields of a transaction header that are needed to verify its data
+#[derive(Deserialize)]
+struct TxHeader {
+    data_root: String,
+    data_size: String,
+}
+
+impl ArweaveServiceInner {
+    /// Try the gateways in order until one of them has the data for `req`.
+    /// Returns `None` if no gateway has the data yet
+    async fn call_inner(self, req: ArweaveTxId) -> Result<Option<Bytes>, Error> {
+        let mut error = None;
+        for gateway in self.gateways.iter() {
+            match self.fetch(gateway, &req).await {
+                Ok(Some(data)) => return Ok(Some(data)),
+                Ok(None) => {}
+                Err(e) => error = Some(e),
+            }
+        }
+        match error {
+            Some(e) => Err(e),
+            None => Ok(None),
+        }
+    }
+
+    async fn fetch(&self, gateway: &Url, id: &ArweaveTxId) -> Result<Option<Bytes>, Error> {
+        // The data in headers is base64url encoded
+        let max_header_size = self
+            .max_file_size
+            .saturating_mul(2)
+            .saturating_add(MAX_HEADER_SIZE);
+        let header = match self
+            .get(gateway.join(&format!("tx/{}", id))?, max_header_size)
+            .await?
+        {
+            Some(header) => serde_json::from_slice::<TxHeader>(&header)?,
+            None => return Ok(None),
+        };
+        let size: u64 = header.data_size.parse()?;
+        if size > self.max_file_size {
+            return Err(anyhow!(
+                "Arweave file {} is too large. It can be at most {} bytes but is {} bytes",
+                id,
+                self.max_file_size,
+                size
+            ));
+        }
+        if size == 0 {
+            return Ok(Some(Bytes::new()));
+        }
+
+        let data = match self.get(gateway.join(id.as_str())?, size).await? {
+            Some(data) => data,
+            None => return Ok(None),
+        };
+        if data.len() as u64 != size {
+            return Err(anyhow!(
+                "Arweave gateway {} returned {} bytes for {}, but the transaction has {} bytes",
+                gateway,
+                data.len(),
+                id,
+                size
+            ));
+        }
+        let data_root = base64_url::encode(&data_root(&data));
+        if data_root != header.data_root {
+            return Err(anyhow!(
+                "Arweave gateway {} returned data for {} with data root {}, expected {}",
+                gateway,
+                id,
+                data_root,
+                header.data_root
+            ));
+        }
+        Ok(Some(data))
+    }
+
+    /// Get `url`, returning `None` if the gateway does not have it (yet).
+    /// Fails as soon as the response is larger than `max_size` bytes
+    async fn get(&self, url: Url, max_size: u64) -> Result<Option<Bytes>, Error> {
+        let res = match self
+            .client
+            .get(url.clone())
+            .timeout(self.timeout)
+            .send()
+            .await
+        {
+            Ok(res) => res,
+            Err(e) if e.is_timeout() => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let mut res = match res.status().as_u16() {
+            // Pending transactions are reported with a 202
+            202 | 404 | GATEWAY_TIMEOUT | CLOUDFLARE_TIMEOUT => return Ok(None),
+            _ => res.error_for_status()?,
+        };
+
+        let mut data = Vec::new();
+        while let Some(chunk) = res.chunk().await? {
+            if (data.len() + chunk.len()) as u64 > max_size {
+                return Err(anyhow!(
+                    "Arweave gateway response for {} is larger than the limit of {} bytes",
+                    url,
+                    max_size
+                ));
+            }
+            data.extend_from_slice(&chunk);
+        }
+        Ok(Some(Bytes::from(data)))
+    }
+}
+
+fn sha256(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+/// The 32 byte big-endian encoding of an offset
+fn note(offset: usize) -> [u8; 32] {
+    let mut note = [0u8; 32];
+    note[24..].copy_from_slice(&(offset as u64).to_be_bytes());
+    note
+}
+
+/// The byte ranges of the chunks Arweave splits data of length `len` into.
+/// Chunks are `MAX_CHUNK_SIZE` bytes, except that the last two chunks are
+/// balanced if the last chunk would be smaller than `MIN_CHUNK_SIZE`. Like
+/// the reference implementation, this ends in an empty chunk when the data
+/// is a multiple of `MAX_CHUNK_SIZE`
+fn chunk_ranges(len: usize) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut cursor = 0;
+    while len - cursor >= MAX_CHUNK_SIZE {
+        let rest = len - cursor;
+        let next = rest - MAX_CHUNK_SIZE;
+        let size = if next > 0 && next < MIN_CHUNK_SIZE {
+            (rest + 1) / 2
+        } else {
+            MAX_CHUNK_SIZE
+        };
+        ranges.push((cursor, cursor + size));
+        cursor += size;
+    }
+    ranges.push((cursor, len));
+    ranges
+}
+
+/// Compute the root of the Merkle tree over the chunks of `data` that
+/// Arweave transactions commit to in their `data_root`
+fn data_root(data: &[u8]) -> [u8; 32] {
+    // Nodes are pairs of their id and the end of the byte range they cover
+    let mut nodes: Vec<([u8; 32], usize)> = chunk_ranges(data.len())
+        .into_iter()
+        .map(|(start, end)| {
+            let data_hash = sha256(&[&data[start..end]]);
+            let id = sha256(&[&sha256(&[&data_hash]), &sha256(&[&note(end)])]);
+            (id, end)
+        })
+        .collect();
+
+    while nodes.len() > 1 {
+        nodes = nodes
+            .chunks(2)
+            .map(|pair| match pair {
+                [(left, left_end), (right, right_end)] => {
+                    let id = sha256(&[
+                        &sha256(&[left]),
+                        &sha256(&[right]),
+                        &sha256(&[&note(*left_end)]),
+                    ]);
+                    (id, *right_end)
+                }
+                [node] => *node,
+                _ => unreachable!("chunks(2) returns one or two nodes"),
+            })
+            .collect();
+    }
+    nodes[0].0
+}
+
+#[cfg(test)]
+mod test {
+    use super::{chunk_ranges, data_root, note, sha256, MAX_CHUNK_SIZE, MIN_CHUNK_SIZE};
+
+    #[test]
+    fn chunking() {
+        assert_eq!(vec![(0, 0)], chunk_ranges(0));
+        assert_eq!(vec![(0, 10)], chunk_ranges(10));
+        assert_eq!(
+            vec![(0, MAX_CHUNK_SIZE), (MAX_CHUNK_SIZE, MAX_CHUNK_SIZE)],
+            chunk_ranges(MAX_CHUNK_SIZE)
+        );
+        assert_eq!(
+            vec![
+                (0, MAX_CHUNK_SIZE),
+                (MAX_CHUNK_SIZE, MAX_CHUNK_SIZE + MIN_CHUNK_SIZE)
+            ],
+            chunk_ranges(MAX_CHUNK_SIZE + MIN_CHUNK_SIZE)
+        );
+
+        // A last chunk smaller than MIN_CHUNK_SIZE is balanced with the one before it
+        let len = MAX_CHUNK_SIZE + 11;
+        let half = (len + 1) / 2;
+        assert_eq!(vec![(0, half), (half, len)], chunk_ranges(len));
+
+        let len = 2 * MAX_CHUNK_SIZE + 11;
+        let half = (MAX_CHUNK_SIZE + 11 + 1) / 2;
+        assert_eq!(
+            vec![
+                (0, MAX_CHUNK_SIZE),
+                (MAX_CHUNK_SIZE, MAX_CHUNK_SIZE + half),
+                (MAX_CHUNK_SIZE + half, len)
+            ],
+            chunk_ranges(len)
+        );
+    }
+
+    #[test]
+    fn data_roots() {
+        // A single chunk is a leaf
+        let data = b"hello arweave";
+        let leaf = sha256(&[&sha256(&[&sha256(&[data])]), &sha256(&[&note(data.len())])]);
+        assert_eq!(leaf, data_root(data));
+
+        // Two chunks are combined into a branch
+        let data = vec![7u8; MAX_CHUNK_SIZE + MIN_CHUNK_SIZE];
+        let leaf = |start: usize, end: usize| {
+            sha256(&[
+                &sha256(&[&sha256(&[&data[start..end]])]),
+                &sha256(&[&note(end)]),
+            ])
+        };
+        let left = leaf(0, MAX_CHUNK_SIZE);
+        let right = leaf(MAX_CHUNK_SIZE, data.len());
+        let branch = sha256(&[
+            &sha256(&[&left]),
+            &sha256(&[&right]),
+            &sha256(&[&note(MAX_CHUNK_SIZE)]),
+        ]);
+        assert_eq!(branch, data_root(&data));
+    }
+}
@@ -1,28 +1,37 @@
 use anyhow::{anyhow, Error};
 use bytes::Bytes;
 use futures::future::BoxFuture;
+use futures::stream::{FuturesUnordered, StreamExt};
 use graph::{
-    ipfs_client::{CidFile, IpfsClient, StatApi},
+    components::link_resolver::IpfsCache,
+    ipfs_client::{CidFile, IpfsClient, StatApi, CLOUDFLARE_TIMEOUT, GATEWAY_TIMEOUT},
     prelude::CheapClone,
 };
+use std::sync::Arc;
 use std::time::Duration;
 use tower::{buffer::Buffer, ServiceBuilder, ServiceExt};
 
-const CLOUDFLARE_TIMEOUT: u16 = 524;
-const GATEWAY_TIMEOUT: u16 = 504;
-
 pub type IpfsService = Buffer<CidFile, BoxFuture<'static, Result<Option<Bytes>, Error>>>;
 
+/// A service that fetches files from `clients`. Every request goes to
+/// all the healthy clients, and the file is fetched from the first one
+/// that has it. Clients that could not be reached are passed over for
+/// `unhealthy_backoff`. Files are looked up in `store_cache` before they
+/// are fetched, and added to it after they were fetched
 pub fn ipfs_service(
-    client: IpfsClient,
+    clients: Vec<IpfsClient>,
+    store_cache: Option<Arc<dyn IpfsCache>>,
     max_file_size: u64,
     timeout: Duration,
+    unhealthy_backoff: Duration,
     concurrency_and_rate_limit: u16,
 ) -> IpfsService {
     let ipfs = IpfsServiceInner {
-        client,
+        clients: Arc::new(clients.into_iter().map(Arc::new).collect()),
+        store_cache,
         max_file_size,
         timeout,
+        unhealthy_backoff,
     };
 
     let svc = ServiceBuilder::new()
@@ -37,17 +46,21 @@ pub fn ipfs_service(
 
 #[derive(Clone)]
 struct IpfsServiceInner {
-    client: IpfsClient,
+    clients: Arc<Vec<Arc<IpfsClient>>>,
+    store_cache: Option<Arc<dyn IpfsCache>>,
     max_file_size: u64,
     timeout: Duration,
+    unhealthy_backoff: Duration,
 }
 
 impl CheapClone for IpfsServiceInner {
     fn cheap_clone(&self) -> Self {
         Self {
-            client: self.client.cheap_clone(),
+            clients: self.clients.cheap_clone(),
+            store_cache: self.store_cache.clone(),
             max_file_size: self.max_file_size,
             timeout: self.timeout,
+            unhealthy_backoff: self.unhealthy_backoff,
         }
     }
 }
@@ -65,17 +78,54 @@ impl IpfsServiceInner {
             None => cid.to_string(),
         };
 
-        let size = match self
-            .client
-            .stat_size(StatApi::Files, cid_str.clone(), self.timeout)
-            .await
-        {
-            Ok(size) => size,
-            Err(e) => match e.status().map(|e| e.as_u16()) {
-                Some(GATEWAY_TIMEOUT) | Some(CLOUDFLARE_TIMEOUT) => return Ok(None),
-                _ if e.is_timeout() => return Ok(None),
-                _ => return Err(e.into()),
-            },
+        if let Some(store_cache) = &self.store_cache {
+            if let Some(data) = store_cache.get(&cid_str).await {
+                if data.len() as u64 <= self.max_file_size {
+                    return Ok(Some(Bytes::from(data)));
+                }
+            }
+        }
+
+        let timeout = self.timeout;
+        let clients = IpfsClient::healthy(&self.clients, self.unhealthy_backoff);
+        let mut stats: FuturesUnordered<_> = clients
+            .iter()
+            .map(|client| {
+                let cid_str = cid_str.clone();
+                async move {
+                    client
+                        .stat_size(StatApi::Files, cid_str, timeout)
+                        .await
+                        .map(|size| (size, client))
+                }
+            })
+            .collect();
+
+        // The file is only reported as not found if at least one client
+        // timed out looking for it; if all clients failed, the request is
+        // retried later
+        let mut not_found = false;
+        let mut err = None;
+        let mut found = None;
+        while let Some(res) = stats.next().await {
+            match res {
+                Ok(size_and_client) => {
+                    found = Some(size_and_client);
+                    break;
+                }
+                Err(e) => match e.status().map(|e| e.as_u16()) {
+                    Some(GATEWAY_TIMEOUT) | Some(CLOUDFLARE_TIMEOUT) => not_found = true,
+                    _ if e.is_timeout() => not_found = true,
+                    _ => err = Some(e),
+                },
+            }
+        }
+
+        let (size, client) = match (found, err) {
+            (Some(size_and_client), _) => size_and_client,
+            (None, _) if not_found => return Ok(None),
+            (None, Some(e)) => return Err(e.into()),
+            (None, None) => return Err(anyhow!("no IPFS clients to fetch {} from", cid_str)),
         };
 
         if size > self.max_file_size {
@@ -87,11 +137,11 @@ impl IpfsServiceInner {
             ));
         }
 
-        Ok(self
-            .client
-            .cat_all(&cid_str, self.timeout)
-            .await
-            .map(Some)?)
+        let data = client.cat_all(&cid_str, self.timeout).await?;
+        if let Some(store_cache) = &self.store_cache {
+            store_cache.insert(cid_str, data.to_vec()).await;
+        }
+        Ok(Some(data))
     }
 }
 
@@ -144,7 +194,14 @@ mod test {
         let cid = Cid::from_str(&ipfs_folder.hash).unwrap();
         let file = "random.txt".to_string();
 
-        let svc = super::ipfs_service(local, 100000, Duration::from_secs(5), 10);
+        let svc = super::ipfs_service(
+            vec![local],
+            None,
+            100000,
+            Duration::from_secs(5),
+            Duration::from_secs(30),
+            10,
+        );
 
         let content = svc
             .oneshot(super::CidFile {
@@ -1,6 +1,8 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use graph::{
+    components::subgraph::{OffchainFiles, OFFCHAIN_STATUS},
     prelude::{DeploymentHash, MetricsRegistry},
     prometheus::{Counter, Gauge},
 };
@@ -10,43 +12,59 @@ pub struct PollingMonitorMetrics {
     pub errors: Counter,
     pub not_found: Counter,
     pub queue_depth: Gauge,
+    /// The state of the files of the deployment, reported through the
+    /// index node API
+    pub files: Arc<OffchainFiles>,
 }
 
 impl PollingMonitorMetrics {
-    pub fn new(registry: Arc<MetricsRegistry>, subgraph_hash: &DeploymentHash) -> Self {
+    /// Metrics for polling `service`, e.g., `ipfs`, on behalf of the
+    /// deployment `subgraph_hash`
+    pub fn new(
+        registry: Arc<MetricsRegistry>,
+        subgraph_hash: &DeploymentHash,
+        service: &str,
+    ) -> Self {
+        let labels = || -> HashMap<String, String> {
+            HashMap::from([
+                ("deployment".to_string(), subgraph_hash.to_string()),
+                ("service".to_string(), service.to_string()),
+            ])
+        };
         let requests = registry
-            .new_deployment_counter(
+            .new_counter_with_labels(
                 "polling_monitor_requests",
                 "counts the total requests made to the service being polled",
-                subgraph_hash.as_str(),
+                labels(),
             )
             .unwrap();
         let not_found = registry
-            .new_deployment_counter(
+            .new_counter_with_labels(
                 "polling_monitor_not_found",
                 "counts 'not found' responses returned from the service being polled",
-                subgraph_hash.as_str(),
+                labels(),
             )
             .unwrap();
         let errors = registry
-            .new_deployment_counter(
+            .new_counter_with_labels(
                 "polling_monitor_errors",
                 "counts errors returned from the service being polled",
-                subgraph_hash.as_str(),
+                labels(),
             )
             .unwrap();
         let queue_depth = registry
-            .new_deployment_gauge(
+            .new_gauge(
                 "polling_monitor_queue_depth",
                 "size of the queue of polling requests",
-                subgraph_hash.as_str(),
+                labels(),
             )
             .unwrap();
         Self {
-            requests,
-            errors,
-            not_found,
-            queue_depth,
+            requests: *requests,
+            errors: *errors,
+            not_found: *not_found,
+            queue_depth: *queue_depth,
+            files: OFFCHAIN_STATUS.deployment(subgraph_hash.as_str()),
         }
     }
 
@@ -57,6 +75,7 @@ impl PollingMonitorMetrics {
             errors: Counter::new("y", " ").unwrap(),
             not_found: Counter::new("z", " ").unwrap(),
             queue_depth: Gauge::new("w", " ").unwrap(),
+            files: Arc::new(OffchainFiles::default()),
         }
     }
 }
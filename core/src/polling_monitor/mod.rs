@@ -1,5 +1,7 @@
+mod arweave_service;
 mod ipfs_service;
 mod metrics;
+mod object_store_service;
 
 use std::collections::HashMap;
 use std::fmt::Display;
@@ -12,6 +14,7 @@ use futures::future::BoxFuture;
 use futures::stream::StreamExt;
 use futures::{stream, Future, FutureExt, TryFutureExt};
 use graph::cheap_clone::CheapClone;
+use graph::components::subgraph::OffchainFiles;
 use graph::parking_lot::Mutex;
 use graph::prelude::tokio;
 use graph::prometheus::{Counter, Gauge};
@@ -23,7 +26,9 @@ use tower::util::rng::HasherRng;
 use tower::{Service, ServiceExt};
 
 pub use self::metrics::PollingMonitorMetrics;
+pub use arweave_service::{arweave_gateways, arweave_service, ArweaveService};
 pub use ipfs_service::{ipfs_service, IpfsService};
+pub use object_store_service::{object_store_service, ObjectStoreEndpoints, ObjectStoreService};
 
 const MIN_BACKOFF: Duration = Duration::from_secs(5);
 
@@ -110,6 +115,7 @@ where
 {
     let service = ReturnRequest { service };
     let (queue, queue_woken) = Queue::new(metrics.queue_depth.clone(), metrics.requests.clone());
+    let files = metrics.files.cheap_clone();
 
     let cancel_check = response_sender.clone();
     let queue_to_stream = {
@@ -162,6 +168,7 @@ where
                     // Object not found, push the id to the back of the queue.
                     Ok((id, None)) => {
                         metrics.not_found.inc();
+                        metrics.files.not_found(&id.to_string());
                         queue.push_back(id);
                     }
 
@@ -171,6 +178,7 @@ where
                                     "error" => format!("{:#}", e),
                                     "object_id" => id.to_string());
                         metrics.errors.inc();
+                        metrics.files.failed(&id.to_string());
 
                         // Requests that return errors could mean there is a permanent issue with
                         // fetching the given item, or could signal the endpoint is overloaded.
@@ -187,20 +195,27 @@ where
         });
     }
 
-    PollingMonitor { queue }
+    PollingMonitor { queue, files }
 }
 
 /// Handle for adding objects to be monitored.
 pub struct PollingMonitor<ID> {
     queue: Arc<Queue<ID>>,
+    files: Arc<OffchainFiles>,
 }
 
-impl<ID> PollingMonitor<ID> {
+impl<ID: Display> PollingMonitor<ID> {
     /// Add an object id to the polling queue. New requests have priority and are pushed to the
     /// front of the queue.
     pub fn monitor(&self, id: ID) {
+        self.files.monitor(id.to_string());
         self.queue.push_front(id);
     }
+
+    /// Record that the object `id` was received with `bytes` of data
+    pub fn fetched(&self, id: &ID, bytes: usize) {
+        self.files.fetched(&id.to_string(), bytes as u64);
+    }
 }
 
 struct ReturnRequest<S> {
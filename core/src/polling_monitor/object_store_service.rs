@@ -0,0 +1,292 @@
+use anyhow::{anyhow, Error};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use graph::{
+    data_source::offchain::{ObjectStore, ObjectStoreFile},
+    prelude::{chrono::Utc, reqwest, tiny_keccak, CheapClone},
+    url::Url,
+    util::hmac::hmac_sha256,
+};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::Duration;
+use tower::{buffer::Buffer, ServiceBuilder, ServiceExt};
+
+/// The credentials are read from the environment variables that AWS and
+/// Google tools use when the service is created, rather than kept in
+/// `EnvVars`, so that they do not show up when the settings are logged
+const AWS_ACCESS_KEY_ID: &str = "AWS_ACCESS_KEY_ID";
+const AWS_SECRET_ACCESS_KEY: &str = "AWS_SECRET_ACCESS_KEY";
+const AWS_SESSION_TOKEN: &str = "AWS_SESSION_TOKEN";
+/// An OAuth access token for Google Cloud Storage
+const GCS_ACCESS_TOKEN: &str = "GRAPH_OBJECT_STORE_GCS_TOKEN";
+
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+pub type ObjectStoreService =
+    Buffer<ObjectStoreFile, BoxFuture<'static, Result<Option<Bytes>, Error>>>;
+
+/// The endpoints of the object stores, which can point at stores that are
+/// compatible with S3 or GCS
+pub struct ObjectStoreEndpoints {
+    pub s3: Url,
+    pub s3_region: String,
+    pub gcs: Url,
+}
+
+impl ObjectStoreEndpoints {
+    pub fn new(s3: &str, s3_region: &str, gcs: &str) -> Result<Self, Error> {
+        let parse = |endpoint: &str| {
+            Url::parse(endpoint)
+                .map_err(|e| anyhow!("invalid object store endpoint {}: {}", endpoint, e))
+        };
+        Ok(ObjectStoreEndpoints {
+            s3: parse(s3)?,
+            s3_region: s3_region.to_string(),
+            gcs: parse(gcs)?,
+        })
+    }
+}
+
+pub fn object_store_service(
+    client: reqwest::Client,
+    endpoints: ObjectStoreEndpoints,
+    max_file_size: u64,
+    timeout: Duration,
+    concurrency_and_rate_limit: u16,
+) -> ObjectStoreService {
+    let aws = match (
+        std::env::var(AWS_ACCESS_KEY_ID),
+        std::env::var(AWS_SECRET_ACCESS_KEY),
+    ) {
+        (Ok(access_key_id), Ok(secret_access_key)) => Some(AwsCredentials {
+            access_key_id,
+            secret_access_key,
+            session_token: std::env::var(AWS_SESSION_TOKEN).ok(),
+        }),
+        _ => None,
+    };
+    let store = ObjectStoreServiceInner {
+        client,
+        endpoints: Arc::new(endpoints),
+        aws: Arc::new(aws),
+        gcs_token: Arc::new(std::env::var(GCS_ACCESS_TOKEN).ok()),
+        max_file_size,
+        timeout,
+    };
+
+    let svc = ServiceBuilder::new()
+        .rate_limit(concurrency_and_rate_limit.into(), Duration::from_secs(1))
+        .concurrency_limit(concurrency_and_rate_limit as usize)
+        .service_fn(move |req| store.cheap_clone().call_inner(req))
+        .boxed();
+
+    // The `Buffer` makes it so the rate and concurrency limit are shared among clones.
+    Buffer::new(svc, 1)
+}
+
+struct AwsCredentials {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+}
+
+#[derive(Clone)]
+struct ObjectStoreServiceInner {
+    client: reqwest::Client,
+    endpoints: Arc<ObjectStoreEndpoints>,
+    /// Requests to S3 are not signed if there are no credentials, which
+    /// works for public buckets
+    aws: Arc<Option<AwsCredentials>>,
+    gcs_token: Arc<Option<String>>,
+    max_file_size: u64,
+    timeout: Duration,
+}
+
+impl CheapClone for ObjectStoreServiceInner {
+    fn cheap_clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            endpoints: self.endpoints.cheap_clone(),
+            aws: self.aws.cheap_clone(),
+            gcs_token: self.gcs_token.cheap_clone(),
+            max_file_size: self.max_file_size,
+            timeout: self.timeout,
+        }
+    }
+}
+
+impl ObjectStoreServiceInner {
+    /// Fetch the object `req` and check that its content has the hash that
+    /// the source declares. Returns `None` if the object does not exist yet
+    async fn call_inner(self, req: ObjectStoreFile) -> Result<Option<Bytes>, Error> {
+        let endpoint = match req.store {
+            ObjectStore::S3 => &self.endpoints.s3,
+            ObjectStore::Gcs => &self.endpoints.gcs,
+        };
+        let path = format!("/{}/{}", uri_encode(&req.bucket), uri_encode(&req.key));
+        let url = endpoint.join(&path)?;
+
+        let mut request = self.client.get(url.clone()).timeout(self.timeout);
+        match (req.store, &*self.aws, &*self.gcs_token) {
+            (ObjectStore::S3, Some(aws), _) => {
+                for (name, value) in sign_s3_get(&url, &self.endpoints.s3_region, aws) {
+                    request = request.header(name, value);
+                }
+            }
+            (ObjectStore::Gcs, _, Some(token)) => request = request.bearer_auth(token),
+            _ => {}
+        }
+
+        let res = match request.send().await {
+            Ok(res) => res,
+            Err(e) if e.is_timeout() => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        if res.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let res = res.error_for_status()?;
+        let too_large = |size: u64| {
+            anyhow!(
+                "object {} is too large. It can be at most {} bytes but is {} bytes",
+                req.url(),
+                self.max_file_size,
+                size
+            )
+        };
+        if let Some(size) = res.content_length() {
+            if size > self.max_file_size {
+                return Err(too_large(size));
+            }
+        }
+        let data = res.bytes().await?;
+        if data.len() as u64 > self.max_file_size {
+            return Err(too_large(data.len() as u64));
+        }
+
+        let hash = tiny_keccak::keccak256(&data);
+        if hash != req.hash {
+            return Err(anyhow!(
+                "the content of object {} has hash 0x{}, expected 0x{}",
+                req.url(),
+                hex::encode(hash),
+                hex::encode(req.hash)
+            ));
+        }
+        Ok(Some(data))
+    }
+}
+
+/// Percent-encode `s` the way AWS signatures expect, leaving only
+/// unreserved characters and `/` as they are
+fn uri_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(b as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    encoded
+}
+
+/// The key that AWS Signature Version 4 signs requests to `service` in
+/// `region` on `date` with
+fn signing_key(secret_access_key: &str, date: &str, region: &str, service: &str) -> [u8; 32] {
+    let key = hmac_sha256(
+        format!("AWS4{}", secret_access_key).as_bytes(),
+        date.as_bytes(),
+    );
+    let key = hmac_sha256(&key, region.as_bytes());
+    let key = hmac_sha256(&key, service.as_bytes());
+    hmac_sha256(&key, b"aws4_request")
+}
+
+/// The headers that sign a `GET` of `url` with AWS Signature Version 4.
+/// The payload of a `GET` is empty, and is therefore not signed
+fn sign_s3_get(url: &Url, region: &str, aws: &AwsCredentials) -> Vec<(&'static str, String)> {
+    let now = Utc::now();
+    let timestamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+
+    // Headers have to be signed in alphabetical order
+    let mut headers = vec![
+        ("host", host),
+        ("x-amz-content-sha256", UNSIGNED_PAYLOAD.to_string()),
+        ("x-amz-date", timestamp.clone()),
+    ];
+    if let Some(token) = &aws.session_token {
+        headers.push(("x-amz-security-token", token.clone()));
+    }
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let canonical_request = format!(
+        "GET\n{}\n{}\n{}\n{}\n{}",
+        url.path(),
+        url.query().unwrap_or_default(),
+        canonical_headers,
+        signed_headers,
+        UNSIGNED_PAYLOAD
+    );
+    let scope = format!("{}/{}/s3/aws4_request", date, region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        timestamp,
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+    let key = signing_key(&aws.secret_access_key, &date, region, "s3");
+    let signature = hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()));
+
+    // `reqwest` sets the `host` header itself
+    headers.remove(0);
+    headers.push((
+        "authorization",
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            aws.access_key_id, scope, signed_headers, signature
+        ),
+    ));
+    headers
+}
+
+#[cfg(test)]
+mod test {
+    use super::{signing_key, uri_encode};
+
+    #[test]
+    fn aws_signing_key() {
+        // The example from the AWS documentation of Signature Version 4
+        assert_eq!(
+            "c4afb1cc5771d871763a393e44b703571b55cc28424d1a5e86da6ed3c154a4b9",
+            hex::encode(signing_key(
+                "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+                "20150830",
+                "us-east-1",
+                "iam"
+            ))
+        );
+    }
+
+    #[test]
+    fn uri_encoding() {
+        assert_eq!("path/to/file.json", uri_encode("path/to/file.json"));
+        assert_eq!("a%20b%2Bc%3D~", uri_encode("a b+c=~"));
+        assert_eq!("%C3%A9", uri_encode("é"));
+    }
+}
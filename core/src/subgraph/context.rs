@@ -1,6 +1,9 @@
 pub mod instance;
 
-use crate::polling_monitor::{spawn_monitor, IpfsService, PollingMonitor, PollingMonitorMetrics};
+use crate::polling_monitor::{
+    spawn_monitor, ArweaveService, IpfsService, ObjectStoreService, PollingMonitor,
+    PollingMonitorMetrics,
+};
 use anyhow::{self, Error};
 use bytes::Bytes;
 use graph::{
@@ -9,7 +12,11 @@ use graph::{
         store::{DeploymentId, SubgraphFork},
         subgraph::{MappingError, SharedProofOfIndexing},
     },
-    data_source::{offchain, CausalityRegion, DataSource, TriggerData},
+    data_source::{
+        offchain,
+        offchain::{ArweaveTxId, ObjectStoreFile},
+        CausalityRegion, DataSource, TriggerData,
+    },
     ipfs_client::CidFile,
     prelude::{
         BlockNumber, BlockState, CancelGuard, CheapClone, DeploymentHash, MetricsRegistry,
@@ -47,6 +54,19 @@ impl SubgraphKeepAlive {
         self.alive_map.write().unwrap().insert(deployment_id, guard);
         self.sg_metrics.running_count.inc();
     }
+
+    /// Cancel the block streams of all running subgraphs. Subgraphs stop
+    /// at the next block boundary; a block that is being processed when
+    /// the stream is canceled is not written
+    pub fn clear(&self) {
+        let count = {
+            let mut alive_map = self.alive_map.write().unwrap();
+            let count = alive_map.len();
+            alive_map.clear();
+            count
+        };
+        self.sg_metrics.running_count.sub(count as f64);
+    }
 }
 
 // The context keeps track of mutable in-memory state that is retained across blocks.
@@ -110,6 +130,14 @@ impl<C: Blockchain, T: RuntimeHostBuilder<C>> IndexingContext<C, T> {
         .await
     }
 
+    pub fn hosts(&self) -> &[Arc<T::Host>] {
+        self.instance.hosts()
+    }
+
+    pub fn dynamic_data_source_count(&self) -> usize {
+        self.instance.dynamic_data_source_count()
+    }
+
     pub async fn process_trigger_in_hosts(
         &self,
         logger: &Logger,
@@ -186,6 +214,10 @@ impl<C: Blockchain, T: RuntimeHostBuilder<C>> IndexingContext<C, T> {
 pub struct OffchainMonitor {
     ipfs_monitor: PollingMonitor<CidFile>,
     ipfs_monitor_rx: mpsc::Receiver<(CidFile, Bytes)>,
+    arweave_monitor: PollingMonitor<ArweaveTxId>,
+    arweave_monitor_rx: mpsc::Receiver<(ArweaveTxId, Bytes)>,
+    object_store_monitor: PollingMonitor<ObjectStoreFile>,
+    object_store_monitor_rx: mpsc::Receiver<(ObjectStoreFile, Bytes)>,
 }
 
 impl OffchainMonitor {
@@ -194,23 +226,45 @@ impl OffchainMonitor {
         registry: Arc<MetricsRegistry>,
         subgraph_hash: &DeploymentHash,
         ipfs_service: IpfsService,
+        arweave_service: ArweaveService,
+        object_store_service: ObjectStoreService,
     ) -> Self {
         let (ipfs_monitor_tx, ipfs_monitor_rx) = mpsc::channel(10);
         let ipfs_monitor = spawn_monitor(
             ipfs_service,
             ipfs_monitor_tx,
+            logger.cheap_clone(),
+            PollingMonitorMetrics::new(registry.cheap_clone(), subgraph_hash, "ipfs"),
+        );
+        let (arweave_monitor_tx, arweave_monitor_rx) = mpsc::channel(10);
+        let arweave_monitor = spawn_monitor(
+            arweave_service,
+            arweave_monitor_tx,
+            logger.cheap_clone(),
+            PollingMonitorMetrics::new(registry.cheap_clone(), subgraph_hash, "arweave"),
+        );
+        let (object_store_monitor_tx, object_store_monitor_rx) = mpsc::channel(10);
+        let object_store_monitor = spawn_monitor(
+            object_store_service,
+            object_store_monitor_tx,
             logger,
-            PollingMonitorMetrics::new(registry, subgraph_hash),
+            PollingMonitorMetrics::new(registry, subgraph_hash, "object_store"),
         );
         Self {
             ipfs_monitor,
             ipfs_monitor_rx,
+            arweave_monitor,
+            arweave_monitor_rx,
+            object_store_monitor,
+            object_store_monitor_rx,
         }
     }
 
     fn add_source(&mut self, source: offchain::Source) -> Result<(), Error> {
         match source {
             offchain::Source::Ipfs(cid_file) => self.ipfs_monitor.monitor(cid_file),
+            offchain::Source::Arweave(id) => self.arweave_monitor.monitor(id),
+            offchain::Source::ObjectStore(file) => self.object_store_monitor.monitor(file),
         };
         Ok(())
     }
@@ -221,16 +275,52 @@ impl OffchainMonitor {
         let mut triggers = vec![];
         loop {
             match self.ipfs_monitor_rx.try_recv() {
-                Ok((cid_file, data)) => triggers.push(offchain::TriggerData {
-                    source: offchain::Source::Ipfs(cid_file),
-                    data: Arc::new(data),
-                }),
+                Ok((cid_file, data)) => {
+                    self.ipfs_monitor.fetched(&cid_file, data.len());
+                    triggers.push(offchain::TriggerData {
+                        source: offchain::Source::Ipfs(cid_file),
+                        data: Arc::new(data),
+                        content_type: offchain::ContentType::Raw,
+                    })
+                }
                 Err(TryRecvError::Disconnected) => {
                     anyhow::bail!("ipfs monitor unexpectedly terminated")
                 }
                 Err(TryRecvError::Empty) => break,
             }
         }
+        loop {
+            match self.arweave_monitor_rx.try_recv() {
+                Ok((id, data)) => {
+                    self.arweave_monitor.fetched(&id, data.len());
+                    triggers.push(offchain::TriggerData {
+                        source: offchain::Source::Arweave(id),
+                        data: Arc::new(data),
+                        content_type: offchain::ContentType::Raw,
+                    })
+                }
+                Err(TryRecvError::Disconnected) => {
+                    anyhow::bail!("arweave monitor unexpectedly terminated")
+                }
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+        loop {
+            match self.object_store_monitor_rx.try_recv() {
+                Ok((file, data)) => {
+                    self.object_store_monitor.fetched(&file, data.len());
+                    triggers.push(offchain::TriggerData {
+                        source: offchain::Source::ObjectStore(file),
+                        data: Arc::new(data),
+                        content_type: offchain::ContentType::Raw,
+                    })
+                }
+                Err(TryRecvError::Disconnected) => {
+                    anyhow::bail!("object store monitor unexpectedly terminated")
+                }
+                Err(TryRecvError::Empty) => break,
+            }
+        }
         Ok(triggers)
     }
 }
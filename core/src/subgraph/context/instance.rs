@@ -1,6 +1,7 @@
 use futures01::sync::mpsc::Sender;
 use graph::{
     blockchain::Blockchain,
+    data::subgraph::HandlerLimits,
     data_source::{
         causality_region::CausalityRegionSeq, offchain, CausalityRegion, DataSource,
         DataSourceTemplate,
@@ -17,6 +18,7 @@ pub struct SubgraphInstance<C: Blockchain, T: RuntimeHostBuilder<C>> {
     host_builder: T,
     templates: Arc<Vec<DataSourceTemplate<C>>>,
     host_metrics: Arc<HostMetrics>,
+    handler_limits: HandlerLimits,
 
     /// Runtime hosts, one for each data source mapping.
     ///
@@ -48,6 +50,16 @@ where
         let subgraph_id = manifest.id.clone();
         let network = manifest.network_name();
         let templates = Arc::new(manifest.templates);
+        let handler_limits = manifest.handler_limits;
+        // Manifests that exceed the ceilings are rejected when they are
+        // deployed, but the ceilings might have been lowered since
+        let exceeded = handler_limits.exceeded_ceilings();
+        if !exceeded.is_empty() {
+            return Err(anyhow!(
+                "the handler limits of the subgraph exceed what this node allows: {}",
+                exceeded.join(", ")
+            ));
+        }
 
         let mut this = SubgraphInstance {
             host_builder,
@@ -57,6 +69,7 @@ where
             module_cache: HashMap::new(),
             templates,
             host_metrics,
+            handler_limits,
             causality_region_seq,
         };
 
@@ -106,6 +119,7 @@ where
                     logger,
                     self.subgraph_id.clone(),
                     self.host_metrics.cheap_clone(),
+                    &self.handler_limits,
                 )?;
                 self.module_cache.insert(module_hash, sender.clone());
                 sender
@@ -208,6 +222,15 @@ where
         &self.hosts
     }
 
+    /// The number of hosts for data sources that were created from
+    /// templates. Only those have a creation block
+    pub fn dynamic_data_source_count(&self) -> usize {
+        self.hosts
+            .iter()
+            .filter(|host| host.creation_block_number().is_some())
+            .count()
+    }
+
     pub(super) fn causality_region_next_value(&mut self) -> CausalityRegion {
         self.causality_region_seq.next_val()
     }
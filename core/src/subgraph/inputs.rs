@@ -1,11 +1,11 @@
 use graph::{
     blockchain::{Blockchain, TriggersAdapter},
     components::{
-        store::{DeploymentLocator, SubgraphFork, WritableStore},
-        subgraph::ProofOfIndexingVersion,
+        store::{DeploymentLocator, RetryPolicy, SubgraphFork, SubgraphStore, WritableStore},
+        subgraph::{Notifier, ProofOfIndexingVersion},
     },
     data::subgraph::{SubgraphFeature, UnifiedMappingApiVersion},
-    data_source::DataSourceTemplate,
+    data_source::{subgraph, DataSourceTemplate},
     prelude::BlockNumber,
 };
 use std::collections::BTreeSet;
@@ -24,12 +24,42 @@ pub struct IndexingInputs<C: Blockchain> {
     pub unified_api_version: UnifiedMappingApiVersion,
     pub static_filters: bool,
     pub poi_version: ProofOfIndexingVersion,
+    /// The version of the POI that the deployment computes in addition to
+    /// `poi_version` during a transition
+    pub poi_transition_version: Option<ProofOfIndexingVersion>,
     pub network: String,
 
+    /// The deployments that subgraph data sources read entity changes
+    /// from, and the store through which they are read
+    pub subgraph_sources: Vec<subgraph::Source>,
+    pub subgraph_store: Arc<dyn SubgraphStore>,
+
     // Correspondence between data source or template position in the manifest and name.
     pub manifest_idx_and_name: Vec<(u32, String)>,
 
     /// Whether to instrument trigger processing and log additional,
     /// possibly expensive and noisy, information
     pub instrument: bool,
+
+    /// How to retry the deployment after non-deterministic errors
+    pub retry_policy: RetryPolicy,
+
+    /// How many times the deployment had already been retried when it was
+    /// started, so that restarting it does not start the backoff over
+    pub retry_count: u32,
+
+    /// The size in bytes of the entity cache that is kept between blocks
+    pub entity_cache_size: usize,
+
+    /// How many dynamic data sources the deployment may create before it
+    /// fails with a non-deterministic error
+    pub max_dynamic_data_sources: Option<usize>,
+
+    /// How many blocks per second the deployment may process while it is
+    /// catching up, so that it does not starve other deployments
+    pub max_blocks_per_second: Option<u32>,
+
+    /// Sends notifications about changes in the deployment's health and
+    /// sync status
+    pub notifier: Arc<Notifier>,
 }
@@ -1,4 +1,4 @@
-use crate::polling_monitor::IpfsService;
+use crate::polling_monitor::{ArweaveService, IpfsService, ObjectStoreService};
 use crate::subgraph::context::{IndexingContext, SubgraphKeepAlive};
 use crate::subgraph::inputs::IndexingInputs;
 use crate::subgraph::loader::load_dynamic_data_sources;
@@ -8,8 +8,8 @@ use graph::blockchain::block_stream::BlockStreamMetrics;
 use graph::blockchain::Blockchain;
 use graph::blockchain::NodeCapabilities;
 use graph::blockchain::{BlockchainKind, TriggerFilter};
-use graph::components::subgraph::ProofOfIndexingVersion;
-use graph::data::subgraph::{UnresolvedSubgraphManifest, SPEC_VERSION_0_0_6};
+use graph::components::subgraph::{Notifier, ProofOfIndexingVersion};
+use graph::data::subgraph::UnresolvedSubgraphManifest;
 use graph::data_source::causality_region::CausalityRegionSeq;
 use graph::env::EnvVars;
 use graph::prelude::{SubgraphInstanceManager as SubgraphInstanceManagerTrait, *};
@@ -30,6 +30,9 @@ pub struct SubgraphInstanceManager<S: SubgraphStore> {
     instances: SubgraphKeepAlive,
     link_resolver: Arc<dyn LinkResolver>,
     ipfs_service: IpfsService,
+    arweave_service: ArweaveService,
+    object_store_service: ObjectStoreService,
+    notifier: Arc<Notifier>,
     static_filters: bool,
     env_vars: Arc<EnvVars>,
 }
@@ -153,6 +156,25 @@ impl<S: SubgraphStore> SubgraphInstanceManagerTrait for SubgraphInstanceManager<
 
         info!(logger, "Stopped subgraph");
     }
+
+    async fn stop_all_subgraphs(&self) {
+        let logger = self
+            .logger_factory
+            .component_logger("SubgraphInstanceManager", None);
+
+        // Cancel the block streams first so that no new blocks are
+        // written, then write what the writables still have queued. Every
+        // block is written together with its cursor, and the cursors are
+        // therefore persisted as well
+        self.instances.clear();
+
+        match self.subgraph_store.stop_all_subgraphs().await {
+            Ok(()) => info!(logger, "Stopped all subgraphs"),
+            Err(err) => {
+                error!(logger, "Error stopping subgraph writers"; "error" => format!("{:#}", err))
+            }
+        }
+    }
 }
 
 impl<S: SubgraphStore> SubgraphInstanceManager<S> {
@@ -165,6 +187,9 @@ impl<S: SubgraphStore> SubgraphInstanceManager<S> {
         metrics_registry: Arc<MetricsRegistry>,
         link_resolver: Arc<dyn LinkResolver>,
         ipfs_service: IpfsService,
+        arweave_service: ArweaveService,
+        object_store_service: ObjectStoreService,
+        notifier: Arc<Notifier>,
         static_filters: bool,
     ) -> Self {
         let logger = logger_factory.component_logger("SubgraphInstanceManager", None);
@@ -178,6 +203,9 @@ impl<S: SubgraphStore> SubgraphInstanceManager<S> {
             instances: SubgraphKeepAlive::new(sg_metrics),
             link_resolver,
             ipfs_service,
+            arweave_service,
+            object_store_service,
+            notifier,
             static_filters,
             env_vars,
         }
@@ -330,6 +358,12 @@ impl<S: SubgraphStore> SubgraphInstanceManager<S> {
         };
 
         let start_blocks = manifest.start_blocks();
+        let subgraph_sources = manifest
+            .data_sources
+            .iter()
+            .filter_map(|ds| ds.as_subgraph())
+            .map(|ds| ds.source.clone())
+            .collect::<Vec<_>>();
 
         let templates = Arc::new(manifest.templates.clone());
 
@@ -360,10 +394,18 @@ impl<S: SubgraphStore> SubgraphInstanceManager<S> {
             stopwatch_metrics.clone(),
         ));
 
+        let handlers = manifest
+            .data_sources
+            .iter()
+            .flat_map(|ds| ds.handler_names())
+            .chain(manifest.templates.iter().flat_map(|t| t.handler_names()))
+            .map(str::to_string)
+            .collect();
         let subgraph_metrics = Arc::new(SubgraphInstanceMetrics::new(
             registry.cheap_clone(),
             deployment.hash.as_str(),
             stopwatch_metrics.clone(),
+            handlers,
         ));
 
         let block_stream_metrics = Arc::new(BlockStreamMetrics::new(
@@ -379,6 +421,8 @@ impl<S: SubgraphStore> SubgraphInstanceManager<S> {
             registry.cheap_clone(),
             &manifest.id,
             self.ipfs_service.clone(),
+            self.arweave_service.clone(),
+            self.object_store_service.clone(),
         );
 
         // Initialize deployment_head with current deployment head. Any sort of trouble in
@@ -386,24 +430,36 @@ impl<S: SubgraphStore> SubgraphInstanceManager<S> {
         let deployment_head = store.block_ptr().map(|ptr| ptr.number).unwrap_or(0) as f64;
         block_stream_metrics.deployment_head.set(deployment_head);
 
+        // Mappings read from IPFS with the limits that the subgraph requested,
+        // and give up on an unreachable IPFS node after a few retries so that
+        // the block is retried instead of the handler hanging
+        let handler_limits = &manifest.handler_limits;
+        let mapping_link_resolver = Arc::from(
+            self.link_resolver
+                .with_timeout(handler_limits.ipfs_timeout())
+                .with_max_file_size(handler_limits.max_ipfs_file_size())
+                .with_max_retries(self.env_vars.mappings.ipfs_max_retries),
+        );
         let host_builder = graph_runtime_wasm::RuntimeHostBuilder::new(
             chain.runtime_adapter(),
-            self.link_resolver.cheap_clone(),
+            mapping_link_resolver,
             subgraph_store.ens_lookup(),
         );
 
         let features = manifest.features.clone();
         let unified_api_version = manifest.unified_mapping_api_version()?;
-        let poi_version = if manifest.spec_version.ge(&SPEC_VERSION_0_0_6) {
-            ProofOfIndexingVersion::Fast
-        } else {
-            ProofOfIndexingVersion::Legacy
-        };
+        let poi_version = ProofOfIndexingVersion::for_spec_version(&manifest.spec_version);
 
         let causality_region_seq =
             CausalityRegionSeq::from_current(store.causality_region_curr_val().await?);
 
         let instrument = self.subgraph_store.instrument(&deployment)?;
+        let retry_policy = self.subgraph_store.retry_policy(&deployment)?;
+        let retry_count = self.subgraph_store.retry_count(&deployment)?;
+        let entity_cache_size = self.subgraph_store.entity_cache_size(&deployment)?;
+        let max_dynamic_data_sources = self.subgraph_store.max_dynamic_data_sources(&deployment)?;
+        let max_blocks_per_second = self.subgraph_store.max_blocks_per_second(&deployment)?;
+        let poi_transition_version = self.subgraph_store.poi_transition_version(&deployment)?;
         let instance = super::context::instance::SubgraphInstance::from_manifest(
             &logger,
             manifest,
@@ -427,8 +483,17 @@ impl<S: SubgraphStore> SubgraphInstanceManager<S> {
             static_filters,
             manifest_idx_and_name,
             poi_version,
+            poi_transition_version,
             network,
+            subgraph_sources,
+            subgraph_store: subgraph_store.cheap_clone(),
             instrument,
+            retry_policy,
+            retry_count,
+            entity_cache_size,
+            max_dynamic_data_sources,
+            max_blocks_per_second,
+            notifier: self.notifier.cheap_clone(),
         };
 
         // The subgraph state tracks the state of the subgraph instance over time
@@ -3,6 +3,8 @@ mod error;
 mod inputs;
 mod instance_manager;
 mod loader;
+mod parallel;
+mod pinning;
 mod provider;
 mod registrar;
 mod runner;
@@ -12,6 +14,6 @@ mod trigger_processor;
 
 pub use self::instance_manager::SubgraphInstanceManager;
 pub use self::provider::SubgraphAssignmentProvider;
-pub use self::registrar::SubgraphRegistrar;
+pub use self::registrar::{validate_subgraph, SubgraphRegistrar};
 pub use self::runner::SubgraphRunner;
 pub use self::trigger_processor::*;
@@ -0,0 +1,145 @@
+//! Splitting the triggers of a block into groups that can be processed
+//! concurrently. Two triggers end up in the same group if the data sources
+//! that handle them declare overlapping sets of entities, so that the
+//! handlers of different groups never read or write the same entities.
+use std::collections::BTreeSet;
+
+use graph::components::store::EntityType;
+
+/// The entities that the handlers of a trigger may use
+#[derive(Debug, PartialEq)]
+pub(crate) enum TriggerEntities {
+    /// No data source handles the trigger
+    Unhandled,
+    /// The union of the entities that the data sources that handle the
+    /// trigger declare
+    Declared(BTreeSet<EntityType>),
+    /// One of the data sources that handle the trigger does not declare
+    /// its entities
+    Unknown,
+}
+
+/// Triggers that have to be processed one after the other
+#[derive(Debug, PartialEq)]
+pub(crate) struct Group {
+    /// The indexes of the triggers in the group, in ascending order
+    pub triggers: Vec<usize>,
+    /// The entities that the data sources of the triggers declare
+    pub entities: BTreeSet<EntityType>,
+}
+
+/// Split triggers into groups. Triggers that no data source handles are
+/// not put into any group.
+///
+/// Returns `None` if the triggers can not be split into at least two
+/// groups, either because some data source does not declare its entities,
+/// because the data sources that handle a trigger declare no entities at
+/// all, or because all triggers are connected through the entities they
+/// use
+pub(crate) fn partition(entities: &[TriggerEntities]) -> Option<Vec<Group>> {
+    let mut groups: Vec<Group> = Vec::new();
+    for (idx, trigger_entities) in entities.iter().enumerate() {
+        let trigger_entities = match trigger_entities {
+            TriggerEntities::Unhandled => continue,
+            // Nothing keeps the handlers from using entities anyway
+            TriggerEntities::Declared(entities) if entities.is_empty() => return None,
+            TriggerEntities::Declared(entities) => entities,
+            TriggerEntities::Unknown => return None,
+        };
+
+        let mut group = Group {
+            triggers: vec![idx],
+            entities: trigger_entities.clone(),
+        };
+        let mut i = 0;
+        while i < groups.len() {
+            if groups[i].entities.is_disjoint(&group.entities) {
+                i += 1;
+            } else {
+                let other = groups.remove(i);
+                group.triggers.extend(other.triggers);
+                group.entities.extend(other.entities);
+            }
+        }
+        group.triggers.sort_unstable();
+        groups.push(group);
+    }
+
+    if groups.len() < 2 {
+        return None;
+    }
+    groups.sort_by_key(|group| group.triggers[0]);
+    Some(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use graph::components::store::EntityType;
+
+    use super::{partition, Group, TriggerEntities};
+
+    fn names(names: &[&str]) -> BTreeSet<EntityType> {
+        names
+            .iter()
+            .map(|name| EntityType::new(name.to_string()))
+            .collect()
+    }
+
+    fn entities(entities: &[&str]) -> TriggerEntities {
+        TriggerEntities::Declared(names(entities))
+    }
+
+    fn group(triggers: Vec<usize>, entities: &[&str]) -> Group {
+        Group {
+            triggers,
+            entities: names(entities),
+        }
+    }
+
+    #[test]
+    fn splits_disjoint_triggers() {
+        let triggers = vec![
+            entities(&["Swap"]),
+            entities(&["Transfer"]),
+            TriggerEntities::Unhandled,
+            entities(&["Swap", "Pool"]),
+            entities(&["Approval"]),
+            entities(&["Pool", "Approval"]),
+            entities(&["Token"]),
+        ];
+        assert_eq!(
+            Some(vec![
+                group(vec![0, 3, 4, 5], &["Approval", "Pool", "Swap"]),
+                group(vec![1], &["Transfer"]),
+                group(vec![6], &["Token"]),
+            ]),
+            partition(&triggers)
+        );
+    }
+
+    #[test]
+    fn needs_declared_entities_and_two_groups() {
+        assert_eq!(
+            None,
+            partition(&[entities(&["Swap"]), TriggerEntities::Unknown])
+        );
+        assert_eq!(
+            None,
+            partition(&[entities(&["Swap"]), entities(&["Swap", "Pool"])])
+        );
+        assert_eq!(
+            None,
+            partition(&[entities(&["Swap"]), TriggerEntities::Unhandled])
+        );
+    }
+
+    #[test]
+    fn handled_triggers_without_entities_are_not_skipped() {
+        assert_eq!(
+            None,
+            partition(&[entities(&["Swap"]), entities(&[]), entities(&["Transfer"])])
+        );
+    }
+}
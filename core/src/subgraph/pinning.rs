@@ -0,0 +1,122 @@
+//! Pinning the files of deployed subgraphs with a remote pinning service
+//! that implements the IPFS Pinning Service API, like Pinata or
+//! web3.storage, so that deployments can still be resolved when the IPFS
+//! node they were uploaded to goes away. Pinning happens in the background
+//! and failures are only logged since they do not affect the deployment.
+use std::collections::BTreeSet;
+
+use graph::prelude::{error, info, reqwest, serde_json::json, DeploymentHash, Logger, ENV_VARS};
+use serde_yaml::Value;
+
+/// The environment variable with the access token for the pinning service.
+/// It is read when it is needed rather than kept in `EnvVars` so that it
+/// does not show up when the settings are logged
+const PINNING_SERVICE_TOKEN: &str = "GRAPH_IPFS_PINNING_SERVICE_TOKEN";
+
+/// The CIDs of all the files that make up the deployment `hash`: the
+/// manifest itself and every file it links to, like the schema, ABIs and
+/// mappings
+pub(crate) fn deployment_files(hash: &DeploymentHash, raw: &serde_yaml::Mapping) -> Vec<String> {
+    fn collect(value: &Value, files: &mut BTreeSet<String>) {
+        match value {
+            Value::Mapping(map) => {
+                for (key, value) in map {
+                    match (key.as_str(), value.as_str()) {
+                        (Some("/"), Some(link)) => {
+                            if let Some(path) = link.strip_prefix("/ipfs/") {
+                                let cid = path.split('/').next().unwrap_or(path);
+                                if !cid.is_empty() {
+                                    files.insert(cid.to_string());
+                                }
+                            }
+                        }
+                        _ => collect(value, files),
+                    }
+                }
+            }
+            Value::Sequence(values) => values.iter().for_each(|value| collect(value, files)),
+            _ => {}
+        }
+    }
+
+    let mut files = BTreeSet::new();
+    for (_, value) in raw.iter() {
+        collect(value, &mut files);
+    }
+    files.remove(hash.as_str());
+
+    let mut files: Vec<_> = files.into_iter().collect();
+    files.insert(0, hash.to_string());
+    files
+}
+
+/// Ask the pinning service set with `GRAPH_IPFS_PINNING_SERVICE` to pin
+/// `files`. Does nothing if no pinning service is configured
+pub(crate) fn pin(logger: &Logger, hash: &DeploymentHash, files: Vec<String>) {
+    let service = match &ENV_VARS.mappings.ipfs_pinning_service {
+        Some(service) => service.trim_end_matches('/').to_string(),
+        None => return,
+    };
+    let token = std::env::var(PINNING_SERVICE_TOKEN).ok();
+    let logger = logger.clone();
+    let name = hash.to_string();
+
+    graph::spawn(async move {
+        let client = reqwest::Client::new();
+        for cid in files {
+            let mut req = client
+                .post(format!("{}/pins", service))
+                .json(&json!({ "cid": cid, "name": name }));
+            if let Some(token) = &token {
+                req = req.bearer_auth(token);
+            }
+            match req.send().await.and_then(|res| res.error_for_status()) {
+                Ok(_) => info!(logger, "Pinned subgraph file"; "cid" => &cid),
+                Err(e) => error!(logger, "Failed to pin subgraph file";
+                                 "cid" => &cid, "error" => e.to_string()),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use graph::prelude::DeploymentHash;
+
+    use super::deployment_files;
+
+    #[test]
+    fn finds_linked_files() {
+        let raw = r#"
+specVersion: 0.0.5
+schema:
+  file:
+    /: /ipfs/QmSchema
+dataSources:
+  - kind: ethereum/contract
+    source:
+      abi: Token
+    mapping:
+      abis:
+        - name: Token
+          file:
+            /: /ipfs/QmAbi
+        - name: Pair
+          file:
+            /: /ipfs/QmAbi
+      file:
+        /: /ipfs/QmMapping/mapping.wasm
+templates:
+  - kind: file/ipfs
+    mapping:
+      file:
+        /: /ipfs/QmTemplate
+"#;
+        let raw: serde_yaml::Mapping = serde_yaml::from_str(raw).unwrap();
+        let hash = DeploymentHash::new("QmManifest").unwrap();
+        assert_eq!(
+            vec!["QmManifest", "QmAbi", "QmMapping", "QmSchema", "QmTemplate"],
+            deployment_files(&hash, &raw)
+        );
+    }
+}
@@ -39,6 +39,14 @@ impl DeploymentRegistry {
         self.subgraph_metrics.deployment_count.dec();
         true
     }
+
+    fn clear(&self) {
+        let mut subgraphs_deployed = self.subgraphs_deployed.lock().unwrap();
+        self.subgraph_metrics
+            .deployment_count
+            .sub(subgraphs_deployed.len() as f64);
+        subgraphs_deployed.clear();
+    }
 }
 
 pub struct SubgraphAssignmentProvider<I> {
@@ -114,4 +122,9 @@ impl<I: SubgraphInstanceManager> SubgraphAssignmentProviderTrait for SubgraphAss
         }
         Ok(())
     }
+
+    async fn stop_all(&self) {
+        self.deployment_registry.clear();
+        self.instance_manager.stop_all_subgraphs().await;
+    }
 }
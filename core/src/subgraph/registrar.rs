@@ -1,11 +1,16 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 use std::time::Instant;
 
 use async_trait::async_trait;
 use graph::blockchain::Blockchain;
 use graph::blockchain::BlockchainKind;
 use graph::blockchain::BlockchainMap;
-use graph::components::store::{DeploymentId, DeploymentLocator, SubscriptionManager};
+use graph::blockchain::{DataSource as _, NodeCapabilities as _};
+use graph::components::store::{
+    DeploymentId, DeploymentLocator, SubgraphVersions, SubscriptionManager,
+};
+use graph::components::subgraph::{DeploymentEvent, Notifier};
+use graph::data::subgraph::features::detect_features;
 use graph::data::subgraph::schema::DeploymentCreate;
 use graph::data::subgraph::Graft;
 use graph::prelude::{
@@ -13,6 +18,8 @@ use graph::prelude::{
     SubgraphRegistrar as SubgraphRegistrarTrait, *,
 };
 
+use super::pinning;
+
 pub struct SubgraphRegistrar<P, S, SM> {
     logger: Logger,
     logger_factory: LoggerFactory,
@@ -23,6 +30,7 @@ pub struct SubgraphRegistrar<P, S, SM> {
     chains: Arc<BlockchainMap>,
     node_id: NodeId,
     version_switching_mode: SubgraphVersionSwitchingMode,
+    notifier: Arc<Notifier>,
     assignment_event_stream_cancel_guard: CancelGuard, // cancels on drop
 }
 
@@ -41,6 +49,7 @@ where
         chains: Arc<BlockchainMap>,
         node_id: NodeId,
         version_switching_mode: SubgraphVersionSwitchingMode,
+        notifier: Arc<Notifier>,
     ) -> Self {
         let logger = logger_factory.component_logger("SubgraphRegistrar", None);
         let logger_factory = logger_factory.with_parent(logger.clone());
@@ -57,6 +66,7 @@ where
             chains,
             node_id,
             version_switching_mode,
+            notifier,
             assignment_event_stream_cancel_guard: CancelGuard::new(),
         }
     }
@@ -167,13 +177,20 @@ where
                     match operation {
                         EntityChangeOperation::Set => {
                             store
-                                .assigned_node(&deployment)
+                                .assignment_status(&deployment)
                                 .map_err(|e| {
                                     anyhow!("Failed to get subgraph assignment entity: {}", e)
                                 })
                                 .map(|assigned| -> Box<dyn Stream<Item = _, Error = _> + Send> {
-                                    if let Some(assigned) = assigned {
-                                        if assigned == node_id {
+                                    if let Some((assigned, is_paused)) = assigned {
+                                        if assigned == node_id && is_paused {
+                                            // Stop indexing but keep the assignment
+                                            debug!(logger, "Deployment assignee is this node but the deployment is paused, broadcasting remove event"; "assigned_to" => assigned, "node_id" => &node_id);
+                                            Box::new(stream::once(Ok(AssignmentEvent::Remove {
+                                                deployment,
+                                                node_id: node_id.clone(),
+                                            })))
+                                        } else if assigned == node_id {
                                             // Start subgraph on this node
                                             debug!(logger, "Deployment assignee is this node, broadcasting add event"; "assigned_to" => assigned, "node_id" => &node_id);
                                             Box::new(stream::once(Ok(AssignmentEvent::Add {
@@ -269,6 +286,7 @@ where
         debug_fork: Option<DeploymentHash>,
         start_block_override: Option<BlockPtr>,
         graft_block_override: Option<BlockPtr>,
+        graft_at_earliest_block: bool,
     ) -> Result<DeploymentLocator, SubgraphRegistrarError> {
         // We don't have a location for the subgraph yet; that will be
         // assigned when we deploy for real. For logging purposes, make up a
@@ -296,6 +314,8 @@ where
             SubgraphRegistrarError::ResolveError(SubgraphManifestResolveError::ResolveError(e))
         })?;
 
+        let files = pinning::deployment_files(&hash, &raw);
+
         let deployment_locator = match kind {
             BlockchainKind::Arweave => {
                 create_subgraph_version::<graph_chain_arweave::Chain, _>(
@@ -306,6 +326,7 @@ where
                     hash.cheap_clone(),
                     start_block_override,
                     graft_block_override,
+                    graft_at_earliest_block,
                     raw,
                     node_id,
                     debug_fork,
@@ -323,6 +344,7 @@ where
                     hash.cheap_clone(),
                     start_block_override,
                     graft_block_override,
+                    graft_at_earliest_block,
                     raw,
                     node_id,
                     debug_fork,
@@ -340,6 +362,7 @@ where
                     hash.cheap_clone(),
                     start_block_override,
                     graft_block_override,
+                    graft_at_earliest_block,
                     raw,
                     node_id,
                     debug_fork,
@@ -357,6 +380,7 @@ where
                     hash.cheap_clone(),
                     start_block_override,
                     graft_block_override,
+                    graft_at_earliest_block,
                     raw,
                     node_id,
                     debug_fork,
@@ -374,6 +398,7 @@ where
                     hash.cheap_clone(),
                     start_block_override,
                     graft_block_override,
+                    graft_at_earliest_block,
                     raw,
                     node_id,
                     debug_fork,
@@ -391,9 +416,26 @@ where
             "subgraph_hash" => hash.to_string(),
         );
 
+        pinning::pin(&logger, &hash, files);
+
         Ok(deployment_locator)
     }
 
+    async fn validate_subgraph(&self, hash: &DeploymentHash) -> SubgraphValidation {
+        let logger = self
+            .logger_factory
+            .subgraph_logger(&DeploymentLocator::new(DeploymentId(0), hash.clone()));
+
+        validate_subgraph(
+            &logger,
+            self.store.clone(),
+            Some(self.chains.as_ref()),
+            &self.resolver,
+            hash,
+        )
+        .await
+    }
+
     async fn remove_subgraph(&self, name: SubgraphName) -> Result<(), SubgraphRegistrarError> {
         self.store.clone().remove_subgraph(name.clone())?;
 
@@ -402,6 +444,52 @@ where
         Ok(())
     }
 
+    async fn subgraph_versions(
+        &self,
+        name: &SubgraphName,
+    ) -> Result<Option<SubgraphVersions>, SubgraphRegistrarError> {
+        Ok(self.store.subgraph_versions(name)?)
+    }
+
+    async fn restore_subgraph_versions(
+        &self,
+        name: SubgraphName,
+        versions: SubgraphVersions,
+    ) -> Result<(), SubgraphRegistrarError> {
+        self.store.restore_subgraph_versions(&name, &versions)?;
+
+        debug!(self.logger, "Restored subgraph versions";
+               "subgraph_name" => name.to_string(),
+               "current_version" => &versions.current,
+               "pending_version" => &versions.pending);
+
+        Ok(())
+    }
+
+    async fn pause_subgraph(&self, hash: &DeploymentHash) -> Result<(), SubgraphRegistrarError> {
+        let locator = self.store.active_locator(hash)?;
+        let deployment =
+            locator.ok_or_else(|| SubgraphRegistrarError::DeploymentNotFound(hash.to_string()))?;
+
+        self.store.pause_subgraph(&deployment)?;
+
+        debug!(self.logger, "Paused subgraph"; "deployment" => hash.to_string());
+
+        Ok(())
+    }
+
+    async fn resume_subgraph(&self, hash: &DeploymentHash) -> Result<(), SubgraphRegistrarError> {
+        let locator = self.store.active_locator(hash)?;
+        let deployment =
+            locator.ok_or_else(|| SubgraphRegistrarError::DeploymentNotFound(hash.to_string()))?;
+
+        self.store.resume_subgraph(&deployment)?;
+
+        debug!(self.logger, "Resumed subgraph"; "deployment" => hash.to_string());
+
+        Ok(())
+    }
+
     /// Reassign a subgraph deployment to a different node.
     ///
     /// Reassigning to a nodeId that does not match any reachable graph-nodes will effectively pause the
@@ -415,12 +503,105 @@ where
         let deployment =
             locator.ok_or_else(|| SubgraphRegistrarError::DeploymentNotFound(hash.to_string()))?;
 
+        let from = self.store.assigned_node(&deployment)?;
         self.store.reassign_subgraph(&deployment, node_id)?;
 
+        if from.as_ref() != Some(node_id) {
+            self.notifier.notify(
+                hash,
+                DeploymentEvent::Reassigned {
+                    from,
+                    to: node_id.clone(),
+                },
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn rewind_subgraph(
+        &self,
+        hash: &DeploymentHash,
+        block_ptr_to: BlockPtr,
+    ) -> Result<(), SubgraphRegistrarError> {
+        let locator = self.store.active_locator(hash)?;
+        let deployment =
+            locator.ok_or_else(|| SubgraphRegistrarError::DeploymentNotFound(hash.to_string()))?;
+
+        // Rewinding a deployment while it is indexing would race with the
+        // indexer; require that it is paused first
+        match self.store.assignment_status(&deployment)? {
+            Some((_, true)) | None => {}
+            Some((_, false)) => {
+                return Err(SubgraphRegistrarError::InvalidRewind(format!(
+                    "deployment {} must be paused before it can be rewound",
+                    hash
+                )))
+            }
+        }
+
+        let network = self.store.network_name(&deployment)?;
+        let chain_store = chain_store(&self.chains, &network).ok_or_else(|| {
+            SubgraphRegistrarError::NetworkNotSupported(anyhow!(
+                "network `{}` is not configured on this node",
+                network
+            ))
+        })?;
+        match chain_store.block_number(&block_ptr_to.hash).await? {
+            Some((_, number, _)) if number == block_ptr_to.number => {}
+            Some((_, number, _)) => {
+                return Err(SubgraphRegistrarError::InvalidRewind(format!(
+                    "block {} is block number {} and not {}",
+                    block_ptr_to.hash, number, block_ptr_to.number
+                )))
+            }
+            None => {
+                return Err(SubgraphRegistrarError::InvalidRewind(format!(
+                    "network `{}` does not have a block with hash {}",
+                    network, block_ptr_to.hash
+                )))
+            }
+        }
+
+        self.store.rewind(hash.clone(), block_ptr_to.clone())?;
+
+        info!(self.logger, "Rewound subgraph";
+              "deployment" => hash.to_string(),
+              "block" => block_ptr_to.to_string());
+
         Ok(())
     }
 }
 
+/// The chain store for `network`, regardless of what kind of chain it is
+fn chain_store(chains: &BlockchainMap, network: &str) -> Option<Arc<dyn ChainStore>> {
+    let network = network.to_string();
+    chains
+        .get::<graph_chain_ethereum::Chain>(network.clone())
+        .map(|chain| chain.chain_store())
+        .or_else(|_| {
+            chains
+                .get::<graph_chain_near::Chain>(network.clone())
+                .map(|chain| chain.chain_store())
+        })
+        .or_else(|_| {
+            chains
+                .get::<graph_chain_arweave::Chain>(network.clone())
+                .map(|chain| chain.chain_store())
+        })
+        .or_else(|_| {
+            chains
+                .get::<graph_chain_cosmos::Chain>(network.clone())
+                .map(|chain| chain.chain_store())
+        })
+        .or_else(|_| {
+            chains
+                .get::<graph_chain_substreams::Chain>(network)
+                .map(|chain| chain.chain_store())
+        })
+        .ok()
+}
+
 async fn handle_assignment_event(
     event: AssignmentEvent,
     provider: Arc<impl SubgraphAssignmentProviderTrait>,
@@ -528,6 +709,176 @@ async fn resolve_graft_block(
         })
 }
 
+/// Fetch the manifest for `hash` and the files it links to, and validate
+/// it the same way as deploying it would, without creating a deployment.
+/// If `chains` is given, also check that the manifest's network is one of
+/// them
+pub async fn validate_subgraph<S: SubgraphStore>(
+    logger: &Logger,
+    store: Arc<S>,
+    chains: Option<&BlockchainMap>,
+    resolver: &Arc<dyn LinkResolver>,
+    hash: &DeploymentHash,
+) -> SubgraphValidation {
+    let mut validation = SubgraphValidation::new(hash);
+
+    let raw: serde_yaml::Mapping = match resolver.cat(logger, &hash.to_ipfs_link()).await {
+        Ok(file_bytes) => match serde_yaml::from_slice(&file_bytes) {
+            Ok(raw) => raw,
+            Err(e) => {
+                validation.add(ValidationStage::Resolve, e);
+                return validation;
+            }
+        },
+        Err(e) => {
+            validation.add(ValidationStage::Resolve, e);
+            return validation;
+        }
+    };
+
+    let kind = match BlockchainKind::from_manifest(&raw) {
+        Ok(kind) => kind,
+        Err(e) => {
+            validation.add(ValidationStage::Resolve, e);
+            return validation;
+        }
+    };
+    validation.kind = Some(kind);
+
+    match kind {
+        BlockchainKind::Arweave => {
+            validate_manifest::<graph_chain_arweave::Chain, _>(
+                logger,
+                store,
+                chains,
+                resolver,
+                hash,
+                raw,
+                &mut validation,
+            )
+            .await
+        }
+        BlockchainKind::Ethereum => {
+            validate_manifest::<graph_chain_ethereum::Chain, _>(
+                logger,
+                store,
+                chains,
+                resolver,
+                hash,
+                raw,
+                &mut validation,
+            )
+            .await
+        }
+        BlockchainKind::Near => {
+            validate_manifest::<graph_chain_near::Chain, _>(
+                logger,
+                store,
+                chains,
+                resolver,
+                hash,
+                raw,
+                &mut validation,
+            )
+            .await
+        }
+        BlockchainKind::Cosmos => {
+            validate_manifest::<graph_chain_cosmos::Chain, _>(
+                logger,
+                store,
+                chains,
+                resolver,
+                hash,
+                raw,
+                &mut validation,
+            )
+            .await
+        }
+        BlockchainKind::Substreams => {
+            validate_manifest::<graph_chain_substreams::Chain, _>(
+                logger,
+                store,
+                chains,
+                resolver,
+                hash,
+                raw,
+                &mut validation,
+            )
+            .await
+        }
+    }
+
+    validation
+}
+
+async fn validate_manifest<C: Blockchain, S: SubgraphStore>(
+    logger: &Logger,
+    store: Arc<S>,
+    chains: Option<&BlockchainMap>,
+    resolver: &Arc<dyn LinkResolver>,
+    hash: &DeploymentHash,
+    raw: serde_yaml::Mapping,
+    validation: &mut SubgraphValidation,
+) {
+    let unvalidated = match UnvalidatedSubgraphManifest::<C>::resolve(
+        hash.clone(),
+        raw,
+        resolver,
+        logger,
+        ENV_VARS.max_spec_version.clone(),
+    )
+    .await
+    {
+        Ok(unvalidated) => unvalidated,
+        Err(SubgraphManifestResolveError::Unsupported(unsupported)) => {
+            validation.add(ValidationStage::Resolve, &unsupported);
+            validation.unsupported = Some(unsupported);
+            return;
+        }
+        Err(e) => return validation.add(ValidationStage::Resolve, e),
+    };
+
+    let manifest = unvalidated.manifest();
+    validation.spec_version = Some(manifest.spec_version.to_string());
+    let api_versions: BTreeSet<_> = manifest.api_versions().collect();
+    validation.api_versions = api_versions.iter().map(|v| v.to_string()).collect();
+    // A mapping that can not be parsed is also reported by `validate` below
+    if let Ok(features) = detect_features(manifest) {
+        validation.features = features.iter().map(|f| f.to_string()).collect();
+    }
+    let onchain_data_sources = manifest
+        .data_sources
+        .iter()
+        .filter_map(|d| d.as_onchain().cloned())
+        .collect::<Vec<_>>();
+    validation.required_capabilities =
+        Some(C::NodeCapabilities::from_data_sources(&onchain_data_sources).to_string());
+
+    // A manifest without a network or with several networks is reported
+    // by `validate` below
+    let networks: BTreeSet<_> = onchain_data_sources
+        .iter()
+        .filter_map(|ds| ds.network())
+        .collect();
+    if let [network] = networks.into_iter().collect::<Vec<_>>().as_slice() {
+        validation.network = Some(network.to_string());
+        if let Some(chains) = chains {
+            if chains.get::<C>(network.to_string()).is_err() {
+                validation.add(
+                    ValidationStage::Network,
+                    format!("network `{}` is not supported by this node", network),
+                );
+            }
+        }
+    }
+
+    if let Err(errors) = unvalidated.validate(store, true).await {
+        for e in errors {
+            validation.add(ValidationStage::Manifest, e);
+        }
+    }
+}
+
 async fn create_subgraph_version<C: Blockchain, S: SubgraphStore>(
     logger: &Logger,
     store: Arc<S>,
@@ -536,6 +887,7 @@ async fn create_subgraph_version<C: Blockchain, S: SubgraphStore>(
     deployment: DeploymentHash,
     start_block_override: Option<BlockPtr>,
     graft_block_override: Option<BlockPtr>,
+    graft_at_earliest_block: bool,
     raw: serde_yaml::Mapping,
     node_id: NodeId,
     debug_fork: Option<DeploymentHash>,
@@ -543,7 +895,7 @@ async fn create_subgraph_version<C: Blockchain, S: SubgraphStore>(
     resolver: &Arc<dyn LinkResolver>,
 ) -> Result<DeploymentLocator, SubgraphRegistrarError> {
     let raw_string = serde_yaml::to_string(&raw).unwrap();
-    let unvalidated = UnvalidatedSubgraphManifest::<C>::resolve(
+    let mut unvalidated = UnvalidatedSubgraphManifest::<C>::resolve(
         deployment,
         raw,
         resolver,
@@ -553,6 +905,20 @@ async fn create_subgraph_version<C: Blockchain, S: SubgraphStore>(
     .map_err(SubgraphRegistrarError::ResolveError)
     .await?;
 
+    if graft_at_earliest_block {
+        if let Some(block) = unvalidated
+            .graft_at_earliest_block(store.cheap_clone())
+            .await?
+        {
+            warn!(
+                logger,
+                "The graft base has been pruned past the graft block, grafting at its earliest block instead";
+                "graft_block" => block,
+                "earliest_block" => unvalidated.manifest().graft.as_ref().map(|graft| graft.block),
+            );
+        }
+    }
+
     let manifest = unvalidated
         .validate(store.cheap_clone(), true)
         .await
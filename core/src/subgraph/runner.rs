@@ -1,15 +1,21 @@
 use crate::subgraph::context::IndexingContext;
 use crate::subgraph::error::BlockProcessingError;
 use crate::subgraph::inputs::IndexingInputs;
+use crate::subgraph::parallel::{self, TriggerEntities};
 use crate::subgraph::state::IndexingState;
 use crate::subgraph::stream::new_block_stream;
 use atomic_refcell::AtomicRefCell;
 use graph::blockchain::block_stream::{BlockStreamEvent, BlockWithTriggers, FirehoseCursor};
-use graph::blockchain::{Block, Blockchain, DataSource as _, TriggerFilter as _};
-use graph::components::store::{EmptyStore, EntityKey, StoredDynamicDataSource};
+use graph::blockchain::{Block, Blockchain, BlockchainKind, DataSource as _, TriggerFilter as _};
+use graph::components::store::{
+    DeploymentLoad, EmptyStore, EntityKey, EntityType, RetryPolicy, StoredDynamicDataSource,
+};
 use graph::components::{
     store::ModificationsAndCache,
-    subgraph::{MappingError, PoICausalityRegion, ProofOfIndexing, SharedProofOfIndexing},
+    subgraph::{
+        DeploymentEvent, MappingError, PoICausalityRegion, ProofOfIndexing, ProofOfIndexingVersion,
+        SharedProofOfIndexing,
+    },
 };
 use graph::data::store::scalar::Bytes;
 use graph::data::subgraph::{
@@ -17,11 +23,14 @@ use graph::data::subgraph::{
     SubgraphFeature,
 };
 use graph::data_source::{
-    offchain, CausalityRegion, DataSource, DataSourceCreationError, DataSourceTemplate, TriggerData,
+    offchain, subgraph, CausalityRegion, DataSource, DataSourceCreationError, DataSourceTemplate,
+    TriggerData,
 };
 use graph::env::EnvVars;
+use graph::log::{buffer::LogBuffer, telemetry};
 use graph::prelude::*;
 use graph::util::{backoff::ExponentialBackoff, lfu_cache::LfuCache};
+use std::collections::{BTreeSet, HashMap};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
@@ -29,6 +38,10 @@ const MINUTE: Duration = Duration::from_secs(60);
 
 const SKIP_PTR_UPDATES_THRESHOLD: Duration = Duration::from_secs(60 * 5);
 
+/// How often to check whether the source of a subgraph data source has
+/// caught up with the block that is being processed
+const SUBGRAPH_SOURCE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
 pub struct SubgraphRunner<C, T>
 where
     C: Blockchain,
@@ -41,6 +54,21 @@ where
     pub metrics: RunnerMetrics,
 }
 
+/// The backoff for retrying after non-deterministic errors according to
+/// `policy`. It continues from the `retry_count` retries that were made
+/// before the deployment was last started
+fn retry_backoff(policy: &RetryPolicy, retry_count: u32, env_vars: &EnvVars) -> ExponentialBackoff {
+    let ceiling = policy.ceiling.unwrap_or(env_vars.subgraph_error_retry_ceil);
+    let base = policy.base.unwrap_or(MINUTE * 2);
+    let mut backoff = ExponentialBackoff::with_jitter(
+        base.min(ceiling),
+        ceiling,
+        env_vars.subgraph_error_retry_jitter,
+    );
+    backoff.attempt = retry_count as u64;
+    backoff
+}
+
 impl<C, T> SubgraphRunner<C, T>
 where
     C: Blockchain,
@@ -53,6 +81,7 @@ where
         metrics: RunnerMetrics,
         env_vars: Arc<EnvVars>,
     ) -> Self {
+        let backoff = retry_backoff(&inputs.retry_policy, inputs.retry_count, &env_vars);
         Self {
             inputs: Arc::new(inputs),
             ctx,
@@ -60,12 +89,16 @@ where
                 should_try_unfail_non_deterministic: true,
                 synced: false,
                 skip_ptr_updates_timer: Instant::now(),
-                backoff: ExponentialBackoff::with_jitter(
-                    (MINUTE * 2).min(env_vars.subgraph_error_retry_ceil),
-                    env_vars.subgraph_error_retry_ceil,
-                    env_vars.subgraph_error_retry_jitter,
-                ),
+                handler_time: Duration::ZERO,
+                load_report_timer: Instant::now(),
+                load_report_block: None,
+                lagging: false,
+                backoff,
                 entity_lfu_cache: LfuCache::new(),
+                last_block_start: Instant::now(),
+                batched_cache: None,
+                batched_blocks: 0,
+                batched_metric_increments: Vec::new(),
             },
             logger,
             metrics,
@@ -83,6 +116,9 @@ where
     /// duplicated host insertion and POI issues with dirty entity changes.
     fn revert_state(&mut self, block_number: BlockNumber) -> Result<(), Error> {
         self.state.entity_lfu_cache = LfuCache::new();
+        self.state.batched_cache = None;
+        self.state.batched_blocks = 0;
+        self.state.batched_metric_increments.clear();
 
         // 1. Revert all hosts(created by DDS) up to block_number inclusively.
         // 2. Unmark any offchain data sources that were marked done on the blocks being removed.
@@ -91,6 +127,28 @@ where
         Ok(())
     }
 
+    /// Wait until the deployment may process another block if it is
+    /// limited to a number of blocks per second. The limit only applies
+    /// while the deployment is catching up; once it is synced, it has to
+    /// keep up with the chain
+    async fn throttle(&mut self) {
+        if let Some(max) = self.inputs.max_blocks_per_second {
+            if !self.state.synced && max > 0 {
+                let interval = Duration::from_secs(1) / max;
+                let elapsed = self.state.last_block_start.elapsed();
+                if elapsed < interval {
+                    let wait = interval - elapsed;
+                    self.metrics
+                        .subgraph
+                        .throttled_seconds
+                        .inc_by(wait.as_secs_f64());
+                    tokio::time::sleep(wait).await;
+                }
+            }
+        }
+        self.state.last_block_start = Instant::now();
+    }
+
     #[cfg(debug_assertions)]
     pub fn context(&self) -> &IndexingContext<C, T> {
         &self.ctx
@@ -122,25 +180,34 @@ where
                 // There's no point in calling it if we have no current or parent block
                 // pointers, because there would be: no block to revert to or to search
                 // errors from (first execution).
-                let _outcome = self
+                let outcome = self
                     .inputs
                     .store
                     .unfail_deterministic_error(&current_ptr, &parent_ptr)
                     .await?;
+                if let UnfailOutcome::Unfailed = outcome {
+                    self.notify(DeploymentEvent::Healthy);
+                }
             }
         }
 
+        self.load_entity_cache_snapshot().await;
+
         loop {
             debug!(self.logger, "Starting or restarting subgraph");
 
             let block_stream_canceler = CancelGuard::new();
             let block_stream_cancel_handle = block_stream_canceler.handle();
 
-            let mut block_stream =
-                new_block_stream(&self.inputs, &self.ctx.filter, &self.metrics.subgraph)
-                    .await?
-                    .map_err(CancelableError::Error)
-                    .cancelable(&block_stream_canceler, || Err(CancelableError::Cancel));
+            let mut block_stream = new_block_stream(
+                &self.logger,
+                &self.inputs,
+                &self.ctx.filter,
+                &self.metrics.subgraph,
+            )
+            .await?
+            .map_err(CancelableError::Error)
+            .cancelable(&block_stream_canceler, || Err(CancelableError::Cancel));
 
             // Keep the stream's cancel guard around to be able to shut it down when the subgraph
             // deployment is unassigned
@@ -154,8 +221,14 @@ where
             loop {
                 let event = {
                     let _section = self.metrics.stream.stopwatch.start_section("scan_blocks");
+                    let mut span = telemetry::span("fetch_block")
+                        .attr("deployment", self.inputs.deployment.hash.to_string());
 
-                    block_stream.next().await
+                    let event = block_stream.next().await;
+                    if let Some(Ok(BlockStreamEvent::ProcessBlock(block, _))) = &event {
+                        span.set_attr("block.number", block.ptr().number as i64);
+                    }
+                    event
                 };
 
                 // TODO: move cancel handle to the Context
@@ -167,6 +240,7 @@ where
                     Action::Continue => continue,
                     Action::Stop => {
                         info!(self.logger, "Stopping subgraph");
+                        self.save_entity_cache_snapshot().await;
                         self.inputs.store.flush().await?;
                         return Ok(self);
                     }
@@ -181,6 +255,74 @@ where
         }
     }
 
+    /// Fill the entity cache with the entities that the deployment used
+    /// most often when it last stopped. Only the keys are part of the
+    /// snapshot; the entities are loaded from the store so that they are
+    /// current even if the deployment was rewound in the meantime
+    async fn load_entity_cache_snapshot(&mut self) {
+        let size = ENV_VARS.mappings.entity_cache_snapshot_size;
+        if size == 0 {
+            return;
+        }
+
+        let keys = match self.inputs.store.entity_cache_snapshot().await {
+            Ok(keys) => keys,
+            Err(e) => {
+                warn!(self.logger, "Failed to load entity cache snapshot";
+                      "error" => e.to_string());
+                return;
+            }
+        };
+        let keys: BTreeSet<_> = keys.into_iter().take(size).collect();
+        if keys.is_empty() {
+            return;
+        }
+
+        let start = Instant::now();
+        let mut entities = match self.inputs.store.get_many(keys.clone()) {
+            Ok(entities) => entities,
+            Err(e) => {
+                warn!(self.logger, "Failed to load entities from entity cache snapshot";
+                      "error" => e.to_string());
+                return;
+            }
+        };
+        let count = keys.len();
+        for key in keys {
+            let entity = entities.remove(&key).map(|mut entity| {
+                // `__typename` is for queries not for mappings.
+                entity.remove("__typename");
+                entity
+            });
+            self.state.entity_lfu_cache.insert(key, entity);
+        }
+        self.state
+            .entity_lfu_cache
+            .evict(self.inputs.entity_cache_size);
+        info!(self.logger, "Loaded entity cache snapshot";
+              "entities" => count,
+              "time_ms" => start.elapsed().as_millis());
+    }
+
+    /// Remember which entities the deployment used most often so that the
+    /// next start can load them into the entity cache right away
+    async fn save_entity_cache_snapshot(&self) {
+        let size = ENV_VARS.mappings.entity_cache_snapshot_size;
+        // The cache is empty when the deployment stops in the middle of a
+        // block; keep the previous snapshot in that case
+        if size == 0 || self.state.entity_lfu_cache.is_empty() {
+            return;
+        }
+
+        let keys = self.state.entity_lfu_cache.hottest_keys(size);
+        let count = keys.len();
+        match self.inputs.store.save_entity_cache_snapshot(keys).await {
+            Ok(()) => debug!(self.logger, "Saved entity cache snapshot"; "entities" => count),
+            Err(e) => warn!(self.logger, "Failed to save entity cache snapshot";
+                            "error" => e.to_string()),
+        }
+    }
+
     /// Processes a block and returns the updated context and a boolean flag indicating
     /// whether new dynamic data sources have been added to the subgraph.
     async fn process_block(
@@ -209,10 +351,10 @@ where
         }
 
         let proof_of_indexing = if self.inputs.store.supports_proof_of_indexing().await? {
-            Some(Arc::new(AtomicRefCell::new(ProofOfIndexing::new(
-                block_ptr.number,
-                self.inputs.poi_version,
-            ))))
+            Some(Arc::new(AtomicRefCell::new(
+                ProofOfIndexing::new(block_ptr.number, self.inputs.poi_version)
+                    .with_transition(self.inputs.poi_transition_version),
+            )))
         } else {
             None
         };
@@ -220,16 +362,31 @@ where
         // Causality region for onchain triggers.
         let causality_region = PoICausalityRegion::from_network(&self.inputs.network);
 
+        // Entity changes of the deployments that subgraph data sources read
+        // from are processed after the triggers from the chain
+        let subgraph_triggers = self
+            .subgraph_triggers(&logger, &block_ptr, block_stream_cancel_handle)
+            .await?;
+
         // Process events one after the other, passing in entity operations
         // collected previously to every new event being processed
-        let mut block_state = match self
-            .process_triggers(
+        let span = telemetry::span("process_triggers").attr(
+            "triggers",
+            (triggers.len() + subgraph_triggers.len()) as i64,
+        );
+        let mut block_state = match telemetry::in_span(
+            span,
+            self.process_triggers(
                 &proof_of_indexing,
                 &block,
-                triggers.into_iter().map(TriggerData::Onchain),
+                triggers
+                    .into_iter()
+                    .map(TriggerData::Onchain)
+                    .chain(subgraph_triggers.into_iter().map(TriggerData::Subgraph)),
                 &causality_region,
-            )
-            .await
+            ),
+        )
+        .await
         {
             // Triggers processed with no errors or with only deterministic errors.
             Ok(block_state) => block_state,
@@ -360,12 +517,32 @@ where
             let proof_of_indexing = Arc::try_unwrap(proof_of_indexing).unwrap().into_inner();
             update_proof_of_indexing(
                 proof_of_indexing,
+                self.inputs.poi_transition_version,
                 &self.metrics.host.stopwatch,
                 &mut block_state.entity_cache,
             )
             .await?;
         }
 
+        // Mapping counters are only incremented once the changes of the
+        // block have been written, so that blocks that are processed again
+        // are not counted twice
+        let mut metric_increments = block_state.drain_metric_increments();
+
+        if !has_errors && self.batch_write(&block_ptr).await? {
+            self.state.batched_cache = Some(block_state.entity_cache);
+            self.state.batched_blocks += 1;
+            self.state
+                .batched_metric_increments
+                .extend(metric_increments);
+            return match needs_restart {
+                true => Ok(Action::Restart),
+                false => Ok(Action::Continue),
+            };
+        }
+        self.state.batched_blocks = 0;
+        metric_increments.splice(0..0, self.state.batched_metric_increments.drain(..));
+
         let section = self
             .metrics
             .host
@@ -374,19 +551,22 @@ where
         let ModificationsAndCache {
             modifications: mut mods,
             entity_lfu_cache: cache,
+            stats: cache_stats,
         } = block_state
             .entity_cache
             .as_modifications()
             .map_err(|e| BlockProcessingError::Unknown(e.into()))?;
         section.end();
+        self.metrics.subgraph.observe_entity_cache(&cache_stats);
 
         // Check for offchain events and process them, including their entity modifications in the
         // set to be transacted.
         let offchain_events = self.ctx.offchain_monitor.ready_offchain_events()?;
-        let (offchain_mods, processed_data_sources) = self
+        let (offchain_mods, processed_data_sources, offchain_metric_increments) = self
             .handle_offchain_triggers(offchain_events, &block)
             .await?;
         mods.extend(offchain_mods);
+        metric_increments.extend(offchain_metric_increments);
 
         // Put the cache back in the state, asserting that the placeholder cache was not used.
         assert!(self.state.entity_lfu_cache.is_empty());
@@ -432,8 +612,11 @@ where
 
         let first_error = deterministic_errors.first().cloned();
 
-        store
-            .transact_block_operations(
+        let span = telemetry::span("transact_block_operations")
+            .attr("entity_operations", mods.len() as i64);
+        telemetry::in_span(
+            span,
+            store.transact_block_operations(
                 block_ptr,
                 firehose_cursor,
                 mods,
@@ -442,9 +625,10 @@ where
                 deterministic_errors,
                 self.inputs.manifest_idx_and_name.clone(),
                 processed_data_sources,
-            )
-            .await
-            .context("Failed to transact block operations")?;
+            ),
+        )
+        .await
+        .context("Failed to transact block operations")?;
 
         // For subgraphs with `nonFatalErrors` feature disabled, we consider
         // any error as fatal.
@@ -458,6 +642,13 @@ where
             return Err(BlockProcessingError::Deterministic(first_error.unwrap()));
         }
 
+        for (name, value) in metric_increments {
+            if !self.metrics.host.increment_mapping_counter(&name, value) {
+                debug!(logger, "Ignoring increment of metric since the subgraph has too many metrics";
+                       "name" => &name);
+            }
+        }
+
         let elapsed = start.elapsed().as_secs_f64();
         self.metrics
             .subgraph
@@ -482,6 +673,33 @@ where
         }
     }
 
+    /// Whether the changes of the block `block_ptr` should be written
+    /// together with those of the blocks after it rather than in their own
+    /// transaction. Substreams deployments that are far behind the chain
+    /// head write up to `GRAPH_SUBSTREAMS_WRITE_BATCH_SIZE` blocks at once,
+    /// which makes syncing faster but means that they can only be rewound
+    /// to the blocks at which a batch was written
+    async fn batch_write(&self, block_ptr: &BlockPtr) -> Result<bool, BlockProcessingError> {
+        if C::KIND != BlockchainKind::Substreams
+            || self.state.batched_blocks + 1 >= ENV_VARS.substreams_write_batch_size
+            || self.state.should_try_unfail_non_deterministic
+        {
+            return Ok(false);
+        }
+        if let Some(stop_block) = self.inputs.stop_block {
+            if block_ptr.number >= stop_block {
+                return Ok(false);
+            }
+        }
+
+        // Blocks that can still be reorged are always written by themselves
+        let distance = ENV_VARS
+            .substreams_write_batch_head_distance
+            .max(ENV_VARS.reorg_threshold);
+        let chain_head = self.inputs.chain.chain_store().cached_head_ptr().await?;
+        Ok(chain_head.is_some() && !close_to_chain_head(block_ptr, chain_head, distance))
+    }
+
     async fn process_triggers(
         &mut self,
         proof_of_indexing: &SharedProofOfIndexing,
@@ -489,10 +707,39 @@ where
         triggers: impl Iterator<Item = TriggerData<C>>,
         causality_region: &str,
     ) -> Result<BlockState<C>, MappingError> {
-        let mut block_state = BlockState::new(
-            self.inputs.store.clone(),
-            std::mem::take(&mut self.state.entity_lfu_cache),
-        );
+        let batched = self.state.batched_cache.is_some();
+        let mut block_state = match self.state.batched_cache.take() {
+            Some(cache) => BlockState::with_entity_cache(cache),
+            None => {
+                self.state.batched_blocks = 0;
+                BlockState::new(
+                    self.inputs.store.clone(),
+                    std::mem::take(&mut self.state.entity_lfu_cache),
+                )
+            }
+        };
+        block_state
+            .entity_cache
+            .set_max_weight(self.inputs.entity_cache_size);
+
+        // Triggers that are processed in parallel read from the store, which
+        // does not have the changes of a batch yet
+        let triggers: Vec<_> = triggers.collect();
+        if ENV_VARS.mappings.parallel_triggers
+            && triggers.len() > 1
+            && !batched
+            && self
+                .process_triggers_in_parallel(
+                    &mut block_state,
+                    proof_of_indexing,
+                    block,
+                    &triggers,
+                    causality_region,
+                )
+                .await?
+        {
+            return Ok(block_state);
+        }
 
         for trigger in triggers {
             block_state = self
@@ -520,12 +767,268 @@ where
         Ok(block_state)
     }
 
+    /// Process `triggers` concurrently in groups whose data sources declare
+    /// disjoint sets of entities, and add the results of the groups to
+    /// `block_state`. Returns `false` if the triggers can not be split into
+    /// groups, or if processing them concurrently might have given
+    /// different results than processing them one after the other. The
+    /// triggers then need to be processed one after the other, and nothing
+    /// that the groups did is kept but what they read from the store or
+    /// fetched with `http.get`. Logs and timings of the groups are held
+    /// back until it is clear that their results are kept
+    async fn process_triggers_in_parallel(
+        &self,
+        block_state: &mut BlockState<C>,
+        proof_of_indexing: &SharedProofOfIndexing,
+        block: &Arc<C::Block>,
+        triggers: &[TriggerData<C>],
+        causality_region: &str,
+    ) -> Result<bool, MappingError> {
+        let mut trigger_hosts = Vec::with_capacity(triggers.len());
+        let mut trigger_entities = Vec::with_capacity(triggers.len());
+        for trigger in triggers {
+            let mut hosts = vec![];
+            let mut entities = TriggerEntities::Unhandled;
+            for host in self.ctx.hosts() {
+                if host
+                    .match_and_decode(trigger, block, &self.logger)?
+                    .is_none()
+                {
+                    continue;
+                }
+                entities = match (entities, host.data_source().declared_entities()) {
+                    (TriggerEntities::Unknown, _) | (_, None) => TriggerEntities::Unknown,
+                    (TriggerEntities::Unhandled, Some(declared)) => {
+                        TriggerEntities::Declared(declared.into_iter().collect())
+                    }
+                    (TriggerEntities::Declared(mut entities), Some(declared)) => {
+                        entities.extend(declared);
+                        TriggerEntities::Declared(entities)
+                    }
+                };
+                hosts.push(host.cheap_clone());
+            }
+            trigger_hosts.push(hosts);
+            trigger_entities.push(entities);
+        }
+        let groups = match parallel::partition(&trigger_entities) {
+            Some(groups) => groups,
+            None => return Ok(false),
+        };
+
+        // Each group starts out with what the block's cache has read from
+        // the store for the entities that the group uses
+        let group_idx: HashMap<&EntityType, usize> = groups
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, group)| group.entities.iter().map(move |entity| (entity, idx)))
+            .collect();
+        let caches = block_state
+            .entity_cache
+            .split_current(groups.len(), |entity_type| {
+                group_idx.get(entity_type).copied()
+            });
+
+        let trigger_hosts = &trigger_hosts;
+        let poi_version = self.inputs.poi_version;
+        let results = futures03::future::join_all(groups.iter().zip(caches).map(
+            |(group, cache)| async move {
+                let logs = LogBuffer::new();
+                let logger = logs.logger();
+                let mut state = BlockState::new(self.inputs.store.clone(), cache);
+                state.entity_cache.track_accessed_types();
+                state.defer_timings();
+                let mut pois = Vec::with_capacity(group.triggers.len());
+                for &idx in &group.triggers {
+                    let trigger = &triggers[idx];
+                    // Record the events of each trigger so that they can be
+                    // written in the order of the triggers later
+                    let poi = proof_of_indexing.as_ref().map(|_| {
+                        Arc::new(AtomicRefCell::new(ProofOfIndexing::recording(
+                            block.number(),
+                            poi_version,
+                        )))
+                    });
+                    let result = self
+                        .ctx
+                        .process_trigger_in_hosts(
+                            &logger,
+                            &trigger_hosts[idx],
+                            block,
+                            trigger,
+                            state,
+                            &poi,
+                            causality_region,
+                            &self.inputs.debug_fork,
+                            &self.metrics.subgraph,
+                            self.inputs.instrument,
+                        )
+                        .await
+                        .map_err(|mut e| {
+                            let error_context = trigger.error_context();
+                            if !error_context.is_empty() {
+                                e = e.context(error_context);
+                            }
+                            e.context("failed to process trigger".to_string())
+                        });
+                    state = match result {
+                        Ok(state) => state,
+                        Err(e) => return (Err(e), logs),
+                    };
+                    pois.push((idx, poi));
+                }
+                (Ok((state, pois)), logs)
+            },
+        ))
+        .await;
+
+        // The block is processed again after an error, and the logs of all
+        // groups help with figuring out what happened
+        let (results, logs): (Vec<_>, Vec<_>) = results.into_iter().unzip();
+        let mut results = match results.into_iter().collect::<Result<Vec<_>, _>>() {
+            Ok(results) => results,
+            Err(e) => {
+                for logs in logs {
+                    logs.replay(&self.logger);
+                }
+                return Err(e);
+            }
+        };
+
+        // Handlers could have used entities that their data source does
+        // not declare. Random bytes, new data sources and errors depend on
+        // the order of the triggers if more than one group has them
+        let undeclared = groups.iter().zip(&results).any(|(group, (state, _))| {
+            state
+                .entity_cache
+                .accessed_types()
+                .map_or(true, |accessed| {
+                    !accessed.iter().all(|ty| group.entities.contains(ty))
+                })
+        });
+        let count =
+            |f: fn(&BlockState<C>) -> bool| results.iter().filter(|(state, _)| f(state)).count();
+        if undeclared
+            || count(BlockState::uses_random_bytes) > 1
+            || count(BlockState::has_created_data_sources) > 1
+            || count(BlockState::has_errors) > 1
+        {
+            debug!(self.logger, "Processing triggers again one after the other";
+                "groups" => groups.len(),
+                "undeclared_entities" => undeclared);
+            for (state, _) in results {
+                block_state.extend_caches(state);
+            }
+            return Ok(false);
+        }
+
+        for logs in logs {
+            logs.replay(&self.logger);
+        }
+
+        if let Some(proof_of_indexing) = proof_of_indexing {
+            let mut pois: Vec<_> = results
+                .iter_mut()
+                .flat_map(|(_, pois)| pois.drain(..))
+                .collect();
+            pois.sort_by_key(|(idx, _)| *idx);
+            let mut proof_of_indexing = proof_of_indexing.borrow_mut();
+            for poi in pois.into_iter().filter_map(|(_, poi)| poi) {
+                let poi = Arc::try_unwrap(poi).unwrap().into_inner();
+                poi.replay(&self.logger, &mut proof_of_indexing);
+            }
+        }
+
+        for (mut state, _) in results {
+            state.observe_deferred_timings(&self.metrics.host, &self.metrics.subgraph);
+            block_state.extend(state);
+        }
+        Ok(true)
+    }
+
+    /// The entities that the sources of subgraph data sources created or
+    /// updated after the block that this subgraph processed last, up to and
+    /// including `block_ptr`. Waits for the sources to process `block_ptr`
+    /// first
+    async fn subgraph_triggers(
+        &self,
+        logger: &Logger,
+        block_ptr: &BlockPtr,
+        cancel_handle: &CancelHandle,
+    ) -> Result<Vec<subgraph::TriggerData>, BlockProcessingError> {
+        if self.inputs.subgraph_sources.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let last_block = self.inputs.store.block_ptr().map(|ptr| ptr.number);
+        let mut triggers = vec![];
+        for source in &self.inputs.subgraph_sources {
+            let first_block = match last_block {
+                Some(last_block) => (last_block + 1).max(source.start_block),
+                None => source.start_block,
+            };
+            if first_block > block_ptr.number {
+                continue;
+            }
+
+            self.wait_for_subgraph_source(logger, source, block_ptr, cancel_handle)
+                .await?;
+
+            for number in first_block..=block_ptr.number {
+                let changes = self
+                    .inputs
+                    .subgraph_store
+                    .entity_changes_in_block(&source.address, number)?;
+                triggers.extend(changes.into_iter().filter_map(|op| match op {
+                    EntityOperation::Set { key, data } => Some(subgraph::TriggerData {
+                        source: source.address.clone(),
+                        block: number,
+                        entity_type: key.entity_type,
+                        entity: data,
+                    }),
+                    EntityOperation::Remove { .. } => None,
+                }));
+            }
+        }
+        Ok(triggers)
+    }
+
+    async fn wait_for_subgraph_source(
+        &self,
+        logger: &Logger,
+        source: &subgraph::Source,
+        block_ptr: &BlockPtr,
+        cancel_handle: &CancelHandle,
+    ) -> Result<(), BlockProcessingError> {
+        loop {
+            let source_ptr = self
+                .inputs
+                .subgraph_store
+                .least_block_ptr(&source.address)
+                .await?;
+            if source_ptr
+                .as_ref()
+                .map_or(false, |ptr| ptr.number >= block_ptr.number)
+            {
+                return Ok(());
+            }
+            if cancel_handle.is_canceled() {
+                return Err(BlockProcessingError::Canceled);
+            }
+            debug!(logger, "Waiting for source subgraph to catch up";
+                "source" => source.address.as_str(),
+                "source_block" => source_ptr.as_ref().map(|ptr| ptr.number));
+            tokio::time::sleep(SUBGRAPH_SOURCE_POLL_INTERVAL).await;
+        }
+    }
+
     fn create_dynamic_data_sources(
         &mut self,
         created_data_sources: Vec<DataSourceTemplateInfo<C>>,
     ) -> Result<(Vec<DataSource<C>>, Vec<Arc<T::Host>>), Error> {
         let mut data_sources = vec![];
         let mut runtime_hosts = vec![];
+        let mut dynamic_count = self.ctx.dynamic_data_source_count();
 
         for info in created_data_sources {
             // Try to instantiate a data source from the template
@@ -551,6 +1054,22 @@ where
                 }
             };
 
+            // Creating data sources without bound would make the subgraph
+            // use more and more memory. The limit is set by the operator of
+            // this node, and other nodes might not have one, so exceeding it
+            // must not fail the subgraph deterministically; like for
+            // `GRAPH_SUBGRAPH_MAX_DATA_SOURCES`, the subgraph is retried
+            // until the limit is raised
+            if let Some(max) = self.inputs.max_dynamic_data_sources {
+                if dynamic_count >= max {
+                    anyhow::bail!(
+                        "limit of {} dynamic data sources exceeded when creating data source `{}`",
+                        max,
+                        data_source.name()
+                    );
+                }
+            }
+
             // Try to create a runtime host for the data source
             let host = self
                 .ctx
@@ -558,6 +1077,7 @@ where
 
             match host {
                 Some(host) => {
+                    dynamic_count += 1;
                     data_sources.push(data_source);
                     runtime_hosts.push(host);
                 }
@@ -639,13 +1159,75 @@ where
         Ok(action)
     }
 
+    /// Periodically record how far behind the chain head the deployment is
+    /// and how much of its time it spends processing blocks so that the
+    /// deployment scheduler can take that into account. Failing to report
+    /// the load is not a reason to stop indexing
+    async fn maybe_report_load(&mut self, block_ptr: &BlockPtr, processing_time: Duration) {
+        self.state.handler_time += processing_time;
+        let report_block = *self.state.load_report_block.get_or_insert(block_ptr.number);
+
+        let elapsed = self.state.load_report_timer.elapsed();
+        if elapsed < ENV_VARS.deployment_load_report_interval {
+            return;
+        }
+
+        let blocks_behind = match self.inputs.chain.chain_store().cached_head_ptr().await {
+            Ok(Some(head)) => (head.number - block_ptr.number).max(0),
+            Ok(None) => 0,
+            Err(e) => {
+                warn!(self.logger, "Failed to get chain head to report deployment load";
+                      "error" => e.to_string());
+                return;
+            }
+        };
+        let load = DeploymentLoad {
+            blocks_behind,
+            handler_time: (self.state.handler_time.as_secs_f64() / elapsed.as_secs_f64()).min(1.0),
+            blocks_per_second: (block_ptr.number - report_block).max(0) as f64
+                / elapsed.as_secs_f64(),
+        };
+        if let Err(e) = self.inputs.store.report_load(load) {
+            warn!(self.logger, "Failed to report deployment load";
+                  "error" => e.to_string());
+        }
+
+        // Only notify when the deployment starts lagging, not every time
+        // the load is reported while it is behind
+        if let Some(threshold) = ENV_VARS.notification_lag_threshold {
+            let lagging = blocks_behind > threshold;
+            if lagging && !self.state.lagging {
+                self.notify(DeploymentEvent::Lagging { blocks_behind });
+            }
+            self.state.lagging = lagging;
+        }
+
+        self.state.handler_time = Duration::ZERO;
+        self.state.load_report_timer = Instant::now();
+        self.state.load_report_block = Some(block_ptr.number);
+    }
+
+    fn notify(&self, event: DeploymentEvent) {
+        self.inputs
+            .notifier
+            .notify(&self.inputs.deployment.hash, event);
+    }
+
     async fn handle_offchain_triggers(
         &mut self,
         triggers: Vec<offchain::TriggerData>,
         block: &Arc<C::Block>,
-    ) -> Result<(Vec<EntityModification>, Vec<StoredDynamicDataSource>), Error> {
+    ) -> Result<
+        (
+            Vec<EntityModification>,
+            Vec<StoredDynamicDataSource>,
+            Vec<(String, f64)>,
+        ),
+        Error,
+    > {
         let mut mods = vec![];
         let mut processed_data_sources = vec![];
+        let mut metric_increments = vec![];
 
         for trigger in triggers {
             // Using an `EmptyStore` and clearing the cache for each trigger is a makeshift way to
@@ -692,11 +1274,12 @@ where
                 return Err(anyhow!("{}", err.to_string()));
             }
 
+            metric_increments.extend(block_state.drain_metric_increments());
             mods.extend(block_state.entity_cache.as_modifications()?.modifications);
             processed_data_sources.extend(block_state.processed_data_sources);
         }
 
-        Ok((mods, processed_data_sources))
+        Ok((mods, processed_data_sources, metric_increments))
     }
 }
 
@@ -768,15 +1351,18 @@ where
             self.state.skip_ptr_updates_timer = Instant::now();
         }
 
+        self.throttle().await;
         let start = Instant::now();
 
-        let res = self.process_block(cancel_handle, block, cursor).await;
+        let span = telemetry::block_span(&self.inputs.deployment.hash, &block_ptr);
+        let res = telemetry::in_span(span, self.process_block(cancel_handle, block, cursor)).await;
 
-        let elapsed = start.elapsed().as_secs_f64();
+        let elapsed = start.elapsed();
         self.metrics
             .subgraph
             .block_processing_duration
-            .observe(elapsed);
+            .observe(elapsed.as_secs_f64());
+        self.maybe_report_load(&block_ptr, elapsed).await;
 
         match res {
             Ok(action) => {
@@ -793,7 +1379,11 @@ where
                     // Updating the sync status is an one way operation.
                     // This state change exists: not synced -> synced
                     // This state change does NOT: synced -> not synced
+                    let newly_synced = !self.inputs.store.is_deployment_synced().await?;
                     self.inputs.store.deployment_synced()?;
+                    if newly_synced {
+                        self.notify(DeploymentEvent::Synced);
+                    }
 
                     // Stop trying to update the sync status.
                     self.state.synced = true;
@@ -817,6 +1407,10 @@ where
                         self.state.should_try_unfail_non_deterministic = false;
                         self.metrics.stream.deployment_failed.set(0.0);
                         self.state.backoff.reset();
+                        if let Err(e) = self.inputs.store.set_retry_count(0).await {
+                            warn!(self.logger, "Failed to reset retry count"; "error" => e.to_string());
+                        }
+                        self.notify(DeploymentEvent::Healthy);
                     }
                 }
 
@@ -859,6 +1453,11 @@ where
                     deterministic,
                 };
 
+                let failed = DeploymentEvent::Failed {
+                    error: error.message.clone(),
+                    deterministic,
+                };
+
                 match deterministic {
                     true => {
                         // Fail subgraph:
@@ -869,6 +1468,7 @@ where
                             .fail_subgraph(error)
                             .await
                             .context("Failed to set subgraph status to `failed`")?;
+                        self.notify(failed);
 
                         return Err(err);
                     }
@@ -890,6 +1490,7 @@ where
                                 .fail_subgraph(error)
                                 .await
                                 .context("Failed to set subgraph status to `failed`")?;
+                            self.notify(failed);
                         }
 
                         // Retry logic below:
@@ -898,12 +1499,26 @@ where
                         self.ctx.instances.remove(&self.inputs.deployment.id);
 
                         let message = format!("{:#}", e).replace('\n', "\t");
+                        // Give up and leave the deployment failed once the
+                        // retry policy is exhausted
+                        if let Some(max_attempts) = self.inputs.retry_policy.max_attempts {
+                            if self.state.backoff.attempt >= max_attempts as u64 {
+                                error!(self.logger, "Giving up on subgraph after non-deterministic error: {}", message;
+                                    "attempts" => self.state.backoff.attempt,
+                                    "max_attempts" => max_attempts);
+                                return Err(err);
+                            }
+                        }
                         error!(self.logger, "Subgraph failed with non-deterministic error: {}", message;
                             "attempt" => self.state.backoff.attempt,
                             "retry_delay_s" => self.state.backoff.delay().as_secs());
 
                         // Sleep before restarting.
                         self.state.backoff.sleep_async().await;
+                        self.inputs
+                            .store
+                            .set_retry_count(self.state.backoff.attempt as u32)
+                            .await?;
 
                         self.state.should_try_unfail_non_deterministic = true;
 
@@ -920,6 +1535,17 @@ where
         revert_to_ptr: BlockPtr,
         cursor: FirehoseCursor,
     ) -> Result<Action, Error> {
+        // The blocks of a batch that was not written yet are processed again
+        // from the last block that was written
+        if self.state.batched_cache.is_some() {
+            info!(&self.logger, "Discarding unwritten batch of blocks to revert";
+                  "blocks" => self.state.batched_blocks, "revert_to_ptr" => &revert_to_ptr);
+            self.state.batched_cache = None;
+            self.state.batched_blocks = 0;
+            self.state.batched_metric_increments.clear();
+            return Ok(Action::Restart);
+        }
+
         // Current deployment head in the database / WritableAgent Mutex cache.
         //
         // Safe unwrap because in a Revert event we're sure the subgraph has
@@ -982,6 +1608,7 @@ where
 /// inserted when as_modifications is called.
 async fn update_proof_of_indexing(
     proof_of_indexing: ProofOfIndexing,
+    transition_version: Option<ProofOfIndexingVersion>,
     stopwatch: &StopwatchMetrics,
     entity_cache: &mut EntityCache,
 ) -> Result<(), Error> {
@@ -989,44 +1616,69 @@ async fn update_proof_of_indexing(
 
     let mut proof_of_indexing = proof_of_indexing.take();
 
-    for (causality_region, stream) in proof_of_indexing.drain() {
-        // Create the special POI entity key specific to this causality_region
-        let entity_key = EntityKey {
-            entity_type: POI_OBJECT.to_owned(),
+    for (causality_region, mut stream) in proof_of_indexing.drain() {
+        // The digest for the transition version is kept in its own entity
+        // so that the digest of the deployment's version stays as it was
+        if let Some(version) = transition_version {
+            let entity_key = poi_entity_key(version.transition_id(&causality_region));
+            let prev_poi = poi_digest(entity_cache, &entity_key)?;
+            if let Some(digest) = stream.pause_transition(prev_poi.as_deref()) {
+                set_poi_digest(entity_cache, entity_key, digest)?;
+            }
+        }
 
-            // There are two things called causality regions here, one is the causality region for
-            // the poi which is a string and the PoI entity id. The other is the data source
-            // causality region to which the PoI belongs as an entity. Currently offchain events do
-            // not affect PoI so it is assumed to be `ONCHAIN`.
-            // See also: poi-ignores-offchain
-            entity_id: causality_region.into(),
-            causality_region: CausalityRegion::ONCHAIN,
-        };
+        let entity_key = poi_entity_key(causality_region);
 
         // Grab the current digest attribute on this entity
-        let prev_poi =
-            entity_cache
-                .get(&entity_key)
-                .map_err(Error::from)?
-                .map(|entity| match entity.get("digest") {
-                    Some(Value::Bytes(b)) => b.clone(),
-                    _ => panic!("Expected POI entity to have a digest and for it to be bytes"),
-                });
+        let prev_poi = poi_digest(entity_cache, &entity_key)?;
 
         // Finish the POI stream, getting the new POI value.
         let updated_proof_of_indexing = stream.pause(prev_poi.as_deref());
-        let updated_proof_of_indexing: Bytes = (&updated_proof_of_indexing[..]).into();
 
-        // Put this onto an entity with the same digest attribute
-        // that was expected before when reading.
-        let new_poi_entity = entity! {
-            id: entity_key.entity_id.to_string(),
-            digest: updated_proof_of_indexing,
-        };
+        set_poi_digest(entity_cache, entity_key, updated_proof_of_indexing)?;
+    }
 
-        entity_cache.set(entity_key, new_poi_entity)?;
+    Ok(())
+}
+
+/// The key of the special POI entity with id `id`
+fn poi_entity_key(id: String) -> EntityKey {
+    EntityKey {
+        entity_type: POI_OBJECT.to_owned(),
+
+        // There are two things called causality regions here, one is the causality region for
+        // the poi which is a string and the PoI entity id. The other is the data source
+        // causality region to which the PoI belongs as an entity. Currently offchain events do
+        // not affect PoI so it is assumed to be `ONCHAIN`.
+        // See also: poi-ignores-offchain
+        entity_id: id.into(),
+        causality_region: CausalityRegion::ONCHAIN,
     }
+}
+
+fn poi_digest(entity_cache: &mut EntityCache, key: &EntityKey) -> Result<Option<Bytes>, Error> {
+    Ok(entity_cache
+        .get(key)
+        .map_err(Error::from)?
+        .map(|entity| match entity.get("digest") {
+            Some(Value::Bytes(b)) => b.clone(),
+            _ => panic!("Expected POI entity to have a digest and for it to be bytes"),
+        }))
+}
 
+fn set_poi_digest(
+    entity_cache: &mut EntityCache,
+    key: EntityKey,
+    digest: Vec<u8>,
+) -> Result<(), Error> {
+    // Put this onto an entity with the same digest attribute
+    // that was expected before when reading.
+    let new_poi_entity = entity! {
+        id: key.entity_id.to_string(),
+        digest: Bytes::from(&digest[..]),
+    };
+
+    entity_cache.set(key, new_poi_entity)?;
     Ok(())
 }
 
@@ -1071,3 +1723,23 @@ fn test_close_to_chain_head() {
     assert!(close_to_chain_head(&block_1, Some(block_2.clone()), offset));
     assert!(close_to_chain_head(&block_2, Some(block_2.clone()), offset));
 }
+
+#[test]
+fn test_retry_backoff() {
+    let policy = RetryPolicy {
+        max_attempts: Some(5),
+        base: Some(Duration::from_secs(10)),
+        ceiling: Some(Duration::from_secs(100)),
+    };
+
+    let backoff = retry_backoff(&policy, 0, &ENV_VARS);
+    assert_eq!(0, backoff.attempt);
+
+    // A restarted deployment picks up where it left off, so that
+    // `max_attempts` still applies and the delay keeps growing
+    let backoff = retry_backoff(&policy, 3, &ENV_VARS);
+    assert_eq!(3, backoff.attempt);
+    let delay = backoff.delay().as_secs_f64();
+    let jitter = ENV_VARS.subgraph_error_retry_jitter;
+    assert!(delay >= 80.0 * (1.0 - jitter) && delay <= 80.0 * (1.0 + jitter));
+}
@@ -1,9 +1,9 @@
 use graph::{
     components::store::EntityKey,
-    prelude::Entity,
+    prelude::{BlockNumber, Entity, EntityCache},
     util::{backoff::ExponentialBackoff, lfu_cache::LfuCache},
 };
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 pub struct IndexingState {
     /// `true` -> `false` on the first run
@@ -18,5 +18,25 @@ pub struct IndexingState {
     /// - The time THRESHOLD is passed
     /// - Or the subgraph has triggers for the block
     pub skip_ptr_updates_timer: Instant,
+    /// Time spent processing blocks since the load of the deployment was
+    /// last reported
+    pub handler_time: Duration,
+    /// Resets to `Instant::now` every time the load is reported
+    pub load_report_timer: Instant,
+    /// The block the deployment was at when the load was last reported
+    pub load_report_block: Option<BlockNumber>,
+    /// Whether the deployment was more than the lag threshold behind the
+    /// chain head when the load was last reported
+    pub lagging: bool,
     pub entity_lfu_cache: LfuCache<EntityKey, Option<Entity>>,
+    /// When processing of the last block started, used to limit how many
+    /// blocks per second the deployment processes
+    pub last_block_start: Instant,
+    /// The changes of the blocks that will be written together with the
+    /// changes of the next block
+    pub batched_cache: Option<EntityCache>,
+    /// The number of blocks whose changes are in `batched_cache`
+    pub batched_blocks: usize,
+    /// The increments of mapping counters of the blocks in `batched_cache`
+    pub batched_metric_increments: Vec<(String, f64)>,
 }
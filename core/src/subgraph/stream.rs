@@ -1,13 +1,12 @@
 use crate::subgraph::inputs::IndexingInputs;
 use graph::blockchain::block_stream::{BlockStream, BufferedBlockStream};
+use graph::blockchain::chaos_block_stream::ChaosBlockStream;
 use graph::blockchain::Blockchain;
-use graph::prelude::{CheapClone, Error, SubgraphInstanceMetrics};
+use graph::prelude::{warn, CheapClone, Error, Logger, SubgraphInstanceMetrics, ENV_VARS};
 use std::sync::Arc;
 
-const BUFFERED_BLOCK_STREAM_SIZE: usize = 100;
-const BUFFERED_FIREHOSE_STREAM_SIZE: usize = 1;
-
 pub async fn new_block_stream<C: Blockchain>(
+    logger: &Logger,
     inputs: &IndexingInputs<C>,
     filter: &C::TriggerFilter,
     metrics: &SubgraphInstanceMetrics,
@@ -15,8 +14,8 @@ pub async fn new_block_stream<C: Blockchain>(
     let is_firehose = inputs.chain.chain_client().is_firehose();
 
     let buffer_size = match is_firehose {
-        true => BUFFERED_FIREHOSE_STREAM_SIZE,
-        false => BUFFERED_BLOCK_STREAM_SIZE,
+        true => ENV_VARS.firehose_block_stream_buffer_size,
+        false => ENV_VARS.block_stream_buffer_size,
     };
 
     let block_stream = inputs
@@ -32,9 +31,26 @@ pub async fn new_block_stream<C: Blockchain>(
     if is_firehose && block_stream.is_err() {
         metrics.firehose_connection_errors.inc();
     }
+    let mut block_stream = block_stream?;
+
+    let namespace = format!("sgd{}", inputs.deployment.id);
+    if ENV_VARS.chaos_revert_deployments.contains(&namespace) {
+        warn!(logger, "Injecting synthetic reverts into the block stream";
+              "probability" => ENV_VARS.chaos_revert_probability,
+              "max_depth" => ENV_VARS.chaos_revert_max_depth);
+        block_stream = Box::new(ChaosBlockStream::new(
+            logger.clone(),
+            block_stream,
+            inputs.triggers_adapter.cheap_clone(),
+            Arc::new(filter.clone()),
+            ENV_VARS.chaos_revert_probability,
+            ENV_VARS.chaos_revert_max_depth,
+        ));
+    }
 
     Ok(BufferedBlockStream::spawn_from_stream(
-        block_stream?,
+        block_stream,
         buffer_size,
+        metrics.block_stream_buffered_blocks.clone(),
     ))
 }
@@ -63,6 +63,7 @@ where
         }
 
         for (host, mapping_trigger) in host_mapping {
+            let handler = mapping_trigger.handler_name().to_string();
             let start = Instant::now();
             state = host
                 .process_mapping_trigger(
@@ -76,7 +77,7 @@ where
                 )
                 .await?;
             let elapsed = start.elapsed().as_secs_f64();
-            subgraph_metrics.observe_trigger_processing_duration(elapsed);
+            state.observe_trigger_processing_duration(subgraph_metrics, elapsed, &handler);
 
             if let Some(ds) = host.data_source().as_offchain() {
                 ds.mark_processed_at(block.number());
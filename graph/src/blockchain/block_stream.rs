@@ -19,23 +19,36 @@ pub struct BufferedBlockStream<C: Blockchain> {
 }
 
 impl<C: Blockchain + 'static> BufferedBlockStream<C> {
+    /// Poll `stream` in a separate task that fetches up to `size_hint`
+    /// events ahead of the consumer. The number of events that are waiting
+    /// to be consumed is reported in `buffered`
     pub fn spawn_from_stream(
         stream: Box<dyn BlockStream<C>>,
         size_hint: usize,
+        buffered: Gauge,
     ) -> Box<dyn BlockStream<C>> {
         let (sender, receiver) = mpsc::channel::<Result<BlockStreamEvent<C>, Error>>(size_hint);
-        crate::spawn(async move { BufferedBlockStream::stream_blocks(stream, sender).await });
+        // Events that a previous stream had buffered were dropped with it
+        buffered.set(0.0);
+        let sender_buffered = buffered.clone();
+        crate::spawn(async move {
+            BufferedBlockStream::stream_blocks(stream, sender, sender_buffered).await
+        });
 
-        Box::new(BufferedBlockStream::new(receiver))
+        Box::new(BufferedBlockStream::new(receiver, buffered))
     }
 
-    pub fn new(mut receiver: Receiver<Result<BlockStreamEvent<C>, Error>>) -> Self {
+    pub fn new(
+        mut receiver: Receiver<Result<BlockStreamEvent<C>, Error>>,
+        buffered: Gauge,
+    ) -> Self {
         let inner = stream! {
             loop {
                 let event = match receiver.recv().await {
                     Some(evt) => evt,
                     None => return,
                 };
+                buffered.dec();
 
                 yield event
             }
@@ -49,15 +62,18 @@ impl<C: Blockchain + 'static> BufferedBlockStream<C> {
     pub async fn stream_blocks(
         mut stream: Box<dyn BlockStream<C>>,
         sender: Sender<Result<BlockStreamEvent<C>, Error>>,
+        buffered: Gauge,
     ) -> Result<(), Error> {
         while let Some(event) = stream.next().await {
+            buffered.inc();
             match sender.send(event).await {
                 Ok(_) => continue,
                 Err(err) => {
+                    buffered.dec();
                     return Err(anyhow!(
                         "buffered blockstream channel is closed, stopping. Err: {}",
                         err
-                    ))
+                    ));
                 }
             }
         }
@@ -448,6 +464,7 @@ mod test {
     use crate::{
         blockchain::mock::{MockBlock, MockBlockchain},
         ext::futures::{CancelableError, SharedCancelGuard, StreamExtension},
+        prelude::Gauge,
     };
 
     use super::{
@@ -491,9 +508,11 @@ mod test {
         });
         let guard = SharedCancelGuard::new();
 
-        let mut stream = BufferedBlockStream::spawn_from_stream(stream, buffer_size)
-            .map_err(CancelableError::Error)
-            .cancelable(&guard, || Err(CancelableError::Cancel));
+        let buffered = Gauge::new("buffered_blocks", "buffered blocks").unwrap();
+        let mut stream =
+            BufferedBlockStream::spawn_from_stream(stream, buffer_size, buffered.clone())
+                .map_err(CancelableError::Error)
+                .cancelable(&guard, || Err(CancelableError::Cancel));
 
         let mut blocks = HashSet::<MockBlock>::new();
         let mut count = 0;
@@ -523,5 +542,7 @@ mod test {
             count
         );
         assert_eq!(count, blocks.len(), "should not have duplicated blocks");
+        // The buffer is full, and one more block waits to be sent
+        assert!(buffered.get() <= buffer_size as f64 + 1.0);
     }
 }
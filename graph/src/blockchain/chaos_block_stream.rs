@@ -0,0 +1,214 @@
+//! A block stream that injects synthetic reverts into the blocks of another
+//! block stream to test how a deployment handles reorgs. After a block, the
+//! stream randomly reverts to one of the blocks before it and then sends
+//! the blocks after that block again. Since the blocks are the same as the
+//! ones that were reverted, a deployment that handles reverts correctly
+//! ends up with the same proofs of indexing as one that never saw a revert.
+//!
+//! The triggers of the blocks that are sent again are found with the
+//! triggers adapter of the chain, just like for blocks after a real reorg.
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use async_stream::try_stream;
+use futures03::{Stream, StreamExt};
+use rand::Rng;
+
+use super::block_stream::{BlockStream, BlockStreamEvent, FirehoseCursor};
+use super::{Block, Blockchain, TriggersAdapter};
+use crate::prelude::*;
+
+pub struct ChaosBlockStream<C: Blockchain> {
+    stream: Pin<Box<dyn Stream<Item = Result<BlockStreamEvent<C>, Error>> + Send>>,
+}
+
+impl<C: Blockchain> ChaosBlockStream<C> {
+    /// Inject a revert after each block of `stream` with `probability`.
+    /// Reverts go back at most `max_depth` blocks
+    pub fn new(
+        logger: Logger,
+        stream: Box<dyn BlockStream<C>>,
+        adapter: Arc<dyn TriggersAdapter<C>>,
+        filter: Arc<C::TriggerFilter>,
+        probability: f64,
+        max_depth: usize,
+    ) -> Self {
+        ChaosBlockStream {
+            stream: Box::pin(stream_blocks(
+                logger,
+                stream,
+                adapter,
+                filter,
+                probability,
+                max_depth,
+            )),
+        }
+    }
+}
+
+fn stream_blocks<C: Blockchain>(
+    logger: Logger,
+    mut stream: Box<dyn BlockStream<C>>,
+    adapter: Arc<dyn TriggersAdapter<C>>,
+    filter: Arc<C::TriggerFilter>,
+    probability: f64,
+    max_depth: usize,
+) -> impl Stream<Item = Result<BlockStreamEvent<C>, Error>> {
+    // The last blocks that were sent, oldest first, with their cursors
+    let mut recent: VecDeque<(C::Block, FirehoseCursor)> = VecDeque::new();
+
+    try_stream! {
+        while let Some(event) = stream.next().await {
+            let (block, cursor) = match event? {
+                BlockStreamEvent::ProcessBlock(block, cursor) => {
+                    let recorded = (block.block.clone(), cursor.clone());
+                    yield BlockStreamEvent::ProcessBlock(block, cursor);
+                    recorded
+                }
+                BlockStreamEvent::Revert(ptr, cursor) => {
+                    recent.retain(|(block, _)| block.number() <= ptr.number);
+                    yield BlockStreamEvent::Revert(ptr, cursor);
+                    continue;
+                }
+            };
+
+            recent.push_back((block, cursor));
+            while recent.len() > max_depth + 1 {
+                recent.pop_front();
+            }
+            if recent.len() < 2 || !rand::thread_rng().gen_bool(probability) {
+                continue;
+            }
+
+            // Revert `depth` blocks and send them again
+            let depth = rand::thread_rng().gen_range(1..recent.len());
+            let (target, target_cursor) = &recent[recent.len() - 1 - depth];
+            let target = target.ptr();
+            info!(logger, "Injecting a synthetic revert";
+                  "revert_to" => &target,
+                  "head" => recent.back().map(|(block, _)| block.number()));
+            yield BlockStreamEvent::Revert(target, target_cursor.clone());
+
+            let replay: Vec<_> = recent.iter().skip(recent.len() - depth).cloned().collect();
+            for (block, cursor) in replay {
+                let block = adapter.triggers_in_block(&logger, block, &filter).await?;
+                yield BlockStreamEvent::ProcessBlock(block, cursor);
+            }
+        }
+    }
+}
+
+impl<C: Blockchain> Stream for ChaosBlockStream<C> {
+    type Item = Result<BlockStreamEvent<C>, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.stream.poll_next_unpin(cx)
+    }
+}
+
+impl<C: Blockchain> BlockStream<C> for ChaosBlockStream<C> {}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+
+    use futures03::{Stream, StreamExt};
+
+    use crate::blockchain::block_stream::{
+        BlockStream, BlockStreamEvent, BlockWithTriggers, FirehoseCursor,
+    };
+    use crate::blockchain::mock::{
+        MockBlock, MockBlockchain, MockTriggerFilter, MockTriggersAdapter,
+    };
+    use crate::log::discard;
+
+    use super::ChaosBlockStream;
+
+    /// A block stream that sends the blocks `0..count`
+    struct Blocks(VecDeque<BlockStreamEvent<MockBlockchain>>);
+
+    impl Blocks {
+        fn new(count: u64) -> Self {
+            let events = (0..count)
+                .map(|number| {
+                    BlockStreamEvent::ProcessBlock(
+                        BlockWithTriggers {
+                            block: MockBlock { number },
+                            trigger_data: vec![],
+                        },
+                        FirehoseCursor::None,
+                    )
+                })
+                .collect();
+            Blocks(events)
+        }
+    }
+
+    impl Stream for Blocks {
+        type Item = Result<BlockStreamEvent<MockBlockchain>, anyhow::Error>;
+
+        fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            Poll::Ready(self.0.pop_front().map(Ok))
+        }
+    }
+
+    impl BlockStream<MockBlockchain> for Blocks {}
+
+    /// The events of a chaos stream over `count` blocks as `(true, number)`
+    /// for processing and `(false, number)` for reverting to a block
+    async fn events(count: u64, probability: f64, max_depth: usize) -> Vec<(bool, i32)> {
+        let stream = ChaosBlockStream::new(
+            discard(),
+            Box::new(Blocks::new(count)),
+            Arc::new(MockTriggersAdapter),
+            Arc::new(MockTriggerFilter),
+            probability,
+            max_depth,
+        );
+        stream
+            .map(|event| match event.unwrap() {
+                BlockStreamEvent::ProcessBlock(block, _) => (true, block.block.number as i32),
+                BlockStreamEvent::Revert(ptr, _) => (false, ptr.number),
+            })
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn without_reverts() {
+        let expected: Vec<_> = (0..10).map(|number| (true, number)).collect();
+        assert_eq!(expected, events(10, 0.0, 3).await);
+    }
+
+    #[tokio::test]
+    async fn reverts_and_replays_blocks() {
+        const MAX_DEPTH: usize = 3;
+
+        let events = events(20, 1.0, MAX_DEPTH).await;
+
+        // Every revert goes back at most `MAX_DEPTH` blocks, and the
+        // blocks after it are sent again in order
+        let mut head: Option<i32> = None;
+        for (process, number) in &events {
+            match (process, head) {
+                (true, None) => assert_eq!(0, *number),
+                (true, Some(head)) => assert_eq!(head + 1, *number),
+                (false, head) => {
+                    let head = head.unwrap();
+                    assert!(*number < head);
+                    assert!(head - number <= MAX_DEPTH as i32);
+                }
+            }
+            head = Some(*number);
+        }
+        assert_eq!(Some(19), head);
+        // With a probability of 1, there is a revert after every block
+        // once there is a block to revert to
+        assert_eq!(19, events.iter().filter(|(process, _)| !process).count());
+    }
+}
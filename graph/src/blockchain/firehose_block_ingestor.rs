@@ -2,9 +2,11 @@ use std::{marker::PhantomData, sync::Arc, time::Duration};
 
 use crate::{
     blockchain::Block as BlockchainBlock,
+    components::metrics::ingestors::{IngestorHealth, INGESTOR_HEALTH},
     components::store::ChainStore,
+    env::ENV_VARS,
     firehose::{self, decode_firehose_block, HeaderOnly},
-    prelude::{error, info, Logger},
+    prelude::{error, info, warn, BlockNumber, Logger},
     util::backoff::ExponentialBackoff,
 };
 use anyhow::{Context, Error};
@@ -20,6 +22,10 @@ use super::{client::ChainClient, BlockIngestor, Blockchain};
 const TRANSFORM_ETHEREUM_HEADER_ONLY: &str =
     "type.googleapis.com/sf.ethereum.transform.v1.HeaderOnly";
 
+/// Remove old blocks from the chain store whenever the number of an
+/// ingested block is a multiple of this
+const CLEANUP_INTERVAL: BlockNumber = 100;
+
 pub enum Transforms {
     EthereumHeaderOnly,
 }
@@ -44,6 +50,12 @@ where
     logger: Logger,
     default_transforms: Vec<Transforms>,
     chain_name: String,
+    /// How many blocks before the chain head the chain store should have
+    ancestor_count: BlockNumber,
+    /// Whether to remove blocks that are older than `ancestor_count`
+    /// and that no deployment needs anymore
+    cleanup_blocks: bool,
+    health: Arc<IngestorHealth>,
 
     phantom: PhantomData<M>,
 }
@@ -64,7 +76,10 @@ where
             logger,
             phantom: PhantomData {},
             default_transforms: vec![],
+            health: INGESTOR_HEALTH.chain(&chain_name),
             chain_name,
+            ancestor_count: ENV_VARS.reorg_threshold,
+            cleanup_blocks: false,
         }
     }
 
@@ -73,6 +88,49 @@ where
         self
     }
 
+    /// Periodically remove blocks from the chain store that are more than
+    /// the reorg threshold behind the chain head and older than the head
+    /// of any deployment on the chain
+    pub fn with_cleanup(mut self, cleanup_blocks: bool) -> Self {
+        self.cleanup_blocks = cleanup_blocks;
+        self
+    }
+
+    /// The block at which to start streaming when resuming from `cursor`.
+    /// Without a cursor, nothing has been ingested yet, and streaming
+    /// starts `ancestor_count` blocks before the chain head so that the
+    /// chain store has the recent blocks that block streams and queries by
+    /// block hash need. Negative numbers are relative to the chain head,
+    /// with -1 being the head itself
+    fn start_block_num(&self, cursor: &str) -> i64 {
+        if cursor.is_empty() {
+            -(self.ancestor_count as i64) - 1
+        } else {
+            -1
+        }
+    }
+
+    fn cleanup_cached_blocks(&self) {
+        match self.chain_store.cleanup_cached_blocks(self.ancestor_count) {
+            Ok(Some((min_block, count))) => {
+                if count > 0 {
+                    info!(
+                        self.logger,
+                        "Cleaned {} blocks from the block cache. \
+                         Only blocks with number greater than {} remain",
+                        count,
+                        min_block
+                    );
+                }
+            }
+            Ok(None) => { /* nothing was cleaned, ignore */ }
+            Err(e) => warn!(
+                self.logger,
+                "Failed to clean blocks from block cache: {}", e
+            ),
+        }
+    }
+
     async fn fetch_head_cursor(&self) -> String {
         let mut backoff =
             ExponentialBackoff::new(Duration::from_millis(250), Duration::from_secs(30));
@@ -120,6 +178,7 @@ where
 
                     if let Err(e) = result {
                         error!(self.logger, "Process block failed: {:#}", e);
+                        self.health.observe_error(&format!("{:#}", e));
                         break;
                     }
 
@@ -130,6 +189,7 @@ where
                         self.logger,
                         "An error occurred while streaming blocks: {}", e
                     );
+                    self.health.observe_error(&e.to_string());
                     break;
                 }
             }
@@ -146,13 +206,19 @@ where
         let block = decode_firehose_block::<M>(response)
             .context("Mapping firehose block to blockchain::Block")?;
 
-        trace!(self.logger, "Received new block to ingest {}", block.ptr());
+        let ptr = block.ptr();
+        trace!(self.logger, "Received new block to ingest {}", ptr);
 
         self.chain_store
             .clone()
             .set_chain_head(block, response.cursor.clone())
             .await
             .context("Updating chain head")?;
+        self.health.observe_block(ptr.clone(), None);
+
+        if self.cleanup_blocks && ptr.number % CLEANUP_INTERVAL == 0 {
+            self.cleanup_cached_blocks();
+        }
 
         Ok(())
     }
@@ -176,6 +242,7 @@ where
                         self.logger,
                         "Unable to get a connection for block ingestor, err: {}", err
                     );
+                    self.health.observe_error(&err.to_string());
                     backoff.sleep_async().await;
                     continue;
                 }
@@ -184,6 +251,7 @@ where
             let logger = self.logger.new(
                 o!("provider" => endpoint.provider.to_string(), "network_name"=> self.network_name()),
             );
+            self.health.set_provider(endpoint.provider.as_str());
 
             info!(
                 logger,
@@ -193,8 +261,9 @@ where
             let result = endpoint
                 .clone()
                 .stream_blocks(firehose::Request {
-                    // Starts at current HEAD block of the chain (viewed from Firehose side)
-                    start_block_num: -1,
+                    // Starts at current HEAD block of the chain (viewed from Firehose side),
+                    // or before it if nothing has been ingested yet
+                    start_block_num: self.start_block_num(&latest_cursor),
                     cursor: latest_cursor.clone(),
                     final_blocks_only: false,
                     transforms: self.default_transforms.iter().map(|t| t.into()).collect(),
@@ -211,6 +280,7 @@ where
                 }
                 Err(e) => {
                     error!(logger, "Unable to connect to endpoint: {:#}", e);
+                    self.health.observe_error(&format!("{:#}", e));
                 }
             }
 
@@ -7,11 +7,11 @@ use crate::prelude::*;
 use crate::util::backoff::ExponentialBackoff;
 use crate::{firehose, firehose::FirehoseEndpoint};
 use async_stream::try_stream;
+use futures03::stream::BoxStream;
 use futures03::{Stream, StreamExt};
 use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::{Duration, Instant};
-use tonic::Status;
 
 struct FirehoseBlockStreamMetrics {
     deployment: DeploymentHash,
@@ -244,12 +244,23 @@ fn stream_blocks<C: Blockchain, F: FirehoseMapper<C>>(
             }
 
             let mut connect_start = Instant::now();
-            let req = endpoint.clone().stream_blocks(request);
-            let result = tokio::time::timeout(Duration::from_secs(120), req).await.map_err(|x| x.into()).and_then(|x| x);
+            let shared = endpoint.shared_streams().subscribe(&logger, &endpoint, &request);
+            let is_shared = shared.is_some();
+            let result: Result<BoxStream<'static, firehose::SharedResponse>, Error> = match shared {
+                Some(stream) => Ok(stream),
+                None => {
+                    let req = endpoint.clone().stream_blocks(request.clone());
+                    tokio::time::timeout(Duration::from_secs(120), req)
+                        .await
+                        .map_err(|x| x.into())
+                        .and_then(|x| x)
+                        .map(|stream| stream.map(|response| response.map(Arc::new)).boxed())
+                }
+            };
 
             match result {
                 Ok(stream) => {
-                    info!(&logger, "Blockstream connected");
+                    info!(&logger, "Blockstream connected"; "shared" => is_shared);
 
                     // Track the time it takes to set up the block stream
                     metrics.observe_successful_connection(&mut connect_start, &endpoint.provider);
@@ -278,6 +289,15 @@ fn stream_blocks<C: Blockchain, F: FirehoseMapper<C>>(
                                 yield event;
 
                                 latest_cursor = FirehoseCursor::from(cursor);
+
+                                // Switch to a shared stream as soon as this deployment
+                                // reaches one
+                                if !is_shared && endpoint.shared_streams().can_join(&request, &latest_cursor.to_string()) {
+                                    info!(&logger, "Switching to shared blockstream");
+                                    skip_backoff = true;
+                                    expected_stream_end = true;
+                                    break;
+                                }
                             },
                             Ok(BlockResponse::Rewind(revert_to)) => {
                                 // Reset backoff because we got a good value from the stream
@@ -349,7 +369,7 @@ enum BlockResponse<C: Blockchain> {
 
 async fn process_firehose_response<C: Blockchain, F: FirehoseMapper<C>>(
     endpoint: &Arc<FirehoseEndpoint>,
-    result: Result<firehose::Response, Status>,
+    result: firehose::SharedResponse,
     check_subgraph_continuity: &mut bool,
     manifest_start_block_num: BlockNumber,
     subgraph_current_block: Option<&BlockPtr>,
@@ -410,7 +430,7 @@ async fn process_firehose_response<C: Blockchain, F: FirehoseMapper<C>>(
         *check_subgraph_continuity = false;
     }
 
-    Ok(BlockResponse::Proceed(event, response.cursor))
+    Ok(BlockResponse::Proceed(event, response.cursor.clone()))
 }
 
 impl<C: Blockchain> Stream for FirehoseBlockStream<C> {
@@ -31,13 +31,19 @@ pub struct MockBlock {
     pub number: u64,
 }
 
+impl MockBlock {
+    fn ptr_for(number: u64) -> BlockPtr {
+        BlockPtr::new(number.to_be_bytes().to_vec().into(), number as i32)
+    }
+}
+
 impl Block for MockBlock {
     fn ptr(&self) -> BlockPtr {
-        todo!()
+        Self::ptr_for(self.number)
     }
 
     fn parent_ptr(&self) -> Option<BlockPtr> {
-        todo!()
+        self.number.checked_sub(1).map(Self::ptr_for)
     }
 }
 
@@ -93,6 +99,10 @@ impl<C: Blockchain> DataSource<C> for MockDataSource {
         todo!()
     }
 
+    fn handler_names(&self) -> Vec<&str> {
+        todo!()
+    }
+
     fn match_and_decode(
         &self,
         _trigger: &C::TriggerData,
@@ -156,6 +166,10 @@ impl<C: Blockchain> DataSourceTemplate<C> for MockDataSourceTemplate {
     fn manifest_idx(&self) -> u32 {
         todo!()
     }
+
+    fn handler_names(&self) -> Vec<&str> {
+        todo!()
+    }
 }
 
 #[derive(Clone, Default, Deserialize)]
@@ -196,11 +210,11 @@ impl<C: Blockchain> TriggersAdapter<C> for MockTriggersAdapter {
 
     async fn triggers_in_block(
         &self,
-        _logger: &slog::Logger,
-        _block: C::Block,
+        logger: &slog::Logger,
+        block: C::Block,
         _filter: &C::TriggerFilter,
     ) -> Result<BlockWithTriggers<C>, Error> {
-        todo!()
+        Ok(BlockWithTriggers::new(block, vec![], logger))
     }
 
     async fn is_on_main_chain(&self, _ptr: BlockPtr) -> Result<bool, Error> {
@@ -4,6 +4,7 @@
 
 pub mod block_stream;
 mod builder;
+pub mod chaos_block_stream;
 pub mod client;
 mod empty_node_capabilities;
 pub mod firehose_block_ingestor;
@@ -25,7 +26,7 @@ use crate::{
 };
 use crate::{
     components::{
-        store::{BlockNumber, ChainStore},
+        store::{BlockNumber, ChainStore, EntityType},
         subgraph::DataSourceTemplateInfo,
     },
     prelude::{thiserror::Error, LinkResolver},
@@ -271,6 +272,15 @@ pub trait DataSource<C: Blockchain>: 'static + Sized + Send + Sync + Clone {
     fn creation_block(&self) -> Option<BlockNumber>;
     fn api_version(&self) -> semver::Version;
     fn runtime(&self) -> Option<Arc<Vec<u8>>>;
+    /// The names of the handlers that the mapping of this data source declares
+    fn handler_names(&self) -> Vec<&str>;
+
+    /// The entity types that the mapping of this data source declares in
+    /// its `entities`, or `None` if the kind of data source does not
+    /// declare them
+    fn declared_entities(&self) -> Option<Vec<EntityType>> {
+        None
+    }
 
     /// Checks if `trigger` matches this data source, and if so decodes it into a `MappingTrigger`.
     /// A return of `Ok(None)` mean the trigger does not match.
@@ -315,6 +325,8 @@ pub trait DataSourceTemplate<C: Blockchain>: Send + Sync + Debug {
     fn runtime(&self) -> Option<Arc<Vec<u8>>>;
     fn name(&self) -> &str;
     fn manifest_idx(&self) -> u32;
+    /// The names of the handlers that the mapping of this template declares
+    fn handler_names(&self) -> Vec<&str>;
 }
 
 #[async_trait]
@@ -1,7 +1,7 @@
 use super::block_stream::SubstreamsMapper;
+use super::client::ChainClient;
 use crate::blockchain::block_stream::{BlockStream, BlockStreamEvent};
 use crate::blockchain::Blockchain;
-use crate::firehose::FirehoseEndpoint;
 use crate::prelude::*;
 use crate::substreams::response::Message;
 use crate::substreams::ForkStep::{StepNew, StepUndo};
@@ -12,26 +12,31 @@ use futures03::{Stream, StreamExt};
 use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::{Duration, Instant};
-use tonic::Status;
+use tonic::{Code, Status};
+
+/// How many connection attempts or streams in a row may fail before the
+/// provider is marked unhealthy
+const CIRCUIT_BREAKER_FAILURES: u32 = 5;
+/// How long a provider that was marked unhealthy is avoided
+const CIRCUIT_BREAKER_COOLDOWN: Duration = Duration::from_secs(300);
+/// How long to wait for a response before the head lag watchdog checks
+/// the stream anyway
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(5);
 
 struct SubstreamsBlockStreamMetrics {
     deployment: DeploymentHash,
-    provider: String,
     restarts: CounterVec,
     connect_duration: GaugeVec,
     time_between_responses: HistogramVec,
     responses: CounterVec,
+    head_lag: GaugeVec,
+    watchdog_reconnects: CounterVec,
 }
 
 impl SubstreamsBlockStreamMetrics {
-    pub fn new(
-        registry: Arc<MetricsRegistry>,
-        deployment: DeploymentHash,
-        provider: String,
-    ) -> Self {
+    pub fn new(registry: Arc<MetricsRegistry>, deployment: DeploymentHash) -> Self {
         Self {
             deployment,
-            provider,
 
             restarts: registry
                 .global_counter_vec(
@@ -64,44 +69,177 @@ impl SubstreamsBlockStreamMetrics {
                     vec!["deployment", "provider", "kind"].as_slice(),
                 )
                 .unwrap(),
+
+            head_lag: registry
+                .global_gauge_vec(
+                    "deployment_substreams_blockstream_head_lag",
+                    "Measures how many blocks a Substreams block stream is behind the chain head",
+                    vec!["deployment", "provider"].as_slice(),
+                )
+                .unwrap(),
+
+            watchdog_reconnects: registry
+                .global_counter_vec(
+                    "deployment_substreams_blockstream_watchdog_reconnects",
+                    "Counts the number of times a Substreams block stream reconnected because it lagged behind the chain head or stalled",
+                    vec!["deployment", "provider", "reason"].as_slice(),
+                )
+                .unwrap(),
         }
     }
 
-    fn observe_successful_connection(&self, time: &mut Instant) {
+    fn observe_successful_connection(&self, time: &mut Instant, provider: &str) {
         self.restarts
-            .with_label_values(&[&self.deployment, &self.provider, "true"])
+            .with_label_values(&[&self.deployment, provider, "true"])
             .inc();
         self.connect_duration
-            .with_label_values(&[&self.deployment, &self.provider])
+            .with_label_values(&[&self.deployment, provider])
             .set(time.elapsed().as_secs_f64());
 
         // Reset last connection timestamp
         *time = Instant::now();
     }
 
-    fn observe_failed_connection(&self, time: &mut Instant) {
+    fn observe_failed_connection(&self, time: &mut Instant, provider: &str) {
         self.restarts
-            .with_label_values(&[&self.deployment, &self.provider, "false"])
+            .with_label_values(&[&self.deployment, provider, "false"])
             .inc();
         self.connect_duration
-            .with_label_values(&[&self.deployment, &self.provider])
+            .with_label_values(&[&self.deployment, provider])
             .set(time.elapsed().as_secs_f64());
 
         // Reset last connection timestamp
         *time = Instant::now();
     }
 
-    fn observe_response(&self, kind: &str, time: &mut Instant) {
+    fn observe_response(&self, kind: &str, time: &mut Instant, provider: &str) {
         self.time_between_responses
-            .with_label_values(&[&self.deployment, &self.provider])
+            .with_label_values(&[&self.deployment, provider])
             .observe(time.elapsed().as_secs_f64());
         self.responses
-            .with_label_values(&[&self.deployment, &self.provider, kind])
+            .with_label_values(&[&self.deployment, provider, kind])
             .inc();
 
         // Reset last response timestamp
         *time = Instant::now();
     }
+
+    fn observe_head_lag(&self, lag: BlockNumber, provider: &str) {
+        self.head_lag
+            .with_label_values(&[&self.deployment, provider])
+            .set(lag as f64);
+    }
+
+    fn observe_watchdog_reconnect(&self, reason: &str, provider: &str) {
+        self.watchdog_reconnects
+            .with_label_values(&[&self.deployment, provider, reason])
+            .inc();
+    }
+}
+
+/// Watches how far a Substreams stream is behind the chain head that the
+/// chain store tracks. While the stream is more than `max_lag` blocks
+/// behind, the watchdog adds up how long the block stream waited for the
+/// provider; time that the subgraph spends processing blocks does not
+/// count. The stream is stuck if it did not send anything for `timeout`
+/// of that time, or if it had caught up with the chain head and then fell
+/// behind for `timeout`
+struct HeadLagWatchdog {
+    chain_store: Option<Arc<dyn ChainStore>>,
+    max_lag: BlockNumber,
+    timeout: Duration,
+    last_check: Instant,
+    /// Whether the stream was at most `max_lag` blocks behind the chain
+    /// head since it connected
+    caught_up: bool,
+    /// How long we waited for the provider since its last response
+    waited_since_response: Duration,
+    /// How long we waited for the provider since the stream was last at
+    /// most `max_lag` blocks behind the chain head
+    waited_while_behind: Duration,
+}
+
+impl HeadLagWatchdog {
+    fn new(chain_store: Option<Arc<dyn ChainStore>>) -> Self {
+        HeadLagWatchdog {
+            chain_store,
+            max_lag: ENV_VARS.substreams_max_head_lag,
+            timeout: ENV_VARS.substreams_head_lag_timeout,
+            last_check: Instant::now(),
+            caught_up: false,
+            waited_since_response: Duration::ZERO,
+            waited_while_behind: Duration::ZERO,
+        }
+    }
+
+    /// Start watching a new connection
+    fn reset(&mut self) {
+        self.caught_up = false;
+        self.waited_since_response = Duration::ZERO;
+        self.waited_while_behind = Duration::ZERO;
+    }
+
+    /// Record that we waited `waited` for the provider, and whether it sent
+    /// a response in that time
+    fn observe_wait(&mut self, waited: Duration, responded: bool) {
+        self.waited_since_response = match responded {
+            true => Duration::ZERO,
+            false => self.waited_since_response + waited,
+        };
+        self.waited_while_behind += waited;
+    }
+
+    /// Check the stream, which has processed blocks up to `latest_block`,
+    /// against the chain head. Returns why the stream is stuck if it is
+    async fn check(
+        &mut self,
+        latest_block: BlockNumber,
+        logger: &Logger,
+        metrics: &SubstreamsBlockStreamMetrics,
+        provider: &str,
+    ) -> Option<&'static str> {
+        let chain_store = match &self.chain_store {
+            Some(chain_store) if self.max_lag > 0 => chain_store.cheap_clone(),
+            _ => return None,
+        };
+        if self.last_check.elapsed() < WATCHDOG_INTERVAL {
+            return None;
+        }
+        self.last_check = Instant::now();
+
+        let head = match chain_store.cached_head_ptr().await {
+            Ok(head) => head?.number,
+            Err(e) => {
+                debug!(
+                    logger,
+                    "Could not get the chain head for the head lag watchdog";
+                    "error" => e.to_string(),
+                );
+                return None;
+            }
+        };
+        let lag = (head - latest_block).max(0);
+        metrics.observe_head_lag(lag, provider);
+        self.observe_lag(lag)
+    }
+
+    /// Record that the stream is `lag` blocks behind the chain head.
+    /// Returns why the stream is stuck if it is
+    fn observe_lag(&mut self, lag: BlockNumber) -> Option<&'static str> {
+        if lag <= self.max_lag {
+            self.caught_up = true;
+            self.waited_since_response = Duration::ZERO;
+            self.waited_while_behind = Duration::ZERO;
+            return None;
+        }
+        if self.waited_since_response >= self.timeout {
+            return Some("stalled");
+        }
+        if self.caught_up && self.waited_while_behind >= self.timeout {
+            return Some("lagging");
+        }
+        None
+    }
 }
 
 pub struct SubstreamsBlockStream<C: Blockchain> {
@@ -116,7 +254,8 @@ where
 {
     pub fn new<F>(
         deployment: DeploymentHash,
-        endpoint: Arc<FirehoseEndpoint>,
+        client: Arc<ChainClient<C>>,
+        chain_store: Option<Arc<dyn ChainStore>>,
         subgraph_current_block: Option<BlockPtr>,
         cursor: Option<String>,
         mapper: Arc<F>,
@@ -134,12 +273,12 @@ where
 
         let manifest_end_block_num = end_blocks.into_iter().min().unwrap_or(0);
 
-        let metrics =
-            SubstreamsBlockStreamMetrics::new(registry, deployment, endpoint.provider.to_string());
+        let metrics = SubstreamsBlockStreamMetrics::new(registry, deployment);
 
         SubstreamsBlockStream {
             stream: Box::pin(stream_blocks(
-                endpoint,
+                client,
+                chain_store,
                 cursor,
                 mapper,
                 modules,
@@ -155,7 +294,8 @@ where
 }
 
 fn stream_blocks<C: Blockchain, F: SubstreamsMapper<C>>(
-    endpoint: Arc<FirehoseEndpoint>,
+    client: Arc<ChainClient<C>>,
+    chain_store: Option<Arc<dyn ChainStore>>,
     cursor: Option<String>,
     mapper: Arc<F>,
     modules: Option<Modules>,
@@ -168,18 +308,25 @@ fn stream_blocks<C: Blockchain, F: SubstreamsMapper<C>>(
 ) -> impl Stream<Item = Result<BlockStreamEvent<C>, Error>> {
     let mut latest_cursor = cursor.unwrap_or_default();
 
-    let start_block_num = subgraph_current_block
+    // The last block that the subgraph has processed, if we know it
+    let mut latest_block = subgraph_current_block
         .as_ref()
-        .map(|ptr| {
-            // current_block has already been processed, we start at next block
-            ptr.block_number() as i64 + 1
-        })
-        .unwrap_or(manifest_start_block_num as i64);
+        .map(|ptr| ptr.block_number());
 
     let stop_block_num = manifest_end_block_num as u64;
 
-    // Back off exponentially whenever we encounter a connection error or a stream with bad data
-    let mut backoff = ExponentialBackoff::new(Duration::from_millis(500), Duration::from_secs(45));
+    // Back off exponentially whenever we encounter a connection error or a stream with bad data.
+    // The jitter keeps the deployments that use a provider from reconnecting all at once
+    // after it had a problem
+    let mut backoff =
+        ExponentialBackoff::with_jitter(Duration::from_millis(500), Duration::from_secs(45), 0.5);
+
+    // The number of connection attempts and streams in a row that failed
+    // without producing a single response
+    let mut failures = 0;
+
+    // Notices when the stream falls behind the chain head or stalls
+    let mut watchdog = HeadLagWatchdog::new(chain_store);
 
     // This attribute is needed because `try_stream!` seems to break detection of `skip_backoff` assignments
     #[allow(unused_assignments)]
@@ -187,10 +334,17 @@ fn stream_blocks<C: Blockchain, F: SubstreamsMapper<C>>(
 
     try_stream! {
         loop {
+            let endpoint = client.firehose_endpoint()?;
+            let provider = endpoint.provider.to_string();
+            let start_block_num = latest_block
+                .map(|number| number as i64 + 1)
+                .unwrap_or(manifest_start_block_num as i64);
+
             info!(
                 &logger,
                 "Blockstreams disconnected, connecting";
                 "endpoint_uri" => format_args!("{}", endpoint),
+                "provider" => &provider,
                 "start_block" => start_block_num,
                 "cursor" => &latest_cursor,
             );
@@ -211,6 +365,10 @@ fn stream_blocks<C: Blockchain, F: SubstreamsMapper<C>>(
                 ..Default::default()
             };
 
+            // When we resume from a cursor, the first block we get has to
+            // continue from the last block we processed
+            let mut resume_from = latest_block.filter(|_| !latest_cursor.is_empty());
+
             let result = endpoint.clone().substreams(request).await;
 
             match result {
@@ -218,51 +376,100 @@ fn stream_blocks<C: Blockchain, F: SubstreamsMapper<C>>(
                     info!(&logger, "Blockstreams connected");
 
                     // Track the time it takes to set up the block stream
-                    metrics.observe_successful_connection(&mut connect_start);
+                    metrics.observe_successful_connection(&mut connect_start, &provider);
 
                     let mut last_response_time = Instant::now();
                     let mut expected_stream_end = false;
+                    watchdog.reset();
+
+                    let mut stream = stream;
+                    loop {
+                        // Wake up regularly so that the watchdog notices a
+                        // stream that stopped sending responses
+                        let wait_start = Instant::now();
+                        let response = match tokio::time::timeout(WATCHDOG_INTERVAL, stream.next()).await {
+                            Ok(Some(response)) => Some(response),
+                            Ok(None) => break,
+                            Err(_) => None,
+                        };
+                        watchdog.observe_wait(wait_start.elapsed(), response.is_some());
+
+                        if let Some(response) = response {
+
+                            if is_invalid_cursor(&response) && !latest_cursor.is_empty() {
+                                warn!(
+                                    &logger,
+                                    "Provider rejected the cursor, resuming from the last processed block without it";
+                                    "cursor" => &latest_cursor,
+                                    "start_block" => start_block_num,
+                                );
+                                latest_cursor = String::new();
+                                skip_backoff = true;
+                                expected_stream_end = true;
+                                break;
+                            }
 
-                    for await response in stream{
-                        match process_substreams_response(
-                            response,
-                            mapper.as_ref(),
-                            &logger,
-                        ).await {
-                            Ok(block_response) => {
-                                match block_response {
-                                    None => {}
-                                    Some(BlockResponse::Proceed(event, cursor)) => {
-                                        // Reset backoff because we got a good value from the stream
-                                        backoff.reset();
-
-                                        metrics.observe_response("proceed", &mut last_response_time);
-
-                                        yield event;
-
-                                        latest_cursor = cursor;
+                            match process_substreams_response(
+                                response,
+                                resume_from.take(),
+                                mapper.as_ref(),
+                                &logger,
+                            ).await {
+                                Ok(block_response) => {
+                                    match block_response {
+                                        None => {}
+                                        Some(BlockResponse::Proceed(event, cursor, head)) => {
+                                            // Reset backoff because we got a good value from the stream
+                                            backoff.reset();
+                                            failures = 0;
+
+                                            metrics.observe_response("proceed", &mut last_response_time, &provider);
+
+                                            yield event;
+
+                                            latest_cursor = cursor;
+                                            latest_block = Some(head);
+                                        }
                                     }
+                                },
+                                Err(err) => {
+                                    info!(&logger, "received err");
+                                    // We have an open connection but there was an error processing the Firehose
+                                    // response. We will reconnect the stream after this; this is the case where
+                                    // we actually _want_ to back off in case we keep running into the same error.
+                                    // An example of this situation is if we get invalid block or transaction data
+                                    // that cannot be decoded properly.
+
+                                    metrics.observe_response("error", &mut last_response_time, &provider);
+
+                                    error!(logger, "{:#}", err);
+                                    failures += 1;
+                                    expected_stream_end = true;
+                                    break;
                                 }
-                            },
-                            Err(err) => {
-                                info!(&logger, "received err");
-                                // We have an open connection but there was an error processing the Firehose
-                                // response. We will reconnect the stream after this; this is the case where
-                                // we actually _want_ to back off in case we keep running into the same error.
-                                // An example of this situation is if we get invalid block or transaction data
-                                // that cannot be decoded properly.
-
-                                metrics.observe_response("error", &mut last_response_time);
-
-                                error!(logger, "{:#}", err);
-                                expected_stream_end = true;
-                                break;
                             }
                         }
+
+                        let latest = latest_block.unwrap_or(manifest_start_block_num - 1);
+                        if let Some(reason) = watchdog.check(latest, &logger, &metrics, &provider).await {
+                            warn!(
+                                &logger,
+                                "Substreams block stream is stuck, reconnecting";
+                                "provider" => &provider,
+                                "reason" => reason,
+                                "latest_block" => latest,
+                            );
+                            metrics.observe_watchdog_reconnect(reason, &provider);
+                            endpoint.mark_unhealthy(CIRCUIT_BREAKER_COOLDOWN);
+                            skip_backoff = true;
+                            expected_stream_end = true;
+                            break;
+                        }
                     }
 
                     if !expected_stream_end {
                         error!(logger, "Stream blocks complete unexpectedly, expecting stream to always stream blocks");
+                        failures += 1;
                     }
                 },
                 Err(e) => {
@@ -270,12 +477,27 @@ fn stream_blocks<C: Blockchain, F: SubstreamsMapper<C>>(
                     // case where we actually _want_ to back off in case we keep
                     // having connection errors.
 
-                    metrics.observe_failed_connection(&mut connect_start);
+                    metrics.observe_failed_connection(&mut connect_start, &provider);
 
                     error!(logger, "Unable to connect to endpoint: {:#}", e);
+                    failures += 1;
                 }
             }
 
+            // Stop using a provider that keeps failing so that the next
+            // connection goes to another provider if there is one
+            if failures >= CIRCUIT_BREAKER_FAILURES {
+                warn!(
+                    &logger,
+                    "Provider failed too often, marking it unhealthy";
+                    "provider" => &provider,
+                    "failures" => failures,
+                    "cooldown_secs" => CIRCUIT_BREAKER_COOLDOWN.as_secs(),
+                );
+                endpoint.mark_unhealthy(CIRCUIT_BREAKER_COOLDOWN);
+                failures = 0;
+            }
+
             // If we reach this point, we must wait a bit before retrying, unless `skip_backoff` is true
             if !skip_backoff {
                 backoff.sleep_async().await;
@@ -284,12 +506,26 @@ fn stream_blocks<C: Blockchain, F: SubstreamsMapper<C>>(
     }
 }
 
+/// Whether the provider rejected the cursor of the request
+fn is_invalid_cursor<T>(response: &Result<T, Status>) -> bool {
+    match response {
+        Ok(_) => false,
+        Err(status) => {
+            status.code() == Code::InvalidArgument
+                && status.message().to_lowercase().contains("cursor")
+        }
+    }
+}
+
 enum BlockResponse<C: Blockchain> {
-    Proceed(BlockStreamEvent<C>, String),
+    /// An event for the subgraph, the cursor after it, and the number of
+    /// the block that the subgraph is at after processing the event
+    Proceed(BlockStreamEvent<C>, String, BlockNumber),
 }
 
 async fn process_substreams_response<C: Blockchain, F: SubstreamsMapper<C>>(
     result: Result<Response, Status>,
+    resume_from: Option<BlockNumber>,
     mapper: &F,
     logger: &Logger,
 ) -> Result<Option<BlockResponse<C>>, Error> {
@@ -300,6 +536,28 @@ async fn process_substreams_response<C: Blockchain, F: SubstreamsMapper<C>>(
 
     match response.message {
         Some(Message::Data(block_scoped_data)) => {
+            let number = block_scoped_data
+                .clock
+                .as_ref()
+                .map(|clock| clock.number as BlockNumber)
+                .ok_or_else(|| anyhow!("Substreams response has no clock"))?;
+            let undo = block_scoped_data.step == StepUndo as i32;
+            // A new block has the block we resumed from as an ancestor, and
+            // we can only undo blocks that we have seen
+            let continues = match resume_from {
+                None => true,
+                Some(resume_from) if undo => number <= resume_from,
+                Some(resume_from) => number > resume_from,
+            };
+            if !continues {
+                return Err(anyhow!(
+                    "Substreams did not resume from the cursor: expected a block after #{} but got #{}",
+                    resume_from.unwrap_or_default(),
+                    number
+                ));
+            }
+            let head = if undo { number - 1 } else { number };
+
             match mapper
                 .to_block_stream_event(logger, &block_scoped_data)
                 .await
@@ -308,6 +566,7 @@ async fn process_substreams_response<C: Blockchain, F: SubstreamsMapper<C>>(
                 Some(event) => Ok(Some(BlockResponse::Proceed(
                     event,
                     block_scoped_data.cursor.to_string(),
+                    head,
                 ))),
                 None => Ok(None),
             }
@@ -329,3 +588,57 @@ impl<C: Blockchain> Stream for SubstreamsBlockStream<C> {
 }
 
 impl<C: Blockchain> BlockStream<C> for SubstreamsBlockStream<C> {}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::HeadLagWatchdog;
+
+    fn watchdog() -> HeadLagWatchdog {
+        let mut watchdog = HeadLagWatchdog::new(None);
+        watchdog.max_lag = 10;
+        watchdog.timeout = Duration::from_secs(60);
+        watchdog
+    }
+
+    #[test]
+    fn stalled_stream() {
+        let mut watchdog = watchdog();
+
+        // Waiting while the provider sends responses is fine, even if the
+        // stream has not caught up yet
+        watchdog.observe_wait(Duration::from_secs(100), true);
+        assert_eq!(None, watchdog.observe_lag(100));
+
+        watchdog.observe_wait(Duration::from_secs(30), false);
+        assert_eq!(None, watchdog.observe_lag(100));
+        watchdog.observe_wait(Duration::from_secs(30), false);
+        assert_eq!(Some("stalled"), watchdog.observe_lag(100));
+
+        // Without a lag, a quiet stream is just waiting for new blocks
+        watchdog.reset();
+        watchdog.observe_wait(Duration::from_secs(100), false);
+        assert_eq!(None, watchdog.observe_lag(10));
+    }
+
+    #[test]
+    fn lagging_stream() {
+        let mut watchdog = watchdog();
+
+        // A stream that has not caught up yet is not lagging
+        watchdog.observe_wait(Duration::from_secs(100), true);
+        assert_eq!(None, watchdog.observe_lag(100));
+
+        assert_eq!(None, watchdog.observe_lag(5));
+        watchdog.observe_wait(Duration::from_secs(30), true);
+        assert_eq!(None, watchdog.observe_lag(100));
+        watchdog.observe_wait(Duration::from_secs(30), true);
+        assert_eq!(Some("lagging"), watchdog.observe_lag(100));
+
+        // A new connection has to catch up again first
+        watchdog.reset();
+        watchdog.observe_wait(Duration::from_secs(100), true);
+        assert_eq!(None, watchdog.observe_lag(100));
+    }
+}
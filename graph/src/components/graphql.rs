@@ -1,6 +1,6 @@
 use futures::prelude::*;
 
-use crate::data::query::{CacheStatus, Query, QueryTarget};
+use crate::data::query::{CacheStatus, Query, QueryExecutionError, QueryTarget};
 use crate::data::subscription::{Subscription, SubscriptionError, SubscriptionResult};
 use crate::data::{graphql::effort::LoadManager, query::QueryResults};
 use crate::prelude::DeploymentHash;
@@ -41,6 +41,12 @@ pub trait GraphQlRunner: Send + Sync + 'static {
         target: QueryTarget,
     ) -> Result<SubscriptionResult, SubscriptionError>;
 
+    /// The deployment that queries against `target` currently run against
+    async fn deployment_for(
+        &self,
+        target: &QueryTarget,
+    ) -> Result<DeploymentHash, QueryExecutionError>;
+
     fn load_manager(&self) -> Arc<LoadManager>;
 
     fn metrics(&self) -> Arc<dyn GraphQLMetrics>;
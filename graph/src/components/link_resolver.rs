@@ -21,6 +21,33 @@ pub struct JsonStreamValue {
 pub type JsonValueStream =
     Pin<Box<dyn Stream<Item = Result<JsonStreamValue, Error>> + Send + 'static>>;
 
+/// Whether fetching a link failed because the IPFS node could not be
+/// reached. Such errors may go away when the request is tried again later;
+/// timeouts, error responses from the node and files that are too large
+/// are final
+pub fn is_transient_error(e: &Error) -> bool {
+    match e.downcast_ref::<reqwest::Error>() {
+        Some(e) => !(e.is_status() || e.is_timeout()),
+        None => false,
+    }
+}
+
+/// A cache of IPFS files that is kept across restarts and shared by all
+/// nodes that use the same database. Since the contents of an IPFS path
+/// can not change, entries never become stale. The cache is best effort:
+/// errors are logged by the cache and not reported to callers, who can
+/// always fetch the file from IPFS again
+#[async_trait]
+pub trait IpfsCache: Send + Sync + 'static {
+    /// The contents of `path`, a CID optionally followed by a path within
+    /// it, if they are in the cache
+    async fn get(&self, path: &str) -> Option<Vec<u8>>;
+
+    /// Remember `data` as the contents of `path`. Files that are too large
+    /// for the cache are ignored
+    async fn insert(&self, path: String, data: Vec<u8>);
+}
+
 /// Resolves links to subgraph manifests and resources referenced by them.
 #[async_trait]
 pub trait LinkResolver: Send + Sync + 'static + Debug {
@@ -30,6 +57,14 @@ pub trait LinkResolver: Send + Sync + 'static + Debug {
     /// Enables infinite retries.
     fn with_retries(&self) -> Box<dyn LinkResolver>;
 
+    /// Limits how often requests that fail because the IPFS node could not
+    /// be reached are retried. Without a limit, they are retried until they
+    /// succeed or time out.
+    fn with_max_retries(&self, max_retries: usize) -> Box<dyn LinkResolver>;
+
+    /// Updates the size limit for files fetched with `cat` and `get_block`.
+    fn with_max_file_size(&self, max_file_size: usize) -> Box<dyn LinkResolver>;
+
     /// Fetches the link contents as bytes.
     async fn cat(&self, logger: &Logger, link: &Link) -> Result<Vec<u8>, Error>;
 
@@ -0,0 +1,240 @@
+//! The health of the block ingestors on this node. Each ingestor reports
+//! the blocks that it stores, the provider that it uses, and the errors it
+//! runs into, so that when the whole node seems stuck, the chain whose
+//! ingestor stopped can be found through the index node API or the
+//! `block_ingestor_*` metrics.
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use lazy_static::lazy_static;
+
+use super::{CounterVec, GaugeVec, MetricsRegistry};
+use crate::blockchain::BlockPtr;
+use crate::prelude::BlockNumber;
+
+lazy_static! {
+    /// The health of the block ingestors on this node, by chain
+    pub static ref INGESTOR_HEALTH: IngestorRegistry = IngestorRegistry::default();
+}
+
+/// The health of the block ingestor of one chain
+#[derive(Clone, Debug, PartialEq)]
+pub struct IngestorStatus {
+    pub chain: String,
+    /// The provider that the ingestor uses, or used last
+    pub provider: Option<String>,
+    /// The latest block that the ingestor stored, and when it stored it
+    pub latest_block: Option<(BlockPtr, SystemTime)>,
+    /// The latest block that the provider knew about when the ingestor
+    /// last asked it. Only ingestors that poll the provider know this
+    pub provider_head: Option<BlockNumber>,
+    /// The last error that the ingestor ran into, and when
+    pub last_error: Option<(String, SystemTime)>,
+}
+
+impl IngestorStatus {
+    fn new(chain: &str) -> Self {
+        IngestorStatus {
+            chain: chain.to_string(),
+            provider: None,
+            latest_block: None,
+            provider_head: None,
+            last_error: None,
+        }
+    }
+
+    /// How many blocks the latest stored block is behind the head of the
+    /// provider, if that is known
+    pub fn head_lag(&self) -> Option<BlockNumber> {
+        let (latest, _) = self.latest_block.as_ref()?;
+        Some((self.provider_head? - latest.number).max(0))
+    }
+}
+
+#[derive(Clone)]
+struct IngestorMetrics {
+    latest_block: GaugeVec,
+    latest_block_time: GaugeVec,
+    head_lag: GaugeVec,
+    errors: CounterVec,
+}
+
+impl IngestorMetrics {
+    fn new(registry: &MetricsRegistry) -> Self {
+        IngestorMetrics {
+            latest_block: registry
+                .global_gauge_vec(
+                    "block_ingestor_latest_block",
+                    "The number of the latest block that the block ingestor stored",
+                    &["chain"],
+                )
+                .unwrap(),
+            latest_block_time: registry
+                .global_gauge_vec(
+                    "block_ingestor_latest_block_time",
+                    "When the block ingestor stored its latest block, in seconds since the epoch",
+                    &["chain"],
+                )
+                .unwrap(),
+            head_lag: registry
+                .global_gauge_vec(
+                    "block_ingestor_head_lag",
+                    "How many blocks the block ingestor is behind the head of its provider",
+                    &["chain"],
+                )
+                .unwrap(),
+            errors: registry
+                .global_counter_vec(
+                    "block_ingestor_errors",
+                    "Counts the errors that the block ingestor ran into",
+                    &["chain", "provider"],
+                )
+                .unwrap(),
+        }
+    }
+}
+
+/// Reports the health of the block ingestor of one chain
+pub struct IngestorHealth {
+    status: Mutex<IngestorStatus>,
+    metrics: Arc<RwLock<Option<IngestorMetrics>>>,
+}
+
+impl IngestorHealth {
+    /// Record that the ingestor uses `provider` from now on
+    pub fn set_provider(&self, provider: &str) {
+        self.status.lock().unwrap().provider = Some(provider.to_string());
+    }
+
+    /// Record that the ingestor stored the block `ptr`. If the ingestor
+    /// knows it, `provider_head` is the latest block of the provider
+    pub fn observe_block(&self, ptr: BlockPtr, provider_head: Option<BlockNumber>) {
+        let now = SystemTime::now();
+        let mut status = self.status.lock().unwrap();
+        if let Some(provider_head) = provider_head {
+            status.provider_head = Some(provider_head);
+        }
+        let number = ptr.number;
+        status.latest_block = Some((ptr, now));
+
+        if let Some(metrics) = self.metrics.read().unwrap().as_ref() {
+            let chain = status.chain.as_str();
+            metrics
+                .latest_block
+                .with_label_values(&[chain])
+                .set(number as f64);
+            metrics
+                .latest_block_time
+                .with_label_values(&[chain])
+                .set(seconds_since_epoch(now));
+            if let Some(lag) = status.head_lag() {
+                metrics.head_lag.with_label_values(&[chain]).set(lag as f64);
+            }
+        }
+    }
+
+    /// Record that the ingestor ran into `error`
+    pub fn observe_error(&self, error: &str) {
+        let mut status = self.status.lock().unwrap();
+        status.last_error = Some((error.to_string(), SystemTime::now()));
+
+        if let Some(metrics) = self.metrics.read().unwrap().as_ref() {
+            let provider = status.provider.as_deref().unwrap_or("");
+            metrics
+                .errors
+                .with_label_values(&[status.chain.as_str(), provider])
+                .inc();
+        }
+    }
+}
+
+/// The health of all block ingestors on this node
+#[derive(Default)]
+pub struct IngestorRegistry {
+    chains: RwLock<BTreeMap<String, Arc<IngestorHealth>>>,
+    metrics: Arc<RwLock<Option<IngestorMetrics>>>,
+}
+
+impl IngestorRegistry {
+    /// Export the health of the ingestors as metrics in `registry`
+    pub fn register_metrics(&self, registry: &MetricsRegistry) {
+        *self.metrics.write().unwrap() = Some(IngestorMetrics::new(registry));
+    }
+
+    /// The reporter for the health of the ingestor of `chain`
+    pub fn chain(&self, chain: &str) -> Arc<IngestorHealth> {
+        if let Some(health) = self.chains.read().unwrap().get(chain) {
+            return health.clone();
+        }
+        self.chains
+            .write()
+            .unwrap()
+            .entry(chain.to_string())
+            .or_insert_with(|| {
+                Arc::new(IngestorHealth {
+                    status: Mutex::new(IngestorStatus::new(chain)),
+                    metrics: self.metrics.clone(),
+                })
+            })
+            .clone()
+    }
+
+    /// The health of the ingestors of all chains, ordered by chain
+    pub fn all(&self) -> Vec<IngestorStatus> {
+        self.chains
+            .read()
+            .unwrap()
+            .values()
+            .map(|health| health.status.lock().unwrap().clone())
+            .collect()
+    }
+}
+
+fn seconds_since_epoch(time: SystemTime) -> f64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::BlockHash;
+
+    fn ptr(number: BlockNumber) -> BlockPtr {
+        BlockPtr::new(BlockHash::from(vec![number as u8; 32]), number)
+    }
+
+    #[test]
+    fn ingestor_health() {
+        let registry = IngestorRegistry::default();
+        let health = registry.chain("mainnet");
+        health.set_provider("mainnet-rpc-0");
+        health.observe_block(ptr(10), Some(15));
+        health.observe_block(ptr(12), None);
+        health.observe_error("connection refused");
+        registry.chain("goerli");
+
+        let all = registry.all();
+        assert_eq!(
+            vec!["goerli", "mainnet"],
+            all.iter()
+                .map(|status| status.chain.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(None, all[0].head_lag());
+
+        let mainnet = &all[1];
+        assert_eq!(Some("mainnet-rpc-0"), mainnet.provider.as_deref());
+        assert_eq!(
+            Some(12),
+            mainnet.latest_block.as_ref().map(|(ptr, _)| ptr.number)
+        );
+        assert_eq!(Some(3), mainnet.head_lag());
+        assert_eq!(
+            Some("connection refused"),
+            mainnet.last_error.as_ref().map(|(error, _)| error.as_str())
+        );
+    }
+}
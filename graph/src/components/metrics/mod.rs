@@ -4,7 +4,9 @@ pub use prometheus::{
     HistogramOpts, HistogramVec, Opts, Registry,
 };
 
+pub mod ingestors;
 pub mod registry;
+pub mod resources;
 pub mod subgraph;
 
 pub use registry::MetricsRegistry;
@@ -0,0 +1,217 @@
+//! Accounting of the resources that each deployment uses on this node: the
+//! time spent running mappings, writing to the store, and waiting for
+//! JSON-RPC requests, and how much entity data was written. Usage is kept
+//! in one-minute buckets for the last hour so that it can be reported for
+//! rolling windows, and as totals since the node started.
+use std::collections::{HashMap, VecDeque};
+use std::ops::AddAssign;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use lazy_static::lazy_static;
+
+const BUCKET: Duration = Duration::from_secs(60);
+const BUCKETS: usize = 60;
+
+lazy_static! {
+    /// The resource usage of all deployments on this node
+    pub static ref RESOURCE_USAGE: ResourceRegistry = ResourceRegistry::default();
+}
+
+/// The resources that a deployment used in some period of time
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ResourceUsage {
+    /// Wall-clock time spent running mappings, including the host
+    /// functions they call
+    pub wasm: Duration,
+    /// Wall-clock time spent writing changes to the store
+    pub store_write: Duration,
+    /// Wall-clock time spent waiting for JSON-RPC requests
+    pub rpc: Duration,
+    /// The approximate size of the entity data written to the store
+    pub bytes_written: u64,
+}
+
+impl AddAssign for ResourceUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.wasm += other.wasm;
+        self.store_write += other.store_write;
+        self.rpc += other.rpc;
+        self.bytes_written += other.bytes_written;
+    }
+}
+
+#[derive(Debug)]
+struct Buckets {
+    /// The usage in each of the most recent minutes, oldest first. The
+    /// number is the minute since `DeploymentResources.start`
+    recent: VecDeque<(u64, ResourceUsage)>,
+    total: ResourceUsage,
+}
+
+/// The resource usage of one deployment
+#[derive(Debug)]
+pub struct DeploymentResources {
+    start: Instant,
+    buckets: Mutex<Buckets>,
+}
+
+impl DeploymentResources {
+    fn new(start: Instant) -> Self {
+        DeploymentResources {
+            start,
+            buckets: Mutex::new(Buckets {
+                recent: VecDeque::with_capacity(BUCKETS),
+                total: ResourceUsage::default(),
+            }),
+        }
+    }
+
+    fn minute(&self, now: Instant) -> u64 {
+        now.saturating_duration_since(self.start).as_secs() / BUCKET.as_secs()
+    }
+
+    fn add_at(&self, now: Instant, usage: ResourceUsage) {
+        let minute = self.minute(now);
+        let mut buckets = self.buckets.lock().unwrap();
+        buckets.total += usage;
+        match buckets.recent.back_mut() {
+            Some((last, bucket)) if *last == minute => *bucket += usage,
+            _ => {
+                if buckets.recent.len() == BUCKETS {
+                    buckets.recent.pop_front();
+                }
+                buckets.recent.push_back((minute, usage));
+            }
+        }
+    }
+
+    fn usage_at(&self, now: Instant, window: Duration) -> ResourceUsage {
+        let minute = self.minute(now);
+        let minutes = (window.as_secs() / BUCKET.as_secs()).max(1);
+        let buckets = self.buckets.lock().unwrap();
+        let mut usage = ResourceUsage::default();
+        for (_, bucket) in buckets.recent.iter().filter(|(m, _)| minute - m < minutes) {
+            usage += *bucket;
+        }
+        usage
+    }
+
+    pub fn add_wasm(&self, duration: Duration) {
+        self.add_at(
+            Instant::now(),
+            ResourceUsage {
+                wasm: duration,
+                ..Default::default()
+            },
+        )
+    }
+
+    pub fn add_store_write(&self, duration: Duration, bytes_written: u64) {
+        self.add_at(
+            Instant::now(),
+            ResourceUsage {
+                store_write: duration,
+                bytes_written,
+                ..Default::default()
+            },
+        )
+    }
+
+    pub fn add_rpc(&self, duration: Duration) {
+        self.add_at(
+            Instant::now(),
+            ResourceUsage {
+                rpc: duration,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// The resources used in the most recent `window`, rounded to whole
+    /// minutes. Windows longer than an hour are cut off at an hour
+    pub fn usage(&self, window: Duration) -> ResourceUsage {
+        self.usage_at(Instant::now(), window)
+    }
+
+    /// The resources used since the node started
+    pub fn total(&self) -> ResourceUsage {
+        self.buckets.lock().unwrap().total
+    }
+}
+
+/// The resource usage of all deployments that used any resources on this
+/// node since it started
+#[derive(Debug, Default)]
+pub struct ResourceRegistry {
+    deployments: RwLock<HashMap<String, Arc<DeploymentResources>>>,
+}
+
+impl ResourceRegistry {
+    /// The resource usage of `deployment`, which is created if it does not
+    /// exist yet. Callers should hold on to the result rather than look it
+    /// up for every change
+    pub fn deployment(&self, deployment: &str) -> Arc<DeploymentResources> {
+        if let Some(resources) = self.deployments.read().unwrap().get(deployment) {
+            return resources.clone();
+        }
+        self.deployments
+            .write()
+            .unwrap()
+            .entry(deployment.to_string())
+            .or_insert_with(|| Arc::new(DeploymentResources::new(Instant::now())))
+            .clone()
+    }
+
+    /// The resource usage of all deployments, sorted by deployment
+    pub fn all(&self) -> Vec<(String, Arc<DeploymentResources>)> {
+        let mut all: Vec<_> = self
+            .deployments
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(deployment, resources)| (deployment.clone(), resources.clone()))
+            .collect();
+        all.sort_by(|a, b| a.0.cmp(&b.0));
+        all
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{DeploymentResources, ResourceUsage, BUCKET, BUCKETS};
+
+    fn wasm(secs: u64) -> ResourceUsage {
+        ResourceUsage {
+            wasm: Duration::from_secs(secs),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn rolling_windows() {
+        let start = Instant::now();
+        let resources = DeploymentResources::new(start);
+        let at = |minute: u32| start + BUCKET * minute;
+
+        resources.add_at(at(0), wasm(1));
+        resources.add_at(at(0), wasm(2));
+        resources.add_at(at(3), wasm(4));
+        resources.add_at(at(4), wasm(8));
+
+        let window = |minutes: u32| resources.usage_at(at(4), BUCKET * minutes);
+        assert_eq!(wasm(8), window(1));
+        assert_eq!(wasm(12), window(2));
+        assert_eq!(wasm(15), window(5));
+        assert_eq!(wasm(15), resources.total());
+
+        // Buckets that are older than an hour are dropped, but still count
+        // towards the total
+        resources.add_at(at(BUCKETS as u32 + 3), wasm(16));
+        let window = |minutes: u32| resources.usage_at(at(BUCKETS as u32 + 3), BUCKET * minutes);
+        assert_eq!(wasm(28), window(BUCKETS as u32));
+        assert_eq!(wasm(31), resources.total());
+    }
+}
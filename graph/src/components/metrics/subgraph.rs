@@ -1,10 +1,13 @@
 use prometheus::Counter;
 
 use crate::blockchain::block_stream::BlockStreamMetrics;
-use crate::prelude::{Gauge, Histogram, HostMetrics};
-use std::collections::HashMap;
+use crate::components::store::EntityCacheStats;
+use crate::prelude::{CounterVec, Gauge, Histogram, HistogramVec, HostMetrics};
+use std::collections::{BTreeSet, HashMap};
 use std::sync::Arc;
+use std::time::Duration;
 
+use super::resources::{DeploymentResources, RESOURCE_USAGE};
 use super::stopwatch::StopwatchMetrics;
 use super::MetricsRegistry;
 
@@ -13,16 +16,36 @@ pub struct SubgraphInstanceMetrics {
     pub block_processing_duration: Box<Histogram>,
     pub block_ops_transaction_duration: Box<Histogram>,
     pub firehose_connection_errors: Counter,
+    /// The blocks that the block stream fetched ahead and that are
+    /// waiting to be processed
+    pub block_stream_buffered_blocks: Gauge,
+    /// The time the subgraph waited because it is limited to a number of
+    /// blocks per second
+    pub throttled_seconds: Counter,
+    entity_cache_hits: Counter,
+    entity_cache_misses: Counter,
+    entity_cache_evicted_entities: Counter,
+    entity_cache_size: Gauge,
 
     pub stopwatch: StopwatchMetrics,
     trigger_processing_duration: Box<Histogram>,
+    handler_processing_duration: Box<HistogramVec>,
+    handler_trigger_count: Box<CounterVec>,
+    /// The handlers that the subgraph declares. Metrics for any other
+    /// handler are recorded under `OTHER_HANDLER` so that the number of
+    /// label values stays bounded
+    handlers: BTreeSet<String>,
+    resources: Arc<DeploymentResources>,
 }
 
+const OTHER_HANDLER: &str = "other";
+
 impl SubgraphInstanceMetrics {
     pub fn new(
         registry: Arc<MetricsRegistry>,
         subgraph_hash: &str,
         stopwatch: StopwatchMetrics,
+        handlers: BTreeSet<String>,
     ) -> Self {
         let block_trigger_count = registry
             .new_deployment_histogram(
@@ -40,6 +63,23 @@ impl SubgraphInstanceMetrics {
                 vec![0.01, 0.05, 0.1, 0.5, 1.5, 5.0, 10.0, 30.0, 120.0],
             )
             .expect("failed to create `deployment_trigger_processing_duration` histogram");
+        let handler_processing_duration = registry
+            .new_deployment_histogram_vec(
+                "deployment_handler_processing_duration",
+                "Measures duration of trigger processing for each handler of a subgraph deployment",
+                subgraph_hash,
+                vec![String::from("handler")],
+                vec![0.01, 0.05, 0.1, 0.5, 1.5, 5.0, 10.0, 30.0, 120.0],
+            )
+            .expect("failed to create `deployment_handler_processing_duration` histogram");
+        let handler_trigger_count = registry
+            .new_deployment_counter_vec(
+                "deployment_handler_trigger_count",
+                "Counts the triggers processed by each handler of a subgraph deployment",
+                subgraph_hash,
+                vec![String::from("handler")],
+            )
+            .expect("failed to create `deployment_handler_trigger_count` counter");
         let block_processing_duration = registry
             .new_deployment_histogram(
                 "deployment_block_processing_duration",
@@ -64,6 +104,48 @@ impl SubgraphInstanceMetrics {
                 subgraph_hash,
             )
             .expect("failed to create firehose_connection_errors counter");
+        let block_stream_buffered_blocks = registry
+            .new_deployment_gauge(
+                "deployment_block_stream_buffered_blocks",
+                "Counts the blocks that were fetched ahead and wait to be processed for a subgraph deployment",
+                subgraph_hash,
+            )
+            .expect("failed to create `deployment_block_stream_buffered_blocks` gauge");
+        let throttled_seconds = registry
+            .new_deployment_counter(
+                "deployment_throttled_seconds",
+                "Counts the seconds a subgraph deployment waited because of its blocks per second limit",
+                subgraph_hash,
+            )
+            .expect("failed to create `deployment_throttled_seconds` counter");
+        let entity_cache_hits = registry
+            .new_deployment_counter(
+                "deployment_entity_cache_hits",
+                "Counts the entity lookups of a subgraph deployment that were answered from the entity cache",
+                subgraph_hash,
+            )
+            .expect("failed to create `deployment_entity_cache_hits` counter");
+        let entity_cache_misses = registry
+            .new_deployment_counter(
+                "deployment_entity_cache_misses",
+                "Counts the entity lookups of a subgraph deployment that had to go to the store",
+                subgraph_hash,
+            )
+            .expect("failed to create `deployment_entity_cache_misses` counter");
+        let entity_cache_evicted_entities = registry
+            .new_deployment_counter(
+                "deployment_entity_cache_evicted_entities",
+                "Counts the entities that were evicted from the entity cache of a subgraph deployment",
+                subgraph_hash,
+            )
+            .expect("failed to create `deployment_entity_cache_evicted_entities` counter");
+        let entity_cache_size = registry
+            .new_deployment_gauge(
+                "deployment_entity_cache_size",
+                "Measures the estimated size in bytes of the entity cache of a subgraph deployment",
+                subgraph_hash,
+            )
+            .expect("failed to create `deployment_entity_cache_size` gauge");
 
         Self {
             block_trigger_count,
@@ -71,12 +153,43 @@ impl SubgraphInstanceMetrics {
             trigger_processing_duration,
             block_ops_transaction_duration,
             firehose_connection_errors,
+            block_stream_buffered_blocks,
+            throttled_seconds,
+            entity_cache_hits,
+            entity_cache_misses,
+            entity_cache_evicted_entities,
+            entity_cache_size,
             stopwatch,
+            handler_processing_duration,
+            handler_trigger_count,
+            handlers,
+            resources: RESOURCE_USAGE.deployment(subgraph_hash),
         }
     }
 
-    pub fn observe_trigger_processing_duration(&self, duration: f64) {
+    pub fn observe_trigger_processing_duration(&self, duration: f64, handler: &str) {
         self.trigger_processing_duration.observe(duration);
+        self.resources.add_wasm(Duration::from_secs_f64(duration));
+
+        let handler = if self.handlers.contains(handler) {
+            handler
+        } else {
+            OTHER_HANDLER
+        };
+        self.handler_processing_duration
+            .with_label_values(&[handler])
+            .observe(duration);
+        self.handler_trigger_count
+            .with_label_values(&[handler])
+            .inc();
+    }
+
+    pub fn observe_entity_cache(&self, stats: &EntityCacheStats) {
+        self.entity_cache_hits.inc_by(stats.hits as f64);
+        self.entity_cache_misses.inc_by(stats.misses as f64);
+        self.entity_cache_evicted_entities
+            .inc_by(stats.evicted_count as f64);
+        self.entity_cache_size.set(stats.weight as f64);
     }
 
     pub fn unregister(&self, registry: Arc<MetricsRegistry>) {
@@ -84,6 +197,14 @@ impl SubgraphInstanceMetrics {
         registry.unregister(self.block_trigger_count.clone());
         registry.unregister(self.trigger_processing_duration.clone());
         registry.unregister(self.block_ops_transaction_duration.clone());
+        registry.unregister(self.handler_processing_duration.clone());
+        registry.unregister(self.handler_trigger_count.clone());
+        registry.unregister(Box::new(self.block_stream_buffered_blocks.clone()));
+        registry.unregister(Box::new(self.throttled_seconds.clone()));
+        registry.unregister(Box::new(self.entity_cache_hits.clone()));
+        registry.unregister(Box::new(self.entity_cache_misses.clone()));
+        registry.unregister(Box::new(self.entity_cache_evicted_entities.clone()));
+        registry.unregister(Box::new(self.entity_cache_size.clone()));
     }
 }
 
@@ -0,0 +1,13 @@
+use async_trait::async_trait;
+
+use crate::prelude::Error;
+
+/// Reloads the configuration of a running node
+#[async_trait]
+pub trait ConfigReloader: Send + Sync + 'static {
+    /// Read the configuration again and apply the changes. Returns a
+    /// description of each change that was applied. If any change can not
+    /// be applied while the node is running, nothing is applied and an
+    /// error is returned
+    async fn reload(&self) -> Result<Vec<String>, Error>;
+}
@@ -6,3 +6,9 @@ pub mod subscription;
 
 /// Component for the index node server.
 pub mod index_node;
+
+/// Component for administering a running node.
+pub mod admin;
+
+/// TLS for the servers of a node.
+pub mod tls;
@@ -0,0 +1,222 @@
+//! TLS for the servers of a node, for setups where it is not possible to
+//! put a proxy in front of every port. The servers accept connections
+//! through `connections` or `incoming`, which terminate TLS if the server
+//! has a `ServerTls`, and pass plain connections through otherwise.
+//!
+//! With a client CA, clients have to present a certificate that the CA
+//! signed (mutual TLS).
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context as _, Error};
+use futures03::future::ready;
+use futures03::stream::{self, BoxStream, StreamExt};
+use hyper::server::accept::{self, Accept};
+use slog::{debug, Logger};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::rustls::server::AllowAnyAuthenticatedClient;
+use tokio_rustls::rustls::{Certificate, PrivateKey, RootCertStore, ServerConfig};
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+
+/// How long a client has to complete the TLS handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// How many TLS handshakes can be in progress at the same time
+const MAX_PENDING_HANDSHAKES: usize = 128;
+/// How long to wait before accepting connections again after accepting a
+/// connection failed, for example because the process ran out of file
+/// descriptors
+const ACCEPT_ERROR_DELAY: Duration = Duration::from_secs(1);
+
+/// The certificate and key that a server uses for TLS, and the CA that
+/// client certificates have to be signed by if clients have to
+/// authenticate
+pub struct ServerTls {
+    acceptor: TlsAcceptor,
+}
+
+impl ServerTls {
+    /// Read the certificate chain in `cert`, the private key in `key`,
+    /// and the CA certificates in `client_ca` from PEM files
+    pub fn new(cert: &Path, key: &Path, client_ca: Option<&Path>) -> Result<Self, Error> {
+        let certs = read_certs(cert)?;
+        let key = read_key(key)?;
+
+        let builder = ServerConfig::builder().with_safe_defaults();
+        let builder = match client_ca {
+            Some(client_ca) => {
+                let mut roots = RootCertStore::empty();
+                for cert in read_certs(client_ca)? {
+                    roots.add(&cert).map_err(|e| {
+                        anyhow!("invalid CA certificate in {}: {}", client_ca.display(), e)
+                    })?;
+                }
+                builder.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots))
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let config = builder
+            .with_single_cert(certs, key)
+            .context("invalid TLS certificate or key")?;
+        Ok(ServerTls {
+            acceptor: TlsAcceptor::from(Arc::new(config)),
+        })
+    }
+
+    /// Perform the TLS handshake on `stream`
+    pub async fn accept(&self, stream: TcpStream) -> io::Result<TlsStream<TcpStream>> {
+        match tokio::time::timeout(HANDSHAKE_TIMEOUT, self.acceptor.accept(stream)).await {
+            Ok(result) => result,
+            Err(_) => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "TLS handshake timed out",
+            )),
+        }
+    }
+}
+
+fn read_certs(path: &Path) -> Result<Vec<Certificate>, Error> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+        .with_context(|| format!("invalid certificates in {}", path.display()))?;
+    if certs.is_empty() {
+        return Err(anyhow!("{} does not contain certificates", path.display()));
+    }
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+fn read_key(path: &Path) -> Result<PrivateKey, Error> {
+    use rustls_pemfile::Item;
+
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let items = rustls_pemfile::read_all(&mut BufReader::new(file))
+        .with_context(|| format!("invalid private key in {}", path.display()))?;
+    items
+        .into_iter()
+        .find_map(|item| match item {
+            Item::RSAKey(key) | Item::PKCS8Key(key) | Item::ECKey(key) => Some(PrivateKey(key)),
+            _ => None,
+        })
+        .ok_or_else(|| anyhow!("{} does not contain a private key", path.display()))
+}
+
+/// A connection that a server accepted, with or without TLS
+pub enum Connection {
+    Plain(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl AsyncRead for Connection {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Connection::Plain(stream) => Pin::new(stream).poll_read(cx, buf),
+            Connection::Tls(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Connection {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Connection::Plain(stream) => Pin::new(stream).poll_write(cx, buf),
+            Connection::Tls(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Connection::Plain(stream) => Pin::new(stream).poll_flush(cx),
+            Connection::Tls(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Connection::Plain(stream) => Pin::new(stream).poll_shutdown(cx),
+            Connection::Tls(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+}
+
+/// The connections to `listener`. With `tls`, only connections that
+/// complete the TLS handshake are returned. Connections that fail are
+/// logged and skipped
+pub fn connections(
+    logger: Logger,
+    listener: TcpListener,
+    tls: Option<Arc<ServerTls>>,
+) -> BoxStream<'static, Connection> {
+    let accept_logger = logger.clone();
+    let streams = stream::unfold(listener, move |listener| {
+        let logger = accept_logger.clone();
+        async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => return Some((stream, listener)),
+                    Err(e) => {
+                        debug!(logger, "Failed to accept connection"; "error" => e.to_string());
+                        tokio::time::sleep(ACCEPT_ERROR_DELAY).await;
+                    }
+                }
+            }
+        }
+    });
+
+    match tls {
+        None => streams.map(Connection::Plain).boxed(),
+        Some(tls) => streams
+            .map(move |stream| {
+                let tls = tls.clone();
+                async move { tls.accept(stream).await }
+            })
+            .buffer_unordered(MAX_PENDING_HANDSHAKES)
+            .filter_map(move |result| {
+                ready(match result {
+                    Ok(stream) => Some(Connection::Tls(Box::new(stream))),
+                    Err(e) => {
+                        debug!(logger, "TLS handshake failed"; "error" => e.to_string());
+                        None
+                    }
+                })
+            })
+            .boxed(),
+    }
+}
+
+/// Listen on `addr` and accept connections for a `hyper` server, like
+/// `hyper::Server::try_bind` does, but with TLS if `tls` is set
+pub fn incoming(
+    logger: Logger,
+    addr: SocketAddr,
+    tls: Option<Arc<ServerTls>>,
+) -> io::Result<impl Accept<Conn = Connection, Error = io::Error>> {
+    let listener = std::net::TcpListener::bind(addr)?;
+    listener.set_nonblocking(true)?;
+    let listener = TcpListener::from_std(listener)?;
+    Ok(accept::from_stream(
+        connections(logger, listener, tls).map(Ok::<_, io::Error>),
+    ))
+}
+
+/// The URL scheme for a server with `tls`, for log messages
+pub fn scheme(tls: &Option<Arc<ServerTls>>, plain: &'static str) -> String {
+    match tls {
+        Some(_) => format!("{}s", plain),
+        None => plain.to_string(),
+    }
+}
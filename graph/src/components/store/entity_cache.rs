@@ -1,9 +1,10 @@
 use anyhow::anyhow;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{self, Debug};
 use std::sync::Arc;
 
 use crate::components::store::{self as s, Entity, EntityKey, EntityOp, EntityOperation};
+use crate::data::store::EntityValidationError;
 use crate::prelude::{Schema, ENV_VARS};
 use crate::util::lfu_cache::LfuCache;
 
@@ -34,6 +35,15 @@ pub struct EntityCache {
     pub store: Arc<dyn s::ReadStore>,
 
     schema: Arc<Schema>,
+
+    /// The entity types that were read or written through this cache, if
+    /// they are being tracked
+    accessed_types: Option<HashSet<EntityType>>,
+
+    /// The size in bytes to which `current` is shrunk in `as_modifications`
+    max_weight: usize,
+
+    stats: EntityCacheStats,
 }
 
 impl Debug for EntityCache {
@@ -45,9 +55,36 @@ impl Debug for EntityCache {
     }
 }
 
+/// Whether the reference `field` of `entity` points to the entity with id
+/// `id`, either directly or as one of the elements of a list
+fn refers_to(entity: &Entity, field: &str, id: &str) -> bool {
+    match entity.get(field) {
+        Some(s::Value::List(values)) => values.iter().any(|value| value.to_string() == id),
+        Some(value) => value.to_string() == id,
+        None => false,
+    }
+}
+
+/// How well the entity cache worked while processing a block
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EntityCacheStats {
+    /// Lookups of entities by mappings that were answered from the cache
+    pub hits: u64,
+    /// Lookups of entities by mappings that had to go to the store
+    pub misses: u64,
+    /// The number of entities that were evicted from the cache at the end
+    /// of the block
+    pub evicted_count: usize,
+    /// The estimated size in bytes of the entities that were evicted
+    pub evicted_weight: usize,
+    /// The estimated size in bytes of the cache after eviction
+    pub weight: usize,
+}
+
 pub struct ModificationsAndCache {
     pub modifications: Vec<s::EntityModification>,
     pub entity_lfu_cache: LfuCache<EntityKey, Option<Entity>>,
+    pub stats: EntityCacheStats,
 }
 
 impl EntityCache {
@@ -59,6 +96,9 @@ impl EntityCache {
             in_handler: false,
             schema: store.input_schema(),
             store,
+            accessed_types: None,
+            max_weight: ENV_VARS.mappings.entity_cache_size,
+            stats: EntityCacheStats::default(),
         }
     }
 
@@ -73,6 +113,37 @@ impl EntityCache {
             in_handler: false,
             schema: store.input_schema(),
             store,
+            accessed_types: None,
+            max_weight: ENV_VARS.mappings.entity_cache_size,
+            stats: EntityCacheStats::default(),
+        }
+    }
+
+    /// Set the size in bytes to which the cache of entities from the store
+    /// is shrunk when the block is finished. Entities are weighed by their
+    /// estimated size in memory, not counted. The default is
+    /// `GRAPH_ENTITY_CACHE_SIZE`
+    pub fn set_max_weight(&mut self, max_weight: usize) {
+        self.max_weight = max_weight;
+    }
+
+    /// Start tracking the entity types that are read or written through
+    /// this cache
+    pub fn track_accessed_types(&mut self) {
+        self.accessed_types.get_or_insert_with(HashSet::new);
+    }
+
+    /// The entity types that were read or written since
+    /// `track_accessed_types` was called, or `None` if it was not called
+    pub fn accessed_types(&self) -> Option<&HashSet<EntityType>> {
+        self.accessed_types.as_ref()
+    }
+
+    fn access(&mut self, entity_type: &EntityType) {
+        if let Some(accessed_types) = &mut self.accessed_types {
+            if !accessed_types.contains(entity_type) {
+                accessed_types.insert(entity_type.clone());
+            }
         }
     }
 
@@ -99,9 +170,13 @@ impl EntityCache {
     }
 
     pub fn get(&mut self, eref: &EntityKey) -> Result<Option<Entity>, s::QueryExecutionError> {
+        self.access(&eref.entity_type);
+
         // Get the current entity, apply any updates from `updates`, then
         // from `handler_updates`.
-        let mut entity = self.current.get_entity(&*self.store, eref)?;
+        let mut entity = self
+            .current
+            .get_entity(&*self.store, eref, &mut self.stats)?;
 
         // Always test the cache consistency in debug mode.
         debug_assert!(entity == self.store.get(eref).unwrap());
@@ -115,6 +190,27 @@ impl EntityCache {
         Ok(entity)
     }
 
+    /// Get the entity for `key` if it was changed earlier in this block.
+    /// Unlike `get`, this never reads from the store: it returns `None` if
+    /// the entity was not changed in this block or if it was removed
+    pub fn get_in_block(&mut self, key: &EntityKey) -> Option<Entity> {
+        self.access(&key.entity_type);
+        if !self.updates.contains_key(key) && !self.handler_updates.contains_key(key) {
+            return None;
+        }
+
+        // Changes are merged with the entity from the store if it has been
+        // read before; otherwise they are the entity as it was written
+        let mut entity = self.current.get(key).cloned().flatten();
+        if let Some(op) = self.updates.get(key).cloned() {
+            entity = op.apply_to(entity)
+        }
+        if let Some(op) = self.handler_updates.get(key).cloned() {
+            entity = op.apply_to(entity)
+        }
+        entity
+    }
+
     pub fn load_related(
         &mut self,
         eref: &LoadRelatedRequest,
@@ -127,13 +223,40 @@ impl EntityCache {
             value: eref.entity_id.clone(),
             causality_region: eref.causality_region,
         };
+        self.access(&query.entity_type);
 
-        let entities = self.store.get_derived(&query)?;
+        let mut entities = self.store.get_derived(&query)?;
         entities.iter().for_each(|(key, e)| {
             self.current.insert(key.clone(), Some(e.clone()));
         });
-        let entities: Vec<Entity> = entities.values().cloned().collect();
-        Ok(entities)
+
+        // The store does not know about changes made earlier in this block
+        // yet; they can add related entities, remove them, or make them
+        // point to a different entity
+        let changed: Vec<EntityKey> = self
+            .updates
+            .keys()
+            .chain(self.handler_updates.keys())
+            .filter(|key| {
+                key.entity_type == query.entity_type
+                    && key.causality_region == query.causality_region
+            })
+            .cloned()
+            .collect();
+        for key in changed {
+            match self.get(&key)? {
+                Some(entity)
+                    if refers_to(&entity, query.entity_field.as_str(), query.value.as_str()) =>
+                {
+                    entities.insert(key, entity);
+                }
+                _ => {
+                    entities.remove(&key);
+                }
+            }
+        }
+
+        Ok(entities.into_values().collect())
     }
 
     pub fn remove(&mut self, key: EntityKey) {
@@ -146,15 +269,13 @@ impl EntityCache {
     /// subgraph schema, and any errors will result in an `Err` being
     /// returned.
     pub fn set(&mut self, key: EntityKey, mut entity: Entity) -> Result<(), anyhow::Error> {
-        fn check_id(key: &EntityKey, prev_id: &str) -> Result<(), anyhow::Error> {
+        fn check_id(key: &EntityKey, prev_id: &str) -> Result<(), EntityValidationError> {
             if prev_id != key.entity_id.as_str() {
-                Err(anyhow!(
-                    "Value of {} attribute 'id' conflicts with ID passed to `store.set()`: \
-                {} != {}",
-                    key.entity_type,
-                    prev_id,
-                    key.entity_id,
-                ))
+                Err(EntityValidationError::IdConflict {
+                    entity: key.entity_type.to_string(),
+                    entity_id: key.entity_id.to_string(),
+                    value: prev_id.to_string(),
+                })
             } else {
                 Ok(())
             }
@@ -213,6 +334,8 @@ impl EntityCache {
 
     fn entity_op(&mut self, key: EntityKey, op: EntityOp) {
         use std::collections::hash_map::Entry;
+        self.access(&key.entity_type);
+
         let updates = match self.in_handler {
             true => &mut self.handler_updates,
             false => &mut self.updates,
@@ -229,12 +352,32 @@ impl EntityCache {
     pub(crate) fn extend(&mut self, other: EntityCache) {
         assert!(!other.in_handler);
 
-        self.current.extend(other.current);
+        self.current.append(other.current);
+        self.stats.hits += other.stats.hits;
+        self.stats.misses += other.stats.misses;
         for (key, op) in other.updates {
             self.entity_op(key, op);
         }
     }
 
+    /// Split off `count` caches of the state of entities in the store from
+    /// this cache. Each entity moves to the cache with the index that
+    /// `index` returns for its type, or stays in this cache if that is
+    /// `None`
+    pub fn split_current(
+        &mut self,
+        count: usize,
+        index: impl Fn(&EntityType) -> Option<usize>,
+    ) -> Vec<LfuCache<EntityKey, Option<Entity>>> {
+        self.current.split(count, |key| index(&key.entity_type))
+    }
+
+    /// Add the state of entities in the store that `other` knows about to
+    /// this cache, but discard the changes that were made through `other`
+    pub fn extend_current(&mut self, other: EntityCache) {
+        self.current.append(other.current);
+    }
+
     /// Return the changes that have been made via `set` and `remove` as
     /// `EntityModification`, making sure to only produce one when a change
     /// to the current state is actually needed.
@@ -309,11 +452,16 @@ impl EntityCache {
                 mods.push(modification)
             }
         }
-        self.current.evict(ENV_VARS.mappings.entity_cache_size);
+        if let Some(evicted) = self.current.evict(self.max_weight) {
+            self.stats.evicted_count = evicted.evicted_count;
+            self.stats.evicted_weight = evicted.evicted_weight;
+        }
+        self.stats.weight = self.current.total_weight();
 
         Ok(ModificationsAndCache {
             modifications: mods,
             entity_lfu_cache: self.current,
+            stats: self.stats,
         })
     }
 }
@@ -324,9 +472,11 @@ impl LfuCache<EntityKey, Option<Entity>> {
         &mut self,
         store: &(impl s::ReadStore + ?Sized),
         key: &EntityKey,
+        stats: &mut EntityCacheStats,
     ) -> Result<Option<Entity>, s::QueryExecutionError> {
         match self.get(key) {
             None => {
+                stats.misses += 1;
                 let mut entity = store.get(key)?;
                 if let Some(entity) = &mut entity {
                     // `__typename` is for queries not for mappings.
@@ -335,7 +485,10 @@ impl LfuCache<EntityKey, Option<Entity>> {
                 self.insert(key.clone(), entity.clone());
                 Ok(entity)
             }
-            Some(data) => Ok(data.clone()),
+            Some(data) => {
+                stats.hits += 1;
+                Ok(data.clone())
+            }
         }
     }
 }
@@ -2,7 +2,7 @@ mod entity_cache;
 mod err;
 mod traits;
 
-pub use entity_cache::{EntityCache, ModificationsAndCache};
+pub use entity_cache::{EntityCache, EntityCacheStats, ModificationsAndCache};
 
 use diesel::types::{FromSql, ToSql};
 pub use err::StoreError;
@@ -18,6 +18,7 @@ use std::borrow::Borrow;
 use std::collections::btree_map::Entry;
 use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fmt::Display;
+use std::str::FromStr;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, RwLock};
 use std::time::Duration;
@@ -227,6 +228,7 @@ pub enum EntityFilter {
     NotEndsWith(Attribute, Value),
     NotEndsWithNoCase(Attribute, Value),
     ChangeBlockGte(BlockNumber),
+    ChangeBlockLt(BlockNumber),
     Child(Child),
     Fulltext(Attribute, Value),
 }
@@ -268,6 +270,7 @@ impl fmt::Display for EntityFilter {
             NotEndsWith(a, v) => write!(f, "{a} !~ *{v}$"),
             NotEndsWithNoCase(a, v) => write!(f, "{a} !~ *{v}$i"),
             ChangeBlockGte(b) => write!(f, "block >= {b}"),
+            ChangeBlockLt(b) => write!(f, "block < {b}"),
             Child(child /* a, et, cf, _ */) => write!(
                 f,
                 "join on {} with {}({})",
@@ -348,6 +351,12 @@ pub enum EntityOrder {
     ChildAscending(EntityOrderByChild),
     /// Order descending by the given attribute of a child entity. Use `id` as a tie-breaker
     ChildDescending(EntityOrderByChild),
+    /// Order ascending by the block at which the current version of each
+    /// entity was written. Use `id` as a tie-breaker
+    ChangeBlockAscending,
+    /// Order descending by the block at which the current version of each
+    /// entity was written. Use `id` as a tie-breaker
+    ChangeBlockDescending,
     /// Order by the `id` of the entities
     Default,
     /// Do not order at all. This speeds up queries where we know that
@@ -887,6 +896,54 @@ pub enum EntityOperation {
     Remove { key: EntityKey },
 }
 
+/// A version of an entity exactly as it is stored, together with the
+/// blocks for which it is visible
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntityVersion {
+    pub vid: i64,
+    /// The block at which the version was written
+    pub start: BlockNumber,
+    /// The block at which the version was replaced or deleted, or `None` if
+    /// it is still visible
+    pub end: Option<BlockNumber>,
+    /// The columns of the version, keyed by their names in the database
+    pub data: serde_json::Value,
+}
+
+/// The position of a change in the changes that
+/// [`SubgraphStore::entity_changes_between`] returns, which are ordered by
+/// the block at which the change happened and the id of the entity. It is
+/// written as `<block>/<id>` so that clients can pass it back as an opaque
+/// string
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EntityChangeCursor {
+    pub block: BlockNumber,
+    pub id: String,
+}
+
+impl fmt::Display for EntityChangeCursor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.block, self.id)
+    }
+}
+
+impl FromStr for EntityChangeCursor {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (block, id) = s
+            .split_once('/')
+            .ok_or_else(|| anyhow!("invalid entity change cursor `{}`", s))?;
+        let block = block
+            .parse()
+            .map_err(|_| anyhow!("invalid block in entity change cursor `{}`", s))?;
+        Ok(EntityChangeCursor {
+            block,
+            id: id.to_string(),
+        })
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum UnfailOutcome {
     Noop,
@@ -957,6 +1014,18 @@ impl Display for DeploymentLocator {
     }
 }
 
+/// The versions of a subgraph at some point in time, so that the subgraph
+/// can be given them back after deploying a new version
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SubgraphVersions {
+    /// The id of the current version
+    pub current: Option<String>,
+    /// The id of the pending version
+    pub pending: Option<String>,
+    /// The deployments of the versions and the nodes they were assigned to
+    pub assignments: Vec<(DeploymentLocator, NodeId)>,
+}
+
 // The type that the connection pool uses to track wait times for
 // connection checkouts
 pub type PoolWaitStats = Arc<RwLock<MovingStats>>;
@@ -1195,6 +1264,77 @@ pub struct VersionStats {
     pub last_pruned_block: Option<BlockNumber>,
 }
 
+/// An estimate of what pruning a table would do, produced without
+/// changing any data
+#[derive(Clone, Debug)]
+pub struct PruneEstimate {
+    pub tablename: String,
+    /// The strategy that would be used for the table, or `None` if the
+    /// table would not be pruned
+    pub strategy: Option<PruningStrategy>,
+    /// The number of versions currently in the table
+    pub versions: i32,
+    /// The estimated number of versions pruning would remove
+    pub removed_versions: i64,
+    /// The size of the table including its indexes and TOAST data in bytes
+    pub total_bytes: i64,
+    /// The estimated number of bytes pruning would reclaim
+    pub reclaimable_bytes: i64,
+}
+
+impl PruneEstimate {
+    /// Estimate what pruning according to `req` would do to the table with
+    /// `stats` that takes up `total_bytes`, assuming that the space the
+    /// table uses is proportional to the number of versions in it
+    pub fn new(req: &PruneRequest, stats: &VersionStats, total_bytes: i64) -> Self {
+        let strategy = req.strategy(stats);
+        let ratio = if strategy.is_some() {
+            req.removal_ratio(stats)
+        } else {
+            0.0
+        };
+        PruneEstimate {
+            tablename: stats.tablename.clone(),
+            strategy,
+            versions: stats.versions,
+            removed_versions: (stats.versions as f64 * ratio) as i64,
+            total_bytes,
+            reclaimable_bytes: (total_bytes as f64 * ratio) as i64,
+        }
+    }
+}
+
+/// The load that indexing a deployment puts on the index node it is
+/// assigned to. Index nodes report the load of their deployments
+/// periodically so that new deployments can be placed on the node with the
+/// least load
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DeploymentLoad {
+    /// How many blocks the deployment is behind the chain head
+    pub blocks_behind: BlockNumber,
+    /// The fraction of time the deployment spent processing blocks since
+    /// the last report. A deployment that is syncing is close to 1, one
+    /// that is idle at the chain head close to 0
+    pub handler_time: f64,
+    /// How many blocks per second the deployment advanced since the last
+    /// report
+    pub blocks_per_second: f64,
+}
+
+/// How a deployment is retried after it fails with a non-deterministic
+/// error. Retries back off exponentially from `base` to `ceiling`; when
+/// those are not set, the defaults from the environment are used
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RetryPolicy {
+    /// Give up after this many retries and leave the deployment failed.
+    /// When this is `None`, the deployment is retried forever
+    pub max_attempts: Option<u32>,
+    /// The delay before the first retry
+    pub base: Option<Duration>,
+    /// The longest delay between two retries
+    pub ceiling: Option<Duration>,
+}
+
 /// What phase of pruning we are working on
 pub enum PrunePhase {
     /// Handling final entities
@@ -1352,11 +1492,7 @@ impl PruneRequest {
             return None;
         }
 
-        // Estimate how much data we will throw away; we assume that
-        // entity versions are distributed evenly across all blocks so
-        // that `history_pct` will tell us how much of that data pruning
-        // will remove.
-        let removal_ratio = self.history_pct(stats) * (1.0 - stats.ratio);
+        let removal_ratio = self.removal_ratio(stats);
         if removal_ratio >= self.rebuild_threshold {
             Some(PruningStrategy::Rebuild)
         } else if removal_ratio >= self.delete_threshold {
@@ -1366,6 +1502,14 @@ impl PruneRequest {
         }
     }
 
+    /// Estimate the fraction of the versions in the table whose `stats` we
+    /// are given that pruning will remove. We assume that entity versions
+    /// are distributed evenly across all blocks so that `history_pct` will
+    /// tell us how much of that data pruning will remove.
+    pub fn removal_ratio(&self, stats: &VersionStats) -> f64 {
+        self.history_pct(stats) * (1.0 - stats.ratio)
+    }
+
     /// Return an estimate of the fraction of the entities that are
     /// historical in the table whose `stats` we are given
     fn history_pct(&self, stats: &VersionStats) -> f64 {
@@ -1399,3 +1543,46 @@ pub struct CachedEthereumCall {
     /// The encoded return value of this call.
     pub return_value: Vec<u8>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(versions: i32, ratio: f64) -> VersionStats {
+        VersionStats {
+            entities: (versions as f64 * ratio) as i32,
+            versions,
+            tablename: "thing".to_string(),
+            ratio,
+            last_pruned_block: None,
+        }
+    }
+
+    #[test]
+    fn prune_estimate() {
+        let deployment =
+            DeploymentLocator::new(DeploymentId(1), DeploymentHash::new("test").unwrap());
+        let mut req = PruneRequest::new(&deployment, 100, 10, 0, 800).unwrap();
+        req.rebuild_threshold = 0.5;
+        req.delete_threshold = 0.05;
+
+        // 87.5% of the blocks are history, and 75% of the versions are
+        // historical, so pruning removes 65.625% of the versions
+        let estimate = PruneEstimate::new(&req, &stats(1024, 0.25), 8192);
+        assert_eq!(Some(PruningStrategy::Rebuild), estimate.strategy);
+        assert_eq!(672, estimate.removed_versions);
+        assert_eq!(8192, estimate.total_bytes);
+        assert_eq!(5376, estimate.reclaimable_bytes);
+
+        let estimate = PruneEstimate::new(&req, &stats(1024, 0.875), 8192);
+        assert_eq!(Some(PruningStrategy::Delete), estimate.strategy);
+        assert_eq!(112, estimate.removed_versions);
+        assert_eq!(896, estimate.reclaimable_bytes);
+
+        // Tables that would not be pruned reclaim nothing
+        let estimate = PruneEstimate::new(&req, &stats(1024, 0.96875), 8192);
+        assert_eq!(None, estimate.strategy);
+        assert_eq!(0, estimate.removed_versions);
+        assert_eq!(0, estimate.reclaimable_bytes);
+    }
+}
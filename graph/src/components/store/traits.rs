@@ -3,6 +3,7 @@ use web3::types::{Address, H256};
 use super::*;
 use crate::blockchain::block_stream::FirehoseCursor;
 use crate::components::server::index_node::VersionInfo;
+use crate::components::subgraph::ProofOfIndexingVersion;
 use crate::components::transaction_receipt;
 use crate::components::versions::ApiVersion;
 use crate::data::query::Trace;
@@ -92,14 +93,56 @@ pub trait SubgraphStore: Send + Sync + 'static {
         node_id: &NodeId,
     ) -> Result<(), StoreError>;
 
+    /// Pause indexing of the deployment without removing its assignment.
+    /// The deployment can still be queried while it is paused
+    fn pause_subgraph(&self, deployment: &DeploymentLocator) -> Result<(), StoreError>;
+
+    /// Resume indexing of a deployment that was paused with
+    /// `pause_subgraph`
+    fn resume_subgraph(&self, deployment: &DeploymentLocator) -> Result<(), StoreError>;
+
+    /// Rewind the deployment to `block_ptr_to`, removing all data for
+    /// later blocks. The deployment should not be indexing while it is
+    /// rewound, and can not be rewound to before its earliest block
+    fn rewind(&self, id: DeploymentHash, block_ptr_to: BlockPtr) -> Result<(), StoreError>;
+
+    /// The name of the network the deployment indexes
+    fn network_name(&self, deployment: &DeploymentLocator) -> Result<String, StoreError>;
+
     fn assigned_node(&self, deployment: &DeploymentLocator) -> Result<Option<NodeId>, StoreError>;
 
+    /// Return the node the deployment is assigned to and whether indexing
+    /// of it is paused, or `None` if the deployment is not assigned
+    fn assignment_status(
+        &self,
+        deployment: &DeploymentLocator,
+    ) -> Result<Option<(NodeId, bool)>, StoreError>;
+
+    /// Return the deployments assigned to `node`, leaving out paused ones
     fn assignments(&self, node: &NodeId) -> Result<Vec<DeploymentLocator>, StoreError>;
 
     /// Return `true` if a subgraph `name` exists, regardless of whether the
     /// subgraph has any deployments attached to it
     fn subgraph_exists(&self, name: &SubgraphName) -> Result<bool, StoreError>;
 
+    /// Return the versions of the subgraph `name`, or `None` if there is no
+    /// subgraph with that name
+    fn subgraph_versions(
+        &self,
+        name: &SubgraphName,
+    ) -> Result<Option<SubgraphVersions>, StoreError>;
+
+    /// Give the subgraph `name` back the `versions` that
+    /// [`SubgraphStore::subgraph_versions`] returned earlier. Deployments of
+    /// those versions that have been unassigned since are assigned to the
+    /// nodes they were assigned to again, and deployments that are not used
+    /// anymore are unassigned
+    fn restore_subgraph_versions(
+        &self,
+        name: &SubgraphName,
+        versions: &SubgraphVersions,
+    ) -> Result<(), StoreError>;
+
     /// Returns a collection of all [`EntityModification`] items in relation to
     /// the given [`BlockNumber`]. No distinction is made between inserts and
     /// updates, which may be returned as either [`EntityModification::Insert`]
@@ -110,6 +153,37 @@ pub trait SubgraphStore: Send + Sync + 'static {
         block_number: BlockNumber,
     ) -> Result<Vec<EntityOperation>, StoreError>;
 
+    /// Return the changes to entities of type `entity_type` after block
+    /// `from` up to and including block `to`. Entities that were inserted or
+    /// updated are returned as [`EntityOperation::Set`] with their data at
+    /// block `to`, entities that were deleted as [`EntityOperation::Remove`].
+    /// Changes are ordered by the block at which they happened and the id of
+    /// the entity, and at most `first` changes after `after` are returned,
+    /// together with the cursor for the next page if there might be more.
+    /// Fails if `to` is after the latest block of the deployment, or if
+    /// `from` is before its earliest block
+    fn entity_changes_between(
+        &self,
+        subgraph_id: &DeploymentHash,
+        entity_type: &EntityType,
+        from: BlockNumber,
+        to: BlockNumber,
+        after: Option<&EntityChangeCursor>,
+        first: usize,
+    ) -> Result<(Vec<EntityOperation>, Option<EntityChangeCursor>), StoreError>;
+
+    /// Return all stored versions of the entity `entity_type` with id
+    /// `entity_id`, ordered by the block at which they were written. With
+    /// `block`, only the versions that were written at or before `block`
+    /// are returned
+    fn entity_versions(
+        &self,
+        subgraph_id: &DeploymentHash,
+        entity_type: &EntityType,
+        entity_id: &str,
+        block: Option<BlockNumber>,
+    ) -> Result<Vec<EntityVersion>, StoreError>;
+
     /// Return the GraphQL schema supplied by the user
     fn input_schema(&self, subgraph_id: &DeploymentHash) -> Result<Arc<Schema>, StoreError>;
 
@@ -148,12 +222,20 @@ pub trait SubgraphStore: Send + Sync + 'static {
     /// `writable` might have started
     async fn stop_subgraph(&self, deployment: &DeploymentLocator) -> Result<(), StoreError>;
 
+    /// Write all changes that writables have queued and stop them. This is
+    /// meant to be called when the node shuts down
+    async fn stop_all_subgraphs(&self) -> Result<(), StoreError>;
+
     /// Return the minimum block pointer of all deployments with this `id`
     /// that we would use to query or copy from; in particular, this will
     /// ignore any instances of this deployment that are in the process of
     /// being set up
     async fn least_block_ptr(&self, id: &DeploymentHash) -> Result<Option<BlockPtr>, StoreError>;
 
+    /// Return the earliest block for which the deployment with this `id`
+    /// still has all its data. Blocks before it have been pruned
+    async fn earliest_block(&self, id: &DeploymentHash) -> Result<BlockNumber, StoreError>;
+
     async fn is_healthy(&self, id: &DeploymentHash) -> Result<bool, StoreError>;
 
     /// Find all deployment locators for the subgraph with the given hash.
@@ -174,6 +256,39 @@ pub trait SubgraphStore: Send + Sync + 'static {
     /// When this flag is set, indexing of the deployment should log
     /// additional diagnostic information
     fn instrument(&self, deployment: &DeploymentLocator) -> Result<bool, StoreError>;
+
+    /// Return how the deployment should be retried after non-deterministic
+    /// errors
+    fn retry_policy(&self, deployment: &DeploymentLocator) -> Result<RetryPolicy, StoreError>;
+
+    /// Return how many times the deployment has been retried since it last
+    /// failed with a non-deterministic error
+    fn retry_count(&self, deployment: &DeploymentLocator) -> Result<u32, StoreError>;
+
+    /// Return the size limit in bytes of the entity cache that indexing the
+    /// deployment keeps between blocks
+    fn entity_cache_size(&self, deployment: &DeploymentLocator) -> Result<usize, StoreError>;
+
+    /// Return how many dynamic data sources the deployment may create, or
+    /// `None` if that is not limited for the deployment
+    fn max_dynamic_data_sources(
+        &self,
+        deployment: &DeploymentLocator,
+    ) -> Result<Option<usize>, StoreError>;
+
+    /// Return how many blocks per second the deployment may process while
+    /// it is catching up, or `None` if that is not limited
+    fn max_blocks_per_second(
+        &self,
+        deployment: &DeploymentLocator,
+    ) -> Result<Option<u32>, StoreError>;
+
+    /// Return the version of the POI that the deployment computes in
+    /// addition to its own version during a transition, if any
+    fn poi_transition_version(
+        &self,
+        deployment: &DeploymentLocator,
+    ) -> Result<Option<ProofOfIndexingVersion>, StoreError>;
 }
 
 pub trait ReadStore: Send + Sync + 'static {
@@ -306,6 +421,24 @@ pub trait WritableStore: ReadStore + DeploymentCursorTracker {
 
     fn unassign_subgraph(&self) -> Result<(), StoreError>;
 
+    /// Record the current load of the deployment so that the deployment
+    /// scheduler can take it into account
+    fn report_load(&self, load: DeploymentLoad) -> Result<(), StoreError>;
+
+    /// Record how many times the deployment has been retried since it
+    /// last failed with a non-deterministic error, so that deployments that
+    /// are stuck retrying show up in the indexing status
+    async fn set_retry_count(&self, count: u32) -> Result<(), StoreError>;
+
+    /// Remember `keys` as the entities that the deployment used most often
+    /// so that they can be loaded into its entity cache when it starts
+    /// again
+    async fn save_entity_cache_snapshot(&self, keys: Vec<EntityKey>) -> Result<(), StoreError>;
+
+    /// The keys that were last saved with `save_entity_cache_snapshot`,
+    /// most frequently used first
+    async fn entity_cache_snapshot(&self) -> Result<Vec<EntityKey>, StoreError>;
+
     /// Load the dynamic data sources for the given deployment
     async fn load_dynamic_data_sources(
         &self,
@@ -341,6 +474,12 @@ pub trait QueryStoreManager: Send + Sync + 'static {
         target: QueryTarget,
         for_subscription: bool,
     ) -> Result<Arc<dyn QueryStore + Send + Sync>, QueryExecutionError>;
+
+    /// The deployment that `query_store` would use for `target`
+    async fn deployment_for(
+        &self,
+        target: &QueryTarget,
+    ) -> Result<DeploymentHash, QueryExecutionError>;
 }
 
 pub trait BlockStore: Send + Sync + 'static {
@@ -552,11 +691,17 @@ pub trait StatusStore: Send + Sync + 'static {
     /// re-sync from scratch, so existing deployments will continue without a
     /// Proof of Indexing. Once all subgraphs have been re-deployed the Option
     /// can be removed.
+    ///
+    /// The POI is computed with the version of the deployment unless
+    /// `version` asks for a different one. That is only possible for the
+    /// version that the deployment computes during a transition; for any
+    /// other version, the result is `None`
     async fn get_proof_of_indexing(
         &self,
         subgraph_id: &DeploymentHash,
         indexer: &Option<Address>,
         block: BlockPtr,
+        version: Option<ProofOfIndexingVersion>,
     ) -> Result<Option<[u8; 32]>, StoreError>;
 
     /// Like `get_proof_of_indexing` but returns a Proof of Indexing signed by
@@ -567,4 +712,21 @@ pub trait StatusStore: Send + Sync + 'static {
         subgraph_id: &DeploymentHash,
         block_number: BlockNumber,
     ) -> Result<Option<(PartialBlockPtr, [u8; 32])>, StoreError>;
+
+    /// The GraphQL schema and the manifest of the active deployment for
+    /// `subgraph_id`, or `None` if there is no such deployment
+    fn raw_manifest(
+        &self,
+        subgraph_id: &DeploymentHash,
+    ) -> Result<Option<status::RawManifest>, StoreError>;
+
+    /// All data sources that the active deployment for `subgraph_id` has
+    /// created from templates, in the order in which they were created.
+    /// `manifest_idx_and_name` is the index and name of each template in
+    /// the manifest
+    async fn dynamic_data_sources(
+        &self,
+        subgraph_id: &DeploymentHash,
+        manifest_idx_and_name: Vec<(u32, String)>,
+    ) -> Result<Vec<StoredDynamicDataSource>, StoreError>;
 }
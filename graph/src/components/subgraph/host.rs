@@ -1,12 +1,13 @@
 use std::cmp::PartialEq;
-use std::sync::Arc;
-use std::time::Instant;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
 
 use anyhow::Error;
 use async_trait::async_trait;
 use futures::sync::mpsc;
 
 use crate::components::store::SubgraphFork;
+use crate::data::subgraph::HandlerLimits;
 use crate::data_source::{
     DataSource, DataSourceTemplate, MappingTrigger, TriggerData, TriggerWithHandler,
 };
@@ -84,9 +85,16 @@ pub trait RuntimeHost<C: Blockchain>: Send + Sync + 'static {
     fn set_done_at(&self, block: Option<BlockNumber>);
 }
 
+/// The number of different counters that the mappings of a deployment can
+/// create with `metrics.increment`. Increments of any other counters are
+/// ignored so that a mapping can not flood Prometheus with time series
+pub const MAX_MAPPING_COUNTERS: usize = 100;
+
 pub struct HostMetrics {
     handler_execution_time: Box<HistogramVec>,
     host_fn_execution_time: Box<HistogramVec>,
+    mapping_counters: Box<CounterVec>,
+    mapping_counter_names: Mutex<HashSet<String>>,
     pub stopwatch: StopwatchMetrics,
 }
 
@@ -114,9 +122,19 @@ impl HostMetrics {
                 vec![0.025, 0.05, 0.2, 2.0, 8.0, 20.0],
             )
             .expect("failed to create `deployment_host_fn_execution_time` histogram");
+        let mapping_counters = registry
+            .new_deployment_counter_vec(
+                "deployment_mapping_counter",
+                "Counters that mappings increment with `metrics.increment`",
+                subgraph,
+                vec![String::from("name")],
+            )
+            .expect("failed to create `deployment_mapping_counter` counter");
         Self {
             handler_execution_time,
             host_fn_execution_time,
+            mapping_counters,
+            mapping_counter_names: Mutex::new(HashSet::new()),
             stopwatch,
         }
     }
@@ -133,30 +151,23 @@ impl HostMetrics {
             .observe(duration);
     }
 
-    pub fn time_host_fn_execution_region(
-        self: Arc<HostMetrics>,
-        fn_name: &'static str,
-    ) -> HostFnExecutionTimer {
-        HostFnExecutionTimer {
-            start: Instant::now(),
-            metrics: self,
-            fn_name,
+    /// Increment the mapping counter `name` by `value`. Returns `false`
+    /// without changing anything if the deployment already has
+    /// `MAX_MAPPING_COUNTERS` other counters
+    pub fn increment_mapping_counter(&self, name: &str, value: f64) -> bool {
+        {
+            let mut names = self.mapping_counter_names.lock().unwrap();
+            if !names.contains(name) {
+                if names.len() >= MAX_MAPPING_COUNTERS {
+                    return false;
+                }
+                names.insert(name.to_string());
+            }
         }
-    }
-}
-
-#[must_use]
-pub struct HostFnExecutionTimer {
-    start: Instant,
-    metrics: Arc<HostMetrics>,
-    fn_name: &'static str,
-}
-
-impl Drop for HostFnExecutionTimer {
-    fn drop(&mut self) {
-        let elapsed = (Instant::now() - self.start).as_secs_f64();
-        self.metrics
-            .observe_host_fn_execution_time(elapsed, self.fn_name)
+        self.mapping_counters
+            .with_label_values(&[name][..])
+            .inc_by(value);
+        true
     }
 }
 
@@ -182,5 +193,6 @@ pub trait RuntimeHostBuilder<C: Blockchain>: Clone + Send + Sync + 'static {
         logger: Logger,
         subgraph_id: DeploymentHash,
         metrics: Arc<HostMetrics>,
+        handler_limits: &HandlerLimits,
     ) -> Result<mpsc::Sender<Self::Req>, anyhow::Error>;
 }
@@ -1,6 +1,10 @@
+use std::collections::HashMap;
+
 use crate::{
     blockchain::Blockchain,
+    components::metrics::subgraph::SubgraphInstanceMetrics,
     components::store::{EntityKey, ReadStore, StoredDynamicDataSource},
+    components::subgraph::HostMetrics,
     data::subgraph::schema::SubgraphError,
     data_source::DataSourceTemplate,
     prelude::*,
@@ -15,6 +19,14 @@ pub struct DataSourceTemplateInfo<C: Blockchain> {
     pub creation_block: BlockNumber,
 }
 
+/// How long some part of processing a block took
+#[derive(Debug)]
+enum Timing {
+    Handler(String, f64),
+    HostFn(String, f64),
+    Trigger(String, f64),
+}
+
 #[derive(Debug)]
 pub struct BlockState<C: Blockchain> {
     pub entity_cache: EntityCache,
@@ -32,18 +44,46 @@ pub struct BlockState<C: Blockchain> {
 
     // Marks whether a handler is currently executing.
     in_handler: bool,
+
+    // The number of times deterministic random bytes have been requested
+    // in this block, so that every request gets different bytes.
+    random_counter: u64,
+
+    // Increments of mapping counters, which are only exported once the
+    // changes of the block have been written.
+    metric_increments: Vec<(String, f64)>,
+
+    // Increments of mapping counters in the current handler.
+    handler_metric_increments: Vec<(String, f64)>,
+
+    // The responses to `http.get` requests in this block, by URL.
+    http_responses: HashMap<String, Vec<u8>>,
+
+    // Timings that are held back until it is known whether the results
+    // of processing are kept, or `None` if timings are observed right away.
+    deferred_timings: Option<Vec<Timing>>,
 }
 
 impl<C: Blockchain> BlockState<C> {
     pub fn new(store: impl ReadStore, lfu_cache: LfuCache<EntityKey, Option<Entity>>) -> Self {
+        Self::with_entity_cache(EntityCache::with_current(Arc::new(store), lfu_cache))
+    }
+
+    /// A block state whose changes are added to those in `entity_cache`
+    pub fn with_entity_cache(entity_cache: EntityCache) -> Self {
         BlockState {
-            entity_cache: EntityCache::with_current(Arc::new(store), lfu_cache),
+            entity_cache,
             deterministic_errors: Vec::new(),
             created_data_sources: Vec::new(),
             persisted_data_sources: Vec::new(),
             handler_created_data_sources: Vec::new(),
             processed_data_sources: Vec::new(),
             in_handler: false,
+            random_counter: 0,
+            metric_increments: Vec::new(),
+            handler_metric_increments: Vec::new(),
+            http_responses: HashMap::new(),
+            deferred_timings: None,
         }
     }
 
@@ -58,16 +98,40 @@ impl<C: Blockchain> BlockState<C> {
             handler_created_data_sources,
             processed_data_sources,
             in_handler,
+            random_counter,
+            metric_increments,
+            handler_metric_increments,
+            http_responses,
+            deferred_timings,
         } = self;
 
         match in_handler {
-            true => handler_created_data_sources.extend(other.created_data_sources),
-            false => created_data_sources.extend(other.created_data_sources),
+            true => {
+                handler_created_data_sources.extend(other.created_data_sources);
+                handler_metric_increments.extend(other.metric_increments);
+            }
+            false => {
+                created_data_sources.extend(other.created_data_sources);
+                metric_increments.extend(other.metric_increments);
+            }
         }
         deterministic_errors.extend(other.deterministic_errors);
         entity_cache.extend(other.entity_cache);
         processed_data_sources.extend(other.processed_data_sources);
         persisted_data_sources.extend(other.persisted_data_sources);
+        *random_counter += other.random_counter;
+        http_responses.extend(other.http_responses);
+        if let (Some(timings), Some(other_timings)) = (deferred_timings, other.deferred_timings) {
+            timings.extend(other_timings);
+        }
+    }
+
+    /// Keep what `other` read from the store and fetched with `http.get`
+    /// so that it does not have to be read or fetched again, but discard
+    /// all changes and timings of `other`
+    pub fn extend_caches(&mut self, other: BlockState<C>) {
+        self.entity_cache.extend_current(other.entity_cache);
+        self.http_responses.extend(other.http_responses);
     }
 
     pub fn has_errors(&self) -> bool {
@@ -95,6 +159,8 @@ impl<C: Blockchain> BlockState<C> {
         self.in_handler = false;
         self.created_data_sources
             .append(&mut self.handler_created_data_sources);
+        self.metric_increments
+            .append(&mut self.handler_metric_increments);
         self.entity_cache.exit_handler()
     }
 
@@ -102,10 +168,110 @@ impl<C: Blockchain> BlockState<C> {
         assert!(self.in_handler);
         self.in_handler = false;
         self.handler_created_data_sources.clear();
+        self.handler_metric_increments.clear();
         self.entity_cache.exit_handler_and_discard_changes();
         self.deterministic_errors.push(e);
     }
 
+    /// Return the counter for the next request for random bytes
+    pub fn next_random_counter(&mut self) -> u64 {
+        let counter = self.random_counter;
+        self.random_counter += 1;
+        counter
+    }
+
+    /// Whether any handler requested random bytes in this block
+    pub fn uses_random_bytes(&self) -> bool {
+        self.random_counter > 0
+    }
+
+    /// Increment the mapping counter `name` by `value` once the changes of
+    /// the block have been written
+    pub fn increment_metric(&mut self, name: String, value: f64) {
+        match self.in_handler {
+            true => self.handler_metric_increments.push((name, value)),
+            false => self.metric_increments.push((name, value)),
+        }
+    }
+
+    pub fn drain_metric_increments(&mut self) -> Vec<(String, f64)> {
+        assert!(!self.in_handler);
+        std::mem::take(&mut self.metric_increments)
+    }
+
+    /// The response to an `http.get` request for `url` earlier in this block
+    pub fn http_response(&self, url: &str) -> Option<&Vec<u8>> {
+        self.http_responses.get(url)
+    }
+
+    pub fn cache_http_response(&mut self, url: String, bytes: Vec<u8>) {
+        self.http_responses.insert(url, bytes);
+    }
+
+    /// Hold back the timings that are passed to the `observe_*` methods
+    /// until `observe_deferred_timings` is called
+    pub fn defer_timings(&mut self) {
+        self.deferred_timings.get_or_insert_with(Vec::new);
+    }
+
+    pub fn observe_handler_execution_time(
+        &mut self,
+        metrics: &HostMetrics,
+        duration: f64,
+        handler: &str,
+    ) {
+        match &mut self.deferred_timings {
+            Some(timings) => timings.push(Timing::Handler(handler.to_string(), duration)),
+            None => metrics.observe_handler_execution_time(duration, handler),
+        }
+    }
+
+    pub fn observe_host_fn_execution_time(
+        &mut self,
+        metrics: &HostMetrics,
+        duration: f64,
+        fn_name: &str,
+    ) {
+        match &mut self.deferred_timings {
+            Some(timings) => timings.push(Timing::HostFn(fn_name.to_string(), duration)),
+            None => metrics.observe_host_fn_execution_time(duration, fn_name),
+        }
+    }
+
+    pub fn observe_trigger_processing_duration(
+        &mut self,
+        metrics: &SubgraphInstanceMetrics,
+        duration: f64,
+        handler: &str,
+    ) {
+        match &mut self.deferred_timings {
+            Some(timings) => timings.push(Timing::Trigger(handler.to_string(), duration)),
+            None => metrics.observe_trigger_processing_duration(duration, handler),
+        }
+    }
+
+    /// Observe the timings that were held back since `defer_timings` was
+    /// called, and observe timings right away from now on
+    pub fn observe_deferred_timings(
+        &mut self,
+        host_metrics: &HostMetrics,
+        subgraph_metrics: &SubgraphInstanceMetrics,
+    ) {
+        for timing in self.deferred_timings.take().unwrap_or_default() {
+            match timing {
+                Timing::Handler(handler, duration) => {
+                    host_metrics.observe_handler_execution_time(duration, &handler)
+                }
+                Timing::HostFn(fn_name, duration) => {
+                    host_metrics.observe_host_fn_execution_time(duration, &fn_name)
+                }
+                Timing::Trigger(handler, duration) => {
+                    subgraph_metrics.observe_trigger_processing_duration(duration, &handler)
+                }
+            }
+        }
+    }
+
     pub fn push_created_data_source(&mut self, ds: DataSourceTemplateInfo<C>) {
         assert!(self.in_handler);
         self.handler_created_data_sources.push(ds);
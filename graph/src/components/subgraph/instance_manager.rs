@@ -17,4 +17,8 @@ pub trait SubgraphInstanceManager: Send + Sync + 'static {
         stop_block: Option<BlockNumber>,
     );
     async fn stop_subgraph(&self, deployment: DeploymentLocator);
+
+    /// Stop all subgraphs at the next block boundary and write the blocks
+    /// that they have already processed. Used when the node shuts down
+    async fn stop_all_subgraphs(&self);
 }
@@ -1,6 +1,8 @@
 mod host;
 mod instance;
 mod instance_manager;
+mod notifier;
+mod offchain_status;
 mod proof_of_indexing;
 mod provider;
 mod registrar;
@@ -10,6 +12,10 @@ pub use crate::prelude::Entity;
 pub use self::host::{HostMetrics, MappingError, RuntimeHost, RuntimeHostBuilder};
 pub use self::instance::{BlockState, DataSourceTemplateInfo};
 pub use self::instance_manager::SubgraphInstanceManager;
+pub use self::notifier::{DeploymentEvent, Notifier};
+pub use self::offchain_status::{
+    OffchainFiles, OffchainStatusRegistry, OffchainSummary, OFFCHAIN_STATUS,
+};
 pub use self::proof_of_indexing::{
     PoICausalityRegion, ProofOfIndexing, ProofOfIndexingEvent, ProofOfIndexingFinisher,
     ProofOfIndexingVersion, SharedProofOfIndexing,
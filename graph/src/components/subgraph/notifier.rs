@@ -0,0 +1,175 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::anyhow;
+use serde::Serialize;
+use url::Url;
+
+use crate::env::EnvVars;
+use crate::prelude::{
+    o, reqwest, warn, Arc, BlockNumber, CheapClone, DeploymentHash, Error, Logger, NodeId,
+};
+
+/// How long to wait for a webhook to accept a notification
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A change in the lifecycle of a deployment that operators might want to
+/// be alerted about
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum DeploymentEvent {
+    /// The deployment recovered from an error
+    Healthy,
+    /// The deployment failed
+    Failed { error: String, deterministic: bool },
+    /// The deployment caught up with the chain head
+    Synced,
+    /// The deployment fell more than `GRAPH_NOTIFICATION_LAG_THRESHOLD`
+    /// blocks behind the chain head
+    Lagging { blocks_behind: BlockNumber },
+    /// The deployment was moved to another index node
+    Reassigned { from: Option<NodeId>, to: NodeId },
+}
+
+/// The body of the request sent to webhooks
+#[derive(Serialize)]
+struct Notification<'a> {
+    deployment: &'a DeploymentHash,
+    node: &'a NodeId,
+    timestamp: u64,
+    #[serde(flatten)]
+    event: &'a DeploymentEvent,
+}
+
+/// Posts `DeploymentEvent`s as JSON to a list of webhooks. Notifications
+/// are sent on a best-effort basis: webhooks that can not be reached are
+/// logged and otherwise ignored
+pub struct Notifier {
+    logger: Logger,
+    node: NodeId,
+    client: reqwest::Client,
+    webhooks: Vec<Url>,
+}
+
+impl Notifier {
+    pub fn new(logger: &Logger, node: NodeId, webhooks: Vec<Url>) -> Self {
+        Notifier {
+            logger: logger.new(o!("component" => "Notifier")),
+            node,
+            client: reqwest::Client::new(),
+            webhooks,
+        }
+    }
+
+    /// A notifier for the webhooks set with `GRAPH_NOTIFICATION_WEBHOOKS`
+    pub fn from_env(logger: &Logger, node: NodeId, env: &EnvVars) -> Result<Self, Error> {
+        let webhooks = env
+            .notification_webhooks
+            .iter()
+            .map(|url| Url::parse(url).map_err(|e| anyhow!("invalid webhook {}: {}", url, e)))
+            .collect::<Result<_, _>>()?;
+        Ok(Self::new(logger, node, webhooks))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.webhooks.is_empty()
+    }
+
+    /// Post `event` for `deployment` to all webhooks and wait for them to
+    /// respond
+    pub async fn send(&self, deployment: &DeploymentHash, event: &DeploymentEvent) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let notification = Notification {
+            deployment,
+            node: &self.node,
+            timestamp,
+            event,
+        };
+
+        for webhook in &self.webhooks {
+            let res = self
+                .client
+                .post(webhook.clone())
+                .json(&notification)
+                .timeout(WEBHOOK_TIMEOUT)
+                .send()
+                .await
+                .and_then(|res| res.error_for_status());
+            if let Err(e) = res {
+                warn!(self.logger, "Failed to send notification";
+                      "webhook" => webhook.as_str(),
+                      "deployment" => deployment.as_str(),
+                      "error" => e.to_string());
+            }
+        }
+    }
+
+    /// Like `send`, but send the notification in the background so that
+    /// slow webhooks do not hold up the caller
+    pub fn notify(self: &Arc<Self>, deployment: &DeploymentHash, event: DeploymentEvent) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let notifier = self.cheap_clone();
+        let deployment = deployment.clone();
+        crate::spawn(async move { notifier.send(&deployment, &event).await });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{DeploymentEvent, Notification};
+    use crate::prelude::{DeploymentHash, NodeId};
+
+    #[test]
+    fn notification_json() {
+        let deployment = DeploymentHash::new("QmTest").unwrap();
+        let node = NodeId::new("index_node_0").unwrap();
+        let event = DeploymentEvent::Reassigned {
+            from: None,
+            to: NodeId::new("index_node_1").unwrap(),
+        };
+        let notification = Notification {
+            deployment: &deployment,
+            node: &node,
+            timestamp: 17,
+            event: &event,
+        };
+        assert_eq!(
+            json!({
+                "deployment": "QmTest",
+                "node": "index_node_0",
+                "timestamp": 17,
+                "event": "reassigned",
+                "from": null,
+                "to": "index_node_1"
+            }),
+            serde_json::to_value(&notification).unwrap()
+        );
+
+        let event = DeploymentEvent::Lagging { blocks_behind: 500 };
+        let notification = Notification {
+            event: &event,
+            ..notification
+        };
+        assert_eq!(
+            json!({
+                "deployment": "QmTest",
+                "node": "index_node_0",
+                "timestamp": 17,
+                "event": "lagging",
+                "blocks_behind": 500
+            }),
+            serde_json::to_value(&notification).unwrap()
+        );
+    }
+}
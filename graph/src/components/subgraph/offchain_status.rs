@@ -0,0 +1,196 @@
+//! The state of the files that file data sources are waiting for. The
+//! polling monitors that fetch files report what happened to each file, so
+//! that files that never resolve can be found through the index node API.
+//! The state is only kept in memory on the node that indexes a deployment,
+//! and only covers files that were monitored since the node started.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// The state of the files of all deployments on this node
+    pub static ref OFFCHAIN_STATUS: OffchainStatusRegistry = OffchainStatusRegistry::default();
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum FileState {
+    /// The file has not been found yet
+    Pending,
+    /// The last attempt to fetch the file failed; it will be retried
+    Failed,
+    /// The file was fetched and handed to the data source
+    Fetched,
+}
+
+#[derive(Debug)]
+struct FileStatus {
+    state: FileState,
+    since: Instant,
+    bytes: u64,
+}
+
+/// Aggregate state of the files of one deployment
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OffchainSummary {
+    pub pending: u64,
+    pub failed: u64,
+    pub fetched: u64,
+    /// The total size of the files that were fetched
+    pub bytes_fetched: u64,
+    /// How long the file that has been waiting the longest without being
+    /// fetched has been waiting
+    pub oldest_pending: Option<Duration>,
+}
+
+/// The files of one deployment, keyed by how the polling monitor displays
+/// them, e.g., the CID and path of an IPFS file
+#[derive(Debug, Default)]
+pub struct OffchainFiles {
+    files: Mutex<HashMap<String, FileStatus>>,
+}
+
+impl OffchainFiles {
+    /// Start waiting for `file`. Files that are monitored again, for
+    /// example after a revert, start waiting from scratch
+    pub fn monitor(&self, file: String) {
+        self.files.lock().unwrap().insert(
+            file,
+            FileStatus {
+                state: FileState::Pending,
+                since: Instant::now(),
+                bytes: 0,
+            },
+        );
+    }
+
+    fn set_state(&self, file: &str, state: FileState) {
+        if let Some(status) = self.files.lock().unwrap().get_mut(file) {
+            if status.state != FileState::Fetched {
+                status.state = state;
+            }
+        }
+    }
+
+    /// The file could not be found and will be polled again
+    pub fn not_found(&self, file: &str) {
+        self.set_state(file, FileState::Pending)
+    }
+
+    /// Fetching the file failed and will be retried after a backoff
+    pub fn failed(&self, file: &str) {
+        self.set_state(file, FileState::Failed)
+    }
+
+    pub fn fetched(&self, file: &str, bytes: u64) {
+        if let Some(status) = self.files.lock().unwrap().get_mut(file) {
+            status.state = FileState::Fetched;
+            status.bytes = bytes;
+        }
+    }
+
+    fn summary_at(&self, now: Instant) -> OffchainSummary {
+        let mut summary = OffchainSummary::default();
+        for status in self.files.lock().unwrap().values() {
+            match status.state {
+                FileState::Pending => summary.pending += 1,
+                FileState::Failed => summary.failed += 1,
+                FileState::Fetched => {
+                    summary.fetched += 1;
+                    summary.bytes_fetched += status.bytes;
+                    continue;
+                }
+            }
+            let waiting = now.saturating_duration_since(status.since);
+            if summary
+                .oldest_pending
+                .map_or(true, |oldest| waiting > oldest)
+            {
+                summary.oldest_pending = Some(waiting);
+            }
+        }
+        summary
+    }
+
+    pub fn summary(&self) -> OffchainSummary {
+        self.summary_at(Instant::now())
+    }
+}
+
+/// The files of all deployments that used file data sources on this node
+/// since it started
+#[derive(Debug, Default)]
+pub struct OffchainStatusRegistry {
+    deployments: RwLock<HashMap<String, Arc<OffchainFiles>>>,
+}
+
+impl OffchainStatusRegistry {
+    /// The files of `deployment`, which are created if they do not exist
+    /// yet. Callers should hold on to the result rather than look it up
+    /// for every file
+    pub fn deployment(&self, deployment: &str) -> Arc<OffchainFiles> {
+        if let Some(files) = self.deployments.read().unwrap().get(deployment) {
+            return files.clone();
+        }
+        self.deployments
+            .write()
+            .unwrap()
+            .entry(deployment.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// The state of the files of `deployment`, or `None` if it did not
+    /// monitor any files on this node
+    pub fn summary(&self, deployment: &str) -> Option<OffchainSummary> {
+        self.deployments
+            .read()
+            .unwrap()
+            .get(deployment)
+            .map(|files| files.summary())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{OffchainFiles, OffchainSummary};
+
+    #[test]
+    fn summarizes_files() {
+        let files = OffchainFiles::default();
+        files.monitor("a".to_string());
+        files.monitor("b".to_string());
+        files.monitor("c".to_string());
+        files.not_found("a");
+        files.failed("b");
+        files.fetched("c", 42);
+        // Files that are not monitored are ignored
+        files.fetched("d", 7);
+
+        let now = Instant::now() + Duration::from_secs(60);
+        let summary = files.summary_at(now);
+        assert_eq!(1, summary.pending);
+        assert_eq!(1, summary.failed);
+        assert_eq!(1, summary.fetched);
+        assert_eq!(42, summary.bytes_fetched);
+        assert!(summary.oldest_pending.unwrap() >= Duration::from_secs(60));
+
+        // A fetched file stays fetched even if a stale response arrives
+        files.failed("c");
+        files.fetched("a", 8);
+        files.fetched("b", 16);
+        assert_eq!(
+            OffchainSummary {
+                pending: 0,
+                failed: 0,
+                fetched: 3,
+                bytes_fetched: 66,
+                oldest_pending: None,
+            },
+            files.summary_at(now)
+        );
+    }
+}
@@ -56,6 +56,102 @@ pub enum ProofOfIndexingEvent<'a> {
     ///
     /// for the first and second cases respectively.
     DeterministicError { redacted_events: u64 },
+    /// For when a mapping fetched `url` with `http.get`. The `hash` is the
+    /// hex-encoded keccak256 hash of the response body, so that indexers
+    /// that were served different responses produce different PoIs.
+    HttpResponse { url: &'a str, hash: &'a str },
+    /// For when a mapping created a file data source for the object `url`
+    /// in cloud object storage. The `hash` is the hex-encoded keccak256
+    /// hash that the content of the object must have. Objects are not
+    /// content-addressed, and this ties the PoI to the content that the
+    /// data source will be passed.
+    ObjectStoreFile { url: &'a str, hash: &'a str },
+}
+
+/// An owned copy of a `ProofOfIndexingEvent`, for events that are recorded
+/// and written to a proof of indexing later
+pub(super) enum OwnedProofOfIndexingEvent {
+    RemoveEntity {
+        entity_type: String,
+        id: String,
+    },
+    SetEntity {
+        entity_type: String,
+        id: String,
+        data: HashMap<String, Value>,
+    },
+    DeterministicError {
+        redacted_events: u64,
+    },
+    HttpResponse {
+        url: String,
+        hash: String,
+    },
+    ObjectStoreFile {
+        url: String,
+        hash: String,
+    },
+}
+
+impl ProofOfIndexingEvent<'_> {
+    pub(super) fn to_owned(&self) -> OwnedProofOfIndexingEvent {
+        use OwnedProofOfIndexingEvent as O;
+
+        match self {
+            Self::RemoveEntity { entity_type, id } => O::RemoveEntity {
+                entity_type: entity_type.to_string(),
+                id: id.to_string(),
+            },
+            Self::SetEntity {
+                entity_type,
+                id,
+                data,
+            } => O::SetEntity {
+                entity_type: entity_type.to_string(),
+                id: id.to_string(),
+                data: (*data).clone(),
+            },
+            Self::DeterministicError { redacted_events } => O::DeterministicError {
+                redacted_events: *redacted_events,
+            },
+            Self::HttpResponse { url, hash } => O::HttpResponse {
+                url: url.to_string(),
+                hash: hash.to_string(),
+            },
+            Self::ObjectStoreFile { url, hash } => O::ObjectStoreFile {
+                url: url.to_string(),
+                hash: hash.to_string(),
+            },
+        }
+    }
+}
+
+impl OwnedProofOfIndexingEvent {
+    pub(super) fn as_event(&self) -> ProofOfIndexingEvent<'_> {
+        match self {
+            Self::RemoveEntity { entity_type, id } => {
+                ProofOfIndexingEvent::RemoveEntity { entity_type, id }
+            }
+            Self::SetEntity {
+                entity_type,
+                id,
+                data,
+            } => ProofOfIndexingEvent::SetEntity {
+                entity_type,
+                id,
+                data,
+            },
+            Self::DeterministicError { redacted_events } => {
+                ProofOfIndexingEvent::DeterministicError {
+                    redacted_events: *redacted_events,
+                }
+            }
+            Self::HttpResponse { url, hash } => ProofOfIndexingEvent::HttpResponse { url, hash },
+            Self::ObjectStoreFile { url, hash } => {
+                ProofOfIndexingEvent::ObjectStoreFile { url, hash }
+            }
+        }
+    }
 }
 
 impl stable_hash_legacy::StableHash for ProofOfIndexingEvent<'_> {
@@ -82,6 +178,10 @@ impl stable_hash_legacy::StableHash for ProofOfIndexingEvent<'_> {
             DeterministicError { redacted_events } => {
                 redacted_events.stable_hash(sequence_number.next_child(), state)
             }
+            HttpResponse { url, hash } | ObjectStoreFile { url, hash } => {
+                url.stable_hash(sequence_number.next_child(), state);
+                hash.stable_hash(sequence_number.next_child(), state);
+            }
         }
     }
 }
@@ -110,6 +210,16 @@ impl stable_hash::StableHash for ProofOfIndexingEvent<'_> {
                 redacted_events.stable_hash(field_address.child(0), state);
                 3
             }
+            Self::HttpResponse { url, hash } => {
+                url.stable_hash(field_address.child(0), state);
+                hash.stable_hash(field_address.child(1), state);
+                4
+            }
+            Self::ObjectStoreFile { url, hash } => {
+                url.stable_hash(field_address.child(0), state);
+                hash.stable_hash(field_address.child(1), state);
+                5
+            }
         };
 
         state.write(field_address, &[variant]);
@@ -140,6 +250,10 @@ impl fmt::Debug for ProofOfIndexingEvent<'_> {
             Self::DeterministicError { redacted_events } => {
                 builder.field("redacted_events", redacted_events);
             }
+            Self::HttpResponse { url, hash } | Self::ObjectStoreFile { url, hash } => {
+                builder.field("url", url);
+                builder.field("hash", hash);
+            }
         }
         builder.finish()
     }
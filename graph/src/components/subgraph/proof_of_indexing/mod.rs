@@ -7,14 +7,84 @@ pub use online::{ProofOfIndexing, ProofOfIndexingFinisher};
 pub use reference::PoICausalityRegion;
 
 use atomic_refcell::AtomicRefCell;
+use semver::Version;
 use std::sync::Arc;
 
-#[derive(Copy, Clone, Debug)]
+use crate::data::subgraph::SPEC_VERSION_0_0_6;
+use crate::env::ENV_VARS;
+
+/// The algorithms for computing a proof of indexing. Which one a deployment
+/// uses is determined by the spec version of its manifest so that changing
+/// the algorithm does not change the POI of existing deployments. The index
+/// node API refers to versions by their `number`.
+///
+/// To move existing deployments to a newer version, deployments can compute
+/// the latest version in addition to their own during a transition. The
+/// digests for the latest version are stored in POI entities whose id is
+/// the causality region prefixed with the version (see `transition_id`)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum ProofOfIndexingVersion {
     Fast,
     Legacy,
 }
 
+impl ProofOfIndexingVersion {
+    /// The version that deployments with the newest spec version use
+    pub const LATEST: ProofOfIndexingVersion = ProofOfIndexingVersion::Fast;
+
+    pub fn for_spec_version(spec_version: &Version) -> Self {
+        if spec_version.ge(&SPEC_VERSION_0_0_6) {
+            ProofOfIndexingVersion::Fast
+        } else {
+            ProofOfIndexingVersion::Legacy
+        }
+    }
+
+    pub fn number(self) -> i32 {
+        match self {
+            ProofOfIndexingVersion::Legacy => 1,
+            ProofOfIndexingVersion::Fast => 2,
+        }
+    }
+
+    pub fn from_number(number: i32) -> Option<Self> {
+        match number {
+            1 => Some(ProofOfIndexingVersion::Legacy),
+            2 => Some(ProofOfIndexingVersion::Fast),
+            _ => None,
+        }
+    }
+
+    /// The version that a new deployment using `self` should compute in
+    /// addition to `self`, if any. That is the latest version while
+    /// `GRAPH_POI_TRANSITION` is set
+    pub fn transition(self) -> Option<Self> {
+        if ENV_VARS.poi_transition && self != Self::LATEST {
+            Some(Self::LATEST)
+        } else {
+            None
+        }
+    }
+
+    /// The id of the POI entity that holds the digest of
+    /// `causality_region` for `self` when `self` is computed during a
+    /// transition
+    pub fn transition_id(self, causality_region: &str) -> String {
+        format!("v{}:{}", self.number(), causality_region)
+    }
+
+    /// Split the id of a POI entity into the version it holds the digest
+    /// for if it was written during a transition, and the causality region
+    pub fn parse_id(id: &str) -> (Option<Self>, &str) {
+        let parsed = id.strip_prefix('v').and_then(|rest| {
+            let (number, causality_region) = rest.split_once(':')?;
+            let version = Self::from_number(number.parse().ok()?)?;
+            Some((Some(version), causality_region))
+        });
+        parsed.unwrap_or((None, id))
+    }
+}
+
 /// This concoction of types is to allow MappingContext to be static, yet still
 /// have shared mutable data for derive_with_empty_block_state. The static
 /// requirement is so that host exports can be static for wasmtime.
@@ -315,4 +385,115 @@ mod tests {
             check(case, &mut results);
         }
     }
+
+    #[test]
+    fn replay_matches_direct_writes() {
+        let logger = Logger::root(Discard, o!());
+        let data = hashmap! {
+            "val".to_owned() => Value::Int(1)
+        };
+        let write = |poi: &mut ProofOfIndexing| {
+            poi.start_handler("eth");
+            poi.write(
+                &logger,
+                "eth",
+                &ProofOfIndexingEvent::SetEntity {
+                    entity_type: "t",
+                    id: "id",
+                    data: &data,
+                },
+            );
+            poi.start_handler("eth");
+            poi.write(
+                &logger,
+                "eth",
+                &ProofOfIndexingEvent::RemoveEntity {
+                    entity_type: "t",
+                    id: "other",
+                },
+            );
+            poi.write_deterministic_error(&logger, "eth");
+        };
+        let digests = |poi: ProofOfIndexing| {
+            poi.take()
+                .into_iter()
+                .map(|(region, stream)| (region, stream.pause(None)))
+                .collect::<HashMap<_, _>>()
+        };
+
+        for version in [ProofOfIndexingVersion::Legacy, ProofOfIndexingVersion::Fast] {
+            let mut direct = ProofOfIndexing::new(1, version);
+            write(&mut direct);
+
+            let mut recording = ProofOfIndexing::recording(1, version);
+            write(&mut recording);
+            let mut replayed = ProofOfIndexing::new(1, version);
+            recording.replay(&logger, &mut replayed);
+
+            assert_eq!(digests(direct), digests(replayed));
+        }
+    }
+
+    #[test]
+    fn transition_computes_both_versions() {
+        let logger = Logger::root(Discard, o!());
+        let write = |poi: &mut ProofOfIndexing| {
+            poi.start_handler("eth");
+            poi.write(
+                &logger,
+                "eth",
+                &ProofOfIndexingEvent::RemoveEntity {
+                    entity_type: "t",
+                    id: "id",
+                },
+            );
+        };
+        let pause = |poi: ProofOfIndexing| {
+            poi.take()
+                .into_iter()
+                .map(|(region, mut stream)| {
+                    let transition = stream.pause_transition(None);
+                    (region, stream.pause(None), transition)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut legacy = ProofOfIndexing::new(1, ProofOfIndexingVersion::Legacy);
+        write(&mut legacy);
+        let mut fast = ProofOfIndexing::new(1, ProofOfIndexingVersion::Fast);
+        write(&mut fast);
+        let mut both = ProofOfIndexing::new(1, ProofOfIndexingVersion::Legacy)
+            .with_transition(Some(ProofOfIndexingVersion::Fast));
+        write(&mut both);
+
+        let (_, legacy, none) = pause(legacy).pop().unwrap();
+        let (_, fast, _) = pause(fast).pop().unwrap();
+        let (region, both, transition) = pause(both).pop().unwrap();
+        assert_eq!("eth", region);
+        assert_eq!(None, none);
+        assert_eq!(legacy, both);
+        assert_eq!(Some(fast), transition);
+    }
+
+    #[test]
+    fn transition_ids() {
+        let id = ProofOfIndexingVersion::Fast.transition_id("ethereum/mainnet");
+        assert_eq!("v2:ethereum/mainnet", id);
+        assert_eq!(
+            (Some(ProofOfIndexingVersion::Fast), "ethereum/mainnet"),
+            ProofOfIndexingVersion::parse_id(&id)
+        );
+        assert_eq!(
+            (None, "mainnet"),
+            ProofOfIndexingVersion::parse_id("mainnet")
+        );
+        assert_eq!(
+            (None, "v7:mainnet"),
+            ProofOfIndexingVersion::parse_id("v7:mainnet")
+        );
+        assert_eq!(
+            (None, "vx:mainnet"),
+            ProofOfIndexingVersion::parse_id("vx:mainnet")
+        );
+    }
 }
@@ -2,6 +2,7 @@
 //! Any hash constructed from here should be the same as if the same data was given
 //! to the reference implementation, but this is updated incrementally
 
+use super::event::OwnedProofOfIndexingEvent;
 use super::{ProofOfIndexingEvent, ProofOfIndexingVersion};
 use crate::{
     blockchain::BlockPtr,
@@ -23,6 +24,9 @@ pub struct BlockEventStream {
     handler_start: u64,
     block_index: u64,
     hasher: Hashers,
+    /// The hasher for the version that is computed in addition to the
+    /// version of the deployment during a transition
+    transition: Option<Hashers>,
 }
 
 enum Hashers {
@@ -60,6 +64,32 @@ impl Hashers {
             }
         }
     }
+
+    /// Combine the hasher with `prev`, the paused hasher of earlier blocks,
+    /// and serialize it
+    fn pause(self, prev: Option<&[u8]>) -> Vec<u8> {
+        match self {
+            Hashers::Legacy(mut digest) => {
+                if let Some(prev) = prev {
+                    let prev = SetHasher::from_bytes(prev);
+                    // SequenceNumber::root() is misleading here since the parameter
+                    // is unused.
+                    digest.finish_unordered(prev, SequenceNumber::root());
+                }
+                digest.to_bytes()
+            }
+            Hashers::Fast(mut digest) => {
+                if let Some(prev) = prev {
+                    let prev = prev
+                        .try_into()
+                        .expect("Expected valid fast stable hash representation");
+                    let prev = FastStableHasher::from_bytes(prev);
+                    digest.mixin(&prev);
+                }
+                digest.to_bytes().to_vec()
+            }
+        }
+    }
 }
 
 /// Go directly to a SequenceNumber identifying a field within a struct.
@@ -101,7 +131,11 @@ fn traverse_seq_no(counts: &[u64]) -> Blake3SeqNo {
 }
 
 impl BlockEventStream {
-    fn new(block_number: BlockNumber, version: ProofOfIndexingVersion) -> Self {
+    fn new(
+        block_number: BlockNumber,
+        version: ProofOfIndexingVersion,
+        transition: Option<ProofOfIndexingVersion>,
+    ) -> Self {
         let block_index: u64 = block_number.try_into().unwrap();
 
         Self {
@@ -109,6 +143,7 @@ impl BlockEventStream {
             handler_start: 0,
             block_index,
             hasher: Hashers::new(version),
+            transition: transition.map(Hashers::new),
         }
     }
 
@@ -119,27 +154,16 @@ impl BlockEventStream {
     pub fn pause(mut self, prev: Option<&[u8]>) -> Vec<u8> {
         self.hasher
             .write(&self.vec_length, &[1, 0, self.block_index, 0]);
-        match self.hasher {
-            Hashers::Legacy(mut digest) => {
-                if let Some(prev) = prev {
-                    let prev = SetHasher::from_bytes(prev);
-                    // SequenceNumber::root() is misleading here since the parameter
-                    // is unused.
-                    digest.finish_unordered(prev, SequenceNumber::root());
-                }
-                digest.to_bytes()
-            }
-            Hashers::Fast(mut digest) => {
-                if let Some(prev) = prev {
-                    let prev = prev
-                        .try_into()
-                        .expect("Expected valid fast stable hash representation");
-                    let prev = FastStableHasher::from_bytes(prev);
-                    digest.mixin(&prev);
-                }
-                digest.to_bytes().to_vec()
-            }
-        }
+        self.hasher.pause(prev)
+    }
+
+    /// Like `pause`, but for the hasher of the version that is computed
+    /// during a transition. Returns `None` if there is no transition. Must
+    /// be called before `pause`
+    pub fn pause_transition(&mut self, prev: Option<&[u8]>) -> Option<Vec<u8>> {
+        let mut hasher = self.transition.take()?;
+        hasher.write(&self.vec_length, &[1, 0, self.block_index, 0]);
+        Some(hasher.pause(prev))
     }
 
     fn write(&mut self, event: &ProofOfIndexingEvent<'_>) {
@@ -151,6 +175,9 @@ impl BlockEventStream {
             self.vec_length,
         ];
         self.hasher.write(&event, children);
+        if let Some(transition) = &mut self.transition {
+            transition.write(&event, children);
+        }
         self.vec_length += 1;
     }
 
@@ -161,12 +188,25 @@ impl BlockEventStream {
 
 pub struct ProofOfIndexing {
     version: ProofOfIndexingVersion,
+    /// The version that is computed in addition to `version` during a
+    /// transition
+    transition: Option<ProofOfIndexingVersion>,
     block_number: BlockNumber,
     /// The POI is updated for each data source independently. This is necessary because
     /// some data sources (eg: IPFS files) may be unreliable and therefore cannot mix
     /// state with other data sources. This may also give us some freedom to change
     /// the order of triggers in the future.
     per_causality_region: HashMap<String, BlockEventStream>,
+    /// When this is set, events are not hashed but recorded together with
+    /// their causality region, so that they can be written to another
+    /// proof of indexing later with `replay`
+    recorded: Option<Vec<(String, RecordedEvent)>>,
+}
+
+enum RecordedEvent {
+    StartHandler,
+    DeterministicError,
+    Event(OwnedProofOfIndexingEvent),
 }
 
 impl fmt::Debug for ProofOfIndexing {
@@ -179,14 +219,61 @@ impl ProofOfIndexing {
     pub fn new(block_number: BlockNumber, version: ProofOfIndexingVersion) -> Self {
         Self {
             version,
+            transition: None,
             block_number,
             per_causality_region: HashMap::new(),
+            recorded: None,
+        }
+    }
+
+    /// Also compute the POI for `transition` if it is set
+    pub fn with_transition(self, transition: Option<ProofOfIndexingVersion>) -> Self {
+        Self { transition, ..self }
+    }
+
+    /// A proof of indexing that only records the events written to it.
+    /// This makes it possible to process triggers concurrently and still
+    /// write their events in the order of the triggers
+    pub fn recording(block_number: BlockNumber, version: ProofOfIndexingVersion) -> Self {
+        Self {
+            recorded: Some(Vec::new()),
+            ..Self::new(block_number, version)
+        }
+    }
+
+    /// Write the events recorded by `self` to `poi` in the order in which
+    /// they were recorded. Does nothing if `self` is not recording
+    pub fn replay(self, logger: &Logger, poi: &mut ProofOfIndexing) {
+        for (causality_region, event) in self.recorded.into_iter().flatten() {
+            match event {
+                RecordedEvent::StartHandler => poi.start_handler(&causality_region),
+                RecordedEvent::DeterministicError => {
+                    poi.write_deterministic_error(logger, &causality_region)
+                }
+                RecordedEvent::Event(event) => {
+                    poi.write(logger, &causality_region, &event.as_event())
+                }
+            }
+        }
+    }
+
+    fn record(&mut self, causality_region: &str, event: impl FnOnce() -> RecordedEvent) -> bool {
+        match &mut self.recorded {
+            Some(recorded) => {
+                recorded.push((causality_region.to_owned(), event()));
+                true
+            }
+            None => false,
         }
     }
 }
 
 impl ProofOfIndexing {
     pub fn write_deterministic_error(&mut self, logger: &Logger, causality_region: &str) {
+        if self.record(causality_region, || RecordedEvent::DeterministicError) {
+            return;
+        }
+
         let redacted_events = self.with_causality_region(causality_region, |entry| {
             entry.vec_length - entry.handler_start
         });
@@ -205,6 +292,10 @@ impl ProofOfIndexing {
         causality_region: &str,
         event: &ProofOfIndexingEvent<'_>,
     ) {
+        if self.record(causality_region, || RecordedEvent::Event(event.to_owned())) {
+            return;
+        }
+
         if ENV_VARS.log_poi_events {
             debug!(
                 logger,
@@ -218,6 +309,9 @@ impl ProofOfIndexing {
     }
 
     pub fn start_handler(&mut self, causality_region: &str) {
+        if self.record(causality_region, || RecordedEvent::StartHandler) {
+            return;
+        }
         self.with_causality_region(causality_region, |entry| entry.start_handler())
     }
 
@@ -229,7 +323,7 @@ impl ProofOfIndexing {
         if let Some(causality_region) = self.per_causality_region.get_mut(causality_region) {
             f(causality_region)
         } else {
-            let mut entry = BlockEventStream::new(self.block_number, self.version);
+            let mut entry = BlockEventStream::new(self.block_number, self.version, self.transition);
             let result = f(&mut entry);
             self.per_causality_region
                 .insert(causality_region.to_owned(), entry);
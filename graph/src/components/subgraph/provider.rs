@@ -14,4 +14,8 @@ pub trait SubgraphAssignmentProvider: Send + Sync + 'static {
         &self,
         deployment: DeploymentLocator,
     ) -> Result<(), SubgraphAssignmentProviderError>;
+
+    /// Stop all running subgraphs, writing the blocks they have already
+    /// processed. Used when the node shuts down
+    async fn stop_all(&self);
 }
@@ -2,7 +2,10 @@ use std::str::FromStr;
 
 use async_trait::async_trait;
 
-use crate::{components::store::DeploymentLocator, prelude::*};
+use crate::{
+    components::store::{DeploymentLocator, SubgraphVersions},
+    prelude::*,
+};
 
 #[derive(Clone, Copy, Debug)]
 pub enum SubgraphVersionSwitchingMode {
@@ -36,6 +39,10 @@ pub trait SubgraphRegistrar: Send + Sync + 'static {
         name: SubgraphName,
     ) -> Result<CreateSubgraphResult, SubgraphRegistrarError>;
 
+    /// Deploy `hash` as a new version of the subgraph `name`. If
+    /// `graft_at_earliest_block` is set and the graft base has been pruned
+    /// past the graft block of the manifest, graft at the earliest block
+    /// that the base still has data for rather than failing
     async fn create_subgraph_version(
         &self,
         name: SubgraphName,
@@ -44,13 +51,49 @@ pub trait SubgraphRegistrar: Send + Sync + 'static {
         debug_fork: Option<DeploymentHash>,
         start_block_block: Option<BlockPtr>,
         graft_block_override: Option<BlockPtr>,
+        graft_at_earliest_block: bool,
     ) -> Result<DeploymentLocator, SubgraphRegistrarError>;
 
+    /// Fetch the manifest for `hash` and check that this node could deploy
+    /// it, without creating a deployment
+    async fn validate_subgraph(&self, hash: &DeploymentHash) -> SubgraphValidation;
+
     async fn remove_subgraph(&self, name: SubgraphName) -> Result<(), SubgraphRegistrarError>;
 
+    /// The versions of the subgraph `name`, or `None` if there is no
+    /// subgraph with that name
+    async fn subgraph_versions(
+        &self,
+        name: &SubgraphName,
+    ) -> Result<Option<SubgraphVersions>, SubgraphRegistrarError>;
+
+    /// Give the subgraph `name` back the `versions` that `subgraph_versions`
+    /// returned earlier, e.g., to undo deploying a new version
+    async fn restore_subgraph_versions(
+        &self,
+        name: SubgraphName,
+        versions: SubgraphVersions,
+    ) -> Result<(), SubgraphRegistrarError>;
+
+    /// Stop indexing the active deployment for `hash` without unassigning
+    /// it. The deployment can still be queried while it is paused
+    async fn pause_subgraph(&self, hash: &DeploymentHash) -> Result<(), SubgraphRegistrarError>;
+
+    /// Resume indexing a deployment that was paused with `pause_subgraph`
+    async fn resume_subgraph(&self, hash: &DeploymentHash) -> Result<(), SubgraphRegistrarError>;
+
     async fn reassign_subgraph(
         &self,
         hash: &DeploymentHash,
         node_id: &NodeId,
     ) -> Result<(), SubgraphRegistrarError>;
+
+    /// Rewind the active deployment for `hash` to `block_ptr_to`. The
+    /// deployment must be paused, and the block must be on the chain the
+    /// deployment indexes
+    async fn rewind_subgraph(
+        &self,
+        hash: &DeploymentHash,
+        block_ptr_to: BlockPtr,
+    ) -> Result<(), SubgraphRegistrarError>;
 }
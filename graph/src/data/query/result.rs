@@ -82,6 +82,10 @@ impl QueryResults {
     pub fn errors(&self) -> Vec<QueryError> {
         self.results.iter().flat_map(|r| r.errors.clone()).collect()
     }
+
+    pub fn is_truncated(&self) -> bool {
+        self.results.iter().any(|result| result.is_truncated())
+    }
 }
 
 impl Serialize for QueryResults {
@@ -103,6 +107,10 @@ impl Serialize for QueryResults {
         if first_trace.is_some() {
             len += 1;
         }
+        let is_truncated = self.is_truncated();
+        if is_truncated {
+            len += 1;
+        }
         let mut state = serializer.serialize_struct("QueryResults", len)?;
 
         // Serialize data.
@@ -141,6 +149,24 @@ impl Serialize for QueryResults {
         if let Some(trace) = first_trace {
             state.serialize_field("trace", trace)?;
         }
+
+        // Tell clients which lists were cut short and how to continue
+        if is_truncated {
+            struct SerExtensions<'a>(&'a QueryResults);
+
+            impl Serialize for SerExtensions<'_> {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    let truncations: Vec<_> =
+                        self.0.results.iter().flat_map(|r| &r.truncations).collect();
+                    let mut map = serializer.serialize_map(Some(2))?;
+                    map.serialize_entry("truncated", &true)?;
+                    map.serialize_entry("truncations", &truncations)?;
+                    map.end()
+                }
+            }
+
+            state.serialize_field("extensions", &SerExtensions(self))?;
+        }
         state.end()
     }
 }
@@ -189,7 +215,11 @@ impl QueryResults {
     }
 
     pub fn as_http_response<T: From<String>>(&self) -> http::Response<T> {
-        let status_code = http::StatusCode::OK;
+        let status_code = if self.results.iter().any(|r| r.in_maintenance()) {
+            http::StatusCode::SERVICE_UNAVAILABLE
+        } else {
+            http::StatusCode::OK
+        };
         let json =
             serde_json::to_string(self).expect("Failed to serialize GraphQL response to JSON");
         http::Response::builder()
@@ -221,6 +251,25 @@ pub struct QueryResult {
     pub deployment: Option<DeploymentHash>,
     #[serde(skip_serializing)]
     pub trace: Trace,
+    #[serde(skip_serializing)]
+    truncations: Vec<Truncation>,
+    #[serde(skip_serializing)]
+    data_size: usize,
+}
+
+/// A list in the data of a query result that was cut short because the
+/// result would otherwise have exceeded the maximum result size
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Truncation {
+    /// The response keys and list indices that lead to the list
+    pub path: Vec<serde_json::Value>,
+    /// The number of complete elements of the list in the result. Clients
+    /// can continue by skipping that many elements
+    pub returned: usize,
+    /// The `id` of the last complete element, if the elements have one.
+    /// For lists ordered by `id`, clients can continue with `id_gt`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
 }
 
 impl QueryResult {
@@ -230,6 +279,8 @@ impl QueryResult {
             errors: Vec::new(),
             deployment: None,
             trace: Trace::None,
+            truncations: Vec::new(),
+            data_size: 0,
         }
     }
 
@@ -242,6 +293,8 @@ impl QueryResult {
             errors: self.errors.clone(),
             deployment: self.deployment.clone(),
             trace: Trace::None,
+            truncations: self.truncations.clone(),
+            data_size: self.data_size,
         }
     }
 
@@ -258,6 +311,15 @@ impl QueryResult {
         })
     }
 
+    pub fn in_maintenance(&self) -> bool {
+        self.errors.iter().any(|e| {
+            matches!(
+                e,
+                QueryError::ExecutionError(QueryExecutionError::DeploymentInMaintenance(_, _))
+            )
+        })
+    }
+
     pub fn has_data(&self) -> bool {
         self.data.is_some()
     }
@@ -289,6 +351,30 @@ impl QueryResult {
     pub fn data(&self) -> Option<&Data> {
         self.data.as_ref()
     }
+
+    pub fn is_truncated(&self) -> bool {
+        !self.truncations.is_empty()
+    }
+
+    pub fn truncations(&self) -> &[Truncation] {
+        &self.truncations
+    }
+
+    /// Record the lists in the data of this result that were cut short
+    /// while the result was built
+    pub fn set_truncations(&mut self, truncations: Vec<Truncation>) {
+        self.truncations = truncations;
+    }
+
+    /// The size of the data of this result when serialized, if it was
+    /// tracked while the result was built, and 0 otherwise
+    pub fn data_size(&self) -> usize {
+        self.data_size
+    }
+
+    pub fn set_data_size(&mut self, data_size: usize) {
+        self.data_size = data_size;
+    }
 }
 
 impl From<QueryExecutionError> for QueryResult {
@@ -298,6 +384,8 @@ impl From<QueryExecutionError> for QueryResult {
             errors: vec![e.into()],
             deployment: None,
             trace: Trace::None,
+            truncations: Vec::new(),
+            data_size: 0,
         }
     }
 }
@@ -309,6 +397,8 @@ impl From<QueryError> for QueryResult {
             errors: vec![e],
             deployment: None,
             trace: Trace::None,
+            truncations: Vec::new(),
+            data_size: 0,
         }
     }
 }
@@ -320,6 +410,8 @@ impl From<Vec<QueryExecutionError>> for QueryResult {
             errors: e.into_iter().map(QueryError::from).collect(),
             deployment: None,
             trace: Trace::None,
+            truncations: Vec::new(),
+            data_size: 0,
         }
     }
 }
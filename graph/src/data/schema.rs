@@ -1048,6 +1048,7 @@ impl Schema {
             "String".into(),
             "Bytes".into(),
             "BigInt".into(),
+            "Int8".into(),
             // Reserved Query and Subscription types
             "Query".into(),
             "Subscription".into(),
@@ -1502,6 +1503,7 @@ fn test_reserved_types_validation() {
         "String",
         "Bytes",
         "BigInt",
+        "Int8",
         // Reserved keywords
         "Query",
         "Subscription",
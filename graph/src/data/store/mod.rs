@@ -1,7 +1,7 @@
 use crate::{
     components::store::{DeploymentLocator, EntityKey, EntityType},
     data::graphql::ObjectTypeExt,
-    prelude::{anyhow::Context, q, r, s, CacheWeight, QueryExecutionError, Schema},
+    prelude::{q, r, s, CacheWeight, QueryExecutionError, Schema},
     runtime::gas::{Gas, GasSizeOf},
 };
 use crate::{data::subgraph::DeploymentHash, prelude::EntityChange};
@@ -90,6 +90,15 @@ impl slog::Value for NodeId {
     }
 }
 
+impl Serialize for NodeId {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.0)
+    }
+}
+
 impl<'de> de::Deserialize<'de> for NodeId {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -130,6 +139,7 @@ pub const ID: &str = "ID";
 pub const BYTES_SCALAR: &str = "Bytes";
 pub const BIG_INT_SCALAR: &str = "BigInt";
 pub const BIG_DECIMAL_SCALAR: &str = "BigDecimal";
+pub const INT8_SCALAR: &str = "Int8";
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum ValueType {
@@ -138,6 +148,7 @@ pub enum ValueType {
     Bytes,
     BigDecimal,
     Int,
+    Int8,
     String,
 }
 
@@ -151,6 +162,7 @@ impl FromStr for ValueType {
             "Bytes" => Ok(ValueType::Bytes),
             "BigDecimal" => Ok(ValueType::BigDecimal),
             "Int" => Ok(ValueType::Int),
+            "Int8" => Ok(ValueType::Int8),
             "String" | "ID" => Ok(ValueType::String),
             s => Err(anyhow!("Type not available in this context: {}", s)),
         }
@@ -172,6 +184,7 @@ impl ValueType {
 pub enum Value {
     String(String),
     Int(i32),
+    Int8(i64),
     BigDecimal(scalar::BigDecimal),
     Bool(bool),
     List(Vec<Value>),
@@ -207,6 +220,9 @@ impl stable_hash_legacy::StableHash for Value {
             Int(inner) => {
                 stable_hash_legacy::StableHash::stable_hash(inner, sequence_number, state)
             }
+            Int8(inner) => {
+                stable_hash_legacy::StableHash::stable_hash(inner, sequence_number, state)
+            }
             BigDecimal(inner) => {
                 stable_hash_legacy::StableHash::stable_hash(inner, sequence_number, state)
             }
@@ -265,6 +281,10 @@ impl StableHash for Value {
                 inner.stable_hash(field_address.child(0), state);
                 7
             }
+            Int8(inner) => {
+                inner.stable_hash(field_address.child(0), state);
+                8
+            }
         };
 
         state.write(field_address, &[variant])
@@ -300,9 +320,13 @@ impl Value {
                     BYTES_SCALAR => Value::Bytes(scalar::Bytes::from_str(s)?),
                     BIG_INT_SCALAR => Value::BigInt(scalar::BigInt::from_str(s)?),
                     BIG_DECIMAL_SCALAR => Value::BigDecimal(scalar::BigDecimal::from_str(s)?),
+                    INT8_SCALAR => Value::Int8(s.parse::<i64>().map_err(|_| {
+                        QueryExecutionError::ValueParseError(INT8_SCALAR.to_string(), s.clone())
+                    })?),
                     _ => Value::String(s.clone()),
                 }
             }
+            (r::Value::Int(i), NamedType(n)) if n == INT8_SCALAR => Value::Int8(*i),
             (r::Value::Int(i), _) => Value::Int(*i as i32),
             (r::Value::Boolean(b), _) => Value::Bool(b.to_owned()),
             (r::Value::Null, _) => Value::Null,
@@ -343,6 +367,14 @@ impl Value {
         }
     }
 
+    pub fn as_int8(&self) -> Option<i64> {
+        if let Value::Int8(i) = self {
+            Some(*i)
+        } else {
+            None
+        }
+    }
+
     pub fn as_big_decimal(self) -> Option<scalar::BigDecimal> {
         if let Value::BigDecimal(d) = self {
             Some(d)
@@ -391,6 +423,7 @@ impl Value {
             Value::Bool(_) => "Boolean".to_owned(),
             Value::Bytes(_) => "Bytes".to_owned(),
             Value::Int(_) => "Int".to_owned(),
+            Value::Int8(_) => "Int8".to_owned(),
             Value::List(values) => {
                 if let Some(v) = values.first() {
                     format!("[{}]", v.type_name())
@@ -411,6 +444,7 @@ impl Value {
             | (Value::Bool(_), ValueType::Boolean)
             | (Value::Bytes(_), ValueType::Bytes)
             | (Value::Int(_), ValueType::Int)
+            | (Value::Int8(_), ValueType::Int8)
             | (Value::Null, _) => true,
             (Value::List(values), _) if is_list => values
                 .iter()
@@ -428,6 +462,7 @@ impl fmt::Display for Value {
             match self {
                 Value::String(s) => s.to_string(),
                 Value::Int(i) => i.to_string(),
+                Value::Int8(i) => i.to_string(),
                 Value::BigDecimal(d) => d.to_string(),
                 Value::Bool(b) => b.to_string(),
                 Value::Null => "null".to_string(),
@@ -445,6 +480,7 @@ impl fmt::Debug for Value {
         match self {
             Self::String(s) => f.debug_tuple("String").field(s).finish(),
             Self::Int(i) => f.debug_tuple("Int").field(i).finish(),
+            Self::Int8(i) => f.debug_tuple("Int8").field(i).finish(),
             Self::BigDecimal(d) => d.fmt(f),
             Self::Bool(arg0) => f.debug_tuple("Bool").field(arg0).finish(),
             Self::List(arg0) => f.debug_tuple("List").field(arg0).finish(),
@@ -460,6 +496,7 @@ impl From<Value> for q::Value {
         match value {
             Value::String(s) => q::Value::String(s),
             Value::Int(i) => q::Value::Int(q::Number::from(i)),
+            Value::Int8(i) => q::Value::String(i.to_string()),
             Value::BigDecimal(d) => q::Value::String(d.to_string()),
             Value::Bool(b) => q::Value::Boolean(b),
             Value::Null => q::Value::Null,
@@ -477,6 +514,9 @@ impl From<Value> for r::Value {
         match value {
             Value::String(s) => r::Value::String(s),
             Value::Int(i) => r::Value::Int(i as i64),
+            // Serialized as a string since JSON clients can not represent
+            // all 64 bit integers as numbers
+            Value::Int8(i) => r::Value::String(i.to_string()),
             Value::BigDecimal(d) => r::Value::String(d.to_string()),
             Value::Bool(b) => r::Value::Boolean(b),
             Value::Null => r::Value::Null,
@@ -525,6 +565,12 @@ impl From<i32> for Value {
     }
 }
 
+impl From<i64> for Value {
+    fn from(value: i64) -> Value {
+        Value::Int8(value)
+    }
+}
+
 impl From<scalar::BigDecimal> for Value {
     fn from(value: scalar::BigDecimal) -> Value {
         Value::BigDecimal(value)
@@ -691,7 +737,7 @@ impl Entity {
     /// Validate that this entity matches the object type definition in the
     /// schema. An entity that passes these checks can be stored
     /// successfully in the subgraph's database schema
-    pub fn validate(&self, schema: &Schema, key: &EntityKey) -> Result<(), anyhow::Error> {
+    pub fn validate(&self, schema: &Schema, key: &EntityKey) -> Result<(), EntityValidationError> {
         fn scalar_value_type(schema: &Schema, field_type: &s::Type) -> ValueType {
             use s::TypeDefinition as t;
             match field_type {
@@ -742,15 +788,15 @@ impl Entity {
             // type for them, and validation would therefore fail
             return Ok(());
         }
+        let entity = key.entity_type.to_string();
+        let entity_id = key.entity_id.to_string();
         let object_type_definitions = schema.document.get_object_type_definitions();
         let object_type = object_type_definitions
             .iter()
             .find(|object_type| key.entity_type.as_str() == object_type.name)
-            .with_context(|| {
-                format!(
-                    "Entity {}[{}]: unknown entity type `{}`",
-                    key.entity_type, key.entity_id, key.entity_type
-                )
+            .ok_or_else(|| EntityValidationError::UnknownEntityType {
+                entity: entity.clone(),
+                entity_id: entity_id.clone(),
             })?;
 
         for field in &object_type.fields {
@@ -765,50 +811,47 @@ impl Entity {
                         if let Value::List(elts) = value {
                             for (index, elt) in elts.iter().enumerate() {
                                 if !elt.is_assignable(&scalar_type, false) {
-                                    anyhow::bail!(
-                                        "Entity {}[{}]: field `{}` is of type {}, but the value `{}` \
-                                        contains a {} at index {}",
-                                        key.entity_type,
-                                        key.entity_id,
-                                        field.name,
-                                        &field.field_type,
-                                        value,
-                                        elt.type_name(),
-                                        index
+                                    return Err(
+                                        EntityValidationError::MismatchedElementTypeInList {
+                                            entity,
+                                            entity_id,
+                                            field: field.name.clone(),
+                                            expected_type: field.field_type.to_string(),
+                                            value: value.to_string(),
+                                            actual_type: elt.type_name(),
+                                            index,
+                                        },
                                     );
                                 }
                             }
                         }
                     }
                     if !value.is_assignable(&scalar_type, field.field_type.is_list()) {
-                        anyhow::bail!(
-                            "Entity {}[{}]: the value `{}` for field `{}` must have type {} but has type {}",
-                            key.entity_type,
-                            key.entity_id,
-                            value,
-                            field.name,
-                            &field.field_type,
-                            value.type_name()
-                        );
+                        return Err(EntityValidationError::InvalidFieldType {
+                            entity,
+                            entity_id,
+                            field: field.name.clone(),
+                            expected_type: field.field_type.to_string(),
+                            value: value.to_string(),
+                            actual_type: value.type_name(),
+                        });
                     }
                 }
                 (None, false) => {
                     if field.field_type.is_non_null() {
-                        anyhow::bail!(
-                            "Entity {}[{}]: missing value for non-nullable field `{}`",
-                            key.entity_type,
-                            key.entity_id,
-                            field.name,
-                        );
+                        return Err(EntityValidationError::MissingValueForNonNullableField {
+                            entity,
+                            entity_id,
+                            field: field.name.clone(),
+                        });
                     }
                 }
                 (Some(_), true) => {
-                    anyhow::bail!(
-                        "Entity {}[{}]: field `{}` is derived and can not be set",
-                        key.entity_type,
-                        key.entity_id,
-                        field.name,
-                    );
+                    return Err(EntityValidationError::CannotSetDerivedField {
+                        entity,
+                        entity_id,
+                        field: field.name.clone(),
+                    });
                 }
                 (None, true) => {
                     // derived fields should not be set
@@ -819,6 +862,66 @@ impl Entity {
     }
 }
 
+/// The reason why an entity does not match its type in the schema. These
+/// errors only depend on the entity and the schema, and are therefore
+/// deterministic
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum EntityValidationError {
+    #[error("Entity {entity}[{entity_id}]: unknown entity type `{entity}`")]
+    UnknownEntityType { entity: String, entity_id: String },
+
+    #[error(
+        "Entity {entity}[{entity_id}]: field `{field}` is of type {expected_type}, \
+         but the value `{value}` contains a {actual_type} at index {index}"
+    )]
+    MismatchedElementTypeInList {
+        entity: String,
+        entity_id: String,
+        field: String,
+        expected_type: String,
+        value: String,
+        actual_type: String,
+        index: usize,
+    },
+
+    #[error(
+        "Entity {entity}[{entity_id}]: the value `{value}` for field `{field}` must have \
+         type {expected_type} but has type {actual_type}"
+    )]
+    InvalidFieldType {
+        entity: String,
+        entity_id: String,
+        field: String,
+        expected_type: String,
+        value: String,
+        actual_type: String,
+    },
+
+    #[error("Entity {entity}[{entity_id}]: missing value for non-nullable field `{field}`")]
+    MissingValueForNonNullableField {
+        entity: String,
+        entity_id: String,
+        field: String,
+    },
+
+    #[error("Entity {entity}[{entity_id}]: field `{field}` is derived and can not be set")]
+    CannotSetDerivedField {
+        entity: String,
+        entity_id: String,
+        field: String,
+    },
+
+    #[error(
+        "Entity {entity}[{entity_id}]: value of attribute 'id' conflicts with ID passed \
+         to `store.set()`: {value} != {entity_id}"
+    )]
+    IdConflict {
+        entity: String,
+        entity_id: String,
+        value: String,
+    },
+}
+
 impl From<HashMap<Attribute, Value>> for Entity {
     fn from(m: HashMap<Attribute, Value>) -> Entity {
         Entity(m)
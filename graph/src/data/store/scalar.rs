@@ -1,3 +1,4 @@
+use anyhow::{anyhow, Error};
 use diesel::deserialize::FromSql;
 use diesel::serialize::ToSql;
 use diesel_derives::{AsExpression, FromSqlRow};
@@ -13,7 +14,7 @@ use web3::types::*;
 use std::convert::{TryFrom, TryInto};
 use std::fmt::{self, Display, Formatter};
 use std::io::Write;
-use std::ops::{Add, BitAnd, BitOr, Deref, Div, Mul, Rem, Shl, Shr, Sub};
+use std::ops::{Add, BitAnd, BitOr, BitXor, Deref, Div, Mul, Not, Rem, Shl, Shr, Sub};
 use std::str::FromStr;
 
 pub use num_bigint::Sign as BigIntSign;
@@ -44,6 +45,10 @@ impl BigDecimal {
     pub const MIN_EXP: i32 = -6143;
     pub const MAX_EXP: i32 = 6144;
     pub const MAX_SIGNFICANT_DIGITS: i32 = 34;
+    /// Exponents for `pow` must be less than `10^MAX_POW_EXP_DIGITS` in
+    /// absolute value. Since `self` has at most `MAX_SIGNFICANT_DIGITS`,
+    /// raising anything but 1 to a bigger power is out of range anyway
+    pub const MAX_POW_EXP_DIGITS: i64 = 40;
 
     pub fn new(digits: BigInt, exp: i64) -> Self {
         // bigdecimal uses `scale` as the opposite of the power of ten, so negate `exp`.
@@ -88,6 +93,317 @@ impl BigDecimal {
 
         BigDecimal(bigdecimal::BigDecimal::new(int_val, scale))
     }
+
+    /// The square root of `self`, rounded to `MAX_SIGNFICANT_DIGITS`.
+    /// Returns `None` for negative numbers
+    pub fn sqrt(&self) -> Option<BigDecimal> {
+        if self < &BigDecimal::zero() {
+            return None;
+        }
+        if self == &BigDecimal::zero() {
+            return Some(BigDecimal::zero());
+        }
+
+        // Compute the integer square root of the digits after scaling them
+        // up far enough that it has a few more digits than we keep. The
+        // scale needs to be even so that it can be halved
+        let (mut digits, mut scale) = self.as_bigint_and_exponent();
+        let min_digits = 2 * (Self::MAX_SIGNFICANT_DIGITS as i64 + 2);
+        let mut shift = (min_digits - decimal_digits(&digits) as i64).max(0);
+        if (scale + shift) % 2 != 0 {
+            shift += 1;
+        }
+        digits *= fixed::ten_pow(shift as usize);
+        scale += shift;
+        Some(Self::from(bigdecimal::BigDecimal::new(
+            digits.sqrt(),
+            scale / 2,
+        )))
+    }
+
+    /// The decimal logarithm of `self`, rounded to `MAX_SIGNFICANT_DIGITS`.
+    /// Returns `None` if `self` is not positive
+    pub fn log10(&self) -> Option<BigDecimal> {
+        if self <= &BigDecimal::zero() {
+            return None;
+        }
+
+        let (mantissa, exp) = self.fixed_parts();
+        let log = fixed::one() * exp + fixed::div(&fixed::ln_mantissa(&mantissa), fixed::ln10());
+        Some(Self::from(bigdecimal::BigDecimal::new(
+            log,
+            fixed::DIGITS as i64,
+        )))
+    }
+
+    /// An upper bound for the number of multiplications that `log10`
+    /// performs, which is what its cost mostly consists of
+    pub fn log10_work() -> u64 {
+        fixed::LN_WORK + 1
+    }
+
+    /// `self` raised to the power `exp`, rounded to `MAX_SIGNFICANT_DIGITS`.
+    ///
+    /// Integer exponents that fit into a `u32` are computed by repeated
+    /// squaring, rounding after each multiplication. All other exponents
+    /// are computed as `exp(exp * ln(self))` with fixed precision, and
+    /// negative numbers can only be raised to integer powers. Results that
+    /// would have an exponent outside of `MIN_EXP..=MAX_EXP` and exponents
+    /// that are not less than `10^MAX_POW_EXP_DIGITS` are an error
+    pub fn pow(&self, exp: &BigDecimal) -> Result<BigDecimal, Error> {
+        use num_traits::ToPrimitive;
+
+        let zero = BigDecimal::zero();
+        let one = BigDecimal::from(1);
+        if exp == &zero || self == &one {
+            return Ok(one);
+        }
+        if self == &zero {
+            return match exp > &zero {
+                true => Ok(zero),
+                false => Err(anyhow!("cannot raise zero to negative power {}", exp)),
+            };
+        }
+
+        let (exp_digits, exp_scale) = exp.normalized().as_bigint_and_exponent();
+        let is_integer = exp_scale <= 0;
+        // `exp` is at least `10^exp_magnitude` and less than
+        // `10^(exp_magnitude + 1)` in absolute value
+        let exp_magnitude = decimal_digits(&exp_digits) as i64 - 1 - exp_scale;
+        if exp_magnitude >= Self::MAX_POW_EXP_DIGITS {
+            return Err(anyhow!("the exponent {} is too big", exp));
+        }
+        if exp_scale == 0 {
+            if let Some(mut n) = exp_digits.magnitude().to_u32() {
+                let mut result = BigDecimal::from(1);
+                let mut base = self.clone();
+                loop {
+                    if n % 2 == 1 {
+                        result = result * base.clone();
+                    }
+                    n /= 2;
+                    if n == 0 {
+                        break;
+                    }
+                    base = base.clone() * base;
+                }
+                if exp < &zero {
+                    result = BigDecimal::from(1) / result;
+                }
+                return result.check_range();
+            }
+        }
+
+        let negative = self < &zero;
+        if negative && !is_integer {
+            return Err(anyhow!(
+                "cannot raise negative number {} to fractional power {}",
+                self,
+                exp
+            ));
+        }
+        // With an exponent this small, `exp * ln(self)` is truncated to 0
+        // for any `self` in range
+        if exp_magnitude < -2 * fixed::DIGITS as i64 {
+            return Ok(one);
+        }
+        // An integer with a negative scale is a multiple of 10 and even
+        let odd = exp_scale == 0 && &exp_digits % 2u32 != num_bigint::BigInt::from(0);
+
+        let abs = if negative {
+            BigDecimal::zero() - self.clone()
+        } else {
+            self.clone()
+        };
+        let (mantissa, self_exp) = abs.fixed_parts();
+        let ln = fixed::ln_mantissa(&mantissa) + fixed::ln10() * self_exp;
+        let t = if exp_scale >= 0 {
+            ln * exp_digits / fixed::ten_pow(exp_scale as usize)
+        } else {
+            ln * exp_digits * fixed::ten_pow(-exp_scale as usize)
+        };
+
+        // exp(t) = exp(f) * 10^q with 0 <= f < ln(10)
+        let ln10 = fixed::ln10();
+        let mut q = &t / ln10;
+        if t < &q * ln10 {
+            q -= 1;
+        }
+        let f = t - &q * ln10;
+        let q = q
+            .to_i64()
+            .filter(|q| (Self::MIN_EXP as i64..=Self::MAX_EXP as i64).contains(q))
+            .ok_or_else(|| anyhow!("{} raised to the power {} is out of range", self, exp))?;
+        let result = Self::from(bigdecimal::BigDecimal::new(
+            fixed::exp_fraction(&f),
+            fixed::DIGITS as i64 - q,
+        ));
+        match odd && negative {
+            true => Ok(BigDecimal::zero() - result),
+            false => Ok(result),
+        }
+    }
+
+    /// An upper bound for the number of multiplications that
+    /// `self.pow(exp)` performs for any `self`, which is what its cost
+    /// mostly consists of. It grows with the number of bits of integer
+    /// exponents, and is bounded for all other exponents
+    pub fn pow_work(exp: &BigDecimal) -> u64 {
+        use num_traits::ToPrimitive;
+
+        let (exp_digits, exp_scale) = exp.normalized().as_bigint_and_exponent();
+        if exp_scale == 0 {
+            if let Some(n) = exp_digits.magnitude().to_u32() {
+                // Up to two multiplications for each bit of `n`, and one
+                // division for negative exponents
+                return 2 * (u32::BITS - n.leading_zeros()) as u64 + 1;
+            }
+        }
+        fixed::LN_WORK + fixed::EXP_WORK + 2
+    }
+
+    /// Split a positive `self` into a fixed-point mantissa `1 <= m < 10`
+    /// and an exponent `e` such that `self = m * 10^e`
+    fn fixed_parts(&self) -> (num_bigint::BigInt, i64) {
+        let (digits, scale) = self.as_bigint_and_exponent();
+        let leading = decimal_digits(&digits) - 1;
+        let mantissa = if leading <= fixed::DIGITS {
+            digits * fixed::ten_pow(fixed::DIGITS - leading)
+        } else {
+            digits / fixed::ten_pow(leading - fixed::DIGITS)
+        };
+        (mantissa, leading as i64 - scale)
+    }
+
+    fn check_range(self) -> Result<BigDecimal, Error> {
+        if self == BigDecimal::zero() {
+            return Ok(self);
+        }
+        let (digits, scale) = self.as_bigint_and_exponent();
+        let exp = decimal_digits(&digits) as i64 - 1 - scale;
+        if exp < Self::MIN_EXP as i64 || exp > Self::MAX_EXP as i64 {
+            return Err(anyhow!("BigDecimal result {} is out of range", self));
+        }
+        Ok(self)
+    }
+}
+
+/// The number of decimal digits of `n`, ignoring its sign
+fn decimal_digits(n: &num_bigint::BigInt) -> usize {
+    n.magnitude().to_str_radix(10).len()
+}
+
+/// Arithmetic on fixed-point numbers with `DIGITS` fractional digits that
+/// is used to compute logarithms and powers of `BigDecimal`. All operations
+/// truncate and only use integer arithmetic, which makes their results
+/// the same on every platform
+mod fixed {
+    use lazy_static::lazy_static;
+    use num_bigint::BigInt;
+    use num_traits::Zero;
+
+    /// Enough digits that the errors of all steps of a computation stay
+    /// well below the precision of `BigDecimal`
+    pub const DIGITS: usize = 64;
+
+    /// The number of times `exp_fraction` halves its argument
+    const EXP_HALVINGS: u32 = 10;
+
+    /// An upper bound for the number of terms that `ln_mantissa` sums up.
+    /// Its series gains at least 1.39 digits per term since its argument
+    /// is at most 1/5
+    pub const LN_WORK: u64 = 50;
+
+    /// An upper bound for the number of multiplications in
+    /// `exp_fraction`. Its series gains at least 2.6 digits per term since
+    /// its reduced argument is less than `ln(10) / 2^EXP_HALVINGS`, and it
+    /// squares the sum `EXP_HALVINGS` times
+    pub const EXP_WORK: u64 = 25 + EXP_HALVINGS as u64;
+
+    lazy_static! {
+        // ln(2) = 2 atanh(1/3)
+        static ref LN2: BigInt = atanh(&(one() / 3)) * 2;
+        // ln(10) = 3 ln(2) + ln(5/4) = 3 ln(2) + 2 atanh(1/9)
+        static ref LN10: BigInt = &*LN2 * 3 + atanh(&(one() / 9)) * 2;
+    }
+
+    pub fn ten_pow(n: usize) -> BigInt {
+        num_traits::pow(BigInt::from(10), n)
+    }
+
+    pub fn one() -> BigInt {
+        ten_pow(DIGITS)
+    }
+
+    pub fn mul(a: &BigInt, b: &BigInt) -> BigInt {
+        a * b / one()
+    }
+
+    pub fn div(a: &BigInt, b: &BigInt) -> BigInt {
+        a * one() / b
+    }
+
+    /// `atanh(z)` for `|z| < 1`; converges quickly for small `z`
+    fn atanh(z: &BigInt) -> BigInt {
+        let z2 = mul(z, z);
+        let mut power = z.clone();
+        let mut sum = BigInt::zero();
+        let mut k = 1u32;
+        loop {
+            let term = &power / BigInt::from(k);
+            if term.is_zero() {
+                return sum;
+            }
+            sum += term;
+            power = mul(&power, &z2);
+            k += 2;
+        }
+    }
+
+    pub fn ln2() -> &'static BigInt {
+        &LN2
+    }
+
+    pub fn ln10() -> &'static BigInt {
+        &LN10
+    }
+
+    /// `ln(m)` for `1 <= m < 10`
+    pub fn ln_mantissa(m: &BigInt) -> BigInt {
+        // Halve `m` until it is at most 1.5 so that the series for
+        // `ln(m) = 2 atanh((m - 1) / (m + 1))` converges quickly
+        let limit = one() * 3 / 2;
+        let mut m = m.clone();
+        let mut halvings = 0u32;
+        while m > limit {
+            m /= 2;
+            halvings += 1;
+        }
+        let z = div(&(&m - one()), &(&m + one()));
+        ln2() * halvings + atanh(&z) * 2
+    }
+
+    /// `exp(f)` for `0 <= f < ln(10)`
+    pub fn exp_fraction(f: &BigInt) -> BigInt {
+        // exp(f) = exp(f / 2^k)^(2^k), and the Taylor series for the
+        // reduced argument converges quickly
+        let x = f / BigInt::from(2u32.pow(EXP_HALVINGS));
+        let mut sum = one();
+        let mut term = one();
+        let mut k = 1u32;
+        loop {
+            term = mul(&term, &x) / BigInt::from(k);
+            if term.is_zero() {
+                break;
+            }
+            sum += &term;
+            k += 1;
+        }
+        for _ in 0..EXP_HALVINGS {
+            sum = mul(&sum, &sum);
+        }
+        sum
+    }
 }
 
 impl Display for BigDecimal {
@@ -379,6 +695,15 @@ impl BigInt {
     pub fn bits(&self) -> usize {
         self.0.bits()
     }
+
+    /// The square root of `self`, rounded down. Returns `None` for negative
+    /// numbers
+    pub fn sqrt(&self) -> Option<BigInt> {
+        match self.0.sign() {
+            BigIntSign::Minus => None,
+            _ => Some(BigInt(self.0.sqrt())),
+        }
+    }
 }
 
 impl Display for BigInt {
@@ -518,6 +843,22 @@ impl BitAnd for BigInt {
     }
 }
 
+impl BitXor for BigInt {
+    type Output = Self;
+
+    fn bitxor(self, other: Self) -> Self {
+        Self::from(self.0.bitxor(other.0))
+    }
+}
+
+impl Not for BigInt {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self::from(self.0.not())
+    }
+}
+
 impl Shl<u8> for BigInt {
     type Output = Self;
 
@@ -698,6 +1039,47 @@ mod test {
         }
     }
 
+    #[test]
+    fn big_int_math() {
+        assert_eq!(Some(BigInt::from(12)), BigInt::from(150).sqrt());
+        assert_eq!(None, BigInt::from(-4).sqrt());
+        assert_eq!(BigInt::from(6), BigInt::from(12) ^ BigInt::from(10));
+        assert_eq!(BigInt::from(-13), !BigInt::from(12));
+    }
+
+    #[test]
+    fn big_decimal_math() {
+        let dec = |s: &str| BigDecimal::from_str(s).unwrap();
+
+        assert_eq!(Some(dec("2")), dec("4").sqrt());
+        assert_eq!(Some(dec("0.03")), dec("0.0009").sqrt());
+        assert_eq!(
+            Some(dec("1.414213562373095048801688724209698")),
+            dec("2").sqrt()
+        );
+        assert_eq!(None, dec("-1").sqrt());
+
+        assert_eq!(Some(dec("3")), dec("1000").log10());
+        assert_eq!(Some(dec("-2")), dec("0.01").log10());
+        assert_eq!(
+            Some(dec("0.301029995663981195213738894724493")),
+            dec("2").log10()
+        );
+        assert_eq!(None, dec("0").log10());
+
+        assert_eq!(dec("1024"), dec("2").pow(&dec("10")).unwrap());
+        assert_eq!(dec("0.25"), dec("2").pow(&dec("-2")).unwrap());
+        assert_eq!(dec("-8"), dec("-2").pow(&dec("3")).unwrap());
+        assert_eq!(dec("1"), dec("0").pow(&dec("0")).unwrap());
+        // Fractional powers are computed with logarithms
+        let root = dec("2").pow(&dec("0.5")).unwrap();
+        let error = root - dec("2").sqrt().unwrap();
+        assert!(dec("-1e-32") < error && error < dec("1e-32"));
+        assert!(dec("-2").pow(&dec("0.5")).is_err());
+        assert!(dec("0").pow(&dec("-1")).is_err());
+        assert!(dec("10").pow(&dec("7000")).is_err());
+    }
+
     #[test]
     fn test_normalize() {
         let vals = vec![
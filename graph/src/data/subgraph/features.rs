@@ -36,6 +36,16 @@ pub enum SubgraphFeature {
     IpfsOnEthereumContracts,
 }
 
+impl SubgraphFeature {
+    /// All the features that this node knows about
+    pub const ALL: [SubgraphFeature; 4] = [
+        SubgraphFeature::NonFatalErrors,
+        SubgraphFeature::Grafting,
+        SubgraphFeature::FullTextSearch,
+        SubgraphFeature::IpfsOnEthereumContracts,
+    ];
+}
+
 impl fmt::Display for SubgraphFeature {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         serde_plain::to_string(self)
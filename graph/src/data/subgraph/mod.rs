@@ -7,8 +7,10 @@ pub use api_version::*;
 
 pub mod features;
 pub mod status;
+pub mod support;
 
 pub use features::{SubgraphFeature, SubgraphFeatureValidationError};
+pub use support::{ManifestSupport, UnsupportedManifest};
 
 use anyhow::{anyhow, Context, Error};
 use futures03::{future::try_join3, stream::FuturesOrdered, TryStreamExt as _};
@@ -18,14 +20,14 @@ use serde_yaml;
 use slog::Logger;
 use stable_hash::{FieldAddress, StableHash};
 use stable_hash_legacy::SequenceNumber;
-use std::{collections::BTreeSet, marker::PhantomData};
+use std::{collections::BTreeSet, marker::PhantomData, time::Duration};
 use thiserror::Error;
 use wasmparser;
 use web3::types::Address;
 
 use crate::{
     bail,
-    blockchain::{BlockPtr, Blockchain, DataSource as _},
+    blockchain::{BlockPtr, Blockchain, BlockchainKind, DataSource as _},
     components::{
         link_resolver::LinkResolver,
         store::{StoreError, SubgraphStore},
@@ -41,7 +43,6 @@ use crate::{
         offchain::OFFCHAIN_KINDS, DataSource, DataSourceTemplate, UnresolvedDataSource,
         UnresolvedDataSourceTemplate,
     },
-    ensure,
     prelude::{r, CheapClone, ENV_VARS},
 };
 
@@ -256,6 +257,73 @@ pub struct CreateSubgraphResult {
     pub id: String,
 }
 
+/// The step of validating a subgraph manifest that found a problem
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValidationStage {
+    /// Fetching and parsing the manifest and the files it links to
+    Resolve,
+    /// Validating the resolved manifest
+    Manifest,
+    /// Checking that the node supports the manifest's network
+    Network,
+}
+
+impl fmt::Display for ValidationStage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationStage::Resolve => write!(f, "resolve"),
+            ValidationStage::Manifest => write!(f, "manifest"),
+            ValidationStage::Network => write!(f, "network"),
+        }
+    }
+}
+
+/// A problem with a subgraph manifest
+#[derive(Clone, Debug, Serialize)]
+pub struct ValidationDiagnostic {
+    pub stage: ValidationStage,
+    pub message: String,
+}
+
+/// Result of validating a subgraph manifest without deploying it. The
+/// fields other than `valid` and `diagnostics` are filled in as far as
+/// validation got
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SubgraphValidation {
+    pub deployment: String,
+    pub valid: bool,
+    pub kind: Option<BlockchainKind>,
+    pub network: Option<String>,
+    pub spec_version: Option<String>,
+    pub api_versions: Vec<String>,
+    /// The features that the subgraph uses
+    pub features: Vec<String>,
+    /// The capabilities that the subgraph needs from the chain's providers
+    pub required_capabilities: Option<String>,
+    /// What the manifest needs that this node does not support, if anything
+    pub unsupported: Option<UnsupportedManifest>,
+    pub diagnostics: Vec<ValidationDiagnostic>,
+}
+
+impl SubgraphValidation {
+    pub fn new(deployment: &DeploymentHash) -> Self {
+        SubgraphValidation {
+            deployment: deployment.to_string(),
+            valid: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn add(&mut self, stage: ValidationStage, message: impl ToString) {
+        self.valid = false;
+        self.diagnostics.push(ValidationDiagnostic {
+            stage,
+            message: message.to_string(),
+        });
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum SubgraphRegistrarError {
     #[error("subgraph resolve error: {0}")]
@@ -270,6 +338,8 @@ pub enum SubgraphRegistrarError {
     DeploymentNotFound(String),
     #[error("deployment assignment unchanged: {0}")]
     DeploymentAssignmentUnchanged(String),
+    #[error("can not rewind deployment: {0}")]
+    InvalidRewind(String),
     #[error("subgraph registrar internal query error: {0}")]
     QueryExecutionError(#[from] QueryExecutionError),
     #[error("subgraph registrar error with store: {0}")]
@@ -336,6 +406,12 @@ pub enum SubgraphManifestValidationError {
     FeatureValidationError(#[from] SubgraphFeatureValidationError),
     #[error("data source {0} is invalid: {1}")]
     DataSourceValidation(String, Error),
+    #[error("data source {0} reads from subgraph {1}, which is not deployed on this node")]
+    SubgraphSourceNotFound(String, DeploymentHash),
+    #[error("handlerLimits.skipTimeout requires the `nonFatalErrors` feature")]
+    SkipTimeoutWithoutNonFatalErrors,
+    #[error("handlerLimits exceed what this node allows: {}", .0.join(", "))]
+    HandlerLimitsExceedCeilings(Vec<String>),
 }
 
 #[derive(Error, Debug)]
@@ -348,6 +424,8 @@ pub enum SubgraphManifestResolveError {
     InvalidFormat,
     #[error("resolve error: {0}")]
     ResolveError(#[from] anyhow::Error),
+    #[error("{0}")]
+    Unsupported(#[from] UnsupportedManifest),
 }
 
 /// Data source contexts are conveniently represented as entities.
@@ -417,6 +495,96 @@ pub fn calls_host_fn(runtime: &[u8], host_fn: &str) -> anyhow::Result<bool> {
     Ok(false)
 }
 
+/// Limits for running the handlers of a subgraph that override the limits
+/// configured for the node. They let heavyweight subgraphs request more
+/// resources without raising the limits for all subgraphs, but can not
+/// exceed the ceilings that the node operator sets
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct HandlerLimits {
+    pub max_gas: Option<u64>,
+    /// The handler timeout in seconds
+    pub timeout: Option<u64>,
+    /// The time in seconds after which a handler invocation is skipped and
+    /// recorded as a non-fatal error instead of failing the block. Only
+    /// subgraphs with the `nonFatalErrors` feature can use it
+    pub skip_timeout: Option<u64>,
+    /// The timeout for IPFS requests from handlers in seconds
+    pub ipfs_timeout: Option<u64>,
+    /// The size limit for files read with `ipfs.cat` in bytes
+    pub max_ipfs_file_size: Option<usize>,
+}
+
+impl HandlerLimits {
+    /// The gas limit for each handler invocation
+    pub fn max_gas(&self) -> u64 {
+        self.max_gas.unwrap_or(ENV_VARS.max_gas_per_handler)
+    }
+
+    /// The time that each handler invocation may take
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+            .map(Duration::from_secs)
+            .or(ENV_VARS.mappings.timeout)
+    }
+
+    /// The time after which a handler invocation is skipped, which
+    /// replaces `timeout` when it is set
+    pub fn skip_timeout(&self) -> Option<Duration> {
+        self.skip_timeout.map(Duration::from_secs)
+    }
+
+    /// The timeout for IPFS requests from handlers
+    pub fn ipfs_timeout(&self) -> Duration {
+        self.ipfs_timeout
+            .map(Duration::from_secs)
+            .unwrap_or(ENV_VARS.mappings.ipfs_timeout)
+    }
+
+    /// The size limit for files read with `ipfs.cat`
+    pub fn max_ipfs_file_size(&self) -> usize {
+        self.max_ipfs_file_size
+            .unwrap_or(ENV_VARS.mappings.max_ipfs_file_bytes)
+    }
+
+    /// The requested limits that exceed the ceilings of this node. The
+    /// limits decide whether a handler fails or which files it can read,
+    /// and with that what the subgraph indexes, so they can not be capped
+    /// to the ceilings without the subgraph indexing differently on
+    /// different nodes
+    pub fn exceeded_ceilings(&self) -> Vec<String> {
+        let mut exceeded = vec![];
+        let gas_ceiling = ENV_VARS.max_gas_per_handler_ceiling;
+        if let Some(max_gas) = self.max_gas.filter(|max_gas| *max_gas > gas_ceiling) {
+            exceeded.push(format!("maxGas {} is above {}", max_gas, gas_ceiling));
+        }
+        if let Some(ceiling) = ENV_VARS.mappings.timeout_ceiling {
+            for (name, timeout) in [
+                ("timeout", self.timeout),
+                ("skipTimeout", self.skip_timeout),
+            ] {
+                if let Some(timeout) = timeout.filter(|timeout| *timeout > ceiling.as_secs()) {
+                    exceeded.push(format!(
+                        "{} {}s is above {}s",
+                        name,
+                        timeout,
+                        ceiling.as_secs()
+                    ));
+                }
+            }
+        }
+        let ceiling = ENV_VARS.mappings.ipfs_timeout_ceiling.as_secs();
+        if let Some(timeout) = self.ipfs_timeout.filter(|timeout| *timeout > ceiling) {
+            exceeded.push(format!("ipfsTimeout {}s is above {}s", timeout, ceiling));
+        }
+        let ceiling = ENV_VARS.mappings.max_ipfs_file_bytes_ceiling;
+        if let Some(size) = self.max_ipfs_file_size.filter(|size| *size > ceiling) {
+            exceeded.push(format!("maxIpfsFileSize {} is above {}", size, ceiling));
+        }
+        exceeded
+    }
+}
+
 #[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Graft {
@@ -439,6 +607,10 @@ impl Graft {
             .is_healthy(&self.base)
             .await
             .map_err(|e| GraftBaseInvalid(e.to_string()))?;
+        let earliest_block = store
+            .earliest_block(&self.base)
+            .await
+            .map_err(|e| GraftBaseInvalid(e.to_string()))?;
 
         // We are being defensive here: we don't know which specific
         // instance of a subgraph we will use as the base for the graft,
@@ -464,6 +636,13 @@ impl Graft {
                 "failed to graft onto `{}` at block {} since it's not healthy. You can graft it starting at block {} backwards",
                 self.base, self.block, ptr.number - 1
             ))),
+            // The base has been pruned and no longer has all the data at
+            // `graft.block` that the graft would need to copy
+            (Some(_), _) if self.block < earliest_block => Err(GraftBaseInvalid(format!(
+                "failed to graft onto `{}` at block {} since it has been pruned and only has data starting at block {}. \
+                 You can graft it starting at block {} or later, or deploy with `graft_at_earliest_block` to graft at that block",
+                self.base, self.block, earliest_block, earliest_block
+            ))),
             (Some(_), _) => Ok(()),
         }
     }
@@ -483,6 +662,8 @@ pub struct BaseSubgraphManifest<C, S, D, T> {
     pub graft: Option<Graft>,
     #[serde(default)]
     pub templates: Vec<T>,
+    #[serde(default)]
+    pub handler_limits: HandlerLimits,
     #[serde(skip_serializing, default)]
     pub chain: PhantomData<C>,
 }
@@ -539,6 +720,21 @@ impl<C: Blockchain> UnvalidatedSubgraphManifest<C> {
             }));
         }
 
+        // Subgraph data sources can only read from deployments that exist
+        for ds in self.0.data_sources.iter().filter_map(|ds| ds.as_subgraph()) {
+            match store.is_deployed(&ds.source.address) {
+                Ok(true) => {}
+                Ok(false) => errors.push(SubgraphManifestValidationError::SubgraphSourceNotFound(
+                    ds.name.clone(),
+                    ds.source.address.clone(),
+                )),
+                Err(e) => errors.push(SubgraphManifestValidationError::DataSourceValidation(
+                    ds.name.clone(),
+                    e.into(),
+                )),
+            }
+        }
+
         // For API versions newer than 0.0.5, validate that all mappings uses the same api_version
         if let Err(different_api_versions) = self.0.unified_mapping_api_version() {
             errors.push(different_api_versions.into());
@@ -577,6 +773,18 @@ impl<C: Blockchain> UnvalidatedSubgraphManifest<C> {
             }
         }
 
+        // Skipping handlers records errors that would otherwise be fatal
+        if self.0.handler_limits.skip_timeout.is_some()
+            && !self.0.features.contains(&SubgraphFeature::NonFatalErrors)
+        {
+            errors.push(SubgraphManifestValidationError::SkipTimeoutWithoutNonFatalErrors);
+        }
+
+        let exceeded = self.0.handler_limits.exceeded_ceilings();
+        if !exceeded.is_empty() {
+            errors.push(SubgraphManifestValidationError::HandlerLimitsExceedCeilings(exceeded));
+        }
+
         // Validate subgraph feature usage and declaration.
         if self.0.spec_version >= SPEC_VERSION_0_0_4 {
             if let Err(feature_validation_error) = validate_subgraph_features(&self.0) {
@@ -590,9 +798,31 @@ impl<C: Blockchain> UnvalidatedSubgraphManifest<C> {
         }
     }
 
+    /// If the graft base has been pruned past the graft block, graft at
+    /// the earliest block for which the base still has all its data
+    /// instead. Returns the graft block from the manifest if it was moved
+    pub async fn graft_at_earliest_block<S: SubgraphStore>(
+        &mut self,
+        store: Arc<S>,
+    ) -> Result<Option<BlockNumber>, StoreError> {
+        let graft = match self.0.graft.as_mut() {
+            Some(graft) => graft,
+            None => return Ok(None),
+        };
+        let earliest_block = store.earliest_block(&graft.base).await?;
+        if graft.block >= earliest_block {
+            return Ok(None);
+        }
+        Ok(Some(std::mem::replace(&mut graft.block, earliest_block)))
+    }
+
     pub fn spec_version(&self) -> &Version {
         &self.0.spec_version
     }
+
+    pub fn manifest(&self) -> &SubgraphManifest<C> {
+        &self.0
+    }
 }
 
 impl<C: Blockchain> SubgraphManifest<C> {
@@ -604,6 +834,7 @@ impl<C: Blockchain> SubgraphManifest<C> {
         logger: &Logger,
         max_spec_version: semver::Version,
     ) -> Result<Self, SubgraphManifestResolveError> {
+        ManifestSupport::new(max_spec_version.clone()).check_raw(&raw)?;
         let unresolved = UnresolvedSubgraphManifest::parse(id, raw)?;
 
         let resolved = unresolved
@@ -692,17 +923,15 @@ impl<C: Blockchain> UnresolvedSubgraphManifest<C> {
             data_sources,
             graft,
             templates,
+            handler_limits,
             chain,
         } = self;
 
-        if !(MIN_SPEC_VERSION..=max_spec_version.clone()).contains(&spec_version) {
-            return Err(anyhow!(
-                "This Graph Node only supports manifest spec versions between {} and {}, but subgraph `{}` uses `{}`",
-                MIN_SPEC_VERSION,
-                max_spec_version,
-                id,
-                spec_version
-            ).into());
+        let support = ManifestSupport::new(max_spec_version);
+        if !support.supports_spec_version(&spec_version) {
+            let mut unsupported = UnsupportedManifest::new(support);
+            unsupported.spec_version = Some(spec_version);
+            return Err(unsupported.into());
         }
 
         let ds_count = data_sources.len();
@@ -731,16 +960,14 @@ impl<C: Blockchain> UnresolvedSubgraphManifest<C> {
         )
         .await?;
 
-        for ds in &data_sources {
-            ensure!(
-                semver::VersionReq::parse(&format!("<= {}", ENV_VARS.mappings.max_api_version))
-                    .unwrap()
-                    .matches(&ds.api_version()),
-                "The maximum supported mapping API version of this indexer is {}, but `{}` was found",
-                ENV_VARS.mappings.max_api_version,
-                ds.api_version()
-            );
-        }
+        let api_versions: BTreeSet<_> = data_sources
+            .iter()
+            .map(|ds| ds.api_version())
+            .filter(|version| !support.supports_api_version(version))
+            .collect();
+        let mut unsupported = UnsupportedManifest::new(support);
+        unsupported.api_versions = api_versions.into_iter().collect();
+        unsupported.into_result()?;
 
         if spec_version < SPEC_VERSION_0_0_7
             && data_sources
@@ -763,6 +990,7 @@ impl<C: Blockchain> UnresolvedSubgraphManifest<C> {
             data_sources,
             graft,
             templates,
+            handler_limits,
             chain,
         })
     }
@@ -788,6 +1016,9 @@ pub struct DeploymentState {
     pub latest_block: BlockPtr,
     /// The earliest block that the subgraph has processed
     pub earliest_block_number: BlockNumber,
+    /// If the deployment is in maintenance mode, the message that queries
+    /// are answered with. Indexing is not affected by maintenance mode
+    pub maintenance: Option<String>,
 }
 
 impl DeploymentState {
@@ -3,8 +3,10 @@
 use super::schema::{SubgraphError, SubgraphHealth};
 use crate::blockchain::BlockHash;
 use crate::components::store::{BlockNumber, DeploymentId};
+use crate::components::subgraph::OffchainSummary;
 use crate::data::graphql::{object, IntoValue};
 use crate::prelude::{r, BlockPtr, Value};
+use std::time::Duration;
 
 pub enum Filter {
     /// Get all versions for the named subgraph
@@ -109,22 +111,68 @@ pub struct Info {
 
     pub entity_count: u64,
 
+    /// How many times the subgraph has been retried since it last failed
+    /// with a non-deterministic error
+    pub retry_count: u32,
+
+    /// The number of dynamic data sources the subgraph has created
+    pub dynamic_data_source_count: u64,
+
+    /// How many dynamic data sources the subgraph may create, if that is
+    /// limited for the deployment
+    pub max_dynamic_data_sources: Option<u32>,
+
+    /// The state of the files that the file data sources of the subgraph
+    /// are waiting for, if they are monitored by this node
+    pub offchain_data_sources: Option<OffchainSummary>,
+
     /// ID of the Graph Node that the subgraph is indexed by.
     pub node: Option<String>,
+
+    /// How many blocks per second the subgraph advanced recently, as last
+    /// reported by the node that indexes it
+    pub blocks_per_second: Option<f64>,
+}
+
+impl Info {
+    /// How long it will take the subgraph to reach the chain head if it
+    /// keeps advancing at the rate it did recently. This is `None` if the
+    /// subgraph is synced, failed, or has not reported a rate yet
+    pub fn sync_eta(&self) -> Option<Duration> {
+        if self.synced || self.health == SubgraphHealth::Failed {
+            return None;
+        }
+        let blocks_per_second = self.blocks_per_second.filter(|rate| *rate > 0.0)?;
+        let chain = self.chains.first()?;
+        let head = chain.chain_head_block.as_ref()?.number();
+        let latest = chain
+            .latest_block
+            .as_ref()
+            .map(|block| block.number())
+            .unwrap_or(chain.earliest_block_number);
+        let behind = (head - latest).max(0);
+        Some(Duration::from_secs_f64(behind as f64 / blocks_per_second))
+    }
 }
 
 impl IntoValue for Info {
     fn into_value(self) -> r::Value {
+        let sync_eta = self.sync_eta();
         let Info {
             id: _,
             subgraph,
             chains,
             entity_count,
+            dynamic_data_source_count,
+            max_dynamic_data_sources,
+            offchain_data_sources,
             fatal_error,
             health,
             node,
             non_fatal_errors,
+            retry_count,
             synced,
+            blocks_per_second,
         } = self;
 
         fn subgraph_error_to_value(subgraph_error: SubgraphError) -> r::Value {
@@ -165,7 +213,30 @@ impl IntoValue for Info {
             nonFatalErrors: non_fatal_errors,
             chains: chains.into_iter().map(|chain| chain.into_value()).collect::<Vec<_>>(),
             entityCount: format!("{}", entity_count),
+            retryCount: retry_count as i32,
+            dynamicDataSourceCount: dynamic_data_source_count as i32,
+            maxDynamicDataSources: max_dynamic_data_sources.map(|max| max as i32),
+            offchainDataSources: offchain_data_sources.map(|summary| object! {
+                __typename: "OffchainDataSources",
+                pending: summary.pending as i32,
+                failed: summary.failed as i32,
+                fetched: summary.fetched as i32,
+                bytesFetched: format!("{}", summary.bytes_fetched),
+                oldestPendingSeconds: summary.oldest_pending.map(|age| age.as_secs_f64()),
+            }),
             node: node,
+            blocksPerSecond: blocks_per_second,
+            syncEtaSeconds: sync_eta.map(|eta| eta.as_secs_f64()),
         }
     }
 }
+
+/// The GraphQL schema and the manifest of a deployment as they were
+/// deployed
+#[derive(Clone, Debug)]
+pub struct RawManifest {
+    pub schema: String,
+    /// The manifest YAML. `None` for deployments that were created before
+    /// the manifest was stored and that have not been started since
+    pub manifest: Option<String>,
+}
@@ -0,0 +1,225 @@
+//! The manifest spec versions, mapping API versions, and features that this
+//! node supports. Deploy tooling can ask for them through the index node
+//! API to check a subgraph before deploying it, and a manifest that needs
+//! more than that is rejected with an [`UnsupportedManifest`] error that
+//! lists everything that is missing rather than just the first problem.
+use std::collections::BTreeSet;
+use std::fmt;
+
+use itertools::Itertools;
+use semver::Version;
+use serde::Serialize;
+use serde_yaml::{Mapping, Value};
+use thiserror::Error;
+
+use super::{SubgraphFeature, MIN_SPEC_VERSION};
+use crate::prelude::ENV_VARS;
+
+/// What manifests this node supports
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestSupport {
+    pub min_spec_version: Version,
+    pub max_spec_version: Version,
+    pub max_api_version: Version,
+    pub features: Vec<SubgraphFeature>,
+}
+
+impl ManifestSupport {
+    /// The manifests that this node supports if `max_spec_version` is the
+    /// highest spec version that it accepts
+    pub fn new(max_spec_version: Version) -> Self {
+        ManifestSupport {
+            min_spec_version: MIN_SPEC_VERSION,
+            max_spec_version,
+            max_api_version: ENV_VARS.mappings.max_api_version.clone(),
+            features: SubgraphFeature::ALL.to_vec(),
+        }
+    }
+
+    /// The manifests that this node supports with its current configuration
+    pub fn current() -> Self {
+        Self::new(ENV_VARS.max_spec_version.clone())
+    }
+
+    pub fn supports_spec_version(&self, version: &Version) -> bool {
+        (&self.min_spec_version..=&self.max_spec_version).contains(&version)
+    }
+
+    pub fn supports_api_version(&self, version: &Version) -> bool {
+        version <= &self.max_api_version
+    }
+
+    /// Check the spec version, the mapping API versions, and the features
+    /// of the manifest `raw` before it is parsed, since parsing fails on
+    /// the first feature it does not know. Values that can not be parsed
+    /// at all are left for parsing the manifest to report
+    pub fn check_raw(&self, raw: &Mapping) -> Result<(), UnsupportedManifest> {
+        let get = |key: &str| raw.get(&Value::String(key.to_owned()));
+        let version = |value: Option<&Value>| value?.as_str()?.parse::<Version>().ok();
+
+        let spec_version =
+            version(get("specVersion")).filter(|version| !self.supports_spec_version(version));
+        let api_versions: BTreeSet<_> = [get("dataSources"), get("templates")]
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_sequence)
+            .flatten()
+            .filter_map(|ds| version(ds.get("mapping")?.get("apiVersion")))
+            .filter(|version| !self.supports_api_version(version))
+            .collect();
+        let features = get("features")
+            .and_then(Value::as_sequence)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .filter(|feature| feature.parse::<SubgraphFeature>().is_err())
+            .map(str::to_string)
+            .collect();
+
+        UnsupportedManifest {
+            spec_version,
+            api_versions: api_versions.into_iter().collect(),
+            features,
+            supported: self.clone(),
+        }
+        .into_result()
+    }
+}
+
+/// The parts of a manifest that this node does not support, together with
+/// what it supports instead
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Error)]
+#[serde(rename_all = "camelCase")]
+pub struct UnsupportedManifest {
+    /// The spec version of the manifest if it is not supported
+    pub spec_version: Option<Version>,
+    /// The mapping API versions of the manifest that are not supported
+    pub api_versions: Vec<Version>,
+    /// The features that the manifest declares and that this node does not
+    /// know
+    pub features: Vec<String>,
+    pub supported: ManifestSupport,
+}
+
+impl UnsupportedManifest {
+    pub fn new(supported: ManifestSupport) -> Self {
+        UnsupportedManifest {
+            spec_version: None,
+            api_versions: vec![],
+            features: vec![],
+            supported,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spec_version.is_none() && self.api_versions.is_empty() && self.features.is_empty()
+    }
+
+    /// `Ok(())` if nothing is unsupported, `Err(self)` otherwise
+    pub fn into_result(self) -> Result<(), Self> {
+        match self.is_empty() {
+            true => Ok(()),
+            false => Err(self),
+        }
+    }
+}
+
+impl fmt::Display for UnsupportedManifest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let supported = &self.supported;
+        let mut problems = vec![];
+        if let Some(version) = &self.spec_version {
+            problems.push(format!(
+                "manifest spec version {} (supported are {} to {})",
+                version, supported.min_spec_version, supported.max_spec_version
+            ));
+        }
+        if !self.api_versions.is_empty() {
+            problems.push(format!(
+                "mapping API versions {} (the highest supported is {})",
+                self.api_versions.iter().join(", "),
+                supported.max_api_version
+            ));
+        }
+        if !self.features.is_empty() {
+            problems.push(format!(
+                "features {} (supported are {})",
+                self.features.iter().join(", "),
+                supported.features.iter().join(", ")
+            ));
+        }
+        write!(
+            f,
+            "this Graph Node does not support {}",
+            problems.join("; ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn support() -> ManifestSupport {
+        ManifestSupport {
+            min_spec_version: Version::new(0, 0, 2),
+            max_spec_version: Version::new(0, 0, 5),
+            max_api_version: Version::new(0, 0, 6),
+            features: SubgraphFeature::ALL.to_vec(),
+        }
+    }
+
+    fn check(manifest: &str) -> Result<(), UnsupportedManifest> {
+        support().check_raw(&serde_yaml::from_str(manifest).unwrap())
+    }
+
+    #[test]
+    fn supported_manifest() {
+        const YAML: &str = "
+specVersion: 0.0.4
+features:
+  - grafting
+  - nonDeterministicIpfs
+dataSources:
+  - mapping:
+      apiVersion: 0.0.6
+";
+        assert_eq!(Ok(()), check(YAML));
+    }
+
+    #[test]
+    fn unsupported_manifest() {
+        const YAML: &str = "
+specVersion: 0.0.7
+features:
+  - grafting
+  - aggregations
+  - timeTravel
+dataSources:
+  - mapping:
+      apiVersion: 0.0.8
+  - mapping:
+      apiVersion: 0.0.5
+templates:
+  - mapping:
+      apiVersion: 0.0.8
+  - mapping:
+      apiVersion: 0.0.7
+";
+        let err = check(YAML).unwrap_err();
+        assert_eq!(Some(Version::new(0, 0, 7)), err.spec_version);
+        assert_eq!(
+            vec![Version::new(0, 0, 7), Version::new(0, 0, 8)],
+            err.api_versions
+        );
+        assert_eq!(vec!["aggregations", "timeTravel"], err.features);
+        assert_eq!(
+            "this Graph Node does not support manifest spec version 0.0.7 (supported are \
+             0.0.2 to 0.0.5); mapping API versions 0.0.7, 0.0.8 (the highest supported is \
+             0.0.6); features aggregations, timeTravel (supported are nonFatalErrors, grafting, \
+             fullTextSearch, ipfsOnEthereumContracts)",
+            err.to_string()
+        );
+    }
+}
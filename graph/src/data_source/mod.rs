@@ -1,5 +1,6 @@
 pub mod causality_region;
 pub mod offchain;
+pub mod subgraph;
 
 pub use causality_region::CausalityRegion;
 
@@ -8,14 +9,14 @@ mod tests;
 
 use crate::{
     blockchain::{
-        BlockPtr, Blockchain, DataSource as _, DataSourceTemplate as _, TriggerData as _,
-        UnresolvedDataSource as _, UnresolvedDataSourceTemplate as _,
+        Block as _, BlockPtr, Blockchain, DataSource as _, DataSourceTemplate as _,
+        TriggerData as _, UnresolvedDataSource as _, UnresolvedDataSourceTemplate as _,
     },
     components::{
         link_resolver::LinkResolver,
         store::{BlockNumber, EntityType, StoredDynamicDataSource},
     },
-    data_source::offchain::OFFCHAIN_KINDS,
+    data_source::{offchain::OFFCHAIN_KINDS, subgraph::SUBGRAPH_DS_KIND},
     prelude::{CheapClone as _, DataSourceContext},
 };
 use anyhow::Error;
@@ -29,6 +30,7 @@ use thiserror::Error;
 pub enum DataSource<C: Blockchain> {
     Onchain(C::DataSource),
     Offchain(offchain::DataSource),
+    Subgraph(subgraph::DataSource),
 }
 
 #[derive(Error, Debug)]
@@ -82,21 +84,29 @@ impl<C: Blockchain> DataSource<C> {
     pub fn as_onchain(&self) -> Option<&C::DataSource> {
         match self {
             Self::Onchain(ds) => Some(ds),
-            Self::Offchain(_) => None,
+            Self::Offchain(_) | Self::Subgraph(_) => None,
         }
     }
 
     pub fn as_offchain(&self) -> Option<&offchain::DataSource> {
         match self {
-            Self::Onchain(_) => None,
+            Self::Onchain(_) | Self::Subgraph(_) => None,
             Self::Offchain(ds) => Some(ds),
         }
     }
 
+    pub fn as_subgraph(&self) -> Option<&subgraph::DataSource> {
+        match self {
+            Self::Onchain(_) | Self::Offchain(_) => None,
+            Self::Subgraph(ds) => Some(ds),
+        }
+    }
+
     pub fn address(&self) -> Option<Vec<u8>> {
         match self {
             Self::Onchain(ds) => ds.address().map(ToOwned::to_owned),
             Self::Offchain(ds) => ds.address(),
+            Self::Subgraph(ds) => ds.address(),
         }
     }
 
@@ -104,6 +114,7 @@ impl<C: Blockchain> DataSource<C> {
         match self {
             Self::Onchain(ds) => ds.name(),
             Self::Offchain(ds) => &ds.name,
+            Self::Subgraph(ds) => &ds.name,
         }
     }
 
@@ -111,6 +122,7 @@ impl<C: Blockchain> DataSource<C> {
         match self {
             Self::Onchain(ds) => ds.kind(),
             Self::Offchain(ds) => &ds.kind,
+            Self::Subgraph(ds) => &ds.kind,
         }
     }
 
@@ -118,6 +130,7 @@ impl<C: Blockchain> DataSource<C> {
         match self {
             Self::Onchain(ds) => ds.creation_block(),
             Self::Offchain(ds) => ds.creation_block,
+            Self::Subgraph(_) => None,
         }
     }
 
@@ -125,6 +138,7 @@ impl<C: Blockchain> DataSource<C> {
         match self {
             Self::Onchain(ds) => ds.context(),
             Self::Offchain(ds) => ds.context.clone(),
+            Self::Subgraph(ds) => ds.context.clone(),
         }
     }
 
@@ -132,6 +146,7 @@ impl<C: Blockchain> DataSource<C> {
         match self {
             Self::Onchain(ds) => ds.api_version(),
             Self::Offchain(ds) => ds.mapping.api_version.clone(),
+            Self::Subgraph(ds) => ds.mapping.api_version.clone(),
         }
     }
 
@@ -139,18 +154,38 @@ impl<C: Blockchain> DataSource<C> {
         match self {
             Self::Onchain(ds) => ds.runtime(),
             Self::Offchain(ds) => Some(ds.mapping.runtime.cheap_clone()),
+            Self::Subgraph(ds) => Some(ds.mapping.runtime.cheap_clone()),
+        }
+    }
+
+    pub fn handler_names(&self) -> Vec<&str> {
+        match self {
+            Self::Onchain(ds) => ds.handler_names(),
+            Self::Offchain(ds) => vec![ds.mapping.handler.as_str()],
+            Self::Subgraph(ds) => ds.handler_names(),
         }
     }
 
     pub fn entities(&self) -> EntityTypeAccess {
         match self {
             // Note: Onchain data sources have an `entities` field in the manifest, but it has never
-            // been enforced.
-            Self::Onchain(_) => EntityTypeAccess::Any,
+            // been enforced. Subgraph data sources write in the onchain causality region, and
+            // are treated the same way.
+            Self::Onchain(_) | Self::Subgraph(_) => EntityTypeAccess::Any,
             Self::Offchain(ds) => EntityTypeAccess::Restriced(ds.mapping.entities.clone()),
         }
     }
 
+    /// The entity types that the mapping of an onchain data source
+    /// declares, if its kind of data source declares them. Other data
+    /// sources return `None`
+    pub fn declared_entities(&self) -> Option<Vec<EntityType>> {
+        match self {
+            Self::Onchain(ds) => ds.declared_entities(),
+            Self::Offchain(_) | Self::Subgraph(_) => None,
+        }
+    }
+
     pub fn match_and_decode(
         &self,
         trigger: &TriggerData<C>,
@@ -162,10 +197,12 @@ impl<C: Blockchain> DataSource<C> {
                 .match_and_decode(trigger, block, logger)
                 .map(|t| t.map(|t| t.map(MappingTrigger::Onchain))),
             (Self::Offchain(ds), TriggerData::Offchain(trigger)) => {
-                Ok(ds.match_and_decode(trigger))
+                Ok(ds.match_and_decode(trigger, logger))
+            }
+            (Self::Subgraph(ds), TriggerData::Subgraph(trigger)) => {
+                Ok(ds.match_and_decode(trigger, block.ptr()))
             }
-            (Self::Onchain(_), TriggerData::Offchain(_))
-            | (Self::Offchain(_), TriggerData::Onchain(_)) => Ok(None),
+            _ => Ok(None),
         }
     }
 
@@ -173,6 +210,7 @@ impl<C: Blockchain> DataSource<C> {
         match (self, other) {
             (Self::Onchain(a), Self::Onchain(b)) => a.is_duplicate_of(b),
             (Self::Offchain(a), Self::Offchain(b)) => a.is_duplicate_of(b),
+            (Self::Subgraph(a), Self::Subgraph(b)) => a.is_duplicate_of(b),
             _ => false,
         }
     }
@@ -181,6 +219,7 @@ impl<C: Blockchain> DataSource<C> {
         match self {
             Self::Onchain(ds) => ds.as_stored_dynamic_data_source(),
             Self::Offchain(ds) => ds.as_stored_dynamic_data_source(),
+            Self::Subgraph(ds) => ds.as_stored_dynamic_data_source(),
         }
     }
 
@@ -204,12 +243,13 @@ impl<C: Blockchain> DataSource<C> {
         match self {
             Self::Onchain(ds) => ds.validate(),
             Self::Offchain(_) => vec![],
+            Self::Subgraph(ds) => ds.validate(),
         }
     }
 
     pub fn causality_region(&self) -> CausalityRegion {
         match self {
-            Self::Onchain(_) => CausalityRegion::ONCHAIN,
+            Self::Onchain(_) | Self::Subgraph(_) => CausalityRegion::ONCHAIN,
             Self::Offchain(ds) => ds.causality_region,
         }
     }
@@ -219,6 +259,7 @@ impl<C: Blockchain> DataSource<C> {
 pub enum UnresolvedDataSource<C: Blockchain> {
     Onchain(C::UnresolvedDataSource),
     Offchain(offchain::UnresolvedDataSource),
+    Subgraph(subgraph::UnresolvedDataSource),
 }
 
 impl<C: Blockchain> UnresolvedDataSource<C> {
@@ -239,6 +280,10 @@ impl<C: Blockchain> UnresolvedDataSource<C> {
                      for details see https://github.com/graphprotocol/graph-node/issues/3864"
                 );
             }
+            Self::Subgraph(unresolved) => unresolved
+                .resolve(resolver, logger, manifest_idx)
+                .await
+                .map(DataSource::Subgraph),
         }
     }
 }
@@ -292,6 +337,13 @@ impl<C: Blockchain> DataSourceTemplate<C> {
         }
     }
 
+    pub fn handler_names(&self) -> Vec<&str> {
+        match self {
+            Self::Onchain(ds) => ds.handler_names(),
+            Self::Offchain(ds) => vec![ds.mapping.handler.as_str()],
+        }
+    }
+
     pub fn manifest_idx(&self) -> u32 {
         match self {
             Self::Onchain(ds) => ds.manifest_idx(),
@@ -398,6 +450,7 @@ impl<T> TriggerWithHandler<T> {
 pub enum TriggerData<C: Blockchain> {
     Onchain(C::TriggerData),
     Offchain(offchain::TriggerData),
+    Subgraph(subgraph::TriggerData),
 }
 
 impl<C: Blockchain> TriggerData<C> {
@@ -405,6 +458,7 @@ impl<C: Blockchain> TriggerData<C> {
         match self {
             Self::Onchain(trigger) => trigger.error_context(),
             Self::Offchain(trigger) => format!("{:?}", trigger.source),
+            Self::Subgraph(trigger) => format!("{:?}", trigger),
         }
     }
 }
@@ -413,23 +467,27 @@ impl<C: Blockchain> TriggerData<C> {
 pub enum MappingTrigger<C: Blockchain> {
     Onchain(C::MappingTrigger),
     Offchain(offchain::TriggerData),
+    Subgraph(subgraph::TriggerData),
 }
 
-macro_rules! clone_data_source {
-    ($t:ident) => {
-        impl<C: Blockchain> Clone for $t<C> {
-            fn clone(&self) -> Self {
-                match self {
-                    Self::Onchain(ds) => Self::Onchain(ds.clone()),
-                    Self::Offchain(ds) => Self::Offchain(ds.clone()),
-                }
-            }
+impl<C: Blockchain> Clone for DataSource<C> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Onchain(ds) => Self::Onchain(ds.clone()),
+            Self::Offchain(ds) => Self::Offchain(ds.clone()),
+            Self::Subgraph(ds) => Self::Subgraph(ds.clone()),
         }
-    };
+    }
 }
 
-clone_data_source!(DataSource);
-clone_data_source!(DataSourceTemplate);
+impl<C: Blockchain> Clone for DataSourceTemplate<C> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Onchain(ds) => Self::Onchain(ds.clone()),
+            Self::Offchain(ds) => Self::Offchain(ds.clone()),
+        }
+    }
+}
 
 macro_rules! deserialize_data_source {
     ($t:ident) => {
@@ -444,7 +502,9 @@ macro_rules! deserialize_data_source {
                     .ok_or(serde::de::Error::missing_field("kind"))?
                     .as_str()
                     .unwrap_or("?");
-                if OFFCHAIN_KINDS.contains(&kind) {
+                if kind == SUBGRAPH_DS_KIND {
+                    Self::deserialize_subgraph::<D::Error>(map)
+                } else if OFFCHAIN_KINDS.contains(&kind) {
                     offchain::$t::deserialize(map.into_deserializer())
                         .map_err(serde::de::Error::custom)
                         .map($t::Offchain)
@@ -454,7 +514,7 @@ macro_rules! deserialize_data_source {
                         .map($t::Onchain)
                 } else {
                     Err(serde::de::Error::custom(format!(
-                        "data source has invalid `kind`; expected {}, file/ipfs",
+                        "data source has invalid `kind`; expected {}, file/ipfs or subgraph",
                         C::KIND,
                     )))
                 }
@@ -463,5 +523,25 @@ macro_rules! deserialize_data_source {
     };
 }
 
+impl<C: Blockchain> UnresolvedDataSource<C> {
+    fn deserialize_subgraph<E: serde::de::Error>(
+        map: BTreeMap<String, serde_json::Value>,
+    ) -> Result<Self, E> {
+        subgraph::UnresolvedDataSource::deserialize(map.into_deserializer())
+            .map_err(E::custom)
+            .map(UnresolvedDataSource::Subgraph)
+    }
+}
+
+impl<C: Blockchain> UnresolvedDataSourceTemplate<C> {
+    fn deserialize_subgraph<E: serde::de::Error>(
+        _map: BTreeMap<String, serde_json::Value>,
+    ) -> Result<Self, E> {
+        Err(E::custom(
+            "data source templates of kind `subgraph` are not supported",
+        ))
+    }
+}
+
 deserialize_data_source!(UnresolvedDataSource);
 deserialize_data_source!(UnresolvedDataSourceTemplate);
@@ -13,23 +13,29 @@ use crate::{
 };
 use anyhow::{self, Context, Error};
 use serde::Deserialize;
-use slog::{info, Logger};
+use slog::{info, warn, Logger};
 use std::{
     fmt,
+    str::FromStr,
     sync::{atomic::AtomicI32, Arc},
 };
 
 use super::{CausalityRegion, DataSourceCreationError, TriggerWithHandler};
 
-pub const OFFCHAIN_KINDS: &[&str] = &["file/ipfs"];
+pub const OFFCHAIN_KINDS: &[&str] = &["file/ipfs", "file/arweave", "file/object-store"];
 const NOT_DONE_VALUE: i32 = -1;
 
+/// Separates the source from the content type in the stored param of a
+/// data source. Neither CIDs, paths, Arweave ids nor object URLs contain it
+const CONTENT_TYPE_SEPARATOR: char = '\n';
+
 #[derive(Debug, Clone)]
 pub struct DataSource {
     pub kind: String,
     pub name: String,
     pub manifest_idx: u32,
     pub source: Source,
+    pub content_type: ContentType,
     pub mapping: Mapping,
     pub context: Arc<Option<DataSourceContext>>,
     pub creation_block: Option<BlockNumber>,
@@ -43,6 +49,7 @@ impl DataSource {
         name: String,
         manifest_idx: u32,
         source: Source,
+        content_type: ContentType,
         mapping: Mapping,
         context: Arc<Option<DataSourceContext>>,
         creation_block: Option<BlockNumber>,
@@ -53,6 +60,7 @@ impl DataSource {
             name,
             manifest_idx,
             source,
+            content_type,
             mapping,
             context,
             creation_block,
@@ -99,15 +107,26 @@ impl DataSource {
                 bail!("Cannot create offchain data source from onchain template")
             }
         };
-        let source = info.params.into_iter().next().ok_or(anyhow::anyhow!(
+        let mut params = info.params.into_iter();
+        let source = params.next().ok_or(anyhow::anyhow!(
             "Failed to create data source from template `{}`: source parameter is missing",
             template.name
         ))?;
+        // The optional second parameter declares the content type of the file
+        let content_type = match params.next() {
+            Some(content_type) => content_type.parse::<ContentType>().with_context(|| {
+                format!(
+                    "Failed to create data source from template `{}`",
+                    template.name
+                )
+            })?,
+            None => ContentType::Raw,
+        };
 
-        let source = match source.parse() {
-            Ok(source) => Source::Ipfs(source),
+        let source = match Source::parse(&template.kind, &source) {
+            Ok(source) => source,
 
-            // Ignore data sources created with an invalid CID.
+            // Ignore data sources created with an invalid CID or transaction id.
             Err(e) => return Err(DataSourceCreationError::Ignore(source, e)),
         };
 
@@ -116,6 +135,7 @@ impl DataSource {
             name: template.name.clone(),
             manifest_idx: template.manifest_idx,
             source,
+            content_type,
             mapping: template.mapping,
             context: Arc::new(info.context),
             creation_block: Some(info.creation_block),
@@ -127,21 +147,42 @@ impl DataSource {
     pub fn match_and_decode<C: Blockchain>(
         &self,
         trigger: &TriggerData,
+        logger: &Logger,
     ) -> Option<TriggerWithHandler<super::MappingTrigger<C>>> {
         if self.source != trigger.source || self.is_processed() {
             return None;
         }
+        // Files whose content does not match the declared content type are
+        // not passed to the handler
+        if let Err(e) = self.content_type.check(&trigger.data) {
+            warn!(logger, "File does not match the declared content type";
+                "source" => format!("{:?}", self.source),
+                "content_type" => self.content_type.as_str(),
+                "error" => e.to_string());
+            return None;
+        }
         Some(TriggerWithHandler::new(
-            data_source::MappingTrigger::Offchain(trigger.clone()),
+            data_source::MappingTrigger::Offchain(TriggerData {
+                content_type: self.content_type,
+                ..trigger.clone()
+            }),
             self.mapping.handler.clone(),
             BlockPtr::new(Default::default(), self.creation_block.unwrap_or(0)),
         ))
     }
 
     pub fn as_stored_dynamic_data_source(&self) -> StoredDynamicDataSource {
-        let param = match self.source {
-            Source::Ipfs(ref link) => Bytes::from(link.to_bytes()),
+        let mut param = match self.source {
+            Source::Ipfs(ref link) => link.to_bytes(),
+            Source::Arweave(ref id) => id.to_bytes(),
+            Source::ObjectStore(ref file) => file.to_bytes(),
         };
+        // Raw files are stored with just the source for compatibility with
+        // data sources created before content types existed
+        if self.content_type != ContentType::Raw {
+            param.extend(format!("{}{}", CONTENT_TYPE_SEPARATOR, self.content_type).bytes());
+        }
+        let param = Bytes::from(param);
 
         let done_at = self.done_at.load(std::sync::atomic::Ordering::SeqCst);
         let done_at = if done_at == NOT_DONE_VALUE {
@@ -180,9 +221,12 @@ impl DataSource {
         } = stored;
 
         let param = param.context("no param on stored data source")?;
-        let cid_file = CidFile::try_from(param)?;
-
-        let source = Source::Ipfs(cid_file);
+        let param = String::from_utf8(param.to_vec())?;
+        let (source, content_type) = match param.split_once(CONTENT_TYPE_SEPARATOR) {
+            Some((source, content_type)) => (source, content_type.parse::<ContentType>()?),
+            None => (param.as_str(), ContentType::Raw),
+        };
+        let source = Source::parse(&template.kind, source)?;
         let context = Arc::new(context.map(serde_json::from_value).transpose()?);
 
         Ok(Self {
@@ -190,6 +234,7 @@ impl DataSource {
             name: template.name.clone(),
             manifest_idx,
             source,
+            content_type,
             mapping: template.mapping.clone(),
             context,
             creation_block,
@@ -203,6 +248,8 @@ impl DataSource {
     pub fn address(&self) -> Option<Vec<u8>> {
         match self.source {
             Source::Ipfs(ref cid) => Some(cid.to_bytes()),
+            Source::Arweave(ref id) => Some(id.to_bytes()),
+            Source::ObjectStore(ref file) => Some(file.to_bytes()),
         }
     }
 
@@ -215,6 +262,7 @@ impl DataSource {
 
             manifest_idx,
             source,
+            content_type,
             context,
 
             // We want to deduplicate across done status or creation block.
@@ -232,13 +280,245 @@ impl DataSource {
         } = self;
 
         // See also: data-source-is-duplicate-of
-        manifest_idx == &b.manifest_idx && source == &b.source && context == &b.context
+        manifest_idx == &b.manifest_idx
+            && source == &b.source
+            && content_type == &b.content_type
+            && context == &b.context
+    }
+}
+
+/// The content type that a mapping declares for a file when it creates a
+/// file data source. It determines what the file handler receives: raw
+/// files are passed as `Bytes`, all other files as a `JSONValue`
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ContentType {
+    #[default]
+    Raw,
+    Json,
+    /// NFT metadata as described in ERC-721 and ERC-1155, i.e., a JSON
+    /// object with an `image` field
+    ImageMetadata,
+}
+
+impl ContentType {
+    pub const NAMES: &'static [&'static str] = &["raw", "json", "image-metadata"];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentType::Raw => "raw",
+            ContentType::Json => "json",
+            ContentType::ImageMetadata => "image-metadata",
+        }
+    }
+
+    /// Parse `data` as JSON. Fails if `data` does not have this content
+    /// type or if the content type is not JSON based
+    pub fn parse_json(&self, data: &[u8]) -> Result<serde_json::Value, Error> {
+        let value: serde_json::Value = match self {
+            ContentType::Raw => bail!("raw files are not parsed as JSON"),
+            ContentType::Json | ContentType::ImageMetadata => serde_json::from_slice(data)
+                .map_err(|e| anyhow::anyhow!("file is not valid JSON: {}", e))?,
+        };
+        if *self == ContentType::ImageMetadata {
+            match value.get("image") {
+                Some(serde_json::Value::String(_)) => {}
+                _ => bail!("image metadata must be a JSON object with an `image` string"),
+            }
+        }
+        Ok(value)
+    }
+
+    /// Check that `data` has this content type
+    pub fn check(&self, data: &[u8]) -> Result<(), Error> {
+        match self {
+            ContentType::Raw => Ok(()),
+            ContentType::Json | ContentType::ImageMetadata => self.parse_json(data).map(|_| ()),
+        }
+    }
+}
+
+impl fmt::Display for ContentType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ContentType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "raw" => Ok(ContentType::Raw),
+            "json" => Ok(ContentType::Json),
+            "image-metadata" => Ok(ContentType::ImageMetadata),
+            _ => bail!(
+                "invalid content type `{}`, expected one of {}",
+                s,
+                ContentType::NAMES.join(", ")
+            ),
+        }
     }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Source {
     Ipfs(CidFile),
+    Arweave(ArweaveTxId),
+    ObjectStore(ObjectStoreFile),
+}
+
+impl Source {
+    /// Parse the source of a data source of kind `kind`
+    pub fn parse(kind: &str, source: &str) -> Result<Self, Error> {
+        match kind {
+            "file/ipfs" => Ok(Source::Ipfs(source.parse()?)),
+            "file/arweave" => Ok(Source::Arweave(source.parse()?)),
+            "file/object-store" => Ok(Source::ObjectStore(source.parse()?)),
+            _ => anyhow::bail!(
+                "offchain data source has invalid `kind`, expected one of {} but found {}",
+                OFFCHAIN_KINDS.join(", "),
+                kind
+            ),
+        }
+    }
+}
+
+/// The id of an Arweave transaction, i.e., the base64url encoding of its
+/// 32 byte hash. It can be given with or without an `ar://` prefix
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct ArweaveTxId(String);
+
+impl ArweaveTxId {
+    const PREFIX: &'static str = "ar://";
+    /// The length of the base64url encoding of 32 bytes without padding
+    const LEN: usize = 43;
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.as_bytes().to_vec()
+    }
+}
+
+impl fmt::Display for ArweaveTxId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for ArweaveTxId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let id = s.strip_prefix(Self::PREFIX).unwrap_or(s);
+        if id.len() != Self::LEN
+            || !id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            anyhow::bail!("`{}` is not a valid Arweave transaction id", s);
+        }
+        Ok(ArweaveTxId(id.to_string()))
+    }
+}
+
+/// The cloud object stores that file data sources can read from
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum ObjectStore {
+    S3,
+    Gcs,
+}
+
+impl ObjectStore {
+    pub fn scheme(&self) -> &'static str {
+        match self {
+            ObjectStore::S3 => "s3",
+            ObjectStore::Gcs => "gs",
+        }
+    }
+}
+
+/// An object in cloud object storage, written as
+/// `s3://<bucket>/<key>#keccak256=<hash>` or `gs://<bucket>/<key>#keccak256=<hash>`.
+/// Unlike IPFS and Arweave files, objects are not content-addressed, and
+/// the source therefore has to include the hex-encoded keccak256 hash of
+/// the content, usually taken from the chain. Objects with different
+/// content are never passed to the data source
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ObjectStoreFile {
+    pub store: ObjectStore,
+    pub bucket: String,
+    pub key: String,
+    pub hash: [u8; 32],
+}
+
+impl ObjectStoreFile {
+    const HASH_PREFIX: &'static str = "keccak256=";
+
+    /// The URL of the object without the hash
+    pub fn url(&self) -> String {
+        format!("{}://{}/{}", self.store.scheme(), self.bucket, self.key)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_string().into_bytes()
+    }
+}
+
+impl fmt::Display for ObjectStoreFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}#{}0x{}",
+            self.url(),
+            Self::HASH_PREFIX,
+            hex::encode(self.hash)
+        )
+    }
+}
+
+impl FromStr for ObjectStoreFile {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            |reason: &str| anyhow::anyhow!("`{}` is not a valid object URL: {}", s, reason);
+
+        let (store, rest) = if let Some(rest) = s.strip_prefix("s3://") {
+            (ObjectStore::S3, rest)
+        } else if let Some(rest) = s.strip_prefix("gs://") {
+            (ObjectStore::Gcs, rest)
+        } else {
+            return Err(invalid("it must start with `s3://` or `gs://`"));
+        };
+        let (path, hash) = rest
+            .split_once('#')
+            .ok_or_else(|| invalid("it must end in `#keccak256=<hash>`"))?;
+        let (bucket, key) = path
+            .split_once('/')
+            .filter(|(bucket, key)| !bucket.is_empty() && !key.is_empty())
+            .ok_or_else(|| invalid("it must have a bucket and a key"))?;
+        if key.contains(CONTENT_TYPE_SEPARATOR) {
+            return Err(invalid("the key must not contain a newline"));
+        }
+
+        let hash = hash
+            .strip_prefix(Self::HASH_PREFIX)
+            .ok_or_else(|| invalid("it must end in `#keccak256=<hash>`"))?;
+        let hash = hex::decode(hash.trim_start_matches("0x"))
+            .ok()
+            .and_then(|hash| <[u8; 32]>::try_from(hash).ok())
+            .ok_or_else(|| invalid("the hash must be 32 hex-encoded bytes"))?;
+
+        Ok(ObjectStoreFile {
+            store,
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            hash,
+        })
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -288,20 +568,13 @@ impl UnresolvedDataSource {
             "kind" => &self.kind,
             "source" => format_args!("{:?}", &self.source),
         );
-        let source = match self.kind.as_str() {
-            "file/ipfs" => Source::Ipfs(self.source.file.link.parse()?),
-            _ => {
-                anyhow::bail!(
-                    "offchain data source has invalid `kind`, expected `file/ipfs` but found {}",
-                    self.kind
-                );
-            }
-        };
+        let source = Source::parse(&self.kind, &self.source.file.link)?;
         Ok(DataSource {
             manifest_idx,
             kind: self.kind,
             name: self.name,
             source,
+            content_type: ContentType::Raw,
             mapping: self.mapping.resolve(resolver, logger).await?,
             context: Arc::new(None),
             creation_block: None,
@@ -373,6 +646,9 @@ impl UnresolvedDataSourceTemplate {
 pub struct TriggerData {
     pub source: Source,
     pub data: Arc<bytes::Bytes>,
+    /// The content type of the data source that handles the file. Files
+    /// are always fetched as `Raw`
+    pub content_type: ContentType,
 }
 
 impl fmt::Debug for TriggerData {
@@ -0,0 +1,230 @@
+//! Data sources of kind `subgraph` read the entity changes of another
+//! deployment on the same node instead of data from the chain. For every
+//! block that the subgraph processes, the changes that the source
+//! deployment made since the previous block the subgraph processed are
+//! passed to the handlers that the data source declares for their entity
+//! types. This makes it possible to build subgraphs on top of other
+//! subgraphs without indexing the chain again.
+//!
+//! The source deployment must index the same network as the subgraph, and
+//! the subgraph must have at least one onchain data source, whose blocks
+//! determine when entity changes are delivered. Removals of entities are
+//! not delivered.
+use crate::{
+    blockchain::{BlockPtr, Blockchain},
+    components::{
+        link_resolver::LinkResolver,
+        store::{BlockNumber, EntityType, StoredDynamicDataSource},
+    },
+    data::store::scalar::Bytes,
+    data_source,
+    prelude::{DataSourceContext, DeploymentHash, Entity, Link},
+};
+use anyhow::{anyhow, Error};
+use serde::Deserialize;
+use slog::{info, Logger};
+use std::{fmt, sync::Arc};
+
+use super::TriggerWithHandler;
+
+pub const SUBGRAPH_DS_KIND: &str = "subgraph";
+
+#[derive(Debug, Clone)]
+pub struct DataSource {
+    pub kind: String,
+    pub name: String,
+    pub manifest_idx: u32,
+    pub source: Source,
+    pub mapping: Mapping,
+    pub context: Arc<Option<DataSourceContext>>,
+}
+
+impl DataSource {
+    /// The deployment hash of the source, which is returned to mappings
+    /// from the `dataSource.address()` host function
+    pub fn address(&self) -> Option<Vec<u8>> {
+        Some(self.source.address.as_bytes().to_vec())
+    }
+
+    pub fn handler_names(&self) -> Vec<&str> {
+        self.mapping
+            .handlers
+            .iter()
+            .map(|handler| handler.handler.as_str())
+            .collect()
+    }
+
+    /// Match `trigger` to a handler. The trigger is handled as part of the
+    /// block `block_ptr` of this subgraph, which can be later than the block
+    /// in which the source deployment made the change
+    pub fn match_and_decode<C: Blockchain>(
+        &self,
+        trigger: &TriggerData,
+        block_ptr: BlockPtr,
+    ) -> Option<TriggerWithHandler<super::MappingTrigger<C>>> {
+        if self.source.address != trigger.source || trigger.block < self.source.start_block {
+            return None;
+        }
+        let handler = self
+            .mapping
+            .handlers
+            .iter()
+            .find(|handler| handler.entity == trigger.entity_type)?;
+        Some(TriggerWithHandler::new(
+            data_source::MappingTrigger::Subgraph(trigger.clone()),
+            handler.handler.clone(),
+            block_ptr,
+        ))
+    }
+
+    /// Subgraph data sources can not be created from templates, and this
+    /// is therefore never stored. It is only needed to give the data
+    /// source a uniform interface with the other kinds of data sources
+    pub fn as_stored_dynamic_data_source(&self) -> StoredDynamicDataSource {
+        StoredDynamicDataSource {
+            manifest_idx: self.manifest_idx,
+            param: Some(Bytes::from(self.source.address.as_bytes())),
+            context: self
+                .context
+                .as_ref()
+                .as_ref()
+                .map(|ctx| serde_json::to_value(ctx).unwrap()),
+            creation_block: None,
+            done_at: None,
+            causality_region: super::CausalityRegion::ONCHAIN,
+        }
+    }
+
+    pub(super) fn is_duplicate_of(&self, other: &DataSource) -> bool {
+        self.manifest_idx == other.manifest_idx
+            && self.source == other.source
+            && self.context == other.context
+    }
+
+    pub fn validate(&self) -> Vec<Error> {
+        let mut errors = vec![];
+        if self.mapping.handlers.is_empty() {
+            errors.push(anyhow!("subgraph data source has no entity handlers"));
+        }
+        for handler in &self.mapping.handlers {
+            if !self.mapping.entities.contains(&handler.entity) {
+                errors.push(anyhow!(
+                    "entity handler `{}` is for entity type `{}`, which is not listed in the \
+                     entities of the mapping",
+                    handler.handler,
+                    handler.entity
+                ));
+            }
+        }
+        errors
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Source {
+    /// The deployment whose entity changes the data source reads
+    pub address: DeploymentHash,
+    /// The first block of the source deployment whose changes are read
+    pub start_block: BlockNumber,
+}
+
+#[derive(Clone, Debug)]
+pub struct Mapping {
+    pub language: String,
+    pub api_version: semver::Version,
+    pub entities: Vec<EntityType>,
+    pub handlers: Vec<EntityHandler>,
+    pub runtime: Arc<Vec<u8>>,
+    pub link: Link,
+}
+
+/// A handler that is called with every entity of type `entity` that the
+/// source deployment creates or updates
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Deserialize)]
+pub struct EntityHandler {
+    pub handler: String,
+    pub entity: EntityType,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
+pub struct UnresolvedDataSource {
+    pub kind: String,
+    pub name: String,
+    pub source: UnresolvedSource,
+    pub mapping: UnresolvedMapping,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnresolvedSource {
+    address: DeploymentHash,
+    #[serde(default)]
+    start_block: BlockNumber,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnresolvedMapping {
+    pub api_version: String,
+    pub language: String,
+    pub file: Link,
+    pub entities: Vec<EntityType>,
+    pub handlers: Vec<EntityHandler>,
+}
+
+impl UnresolvedDataSource {
+    pub(super) async fn resolve(
+        self,
+        resolver: &Arc<dyn LinkResolver>,
+        logger: &Logger,
+        manifest_idx: u32,
+    ) -> Result<DataSource, Error> {
+        info!(logger, "Resolve subgraph data source";
+            "name" => &self.name,
+            "source" => self.source.address.as_str(),
+        );
+        let mapping = self.mapping;
+        info!(logger, "Resolve subgraph mapping"; "link" => &mapping.file.link);
+        let mapping = Mapping {
+            language: mapping.language,
+            api_version: semver::Version::parse(&mapping.api_version)?,
+            entities: mapping.entities,
+            handlers: mapping.handlers,
+            runtime: Arc::new(resolver.cat(logger, &mapping.file).await?),
+            link: mapping.file,
+        };
+        Ok(DataSource {
+            kind: self.kind,
+            name: self.name,
+            manifest_idx,
+            source: Source {
+                address: self.source.address,
+                start_block: self.source.start_block,
+            },
+            mapping,
+            context: Arc::new(None),
+        })
+    }
+}
+
+/// An entity that the source deployment created or updated in `block`
+#[derive(Clone)]
+pub struct TriggerData {
+    pub source: DeploymentHash,
+    pub block: BlockNumber,
+    pub entity_type: EntityType,
+    pub entity: Entity,
+}
+
+impl fmt::Debug for TriggerData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {}[{}] at block {}",
+            self.source,
+            self.entity_type,
+            self.entity.id().unwrap_or_default(),
+            self.block
+        )
+    }
+}
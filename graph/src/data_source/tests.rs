@@ -8,7 +8,7 @@ use crate::{
 };
 
 use super::{
-    offchain::{Mapping, Source},
+    offchain::{ContentType, Mapping, Source},
     *,
 };
 
@@ -38,9 +38,124 @@ fn offchain_duplicate() {
     });
     assert!(!a.is_duplicate_of(&c));
 
+    let mut c = a.clone();
+    c.source = Source::Arweave(
+        "ar://fvOVSPdgJB_HGutJxh8GnLZfJNLaYOvrbt_T0DQ0fEQ"
+            .parse()
+            .unwrap(),
+    );
+    assert!(!a.is_duplicate_of(&c));
+
     let mut c = a.clone();
     c.context = Arc::new(Some(Entity::new()));
     assert!(!a.is_duplicate_of(&c));
+
+    let mut c = a.clone();
+    c.content_type = ContentType::Json;
+    assert!(!a.is_duplicate_of(&c));
+}
+
+#[test]
+fn offchain_content_types() {
+    assert_eq!(ContentType::Json, "json".parse().unwrap());
+    assert_eq!(
+        ContentType::ImageMetadata,
+        "image-metadata".parse().unwrap()
+    );
+    assert!("text".parse::<ContentType>().is_err());
+
+    assert!(ContentType::Raw.check(b"not json").is_ok());
+    assert!(ContentType::Json.check(b"[1, 2]").is_ok());
+    assert!(ContentType::Json.check(b"not json").is_err());
+    assert!(ContentType::ImageMetadata
+        .check(br#"{ "name": "Punk", "image": "ipfs://QmImage" }"#)
+        .is_ok());
+    assert!(ContentType::ImageMetadata
+        .check(br#"{ "name": "Punk" }"#)
+        .is_err());
+}
+
+#[test]
+fn offchain_stored_content_type() {
+    let mut ds = new_datasource();
+    let template = offchain::DataSourceTemplate {
+        kind: "file/ipfs".into(),
+        network: None,
+        name: ds.name.clone(),
+        manifest_idx: ds.manifest_idx,
+        mapping: ds.mapping.clone(),
+    };
+
+    let stored = ds.as_stored_dynamic_data_source();
+    let restored =
+        offchain::DataSource::from_stored_dynamic_data_source(&template, stored).unwrap();
+    assert_eq!(ContentType::Raw, restored.content_type);
+
+    ds.content_type = ContentType::ImageMetadata;
+    let stored = ds.as_stored_dynamic_data_source();
+    let restored =
+        offchain::DataSource::from_stored_dynamic_data_source(&template, stored).unwrap();
+    assert_eq!(ds.source, restored.source);
+    assert_eq!(ContentType::ImageMetadata, restored.content_type);
+}
+
+#[test]
+fn offchain_source_parse() {
+    let id = "fvOVSPdgJB_HGutJxh8GnLZfJNLaYOvrbt_T0DQ0fEQ";
+    let source = Source::parse("file/arweave", &format!("ar://{}", id)).unwrap();
+    assert_eq!(Source::Arweave(id.parse().unwrap()), source);
+    // The prefix is optional
+    assert_eq!(source, Source::parse("file/arweave", id).unwrap());
+
+    assert!(Source::parse("file/arweave", "ar://tooShort").is_err());
+    assert!(Source::parse("file/arweave", &format!("{}+", &id[1..])).is_err());
+    assert!(Source::parse("file/http", id).is_err());
+    assert!(matches!(
+        Source::parse(
+            "file/ipfs",
+            "QmVkvoPGi9jvvuxsHDVJDgzPEzagBaWSZRYoRDzU244HjZ"
+        ),
+        Ok(Source::Ipfs(_))
+    ));
+}
+
+#[test]
+fn offchain_object_store_source_parse() {
+    let hash = "0x".to_string() + &"ab".repeat(32);
+    let url = format!("s3://bucket/path/to/blob.json#keccak256={}", hash);
+    let source = Source::parse("file/object-store", &url).unwrap();
+    let file = match source {
+        Source::ObjectStore(ref file) => file,
+        _ => panic!("expected an object store source"),
+    };
+    assert_eq!(offchain::ObjectStore::S3, file.store);
+    assert_eq!("bucket", file.bucket);
+    assert_eq!("path/to/blob.json", file.key);
+    assert_eq!([0xab; 32], file.hash);
+    assert_eq!(url, file.to_string());
+    // The `0x` prefix of the hash is optional
+    assert_eq!(
+        source,
+        Source::parse("file/object-store", &url.replace("=0x", "=")).unwrap()
+    );
+    assert!(matches!(
+        Source::parse(
+            "file/object-store",
+            &format!("gs://bucket/blob#keccak256={}", hash)
+        ),
+        Ok(Source::ObjectStore(_))
+    ));
+
+    for invalid in [
+        "s3://bucket/blob".to_string(),
+        format!("https://bucket/blob#keccak256={}", hash),
+        format!("s3://bucket#keccak256={}", hash),
+        format!("s3://bucket/#keccak256={}", hash),
+        "s3://bucket/blob#keccak256=0xab".to_string(),
+        format!("s3://bucket/blob#sha256={}", hash),
+    ] {
+        assert!(Source::parse("file/object-store", &invalid).is_err());
+    }
 }
 
 #[test]
@@ -74,6 +189,7 @@ fn new_datasource() -> offchain::DataSource {
             cid: Cid::default(),
             path: None,
         }),
+        ContentType::Raw,
         Mapping {
             language: String::new(),
             api_version: Version::new(0, 0, 0),
@@ -1,9 +1,10 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     sync::{
         atomic::{AtomicU64, Ordering},
-        Arc,
+        Arc, Mutex,
     },
+    time::{Duration, Instant},
 };
 
 use prometheus::IntCounterVec;
@@ -11,10 +12,107 @@ use slog::{warn, Logger};
 
 use crate::{components::metrics::MetricsRegistry, data::value::Word};
 
+/// The number of recent requests to a provider from which its error rate
+/// and latency percentiles are computed
+const RECENT_REQUESTS: usize = 1000;
+
 /// ProviderCount is the underlying structure to keep the count,
 /// we require that all the hosts are known ahead of time, this way we can
 /// avoid locking since we don't need to modify the entire struture.
-type ProviderCount = Arc<HashMap<Provider, AtomicU64>>;
+type ProviderCount = Arc<HashMap<Provider, Arc<ProviderStats>>>;
+
+#[derive(Debug, Default)]
+struct ProviderStats {
+    /// The number of errors since the last successful request
+    errors: AtomicU64,
+    /// The latency and success of the most recent requests, oldest first
+    recent: Mutex<VecDeque<(Duration, bool)>>,
+    /// Until when the provider should not be used because requests to it
+    /// kept failing
+    unhealthy_until: Mutex<Option<Instant>>,
+}
+
+impl ProviderStats {
+    fn record(&self, latency: Duration, success: bool) {
+        let mut recent = self.recent.lock().unwrap();
+        if recent.len() == RECENT_REQUESTS {
+            recent.pop_front();
+        }
+        recent.push_back((latency, success));
+    }
+
+    fn health(&self) -> ProviderHealth {
+        let (mut latencies, failures): (Vec<_>, Vec<_>) = {
+            let recent = self.recent.lock().unwrap();
+            recent.iter().copied().unzip()
+        };
+        latencies.sort();
+        let failures = failures.iter().filter(|success| !**success).count();
+        let requests = latencies.len();
+        let error_rate = if requests == 0 {
+            0.0
+        } else {
+            failures as f64 / requests as f64
+        };
+
+        ProviderHealth {
+            requests,
+            consecutive_errors: self.errors.load(Ordering::Relaxed),
+            error_rate,
+            latency_p50: percentile(&latencies, 0.5),
+            latency_p90: percentile(&latencies, 0.9),
+            latency_p99: percentile(&latencies, 0.99),
+        }
+    }
+}
+
+/// The `p`-th percentile of the sorted values in `sorted`
+fn percentile(sorted: &[Duration], p: f64) -> Option<Duration> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let idx = ((sorted.len() as f64 * p).ceil() as usize).clamp(1, sorted.len()) - 1;
+    Some(sorted[idx])
+}
+
+/// How a provider has fared in its most recent requests
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProviderHealth {
+    /// The number of recent requests that the error rate and the latencies
+    /// are computed from
+    pub requests: usize,
+    /// The number of errors since the last successful request
+    pub consecutive_errors: u64,
+    /// The fraction of recent requests that failed
+    pub error_rate: f64,
+    pub latency_p50: Option<Duration>,
+    pub latency_p90: Option<Duration>,
+    pub latency_p99: Option<Duration>,
+}
+
+/// A provider for a chain and what it can do
+#[derive(Clone, Debug)]
+pub struct ProviderInfo {
+    pub chain: String,
+    pub provider: Provider,
+    pub conn_type: ConnectionType,
+    /// Capabilities like `archive` and `traces` for RPC providers or
+    /// `filters` for Firehose providers
+    pub capabilities: Vec<String>,
+    pub health: ProviderHealth,
+}
+
+/// Lists the providers that a node currently uses
+pub trait ProviderInventory: std::fmt::Debug + Send + Sync + 'static {
+    fn providers(&self) -> Vec<ProviderInfo>;
+}
+
+/// A fixed list of providers
+impl ProviderInventory for Vec<ProviderInfo> {
+    fn providers(&self) -> Vec<ProviderInfo> {
+        self.clone()
+    }
+}
 
 /// Provider represents label of the underlying endpoint.
 pub type Provider = Word;
@@ -30,7 +128,7 @@ pub struct RequestLabels {
 }
 
 /// The type of underlying connection we are reporting for.
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum ConnectionType {
     Firehose,
     Substreams,
@@ -81,11 +179,12 @@ impl EndpointMetrics {
         providers: &[impl AsRef<str>],
         registry: Arc<MetricsRegistry>,
     ) -> Self {
-        let providers = Arc::new(HashMap::from_iter(
-            providers
-                .iter()
-                .map(|h| (Provider::from(h.as_ref()), AtomicU64::new(0))),
-        ));
+        let providers = Arc::new(HashMap::from_iter(providers.iter().map(|h| {
+            (
+                Provider::from(h.as_ref()),
+                Arc::new(ProviderStats::default()),
+            )
+        })));
 
         let counter = registry
             .new_int_counter_vec(
@@ -102,6 +201,25 @@ impl EndpointMetrics {
         }
     }
 
+    /// Metrics for `providers` that share the request counter with these
+    /// metrics. Since the set of providers can not change, this is how
+    /// providers that are added by reloading the configuration get tracked.
+    /// Providers that are already known share their error counts and
+    /// recent requests with these metrics
+    pub fn with_providers(&self, providers: &[impl AsRef<str>]) -> Self {
+        let providers = Arc::new(HashMap::from_iter(providers.iter().map(|h| {
+            let provider = Provider::from(h.as_ref());
+            let stats = self.providers.get(&provider).cloned().unwrap_or_default();
+            (provider, stats)
+        })));
+
+        Self {
+            logger: self.logger.clone(),
+            providers,
+            counter: self.counter.clone(),
+        }
+    }
+
     /// This should only be used for testing.
     pub fn mock() -> Self {
         use slog::{o, Discard};
@@ -115,24 +233,23 @@ impl EndpointMetrics {
 
     #[cfg(debug_assertions)]
     pub fn report_for_test(&self, provider: &Provider, success: bool) {
+        let labels = RequestLabels {
+            provider: provider.clone(),
+            req_type: "".into(),
+            conn_type: ConnectionType::Firehose,
+        };
         match success {
-            true => self.success(&RequestLabels {
-                provider: provider.clone(),
-                req_type: "".into(),
-                conn_type: ConnectionType::Firehose,
-            }),
-            false => self.failure(&RequestLabels {
-                provider: provider.clone(),
-                req_type: "".into(),
-                conn_type: ConnectionType::Firehose,
-            }),
+            true => self.success(&labels, Duration::from_millis(10)),
+            false => self.failure(&labels, Duration::from_millis(10)),
         }
     }
 
-    pub fn success(&self, labels: &RequestLabels) {
+    /// Record a request that succeeded after `latency`
+    pub fn success(&self, labels: &RequestLabels, latency: Duration) {
         match self.providers.get(&labels.provider) {
-            Some(count) => {
-                count.store(0, Ordering::Relaxed);
+            Some(stats) => {
+                stats.errors.store(0, Ordering::Relaxed);
+                stats.record(latency, true);
             }
             None => warn!(
                 &self.logger,
@@ -143,10 +260,12 @@ impl EndpointMetrics {
         self.counter.with_label_values(&labels.to_slice(true)).inc();
     }
 
-    pub fn failure(&self, labels: &RequestLabels) {
+    /// Record a request that failed after `latency`
+    pub fn failure(&self, labels: &RequestLabels, latency: Duration) {
         match self.providers.get(&labels.provider) {
-            Some(count) => {
-                count.fetch_add(1, Ordering::Relaxed);
+            Some(stats) => {
+                stats.errors.fetch_add(1, Ordering::Relaxed);
+                stats.record(latency, false);
             }
             None => warn!(
                 &self.logger,
@@ -164,9 +283,37 @@ impl EndpointMetrics {
     pub fn get_count(&self, provider: &Provider) -> u64 {
         self.providers
             .get(provider)
-            .map(|c| c.load(Ordering::Relaxed))
+            .map(|stats| stats.errors.load(Ordering::Relaxed))
             .unwrap_or(0)
     }
+
+    /// Stop using `provider` for `cooldown` if other providers are
+    /// available, because it keeps failing
+    pub fn mark_unhealthy(&self, provider: &Provider, cooldown: Duration) {
+        match self.providers.get(provider) {
+            Some(stats) => {
+                *stats.unhealthy_until.lock().unwrap() = Some(Instant::now() + cooldown);
+            }
+            None => warn!(&self.logger, "metrics not available for host {}", provider),
+        }
+    }
+
+    /// Whether `provider` can be used, i.e., it was not marked unhealthy
+    /// or its cooldown has passed
+    pub fn is_healthy(&self, provider: &Provider) -> bool {
+        self.providers
+            .get(provider)
+            .and_then(|stats| *stats.unhealthy_until.lock().unwrap())
+            .map_or(true, |until| until <= Instant::now())
+    }
+
+    /// How `provider` fared in its most recent requests
+    pub fn health(&self, provider: &Provider) -> ProviderHealth {
+        self.providers
+            .get(provider)
+            .map(|stats| stats.health())
+            .unwrap_or_default()
+    }
 }
 
 #[cfg(test)]
@@ -177,7 +324,7 @@ mod test {
 
     use crate::{
         components::metrics::MetricsRegistry,
-        endpoint::{EndpointMetrics, Provider},
+        endpoint::{ConnectionType, EndpointMetrics, Provider, RequestLabels},
     };
 
     #[tokio::test]
@@ -197,5 +344,66 @@ mod test {
         assert_eq!(metrics.get_count(&a), 1);
         assert_eq!(metrics.get_count(&b), 2);
         assert_eq!(metrics.get_count(&c), 0);
+
+        let d: Provider = "d".into();
+        let hosts: &[&str] = &[&b, &d];
+        let metrics = metrics.with_providers(hosts);
+        metrics.report_for_test(&d, false);
+
+        assert_eq!(metrics.get_count(&a), 0);
+        assert_eq!(metrics.get_count(&b), 2);
+        assert_eq!(metrics.get_count(&d), 1);
+    }
+
+    #[test]
+    fn reports_health() {
+        use std::time::Duration;
+
+        let a: Provider = "a".into();
+        let hosts: &[&str] = &[&a];
+        let logger = Logger::root(Discard, o!());
+        let metrics = EndpointMetrics::new(logger, hosts, Arc::new(MetricsRegistry::mock()));
+
+        let health = metrics.health(&a);
+        assert_eq!(0, health.requests);
+        assert_eq!(None, health.latency_p50);
+
+        let labels = RequestLabels {
+            provider: a.clone(),
+            req_type: "".into(),
+            conn_type: ConnectionType::Rpc,
+        };
+        for ms in 1..=100 {
+            let latency = Duration::from_millis(ms);
+            if ms % 4 == 0 {
+                metrics.failure(&labels, latency);
+            } else {
+                metrics.success(&labels, latency);
+            }
+        }
+
+        let health = metrics.health(&a);
+        assert_eq!(100, health.requests);
+        assert_eq!(1, health.consecutive_errors);
+        assert_eq!(0.25, health.error_rate);
+        assert_eq!(Some(Duration::from_millis(50)), health.latency_p50);
+        assert_eq!(Some(Duration::from_millis(90)), health.latency_p90);
+        assert_eq!(Some(Duration::from_millis(99)), health.latency_p99);
+    }
+
+    #[test]
+    fn marks_unhealthy() {
+        use std::time::Duration;
+
+        let (a, b): (Provider, Provider) = ("a".into(), "b".into());
+        let hosts: &[&str] = &[&a, &b];
+        let logger = Logger::root(Discard, o!());
+        let metrics = EndpointMetrics::new(logger, hosts, Arc::new(MetricsRegistry::mock()));
+
+        metrics.mark_unhealthy(&a, Duration::from_secs(60));
+        metrics.mark_unhealthy(&b, Duration::ZERO);
+        assert!(!metrics.is_healthy(&a));
+        assert!(metrics.is_healthy(&b));
+        assert!(metrics.is_healthy(&"unknown".into()));
     }
 }
@@ -80,9 +80,20 @@ pub struct EnvVarsGraphQl {
     /// Set by the environment variable `GRAPH_GRAPHQL_ERROR_RESULT_SIZE`. The
     /// default value is [`usize::MAX`].
     pub error_result_size: usize,
+    /// Set by the environment variable `GRAPH_GRAPHQL_MAX_RESULT_BYTES`.
+    /// When set, lists in query results are truncated so that the
+    /// serialized result stays below this many bytes. Off by default.
+    pub max_result_bytes: Option<usize>,
     /// Set by the flag `GRAPH_GRAPHQL_MAX_OPERATIONS_PER_CONNECTION`.
     /// Defaults to 1000.
     pub max_operations_per_connection: usize,
+    /// How many query shapes per node are exported as metrics, picking
+    /// the shapes that took the most time overall. A value of 0 disables
+    /// the metrics.
+    ///
+    /// Set by the environment variable `GRAPH_GRAPHQL_TOP_QUERY_SHAPES`. The
+    /// default value is 0.
+    pub top_query_shapes: usize,
     /// Set by the flag `GRAPH_GRAPHQL_DISABLE_BOOL_FILTERS`. Off by default.
     /// Disables AND/OR filters
     pub disable_bool_filters: bool,
@@ -94,6 +105,159 @@ pub struct EnvVarsGraphQl {
     /// header `X-GraphTraceQuery` set to this value will include a trace of
     /// the SQL queries that were run.
     pub query_trace_token: String,
+    /// Set by the environment variable
+    /// `GRAPH_GRAPHQL_MAX_CONCURRENT_QUERIES_PER_DEPLOYMENT`. No default
+    /// value is provided, i.e., the number of queries that can run
+    /// concurrently against one deployment is not limited.
+    pub max_concurrent_queries_per_deployment: Option<usize>,
+    /// Set by the environment variable
+    /// `GRAPH_GRAPHQL_MAX_QUERY_RATE_PER_DEPLOYMENT` (expressed in queries
+    /// per second). No default value is provided.
+    pub max_query_rate_per_deployment: Option<u32>,
+    /// Set by the environment variable
+    /// `GRAPH_GRAPHQL_MAX_QUERY_RATE_PER_API_KEY` (expressed in queries per
+    /// second). No default value is provided.
+    pub max_query_rate_per_api_key: Option<u32>,
+    /// The name of the HTTP header that carries the API key used for
+    /// per-key rate limiting.
+    ///
+    /// Set by the environment variable `GRAPH_GRAPHQL_API_KEY_HEADER`. The
+    /// default value is `X-Api-Key`.
+    pub api_key_header: String,
+    /// The origins that browsers may send GraphQL requests from. A value of
+    /// `*` allows any origin.
+    ///
+    /// Set by the environment variable `GRAPH_GRAPHQL_HTTP_CORS_ORIGINS`
+    /// (comma separated). The default value is `*`.
+    pub http_cors_origins: CorsOrigins,
+    /// Set by the environment variable `GRAPH_GRAPHQL_HTTP_COMPRESSION`
+    /// (comma separated). The default value is `none`.
+    pub http_compression: HttpCompression,
+    /// The maximum size of a request body in bytes.
+    ///
+    /// Set by the environment variable `GRAPH_GRAPHQL_HTTP_MAX_BODY_SIZE`.
+    /// The default value is [`usize::MAX`].
+    pub http_max_body_size: usize,
+    /// Set by the flag `GRAPH_GRAPHQL_HTTP_KEEP_ALIVE`. On by default.
+    pub http_keep_alive: bool,
+    /// Set by the flag `GRAPH_GRAPHQL_DISABLE_GRAPHIQL`. Off by default.
+    /// Disables the GraphiQL query UI at `/subgraphs/.../graphql`
+    pub disable_graphiql: bool,
+    /// Set by the flag `GRAPH_GRAPHQL_ENABLE_REST`. Off by default.
+    /// Enables the REST facade at `/subgraphs/.../rest/<entity>`
+    pub enable_rest: bool,
+    /// The file that the query audit log is written to.
+    ///
+    /// Set by the environment variable `GRAPH_GRAPHQL_AUDIT_LOG_FILE`. No
+    /// default value is provided.
+    pub audit_log_file: Option<String>,
+    /// The size in bytes at which the audit log file is rotated.
+    ///
+    /// Set by the environment variable
+    /// `GRAPH_GRAPHQL_AUDIT_LOG_FILE_MAX_SIZE`. The default value is 100MB.
+    pub audit_log_file_max_size: u64,
+    /// How many rotated audit log files are kept.
+    ///
+    /// Set by the environment variable `GRAPH_GRAPHQL_AUDIT_LOG_FILE_KEEP`.
+    /// The default value is 5.
+    pub audit_log_file_keep: usize,
+    /// The Kafka brokers that the query audit log is sent to.
+    ///
+    /// Set by the environment variable `GRAPH_GRAPHQL_AUDIT_LOG_KAFKA_BROKERS`
+    /// (comma separated). No default value is provided.
+    pub audit_log_kafka_brokers: Option<String>,
+    /// Set by the environment variable `GRAPH_GRAPHQL_AUDIT_LOG_KAFKA_TOPIC`.
+    /// The default value is `graph-node-queries`.
+    pub audit_log_kafka_topic: String,
+    /// The fraction of queries that are recorded in the audit log.
+    ///
+    /// Set by the environment variable `GRAPH_GRAPHQL_AUDIT_LOG_SAMPLE_RATE`.
+    /// The default value is 1.0.
+    pub audit_log_sample_rate: f64,
+    /// Queries that take at least this long are always recorded in the
+    /// audit log, regardless of sampling.
+    ///
+    /// Set by the environment variable `GRAPH_GRAPHQL_AUDIT_LOG_SLOW_QUERY_MS`
+    /// (expressed in milliseconds). No default value is provided.
+    pub audit_log_slow_query: Option<Duration>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CorsOrigins {
+    Any,
+    Only(Vec<String>),
+}
+
+impl CorsOrigins {
+    pub fn allows(&self, origin: &str) -> bool {
+        match self {
+            CorsOrigins::Any => true,
+            CorsOrigins::Only(origins) => origins.iter().any(|o| o == origin),
+        }
+    }
+}
+
+impl FromStr for CorsOrigins {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim() == "*" {
+            return Ok(CorsOrigins::Any);
+        }
+        Ok(CorsOrigins::Only(
+            s.split(',')
+                .map(str::trim)
+                .filter(|o| !o.is_empty())
+                .map(str::to_string)
+                .collect(),
+        ))
+    }
+}
+
+/// A content encoding that the GraphQL HTTP server can compress responses
+/// with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContentEncoding {
+    Gzip,
+    Brotli,
+}
+
+impl ContentEncoding {
+    /// The name of the encoding in `Accept-Encoding` and
+    /// `Content-Encoding` headers
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Brotli => "br",
+        }
+    }
+}
+
+/// How responses of the GraphQL HTTP server are compressed for clients that
+/// accept compressed responses. When a client accepts several of the
+/// encodings equally, the one that comes first is used
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HttpCompression(pub Vec<ContentEncoding>);
+
+impl FromStr for HttpCompression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "none" {
+            return Ok(HttpCompression(vec![]));
+        }
+        s.split(',')
+            .map(|encoding| match encoding.trim() {
+                "gzip" => Ok(ContentEncoding::Gzip),
+                "br" => Ok(ContentEncoding::Brotli),
+                encoding => Err(format!(
+                    "invalid HTTP compression `{}`, expected `none` or a list of `gzip` and `br`",
+                    encoding
+                )),
+            })
+            .collect::<Result<_, _>>()
+            .map(HttpCompression)
+    }
 }
 
 // This does not print any values avoid accidentally leaking any sensitive env vars
@@ -134,10 +298,29 @@ impl From<InnerGraphQl> for EnvVarsGraphQl {
             allow_deployment_change: x.allow_deployment_change.0,
             warn_result_size: x.warn_result_size.0 .0,
             error_result_size: x.error_result_size.0 .0,
+            max_result_bytes: x.max_result_bytes.map(|n| n.0),
             max_operations_per_connection: x.max_operations_per_connection,
+            top_query_shapes: x.top_query_shapes,
             disable_bool_filters: x.disable_bool_filters.0,
             disable_child_sorting: x.disable_child_sorting.0,
             query_trace_token: x.query_trace_token,
+            max_concurrent_queries_per_deployment: x.max_concurrent_queries_per_deployment,
+            max_query_rate_per_deployment: x.max_query_rate_per_deployment,
+            max_query_rate_per_api_key: x.max_query_rate_per_api_key,
+            api_key_header: x.api_key_header,
+            http_cors_origins: x.http_cors_origins,
+            http_compression: x.http_compression,
+            http_max_body_size: x.http_max_body_size.0 .0,
+            http_keep_alive: x.http_keep_alive.0,
+            disable_graphiql: x.disable_graphiql.0,
+            enable_rest: x.enable_rest.0,
+            audit_log_file: x.audit_log_file,
+            audit_log_file_max_size: x.audit_log_file_max_size.0,
+            audit_log_file_keep: x.audit_log_file_keep,
+            audit_log_kafka_brokers: x.audit_log_kafka_brokers,
+            audit_log_kafka_topic: x.audit_log_kafka_topic,
+            audit_log_sample_rate: x.audit_log_sample_rate,
+            audit_log_slow_query: x.audit_log_slow_query_in_ms.map(Duration::from_millis),
         }
     }
 }
@@ -179,12 +362,56 @@ pub struct InnerGraphQl {
     warn_result_size: WithDefaultUsize<NoUnderscores<usize>, { usize::MAX }>,
     #[envconfig(from = "GRAPH_GRAPHQL_ERROR_RESULT_SIZE", default = "")]
     error_result_size: WithDefaultUsize<NoUnderscores<usize>, { usize::MAX }>,
+    #[envconfig(from = "GRAPH_GRAPHQL_MAX_RESULT_BYTES")]
+    max_result_bytes: Option<NoUnderscores<usize>>,
     #[envconfig(from = "GRAPH_GRAPHQL_MAX_OPERATIONS_PER_CONNECTION", default = "1000")]
     max_operations_per_connection: usize,
+    #[envconfig(from = "GRAPH_GRAPHQL_TOP_QUERY_SHAPES", default = "0")]
+    top_query_shapes: usize,
     #[envconfig(from = "GRAPH_GRAPHQL_DISABLE_BOOL_FILTERS", default = "false")]
     pub disable_bool_filters: EnvVarBoolean,
     #[envconfig(from = "GRAPH_GRAPHQL_DISABLE_CHILD_SORTING", default = "false")]
     pub disable_child_sorting: EnvVarBoolean,
     #[envconfig(from = "GRAPH_GRAPHQL_TRACE_TOKEN", default = "")]
     query_trace_token: String,
+    #[envconfig(from = "GRAPH_GRAPHQL_MAX_CONCURRENT_QUERIES_PER_DEPLOYMENT")]
+    max_concurrent_queries_per_deployment: Option<usize>,
+    #[envconfig(from = "GRAPH_GRAPHQL_MAX_QUERY_RATE_PER_DEPLOYMENT")]
+    max_query_rate_per_deployment: Option<u32>,
+    #[envconfig(from = "GRAPH_GRAPHQL_MAX_QUERY_RATE_PER_API_KEY")]
+    max_query_rate_per_api_key: Option<u32>,
+    #[envconfig(from = "GRAPH_GRAPHQL_API_KEY_HEADER", default = "X-Api-Key")]
+    api_key_header: String,
+    #[envconfig(from = "GRAPH_GRAPHQL_HTTP_CORS_ORIGINS", default = "*")]
+    http_cors_origins: CorsOrigins,
+    #[envconfig(from = "GRAPH_GRAPHQL_HTTP_COMPRESSION", default = "none")]
+    http_compression: HttpCompression,
+    #[envconfig(from = "GRAPH_GRAPHQL_HTTP_MAX_BODY_SIZE", default = "")]
+    http_max_body_size: WithDefaultUsize<NoUnderscores<usize>, { usize::MAX }>,
+    #[envconfig(from = "GRAPH_GRAPHQL_HTTP_KEEP_ALIVE", default = "true")]
+    http_keep_alive: EnvVarBoolean,
+    #[envconfig(from = "GRAPH_GRAPHQL_DISABLE_GRAPHIQL", default = "false")]
+    disable_graphiql: EnvVarBoolean,
+    #[envconfig(from = "GRAPH_GRAPHQL_ENABLE_REST", default = "false")]
+    enable_rest: EnvVarBoolean,
+    #[envconfig(from = "GRAPH_GRAPHQL_AUDIT_LOG_FILE")]
+    audit_log_file: Option<String>,
+    #[envconfig(
+        from = "GRAPH_GRAPHQL_AUDIT_LOG_FILE_MAX_SIZE",
+        default = "100_000_000"
+    )]
+    audit_log_file_max_size: NoUnderscores<u64>,
+    #[envconfig(from = "GRAPH_GRAPHQL_AUDIT_LOG_FILE_KEEP", default = "5")]
+    audit_log_file_keep: usize,
+    #[envconfig(from = "GRAPH_GRAPHQL_AUDIT_LOG_KAFKA_BROKERS")]
+    audit_log_kafka_brokers: Option<String>,
+    #[envconfig(
+        from = "GRAPH_GRAPHQL_AUDIT_LOG_KAFKA_TOPIC",
+        default = "graph-node-queries"
+    )]
+    audit_log_kafka_topic: String,
+    #[envconfig(from = "GRAPH_GRAPHQL_AUDIT_LOG_SAMPLE_RATE", default = "1.0")]
+    audit_log_sample_rate: f64,
+    #[envconfig(from = "GRAPH_GRAPHQL_AUDIT_LOG_SLOW_QUERY_MS")]
+    audit_log_slow_query_in_ms: Option<u64>,
 }
@@ -1,4 +1,5 @@
 use std::fmt;
+use std::path::PathBuf;
 
 use super::*;
 
@@ -15,17 +16,42 @@ pub struct EnvVarsMapping {
     /// Set by the environment variable `GRAPH_ENTITY_CACHE_SIZE` (expressed in
     /// kilobytes). The default value is 10 megabytes.
     pub entity_cache_size: usize,
+    /// How many of the most frequently used entries of the entity cache of
+    /// a deployment are remembered when the deployment stops, so that they
+    /// can be loaded into the cache again when it starts.
+    ///
+    /// Set by the environment variable `GRAPH_ENTITY_CACHE_SNAPSHOT_SIZE`.
+    /// The default value is 0, which turns snapshots off.
+    pub entity_cache_snapshot_size: usize,
     /// Set by the environment variable `GRAPH_MAX_API_VERSION`. The default
     /// value is `0.0.7`.
     pub max_api_version: Version,
     /// Set by the environment variable `GRAPH_MAPPING_HANDLER_TIMEOUT`
     /// (expressed in seconds). No default is provided.
     pub timeout: Option<Duration>,
+    /// The longest handler timeout that a subgraph can request with
+    /// `handlerLimits` in its manifest.
+    ///
+    /// Set by the environment variable `GRAPH_MAPPING_HANDLER_TIMEOUT_CEILING`
+    /// (expressed in seconds). Defaults to `timeout`.
+    pub timeout_ceiling: Option<Duration>,
+    /// The directory in which compiled WASM modules are stored so that
+    /// they can be reused across restarts.
+    ///
+    /// Set by the environment variable `GRAPH_WASM_MODULE_CACHE_DIR`. Compiled
+    /// modules are only kept in memory if it is not set.
+    pub module_cache_dir: Option<PathBuf>,
     /// Maximum stack size for the WASM runtime.
     ///
     /// Set by the environment variable `GRAPH_RUNTIME_MAX_STACK_SIZE`
     /// (expressed in bytes). The default value is 512KiB.
     pub max_stack_size: usize,
+    /// Maximum size of the linear memory of a WASM module.
+    ///
+    /// Set by the environment variable `GRAPH_RUNTIME_MAX_MEMORY` (expressed
+    /// in bytes). Modules can use the 4GiB that WASM can address if it is
+    /// not set.
+    pub max_memory: Option<usize>,
 
     /// Set by the environment variable `GRAPH_MAX_IPFS_CACHE_FILE_SIZE`
     /// (expressed in bytes). The default value is 1MiB.
@@ -38,6 +64,19 @@ pub struct EnvVarsMapping {
     /// Set by the environment variable `GRAPH_IPFS_TIMEOUT` (expressed in
     /// seconds). The default value is 60s.
     pub ipfs_timeout: Duration,
+    /// The longest IPFS timeout that a subgraph can request with
+    /// `handlerLimits` in its manifest.
+    ///
+    /// Set by the environment variable `GRAPH_IPFS_TIMEOUT_CEILING`
+    /// (expressed in seconds). Defaults to `ipfs_timeout`.
+    pub ipfs_timeout_ceiling: Duration,
+    /// How often IPFS requests from mappings are retried after network
+    /// errors, with exponential backoff. Timeouts and error responses from
+    /// the IPFS node are not retried.
+    ///
+    /// Set by the environment variable `GRAPH_IPFS_MAX_RETRIES`. The default
+    /// value is 5.
+    pub ipfs_max_retries: usize,
     /// Sets the `ipfs.map` file size limit.
     ///
     /// Set by the environment variable `GRAPH_MAX_IPFS_MAP_FILE_SIZE_LIMIT`
@@ -48,15 +87,126 @@ pub struct EnvVarsMapping {
     /// Set by the environment variable `GRAPH_MAX_IPFS_FILE_BYTES` (expressed in
     /// bytes). Defaults to 256 MiB.
     pub max_ipfs_file_bytes: usize,
+    /// The largest `ipfs.cat` file size limit that a subgraph can request
+    /// with `handlerLimits` in its manifest.
+    ///
+    /// Set by the environment variable `GRAPH_MAX_IPFS_FILE_BYTES_CEILING`
+    /// (expressed in bytes). Defaults to `max_ipfs_file_bytes`.
+    pub max_ipfs_file_bytes_ceiling: usize,
 
     /// Limits both concurrent and per second requests to IPFS for file data sources.
     ///
     /// Set by the environment variable `GRAPH_IPFS_REQUEST_LIMIT`. Defaults to 100.
     pub ipfs_request_limit: u16,
+    /// How long an IPFS node that could not be reached is passed over when
+    /// there are other IPFS nodes to choose from.
+    ///
+    /// Set by the environment variable `GRAPH_IPFS_UNHEALTHY_BACKOFF`
+    /// (expressed in seconds). The default value is 30s.
+    pub ipfs_unhealthy_backoff: Duration,
+    /// The largest IPFS file that is kept in the cache in the primary
+    /// database, which is shared by all nodes and kept across restarts. A
+    /// value of 0 turns that cache off.
+    ///
+    /// Set by the environment variable `GRAPH_IPFS_STORE_CACHE_MAX_FILE_SIZE`
+    /// (expressed in bytes). Defaults to 0.
+    pub ipfs_store_cache_max_file_size: usize,
+    /// The URL of a service implementing the IPFS Pinning Service API that
+    /// the files of deployed subgraphs are pinned with. The access token
+    /// for it is read from `GRAPH_IPFS_PINNING_SERVICE_TOKEN` when files
+    /// are pinned.
+    ///
+    /// Set by the environment variable `GRAPH_IPFS_PINNING_SERVICE`. Off
+    /// by default.
+    pub ipfs_pinning_service: Option<String>,
 
     /// Set by the flag `GRAPH_ALLOW_NON_DETERMINISTIC_IPFS`. Off by
     /// default.
     pub allow_non_deterministic_ipfs: bool,
+
+    /// The Arweave gateways that file data sources with `ar://` sources
+    /// are fetched from, in order of preference.
+    ///
+    /// Set by the environment variable `GRAPH_ARWEAVE_GATEWAYS` as a
+    /// comma-separated list. Defaults to `https://arweave.net`.
+    pub arweave_gateways: Vec<String>,
+    /// The timeout for all Arweave requests.
+    ///
+    /// Set by the environment variable `GRAPH_ARWEAVE_TIMEOUT` (expressed in
+    /// seconds). The default value is 60s.
+    pub arweave_timeout: Duration,
+    /// Sets the size limit for Arweave files.
+    ///
+    /// Set by the environment variable `GRAPH_MAX_ARWEAVE_FILE_BYTES`
+    /// (expressed in bytes). Defaults to 256 MiB.
+    pub max_arweave_file_bytes: usize,
+    /// Limits both concurrent and per second requests to Arweave for file
+    /// data sources.
+    ///
+    /// Set by the environment variable `GRAPH_ARWEAVE_REQUEST_LIMIT`. Defaults to 100.
+    pub arweave_request_limit: u16,
+
+    /// The endpoint that file data sources with `s3://` sources are
+    /// fetched from. Objects are requested with path-style URLs, which
+    /// makes it possible to use S3-compatible stores.
+    ///
+    /// Set by the environment variable `GRAPH_OBJECT_STORE_S3_ENDPOINT`.
+    /// Defaults to `https://s3.amazonaws.com`.
+    pub object_store_s3_endpoint: String,
+    /// The region that requests to S3 are signed for.
+    ///
+    /// Set by the environment variable `GRAPH_OBJECT_STORE_S3_REGION`.
+    /// Defaults to `us-east-1`.
+    pub object_store_s3_region: String,
+    /// The endpoint that file data sources with `gs://` sources are
+    /// fetched from.
+    ///
+    /// Set by the environment variable `GRAPH_OBJECT_STORE_GCS_ENDPOINT`.
+    /// Defaults to `https://storage.googleapis.com`.
+    pub object_store_gcs_endpoint: String,
+    /// The timeout for all object store requests.
+    ///
+    /// Set by the environment variable `GRAPH_OBJECT_STORE_TIMEOUT`
+    /// (expressed in seconds). The default value is 60s.
+    pub object_store_timeout: Duration,
+    /// Sets the size limit for files in object stores.
+    ///
+    /// Set by the environment variable `GRAPH_MAX_OBJECT_STORE_FILE_BYTES`
+    /// (expressed in bytes). Defaults to 256 MiB.
+    pub max_object_store_file_bytes: usize,
+    /// Limits both concurrent and per second requests to object stores for
+    /// file data sources.
+    ///
+    /// Set by the environment variable `GRAPH_OBJECT_STORE_REQUEST_LIMIT`.
+    /// Defaults to 100.
+    pub object_store_request_limit: u16,
+
+    /// Whether mappings can use `http.get`. When it is off, subgraphs that
+    /// import `http.get` can not be deployed.
+    ///
+    /// Set by the flag `GRAPH_ALLOW_MAPPING_HTTP_GET`. Off by default.
+    pub allow_http_get: bool,
+    /// The hosts that mappings can fetch URLs that are not content-addressed
+    /// from with `http.get`.
+    ///
+    /// Set by the environment variable `GRAPH_MAPPING_HTTP_ALLOWED_HOSTS` as
+    /// a comma-separated list. Empty by default.
+    pub http_allowed_hosts: Vec<String>,
+    /// The timeout for `http.get` requests.
+    ///
+    /// Set by the environment variable `GRAPH_MAPPING_HTTP_TIMEOUT`
+    /// (expressed in seconds). The default value is 30s.
+    pub http_timeout: Duration,
+    /// Sets the `http.get` response size limit.
+    ///
+    /// Set by the environment variable `GRAPH_MAPPING_HTTP_MAX_RESPONSE_SIZE`
+    /// (expressed in bytes). The default value is 1MiB.
+    pub http_max_response_size: usize,
+    /// Whether the triggers in a block are processed concurrently when
+    /// they belong to data sources that declare disjoint sets of entities.
+    ///
+    /// Set by the flag `GRAPH_MAPPING_PARALLEL_TRIGGERS`. Off by default.
+    pub parallel_triggers: bool,
 }
 
 // This does not print any values avoid accidentally leaking any sensitive env vars
@@ -71,18 +221,63 @@ impl From<InnerMappingHandlers> for EnvVarsMapping {
         Self {
             entity_cache_dead_weight: x.entity_cache_dead_weight.0,
             entity_cache_size: x.entity_cache_size_in_kb * 1000,
+            entity_cache_snapshot_size: x.entity_cache_snapshot_size,
 
             max_api_version: x.max_api_version,
             timeout: x.mapping_handler_timeout_in_secs.map(Duration::from_secs),
+            timeout_ceiling: x
+                .mapping_handler_timeout_ceiling_in_secs
+                .or(x.mapping_handler_timeout_in_secs)
+                .map(Duration::from_secs),
+            module_cache_dir: x.module_cache_dir.map(PathBuf::from),
             max_stack_size: x.runtime_max_stack_size.0 .0,
+            max_memory: x.runtime_max_memory,
 
             max_ipfs_cache_file_size: x.max_ipfs_cache_file_size.0,
             max_ipfs_cache_size: x.max_ipfs_cache_size,
             ipfs_timeout: Duration::from_secs(x.ipfs_timeout_in_secs),
+            ipfs_timeout_ceiling: Duration::from_secs(
+                x.ipfs_timeout_ceiling_in_secs
+                    .unwrap_or(x.ipfs_timeout_in_secs),
+            ),
+            ipfs_max_retries: x.ipfs_max_retries,
             max_ipfs_map_file_size: x.max_ipfs_map_file_size.0,
             max_ipfs_file_bytes: x.max_ipfs_file_bytes.0,
+            max_ipfs_file_bytes_ceiling: x
+                .max_ipfs_file_bytes_ceiling
+                .unwrap_or(x.max_ipfs_file_bytes.0),
             ipfs_request_limit: x.ipfs_request_limit,
+            ipfs_unhealthy_backoff: Duration::from_secs(x.ipfs_unhealthy_backoff_in_secs),
+            ipfs_store_cache_max_file_size: x.ipfs_store_cache_max_file_size,
+            ipfs_pinning_service: x.ipfs_pinning_service,
             allow_non_deterministic_ipfs: x.allow_non_deterministic_ipfs.0,
+            arweave_gateways: x
+                .arweave_gateways
+                .split(',')
+                .map(str::trim)
+                .filter(|gateway| !gateway.is_empty())
+                .map(str::to_string)
+                .collect(),
+            arweave_timeout: Duration::from_secs(x.arweave_timeout_in_secs),
+            max_arweave_file_bytes: x.max_arweave_file_bytes.0,
+            arweave_request_limit: x.arweave_request_limit,
+            object_store_s3_endpoint: x.object_store_s3_endpoint,
+            object_store_s3_region: x.object_store_s3_region,
+            object_store_gcs_endpoint: x.object_store_gcs_endpoint,
+            object_store_timeout: Duration::from_secs(x.object_store_timeout_in_secs),
+            max_object_store_file_bytes: x.max_object_store_file_bytes.0,
+            object_store_request_limit: x.object_store_request_limit,
+            allow_http_get: x.allow_http_get.0,
+            http_allowed_hosts: x
+                .http_allowed_hosts
+                .split(',')
+                .map(str::trim)
+                .filter(|host| !host.is_empty())
+                .map(str::to_lowercase)
+                .collect(),
+            http_timeout: Duration::from_secs(x.http_timeout_in_secs),
+            http_max_response_size: x.http_max_response_size.0,
+            parallel_triggers: x.parallel_triggers.0,
         }
     }
 }
@@ -93,12 +288,20 @@ pub struct InnerMappingHandlers {
     entity_cache_dead_weight: EnvVarBoolean,
     #[envconfig(from = "GRAPH_ENTITY_CACHE_SIZE", default = "10000")]
     entity_cache_size_in_kb: usize,
+    #[envconfig(from = "GRAPH_ENTITY_CACHE_SNAPSHOT_SIZE", default = "0")]
+    entity_cache_snapshot_size: usize,
     #[envconfig(from = "GRAPH_MAX_API_VERSION", default = "0.0.7")]
     max_api_version: Version,
     #[envconfig(from = "GRAPH_MAPPING_HANDLER_TIMEOUT")]
     mapping_handler_timeout_in_secs: Option<u64>,
+    #[envconfig(from = "GRAPH_MAPPING_HANDLER_TIMEOUT_CEILING")]
+    mapping_handler_timeout_ceiling_in_secs: Option<u64>,
+    #[envconfig(from = "GRAPH_WASM_MODULE_CACHE_DIR")]
+    module_cache_dir: Option<String>,
     #[envconfig(from = "GRAPH_RUNTIME_MAX_STACK_SIZE", default = "")]
     runtime_max_stack_size: WithDefaultUsize<NoUnderscores<usize>, { 512 * 1024 }>,
+    #[envconfig(from = "GRAPH_RUNTIME_MAX_MEMORY")]
+    runtime_max_memory: Option<usize>,
 
     // IPFS.
     #[envconfig(from = "GRAPH_MAX_IPFS_CACHE_FILE_SIZE", default = "")]
@@ -107,12 +310,67 @@ pub struct InnerMappingHandlers {
     max_ipfs_cache_size: u64,
     #[envconfig(from = "GRAPH_IPFS_TIMEOUT", default = "60")]
     ipfs_timeout_in_secs: u64,
+    #[envconfig(from = "GRAPH_IPFS_TIMEOUT_CEILING")]
+    ipfs_timeout_ceiling_in_secs: Option<u64>,
+    #[envconfig(from = "GRAPH_IPFS_MAX_RETRIES", default = "5")]
+    ipfs_max_retries: usize,
     #[envconfig(from = "GRAPH_MAX_IPFS_MAP_FILE_SIZE", default = "")]
     max_ipfs_map_file_size: WithDefaultUsize<usize, { 256 * 1024 * 1024 }>,
     #[envconfig(from = "GRAPH_MAX_IPFS_FILE_BYTES", default = "")]
     max_ipfs_file_bytes: WithDefaultUsize<usize, { 256 * 1024 * 1024 }>,
+    #[envconfig(from = "GRAPH_MAX_IPFS_FILE_BYTES_CEILING")]
+    max_ipfs_file_bytes_ceiling: Option<usize>,
     #[envconfig(from = "GRAPH_IPFS_REQUEST_LIMIT", default = "100")]
     ipfs_request_limit: u16,
+    #[envconfig(from = "GRAPH_IPFS_UNHEALTHY_BACKOFF", default = "30")]
+    ipfs_unhealthy_backoff_in_secs: u64,
+    #[envconfig(from = "GRAPH_IPFS_STORE_CACHE_MAX_FILE_SIZE", default = "0")]
+    ipfs_store_cache_max_file_size: usize,
+    #[envconfig(from = "GRAPH_IPFS_PINNING_SERVICE")]
+    ipfs_pinning_service: Option<String>,
     #[envconfig(from = "GRAPH_ALLOW_NON_DETERMINISTIC_IPFS", default = "false")]
     allow_non_deterministic_ipfs: EnvVarBoolean,
+
+    // Arweave.
+    #[envconfig(from = "GRAPH_ARWEAVE_GATEWAYS", default = "https://arweave.net")]
+    arweave_gateways: String,
+    #[envconfig(from = "GRAPH_ARWEAVE_TIMEOUT", default = "60")]
+    arweave_timeout_in_secs: u64,
+    #[envconfig(from = "GRAPH_MAX_ARWEAVE_FILE_BYTES", default = "")]
+    max_arweave_file_bytes: WithDefaultUsize<usize, { 256 * 1024 * 1024 }>,
+    #[envconfig(from = "GRAPH_ARWEAVE_REQUEST_LIMIT", default = "100")]
+    arweave_request_limit: u16,
+
+    // Object stores.
+    #[envconfig(
+        from = "GRAPH_OBJECT_STORE_S3_ENDPOINT",
+        default = "https://s3.amazonaws.com"
+    )]
+    object_store_s3_endpoint: String,
+    #[envconfig(from = "GRAPH_OBJECT_STORE_S3_REGION", default = "us-east-1")]
+    object_store_s3_region: String,
+    #[envconfig(
+        from = "GRAPH_OBJECT_STORE_GCS_ENDPOINT",
+        default = "https://storage.googleapis.com"
+    )]
+    object_store_gcs_endpoint: String,
+    #[envconfig(from = "GRAPH_OBJECT_STORE_TIMEOUT", default = "60")]
+    object_store_timeout_in_secs: u64,
+    #[envconfig(from = "GRAPH_MAX_OBJECT_STORE_FILE_BYTES", default = "")]
+    max_object_store_file_bytes: WithDefaultUsize<usize, { 256 * 1024 * 1024 }>,
+    #[envconfig(from = "GRAPH_OBJECT_STORE_REQUEST_LIMIT", default = "100")]
+    object_store_request_limit: u16,
+
+    // HTTP.
+    #[envconfig(from = "GRAPH_ALLOW_MAPPING_HTTP_GET", default = "false")]
+    allow_http_get: EnvVarBoolean,
+    #[envconfig(from = "GRAPH_MAPPING_HTTP_ALLOWED_HOSTS", default = "")]
+    http_allowed_hosts: String,
+    #[envconfig(from = "GRAPH_MAPPING_HTTP_TIMEOUT", default = "30")]
+    http_timeout_in_secs: u64,
+    #[envconfig(from = "GRAPH_MAPPING_HTTP_MAX_RESPONSE_SIZE", default = "")]
+    http_max_response_size: WithDefaultUsize<usize, { 1024 * 1024 }>,
+
+    #[envconfig(from = "GRAPH_MAPPING_PARALLEL_TRIGGERS", default = "false")]
+    parallel_triggers: EnvVarBoolean,
 }
@@ -8,6 +8,7 @@ use semver::Version;
 use std::{collections::HashSet, env::VarError, fmt, str::FromStr, time::Duration};
 
 use self::graphql::*;
+pub use self::graphql::{ContentEncoding, CorsOrigins, HttpCompression};
 use self::mappings::*;
 use self::store::*;
 use crate::{
@@ -98,6 +99,13 @@ pub struct EnvVars {
     ///
     /// Set by the environment variable `GRAPH_MAX_GAS_PER_HANDLER`.
     pub max_gas_per_handler: u64,
+    /// The highest gas limit per handler that a subgraph can request with
+    /// `handlerLimits` in its manifest.
+    ///
+    /// Set by the environment variable `GRAPH_MAX_GAS_PER_HANDLER_CEILING`.
+    /// Defaults to `max_gas_per_handler`, i.e., subgraphs can not raise
+    /// their gas limit.
+    pub max_gas_per_handler_ceiling: u64,
     /// Set by the environment variable `GRAPH_LOG_QUERY_TIMING`.
     pub log_query_timing: HashSet<String>,
     /// A
@@ -109,6 +117,10 @@ pub struct EnvVars {
     pub log_time_format: String,
     /// Set by the flag `GRAPH_LOG_POI_EVENTS`.
     pub log_poi_events: bool,
+    /// Set by the flag `GRAPH_POI_TRANSITION`. Off by default. Deployments
+    /// that are created while this is on and that use an older version of
+    /// the proof of indexing also compute the latest version.
+    pub poi_transition: bool,
     /// Set by the environment variable `GRAPH_LOG`.
     pub log_levels: Option<String>,
     /// Set by the flag `EXPERIMENTAL_STATIC_FILTERS`. Off by default.
@@ -124,6 +136,12 @@ pub struct EnvVars {
     /// Set by the environment variable `GRAPH_POI_ACCESS_TOKEN`. No default
     /// value is provided.
     pub poi_access_token: Option<String>,
+    /// The file with the API keys for the admin JSON-RPC server. Without
+    /// it, the admin server does not require API keys.
+    ///
+    /// Set by the environment variable `GRAPH_ADMIN_API_KEYS_FILE`. No
+    /// default value is provided.
+    pub admin_api_keys_file: Option<String>,
     /// Set by the environment variable `GRAPH_SUBGRAPH_MAX_DATA_SOURCES`. Defaults to 1 billion.
     pub subgraph_max_data_sources: usize,
     /// Keep deterministic errors non-fatal even if the subgraph is pending.
@@ -172,6 +190,135 @@ pub struct EnvVars {
     /// Set by the environment variable `ETHEREUM_REORG_THRESHOLD`. The default
     /// value is 250 blocks.
     pub reorg_threshold: BlockNumber,
+    /// How many blocks, with their triggers, block streams fetch ahead of
+    /// the block that is being processed.
+    ///
+    /// Set by the environment variable `GRAPH_BLOCK_STREAM_BUFFER_SIZE`. The
+    /// default value is 100 blocks.
+    pub block_stream_buffer_size: usize,
+    /// Like `block_stream_buffer_size`, but for Firehose and Substreams
+    /// block streams.
+    ///
+    /// Set by the environment variable
+    /// `GRAPH_FIREHOSE_BLOCK_STREAM_BUFFER_SIZE`. The default value is 1
+    /// block.
+    pub firehose_block_stream_buffer_size: usize,
+    /// How many Firehose responses a block stream that several deployments
+    /// share keeps, so that deployments that are a little behind the
+    /// stream can still join it. With 0, every deployment has a block
+    /// stream of its own.
+    ///
+    /// Set by the environment variable
+    /// `GRAPH_FIREHOSE_SHARED_STREAM_BUFFER_SIZE`. The default value is 0.
+    pub firehose_shared_stream_buffer_size: usize,
+    /// How often index nodes report the load of the deployments they are
+    /// indexing for the deployment scheduler.
+    ///
+    /// Set by the environment variable
+    /// `GRAPH_DEPLOYMENT_LOAD_REPORT_INTERVAL` (expressed in seconds). The
+    /// default value is 60s.
+    pub deployment_load_report_interval: Duration,
+    /// The URLs that notifications about deployment lifecycle events are
+    /// posted to.
+    ///
+    /// Set by the environment variable `GRAPH_NOTIFICATION_WEBHOOKS` as a
+    /// comma-separated list. No notifications are sent by default.
+    pub notification_webhooks: Vec<String>,
+    /// How many blocks a deployment can fall behind the chain head before
+    /// a notification is sent.
+    ///
+    /// Set by the environment variable `GRAPH_NOTIFICATION_LAG_THRESHOLD`.
+    /// No notifications about lag are sent by default.
+    pub notification_lag_threshold: Option<BlockNumber>,
+    /// The OTLP endpoint of the OpenTelemetry collector that traces are
+    /// exported to.
+    ///
+    /// Set by the environment variable `GRAPH_OTLP_ENDPOINT`. Traces are
+    /// not exported by default.
+    pub otlp_endpoint: Option<String>,
+    /// The fraction of block processing and query traces that are
+    /// exported.
+    ///
+    /// Set by the environment variable `GRAPH_OTLP_SAMPLE_RATIO`. The
+    /// default value is 1.0.
+    pub otlp_sample_ratio: f64,
+    /// Whether logs are written as text or as JSON.
+    ///
+    /// Set by the environment variable `GRAPH_LOG_FORMAT`. The default
+    /// value is `text`.
+    pub log_format: LogFormat,
+    /// The directory to which the logs of each deployment are written, one
+    /// file per deployment.
+    ///
+    /// Set by the environment variable `GRAPH_LOG_DEPLOYMENT_DIR`. Not set
+    /// by default.
+    pub log_deployment_dir: Option<String>,
+    /// The URL to which the logs of each deployment are posted as JSON.
+    ///
+    /// Set by the environment variable `GRAPH_LOG_DEPLOYMENT_URL`. Not set
+    /// by default.
+    pub log_deployment_url: Option<String>,
+    /// How long the node waits on `SIGTERM` for running subgraphs to write
+    /// the blocks they already processed before it exits.
+    ///
+    /// Set by the environment variable `GRAPH_SHUTDOWN_TIMEOUT` (expressed
+    /// in seconds). The default value is 25s.
+    pub shutdown_timeout: Duration,
+    /// The base URL of the Substreams registry that packages with a
+    /// `registry` name in the manifest are fetched from.
+    ///
+    /// Set by the environment variable `GRAPH_SUBSTREAMS_REGISTRY_URL`. The
+    /// default value is `https://spkg.io`.
+    pub substreams_registry_url: String,
+    /// The largest number of blocks whose changes a Substreams deployment
+    /// writes in one transaction while it is far behind the chain head.
+    ///
+    /// Set by the environment variable `GRAPH_SUBSTREAMS_WRITE_BATCH_SIZE`.
+    /// The default value is 1, which writes every block by itself.
+    pub substreams_write_batch_size: usize,
+    /// How close to the chain head, in blocks, a Substreams deployment
+    /// writes every block by itself. It is never less than the reorg
+    /// threshold.
+    ///
+    /// Set by the environment variable
+    /// `GRAPH_SUBSTREAMS_WRITE_BATCH_HEAD_DISTANCE`. The default value is
+    /// 1000.
+    pub substreams_write_batch_head_distance: BlockNumber,
+    /// How many blocks a Substreams block stream that had caught up with
+    /// the chain head may fall behind it before it reconnects, switching to
+    /// another provider if there is one. A value of 0 turns off watching the
+    /// distance to the chain head.
+    ///
+    /// Set by the environment variable `GRAPH_SUBSTREAMS_MAX_HEAD_LAG`. The
+    /// default value is 0.
+    pub substreams_max_head_lag: BlockNumber,
+    /// How long a Substreams block stream may be more than
+    /// `substreams_max_head_lag` blocks behind the chain head, or not send
+    /// any blocks while the chain head moves on, before it reconnects.
+    ///
+    /// Set by the environment variable
+    /// `GRAPH_SUBSTREAMS_HEAD_LAG_TIMEOUT` (expressed in seconds). The
+    /// default value is 120 seconds.
+    pub substreams_head_lag_timeout: Duration,
+    /// The deployments, given by their namespace `sgdNNN`, whose block
+    /// streams inject synthetic reverts to test how well they handle
+    /// reorgs. This is only meant for copies of deployments on test
+    /// installations.
+    ///
+    /// Set by the environment variable `GRAPH_CHAOS_REVERT_DEPLOYMENTS` as
+    /// a comma-separated list. No reverts are injected by default.
+    pub chaos_revert_deployments: Vec<String>,
+    /// The probability with which the block stream of a deployment in
+    /// `chaos_revert_deployments` injects a revert after a block.
+    ///
+    /// Set by the environment variable `GRAPH_CHAOS_REVERT_PROBABILITY`.
+    /// The default value is 0.01.
+    pub chaos_revert_probability: f64,
+    /// How many blocks an injected revert goes back at most.
+    ///
+    /// Set by the environment variable `GRAPH_CHAOS_REVERT_MAX_DEPTH`. The
+    /// default value is 5.
+    pub chaos_revert_max_depth: usize,
 }
 
 impl EnvVars {
@@ -204,6 +351,10 @@ impl EnvVars {
                 inner.lock_contention_log_threshold_in_ms,
             ),
             max_gas_per_handler: inner.max_gas_per_handler.0 .0,
+            max_gas_per_handler_ceiling: inner
+                .max_gas_per_handler_ceiling
+                .map(|gas| gas.0)
+                .unwrap_or(inner.max_gas_per_handler.0 .0),
             log_query_timing: inner
                 .log_query_timing
                 .split(',')
@@ -211,11 +362,13 @@ impl EnvVars {
                 .collect(),
             log_time_format: inner.log_time_format,
             log_poi_events: inner.log_poi_events.0,
+            poi_transition: inner.poi_transition.0,
             log_levels: inner.log_levels,
             experimental_static_filters: inner.experimental_static_filters.0,
             subgraph_version_switching_mode: inner.subgraph_version_switching_mode,
             kill_if_unresponsive: inner.kill_if_unresponsive.0,
             poi_access_token: inner.poi_access_token,
+            admin_api_keys_file: inner.admin_api_keys_file,
             subgraph_max_data_sources: inner.subgraph_max_data_sources.0,
             disable_fail_fast: inner.disable_fail_fast.0,
             subgraph_error_retry_ceil: Duration::from_secs(inner.subgraph_error_retry_ceil_in_secs),
@@ -229,6 +382,42 @@ impl EnvVars {
             external_ws_base_url: inner.external_ws_base_url,
             static_filters_threshold: inner.static_filters_threshold,
             reorg_threshold: inner.reorg_threshold,
+            block_stream_buffer_size: inner.block_stream_buffer_size.max(1),
+            firehose_block_stream_buffer_size: inner.firehose_block_stream_buffer_size.max(1),
+            firehose_shared_stream_buffer_size: inner.firehose_shared_stream_buffer_size,
+            deployment_load_report_interval: Duration::from_secs(
+                inner.deployment_load_report_interval_in_secs,
+            ),
+            notification_webhooks: inner
+                .notification_webhooks
+                .split(',')
+                .map(str::trim)
+                .filter(|url| !url.is_empty())
+                .map(str::to_string)
+                .collect(),
+            notification_lag_threshold: inner.notification_lag_threshold,
+            otlp_endpoint: inner.otlp_endpoint,
+            otlp_sample_ratio: inner.otlp_sample_ratio,
+            log_format: inner.log_format,
+            log_deployment_dir: inner.log_deployment_dir,
+            log_deployment_url: inner.log_deployment_url,
+            shutdown_timeout: Duration::from_secs(inner.shutdown_timeout_in_secs),
+            substreams_registry_url: inner.substreams_registry_url,
+            substreams_write_batch_size: inner.substreams_write_batch_size.max(1),
+            substreams_write_batch_head_distance: inner.substreams_write_batch_head_distance,
+            substreams_max_head_lag: inner.substreams_max_head_lag.max(0),
+            substreams_head_lag_timeout: Duration::from_secs(
+                inner.substreams_head_lag_timeout_in_secs,
+            ),
+            chaos_revert_deployments: inner
+                .chaos_revert_deployments
+                .split(',')
+                .map(str::trim)
+                .filter(|namespace| !namespace.is_empty())
+                .map(str::to_string)
+                .collect(),
+            chaos_revert_probability: inner.chaos_revert_probability.clamp(0.0, 1.0),
+            chaos_revert_max_depth: inner.chaos_revert_max_depth.max(1),
         })
     }
 
@@ -301,12 +490,16 @@ struct Inner {
     #[envconfig(from = "GRAPH_MAX_GAS_PER_HANDLER", default = "1_000_000_000_000_000")]
     max_gas_per_handler:
         WithDefaultUsize<NoUnderscores<u64>, { CONST_MAX_GAS_PER_HANDLER as usize }>,
+    #[envconfig(from = "GRAPH_MAX_GAS_PER_HANDLER_CEILING")]
+    max_gas_per_handler_ceiling: Option<NoUnderscores<u64>>,
     #[envconfig(from = "GRAPH_LOG_QUERY_TIMING", default = "")]
     log_query_timing: String,
     #[envconfig(from = "GRAPH_LOG_TIME_FORMAT", default = "%b %d %H:%M:%S%.3f")]
     log_time_format: String,
     #[envconfig(from = "GRAPH_LOG_POI_EVENTS", default = "false")]
     log_poi_events: EnvVarBoolean,
+    #[envconfig(from = "GRAPH_POI_TRANSITION", default = "false")]
+    poi_transition: EnvVarBoolean,
     #[envconfig(from = "GRAPH_LOG")]
     log_levels: Option<String>,
     #[envconfig(from = "EXPERIMENTAL_STATIC_FILTERS", default = "false")]
@@ -320,6 +513,8 @@ struct Inner {
     kill_if_unresponsive: EnvVarBoolean,
     #[envconfig(from = "GRAPH_POI_ACCESS_TOKEN")]
     poi_access_token: Option<String>,
+    #[envconfig(from = "GRAPH_ADMIN_API_KEYS_FILE")]
+    admin_api_keys_file: Option<String>,
     #[envconfig(from = "GRAPH_SUBGRAPH_MAX_DATA_SOURCES", default = "1_000_000_000")]
     subgraph_max_data_sources: NoUnderscores<usize>,
     #[envconfig(from = "GRAPH_DISABLE_FAIL_FAST", default = "false")]
@@ -348,6 +543,70 @@ struct Inner {
     // JSON-RPC specific.
     #[envconfig(from = "ETHEREUM_REORG_THRESHOLD", default = "250")]
     reorg_threshold: BlockNumber,
+    #[envconfig(from = "GRAPH_BLOCK_STREAM_BUFFER_SIZE", default = "100")]
+    block_stream_buffer_size: usize,
+    #[envconfig(from = "GRAPH_FIREHOSE_BLOCK_STREAM_BUFFER_SIZE", default = "1")]
+    firehose_block_stream_buffer_size: usize,
+    #[envconfig(from = "GRAPH_FIREHOSE_SHARED_STREAM_BUFFER_SIZE", default = "0")]
+    firehose_shared_stream_buffer_size: usize,
+    #[envconfig(from = "GRAPH_DEPLOYMENT_LOAD_REPORT_INTERVAL", default = "60")]
+    deployment_load_report_interval_in_secs: u64,
+    #[envconfig(from = "GRAPH_NOTIFICATION_WEBHOOKS", default = "")]
+    notification_webhooks: String,
+    #[envconfig(from = "GRAPH_NOTIFICATION_LAG_THRESHOLD")]
+    notification_lag_threshold: Option<BlockNumber>,
+    #[envconfig(from = "GRAPH_OTLP_ENDPOINT")]
+    otlp_endpoint: Option<String>,
+    #[envconfig(from = "GRAPH_OTLP_SAMPLE_RATIO", default = "1.0")]
+    otlp_sample_ratio: f64,
+    #[envconfig(from = "GRAPH_LOG_FORMAT", default = "text")]
+    log_format: LogFormat,
+    #[envconfig(from = "GRAPH_LOG_DEPLOYMENT_DIR")]
+    log_deployment_dir: Option<String>,
+    #[envconfig(from = "GRAPH_LOG_DEPLOYMENT_URL")]
+    log_deployment_url: Option<String>,
+    #[envconfig(from = "GRAPH_SHUTDOWN_TIMEOUT", default = "25")]
+    shutdown_timeout_in_secs: u64,
+    #[envconfig(from = "GRAPH_SUBSTREAMS_REGISTRY_URL", default = "https://spkg.io")]
+    substreams_registry_url: String,
+    #[envconfig(from = "GRAPH_SUBSTREAMS_WRITE_BATCH_SIZE", default = "1")]
+    substreams_write_batch_size: usize,
+    #[envconfig(from = "GRAPH_SUBSTREAMS_WRITE_BATCH_HEAD_DISTANCE", default = "1000")]
+    substreams_write_batch_head_distance: BlockNumber,
+    #[envconfig(from = "GRAPH_SUBSTREAMS_MAX_HEAD_LAG", default = "0")]
+    substreams_max_head_lag: BlockNumber,
+    #[envconfig(from = "GRAPH_SUBSTREAMS_HEAD_LAG_TIMEOUT", default = "120")]
+    substreams_head_lag_timeout_in_secs: u64,
+    #[envconfig(from = "GRAPH_CHAOS_REVERT_DEPLOYMENTS", default = "")]
+    chaos_revert_deployments: String,
+    #[envconfig(from = "GRAPH_CHAOS_REVERT_PROBABILITY", default = "0.01")]
+    chaos_revert_probability: f64,
+    #[envconfig(from = "GRAPH_CHAOS_REVERT_MAX_DEPTH", default = "5")]
+    chaos_revert_max_depth: usize,
+}
+
+/// The format in which the process writes its logs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable lines of text
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!(
+                "invalid log format `{}`, expected `text` or `json`",
+                s
+            )),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
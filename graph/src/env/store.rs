@@ -98,6 +98,35 @@ pub struct EnvVarsStore {
     /// blocks) than its history limit. The default value is 1.2 and the
     /// value must be at least 1.01
     pub history_slack_factor: f64,
+    /// How often index nodes renew their lease in the primary. Set by
+    /// `GRAPH_NODE_LEASE_RENEWAL_INTERVAL` (expressed in seconds). The
+    /// default is 30s
+    pub node_lease_renewal_interval: Duration,
+    /// How long after its lease was last renewed an index node is
+    /// considered dead and its deployments are moved to other nodes. Set
+    /// by `GRAPH_NODE_FAILOVER_GRACE_PERIOD` (expressed in seconds). No
+    /// default value is provided, and deployments are not failed over
+    /// unless it is set
+    pub node_failover_grace_period: Option<Duration>,
+    /// Where to publish the entity changes of all deployments to as they
+    /// are committed, either a NATS server as `nats://host:port` or Kafka
+    /// brokers as `kafka://host:port,..`. Set by `GRAPH_ENTITY_SINK`.
+    /// No default value is provided, and changes are not published unless
+    /// it is set
+    pub entity_sink: Option<String>,
+    /// The Kafka topic, or the prefix of the NATS subjects, that entity
+    /// changes are published to. Set by `GRAPH_ENTITY_SINK_TOPIC`. The
+    /// default is `graph-node.entity-changes`
+    pub entity_sink_topic: String,
+    /// How many blocks worth of entity changes can wait to be published
+    /// before writes wait for them. Set by `GRAPH_ENTITY_SINK_QUEUE_SIZE`.
+    /// The default is 1000
+    pub entity_sink_queue_size: usize,
+    /// Do not run database migrations on startup because they are applied
+    /// out of band, e.g., with the SQL that `graphman database migrate
+    /// --output` writes. Startup fails if migrations are pending. Set by
+    /// `GRAPH_MIGRATIONS_EXTERNALLY_MANAGED`. The default is `false`
+    pub migrations_externally_managed: bool,
 }
 
 // This does not print any values avoid accidentally leaking any sensitive env vars
@@ -137,6 +166,14 @@ impl From<InnerStore> for EnvVarsStore {
             rebuild_threshold: x.rebuild_threshold.0,
             delete_threshold: x.delete_threshold.0,
             history_slack_factor: x.history_slack_factor.0,
+            node_lease_renewal_interval: Duration::from_secs(x.node_lease_renewal_interval_in_secs),
+            node_failover_grace_period: x
+                .node_failover_grace_period_in_secs
+                .map(Duration::from_secs),
+            entity_sink: x.entity_sink,
+            entity_sink_topic: x.entity_sink_topic,
+            entity_sink_queue_size: x.entity_sink_queue_size,
+            migrations_externally_managed: x.migrations_externally_managed.0,
         }
     }
 }
@@ -186,6 +223,21 @@ pub struct InnerStore {
     delete_threshold: ZeroToOneF64,
     #[envconfig(from = "GRAPH_STORE_HISTORY_SLACK_FACTOR", default = "1.2")]
     history_slack_factor: HistorySlackF64,
+    #[envconfig(from = "GRAPH_NODE_LEASE_RENEWAL_INTERVAL", default = "30")]
+    node_lease_renewal_interval_in_secs: u64,
+    #[envconfig(from = "GRAPH_NODE_FAILOVER_GRACE_PERIOD")]
+    node_failover_grace_period_in_secs: Option<u64>,
+    #[envconfig(from = "GRAPH_ENTITY_SINK")]
+    entity_sink: Option<String>,
+    #[envconfig(
+        from = "GRAPH_ENTITY_SINK_TOPIC",
+        default = "graph-node.entity-changes"
+    )]
+    entity_sink_topic: String,
+    #[envconfig(from = "GRAPH_ENTITY_SINK_QUEUE_SIZE", default = "1000")]
+    entity_sink_queue_size: usize,
+    #[envconfig(from = "GRAPH_MIGRATIONS_EXTERNALLY_MANAGED", default = "false")]
+    migrations_externally_managed: EnvVarBoolean,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -4,8 +4,9 @@ use crate::{
     blockchain::BlockPtr,
     cheap_clone::CheapClone,
     components::store::BlockNumber,
-    endpoint::{ConnectionType, EndpointMetrics, Provider, RequestLabels},
-    firehose::decode_firehose_block,
+    endpoint::{ConnectionType, EndpointMetrics, Provider, ProviderHealth, RequestLabels},
+    env::ENV_VARS,
+    firehose::{decode_firehose_block, SharedStreams},
     prelude::{anyhow, debug, info},
     substreams,
 };
@@ -44,6 +45,7 @@ pub struct FirehoseEndpoint {
     pub subgraph_limit: SubgraphLimit,
     endpoint_metrics: Arc<EndpointMetrics>,
     channel: Channel,
+    shared_streams: Arc<SharedStreams>,
 }
 
 #[derive(Clone, Debug, PartialEq, Ord, Eq, PartialOrd)]
@@ -171,13 +173,43 @@ impl FirehoseEndpoint {
             compression_enabled,
             subgraph_limit,
             endpoint_metrics,
+            shared_streams: Arc::new(SharedStreams::new(
+                ENV_VARS.firehose_shared_stream_buffer_size,
+            )),
         }
     }
 
+    /// Share block streams with the other endpoints that use
+    /// `shared_streams`, usually the other connections to the same
+    /// provider
+    pub fn with_shared_streams(mut self, shared_streams: Arc<SharedStreams>) -> Self {
+        self.shared_streams = shared_streams;
+        self
+    }
+
+    pub fn shared_streams(&self) -> &Arc<SharedStreams> {
+        &self.shared_streams
+    }
+
     pub fn current_error_count(&self) -> u64 {
         self.endpoint_metrics.get_count(&self.provider)
     }
 
+    pub fn health(&self) -> ProviderHealth {
+        self.endpoint_metrics.health(&self.provider)
+    }
+
+    /// Avoid the provider of this endpoint for `cooldown`, for example,
+    /// because streams from it keep failing
+    pub fn mark_unhealthy(&self, cooldown: Duration) {
+        self.endpoint_metrics
+            .mark_unhealthy(&self.provider, cooldown)
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.endpoint_metrics.is_healthy(&self.provider)
+    }
+
     // we need to -1 because there will always be a reference
     // inside FirehoseEndpoints that is not used (is always cloned).
     pub fn get_capacity(self: &Arc<Self>) -> AvailableCapacity {
@@ -423,11 +455,19 @@ impl FirehoseEndpoints {
 
     /// This function will attempt to grab an endpoint based on the Lowest error count
     //  with high capacity available. If an adapter cannot be found `endpoint` will
-    // return an error.
+    // return an error. Endpoints whose provider was marked unhealthy are only
+    // used if no healthy endpoint has capacity.
     pub fn endpoint(&self) -> anyhow::Result<Arc<FirehoseEndpoint>> {
-        let endpoint = self
-            .0
-            .iter()
+        Self::select(self.0.iter().filter(|endpoint| endpoint.is_healthy()))
+            .or_else(|| Self::select(self.0.iter()))
+            .cloned()
+            .ok_or(anyhow!("unable to get a connection, increase the firehose conn_pool_size or limit for the node"))
+    }
+
+    fn select<'a>(
+        endpoints: impl Iterator<Item = &'a Arc<FirehoseEndpoint>>,
+    ) -> Option<&'a Arc<FirehoseEndpoint>> {
+        let endpoint = endpoints
             .sorted_by_key(|x| x.current_error_count())
             .try_fold(None, |acc, adapter| {
                 match adapter.get_capacity() {
@@ -444,9 +484,7 @@ impl FirehoseEndpoints {
             });
 
         match endpoint {
-            ControlFlow::Continue(adapter)
-            | ControlFlow::Break(adapter) =>
-            adapter.cloned().ok_or(anyhow!("unable to get a connection, increase the firehose conn_pool_size or limit for the node"))
+            ControlFlow::Continue(adapter) | ControlFlow::Break(adapter) => adapter,
         }
     }
 
@@ -510,7 +548,7 @@ impl FirehoseNetworks {
 
 #[cfg(test)]
 mod test {
-    use std::{mem, sync::Arc};
+    use std::{mem, sync::Arc, time::Duration};
 
     use slog::{o, Discard, Logger};
 
@@ -679,6 +717,36 @@ mod test {
         assert_eq!(res.provider, high_error_adapter1.provider);
     }
 
+    #[tokio::test]
+    async fn firehose_endpoint_unhealthy() {
+        let logger = Logger::root(Discard, o!());
+        let endpoint_metrics = Arc::new(EndpointMetrics::new(
+            logger,
+            &["a", "b"],
+            Arc::new(MetricsRegistry::mock()),
+        ));
+        let endpoint = |provider: &str| {
+            Arc::new(FirehoseEndpoint::new(
+                provider.to_string(),
+                "http://127.0.0.1".to_string(),
+                None,
+                false,
+                false,
+                SubgraphLimit::Unlimited,
+                endpoint_metrics.clone(),
+            ))
+        };
+        let (a, b) = (endpoint("a"), endpoint("b"));
+        let endpoints = FirehoseEndpoints::from(vec![a.clone(), b.clone()]);
+
+        a.mark_unhealthy(Duration::from_secs(60));
+        assert_eq!(b.provider, endpoints.endpoint().unwrap().provider);
+
+        // With all providers unhealthy, they are still used
+        b.mark_unhealthy(Duration::from_secs(60));
+        endpoints.endpoint().unwrap();
+    }
+
     #[test]
     fn subgraph_limit_calculates_availability() {
         #[derive(Debug)]
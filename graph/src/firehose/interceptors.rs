@@ -1,5 +1,6 @@
 use std::future::Future;
 use std::pin::Pin;
+use std::time::Instant;
 use std::{fmt, sync::Arc};
 
 use tonic::{
@@ -65,11 +66,12 @@ where
 
         let fut = self.service.call(req);
         let res = async move {
+            let start = Instant::now();
             let res = fut.await;
             if res.is_ok() {
-                metrics.success(&labels);
+                metrics.success(&labels, start.elapsed());
             } else {
-                metrics.failure(&labels);
+                metrics.failure(&labels, start.elapsed());
             }
             res
         };
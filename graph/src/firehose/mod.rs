@@ -2,7 +2,9 @@ mod codec;
 mod endpoints;
 mod helpers;
 mod interceptors;
+mod shared;
 
 pub use codec::*;
 pub use endpoints::*;
 pub use helpers::decode_firehose_block;
+pub use shared::{SharedResponse, SharedStreams};
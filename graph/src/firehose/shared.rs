@@ -0,0 +1,324 @@
+//! Block streams that several deployments share. Deployments that index the
+//! same chain with the same filters and are at the same place in the chain
+//! receive the same responses from Firehose. Instead of opening one stream
+//! per deployment, the first deployment opens a stream that all others
+//! join as long as their cursor is among the last responses of that stream.
+//!
+//! The shared stream goes only as fast as its slowest subscriber. A
+//! subscriber that does not take a response within `SEND_TIMEOUT` is
+//! dropped from the shared stream and reconnects with a stream of its own.
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures03::stream::{BoxStream, StreamExt};
+use slog::{debug, Logger};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::Status;
+
+use super::{codec as firehose, FirehoseEndpoint};
+
+/// How long a shared stream waits for a subscriber to take a response
+/// before it drops the subscriber
+const SEND_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long to wait for Firehose to accept a request for a shared stream
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(120);
+
+pub type SharedResponse = Result<Arc<firehose::Response>, Status>;
+
+/// The parts of a request that have to be the same for deployments to
+/// share a stream
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct StreamKey {
+    stop_block_num: u64,
+    final_blocks_only: bool,
+    transforms: Vec<(String, Vec<u8>)>,
+}
+
+impl From<&firehose::Request> for StreamKey {
+    fn from(request: &firehose::Request) -> Self {
+        StreamKey {
+            stop_block_num: request.stop_block_num,
+            final_blocks_only: request.final_blocks_only,
+            transforms: request
+                .transforms
+                .iter()
+                .map(|t| (t.type_url.clone(), t.value.clone()))
+                .collect(),
+        }
+    }
+}
+
+struct State {
+    /// The cursor of the response right before the first response in
+    /// `history`, or the cursor the stream started at
+    base_cursor: String,
+    /// The last responses of the stream, oldest first
+    history: VecDeque<Arc<firehose::Response>>,
+    subscribers: Vec<mpsc::Sender<SharedResponse>>,
+    /// Whether the stream has ended and can not be joined anymore
+    closed: bool,
+}
+
+impl State {
+    fn new(cursor: String) -> Self {
+        State {
+            base_cursor: cursor,
+            history: VecDeque::new(),
+            subscribers: Vec::new(),
+            closed: false,
+        }
+    }
+
+    /// The number of responses in `history` that a subscriber at `cursor`
+    /// has already seen, or `None` if `cursor` is not in the history
+    fn position(&self, cursor: &str) -> Option<usize> {
+        if self.closed {
+            return None;
+        }
+        if self.base_cursor == cursor {
+            return Some(0);
+        }
+        self.history
+            .iter()
+            .position(|response| response.cursor == cursor)
+            .map(|pos| pos + 1)
+    }
+
+    fn push(&mut self, response: Arc<firehose::Response>, buffer_size: usize) {
+        self.history.push_back(response);
+        while self.history.len() > buffer_size {
+            if let Some(response) = self.history.pop_front() {
+                self.base_cursor = response.cursor.clone();
+            }
+        }
+    }
+
+    /// Add a subscriber at `cursor` and send it the responses from the
+    /// history that come after `cursor`
+    fn join(&mut self, cursor: &str, buffer_size: usize) -> Option<mpsc::Receiver<SharedResponse>> {
+        let pos = self.position(cursor)?;
+        let (sender, receiver) = mpsc::channel(buffer_size + 1);
+        for response in self.history.iter().skip(pos) {
+            // The channel has room for the whole history
+            sender.try_send(Ok(response.clone())).ok()?;
+        }
+        self.subscribers.push(sender);
+        Some(receiver)
+    }
+}
+
+struct SharedStream {
+    state: Mutex<State>,
+}
+
+/// The block streams that the deployments using one Firehose provider
+/// share. With a buffer size of 0, streams are never shared.
+pub struct SharedStreams {
+    buffer_size: usize,
+    streams: Mutex<HashMap<StreamKey, Arc<SharedStream>>>,
+}
+
+impl fmt::Debug for SharedStreams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedStreams")
+            .field("buffer_size", &self.buffer_size)
+            .finish()
+    }
+}
+
+impl SharedStreams {
+    /// Create streams that keep the last `buffer_size` responses so that
+    /// deployments that are slightly behind can still join them
+    pub fn new(buffer_size: usize) -> Self {
+        SharedStreams {
+            buffer_size,
+            streams: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Subscribe to the shared stream for `request`, starting after
+    /// `request.cursor`. If there is no shared stream for the request yet,
+    /// one is opened through `endpoint`. Returns `None` if streams are not
+    /// shared, if the request has no cursor, or if the shared stream is
+    /// not at the cursor; the caller then needs to open a stream of its
+    /// own
+    pub fn subscribe(
+        self: &Arc<Self>,
+        logger: &Logger,
+        endpoint: &Arc<FirehoseEndpoint>,
+        request: &firehose::Request,
+    ) -> Option<BoxStream<'static, SharedResponse>> {
+        if self.buffer_size == 0 || request.cursor.is_empty() {
+            return None;
+        }
+
+        let key = StreamKey::from(request);
+        let mut streams = self.streams.lock().unwrap();
+        if let Some(stream) = streams.get(&key) {
+            let mut state = stream.state.lock().unwrap();
+            if !state.closed {
+                return state
+                    .join(&request.cursor, self.buffer_size)
+                    .map(|receiver| ReceiverStream::new(receiver).boxed());
+            }
+        }
+
+        let mut state = State::new(request.cursor.clone());
+        let receiver = state.join(&request.cursor, self.buffer_size)?;
+        let stream = Arc::new(SharedStream {
+            state: Mutex::new(state),
+        });
+        streams.insert(key.clone(), stream.clone());
+        debug!(logger, "Opening shared block stream"; "cursor" => &request.cursor);
+
+        crate::spawn(self.clone().pump(
+            logger.clone(),
+            key,
+            stream,
+            endpoint.clone(),
+            request.clone(),
+        ));
+        Some(ReceiverStream::new(receiver).boxed())
+    }
+
+    /// Whether a deployment at `cursor` could join the shared stream for
+    /// `request` right now
+    pub fn can_join(&self, request: &firehose::Request, cursor: &str) -> bool {
+        if self.buffer_size == 0 || cursor.is_empty() {
+            return false;
+        }
+
+        let streams = self.streams.lock().unwrap();
+        streams
+            .get(&StreamKey::from(request))
+            .map(|stream| stream.state.lock().unwrap().position(cursor).is_some())
+            .unwrap_or(false)
+    }
+
+    /// Read responses from Firehose and send them to all subscribers until
+    /// the stream fails or there are no subscribers left
+    async fn pump(
+        self: Arc<Self>,
+        logger: Logger,
+        key: StreamKey,
+        stream: Arc<SharedStream>,
+        endpoint: Arc<FirehoseEndpoint>,
+        request: firehose::Request,
+    ) {
+        let connect = endpoint.stream_blocks(request);
+        let mut responses = match tokio::time::timeout(CONNECT_TIMEOUT, connect).await {
+            Ok(Ok(responses)) => responses,
+            Ok(Err(e)) => {
+                let status = Status::unavailable(format!("{:#}", e));
+                self.close(&key, &stream, Some(status));
+                return;
+            }
+            Err(_) => {
+                let status = Status::deadline_exceeded("connecting to Firehose timed out");
+                self.close(&key, &stream, Some(status));
+                return;
+            }
+        };
+
+        while let Some(response) = responses.next().await {
+            let response = match response {
+                Ok(response) => Arc::new(response),
+                Err(status) => {
+                    debug!(logger, "Shared block stream failed"; "error" => status.to_string());
+                    self.close(&key, &stream, Some(status));
+                    return;
+                }
+            };
+            let subscribers = {
+                let mut state = stream.state.lock().unwrap();
+                state.push(response.clone(), self.buffer_size);
+                state.subscribers.clone()
+            };
+
+            let mut dropped = Vec::new();
+            for sender in subscribers {
+                let send = sender.send(Ok(response.clone()));
+                if !matches!(tokio::time::timeout(SEND_TIMEOUT, send).await, Ok(Ok(()))) {
+                    dropped.push(sender);
+                }
+            }
+
+            let unsubscribed = {
+                let mut state = stream.state.lock().unwrap();
+                state
+                    .subscribers
+                    .retain(|sender| !dropped.iter().any(|d| d.same_channel(sender)));
+                state.subscribers.is_empty()
+            };
+            if unsubscribed {
+                debug!(logger, "Closing shared block stream without subscribers");
+                self.close(&key, &stream, None);
+                return;
+            }
+        }
+        self.close(&key, &stream, None);
+    }
+
+    /// Remove `stream` so that nobody can join it anymore, and send
+    /// `error` to its subscribers
+    fn close(&self, key: &StreamKey, stream: &Arc<SharedStream>, error: Option<Status>) {
+        {
+            let mut streams = self.streams.lock().unwrap();
+            if streams
+                .get(key)
+                .map_or(false, |current| Arc::ptr_eq(current, stream))
+            {
+                streams.remove(key);
+            }
+        }
+        let subscribers = {
+            let mut state = stream.state.lock().unwrap();
+            state.closed = true;
+            std::mem::take(&mut state.subscribers)
+        };
+        if let Some(error) = error {
+            for sender in subscribers {
+                let status = Status::new(error.code(), error.message());
+                let _ = sender.try_send(Err(status));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(cursor: &str) -> Arc<firehose::Response> {
+        Arc::new(firehose::Response {
+            cursor: cursor.to_string(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn join_from_history() {
+        let mut state = State::new("c0".to_string());
+        for cursor in ["c1", "c2", "c3"] {
+            state.push(response(cursor), 2);
+        }
+
+        assert_eq!(None, state.position("c0"));
+        assert_eq!(Some(0), state.position("c1"));
+        assert_eq!(Some(2), state.position("c3"));
+        assert!(state.join("c0", 2).is_none());
+
+        let mut receiver = state.join("c1", 2).unwrap();
+        let cursors: Vec<_> = std::iter::from_fn(|| receiver.try_recv().ok())
+            .map(|response| response.unwrap().cursor.clone())
+            .collect();
+        assert_eq!(vec!["c2", "c3"], cursors);
+        assert_eq!(1, state.subscribers.len());
+
+        state.closed = true;
+        assert_eq!(None, state.position("c3"));
+    }
+}
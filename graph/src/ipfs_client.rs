@@ -9,7 +9,8 @@ use http::Uri;
 use reqwest::multipart;
 use serde::Deserialize;
 use std::fmt::Display;
-use std::time::Duration;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use std::{str::FromStr, sync::Arc};
 
 /// Represents a file on Ipfs. This file can be the CID or a path within a folder CID.
@@ -106,11 +107,16 @@ pub struct AddResponse {
     pub size: String,
 }
 
-/// Reference type, clones will share the connection pool.
+/// Reference type, clones will share the connection pool and the health
+/// of the IPFS node.
 #[derive(Clone)]
 pub struct IpfsClient {
     base: Arc<Uri>,
     client: Arc<reqwest::Client>,
+    /// When a request to the node last failed because the node could not
+    /// be reached or had an internal error, or `None` if the last request
+    /// succeeded
+    unhealthy_since: Arc<Mutex<Option<Instant>>>,
 }
 
 impl CheapClone for IpfsClient {
@@ -118,6 +124,7 @@ impl CheapClone for IpfsClient {
         IpfsClient {
             base: self.base.cheap_clone(),
             client: self.client.cheap_clone(),
+            unhealthy_since: self.unhealthy_since.cheap_clone(),
         }
     }
 }
@@ -127,6 +134,7 @@ impl IpfsClient {
         Ok(IpfsClient {
             client: Arc::new(reqwest::Client::new()),
             base: Arc::new(Uri::from_str(base)?),
+            unhealthy_since: Arc::new(Mutex::new(None)),
         })
     }
 
@@ -134,6 +142,42 @@ impl IpfsClient {
         IpfsClient {
             client: Arc::new(reqwest::Client::new()),
             base: Arc::new(Uri::from_str("http://localhost:5001").unwrap()),
+            unhealthy_since: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Whether requests should be sent to this node. A node that failed
+    /// becomes healthy again once `backoff` has passed, so that the next
+    /// request finds out whether it has recovered
+    pub fn is_healthy(&self, backoff: Duration) -> bool {
+        match *self.unhealthy_since.lock().unwrap() {
+            None => true,
+            Some(since) => since.elapsed() >= backoff,
+        }
+    }
+
+    /// The clients that are healthy, or all of them if none are, so that
+    /// requests are still attempted when every node is down
+    pub fn healthy(clients: &[Arc<IpfsClient>], backoff: Duration) -> Vec<Arc<IpfsClient>> {
+        let healthy: Vec<_> = clients
+            .iter()
+            .filter(|client| client.is_healthy(backoff))
+            .cloned()
+            .collect();
+        if healthy.is_empty() {
+            clients.to_vec()
+        } else {
+            healthy
+        }
+    }
+
+    fn record_health<T>(&self, res: &Result<T, reqwest::Error>) {
+        let mut unhealthy_since = self.unhealthy_since.lock().unwrap();
+        match res {
+            Ok(_) => *unhealthy_since = None,
+            Err(e) if is_unhealthy(e) => *unhealthy_since = Some(Instant::now()),
+            // The node answered, e.g., that it does not have the file
+            Err(_) => {}
         }
     }
 
@@ -223,21 +267,41 @@ impl IpfsClient {
             req = req.timeout(timeout)
         }
 
-        req.send()
+        let res = req
+            .send()
             .await
             .map(|res| res.error_for_status())
-            .and_then(|x| x)
+            .and_then(|x| x);
+        self.record_health(&res);
+        res
     }
 }
 
+pub const CLOUDFLARE_TIMEOUT: u16 = 524;
+pub const GATEWAY_TIMEOUT: u16 = 504;
+
+/// Whether `e` means that the node could not be reached or is broken.
+/// Gateways respond with a timeout when they do not find a file, which
+/// says nothing about their health
+fn is_unhealthy(e: &reqwest::Error) -> bool {
+    e.is_connect()
+        || e.status().map_or(false, |status| {
+            status.is_server_error()
+                && status.as_u16() != GATEWAY_TIMEOUT
+                && status.as_u16() != CLOUDFLARE_TIMEOUT
+        })
+}
+
 #[cfg(test)]
 mod test {
     use std::str::FromStr;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
 
     use anyhow::anyhow;
     use cid::Cid;
 
-    use crate::ipfs_client::CidFile;
+    use crate::ipfs_client::{CidFile, IpfsClient};
 
     #[test]
     fn test_cid_parsing() {
@@ -321,4 +385,33 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn passes_over_unhealthy_clients() {
+        let backoff = Duration::from_secs(30);
+        let healthy = Arc::new(IpfsClient::new("http://healthy:5001").unwrap());
+        let down = Arc::new(IpfsClient::new("http://down:5001").unwrap());
+        let recovered = Arc::new(IpfsClient::new("http://recovered:5001").unwrap());
+        *down.unhealthy_since.lock().unwrap() = Some(Instant::now());
+        *recovered.unhealthy_since.lock().unwrap() = Some(Instant::now() - backoff);
+
+        let bases = |clients: Vec<Arc<IpfsClient>>| -> Vec<String> {
+            clients
+                .iter()
+                .map(|client| client.base.to_string())
+                .collect()
+        };
+        assert_eq!(
+            vec!["http://healthy:5001/", "http://recovered:5001/"],
+            bases(IpfsClient::healthy(
+                &[healthy, down.clone(), recovered],
+                backoff
+            ))
+        );
+        // If all clients are down, requests go to all of them
+        assert_eq!(
+            vec!["http://down:5001/"],
+            bases(IpfsClient::healthy(&[down], backoff))
+        );
+    }
 }
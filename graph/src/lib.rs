@@ -117,6 +117,7 @@ pub mod prelude {
         stopwatch::StopwatchMetrics, subgraph::*, Collector, Counter, CounterVec, Gauge, GaugeVec,
         Histogram, HistogramOpts, HistogramVec, MetricsRegistry, Opts, PrometheusError, Registry,
     };
+    pub use crate::components::server::admin::ConfigReloader;
     pub use crate::components::server::index_node::IndexNodeServer;
     pub use crate::components::server::query::GraphQLServer;
     pub use crate::components::server::subscription::SubscriptionServer;
@@ -157,8 +158,8 @@ pub mod prelude {
     pub use crate::data::subgraph::{
         CreateSubgraphResult, DataSourceContext, DeploymentHash, DeploymentState, Link,
         SubgraphAssignmentProviderError, SubgraphManifest, SubgraphManifestResolveError,
-        SubgraphManifestValidationError, SubgraphName, SubgraphRegistrarError,
-        UnvalidatedSubgraphManifest,
+        SubgraphManifestValidationError, SubgraphName, SubgraphRegistrarError, SubgraphValidation,
+        UnvalidatedSubgraphManifest, ValidationStage,
     };
     pub use crate::data::subscription::{
         QueryResultStream, Subscription, SubscriptionError, SubscriptionResult,
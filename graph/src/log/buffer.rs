@@ -0,0 +1,135 @@
+use std::fmt;
+use std::result::Result as StdResult;
+use std::sync::{Arc, Mutex};
+
+use slog::*;
+
+/// A log message whose key-value pairs have been formatted
+struct BufferedRecord {
+    location: RecordLocation,
+    level: Level,
+    tag: String,
+    msg: String,
+    kvs: BufferedKV,
+}
+
+/// The key-value pairs of a log message and of the loggers it was logged
+/// through, formatted as strings
+struct BufferedKV(Vec<(Key, String)>);
+
+impl Serializer for BufferedKV {
+    fn emit_arguments(&mut self, key: Key, val: &fmt::Arguments) -> Result {
+        self.0.push((key, val.to_string()));
+        Ok(())
+    }
+}
+
+impl KV for BufferedKV {
+    fn serialize(&self, _record: &Record, serializer: &mut dyn Serializer) -> Result {
+        for (key, value) in &self.0 {
+            serializer.emit_str(key, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// An slog `Drain` that keeps all log messages in memory
+struct BufferDrain {
+    records: Arc<Mutex<Vec<BufferedRecord>>>,
+}
+
+impl Drain for BufferDrain {
+    type Ok = ();
+    type Err = Never;
+
+    fn log(&self, record: &Record, values: &OwnedKVList) -> StdResult<Self::Ok, Self::Err> {
+        let mut kvs = BufferedKV(Vec::new());
+        // Serializing into a `BufferedKV` never fails
+        record.kv().serialize(record, &mut kvs).ok();
+        values.serialize(record, &mut kvs).ok();
+
+        self.records.lock().unwrap().push(BufferedRecord {
+            location: *record.location(),
+            level: record.level(),
+            tag: record.tag().to_string(),
+            msg: record.msg().to_string(),
+            kvs,
+        });
+        Ok(())
+    }
+}
+
+/// Holds on to the messages logged through its `logger` until they are
+/// either replayed to another logger or discarded. That makes it possible
+/// to only log what happened during work whose results might get thrown
+/// away once it is known that they are kept
+pub struct LogBuffer {
+    records: Arc<Mutex<Vec<BufferedRecord>>>,
+}
+
+impl LogBuffer {
+    pub fn new() -> Self {
+        LogBuffer {
+            records: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// A logger whose messages are kept in this buffer
+    pub fn logger(&self) -> Logger {
+        Logger::root(
+            BufferDrain {
+                records: self.records.clone(),
+            },
+            o!(),
+        )
+    }
+
+    /// Log all buffered messages to `logger` in the order in which they
+    /// were logged
+    pub fn replay(self, logger: &Logger) {
+        let records = std::mem::take(&mut *self.records.lock().unwrap());
+        for record in records {
+            let rs = RecordStatic {
+                location: &record.location,
+                tag: &record.tag,
+                level: record.level,
+            };
+            logger.log(&Record::new(
+                &rs,
+                &format_args!("{}", record.msg),
+                BorrowedKV(&record.kvs),
+            ));
+        }
+    }
+}
+
+impl Default for LogBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[test]
+fn replays_buffered_messages() {
+    let buffer = LogBuffer::new();
+    let logger = buffer.logger().new(o!("component" => "Test"));
+    info!(logger, "first"; "count" => 1);
+    debug!(logger, "second");
+
+    let target = LogBuffer::new();
+    buffer.replay(&target.logger());
+
+    let records = target.records.lock().unwrap();
+    assert_eq!(2, records.len());
+    assert_eq!("first", records[0].msg);
+    assert_eq!(Level::Info, records[0].level);
+    assert_eq!(
+        vec![
+            ("count", "1".to_string()),
+            ("component", "Test".to_string())
+        ],
+        records[0].kvs.0
+    );
+    assert_eq!("second", records[1].msg);
+    assert_eq!(Level::Debug, records[1].level);
+}
@@ -6,6 +6,7 @@ use slog::*;
 use crate::components::metrics::MetricsRegistry;
 use crate::components::store::DeploymentLocator;
 use crate::log::elastic::*;
+use crate::log::json::deployment_logger;
 use crate::log::split::*;
 use crate::prelude::ENV_VARS;
 
@@ -88,12 +89,21 @@ impl LoggerFactory {
         }
     }
 
-    /// Creates a subgraph logger with Elasticsearch support.
+    /// Creates a subgraph logger with Elasticsearch support that also
+    /// sends its logs to the per-deployment log streams if they are set up.
     pub fn subgraph_logger(&self, loc: &DeploymentLocator) -> Logger {
         let term_logger = self
             .parent
             .new(o!("subgraph_id" => loc.hash.to_string(), "sgd" => loc.id.to_string()));
 
+        let logger = self.elastic_subgraph_logger(loc, term_logger.clone());
+        match deployment_logger(&loc.hash, &term_logger) {
+            Some(deployment_logger) => split_logger(logger, deployment_logger),
+            None => logger,
+        }
+    }
+
+    fn elastic_subgraph_logger(&self, loc: &DeploymentLocator, term_logger: Logger) -> Logger {
         self.elastic_config
             .clone()
             .map(|elastic_config| {
@@ -0,0 +1,394 @@
+//! Structured logging: log records are written as JSON objects, one per
+//! line, and the logs of each deployment can additionally be sent to a file
+//! of their own or to an HTTP endpoint.
+//!
+//! Every JSON log record has the fields `timestamp`, `level` and `msg`, and
+//! all key/value pairs of the record and its loggers. The subgraph id and
+//! block number that the subgraph loggers attach are reported as
+//! `deployment` and `block`, and the component hierarchy as `component`
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::prelude::{SecondsFormat, Utc};
+use futures03::TryFutureExt;
+use http::header::CONTENT_TYPE;
+use serde_json::{Map, Number, Value};
+use slog::*;
+
+use crate::data::subgraph::DeploymentHash;
+use crate::log::split::split_logger;
+use crate::prelude::ENV_VARS;
+use crate::util::futures::retry;
+
+/// How often logs are posted to `GRAPH_LOG_DEPLOYMENT_URL`
+const HTTP_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often posting a batch of logs is retried
+const HTTP_MAX_RETRIES: usize = 5;
+
+/// Collects the key/value pairs of a log record into a JSON object. Pairs
+/// that were already seen are not overwritten so that the values of the
+/// record take precedence over those of its loggers, and the values of a
+/// logger over those of its parents
+struct JsonSerializer {
+    fields: Map<String, Value>,
+    components: Vec<String>,
+}
+
+impl JsonSerializer {
+    fn new() -> Self {
+        JsonSerializer {
+            fields: Map::new(),
+            components: vec![],
+        }
+    }
+
+    fn emit(&mut self, key: Key, value: Value) -> slog::Result {
+        let key = match key {
+            "component" => {
+                if let Value::String(component) = value {
+                    self.components.push(component);
+                }
+                return Ok(());
+            }
+            "subgraph_id" => "deployment",
+            "block_number" => "block",
+            key => key,
+        };
+        // Block numbers are sometimes logged as strings
+        let value = match (key, value) {
+            ("block", Value::String(s)) => s
+                .parse::<i64>()
+                .map(Value::from)
+                .unwrap_or(Value::String(s)),
+            (_, value) => value,
+        };
+        self.fields.entry(key.to_string()).or_insert(value);
+        Ok(())
+    }
+
+    fn finish(mut self, record: &Record) -> Map<String, Value> {
+        let mut fields = Map::new();
+        fields.insert(
+            "timestamp".to_string(),
+            Value::String(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)),
+        );
+        fields.insert(
+            "level".to_string(),
+            Value::String(level_name(record.level()).to_string()),
+        );
+        fields.insert("msg".to_string(), Value::String(record.msg().to_string()));
+        if !self.components.is_empty() {
+            // The components were collected from the innermost logger
+            // outwards; list the parent components first
+            self.components.reverse();
+            fields.insert(
+                "component".to_string(),
+                Value::String(self.components.join(" > ")),
+            );
+        }
+        for (key, value) in self.fields {
+            fields.entry(key).or_insert(value);
+        }
+        fields
+    }
+}
+
+impl Serializer for JsonSerializer {
+    fn emit_none(&mut self, key: Key) -> slog::Result {
+        self.emit(key, Value::Null)
+    }
+
+    fn emit_unit(&mut self, key: Key) -> slog::Result {
+        self.emit(key, Value::Null)
+    }
+
+    fn emit_bool(&mut self, key: Key, val: bool) -> slog::Result {
+        self.emit(key, Value::Bool(val))
+    }
+
+    fn emit_usize(&mut self, key: Key, val: usize) -> slog::Result {
+        self.emit(key, Value::from(val))
+    }
+
+    fn emit_isize(&mut self, key: Key, val: isize) -> slog::Result {
+        self.emit(key, Value::from(val))
+    }
+
+    fn emit_u8(&mut self, key: Key, val: u8) -> slog::Result {
+        self.emit(key, Value::from(val))
+    }
+
+    fn emit_i8(&mut self, key: Key, val: i8) -> slog::Result {
+        self.emit(key, Value::from(val))
+    }
+
+    fn emit_u16(&mut self, key: Key, val: u16) -> slog::Result {
+        self.emit(key, Value::from(val))
+    }
+
+    fn emit_i16(&mut self, key: Key, val: i16) -> slog::Result {
+        self.emit(key, Value::from(val))
+    }
+
+    fn emit_u32(&mut self, key: Key, val: u32) -> slog::Result {
+        self.emit(key, Value::from(val))
+    }
+
+    fn emit_i32(&mut self, key: Key, val: i32) -> slog::Result {
+        self.emit(key, Value::from(val))
+    }
+
+    fn emit_u64(&mut self, key: Key, val: u64) -> slog::Result {
+        self.emit(key, Value::from(val))
+    }
+
+    fn emit_i64(&mut self, key: Key, val: i64) -> slog::Result {
+        self.emit(key, Value::from(val))
+    }
+
+    fn emit_f32(&mut self, key: Key, val: f32) -> slog::Result {
+        self.emit_f64(key, val as f64)
+    }
+
+    fn emit_f64(&mut self, key: Key, val: f64) -> slog::Result {
+        let value = Number::from_f64(val)
+            .map(Value::Number)
+            .unwrap_or_else(|| Value::String(val.to_string()));
+        self.emit(key, value)
+    }
+
+    fn emit_str(&mut self, key: Key, val: &str) -> slog::Result {
+        self.emit(key, Value::String(val.to_string()))
+    }
+
+    fn emit_arguments(&mut self, key: Key, val: &fmt::Arguments) -> slog::Result {
+        self.emit(key, Value::String(val.to_string()))
+    }
+}
+
+fn level_name(level: Level) -> &'static str {
+    match level {
+        Level::Critical => "critical",
+        Level::Error => "error",
+        Level::Warning => "warning",
+        Level::Info => "info",
+        Level::Debug => "debug",
+        Level::Trace => "trace",
+    }
+}
+
+/// Turn a log record and the values of its loggers into a JSON object
+pub fn record_to_json(record: &Record, values: &OwnedKVList) -> Result<Value> {
+    let mut serializer = JsonSerializer::new();
+    record.kv().serialize(record, &mut serializer)?;
+    values.serialize(record, &mut serializer)?;
+    Ok(Value::Object(serializer.finish(record)))
+}
+
+/// An slog `Drain` that writes each log record as a line of JSON
+pub struct JsonDrain<W: Write> {
+    out: Mutex<W>,
+}
+
+impl<W: Write> JsonDrain<W> {
+    pub fn new(out: W) -> Self {
+        JsonDrain {
+            out: Mutex::new(out),
+        }
+    }
+}
+
+impl JsonDrain<File> {
+    /// A drain that appends to the file at `path`, creating it if needed
+    pub fn append(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self::new(file))
+    }
+}
+
+impl<W: Write> Drain for JsonDrain<W> {
+    type Ok = ();
+    type Err = io::Error;
+
+    fn log(&self, record: &Record, values: &OwnedKVList) -> io::Result<()> {
+        let log = record_to_json(record, values)?;
+        let mut out = self.out.lock().unwrap();
+        serde_json::to_writer(&mut *out, &log)?;
+        writeln!(out)?;
+        out.flush()
+    }
+}
+
+/// An slog `Drain` that collects log records as JSON and periodically
+/// posts them to an HTTP endpoint as newline-delimited JSON. The flushing
+/// stops once the drain is dropped
+pub struct HttpDrain {
+    logs: Arc<Mutex<Vec<Value>>>,
+}
+
+impl HttpDrain {
+    pub fn new(url: reqwest::Url, error_logger: Logger) -> Self {
+        let logs = Arc::new(Mutex::new(vec![]));
+        let weak_logs = Arc::downgrade(&logs);
+        let client = reqwest::Client::new();
+        let mut interval = tokio::time::interval(HTTP_FLUSH_INTERVAL);
+
+        crate::task_spawn::spawn(async move {
+            loop {
+                interval.tick().await;
+
+                let logs_to_send = match weak_logs.upgrade() {
+                    Some(logs) => std::mem::take(&mut *logs.lock().unwrap()),
+                    None => return,
+                };
+                if logs_to_send.is_empty() {
+                    continue;
+                }
+
+                let body = logs_to_send.iter().fold(String::new(), |mut body, log| {
+                    body.push_str(&log.to_string());
+                    body.push('\n');
+                    body
+                });
+                let request = client
+                    .post(url.clone())
+                    .header(CONTENT_TYPE, "application/x-ndjson");
+                retry("send deployment logs", &error_logger)
+                    .limit(HTTP_MAX_RETRIES)
+                    .timeout_secs(30)
+                    .run(move || {
+                        request
+                            .try_clone()
+                            .unwrap() // Unwrap: Request body not yet set
+                            .body(body.clone())
+                            .send()
+                            .and_then(|response| async { response.error_for_status() })
+                            .map_ok(|_| ())
+                    })
+                    .await
+                    .unwrap_or_else(|e| {
+                        error!(error_logger, "Failed to send deployment logs";
+                               "url" => url.as_str(), "error" => e.to_string());
+                    })
+            }
+        });
+
+        HttpDrain { logs }
+    }
+}
+
+impl Drain for HttpDrain {
+    type Ok = ();
+    type Err = slog::Error;
+
+    fn log(&self, record: &Record, values: &OwnedKVList) -> Result<()> {
+        if record.level() == Level::Trace {
+            return Ok(());
+        }
+        let log = record_to_json(record, values)?;
+        self.logs.lock().unwrap().push(log);
+        Ok(())
+    }
+}
+
+fn async_logger<D>(drain: D, deployment: &DeploymentHash) -> Logger
+where
+    D: Drain + Send + 'static,
+    D::Err: fmt::Debug,
+{
+    let drain = slog_async::Async::new(drain.fuse())
+        .chan_size(20000)
+        .build()
+        .fuse();
+    Logger::root(drain, o!("subgraph_id" => deployment.to_string()))
+}
+
+/// A logger that writes the logs of `deployment` to the file for it in
+/// `GRAPH_LOG_DEPLOYMENT_DIR` and posts them to `GRAPH_LOG_DEPLOYMENT_URL`,
+/// or `None` if neither is set. Problems with setting up either are
+/// logged to `error_logger`
+pub fn deployment_logger(deployment: &DeploymentHash, error_logger: &Logger) -> Option<Logger> {
+    let file_logger = ENV_VARS.log_deployment_dir.as_ref().and_then(|dir| {
+        let path = Path::new(dir).join(format!("{}.log", deployment));
+        match JsonDrain::append(&path) {
+            Ok(drain) => Some(async_logger(drain, deployment)),
+            Err(e) => {
+                error!(error_logger, "Failed to open deployment log file";
+                       "path" => path.display().to_string(), "error" => e.to_string());
+                None
+            }
+        }
+    });
+
+    let http_logger =
+        ENV_VARS
+            .log_deployment_url
+            .as_ref()
+            .and_then(|url| match reqwest::Url::parse(url) {
+                Ok(url) => Some(async_logger(
+                    HttpDrain::new(url, error_logger.clone()),
+                    deployment,
+                )),
+                Err(e) => {
+                    error!(error_logger, "Invalid GRAPH_LOG_DEPLOYMENT_URL";
+                       "url" => url, "error" => e.to_string());
+                    None
+                }
+            });
+
+    match (file_logger, http_logger) {
+        (Some(file_logger), Some(http_logger)) => Some(split_logger(file_logger, http_logger)),
+        (file_logger, http_logger) => file_logger.or(http_logger),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::Value;
+    use slog::{info, o, Logger};
+
+    use super::JsonDrain;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn json_fields() {
+        let buffer = Buffer::default();
+        let drain = slog::Fuse(JsonDrain::new(buffer.clone()));
+        let logger = Logger::root(drain, o!("component" => "SubgraphInstanceManager"))
+            .new(o!("subgraph_id" => "QmTest", "component" => "Runner"))
+            .new(o!("block_number" => "17", "handler" => "handleTransfer"));
+
+        info!(logger, "Done processing trigger"; "handler" => "handleApproval", "total_ms" => 5);
+
+        let out = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let log: Value = serde_json::from_str(out.trim_end()).unwrap();
+        assert_eq!("info", log["level"]);
+        assert_eq!("Done processing trigger", log["msg"]);
+        assert_eq!("QmTest", log["deployment"]);
+        assert_eq!(17, log["block"]);
+        assert_eq!("handleApproval", log["handler"]);
+        assert_eq!(5, log["total_ms"]);
+        assert_eq!("SubgraphInstanceManager > Runner", log["component"]);
+        assert!(log["timestamp"].is_string());
+    }
+}
@@ -24,21 +24,40 @@ use slog_envlogger;
 use slog_term::*;
 use std::{fmt, io, result};
 
+use crate::env::LogFormat;
 use crate::prelude::ENV_VARS;
 
+pub mod buffer;
 pub mod codes;
 pub mod elastic;
 pub mod factory;
+pub mod json;
 pub mod split;
+pub mod telemetry;
 
 pub fn logger(show_debug: bool) -> Logger {
     logger_with_levels(show_debug, ENV_VARS.log_levels.as_deref())
 }
 
 pub fn logger_with_levels(show_debug: bool, levels: Option<&str>) -> Logger {
-    let use_color = isatty::stdout_isatty();
-    let decorator = slog_term::TermDecorator::new().build();
-    let drain = CustomFormat::new(decorator, use_color).fuse();
+    match ENV_VARS.log_format {
+        LogFormat::Text => {
+            let use_color = isatty::stdout_isatty();
+            let decorator = slog_term::TermDecorator::new().build();
+            let drain = CustomFormat::new(decorator, use_color).fuse();
+            filtered_logger(drain, show_debug, levels)
+        }
+        LogFormat::Json => {
+            let drain = json::JsonDrain::new(io::stdout()).fuse();
+            filtered_logger(drain, show_debug, levels)
+        }
+    }
+}
+
+fn filtered_logger<D>(drain: D, show_debug: bool, levels: Option<&str>) -> Logger
+where
+    D: Drain<Ok = (), Err = Never> + Send + 'static,
+{
     let drain = slog_envlogger::LogBuilder::new(drain)
         .filter(
             None,
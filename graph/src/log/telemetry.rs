@@ -0,0 +1,95 @@
+//! OpenTelemetry tracing for block processing and query execution. Spans
+//! are exported with OTLP to the collector set with `GRAPH_OTLP_ENDPOINT`;
+//! if it is not set, spans are not recorded and creating them is cheap.
+//!
+//! Processing a block starts a new trace with `block_span`. The spans for
+//! the steps of processing the block are started with `span` while the
+//! block span is current and are therefore part of the same trace
+use std::future::Future;
+
+use opentelemetry::global::{self, BoxedSpan};
+use opentelemetry::sdk::trace::{self as sdktrace, Sampler};
+use opentelemetry::sdk::Resource;
+use opentelemetry::trace::{FutureExt as _, Span as _, Status, TraceContextExt as _, Tracer as _};
+use opentelemetry::{Context, KeyValue, Value};
+use opentelemetry_otlp::WithExportConfig;
+
+use crate::prelude::{info, BlockPtr, DeploymentHash, Error, Logger, NodeId, ENV_VARS};
+
+const TRACER: &str = "graph-node";
+
+/// Set up exporting spans to the collector at `GRAPH_OTLP_ENDPOINT`. Does
+/// nothing if that is not set. Must be called from within a Tokio runtime
+pub fn init(logger: &Logger, node_id: &NodeId) -> Result<(), Error> {
+    let endpoint = match &ENV_VARS.otlp_endpoint {
+        Some(endpoint) => endpoint,
+        None => return Ok(()),
+    };
+
+    let sampler = Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+        ENV_VARS.otlp_sample_ratio,
+    )));
+    let resource = Resource::new(vec![
+        KeyValue::new("service.name", TRACER),
+        KeyValue::new("service.instance.id", node_id.to_string()),
+    ]);
+    opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint),
+        )
+        .with_trace_config(
+            sdktrace::config()
+                .with_sampler(sampler)
+                .with_resource(resource),
+        )
+        .install_batch(opentelemetry::runtime::Tokio)?;
+
+    info!(logger, "Exporting traces with OTLP";
+          "endpoint" => endpoint,
+          "sample_ratio" => ENV_VARS.otlp_sample_ratio);
+    Ok(())
+}
+
+/// A span that ends when it is dropped
+pub struct Span(BoxedSpan);
+
+impl Span {
+    pub fn attr(mut self, key: &'static str, value: impl Into<Value>) -> Self {
+        self.set_attr(key, value);
+        self
+    }
+
+    pub fn set_attr(&mut self, key: &'static str, value: impl Into<Value>) {
+        self.0.set_attribute(KeyValue::new(key, value));
+    }
+
+    /// Mark the operation that this span covers as failed
+    pub fn set_error(&mut self, error: impl ToString) {
+        self.0.set_status(Status::error(error.to_string()));
+    }
+}
+
+/// Start a span that is a child of the current span, or the root of a new
+/// trace if there is no current span
+pub fn span(name: &'static str) -> Span {
+    Span(global::tracer(TRACER).start(name))
+}
+
+/// Start a new trace for processing `block` for `deployment`
+pub fn block_span(deployment: &DeploymentHash, block: &BlockPtr) -> Span {
+    let span = global::tracer(TRACER).start_with_context("process_block", &Context::new());
+    Span(span)
+        .attr("deployment", deployment.to_string())
+        .attr("block.number", block.number as i64)
+        .attr("block.hash", block.hash_hex())
+}
+
+/// Run `fut` with `span` as the current span so that the spans started
+/// while `fut` runs become children of `span`. The span ends when `fut`
+/// finishes
+pub fn in_span<F: Future>(span: Span, fut: F) -> impl Future<Output = F::Output> {
+    fut.with_context(Context::current_with_span(span.0))
+}
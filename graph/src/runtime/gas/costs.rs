@@ -50,6 +50,10 @@ pub const BIG_MATH_GAS_OP: GasOp = GasOp {
     size_mult: BIG_MATH_GAS_PER_BYTE,
 };
 
+/// The cost of one of the multiplications that `BigDecimal::log10` and
+/// `BigDecimal::pow` perform, which multiply numbers of at most 32 bytes.
+pub const BIG_DECIMAL_STEP: Gas = Gas(32 * 32 * BIG_MATH_GAS_PER_BYTE);
+
 // Allow up to 100,000 data sources to be created
 pub const CREATE_DATA_SOURCE: Gas = Gas(CONST_MAX_GAS_PER_HANDLER / 100_000);
 
@@ -74,3 +78,7 @@ pub const STORE_GET: GasOp = GasOp {
 };
 
 pub const STORE_REMOVE: GasOp = STORE_SET;
+
+// Recovering a secp256k1 public key takes about 50µs, be conservative and
+// charge for 100µs.
+pub const SECP256K1_RECOVER: Gas = Gas(GAS_PER_SECOND / 10_000);
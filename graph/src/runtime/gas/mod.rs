@@ -75,26 +75,43 @@ impl Display for Gas {
     }
 }
 
-#[derive(Clone, Default)]
-pub struct GasCounter(Arc<AtomicU64>);
+#[derive(Clone)]
+pub struct GasCounter {
+    gas: Arc<AtomicU64>,
+    limit: u64,
+}
 
 impl CheapClone for GasCounter {}
 
+impl Default for GasCounter {
+    fn default() -> Self {
+        Self::with_limit(ENV_VARS.max_gas_per_handler)
+    }
+}
+
 impl GasCounter {
     /// Alias of [`Default::default`].
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// A counter that fails once more than `limit` gas has been used
+    pub fn with_limit(limit: u64) -> Self {
+        GasCounter {
+            gas: Arc::new(AtomicU64::new(0)),
+            limit,
+        }
+    }
+
     /// This should be called once per host export
     pub fn consume_host_fn(&self, mut amount: Gas) -> Result<(), DeterministicHostError> {
         amount += costs::HOST_EXPORT_GAS;
         let old = self
-            .0
+            .gas
             .fetch_update(SeqCst, SeqCst, |v| Some(v.saturating_add(amount.0)))
             .unwrap();
         let new = old.saturating_add(amount.0);
-        if new >= ENV_VARS.max_gas_per_handler {
+        if new >= self.limit {
             Err(DeterministicHostError::gas(anyhow::anyhow!(
                 "Gas limit exceeded. Used: {}",
                 new
@@ -105,6 +122,6 @@ impl GasCounter {
     }
 
     pub fn get(&self) -> Gas {
-        Gas(self.0.load(SeqCst))
+        Gas(self.gas.load(SeqCst))
     }
 }
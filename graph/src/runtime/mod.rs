@@ -261,10 +261,12 @@ pub enum IndexForAscTypeId {
     ArrayH256 = 1002,
     ArrayLog = 1003,
     ArrayTypedMapStringStoreValue = 1004,
+    EthereumCallResult = 1005,
+    EthereumCallRevert = 1006,
     // Continue to add more Ethereum type IDs here.
     // e.g.:
-    // NextEthereumType = 1004,
-    // AnotherEthereumType = 1005,
+    // NextEthereumType = 1007,
+    // AnotherEthereumType = 1008,
     // ...
     // LastEthereumType = 1499,
 
@@ -0,0 +1,37 @@
+use tiny_keccak::keccak256;
+
+/// The node of the root of the ENS name tree, i.e., the namehash of the
+/// empty name
+pub const ROOT_NODE: [u8; 32] = [0; 32];
+
+/// Compute the ENS namehash of `name` as described in EIP-137. The name is
+/// hashed as is, i.e., it must already be normalized
+pub fn namehash(name: &str) -> [u8; 32] {
+    if name.is_empty() {
+        return ROOT_NODE;
+    }
+    name.rsplit('.').fold(ROOT_NODE, |node, label| {
+        let mut data = [0u8; 64];
+        data[..32].copy_from_slice(&node);
+        data[32..].copy_from_slice(&keccak256(label.as_bytes()));
+        keccak256(&data)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{namehash, ROOT_NODE};
+
+    #[test]
+    fn namehashes() {
+        assert_eq!(ROOT_NODE, namehash(""));
+        assert_eq!(
+            "93cdeb708b7545dc668eb9280176169d1c33cfd8ed6f04690a0bcc88a93fc4ae",
+            hex::encode(namehash("eth"))
+        );
+        assert_eq!(
+            "de9b09fd7c5f901e23a3f19fecc54828e9c848539801e86591bd9801b019f84f",
+            hex::encode(namehash("foo.eth"))
+        );
+    }
+}
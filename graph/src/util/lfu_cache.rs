@@ -187,6 +187,62 @@ impl<K: Clone + Ord + Eq + Hash + Debug + CacheWeight, V: CacheWeight + Default>
         self.queue.len()
     }
 
+    /// The keys of the `count` entries that are accessed most frequently,
+    /// most frequently accessed first. Stale entries come last
+    pub fn hottest_keys(&self, count: usize) -> Vec<K> {
+        let mut entries: Vec<_> = self.queue.iter().collect();
+        entries.sort_by(|(_, a), (_, b)| a.cmp(b));
+        entries
+            .into_iter()
+            .take(count)
+            .map(|(entry, _)| entry.key.clone())
+            .collect()
+    }
+
+    /// The estimated size in bytes of all entries in the cache
+    pub fn total_weight(&self) -> usize {
+        self.total_weight
+    }
+
+    /// Split off `count` caches from this cache. Each entry is moved to the
+    /// cache with index `index(key)`, or stays in this cache if that is
+    /// `None`. Entries keep how frequently they were accessed
+    pub fn split(&mut self, count: usize, index: impl Fn(&K) -> Option<usize>) -> Vec<Self> {
+        let mut caches: Vec<_> = (0..count)
+            .map(|_| LfuCache {
+                queue: PriorityQueue::new(),
+                total_weight: 0,
+                stale_counter: self.stale_counter,
+                dead_weight: self.dead_weight,
+            })
+            .collect();
+        let queue = std::mem::replace(&mut self.queue, PriorityQueue::new());
+        for (entry, priority) in queue {
+            match index(&entry.key) {
+                Some(idx) => {
+                    self.total_weight -= entry.weight;
+                    caches[idx].total_weight += entry.weight;
+                    caches[idx].queue.push(entry, priority);
+                }
+                None => {
+                    self.queue.push(entry, priority);
+                }
+            }
+        }
+        caches
+    }
+
+    /// Move all entries of `other` into this cache. Entries of `other` for
+    /// keys that this cache already has are dropped
+    pub fn append(&mut self, other: Self) {
+        for (entry, priority) in other.queue {
+            if self.queue.get(&entry).is_none() {
+                self.total_weight += entry.weight;
+                self.queue.push(entry, priority);
+            }
+        }
+    }
+
     /// Same as `evict_with_period(max_weight, STALE_PERIOD)`
     pub fn evict(&mut self, max_weight: usize) -> Option<EvictStats> {
         self.evict_with_period(max_weight, STALE_PERIOD)
@@ -332,3 +388,51 @@ fn entity_lru_cache() {
     assert!(cache.get(&"alligator").is_none());
     assert_eq!(cache.get(&"lion"), Some(&Weight(lion_inner_weight)));
 }
+
+#[test]
+fn hottest_keys() {
+    let mut cache: LfuCache<&'static str, usize> = LfuCache::new();
+    cache.insert("panda", 1);
+    cache.insert("cow", 1);
+    cache.insert("lion", 1);
+    for _ in 0..3 {
+        cache.get(&"lion");
+    }
+    cache.get(&"cow");
+
+    assert_eq!(vec!["lion", "cow"], cache.hottest_keys(2));
+    assert_eq!(vec!["lion", "cow", "panda"], cache.hottest_keys(10));
+}
+
+#[test]
+fn split_and_append() {
+    let mut cache: LfuCache<&'static str, usize> = LfuCache::new();
+    cache.insert("panda", 1);
+    cache.insert("cow", 2);
+    cache.insert("lion", 3);
+    for _ in 0..2 {
+        cache.get(&"lion");
+    }
+    cache.get(&"cow");
+    let weight = cache.total_weight();
+
+    let mut caches = cache.split(2, |key| match *key {
+        "panda" => Some(0),
+        "lion" => Some(1),
+        _ => None,
+    });
+    assert_eq!(vec!["cow"], cache.hottest_keys(10));
+    assert_eq!(vec!["panda"], caches[0].hottest_keys(10));
+    assert_eq!(vec!["lion"], caches[1].hottest_keys(10));
+    assert_eq!(
+        weight,
+        cache.total_weight() + caches[0].total_weight() + caches[1].total_weight()
+    );
+
+    caches[1].insert("cow", 2);
+    for other in caches {
+        cache.append(other);
+    }
+    assert_eq!(vec!["lion", "cow", "panda"], cache.hottest_keys(10));
+    assert_eq!(weight, cache.total_weight());
+}
@@ -4,6 +4,8 @@ pub mod futures;
 /// Security utilities.
 pub mod security;
 
+pub mod hmac;
+
 pub mod lfu_cache;
 
 pub mod timed_cache;
@@ -29,3 +31,6 @@ pub mod mem;
 
 /// Data structures instrumented with Prometheus metrics.
 pub mod monitored;
+
+/// Helpers for the Ethereum Name Service
+pub mod ens;
@@ -1,9 +1,10 @@
 use async_trait::async_trait;
 use graph::blockchain::block_stream::FirehoseCursor;
 use graph::components::store::{
-    DeploymentCursorTracker, DerivedEntityQuery, EntityKey, EntityType, LoadRelatedRequest,
-    ReadStore, StoredDynamicDataSource, WritableStore,
+    DeploymentCursorTracker, DeploymentLoad, DerivedEntityQuery, EntityKey, EntityType,
+    LoadRelatedRequest, ReadStore, StoredDynamicDataSource, WritableStore,
 };
+use graph::data::store::EntityValidationError;
 use graph::data::subgraph::schema::{DeploymentCreate, SubgraphError, SubgraphHealth};
 use graph::data_source::CausalityRegion;
 use graph::prelude::*;
@@ -143,6 +144,22 @@ impl WritableStore for MockStore {
         unimplemented!()
     }
 
+    fn report_load(&self, _load: DeploymentLoad) -> Result<(), StoreError> {
+        unimplemented!()
+    }
+
+    async fn set_retry_count(&self, _count: u32) -> Result<(), StoreError> {
+        unimplemented!()
+    }
+
+    async fn save_entity_cache_snapshot(&self, _keys: Vec<EntityKey>) -> Result<(), StoreError> {
+        unimplemented!()
+    }
+
+    async fn entity_cache_snapshot(&self) -> Result<Vec<EntityKey>, StoreError> {
+        unimplemented!()
+    }
+
     async fn load_dynamic_data_sources(
         &self,
         _manifest_idx_and_name: Vec<(u32, String)>,
@@ -365,6 +382,61 @@ fn consecutive_modifications() {
     );
 }
 
+#[test]
+fn cache_stats_and_eviction() {
+    let (mogwai_key, mogwai_data) = make_band(
+        "mogwai",
+        vec![("id", "mogwai".into()), ("name", "Mogwai".into())],
+    );
+    let store = MockStore::new(entity_version_map("Band", vec![mogwai_data.clone()]));
+    let mut cache = EntityCache::new(Arc::new(store));
+
+    // The first lookup goes to the store, the second one is answered from
+    // the cache
+    assert_eq!(Some(mogwai_data.clone()), cache.get(&mogwai_key).unwrap());
+    assert_eq!(Some(mogwai_data), cache.get(&mogwai_key).unwrap());
+
+    // With no room in the cache, the entity is evicted at the end of the
+    // block
+    cache.set_max_weight(0);
+    let result = cache.as_modifications().unwrap();
+    assert!(result.entity_lfu_cache.is_empty());
+    assert_eq!(1, result.stats.hits);
+    assert_eq!(1, result.stats.misses);
+    assert_eq!(1, result.stats.evicted_count);
+    assert!(result.stats.evicted_weight > 0);
+    assert_eq!(0, result.stats.weight);
+}
+
+#[test]
+fn invalid_entities_are_validation_errors() {
+    let store = MockStore::new(BTreeMap::new());
+    let mut cache = EntityCache::new(Arc::new(store));
+
+    // The id in the entity does not match the id in the key
+    let (key, data) = make_band(
+        "mogwai",
+        vec![("id", "sigurros".into()), ("name", "Mogwai".into())],
+    );
+    let err = cache.set(key, data).unwrap_err();
+    assert_eq!(
+        Some(&EntityValidationError::IdConflict {
+            entity: "Band".to_string(),
+            entity_id: "mogwai".to_string(),
+            value: "sigurros".to_string(),
+        }),
+        err.downcast_ref::<EntityValidationError>()
+    );
+
+    // A required field is missing
+    let (key, data) = make_band("mogwai", vec![("id", "mogwai".into())]);
+    let err = cache.set(key, data).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<EntityValidationError>(),
+        Some(EntityValidationError::MissingValueForNonNullableField { .. })
+    ));
+}
+
 const ACCOUNT_GQL: &str = "
     type Account @entity {
         id: ID!
@@ -454,6 +526,7 @@ async fn insert_test_data(store: Arc<DieselSubgraphStore>) -> DeploymentLocator
         data_sources: vec![],
         graft: None,
         templates: vec![],
+        handler_limits: Default::default(),
         chain: PhantomData,
     };
 
@@ -724,6 +797,59 @@ fn check_for_update_async_related() {
     });
 }
 
+#[test]
+fn check_for_changes_in_block() {
+    run_store_test(|mut cache, _store, _deployment, _writable| async move {
+        let account_id = "1";
+        // A new wallet for the account, and a wallet that is moved to a
+        // different account, neither of which is in the store yet
+        let wallet_7 = create_wallet_entity("7", account_id, 7_i32);
+        cache
+            .set(
+                EntityKey::data(WALLET.to_owned(), "7".to_owned()),
+                wallet_7.clone(),
+            )
+            .unwrap();
+        cache
+            .set(
+                EntityKey::data(WALLET.to_owned(), "2".to_owned()),
+                create_wallet_entity("2", "2", 92_i32),
+            )
+            .unwrap();
+        cache.remove(EntityKey::data(WALLET.to_owned(), "3".to_owned()));
+
+        let request = LoadRelatedRequest {
+            entity_type: EntityType::new(ACCOUNT.to_string()),
+            entity_field: "wallets".into(),
+            entity_id: account_id.into(),
+            causality_region: CausalityRegion::ONCHAIN,
+        };
+        let result = cache.load_related(&request).unwrap();
+        let wallet_1 = create_wallet_entity("1", account_id, 67_i32);
+        let expeted_vec = vec![wallet_1, wallet_7];
+
+        assert_eq!(result, expeted_vec);
+    });
+}
+
+#[test]
+fn get_in_block_ignores_store() {
+    run_store_test(|mut cache, _store, _deployment, _writable| async move {
+        let wallet_key = |id: &str| EntityKey::data(WALLET.to_owned(), id.to_owned());
+
+        // Wallet 1 is in the store, but has not been changed in this block
+        assert!(cache.get(&wallet_key("1")).unwrap().is_some());
+        assert_eq!(None, cache.get_in_block(&wallet_key("1")));
+
+        let wallet_7 = create_wallet_entity("7", "1", 7_i32);
+        cache.set(wallet_key("7"), wallet_7.clone()).unwrap();
+        assert_eq!(Some(wallet_7), cache.get_in_block(&wallet_key("7")));
+
+        cache.remove(wallet_key("2"));
+        assert_eq!(None, cache.get_in_block(&wallet_key("2")));
+    });
+}
+
 #[test]
 fn check_for_delete_async_related() {
     run_store_test(|mut cache, store, deployment, _writable| async move {
@@ -17,7 +17,7 @@ use graph::env::CachedSubgraphIds;
 use graph::prelude::*;
 use graph::util::{lfu_cache::LfuCache, stable_hash_glue::impl_stable_hash};
 
-use super::QueryHash;
+use super::{QueryHash, ResultSize};
 use crate::execution::ast as a;
 use crate::introspection::{is_introspection_field, INTROSPECTION_QUERY_TYPE};
 use crate::prelude::*;
@@ -66,6 +66,7 @@ struct HashableQuery<'a> {
     query_schema_id: &'a DeploymentHash,
     selection_set: &'a a::SelectionSet,
     block_ptr: &'a BlockPtr,
+    max_result_bytes: Option<usize>,
 }
 
 // Note that the use of StableHash here is a little bit loose. In particular,
@@ -90,14 +91,16 @@ impl_stable_hash!(HashableQuery<'_> {
     // TODO: Performance: Save a cryptographic hash (Blake3) of the original query
     // and pass it through, rather than formatting the selection set.
     selection_set: format_selection_set,
-    block_ptr
+    block_ptr,
+    max_result_bytes
 });
 
 fn format_selection_set(s: &a::SelectionSet) -> String {
     format!("{:?}", s)
 }
 
-// The key is: subgraph id + selection set + variables + fragment definitions
+// The key is: subgraph id + selection set + variables + fragment definitions,
+// and the result size limit since results are cut short to fit into it
 fn cache_key(
     ctx: &ExecutionContext<impl Resolver>,
     selection_set: &a::SelectionSet,
@@ -109,6 +112,7 @@ fn cache_key(
         query_schema_id: ctx.query.schema.id(),
         selection_set,
         block_ptr,
+        max_result_bytes: ctx.result_size.limit(),
     };
     // Security:
     // This uses the crypo stable hash because a collision would
@@ -207,6 +211,10 @@ where
 
     /// Whether to include an execution trace in the result
     pub trace: bool,
+
+    /// The size of the result while it is being built, so that lists can
+    /// be cut short when the result gets too big
+    pub result_size: ResultSize,
 }
 
 pub(crate) fn get_field<'a>(
@@ -240,6 +248,7 @@ where
             // `cache_status` is a dead value for the introspection context.
             cache_status: AtomicCell::new(CacheStatus::Miss),
             trace: ENV_VARS.log_sql_timing(),
+            result_size: ResultSize::new(None),
         }
     }
 }
@@ -368,6 +377,8 @@ pub(crate) async fn execute_root_selection_set<R: Resolver>(
             // Unwrap: In practice should never fail, but if it does we will catch the panic.
             execute_ctx.resolver.post_process(&mut query_res).unwrap();
             query_res.deployment = Some(execute_ctx.query.schema.id().clone());
+            query_res.set_truncations(execute_ctx.result_size.take_truncations());
+            query_res.set_data_size(execute_ctx.result_size.used());
             Arc::new(query_res)
         })
         .await
@@ -480,6 +491,8 @@ async fn execute_selection_set_to_map<'a>(
     };
     let mut errors: Vec<QueryExecutionError> = Vec::new();
     let mut results = Vec::new();
+    // Braces
+    ctx.result_size.add(2);
 
     // Gather fields that appear more than once with the same response key.
     let multiple_response_keys = {
@@ -523,9 +536,14 @@ async fn execute_selection_set_to_map<'a>(
             }
         });
 
+        // The key, quotes, colon and comma
+        ctx.result_size.add(response_key.len() + 4);
         if field.name.as_str() == "__typename" && field_value.is_none() {
-            results.push((response_key, r::Value::String(object_type.name.clone())));
+            let typename = r::Value::String(object_type.name.clone());
+            ctx.result_size.add_value(&typename);
+            results.push((response_key, typename));
         } else {
+            ctx.result_size.enter(response_key);
             match execute_field(ctx, object_type, field_value, field, field_type).await {
                 Ok(v) => {
                     results.push((response_key, v));
@@ -534,6 +552,7 @@ async fn execute_selection_set_to_map<'a>(
                     errors.append(&mut e);
                 }
             }
+            ctx.result_size.leave();
         }
     }
 
@@ -758,7 +777,10 @@ async fn complete_value(
         }
 
         // If the resolved value is null, return null
-        _ if resolved_value.is_null() => Ok(resolved_value),
+        _ if resolved_value.is_null() => {
+            ctx.result_size.add_value(&resolved_value);
+            Ok(resolved_value)
+        }
 
         // Complete list values
         s::Type::ListType(inner_type) => {
@@ -766,17 +788,65 @@ async fn complete_value(
                 // Complete list values individually
                 r::Value::List(mut values) => {
                     let mut errors = Vec::new();
+                    let size = &ctx.result_size;
+                    // Brackets
+                    size.add(2);
+
+                    // The number of values to keep, and how many of them
+                    // are complete, if the result gets too big
+                    let mut kept = values.len();
+                    let mut complete = values.len();
 
                     // To avoid allocating a new vector this completes the values in place.
-                    for value_place in &mut values {
+                    for (index, value_place) in values.iter_mut().enumerate() {
+                        if index > 0 && size.is_exceeded() {
+                            (kept, complete) = (index, index);
+                            break;
+                        }
+                        let mark = size.mark();
+                        if index > 0 {
+                            // Comma
+                            size.add(1);
+                        }
+
                         // Put in a placeholder, complete the value, put the completed value back.
                         let value = std::mem::replace(value_place, r::Value::Null);
+                        size.enter(index);
                         match complete_value(ctx, field, inner_type, value).await {
                             Ok(value) => {
                                 *value_place = value;
                             }
                             Err(errs) => errors.extend(errs),
                         }
+                        size.leave();
+
+                        if size.overflowed_since(&mark) {
+                            // Lists end after the last value that fits
+                            // completely. The first value is kept even if
+                            // its own lists had to be cut short so that
+                            // the result always makes progress
+                            (kept, complete) = match index {
+                                0 => (1, 0),
+                                _ => {
+                                    size.reset(mark);
+                                    (index, index)
+                                }
+                            };
+                            break;
+                        }
+                    }
+                    if kept < values.len() {
+                        values.truncate(kept);
+                        let cursor = complete
+                            .checked_sub(1)
+                            .and_then(|last| match &values[last] {
+                                r::Value::Object(obj) => match obj.get("id") {
+                                    Some(r::Value::String(id)) => Some(id.clone()),
+                                    _ => None,
+                                },
+                                _ => None,
+                            });
+                        size.truncated(complete, cursor);
                     }
                     match errors.is_empty() {
                         true => Ok(r::Value::List(values)),
@@ -798,19 +868,21 @@ async fn complete_value(
             match named_type {
                 // Complete scalar values
                 s::TypeDefinition::Scalar(scalar_type) => {
-                    resolved_value.coerce_scalar(scalar_type).map_err(|value| {
+                    let value = resolved_value.coerce_scalar(scalar_type).map_err(|value| {
                         vec![QueryExecutionError::ScalarCoercionError(
                             field.position,
                             field.name.clone(),
                             value.into(),
                             scalar_type.name.clone(),
                         )]
-                    })
+                    })?;
+                    ctx.result_size.add_value(&value);
+                    Ok(value)
                 }
 
                 // Complete enum values
                 s::TypeDefinition::Enum(enum_type) => {
-                    resolved_value.coerce_enum(enum_type).map_err(|value| {
+                    let value = resolved_value.coerce_enum(enum_type).map_err(|value| {
                         vec![QueryExecutionError::EnumCoercionError(
                             field.position,
                             field.name.clone(),
@@ -822,7 +894,9 @@ async fn complete_value(
                                 .map(|value| value.name.clone())
                                 .collect(),
                         )]
-                    })
+                    })?;
+                    ctx.result_size.add_value(&value);
+                    Ok(value)
                 }
 
                 // Complete object types recursively
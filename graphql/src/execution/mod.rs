@@ -4,6 +4,8 @@ mod execution;
 mod query;
 /// Common trait for field resolvers used in the execution.
 mod resolver;
+/// Limiting the size of query results while they are built.
+mod result_size;
 
 /// Our representation of a query AST
 pub mod ast;
@@ -13,5 +15,6 @@ use stable_hash_legacy::{crypto::SetHasher, StableHasher};
 pub use self::execution::*;
 pub use self::query::Query;
 pub use self::resolver::Resolver;
+pub use self::result_size::ResultSize;
 
 type QueryHash = <SetHasher as StableHasher>::Out;
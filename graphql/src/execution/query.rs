@@ -7,6 +7,7 @@ use lazy_static::lazy_static;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 use std::iter::FromIterator;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 use std::{collections::hash_map::DefaultHasher, convert::TryFrom};
@@ -136,6 +137,10 @@ pub struct Query {
     pub query_text: Arc<String>,
     pub variables_text: Arc<String>,
     pub query_id: String,
+
+    /// The number of entities that were loaded from the store to execute
+    /// the query
+    entities_fetched: AtomicUsize,
 }
 
 fn validate_query(
@@ -275,6 +280,7 @@ impl Query {
             query_text: query.query_text.cheap_clone(),
             variables_text: query.variables_text.cheap_clone(),
             query_id,
+            entities_fetched: AtomicUsize::new(0),
         };
 
         Ok(Arc::new(query))
@@ -352,6 +358,15 @@ impl Query {
         }
     }
 
+    pub(crate) fn add_entities_fetched(&self, count: usize) {
+        self.entities_fetched.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// The number of entities that were loaded from the store so far
+    pub fn entities_fetched(&self) -> usize {
+        self.entities_fetched.load(Ordering::Relaxed)
+    }
+
     /// Log details about the overall execution of the query
     pub fn log_execution(&self, block: BlockNumber) {
         if ENV_VARS.log_gql_timing() {
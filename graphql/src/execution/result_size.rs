@@ -0,0 +1,189 @@
+use std::sync::Mutex;
+
+use graph::data::query::Truncation;
+use graph::prelude::{r, serde_json};
+
+/// An `io::Write` that only counts the bytes written to it
+struct ByteCounter(usize);
+
+impl std::io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The number of bytes `value` takes up when serialized to JSON
+fn serialized_size(value: &r::Value) -> usize {
+    let mut counter = ByteCounter(0);
+    serde_json::to_writer(&mut counter, value).expect("values can be serialized");
+    counter.0
+}
+
+#[derive(Default)]
+struct State {
+    /// The number of bytes that the result built so far takes up
+    used: usize,
+    /// The response keys and list indices that lead to the value that is
+    /// being built
+    path: Vec<serde_json::Value>,
+    truncations: Vec<Truncation>,
+}
+
+/// How far building a result had gotten at some point, so that what was
+/// built after that can be undone
+pub(crate) struct Mark {
+    used: usize,
+    truncations: usize,
+}
+
+/// Keeps track of how many bytes the result of a query takes up when it is
+/// serialized while the result is being built, and of the lists that had
+/// to be cut short to stay below the limit `GRAPH_GRAPHQL_MAX_RESULT_BYTES`.
+/// Scalars add their size when they are completed, and objects and lists
+/// the size of their punctuation, so that every value is only measured
+/// once. Without a limit, nothing is tracked
+pub struct ResultSize {
+    limit: Option<usize>,
+    state: Mutex<State>,
+}
+
+impl ResultSize {
+    pub fn new(limit: Option<usize>) -> Self {
+        ResultSize {
+            limit,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    fn with_state<T: Default>(&self, f: impl FnOnce(&mut State) -> T) -> T {
+        match self.limit {
+            Some(_) => f(&mut self.state.lock().unwrap()),
+            None => T::default(),
+        }
+    }
+
+    /// The number of bytes that the result built so far takes up
+    pub(crate) fn used(&self) -> usize {
+        self.with_state(|state| state.used)
+    }
+
+    pub(crate) fn add(&self, bytes: usize) {
+        self.with_state(|state| state.used += bytes)
+    }
+
+    /// Add the size of the scalar `value`
+    pub(crate) fn add_value(&self, value: &r::Value) {
+        self.with_state(|state| state.used += serialized_size(value))
+    }
+
+    /// Whether the result built so far is bigger than the limit
+    pub(crate) fn is_exceeded(&self) -> bool {
+        match self.limit {
+            Some(limit) => self.used() > limit,
+            None => false,
+        }
+    }
+
+    /// Start building the value at `segment`, a response key or a list
+    /// index, of the current value
+    pub(crate) fn enter(&self, segment: impl Into<serde_json::Value>) {
+        self.with_state(|state| state.path.push(segment.into()))
+    }
+
+    /// Finish building the value that was last entered
+    pub(crate) fn leave(&self) {
+        self.with_state(|state| {
+            state.path.pop();
+        })
+    }
+
+    pub(crate) fn mark(&self) -> Mark {
+        let (used, truncations) = self.with_state(|state| (state.used, state.truncations.len()));
+        Mark { used, truncations }
+    }
+
+    /// Whether the value built since `mark` did not fit completely, either
+    /// because the result is now bigger than the limit or because some of
+    /// its lists had to be cut short
+    pub(crate) fn overflowed_since(&self, mark: &Mark) -> bool {
+        self.is_exceeded() || self.with_state(|state| state.truncations.len() > mark.truncations)
+    }
+
+    /// Forget the value built since `mark` because it will not be part of
+    /// the result
+    pub(crate) fn reset(&self, mark: Mark) {
+        self.with_state(|state| {
+            state.used = mark.used;
+            state.truncations.truncate(mark.truncations);
+        })
+    }
+
+    /// Record that the current list was cut short after its first
+    /// `returned` complete elements, the last of which has the id `cursor`
+    pub(crate) fn truncated(&self, returned: usize, cursor: Option<String>) {
+        self.with_state(|state| {
+            let truncation = Truncation {
+                path: state.path.clone(),
+                returned,
+                cursor,
+            };
+            state.truncations.push(truncation)
+        })
+    }
+
+    pub(crate) fn take_truncations(&self) -> Vec<Truncation> {
+        self.with_state(|state| std::mem::take(&mut state.truncations))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marks() {
+        let size = ResultSize::new(Some(10));
+        size.enter("things");
+        size.add_value(&r::Value::String("abc".to_owned()));
+        assert_eq!(5, size.used());
+
+        let mark = size.mark();
+        size.add(4);
+        assert!(!size.overflowed_since(&mark));
+        size.enter(0u64);
+        size.truncated(0, None);
+        size.leave();
+        assert!(size.overflowed_since(&mark));
+        size.reset(mark);
+        assert_eq!(5, size.used());
+
+        let mark = size.mark();
+        size.add(6);
+        assert!(size.is_exceeded());
+        assert!(size.overflowed_since(&mark));
+        size.truncated(1, Some("a".to_owned()));
+        assert_eq!(
+            vec![Truncation {
+                path: vec![serde_json::json!("things")],
+                returned: 1,
+                cursor: Some("a".to_owned())
+            }],
+            size.take_truncations()
+        );
+
+        // Without a limit, nothing is tracked
+        let size = ResultSize::new(None);
+        size.add(100);
+        assert_eq!(0, size.used());
+        assert!(!size.is_exceeded());
+    }
+}
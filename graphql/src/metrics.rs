@@ -1,11 +1,25 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::fmt;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use graph::data::query::QueryResults;
-use graph::prelude::{DeploymentHash, GraphQLMetrics as GraphQLMetricsTrait, MetricsRegistry};
-use graph::prometheus::{CounterVec, Gauge, Histogram, HistogramVec};
+use graph::prelude::{
+    DeploymentHash, GraphQLMetrics as GraphQLMetricsTrait, MetricsRegistry, ENV_VARS,
+};
+use graph::prometheus::core::{Collector, Desc};
+use graph::prometheus::proto::MetricFamily;
+use graph::prometheus::{
+    CounterVec, Error as PrometheusError, Gauge, GaugeVec, Histogram, HistogramVec, Opts,
+};
+
+/// How many of the most recent execution times of a query shape are kept
+/// to estimate percentiles
+const SHAPE_DURATION_SAMPLES: usize = 128;
+/// How many more query shapes are tracked than are exported, so that shapes
+/// that only recently became expensive can make it into the top
+const SHAPE_TRACKING_FACTOR: usize = 10;
+const SHAPE_QUANTILES: [(&str, f64); 3] = [("0.5", 0.5), ("0.9", 0.9), ("0.99", 0.99)];
 
 pub struct GraphQLMetrics {
     query_execution_time: Box<HistogramVec>,
@@ -14,6 +28,7 @@ pub struct GraphQLMetrics {
     query_result_size: Box<Histogram>,
     query_result_size_max: Box<Gauge>,
     query_validation_error_counter: Box<CounterVec>,
+    query_shapes: Option<QueryShapes>,
 }
 
 impl fmt::Debug for GraphQLMetrics {
@@ -128,6 +143,15 @@ impl GraphQLMetrics {
             )
             .unwrap();
 
+        let query_shapes = match ENV_VARS.graphql.top_query_shapes {
+            0 => None,
+            top => {
+                let shapes = QueryShapes::new(top).expect("failed to create query shape metrics");
+                registry.register("query_shapes", Box::new(shapes.clone()));
+                Some(shapes)
+            }
+        };
+
         Self {
             query_execution_time,
             query_parsing_time,
@@ -135,6 +159,7 @@ impl GraphQLMetrics {
             query_result_size,
             query_result_size_max,
             query_validation_error_counter,
+            query_shapes,
         }
     }
 
@@ -151,4 +176,241 @@ impl GraphQLMetrics {
             self.query_result_size_max.set(size);
         }
     }
+
+    /// Record the execution of a query with shape `shape_hash` against
+    /// `deployment` if query shapes are tracked
+    pub fn observe_query_shape(
+        &self,
+        deployment: &DeploymentHash,
+        shape_hash: u64,
+        duration: Duration,
+        failed: bool,
+        entities: usize,
+    ) {
+        if let Some(shapes) = &self.query_shapes {
+            shapes.observe(deployment, shape_hash, duration, failed, entities);
+        }
+    }
+}
+
+#[derive(Default)]
+struct ShapeStats {
+    count: u64,
+    errors: u64,
+    entities: u64,
+    /// The total execution time in seconds
+    duration: f64,
+    samples: VecDeque<f64>,
+}
+
+impl ShapeStats {
+    fn observe(&mut self, duration: f64, failed: bool, entities: usize) {
+        self.count += 1;
+        if failed {
+            self.errors += 1;
+        }
+        self.entities += entities as u64;
+        self.duration += duration;
+        if self.samples.len() == SHAPE_DURATION_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(duration);
+    }
+
+    /// Estimate the quantiles in `SHAPE_QUANTILES` from the recent samples
+    fn quantiles(&self) -> Vec<(&'static str, f64)> {
+        let mut samples: Vec<_> = self.samples.iter().copied().collect();
+        samples.sort_by(|a, b| a.total_cmp(b));
+        SHAPE_QUANTILES
+            .iter()
+            .map(|(label, q)| {
+                let value = match samples.len() {
+                    0 => 0.0,
+                    len => samples[((len - 1) as f64 * q).round() as usize],
+                };
+                (*label, value)
+            })
+            .collect()
+    }
+}
+
+type ShapeKey = (DeploymentHash, u64);
+
+/// The queries that ran on this node, aggregated by deployment and query
+/// shape. Only the `top` shapes that took the most time overall are
+/// exported; the metrics are filled in whenever they are scraped. At most
+/// `SHAPE_TRACKING_FACTOR * top` shapes are tracked, and when a new shape
+/// comes along, the shape that took the least time is forgotten
+#[derive(Clone)]
+struct QueryShapes {
+    top: usize,
+    shapes: Arc<Mutex<HashMap<ShapeKey, ShapeStats>>>,
+    count: CounterVec,
+    errors: CounterVec,
+    entities: CounterVec,
+    duration: CounterVec,
+    quantiles: GaugeVec,
+}
+
+impl QueryShapes {
+    fn new(top: usize) -> Result<Self, PrometheusError> {
+        let labels = ["deployment", "shape"];
+        let counter = |name: &str, help: &str| CounterVec::new(Opts::new(name, help), &labels);
+        Ok(Self {
+            top,
+            shapes: Arc::new(Mutex::new(HashMap::new())),
+            count: counter("query_shape_count", "the number of queries with this shape")?,
+            errors: counter(
+                "query_shape_errors",
+                "the number of queries with this shape that failed",
+            )?,
+            entities: counter(
+                "query_shape_entities_fetched",
+                "the number of entities that queries with this shape loaded from the store",
+            )?,
+            duration: counter(
+                "query_shape_execution_time",
+                "the total execution time of queries with this shape in seconds",
+            )?,
+            quantiles: GaugeVec::new(
+                Opts::new(
+                    "query_shape_execution_time_quantile",
+                    "quantiles of the execution time of recent queries with this shape in seconds",
+                ),
+                &["deployment", "shape", "quantile"],
+            )?,
+        })
+    }
+
+    fn observe(
+        &self,
+        deployment: &DeploymentHash,
+        shape_hash: u64,
+        duration: Duration,
+        failed: bool,
+        entities: usize,
+    ) {
+        let mut shapes = self.shapes.lock().unwrap();
+        let key = (deployment.clone(), shape_hash);
+        if !shapes.contains_key(&key) && shapes.len() >= self.top * SHAPE_TRACKING_FACTOR {
+            let cheapest = shapes
+                .iter()
+                .min_by(|(_, a), (_, b)| a.duration.total_cmp(&b.duration))
+                .map(|(key, _)| key.clone());
+            if let Some(cheapest) = cheapest {
+                shapes.remove(&cheapest);
+            }
+        }
+        shapes
+            .entry(key)
+            .or_default()
+            .observe(duration.as_secs_f64(), failed, entities);
+    }
+}
+
+/// The `top` shapes in `shapes` that took the most time overall
+fn top_shapes(shapes: &HashMap<ShapeKey, ShapeStats>, top: usize) -> Vec<(&ShapeKey, &ShapeStats)> {
+    let mut shapes: Vec<_> = shapes.iter().collect();
+    shapes.sort_by(|(_, a), (_, b)| b.duration.total_cmp(&a.duration));
+    shapes.truncate(top);
+    shapes
+}
+
+impl Collector for QueryShapes {
+    fn desc(&self) -> Vec<&Desc> {
+        let mut desc = self.count.desc();
+        desc.extend(self.errors.desc());
+        desc.extend(self.entities.desc());
+        desc.extend(self.duration.desc());
+        desc.extend(self.quantiles.desc());
+        desc
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        // Hold the lock until everything is collected so that concurrent
+        // scrapes do not see each other's partially filled metrics
+        let shapes = self.shapes.lock().unwrap();
+
+        self.count.reset();
+        self.errors.reset();
+        self.entities.reset();
+        self.duration.reset();
+        self.quantiles.reset();
+        for ((deployment, shape_hash), stats) in top_shapes(&shapes, self.top) {
+            let shape = format!("{:016x}", shape_hash);
+            let labels = [deployment.as_str(), shape.as_str()];
+            self.count
+                .with_label_values(&labels)
+                .inc_by(stats.count as f64);
+            self.errors
+                .with_label_values(&labels)
+                .inc_by(stats.errors as f64);
+            self.entities
+                .with_label_values(&labels)
+                .inc_by(stats.entities as f64);
+            self.duration
+                .with_label_values(&labels)
+                .inc_by(stats.duration);
+            for (quantile, value) in stats.quantiles() {
+                self.quantiles
+                    .with_label_values(&[deployment.as_str(), shape.as_str(), quantile])
+                    .set(value);
+            }
+        }
+
+        let mut families = self.count.collect();
+        families.extend(self.errors.collect());
+        families.extend(self.entities.collect());
+        families.extend(self.duration.collect());
+        families.extend(self.quantiles.collect());
+        families
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use graph::prelude::DeploymentHash;
+
+    use super::{top_shapes, QueryShapes, ShapeStats, SHAPE_TRACKING_FACTOR};
+
+    #[test]
+    fn tracks_most_expensive_shapes() {
+        let shapes = QueryShapes::new(1).unwrap();
+        let deployment = DeploymentHash::new("QmShapes").unwrap();
+        let observe = |shape, millis| {
+            shapes.observe(&deployment, shape, Duration::from_millis(millis), false, 1)
+        };
+
+        for shape in 0..SHAPE_TRACKING_FACTOR as u64 {
+            observe(shape, 10 + shape);
+        }
+        observe(3, 100);
+        // Shape 0 took the least time and is evicted to make room
+        observe(100, 1);
+
+        let tracked = shapes.shapes.lock().unwrap();
+        assert_eq!(SHAPE_TRACKING_FACTOR, tracked.len());
+        assert!(!tracked.contains_key(&(deployment.clone(), 0)));
+
+        let top = top_shapes(&tracked, 1);
+        assert_eq!(1, top.len());
+        assert_eq!(3, top[0].0 .1);
+        assert_eq!(2, top[0].1.count);
+        assert_eq!(2, top[0].1.entities);
+    }
+
+    #[test]
+    fn estimates_quantiles() {
+        let mut stats = ShapeStats::default();
+        for i in 1..=100 {
+            stats.observe(i as f64, i % 10 == 0, 0);
+        }
+        assert_eq!(10, stats.errors);
+        assert_eq!(
+            vec![("0.5", 51.0), ("0.9", 90.0), ("0.99", 99.0)],
+            stats.quantiles()
+        );
+    }
 }
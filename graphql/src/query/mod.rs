@@ -30,6 +30,9 @@ pub struct QueryExecutionOptions<R> {
 
     /// Whether to include an execution trace in the result
     pub trace: bool,
+
+    /// How many bytes the result may take up before its lists are cut short
+    pub max_result_bytes: Option<usize>,
 }
 
 /// Executes a query and returns a result.
@@ -53,6 +56,7 @@ where
         max_skip: options.max_skip,
         cache_status: Default::default(),
         trace: options.trace,
+        result_size: ResultSize::new(options.max_result_bytes),
     });
 
     if !query.is_query() {
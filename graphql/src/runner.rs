@@ -5,13 +5,14 @@ use crate::metrics::GraphQLMetrics;
 use crate::prelude::{QueryExecutionOptions, StoreResolver, SubscriptionExecutionOptions};
 use crate::query::execute_query;
 use crate::subscription::execute_prepared_subscription;
+use graph::log::telemetry;
 use graph::prelude::MetricsRegistry;
 use graph::{
     components::store::SubscriptionManager,
     prelude::{
-        async_trait, o, CheapClone, DeploymentState, GraphQLMetrics as GraphQLMetricsTrait,
-        GraphQlRunner as GraphQlRunnerTrait, Logger, Query, QueryExecutionError, Subscription,
-        SubscriptionError, SubscriptionResult, ENV_VARS,
+        async_trait, o, CheapClone, DeploymentHash, DeploymentState,
+        GraphQLMetrics as GraphQLMetricsTrait, GraphQlRunner as GraphQlRunnerTrait, Logger, Query,
+        QueryExecutionError, Subscription, SubscriptionError, SubscriptionResult, ENV_VARS,
     },
 };
 use graph::{data::graphql::effort::LoadManager, prelude::QueryStoreManager};
@@ -122,8 +123,17 @@ where
             .clone()
             .unwrap_or(state);
 
+        if let Some(message) = &state.maintenance {
+            return Err(QueryExecutionError::DeploymentInMaintenance(
+                state.id.clone(),
+                message.clone(),
+            )
+            .into());
+        }
+
         let max_depth = max_depth.unwrap_or(ENV_VARS.graphql.max_depth);
         let trace = query.trace;
+        let start = Instant::now();
         let query = crate::execution::Query::new(
             &self.logger,
             schema,
@@ -133,6 +143,9 @@ where
             max_depth,
             metrics.cheap_clone(),
         )?;
+        let mut span = telemetry::span("graphql_query")
+            .attr("deployment", query.schema.id().to_string())
+            .attr("shape_hash", format!("{:016x}", query.shape_hash));
         self.load_manager
             .decide(
                 &store.wait_stats().map_err(QueryExecutionError::from)?,
@@ -143,6 +156,7 @@ where
         let by_block_constraint = query.block_constraint()?;
         let mut max_block = 0;
         let mut result: QueryResults = QueryResults::empty();
+        let mut result_budget = ENV_VARS.graphql.max_result_bytes;
 
         // Note: This will always iterate at least once.
         for (bc, (selection_set, error_policy)) in by_block_constraint {
@@ -170,17 +184,36 @@ where
                     max_skip: max_skip.unwrap_or(ENV_VARS.graphql.max_skip),
                     load_manager: self.load_manager.clone(),
                     trace,
+                    max_result_bytes: result_budget,
                 },
             )
             .await;
             query_res.trace.finish(query_start.elapsed());
+            // All parts of the query share the limit on the result size
+            if let Some(budget) = result_budget.as_mut() {
+                *budget = budget.saturating_sub(query_res.data_size());
+            }
             result.append(query_res);
         }
 
         query.log_execution(max_block);
+        metrics.observe_query_shape(
+            query.schema.id(),
+            query.shape_hash,
+            start.elapsed(),
+            result.has_errors(),
+            query.entities_fetched(),
+        );
+        span.set_attr("block.number", max_block as i64);
+        if result.has_errors() {
+            span.set_error("the query failed");
+        }
         self.deployment_changed(store.as_ref(), state, max_block as u64)
             .await
-            .map_err(QueryResults::from)
+            .map_err(|e| {
+                span.set_error(&e);
+                QueryResults::from(e)
+            })
             .map(|()| result)
     }
 }
@@ -272,6 +305,13 @@ where
         )
     }
 
+    async fn deployment_for(
+        &self,
+        target: &QueryTarget,
+    ) -> Result<DeploymentHash, QueryExecutionError> {
+        self.store.deployment_for(target).await
+    }
+
     fn load_manager(&self) -> Arc<LoadManager> {
         self.load_manager.clone()
     }
@@ -123,7 +123,7 @@ fn add_types_for_object_types(
 ) -> Result<(), APISchemaError> {
     for object_type in object_types {
         if !object_type.name.eq(SCHEMA_TYPE_NAME) {
-            add_order_by_type(schema, &object_type.name, &object_type.fields)?;
+            add_order_by_type(schema, &object_type.name, &object_type.fields, true)?;
             add_filter_type(schema, &object_type.name, &object_type.fields)?;
         }
     }
@@ -136,28 +136,41 @@ fn add_types_for_interface_types(
     interface_types: &[&InterfaceType],
 ) -> Result<(), APISchemaError> {
     for interface_type in interface_types {
-        add_order_by_type(schema, &interface_type.name, &interface_type.fields)?;
+        add_order_by_type(schema, &interface_type.name, &interface_type.fields, false)?;
         add_filter_type(schema, &interface_type.name, &interface_type.fields)?;
     }
     Ok(())
 }
 
 /// Adds a `<type_name>_orderBy` enum type for the given fields to the schema.
+/// With `change_block`, the enum also allows ordering by `_change_block`, the
+/// block at which entities were changed; that is only possible for queries
+/// of a single entity type and therefore not for interfaces
 fn add_order_by_type(
     schema: &mut Document,
     type_name: &str,
     fields: &[Field],
+    change_block: bool,
 ) -> Result<(), APISchemaError> {
     let type_name = format!("{}_orderBy", type_name);
 
     match schema.get_named_type(&type_name) {
         None => {
+            let mut values = field_enum_values(schema, fields)?;
+            if change_block {
+                values.push(EnumValue {
+                    position: Pos::default(),
+                    description: None,
+                    name: "_change_block".to_string(),
+                    directives: vec![],
+                });
+            }
             let typedef = TypeDefinition::Enum(EnumType {
                 position: Pos::default(),
                 description: None,
                 name: type_name,
                 directives: vec![],
-                values: field_enum_values(schema, fields)?,
+                values,
             });
             let def = Definition::TypeDefinition(typedef);
             schema.definitions.push(def);
@@ -363,10 +376,25 @@ fn field_scalar_filter_input_values(
             "not_in",
             "contains",
             "not_contains",
+            "starts_with",
+            "not_starts_with",
+            "ends_with",
+            "not_ends_with",
         ],
         "BigDecimal" => vec!["", "not", "gt", "lt", "gte", "lte", "in", "not_in"],
-        "ID" => vec!["", "not", "gt", "lt", "gte", "lte", "in", "not_in"],
-        "Int" => vec!["", "not", "gt", "lt", "gte", "lte", "in", "not_in"],
+        "ID" => vec![
+            "",
+            "not",
+            "gt",
+            "lt",
+            "gte",
+            "lte",
+            "in",
+            "not_in",
+            "starts_with",
+            "not_starts_with",
+        ],
+        "Int" | "Int8" => vec!["", "not", "gt", "lt", "gte", "lte", "in", "not_in"],
         "String" => vec![
             "",
             "not",
@@ -891,6 +919,9 @@ mod tests {
         schema
             .get_named_type("BigDecimal")
             .expect("BigDecimal type is missing in API schema");
+        schema
+            .get_named_type("Int8")
+            .expect("Int8 type is missing in API schema");
         schema
             .get_named_type("String")
             .expect("String type is missing in API schema");
@@ -950,7 +981,7 @@ mod tests {
             .iter()
             .map(|value| value.name.as_str())
             .collect();
-        assert_eq!(values, ["id", "name"]);
+        assert_eq!(values, ["id", "name", "_change_block"]);
     }
 
     #[test]
@@ -1073,6 +1104,7 @@ mod tests {
                 "leastFavoriteMeal__name",
                 "mostFavoriteMeals",
                 "recipes",
+                "_change_block",
             ]
         );
 
@@ -1122,7 +1154,8 @@ mod tests {
                 "author__favoritePet",
                 "author__leastFavoritePet",
                 "lovedBy",
-                "ingredients"
+                "ingredients",
+                "_change_block",
             ]
         );
     }
@@ -1184,6 +1217,8 @@ mod tests {
                 "id_lte",
                 "id_in",
                 "id_not_in",
+                "id_starts_with",
+                "id_not_starts_with",
                 "name",
                 "name_not",
                 "name_gt",
@@ -1289,6 +1324,8 @@ mod tests {
                 "id_lte",
                 "id_in",
                 "id_not_in",
+                "id_starts_with",
+                "id_not_starts_with",
                 "name",
                 "name_not",
                 "name_gt",
@@ -1407,6 +1444,8 @@ mod tests {
                 "id_lte",
                 "id_in",
                 "id_not_in",
+                "id_starts_with",
+                "id_not_starts_with",
                 "name",
                 "name_not",
                 "name_gt",
@@ -700,6 +700,7 @@ fn fetch(
         .store
         .find_query_values(query)
         .map(|(values, trace)| {
+            ctx.query.add_entities_fetched(values.len());
             (
                 values.into_iter().map(|entity| entity.into()).collect(),
                 trace,
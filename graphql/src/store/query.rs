@@ -56,6 +56,12 @@ pub(crate) fn build_query<'a>(
         build_order_by(entity, field, schema)?,
         build_order_direction(field)?,
     ) {
+        (Some((attr, _, None)), OrderDirection::Ascending) if attr == "_change_block" => {
+            EntityOrder::ChangeBlockAscending
+        }
+        (Some((attr, _, None)), OrderDirection::Descending) if attr == "_change_block" => {
+            EntityOrder::ChangeBlockDescending
+        }
         (Some((attr, value_type, None)), OrderDirection::Ascending) => {
             EntityOrder::Ascending(attr, value_type)
         }
@@ -214,15 +220,37 @@ fn build_fulltext_filter_from_object(
     )
 }
 
-fn parse_change_block_filter(value: &r::Value) -> Result<BlockNumber, QueryExecutionError> {
-    match value {
-        r::Value::Object(object) => i32::try_from_value(
-            object
-                .get("number_gte")
-                .ok_or(QueryExecutionError::InvalidFilterError)?,
-        )
-        .map_err(|_| QueryExecutionError::InvalidFilterError),
-        _ => Err(QueryExecutionError::InvalidFilterError),
+/// Parses the `_change_block` filter into filters on the block at which
+/// the current version of an entity was written
+fn parse_change_block_filter(value: &r::Value) -> Result<EntityFilter, QueryExecutionError> {
+    let object = match value {
+        r::Value::Object(object) => object,
+        _ => return Err(QueryExecutionError::InvalidFilterError),
+    };
+    let bound = |name: &str| match object.get(name) {
+        None | Some(r::Value::Null) => Ok(None),
+        Some(value) => i32::try_from_value(value)
+            .map(Some)
+            .map_err(|_| QueryExecutionError::InvalidFilterError),
+    };
+
+    let mut filters = Vec::new();
+    if let Some(block) = bound("number_gte")? {
+        filters.push(EntityFilter::ChangeBlockGte(block));
+    }
+    if let Some(block) = bound("number_gt")? {
+        filters.push(EntityFilter::ChangeBlockGte(block.saturating_add(1)));
+    }
+    if let Some(block) = bound("number_lt")? {
+        filters.push(EntityFilter::ChangeBlockLt(block));
+    }
+    if let Some(block) = bound("number_lte")? {
+        filters.push(EntityFilter::ChangeBlockLt(block.saturating_add(1)));
+    }
+    match filters.len() {
+        0 => Err(QueryExecutionError::InvalidFilterError),
+        1 => Ok(filters.remove(0)),
+        _ => Ok(EntityFilter::And(filters)),
     }
 }
 
@@ -320,10 +348,7 @@ fn build_filter_from_object(
             // Special handling for _change_block input filter since its not a
             // standard entity filter that is based on entity structure/fields
             if key == "_change_block" {
-                return match parse_change_block_filter(value) {
-                    Ok(block_number) => Ok(EntityFilter::ChangeBlockGte(block_number)),
-                    Err(e) => Err(e),
-                };
+                return parse_change_block_filter(value);
             }
             use self::sast::FilterOp::*;
             let (field_name, op) = sast::parse_field_as_filter(key);
@@ -547,6 +572,15 @@ fn build_order_by(
 ) -> Result<Option<(String, ValueType, Option<OrderByChild>)>, QueryExecutionError> {
     match field.argument_value("orderBy") {
         Some(r::Value::Enum(name)) => match parse_order_by(name)? {
+            // Ordering by the block at which entities were changed is only
+            // possible for queries of a single entity type
+            OrderByValue::Direct(name) if name == "_change_block" => match entity.is_interface() {
+                true => Err(QueryExecutionError::OrderByNotSupportedError(
+                    entity.name().to_owned(),
+                    name,
+                )),
+                false => Ok(Some((name, ValueType::Int, None))),
+            },
             OrderByValue::Direct(name) => {
                 let field = sast::get_field(entity, name.as_str()).ok_or_else(|| {
                     QueryExecutionError::EntityFieldError(entity.name().to_owned(), name.clone())
@@ -1209,4 +1243,40 @@ mod tests {
             Some(EntityFilter::And(vec![EntityFilter::ChangeBlockGte(10)]))
         )
     }
+
+    #[test]
+    fn build_query_yields_block_change_range_filter() {
+        let schema = build_default_schema();
+        let query_field = default_field_with(
+            "where",
+            r::Value::Object(Object::from_iter(vec![(
+                "_change_block".to_string(),
+                r::Value::Object(Object::from_iter(vec![
+                    ("number_gt".to_string(), r::Value::Int(10)),
+                    ("number_lte".to_string(), r::Value::Int(20)),
+                ])),
+            )])),
+        );
+        assert_eq!(
+            build_query(
+                &ObjectType {
+                    fields: vec![field("name", Type::NamedType("string".to_owned()))],
+                    ..default_object()
+                },
+                BLOCK_NUMBER_MAX,
+                &query_field,
+                &BTreeMap::new(),
+                std::u32::MAX,
+                std::u32::MAX,
+                Default::default(),
+                &schema
+            )
+            .unwrap()
+            .filter,
+            Some(EntityFilter::And(vec![EntityFilter::And(vec![
+                EntityFilter::ChangeBlockGte(11),
+                EntityFilter::ChangeBlockLt(21),
+            ])]))
+        )
+    }
 }
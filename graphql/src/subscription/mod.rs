@@ -98,6 +98,7 @@ fn create_source_event_stream(
         max_skip: options.max_skip,
         cache_status: Default::default(),
         trace: ENV_VARS.log_sql_timing(),
+        result_size: ResultSize::new(None),
     };
 
     let subscription_type = ctx
@@ -224,6 +225,7 @@ async fn execute_subscription_event(
         max_skip,
         cache_status: Default::default(),
         trace: ENV_VARS.log_sql_timing(),
+        result_size: ResultSize::new(None),
     });
 
     let subscription_type = match ctx.query.schema.subscription_type.as_ref() {
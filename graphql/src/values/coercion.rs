@@ -52,6 +52,11 @@ impl MaybeCoercible<ScalarType> for q::Value {
             ("BigInt", q::Value::Int(n)) => Ok(r::Value::String(
                 n.as_i64().ok_or(q::Value::Int(n))?.to_string(),
             )),
+            ("Int8", q::Value::Int(n)) => Ok(r::Value::Int(n.as_i64().ok_or(q::Value::Int(n))?)),
+            ("Int8", q::Value::String(s)) => match s.parse::<i64>() {
+                Ok(n) => Ok(r::Value::Int(n)),
+                Err(_) => Err(q::Value::String(s)),
+            },
             (_, v) => Err(v),
         }
     }
@@ -572,6 +572,7 @@ async fn introspection_query(schema: Schema, query: &str) -> QueryResult {
         max_skip: std::u32::MAX,
         load_manager: LOAD_MANAGER.clone(),
         trace: false,
+        max_result_bytes: None,
     };
 
     let schema = Arc::new(ApiSchema::from_api_schema(schema).unwrap());
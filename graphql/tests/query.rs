@@ -20,7 +20,7 @@ use graph::{
     data::graphql::{object, object_value},
     data::subgraph::schema::SubgraphError,
     data::{
-        query::{QueryResults, QueryTarget},
+        query::{QueryResults, QueryTarget, Truncation},
         subgraph::SubgraphFeature,
     },
     prelude::{
@@ -35,8 +35,9 @@ use graph::{
 use graph_graphql::{prelude::*, subscription::execute_subscription};
 use test_store::{
     deployment_state, execute_subgraph_query, execute_subgraph_query_with_deadline,
-    graphql_metrics, revert_block, run_test_sequentially, transact_errors, Store, BLOCK_ONE,
-    GENESIS_PTR, LOAD_MANAGER, LOGGER, METRICS_REGISTRY, STORE, SUBSCRIPTION_MANAGER,
+    execute_subgraph_query_with_max_result_bytes, graphql_metrics, revert_block,
+    run_test_sequentially, transact_errors, Store, BLOCK_ONE, GENESIS_PTR, LOAD_MANAGER, LOGGER,
+    METRICS_REGISTRY, STORE, SUBSCRIPTION_MANAGER,
 };
 
 const NETWORK_NAME: &str = "fake_network";
@@ -130,6 +131,7 @@ async fn setup(
             data_sources: vec![],
             graft: None,
             templates: vec![],
+            handler_limits: Default::default(),
             chain: PhantomData,
         };
 
@@ -1641,6 +1643,64 @@ fn instant_timeout() {
     })
 }
 
+#[test]
+fn max_result_bytes() {
+    run_test_sequentially(|store| async move {
+        let deployment = setup_readonly(store.as_ref()).await;
+        let run = |max_result_bytes| {
+            let query = Query::new(
+                graphql_parser::parse_query(
+                    "query { musicians(first: 100, orderBy: id) { id name } }",
+                )
+                .unwrap()
+                .into_static(),
+                None,
+                false,
+            );
+            let target = QueryTarget::Deployment(deployment.hash.clone(), Default::default());
+            execute_subgraph_query_with_max_result_bytes(query, target, max_result_bytes)
+        };
+
+        // The first two musicians take up 70 bytes, and the third one
+        // does not fit anymore
+        let result = first_result(run(80).await).await;
+        assert_eq!(
+            &[Truncation {
+                path: vec![serde_json::json!("musicians")],
+                returned: 2,
+                cursor: Some("m2".to_owned()),
+            }],
+            result.truncations()
+        );
+        let exp = object! {
+            musicians: vec![
+                object! { id: "m1", name: "John" },
+                object! { id: "m2", name: "Lisa" },
+            ]
+        };
+        let data = extract_data!(result).unwrap();
+        assert_eq!(data, exp);
+
+        // Even if not even the first musician fits, it is returned
+        let result = first_result(run(30).await).await;
+        assert_eq!(
+            &[Truncation {
+                path: vec![serde_json::json!("musicians")],
+                returned: 0,
+                cursor: None,
+            }],
+            result.truncations()
+        );
+        let exp = object! { musicians: vec![object! { id: "m1", name: "John" }] };
+        let data = extract_data!(result).unwrap();
+        assert_eq!(data, exp);
+
+        // Everything fits
+        let result = first_result(run(1000).await).await;
+        assert!(!result.is_truncated());
+    })
+}
+
 #[test]
 fn variable_defaults() {
     const QUERY: &str = "
@@ -2285,6 +2345,47 @@ fn query_detects_reorg() {
     })
 }
 
+#[test]
+fn query_fails_in_maintenance() {
+    const QUERY: &str = "query { musician(id: \"m1\") { id } }";
+
+    run_test_sequentially(|store| async move {
+        let deployment = setup(
+            store.as_ref(),
+            "graphqlQueryFailsInMaintenance",
+            BTreeSet::new(),
+            IdType::String,
+        )
+        .await;
+
+        store
+            .subgraph_store()
+            .set_maintenance(&deployment, Some("moving to a new shard".to_string()))
+            .await
+            .unwrap();
+        let result = execute_query(&deployment, QUERY).await;
+        assert!(result.in_maintenance());
+        match &result.to_result().unwrap_err()[0] {
+            QueryError::ExecutionError(QueryExecutionError::DeploymentInMaintenance(
+                _,
+                message,
+            )) => assert_eq!("moving to a new shard", message),
+            e => panic!("unexpected error for deployment in maintenance: {}", e),
+        }
+
+        store
+            .subgraph_store()
+            .set_maintenance(&deployment, None)
+            .await
+            .unwrap();
+        let result = execute_query(&deployment, QUERY).await;
+        assert_eq!(
+            extract_data!(result),
+            Some(object!(musician: object!(id: "m1")))
+        );
+    })
+}
+
 #[test]
 fn can_query_meta() {
     // metadata for the latest block (block 1)
@@ -4,16 +4,17 @@ use git_testament::{git_testament, render_testament};
 use graph::bail;
 use graph::endpoint::EndpointMetrics;
 use graph::log::logger_with_levels;
-use graph::prelude::{MetricsRegistry, BLOCK_NUMBER_MAX};
-use graph::{data::graphql::effort::LoadManager, prelude::chrono, prometheus::Registry};
+use graph::prelude::{BlockNumber, MetricsRegistry, BLOCK_NUMBER_MAX};
 use graph::{
+    components::subgraph::Notifier,
     prelude::{
         anyhow::{self, Context as AnyhowContextTrait},
-        info, tokio, Logger, NodeId,
+        info, tokio, Logger, NodeId, ENV_VARS,
     },
     url::Url,
 };
-use graph_chain_ethereum::{EthereumAdapter, EthereumNetworks};
+use graph::{data::graphql::effort::LoadManager, prelude::chrono, prometheus::Registry};
+use graph_chain_ethereum::{EthereumAdapter, EthereumNetworks, ProviderEthRpcMetrics};
 use graph_graphql::prelude::GraphQlRunner;
 use graph_node::config::{self, Config as Cfg};
 use graph_node::manager::color::Terminal;
@@ -27,8 +28,8 @@ use graph_node::{
 use graph_store_postgres::connection_pool::PoolCoordinator;
 use graph_store_postgres::ChainStore;
 use graph_store_postgres::{
-    connection_pool::ConnectionPool, BlockStore, NotificationSender, Shard, Store, SubgraphStore,
-    SubscriptionManager, PRIMARY_SHARD,
+    connection_pool::ConnectionPool, BlockStore, DeploymentProperties, NotificationSender, Shard,
+    Store, SubgraphStore, SubscriptionManager, PRIMARY_SHARD,
 };
 use lazy_static::lazy_static;
 use std::{collections::HashMap, env, num::ParseIntError, sync::Arc, time::Duration};
@@ -158,6 +159,106 @@ pub enum Command {
         /// The deployment (see `help info`)
         deployment: DeploymentSearch,
     },
+    /// Pause indexing a deployment without unassigning it
+    ///
+    /// The deployment can still be queried while it is paused
+    Pause {
+        /// The deployment (see `help info`)
+        deployment: DeploymentSearch,
+    },
+    /// Resume indexing a paused deployment
+    Resume {
+        /// The deployment (see `help info`)
+        deployment: DeploymentSearch,
+    },
+    /// Put a deployment into maintenance mode or take it out of it
+    ///
+    /// Queries against a deployment in maintenance mode fail with an error
+    /// and HTTP status 503. The deployment continues to be indexed
+    #[clap(subcommand)]
+    Maintenance(MaintenanceCommand),
+    /// Move deployments between nodes to even out their load
+    ///
+    /// Index nodes periodically report how much time each of their
+    /// deployments spends processing blocks. Deployments are moved from
+    /// the busiest to the least busy of the given nodes until moving
+    /// another deployment would not even out the load any further.
+    /// Paused deployments are not moved.
+    Rebalance {
+        /// Only show which deployments would be moved
+        #[clap(long)]
+        dry_run: bool,
+        /// The nodes between which deployments should be balanced
+        #[clap(required = true, min_values = 2)]
+        nodes: Vec<String>,
+    },
+    /// Set how a deployment is retried after non-deterministic errors
+    ///
+    /// Retries back off exponentially from the base delay to the ceiling.
+    /// Options that are not given revert to their defaults, so that running
+    /// the command without options resets the policy. The new policy takes
+    /// effect when the deployment is next started, for example after
+    /// `graphman pause` and `graphman resume`
+    RetryPolicy {
+        /// Give up after this many retries and leave the deployment failed.
+        /// By default, the deployment is retried forever
+        #[clap(long)]
+        max_attempts: Option<u32>,
+        /// The delay in seconds before the first retry. Defaults to 120
+        #[clap(long, parse(try_from_str = parse_duration_in_secs))]
+        base: Option<Duration>,
+        /// The longest delay in seconds between retries. Defaults to
+        /// `GRAPH_SUBGRAPH_ERROR_RETRY_CEIL_SECS`
+        #[clap(long, parse(try_from_str = parse_duration_in_secs))]
+        ceiling: Option<Duration>,
+        /// The deployment (see `help info`)
+        deployment: DeploymentSearch,
+    },
+    /// Limit how many data sources a deployment may create from templates
+    ///
+    /// A deployment that tries to create more data sources than that fails
+    /// with a deterministic error. Running the command without `--max`
+    /// removes the limit. The new limit takes effect when the deployment is
+    /// next started, for example after `graphman pause` and `graphman
+    /// resume`
+    DataSourceLimit {
+        /// The largest number of data sources the deployment may create
+        #[clap(long)]
+        max: Option<usize>,
+        /// The deployment (see `help info`)
+        deployment: DeploymentSearch,
+    },
+    /// Limit how many blocks per second a deployment processes
+    ///
+    /// The limit only applies while the deployment is catching up with the
+    /// chain, so that a deployment that is indexing its history can not
+    /// starve deployments that follow the chain head. Running the command
+    /// without `--blocks-per-second` removes the limit. The new limit takes
+    /// effect when the deployment is next started, for example after
+    /// `graphman pause` and `graphman resume`
+    RateLimit {
+        /// The largest number of blocks the deployment may process per second
+        #[clap(long)]
+        blocks_per_second: Option<u32>,
+        /// The deployment (see `help info`)
+        deployment: DeploymentSearch,
+    },
+    /// Set the size of the entity cache of a deployment
+    ///
+    /// The entity cache keeps entities that were loaded from the store
+    /// between blocks. Entities are weighed by their estimated size in
+    /// memory, and the least frequently used ones are evicted once the cache
+    /// is bigger than this size. Running the command without `--size`
+    /// reverts to `GRAPH_ENTITY_CACHE_SIZE`. The new size takes effect when
+    /// the deployment is next started, for example after `graphman pause`
+    /// and `graphman resume`
+    EntityCacheSize {
+        /// The size of the cache in kilobytes
+        #[clap(long)]
+        size: Option<usize>,
+        /// The deployment (see `help info`)
+        deployment: DeploymentSearch,
+    },
     /// Rewind a subgraph to a specific block
     Rewind {
         /// Force rewinding even if the block hash is not found in the local
@@ -185,6 +286,11 @@ pub enum Command {
     /// and testing purposes) -- WARNING: WILL RUN MIGRATIONS ON THE DB, DO
     /// NOT USE IN PRODUCTION
     ///
+    /// Once the stop block is reached, print the POI and the number of
+    /// entities of each type at the stop block. If the subgraph fails with
+    /// a deterministic error before that, print them for the last block
+    /// that was processed and exit with an error.
+    ///
     /// Also worth noting that the deployed subgraph will be removed at the
     /// end.
     Run {
@@ -195,11 +301,23 @@ pub enum Command {
         subgraph: String,
 
         /// Highest block number to process before stopping (inclusive)
+        #[clap(long)]
         stop_block: i32,
 
         /// Prometheus push gateway endpoint.
         prometheus_host: Option<String>,
     },
+    /// Validate a subgraph manifest without deploying it
+    ///
+    /// Fetch the manifest and the files it links to from IPFS and check
+    /// them the same way that deploying the subgraph would: the schema,
+    /// the ABIs, the mappings, the declared features, the spec version,
+    /// and whether the manifest's network is configured. Nothing is
+    /// written to the database
+    Validate {
+        /// The IPFS hash of the manifest
+        deployment: String,
+    },
     /// Check and interrogate the configuration
     ///
     /// Print information about a configuration file without
@@ -268,12 +386,24 @@ pub enum Command {
         /// Prune only this once
         #[clap(long, short)]
         once: bool,
+        /// Only report how much space pruning would reclaim for each table
+        /// without changing anything
+        #[clap(long)]
+        dry_run: bool,
     },
 
     /// General database management
     #[clap(subcommand)]
     Database(DatabaseCommand),
 
+    /// Compare proofs of indexing with other indexers
+    #[clap(subcommand)]
+    Poi(PoiCommand),
+
+    /// Manage webhooks that are called when entities change
+    #[clap(subcommand)]
+    Webhook(WebhookCommand),
+
     /// Delete a deployment and all it's indexed data
     ///
     /// The deployment can be specified as either a subgraph name, an IPFS
@@ -348,6 +478,15 @@ pub enum ConfigCommand {
         name: String,
         /// The network the subgraph indexes
         network: String,
+        /// A feature that the manifest declares. Can be given several times
+        #[clap(long = "feature")]
+        features: Vec<String>,
+        /// The number of data sources in the manifest
+        #[clap(long, default_value = "1")]
+        data_sources: usize,
+        /// The number of entity types in the schema
+        #[clap(long, default_value = "1")]
+        entities: usize,
     },
     /// Information about the size of database pools
     Pools {
@@ -470,6 +609,59 @@ pub enum ChainCommand {
         force: bool,
     },
 
+    /// Rewind the chain head to an earlier block
+    ///
+    /// Use this when a provider served blocks from a bad fork. All blocks
+    /// after the given block are removed from the block cache, and all
+    /// deployments on the chain that have indexed past it are paused,
+    /// rewound to it, and resumed. The block must be in the block cache.
+    /// If a deployment was pruned past the block, nothing is changed.
+    /// Run with `--dry-run` first to see which deployments are affected
+    Rewind {
+        /// Only list the affected deployments, but do not change anything
+        #[clap(long)]
+        dry_run: bool,
+        /// Skips confirmation prompt
+        #[clap(long, short)]
+        force: bool,
+        /// Sleep for this many seconds after pausing subgraphs
+        #[clap(
+            long,
+            short,
+            default_value = "10",
+            parse(try_from_str = parse_duration_in_secs)
+        )]
+        sleep: Duration,
+        /// The hash of the block to rewind to. Only needed if the block
+        /// cache has several blocks with the given number
+        #[clap(long)]
+        block_hash: Option<String>,
+        /// Chain name (must be an existing chain, see 'chain list')
+        #[clap(empty_values = false)]
+        chain_name: String,
+        /// The number of the block to rewind to
+        block_number: i32,
+    },
+
+    /// Fill the block cache with blocks from a Firehose provider
+    ///
+    /// Stores the blocks from `--from` to `--to` with all their data, so
+    /// that features that need blocks from the block cache work even if
+    /// the JSON-RPC providers of the chain have pruned them. Blocks that
+    /// are already in the cache are replaced. The chain must have a
+    /// Firehose provider in the configuration
+    Backfill {
+        /// Chain name (must be an existing chain, see 'chain list')
+        #[clap(empty_values = false)]
+        chain_name: String,
+        /// The first block to store
+        #[clap(long)]
+        from: i32,
+        /// The last block to store
+        #[clap(long)]
+        to: i32,
+    },
+
     /// Execute operations on call cache.
     CallCache {
         #[clap(subcommand)]
@@ -574,6 +766,27 @@ pub enum StatsCommand {
     },
 }
 
+#[derive(Clone, Debug, Subcommand)]
+pub enum MaintenanceCommand {
+    /// Put a deployment into maintenance mode
+    On {
+        /// The message to return to queries
+        #[clap(
+            long,
+            short,
+            default_value = "the deployment is undergoing maintenance, please try again later"
+        )]
+        message: String,
+        /// The deployment (see `help info`)
+        deployment: DeploymentSearch,
+    },
+    /// Take a deployment out of maintenance mode
+    Off {
+        /// The deployment (see `help info`)
+        deployment: DeploymentSearch,
+    },
+}
+
 #[derive(Clone, Debug, Subcommand)]
 pub enum IndexCommand {
     /// Creates a new database index.
@@ -646,12 +859,137 @@ pub enum IndexCommand {
         #[clap(empty_values = false)]
         index_name: String,
     },
+    /// Lists all indexes of a deployment with their size and usage
+    ///
+    /// The number of scans is how often the database used an index since
+    /// its statistics were last reset. Scans on read replicas are not
+    /// counted.
+    Usage {
+        /// Only list unused indexes that `drop-unused` would drop
+        #[clap(long)]
+        unused: bool,
+        /// The deployment (see `help info`).
+        #[clap(empty_values = false)]
+        deployment: DeploymentSearch,
+    },
+    /// Drops the indexes of a deployment that have never been used, concurrently
+    ///
+    /// Only attribute indexes and indexes created with `graphman index
+    /// create` that were never scanned are dropped; other indexes are
+    /// needed by graph-node. Since scans on read replicas are not counted,
+    /// do not use this for deployments that are queried on replicas.
+    /// Indexing continues while the indexes are dropped.
+    DropUnused {
+        /// Only list the indexes that would be dropped
+        #[clap(long)]
+        dry_run: bool,
+        /// The deployment (see `help info`).
+        #[clap(empty_values = false)]
+        deployment: DeploymentSearch,
+    },
+}
+
+#[derive(Clone, Debug, Subcommand)]
+pub enum PoiCommand {
+    /// Compare the proofs of indexing of a deployment with another indexer
+    ///
+    /// Fetches the public proofs of indexing for the deployment from the
+    /// index node status API of the other indexer and compares them with
+    /// the ones of this installation. If they differ at BLOCK, searches
+    /// for the first block at which they differ.
+    ///
+    /// The deployment can be specified as either a subgraph name, an IPFS
+    /// hash `Qm..`, or the database namespace `sgdNNN`.
+    Diff {
+        /// The deployment to compare
+        deployment: DeploymentSearch,
+        /// The URL of the other indexer's index node status API, e.g.,
+        /// `https://indexer.example.com/status`
+        indexer: String,
+        /// The block at which to compare proofs of indexing
+        block: BlockNumber,
+        /// Search for the first divergent block starting at this block
+        /// instead of the earliest block of the deployment
+        #[clap(long)]
+        start: Option<BlockNumber>,
+    },
+    /// Compare the proofs of indexing of two copies of a deployment
+    ///
+    /// This is meant to check how well a deployment handles reorgs: index
+    /// one copy of the deployment with synthetic reverts by listing it in
+    /// `GRAPH_CHAOS_REVERT_DEPLOYMENTS` and compare it with a copy that was
+    /// indexed normally. If their proofs of indexing differ at BLOCK,
+    /// searches for the first block at which they differ.
+    ///
+    /// Since the copies have the same IPFS hash, they need to be specified
+    /// by their database namespace `sgdNNN`.
+    Compare {
+        /// The copy that was indexed with synthetic reverts
+        deployment: DeploymentSearch,
+        /// The copy that was indexed without them
+        control: DeploymentSearch,
+        /// The block at which to compare proofs of indexing
+        block: BlockNumber,
+        /// Search for the first divergent block starting at this block
+        /// instead of the earliest block of the deployment
+        #[clap(long)]
+        start: Option<BlockNumber>,
+    },
+}
+
+#[derive(Clone, Debug, Subcommand)]
+pub enum WebhookCommand {
+    /// Call a URL whenever entities of certain types change
+    ///
+    /// The changes of each block are posted as one JSON document. Each
+    /// request is signed with the secret of the webhook, and requests are
+    /// retried until the URL responds with a 2xx status.
+    Add {
+        /// The deployment (see `help info`)
+        deployment: DeploymentSearch,
+        /// The URL to post changes to
+        url: String,
+        /// The entity types whose changes to post
+        #[clap(min_values = 1, required = true)]
+        entities: Vec<String>,
+        /// The secret to sign requests with. A random secret is generated
+        /// if none is given
+        #[clap(long)]
+        secret: Option<String>,
+    },
+    /// List the webhooks of a deployment
+    List {
+        /// The deployment (see `help info`)
+        deployment: DeploymentSearch,
+    },
+    /// Remove a webhook and the changes it has not received yet
+    Remove {
+        /// The deployment (see `help info`)
+        deployment: DeploymentSearch,
+        /// The id of the webhook, as shown by `list`
+        id: i32,
+    },
 }
 
 #[derive(Clone, Debug, Subcommand)]
 pub enum DatabaseCommand {
     /// Apply any pending migrations to the database schema in all shards
-    Migrate,
+    ///
+    /// With `--dry-run` or `--output`, nothing is applied. Instead, the SQL
+    /// of the pending migrations in each shard is printed or written to
+    /// `<output>/<shard>.sql` so that it can be reviewed and applied out of
+    /// band, for example with `psql -v ON_ERROR_STOP=1 -f <shard>.sql`.
+    /// Applying the files records the migrations as done. Afterwards, run
+    /// `graphman database remap` to update the mappings between shards
+    Migrate {
+        /// Print the SQL of pending migrations without applying them
+        #[clap(long)]
+        dry_run: bool,
+        /// Write the SQL of pending migrations for each shard to a file in
+        /// this directory without applying them
+        #[clap(long, short, value_name = "DIR")]
+        output: Option<String>,
+    },
     /// Refresh the mapping of tables into different shards
     ///
     /// This command rebuilds the mappings of tables from one shard into all
@@ -782,6 +1120,10 @@ impl Context {
         Arc::new(NotificationSender::new(self.registry.clone()))
     }
 
+    fn notifier(&self) -> Result<Notifier, anyhow::Error> {
+        Notifier::from_env(&self.logger, self.node_id.clone(), &ENV_VARS)
+    }
+
     fn primary_pool(self) -> ConnectionPool {
         let primary = self.config.primary_store();
         let coord = Arc::new(PoolCoordinator::new(Arc::new(vec![])));
@@ -840,10 +1182,11 @@ impl Context {
     }
 
     fn store_and_pools(self) -> (Arc<Store>, HashMap<Shard, ConnectionPool>) {
-        let (subgraph_store, pools, _) = StoreBuilder::make_subgraph_store_and_pools(
+        let (subgraph_store, pools, _, _) = StoreBuilder::make_subgraph_store_and_pools(
             &self.logger,
             &self.node_id,
             &self.config,
+            Arc::new(self.config.deployment.clone()),
             self.fork_base,
             self.registry.clone(),
         );
@@ -898,8 +1241,9 @@ impl Context {
     async fn ethereum_networks(&self) -> anyhow::Result<EthereumNetworks> {
         let logger = self.logger.clone();
         let registry = self.metrics_registry();
+        let eth_rpc_metrics = Arc::new(ProviderEthRpcMetrics::new(registry));
         let metrics = Arc::new(EndpointMetrics::mock());
-        create_all_ethereum_networks(logger, registry, &self.config, metrics).await
+        create_all_ethereum_networks(logger, eth_rpc_metrics, &self.config, metrics).await
     }
 
     fn chain_store(self, chain_name: &str) -> anyhow::Result<Arc<ChainStore>> {
@@ -1052,8 +1396,19 @@ async fn main() -> anyhow::Result<()> {
             use ConfigCommand::*;
 
             match cmd {
-                Place { name, network } => {
-                    commands::config::place(&ctx.config.deployment, &name, &network)
+                Place {
+                    name,
+                    network,
+                    features,
+                    data_sources,
+                    entities,
+                } => {
+                    let properties = DeploymentProperties {
+                        features,
+                        data_sources,
+                        entity_types: entities,
+                    };
+                    commands::config::place(&ctx.config.deployment, &name, &network, properties)
                 }
                 Check { print } => commands::config::check(&ctx.config, print),
                 Pools { nodes, shard } => commands::config::pools(&ctx.config, nodes, shard),
@@ -1071,9 +1426,85 @@ async fn main() -> anyhow::Result<()> {
             let sender = ctx.notification_sender();
             commands::assign::unassign(ctx.primary_pool(), &sender, &deployment).await
         }
+        Pause { deployment } => {
+            let sender = ctx.notification_sender();
+            commands::assign::pause(ctx.primary_pool(), &sender, &deployment)
+        }
+        Resume { deployment } => {
+            let sender = ctx.notification_sender();
+            commands::assign::resume(ctx.primary_pool(), &sender, &deployment)
+        }
         Reassign { deployment, node } => {
             let sender = ctx.notification_sender();
-            commands::assign::reassign(ctx.primary_pool(), &sender, &deployment, node)
+            let notifier = ctx.notifier()?;
+            commands::assign::reassign(ctx.primary_pool(), &sender, &notifier, &deployment, node)
+                .await
+        }
+        Maintenance(cmd) => {
+            use MaintenanceCommand::*;
+            let (store, primary_pool) = ctx.store_and_primary();
+            let subgraph_store = store.subgraph_store();
+            match cmd {
+                On {
+                    message,
+                    deployment,
+                } => {
+                    commands::maintenance::on(subgraph_store, primary_pool, &deployment, message)
+                        .await
+                }
+                Off { deployment } => {
+                    commands::maintenance::off(subgraph_store, primary_pool, &deployment).await
+                }
+            }
+        }
+        Rebalance { dry_run, nodes } => {
+            let sender = ctx.notification_sender();
+            let notifier = ctx.notifier()?;
+            commands::assign::rebalance(ctx.primary_pool(), &sender, &notifier, nodes, dry_run)
+                .await
+        }
+        RetryPolicy {
+            max_attempts,
+            base,
+            ceiling,
+            deployment,
+        } => {
+            let (store, primary_pool) = ctx.store_and_primary();
+            let policy = graph::components::store::RetryPolicy {
+                max_attempts,
+                base,
+                ceiling,
+            };
+            commands::retry_policy::set(store.subgraph_store(), primary_pool, &deployment, policy)
+                .await
+        }
+        DataSourceLimit { max, deployment } => {
+            let (store, primary_pool) = ctx.store_and_primary();
+            commands::data_source_limit::set(store.subgraph_store(), primary_pool, &deployment, max)
+                .await
+        }
+        RateLimit {
+            blocks_per_second,
+            deployment,
+        } => {
+            let (store, primary_pool) = ctx.store_and_primary();
+            commands::rate_limit::set(
+                store.subgraph_store(),
+                primary_pool,
+                &deployment,
+                blocks_per_second,
+            )
+            .await
+        }
+        EntityCacheSize { size, deployment } => {
+            let (store, primary_pool) = ctx.store_and_primary();
+            commands::entity_cache::set_size(
+                store.subgraph_store(),
+                primary_pool,
+                &deployment,
+                size,
+            )
+            .await
         }
         Rewind {
             force,
@@ -1094,6 +1525,13 @@ async fn main() -> anyhow::Result<()> {
             )
             .await
         }
+        Validate { deployment } => {
+            let logger = ctx.logger.clone();
+            let config = ctx.config();
+            let ipfs_url = ctx.ipfs_url.clone();
+            let store = ctx.store();
+            commands::validate::run(logger, store, &config, ipfs_url, deployment).await
+        }
         Run {
             network_name,
             subgraph,
@@ -1236,6 +1674,45 @@ async fn main() -> anyhow::Result<()> {
                     let chain_store = ctx.chain_store(&chain_name)?;
                     truncate(chain_store, force)
                 }
+                Rewind {
+                    dry_run,
+                    force,
+                    sleep,
+                    block_hash,
+                    chain_name,
+                    block_number,
+                } => {
+                    use graph::components::store::BlockStore;
+                    let (store, primary) = ctx.store_and_primary();
+                    let chain_store =
+                        store
+                            .block_store()
+                            .chain_store(&chain_name)
+                            .ok_or_else(|| {
+                                anyhow::anyhow!("Could not find a network named '{}'", chain_name)
+                            })?;
+                    commands::chain::rewind(
+                        primary,
+                        store,
+                        chain_store,
+                        block_number,
+                        block_hash,
+                        dry_run,
+                        force,
+                        sleep,
+                    )
+                    .await
+                }
+                Backfill {
+                    chain_name,
+                    from,
+                    to,
+                } => {
+                    let logger = ctx.logger.clone();
+                    let config = ctx.config.clone();
+                    let chain_store = ctx.chain_store(&chain_name)?;
+                    commands::chain::backfill(logger, &config, chain_store, from, to).await
+                }
                 CallCache { method, chain_name } => {
                     match method {
                         CallCacheCommand::Remove {
@@ -1367,16 +1844,32 @@ async fn main() -> anyhow::Result<()> {
                     commands::index::drop(subgraph_store, primary_pool, deployment, &index_name)
                         .await
                 }
+                Usage { unused, deployment } => {
+                    commands::index::usage(subgraph_store, primary_pool, deployment, unused).await
+                }
+                DropUnused {
+                    dry_run,
+                    deployment,
+                } => {
+                    commands::index::drop_unused(subgraph_store, primary_pool, deployment, dry_run)
+                        .await
+                }
             }
         }
         Database(cmd) => {
             match cmd {
-                DatabaseCommand::Migrate => {
+                DatabaseCommand::Migrate {
+                    dry_run: false,
+                    output: None,
+                } => {
                     /* creating the store builder runs migrations */
                     let _store_builder = ctx.store_builder().await;
                     println!("All database migrations have been applied");
                     Ok(())
                 }
+                DatabaseCommand::Migrate { output, .. } => {
+                    commands::database::pending_migrations(ctx.pools(), output)
+                }
                 DatabaseCommand::Remap {
                     source,
                     dest,
@@ -1387,12 +1880,63 @@ async fn main() -> anyhow::Result<()> {
                 }
             }
         }
+        Poi(cmd) => match cmd {
+            PoiCommand::Diff {
+                deployment,
+                indexer,
+                block,
+                start,
+            } => {
+                let (store, primary_pool) = ctx.store_and_primary();
+                commands::poi::diff(store, primary_pool, &deployment, indexer, block, start).await
+            }
+            PoiCommand::Compare {
+                deployment,
+                control,
+                block,
+                start,
+            } => {
+                let (store, primary_pool) = ctx.store_and_primary();
+                commands::poi::compare(store, primary_pool, &deployment, &control, block, start)
+                    .await
+            }
+        },
+        Webhook(cmd) => {
+            use WebhookCommand::*;
+            let (store, primary_pool) = ctx.store_and_primary();
+            let subgraph_store = store.subgraph_store();
+            match cmd {
+                Add {
+                    deployment,
+                    url,
+                    entities,
+                    secret,
+                } => {
+                    commands::webhook::add(
+                        subgraph_store,
+                        primary_pool,
+                        &deployment,
+                        url,
+                        entities,
+                        secret,
+                    )
+                    .await
+                }
+                List { deployment } => {
+                    commands::webhook::list(subgraph_store, primary_pool, &deployment).await
+                }
+                Remove { deployment, id } => {
+                    commands::webhook::remove(subgraph_store, primary_pool, &deployment, id).await
+                }
+            }
+        }
         Prune {
             deployment,
             history,
             rebuild_threshold,
             delete_threshold,
             once,
+            dry_run,
         } => {
             let (store, primary_pool) = ctx.store_and_primary();
             commands::prune::run(
@@ -1403,6 +1947,7 @@ async fn main() -> anyhow::Result<()> {
                 rebuild_threshold,
                 delete_threshold,
                 once,
+                dry_run,
             )
             .await
         }
@@ -1,15 +1,14 @@
 use crate::config::{Config, ProviderDetails};
-use ethereum::{EthereumNetworks, ProviderEthRpcMetrics};
+use ethereum::{EthereumNetworkAdapters, EthereumNetworks, ProviderEthRpcMetrics};
 use futures::future::{join_all, try_join_all};
 use futures::TryFutureExt;
 use graph::anyhow::{bail, Error};
 use graph::blockchain::{Block as BlockchainBlock, BlockchainKind, ChainIdentifier};
 use graph::cheap_clone::CheapClone;
-use graph::endpoint::EndpointMetrics;
-use graph::firehose::{FirehoseEndpoint, FirehoseNetworks, SubgraphLimit};
+use graph::endpoint::{ConnectionType, EndpointMetrics, ProviderInfo, ProviderInventory};
+use graph::firehose::{FirehoseEndpoint, FirehoseNetworks, SharedStreams, SubgraphLimit};
 use graph::ipfs_client::IpfsClient;
-use graph::prelude::{anyhow, tokio};
-use graph::prelude::{prost, MetricsRegistry};
+use graph::prelude::{anyhow, prost, tokio, ENV_VARS};
 use graph::slog::{debug, error, info, o, Logger};
 use graph::url::Url;
 use graph::util::security::SafeDisplay;
@@ -185,21 +184,28 @@ pub fn create_firehose_networks(
                 // instance will have their own subgraph limit.
                 // eg: pool_size = 3 and sg_limit 2 will result in 3 separate instances
                 // of FirehoseEndpoint and each of those instance can be used in 2 different
-                // SubgraphInstances.
+                // SubgraphInstances. All instances share their block streams so that
+                // deployments at the same block can use one stream.
+                let shared_streams = Arc::new(SharedStreams::new(
+                    ENV_VARS.firehose_shared_stream_buffer_size,
+                ));
                 for _ in 0..firehose.conn_pool_size {
                     parsed_networks.insert(
                         name.to_string(),
-                        Arc::new(FirehoseEndpoint::new(
-                            // This label needs to be the original label so that the metrics
-                            // can be deduped.
-                            &provider.label,
-                            &firehose.url,
-                            firehose.token.clone(),
-                            firehose.filters_enabled(),
-                            firehose.compression_enabled(),
-                            firehose.limit_for(&config.node),
-                            endpoint_metrics.cheap_clone(),
-                        )),
+                        Arc::new(
+                            FirehoseEndpoint::new(
+                                // This label needs to be the original label so that the metrics
+                                // can be deduped.
+                                &provider.label,
+                                &firehose.url,
+                                firehose.token.clone(),
+                                firehose.filters_enabled(),
+                                firehose.compression_enabled(),
+                                firehose.limit_for(&config.node),
+                                endpoint_metrics.cheap_clone(),
+                            )
+                            .with_shared_streams(shared_streams.cheap_clone()),
+                        ),
                     );
                 }
             }
@@ -389,11 +395,10 @@ where
 /// `EthereumAdapter`.
 pub async fn create_all_ethereum_networks(
     logger: Logger,
-    registry: Arc<MetricsRegistry>,
+    eth_rpc_metrics: Arc<ProviderEthRpcMetrics>,
     config: &Config,
     endpoint_metrics: Arc<EndpointMetrics>,
 ) -> anyhow::Result<EthereumNetworks> {
-    let eth_rpc_metrics = Arc::new(ProviderEthRpcMetrics::new(registry));
     let eth_networks_futures = config
         .chains
         .chains
@@ -494,6 +499,87 @@ pub async fn create_ethereum_networks_for_chain(
     Ok(parsed_networks)
 }
 
+/// The providers of all chains on this node. Ethereum RPC providers are
+/// looked up whenever they are listed since reloading the configuration can
+/// change them
+#[derive(Debug)]
+pub struct NodeProviders {
+    eth_networks: HashMap<String, EthereumNetworkAdapters>,
+    firehose: Vec<(String, Arc<FirehoseEndpoint>)>,
+    substreams: Vec<(String, Arc<FirehoseEndpoint>)>,
+}
+
+impl NodeProviders {
+    pub fn new(
+        eth_networks: &EthereumNetworks,
+        firehose_networks: &BTreeMap<BlockchainKind, FirehoseNetworks>,
+        substreams_networks: &BTreeMap<BlockchainKind, FirehoseNetworks>,
+    ) -> Self {
+        let flatten = |networks: &BTreeMap<BlockchainKind, FirehoseNetworks>| {
+            networks
+                .values()
+                .flat_map(|networks| networks.flatten())
+                .collect()
+        };
+        NodeProviders {
+            eth_networks: eth_networks.networks.clone(),
+            firehose: flatten(firehose_networks),
+            substreams: flatten(substreams_networks),
+        }
+    }
+}
+
+impl ProviderInventory for NodeProviders {
+    fn providers(&self) -> Vec<ProviderInfo> {
+        let mut providers = Vec::new();
+        for (chain, adapters) in &self.eth_networks {
+            for adapter in adapters.all_adapters() {
+                let mut capabilities = vec![];
+                if adapter.capabilities.archive {
+                    capabilities.push("archive".to_string());
+                }
+                if adapter.capabilities.traces {
+                    capabilities.push("traces".to_string());
+                }
+                providers.push(ProviderInfo {
+                    chain: chain.clone(),
+                    provider: adapter.provider().into(),
+                    conn_type: ConnectionType::Rpc,
+                    capabilities,
+                    health: adapter.health(),
+                });
+            }
+        }
+        for (chain, endpoint) in &self.firehose {
+            let mut capabilities = vec![];
+            if endpoint.filters_enabled {
+                capabilities.push("filters".to_string());
+            }
+            if endpoint.compression_enabled {
+                capabilities.push("compression".to_string());
+            }
+            providers.push(ProviderInfo {
+                chain: chain.clone(),
+                provider: endpoint.provider.clone(),
+                conn_type: ConnectionType::Firehose,
+                capabilities,
+                health: endpoint.health(),
+            });
+        }
+        for (chain, endpoint) in &self.substreams {
+            providers.push(ProviderInfo {
+                chain: chain.clone(),
+                provider: endpoint.provider.clone(),
+                conn_type: ConnectionType::Substreams,
+                capabilities: vec![],
+                health: endpoint.health(),
+            });
+        }
+        providers.sort_by(|a, b| (&a.chain, &a.provider).cmp(&(&b.chain, &b.provider)));
+        providers
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::chain::create_all_ethereum_networks;
@@ -502,7 +588,7 @@ mod test {
     use graph::log::logger;
     use graph::prelude::{tokio, MetricsRegistry};
     use graph::prometheus::Registry;
-    use graph_chain_ethereum::NodeCapabilities;
+    use graph_chain_ethereum::{NodeCapabilities, ProviderEthRpcMetrics};
     use std::sync::Arc;
 
     #[tokio::test]
@@ -535,9 +621,10 @@ mod test {
             logger.clone(),
             prometheus_registry.clone(),
         ));
+        let eth_rpc_metrics = Arc::new(ProviderEthRpcMetrics::new(metrics_registry));
 
         let ethereum_networks =
-            create_all_ethereum_networks(logger, metrics_registry, &config, metrics)
+            create_all_ethereum_networks(logger, eth_rpc_metrics, &config, metrics)
                 .await
                 .expect("Correctly parse Ethereum network args");
         let mut network_names = ethereum_networks.networks.keys().collect::<Vec<&String>>();
@@ -574,7 +661,7 @@ mod test {
             .networks
             .get("goerli")
             .unwrap()
-            .adapters
+            .adapters()
             .first()
             .unwrap()
             .capabilities;
@@ -582,7 +669,7 @@ mod test {
             .networks
             .get("mainnet")
             .unwrap()
-            .adapters
+            .adapters()
             .first()
             .unwrap()
             .capabilities;
@@ -1,6 +1,8 @@
 use graph::{
     anyhow::Error,
     blockchain::BlockchainKind,
+    components::server::tls::ServerTls,
+    data::subgraph::SubgraphFeature,
     firehose::{SubgraphLimit, SUBGRAPHS_PER_CONN},
     prelude::{
         anyhow::{anyhow, bail, Context, Result},
@@ -14,13 +16,18 @@ use graph::{
     },
 };
 use graph_chain_ethereum::{self as ethereum, NodeCapabilities};
-use graph_store_postgres::{DeploymentPlacer, Shard as ShardName, PRIMARY_SHARD};
+use graph_store_postgres::{
+    DeploymentPlacer, DeploymentProperties, Scheduler, Shard as ShardName, PRIMARY_SHARD,
+};
 
 use http::{HeaderMap, Uri};
 use std::fs::read_to_string;
+use std::path::PathBuf;
+use std::str::FromStr;
 use std::{
     collections::{BTreeMap, BTreeSet},
     fmt,
+    sync::{Arc, RwLock},
 };
 use url::Url;
 
@@ -72,6 +79,8 @@ pub struct Config {
     pub stores: BTreeMap<String, Shard>,
     pub chains: ChainSection,
     pub deployment: Deployment,
+    #[serde(default)]
+    pub tls: Option<TlsSection>,
 }
 
 fn validate_name(s: &str) -> Result<()> {
@@ -186,6 +195,7 @@ impl Config {
             stores,
             chains,
             deployment,
+            tls: None,
         })
     }
 
@@ -222,6 +232,74 @@ pub struct GeneralSection {
     query: Regex,
 }
 
+/// TLS for the servers of the node
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsSection {
+    /// A PEM file with the certificate chain of the servers
+    pub cert: PathBuf,
+    /// A PEM file with the private key of the certificate
+    pub key: PathBuf,
+    /// A PEM file with the CAs that client certificates have to be signed
+    /// by. Without it, clients do not need certificates
+    pub client_ca: Option<PathBuf>,
+    /// The servers that use TLS
+    #[serde(default = "TlsSection::all_servers")]
+    pub servers: Vec<TlsServer>,
+}
+
+impl TlsSection {
+    fn all_servers() -> Vec<TlsServer> {
+        use TlsServer::*;
+        vec![Http, Ws, IndexNode, Metrics, Admin]
+    }
+}
+
+/// The TLS settings that the servers of the node use
+#[derive(Clone, Default)]
+pub struct ServersTls {
+    tls: Option<Arc<ServerTls>>,
+    servers: Vec<TlsServer>,
+}
+
+impl ServersTls {
+    /// Load the certificates and key that `section` names
+    pub fn load(section: Option<&TlsSection>) -> Result<Self> {
+        let section = match section {
+            Some(section) => section,
+            None => return Ok(Self::default()),
+        };
+        let tls = ServerTls::new(&section.cert, &section.key, section.client_ca.as_deref())
+            .context("invalid TLS configuration")?;
+        Ok(ServersTls {
+            tls: Some(Arc::new(tls)),
+            servers: section.servers.clone(),
+        })
+    }
+
+    /// The TLS settings for `server`, or `None` if it does not use TLS
+    pub fn get(&self, server: TlsServer) -> Option<Arc<ServerTls>> {
+        if self.servers.contains(&server) {
+            self.tls.clone()
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TlsServer {
+    /// The GraphQL HTTP server
+    Http,
+    /// The GraphQL WebSocket server
+    Ws,
+    IndexNode,
+    Metrics,
+    /// The JSON-RPC admin server
+    Admin,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Shard {
     pub connection: String,
@@ -494,6 +572,7 @@ impl ChainSection {
                 let entry = chains.entry(name.to_string()).or_insert_with(|| Chain {
                     shard: PRIMARY_SHARD.to_string(),
                     protocol: BlockchainKind::Ethereum,
+                    chain_id: None,
                     providers: vec![],
                 });
                 entry.providers.push(provider);
@@ -508,6 +587,11 @@ pub struct Chain {
     pub shard: String,
     #[serde(default = "default_blockchain_kind")]
     pub protocol: BlockchainKind,
+    /// The EIP-155 chain id of an Ethereum chain. It is only used for
+    /// chains that only have Firehose providers, since Firehose does not
+    /// report it
+    #[serde(default)]
+    pub chain_id: Option<u64>,
     #[serde(rename = "provider")]
     pub providers: Vec<Provider>,
 }
@@ -910,6 +994,9 @@ impl std::fmt::Display for Transport {
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Deployment {
+    /// How to pick one of the indexers of the matching rule
+    #[serde(default)]
+    scheduler: Scheduler,
     #[serde(rename = "rule")]
     rules: Vec<Rule>,
 }
@@ -938,7 +1025,10 @@ impl Deployment {
     }
 
     fn from_opt(_: &Opt) -> Self {
-        Self { rules: vec![] }
+        Self {
+            scheduler: Scheduler::default(),
+            rules: vec![],
+        }
     }
 }
 
@@ -947,12 +1037,17 @@ impl DeploymentPlacer for Deployment {
         &self,
         name: &str,
         network: &str,
+        properties: &DeploymentProperties,
     ) -> Result<Option<(Vec<ShardName>, Vec<NodeId>)>, String> {
         // Errors here are really programming errors. We should have validated
         // everything already so that the various conversions can't fail. We
         // still return errors so that they bubble up to the deployment request
         // rather than crashing the node and burying the crash in the logs
-        let placement = match self.rules.iter().find(|rule| rule.matches(name, network)) {
+        let placement = match self
+            .rules
+            .iter()
+            .find(|rule| rule.matches(name, network, properties))
+        {
             Some(rule) => {
                 let shards = rule.shard_names().map_err(|e| e.to_string())?;
                 let indexers: Vec<_> = rule
@@ -969,6 +1064,40 @@ impl DeploymentPlacer for Deployment {
         };
         Ok(placement)
     }
+
+    fn scheduler(&self) -> Scheduler {
+        self.scheduler
+    }
+}
+
+/// A `DeploymentPlacer` whose rules can be replaced while the node is
+/// running, for example, when the configuration file is reloaded
+#[derive(Debug)]
+pub struct ReloadablePlacer(RwLock<Deployment>);
+
+impl ReloadablePlacer {
+    pub fn new(deployment: Deployment) -> Self {
+        Self(RwLock::new(deployment))
+    }
+
+    pub fn replace(&self, deployment: Deployment) {
+        *self.0.write().unwrap() = deployment;
+    }
+}
+
+impl DeploymentPlacer for ReloadablePlacer {
+    fn place(
+        &self,
+        name: &str,
+        network: &str,
+        properties: &DeploymentProperties,
+    ) -> Result<Option<(Vec<ShardName>, Vec<NodeId>)>, String> {
+        self.0.read().unwrap().place(name, network, properties)
+    }
+
+    fn scheduler(&self) -> Scheduler {
+        self.0.read().unwrap().scheduler()
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -990,8 +1119,8 @@ impl Rule {
         self.pred.matches_anything()
     }
 
-    fn matches(&self, name: &str, network: &str) -> bool {
-        self.pred.matches(name, network)
+    fn matches(&self, name: &str, network: &str, properties: &DeploymentProperties) -> bool {
+        self.pred.matches(name, network, properties)
     }
 
     fn shard_names(&self) -> Result<Vec<ShardName>, StoreError> {
@@ -1010,7 +1139,7 @@ impl Rule {
             NodeId::new(indexer).map_err(|()| anyhow!("invalid node id {}", &indexer))?;
         }
         self.shard_names().map_err(Error::from)?;
-        Ok(())
+        self.pred.validate()
     }
 }
 
@@ -1019,25 +1148,75 @@ struct Predicate {
     #[serde(with = "serde_regex", default = "any_name")]
     name: Regex,
     network: Option<NetworkPredicate>,
+    /// Features that the manifest must declare, all of which have to be
+    /// present for the predicate to match
+    #[serde(default)]
+    features: Vec<String>,
+    /// The number of data sources in the manifest, not counting templates
+    data_sources: Option<CountPredicate>,
+    /// The number of entity types in the schema
+    entities: Option<CountPredicate>,
 }
 
 impl Predicate {
     fn matches_anything(&self) -> bool {
-        self.name.as_str() == ANY_NAME && self.network.is_none()
+        self.name.as_str() == ANY_NAME
+            && self.network.is_none()
+            && self.features.is_empty()
+            && self.data_sources.is_none()
+            && self.entities.is_none()
     }
 
-    pub fn matches(&self, name: &str, network: &str) -> bool {
+    pub fn matches(&self, name: &str, network: &str, properties: &DeploymentProperties) -> bool {
         if let Some(n) = &self.network {
             if !n.matches(network) {
                 return false;
             }
         }
 
+        if !self
+            .features
+            .iter()
+            .all(|feature| properties.features.contains(feature))
+        {
+            return false;
+        }
+
+        if let Some(count) = &self.data_sources {
+            if !count.matches(properties.data_sources) {
+                return false;
+            }
+        }
+
+        if let Some(count) = &self.entities {
+            if !count.matches(properties.entity_types) {
+                return false;
+            }
+        }
+
         match self.name.find(name) {
             None => false,
             Some(m) => m.as_str() == name,
         }
     }
+
+    fn validate(&self) -> Result<()> {
+        // A misspelled feature would make the rule never match
+        for feature in &self.features {
+            if feature != DeploymentProperties::FILE_DATA_SOURCES
+                && SubgraphFeature::from_str(feature).is_err()
+            {
+                return Err(anyhow!("unknown feature {} in deployment rule", feature));
+            }
+        }
+        if let Some(count) = &self.data_sources {
+            count.validate("data_sources")?;
+        }
+        if let Some(count) = &self.entities {
+            count.validate("entities")?;
+        }
+        Ok(())
+    }
 }
 
 impl Default for Predicate {
@@ -1045,6 +1224,38 @@ impl Default for Predicate {
         Predicate {
             name: any_name(),
             network: None,
+            features: vec![],
+            data_sources: None,
+            entities: None,
+        }
+    }
+}
+
+/// Bounds on a count; both bounds are inclusive
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct CountPredicate {
+    min: Option<usize>,
+    max: Option<usize>,
+}
+
+impl CountPredicate {
+    fn matches(&self, count: usize) -> bool {
+        self.min.map_or(true, |min| count >= min) && self.max.map_or(true, |max| count <= max)
+    }
+
+    fn validate(&self, name: &str) -> Result<()> {
+        match (self.min, self.max) {
+            (None, None) => Err(anyhow!(
+                "`{}` in deployment rule needs a `min` or a `max`",
+                name
+            )),
+            (Some(min), Some(max)) if min > max => Err(anyhow!(
+                "`{}` in deployment rule has a `min` of {} that is larger than its `max` of {}",
+                name,
+                min,
+                max
+            )),
+            _ => Ok(()),
         }
     }
 }
@@ -1150,12 +1361,14 @@ mod tests {
     use crate::config::Web3Rule;
 
     use super::{
-        Chain, Config, FirehoseProvider, Provider, ProviderDetails, Transport, Web3Provider,
+        Chain, Config, Deployment, FirehoseProvider, Provider, ProviderDetails, TlsSection,
+        TlsServer, Transport, Web3Provider,
     };
     use graph::blockchain::BlockchainKind;
     use graph::firehose::SubgraphLimit;
     use graph::prelude::regex::Regex;
     use graph::prelude::NodeId;
+    use graph_store_postgres::{DeploymentPlacer, DeploymentProperties, Scheduler};
     use http::{HeaderMap, HeaderValue};
     use std::collections::BTreeSet;
     use std::fs::read_to_string;
@@ -1175,6 +1388,133 @@ mod tests {
         assert_eq!(4, actual.chains.chains.len());
         assert_eq!(2, actual.stores.len());
         assert_eq!(3, actual.deployment.rules.len());
+        assert_eq!(Scheduler::Rules, actual.deployment.scheduler);
+    }
+
+    #[test]
+    fn it_works_on_load_scheduler() {
+        let actual: Deployment = toml::from_str(
+            r#"
+            scheduler = "load"
+
+            [[rule]]
+            indexers = [ "index_node_1", "index_node_2" ]
+        "#,
+        )
+        .unwrap();
+
+        assert_eq!(Scheduler::Load, actual.scheduler);
+        assert_eq!(1, actual.rules.len());
+
+        let actual: Result<Deployment, _> = toml::from_str(
+            r#"
+            scheduler = "random"
+
+            [[rule]]
+            indexers = [ "index_node_1" ]
+        "#,
+        );
+        assert!(actual.is_err());
+    }
+
+    #[test]
+    fn it_works_on_tls() {
+        let actual: TlsSection = toml::from_str(
+            r#"
+            cert = "/etc/graph/cert.pem"
+            key = "/etc/graph/key.pem"
+        "#,
+        )
+        .unwrap();
+        assert_eq!(None, actual.client_ca);
+        assert_eq!(5, actual.servers.len());
+
+        let actual: TlsSection = toml::from_str(
+            r#"
+            cert = "/etc/graph/cert.pem"
+            key = "/etc/graph/key.pem"
+            client_ca = "/etc/graph/ca.pem"
+            servers = [ "index-node", "admin" ]
+        "#,
+        )
+        .unwrap();
+        assert_eq!(Some(PathBuf::from("/etc/graph/ca.pem")), actual.client_ca);
+        assert_eq!(vec![TlsServer::IndexNode, TlsServer::Admin], actual.servers);
+
+        let actual: Result<TlsSection, _> = toml::from_str(
+            r#"
+            cert = "/etc/graph/cert.pem"
+            key = "/etc/graph/key.pem"
+            servers = [ "grpc" ]
+        "#,
+        );
+        assert!(actual.is_err());
+    }
+
+    #[test]
+    fn it_places_by_deployment_properties() {
+        let deployment: Deployment = toml::from_str(
+            r#"
+            [[rule]]
+            match = { features = [ "fullTextSearch", "fileDataSources" ] }
+            shard = "search"
+            indexers = [ "index_node_search" ]
+
+            [[rule]]
+            match = { data_sources = { min = 100 }, entities = { max = 10 } }
+            indexers = [ "index_node_many" ]
+
+            [[rule]]
+            indexers = [ "index_node_default" ]
+        "#,
+        )
+        .unwrap();
+        deployment.validate().unwrap();
+
+        let indexer = |features: &[&str], data_sources, entity_types| {
+            let properties = DeploymentProperties {
+                features: features.iter().map(|f| f.to_string()).collect(),
+                data_sources,
+                entity_types,
+            };
+            let (_, nodes) = deployment
+                .place("name", "mainnet", &properties)
+                .unwrap()
+                .unwrap();
+            nodes[0].to_string()
+        };
+        assert_eq!(
+            "index_node_search",
+            indexer(
+                &["nonFatalErrors", "fullTextSearch", "fileDataSources"],
+                1,
+                1
+            )
+        );
+        assert_eq!("index_node_default", indexer(&["fullTextSearch"], 1, 1));
+        assert_eq!("index_node_many", indexer(&[], 100, 10));
+        assert_eq!("index_node_default", indexer(&[], 100, 11));
+        assert_eq!("index_node_default", indexer(&[], 99, 1));
+
+        let invalid = |pred: &str| {
+            let deployment: Deployment = toml::from_str(&format!(
+                r#"
+                [[rule]]
+                match = {}
+                indexers = [ "index_node_0" ]
+
+                [[rule]]
+                indexers = [ "index_node_0" ]
+            "#,
+                pred
+            ))
+            .unwrap();
+            deployment.validate().is_err()
+        };
+        assert!(invalid(r#"{ features = [ "fullTextSaerch" ] }"#));
+        assert!(invalid("{ entities = { min = 10, max = 5 } }"));
+        assert!(invalid("{ data_sources = {} }"));
+        assert!(!invalid("{ entities = { min = 5, max = 5 } }"));
     }
 
     #[test]
@@ -1191,6 +1531,7 @@ mod tests {
             Chain {
                 shard: "primary".to_string(),
                 protocol: BlockchainKind::Ethereum,
+                chain_id: None,
                 providers: vec![],
             },
             actual
@@ -1212,6 +1553,29 @@ mod tests {
             Chain {
                 shard: "primary".to_string(),
                 protocol: BlockchainKind::Near,
+                chain_id: None,
+                providers: vec![],
+            },
+            actual
+        );
+    }
+
+    #[test]
+    fn it_works_on_chain_with_chain_id() {
+        let actual = toml::from_str(
+            r#"
+            shard = "primary"
+            chain_id = 8453
+            provider = []
+        "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            Chain {
+                shard: "primary".to_string(),
+                protocol: BlockchainKind::Ethereum,
+                chain_id: Some(8453),
                 providers: vec![],
             },
             actual
@@ -0,0 +1,465 @@
+//! Support for `graph-node dev`, which runs a node for local subgraph
+//! development. Postgres and IPFS are started in throwaway docker
+//! containers unless the command line says where to find them, and the
+//! subgraph that `graph build` put into a build directory is deployed
+//! every time the directory changes.
+//!
+//! Subgraphs can also be deployed straight from a build directory with
+//! `--subgraph <name>:<dir>`, which does not need IPFS at all.
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use graph::components::subgraph::SubgraphRegistrar;
+use graph::ipfs_client::IpfsClient;
+use graph::prelude::{
+    anyhow::{anyhow, Context},
+    info, serde_yaml, tiny_keccak, warn, DeploymentHash, Error, Logger, NodeId, SubgraphName,
+    SubgraphRegistrarError,
+};
+
+use crate::opt::{DevOpt, Opt};
+
+const POSTGRES_USER: &str = "graph-node";
+const POSTGRES_PASSWORD: &str = "let-me-in";
+const POSTGRES_DB: &str = "graph-node";
+/// How long to wait for a container to accept connections
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+/// How often the build directory is checked for changes
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The containers that were started for a dev node
+pub struct DevEnvironment {
+    containers: Vec<String>,
+}
+
+impl DevEnvironment {
+    /// Start a Postgres container unless `opt` has a database and an IPFS
+    /// container unless it has IPFS nodes, and point `opt` at them
+    pub async fn start(logger: &Logger, opt: &mut Opt, dev: &DevOpt) -> Result<Self, Error> {
+        if opt.config.is_none()
+            && opt.ethereum_rpc.is_empty()
+            && opt.ethereum_ws.is_empty()
+            && opt.ethereum_ipc.is_empty()
+        {
+            return Err(anyhow!(
+                "`graph-node dev` needs an Ethereum provider, \
+                 use `--ethereum-rpc`, `--ethereum-ws`, or `--ethereum-ipc`"
+            ));
+        }
+
+        let mut env = DevEnvironment { containers: vec![] };
+        if opt.config.is_none() && opt.postgres_url.is_none() {
+            match env.start_postgres(logger, &dev.postgres_image).await {
+                Ok(url) => opt.postgres_url = Some(url),
+                Err(e) => {
+                    env.stop();
+                    return Err(e);
+                }
+            }
+        }
+        if opt.ipfs.is_empty() {
+            match env.start_ipfs(logger, &dev.ipfs_image).await {
+                Ok(url) => opt.ipfs = vec![url],
+                Err(e) => {
+                    env.stop();
+                    return Err(e);
+                }
+            }
+        }
+        Ok(env)
+    }
+
+    async fn start_postgres(&mut self, logger: &Logger, image: &str) -> Result<String, Error> {
+        info!(logger, "Starting Postgres container"; "image" => image);
+        let id = docker(&[
+            "run",
+            "-d",
+            "--rm",
+            "-p",
+            "127.0.0.1::5432",
+            "-e",
+            &format!("POSTGRES_USER={}", POSTGRES_USER),
+            "-e",
+            &format!("POSTGRES_PASSWORD={}", POSTGRES_PASSWORD),
+            "-e",
+            &format!("POSTGRES_DB={}", POSTGRES_DB),
+            "-e",
+            "POSTGRES_INITDB_ARGS=-E UTF8 --locale=C",
+            image,
+            "postgres",
+            "-cshared_preload_libraries=pg_stat_statements",
+        ])?;
+        self.containers.push(id.clone());
+        let port = published_port(&id, 5432)?;
+
+        // The server that the image runs while it initializes the database
+        // does not listen on TCP, so this only succeeds once the database
+        // is ready
+        let start = Instant::now();
+        while docker(&[
+            "exec",
+            &id,
+            "pg_isready",
+            "-h",
+            "127.0.0.1",
+            "-U",
+            POSTGRES_USER,
+        ])
+        .is_err()
+        {
+            if start.elapsed() > STARTUP_TIMEOUT {
+                return Err(anyhow!("Postgres in container {} did not start", id));
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+        info!(logger, "Postgres is ready"; "container" => &id, "port" => port);
+        Ok(format!(
+            "postgresql://{}:{}@127.0.0.1:{}/{}",
+            POSTGRES_USER, POSTGRES_PASSWORD, port, POSTGRES_DB
+        ))
+    }
+
+    async fn start_ipfs(&mut self, logger: &Logger, image: &str) -> Result<String, Error> {
+        info!(logger, "Starting IPFS container"; "image" => image);
+        let id = docker(&["run", "-d", "--rm", "-p", "127.0.0.1::5001", image])?;
+        self.containers.push(id.clone());
+        let url = format!("http://127.0.0.1:{}", published_port(&id, 5001)?);
+
+        let client = IpfsClient::new(&url)?;
+        let start = Instant::now();
+        while client.test().await.is_err() {
+            if start.elapsed() > STARTUP_TIMEOUT {
+                return Err(anyhow!("IPFS in container {} did not start", id));
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+        info!(logger, "IPFS is ready"; "container" => &id, "url" => &url);
+        Ok(url)
+    }
+
+    /// Remove the containers, and with them everything the node stored
+    pub fn stop(&self) {
+        if !self.containers.is_empty() {
+            let mut args = vec!["rm", "-f"];
+            args.extend(self.containers.iter().map(String::as_str));
+            docker(&args).ok();
+        }
+    }
+}
+
+fn docker(args: &[&str]) -> Result<String, Error> {
+    let output = Command::new("docker")
+        .args(args)
+        .output()
+        .context("failed to run `docker`, is it installed?")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "`docker {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The port on the host that `port` of `container` is published on
+fn published_port(container: &str, port: u16) -> Result<u16, Error> {
+    let mapping = docker(&["port", container, &format!("{}/tcp", port)])?;
+    // The mapping looks like `127.0.0.1:49153`
+    mapping
+        .lines()
+        .next()
+        .and_then(|line| line.rsplit(':').next())
+        .and_then(|port| port.parse().ok())
+        .ok_or_else(|| anyhow!("unexpected port mapping `{}` of {}", mapping, container))
+}
+
+/// The paths of the files that `value`, a part of a manifest, references
+/// with `file:`
+fn referenced_files(value: &serde_yaml::Value, files: &mut BTreeSet<String>) {
+    match value {
+        serde_yaml::Value::Mapping(map) => {
+            for (key, value) in map {
+                match (key.as_str(), value.as_str()) {
+                    (Some("file"), Some(path)) => {
+                        files.insert(path.to_string());
+                    }
+                    _ => referenced_files(value, files),
+                }
+            }
+        }
+        serde_yaml::Value::Sequence(values) => {
+            for value in values {
+                referenced_files(value, files);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Replace the paths of files in `value` with the IPFS links in `links`
+fn link_files(value: &mut serde_yaml::Value, links: &HashMap<String, String>) {
+    match value {
+        serde_yaml::Value::Mapping(map) => {
+            for (key, value) in map.iter_mut() {
+                let link = value.as_str().and_then(|path| links.get(path)).cloned();
+                match (key.as_str(), link) {
+                    (Some("file"), Some(link)) => {
+                        let mut file = serde_yaml::Mapping::new();
+                        file.insert("/".into(), link.into());
+                        *value = serde_yaml::Value::Mapping(file);
+                    }
+                    _ => link_files(value, links),
+                }
+            }
+        }
+        serde_yaml::Value::Sequence(values) => {
+            for value in values {
+                link_files(value, links);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Add the subgraph in `build_dir` to IPFS the way `graph deploy` does:
+/// every file that the manifest references is added and the reference is
+/// replaced with a link to it. Returns the hash of the manifest
+async fn upload(ipfs: &IpfsClient, build_dir: &Path) -> Result<DeploymentHash, Error> {
+    let path = build_dir.join("subgraph.yaml");
+    let raw = std::fs::read_to_string(&path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let mut manifest: serde_yaml::Value = serde_yaml::from_str(&raw)
+        .with_context(|| format!("failed to parse {}", path.display()))?;
+
+    let mut files = BTreeSet::new();
+    referenced_files(&manifest, &mut files);
+    let mut links = HashMap::new();
+    for file in files {
+        let path = build_dir.join(&file);
+        let data =
+            std::fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?;
+        let hash = ipfs.add(data).await?.hash;
+        links.insert(file, format!("/ipfs/{}", hash));
+    }
+    link_files(&mut manifest, &links);
+
+    let hash = ipfs
+        .add(serde_yaml::to_string(&manifest)?.into_bytes())
+        .await?
+        .hash;
+    DeploymentHash::new(hash).map_err(|hash| anyhow!("IPFS returned the invalid hash {}", hash))
+}
+
+/// A subgraph that is deployed from the files that `graph build` wrote
+/// rather than from IPFS
+pub struct LocalSubgraph {
+    pub hash: DeploymentHash,
+    /// The manifest with `file://` links to the files it references
+    pub manifest: Vec<u8>,
+    pub files: Vec<PathBuf>,
+}
+
+impl LocalSubgraph {
+    /// Load the subgraph with the manifest `path`, or the `subgraph.yaml`
+    /// in `path` if it is a directory. The deployment hash is derived from
+    /// the manifest and the files it references so that every change to
+    /// the subgraph leads to a new deployment
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let path = if path.is_dir() {
+            path.join("subgraph.yaml")
+        } else {
+            path.to_path_buf()
+        };
+        let path = path
+            .canonicalize()
+            .with_context(|| format!("failed to find {}", path.display()))?;
+        let dir = path.parent().unwrap_or_else(|| Path::new("/"));
+        let raw =
+            std::fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?;
+        let mut manifest: serde_yaml::Value = serde_yaml::from_slice(&raw)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+
+        let mut referenced = BTreeSet::new();
+        referenced_files(&manifest, &mut referenced);
+        let mut content = raw;
+        let mut links = HashMap::new();
+        let mut files = Vec::new();
+        for file in referenced {
+            let path = dir
+                .join(&file)
+                .canonicalize()
+                .with_context(|| format!("failed to find {}", file))?;
+            let data = std::fs::read(&path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            content.extend(file.as_bytes());
+            content.extend(data);
+            links.insert(file, format!("file://{}", path.display()));
+            files.push(path);
+        }
+        link_files(&mut manifest, &links);
+
+        let hash: String = tiny_keccak::keccak256(&content)[..20]
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        Ok(LocalSubgraph {
+            hash: DeploymentHash::new(format!("local{}", hash))
+                .expect("local deployment hashes are valid"),
+            manifest: serde_yaml::to_string(&manifest)?.into_bytes(),
+            files,
+        })
+    }
+}
+
+/// The time at which a file in `dir` was last modified
+fn last_modified(dir: &Path) -> Option<SystemTime> {
+    let mut latest = None;
+    let mut dirs = vec![PathBuf::from(dir)];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(dir).ok()?.flatten() {
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            if metadata.is_dir() {
+                dirs.push(entry.path());
+            } else if let Ok(modified) = metadata.modified() {
+                latest = latest.max(Some(modified));
+            }
+        }
+    }
+    latest
+}
+
+/// Deploy the subgraph in the build directory of `dev`, and deploy it
+/// again whenever the directory changes unless watching is turned off.
+/// Deployments wait until the directory has not changed for a moment so
+/// that a build that is still writing files is not deployed
+pub async fn deploy<R: SubgraphRegistrar>(
+    logger: Logger,
+    registrar: Arc<R>,
+    ipfs: IpfsClient,
+    node_id: NodeId,
+    dev: DevOpt,
+) {
+    let name = SubgraphName::new(dev.name.as_str())
+        .expect("Subgraph name must contain only a-z, A-Z, 0-9, '-' and '_'");
+    match registrar.create_subgraph(name.clone()).await {
+        Ok(_) | Err(SubgraphRegistrarError::NameExists(_)) => {}
+        Err(e) => panic!("Failed to create subgraph {}: {}", name, e),
+    }
+
+    let build_dir = PathBuf::from(&dev.build_dir);
+    let mut deployed = None;
+    let mut last_seen = None;
+    loop {
+        let modified = last_modified(&build_dir);
+        if modified.is_some() && modified != deployed && modified == last_seen {
+            deployed = modified;
+            let res = match upload(&ipfs, &build_dir).await {
+                Ok(hash) => registrar
+                    .create_subgraph_version(
+                        name.clone(),
+                        hash.clone(),
+                        node_id.clone(),
+                        None,
+                        None,
+                        None,
+                        false,
+                    )
+                    .await
+                    .map(|_| hash)
+                    .map_err(Error::from),
+                Err(e) => Err(e),
+            };
+            match res {
+                Ok(hash) => info!(logger, "Deployed subgraph";
+                                  "name" => name.to_string(),
+                                  "deployment" => hash.to_string()),
+                Err(e) => warn!(logger, "Failed to deploy subgraph, waiting for changes";
+                                "name" => name.to_string(),
+                                "build_dir" => &dev.build_dir,
+                                "error" => format!("{:#}", e)),
+            }
+            if dev.no_watch {
+                return;
+            }
+        }
+        last_seen = modified;
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeSet, HashMap};
+
+    use graph::prelude::serde_yaml;
+
+    use super::{link_files, referenced_files};
+
+    const MANIFEST: &str = "
+specVersion: 0.0.4
+schema:
+  file: schema.graphql
+dataSources:
+  - kind: ethereum/contract
+    mapping:
+      abis:
+        - name: Token
+          file: Token/abis/Token.json
+      file: Token/Token.wasm
+templates:
+  - kind: file/ipfs
+    mapping:
+      abis:
+        - name: Token
+          file: Token/abis/Token.json
+      file: templates/File/File.wasm
+";
+
+    #[test]
+    fn links_referenced_files() {
+        let mut manifest: serde_yaml::Value = serde_yaml::from_str(MANIFEST).unwrap();
+
+        let mut files = BTreeSet::new();
+        referenced_files(&manifest, &mut files);
+        let files: Vec<_> = files.into_iter().collect();
+        assert_eq!(
+            vec![
+                "Token/Token.wasm",
+                "Token/abis/Token.json",
+                "schema.graphql",
+                "templates/File/File.wasm"
+            ],
+            files
+        );
+
+        let links: HashMap<_, _> = files
+            .iter()
+            .enumerate()
+            .map(|(i, file)| (file.to_string(), format!("/ipfs/Qm{}", i)))
+            .collect();
+        link_files(&mut manifest, &links);
+        assert_eq!(
+            "/ipfs/Qm2",
+            manifest["schema"]["file"]["/"].as_str().unwrap()
+        );
+        assert_eq!(
+            "/ipfs/Qm1",
+            manifest["templates"][0]["mapping"]["abis"][0]["file"]["/"]
+                .as_str()
+                .unwrap()
+        );
+        assert_eq!(
+            "/ipfs/Qm0",
+            manifest["dataSources"][0]["mapping"]["file"]["/"]
+                .as_str()
+                .unwrap()
+        );
+    }
+}
@@ -7,7 +7,9 @@ extern crate diesel;
 
 pub mod chain;
 pub mod config;
+pub mod dev;
 pub mod opt;
+pub mod reload;
 pub mod store_builder;
 
 pub mod manager;
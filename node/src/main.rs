@@ -1,18 +1,21 @@
 use clap::Parser as _;
 use ethereum::chain::{EthereumAdapterSelector, EthereumBlockRefetcher, EthereumStreamBuilder};
-use ethereum::{BlockIngestor, EthereumNetworks, RuntimeAdapter};
+use ethereum::{BlockIngestor, EthereumNetworks, ProviderEthRpcMetrics, RuntimeAdapter};
 use git_testament::{git_testament, render_testament};
 use graph::blockchain::client::ChainClient;
 
 use graph::blockchain::{
     BasicBlockchainBuilder, Blockchain, BlockchainBuilder, BlockchainKind, BlockchainMap,
 };
+use graph::components::link_resolver::IpfsCache as IpfsCacheTrait;
+use graph::components::metrics::ingestors::INGESTOR_HEALTH;
 use graph::components::store::BlockStore;
+use graph::components::subgraph::Notifier;
 use graph::data::graphql::effort::LoadManager;
 use graph::endpoint::EndpointMetrics;
 use graph::env::EnvVars;
 use graph::firehose::{FirehoseEndpoints, FirehoseNetworks};
-use graph::log::logger;
+use graph::log::{logger, telemetry};
 use graph::prelude::{IndexNodeServer as _, *};
 use graph::prometheus::Registry;
 use graph::url::Url;
@@ -21,7 +24,9 @@ use graph_chain_cosmos::{self as cosmos, Block as CosmosFirehoseBlock};
 use graph_chain_ethereum as ethereum;
 use graph_chain_near::{self as near, HeaderOnlyBlock as NearFirehoseHeaderOnlyBlock};
 use graph_chain_substreams as substreams;
-use graph_core::polling_monitor::ipfs_service;
+use graph_core::polling_monitor::{
+    arweave_gateways, arweave_service, ipfs_service, object_store_service, ObjectStoreEndpoints,
+};
 use graph_core::{
     LinkResolver, SubgraphAssignmentProvider as IpfsSubgraphAssignmentProvider,
     SubgraphInstanceManager, SubgraphRegistrar as IpfsSubgraphRegistrar,
@@ -29,22 +34,28 @@ use graph_core::{
 use graph_graphql::prelude::GraphQlRunner;
 use graph_node::chain::{
     connect_ethereum_networks, connect_firehose_networks, create_all_ethereum_networks,
-    create_firehose_networks, create_ipfs_clients, create_substreams_networks,
+    create_firehose_networks, create_ipfs_clients, create_substreams_networks, NodeProviders,
 };
-use graph_node::config::Config;
+use graph_node::config::{Config, ServersTls, TlsServer};
+use graph_node::dev::{self, DevEnvironment, LocalSubgraph};
 use graph_node::opt;
+use graph_node::reload::Reloader;
 use graph_node::store_builder::StoreBuilder;
+use graph_server_grpc::GrpcServer;
 use graph_server_http::GraphQLServer as GraphQLQueryServer;
 use graph_server_index_node::IndexNodeServer;
-use graph_server_json_rpc::JsonRpcServer;
+use graph_server_json_rpc::{ApiKeys, JsonRpcServer};
 use graph_server_metrics::PrometheusMetricsServer;
 use graph_server_websocket::SubscriptionServer as GraphQLSubscriptionServer;
-use graph_store_postgres::{register_jobs as register_store_jobs, ChainHeadUpdateListener, Store};
+use graph_store_postgres::{
+    register_jobs as register_store_jobs, ChainHeadUpdateListener, IpfsCache, Store,
+};
 use std::collections::BTreeMap;
 use std::io::{BufRead, BufReader};
 use std::path::Path;
 use std::time::Duration;
 use std::{collections::HashMap, env};
+use tokio::signal::unix::{signal, SignalKind};
 use tokio::sync::mpsc;
 
 git_testament!(TESTAMENT);
@@ -110,7 +121,7 @@ async fn main() {
     env_logger::init();
 
     let env_vars = Arc::new(EnvVars::from_env().unwrap());
-    let opt = opt::Opt::parse();
+    let mut opt = opt::Opt::parse();
 
     // Set up logger
     let logger = logger(opt.debug);
@@ -129,6 +140,30 @@ async fn main() {
         );
     }
 
+    // `graph-node dev` starts what the node needs in containers and
+    // removes them again on Ctrl-C
+    let dev = match opt.cmd.clone() {
+        Some(opt::Command::Dev(dev)) => {
+            let env = match DevEnvironment::start(&logger, &mut opt, &dev).await {
+                Ok(env) => env,
+                Err(e) => {
+                    eprintln!("failed to start dev environment: {:#}", e);
+                    std::process::exit(1);
+                }
+            };
+            let logger = logger.clone();
+            graph::spawn(async move {
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    info!(logger, "Removing dev containers");
+                    env.stop();
+                    std::process::exit(0);
+                }
+            });
+            Some(dev)
+        }
+        None => None,
+    };
+
     let config = match Config::load(&logger, &opt.clone().into()) {
         Err(e) => {
             eprintln!("configuration error: {}", e);
@@ -149,6 +184,8 @@ async fn main() {
         .expect("Node ID must be between 1 and 63 characters in length");
     let query_only = config.query_only(&node_id);
 
+    telemetry::init(&logger, &node_id).expect("failed to set up exporting traces with OTLP");
+
     // Obtain subgraph related command-line arguments
     let subgraph = opt.subgraph.clone();
 
@@ -165,6 +202,9 @@ async fn main() {
     // Obtain metrics server port
     let metrics_port = opt.metrics_port;
 
+    // Obtain gRPC server port
+    let grpc_port = opt.grpc_port;
+
     // Obtain the fork base URL
     let fork_base = match &opt.fork_base {
         Some(url) => {
@@ -214,19 +254,31 @@ async fn main() {
 
     // Try to create IPFS clients for each URL specified in `--ipfs`
     let ipfs_clients: Vec<_> = create_ipfs_clients(&logger, &opt.ipfs);
-    let ipfs_client = ipfs_clients.first().cloned().expect("Missing IPFS client");
-    let ipfs_service = ipfs_service(
-        ipfs_client,
-        ENV_VARS.mappings.max_ipfs_file_bytes as u64,
-        ENV_VARS.mappings.ipfs_timeout,
-        ENV_VARS.mappings.ipfs_request_limit,
+    let ipfs_dev_client = ipfs_clients.first().cloned();
+    let arweave_service = arweave_service(
+        reqwest::Client::new(),
+        arweave_gateways(&ENV_VARS.mappings.arweave_gateways).expect("invalid Arweave gateway"),
+        ENV_VARS.mappings.max_arweave_file_bytes as u64,
+        ENV_VARS.mappings.arweave_timeout,
+        ENV_VARS.mappings.arweave_request_limit,
+    );
+    let object_store_service = object_store_service(
+        reqwest::Client::new(),
+        ObjectStoreEndpoints::new(
+            &ENV_VARS.mappings.object_store_s3_endpoint,
+            &ENV_VARS.mappings.object_store_s3_region,
+            &ENV_VARS.mappings.object_store_gcs_endpoint,
+        )
+        .expect("invalid object store endpoint"),
+        ENV_VARS.mappings.max_object_store_file_bytes as u64,
+        ENV_VARS.mappings.object_store_timeout,
+        ENV_VARS.mappings.object_store_request_limit,
     );
 
-    // Convert the clients into a link resolver. Since we want to get past
-    // possible temporary DNS failures, make the resolver retry
-    let link_resolver = Arc::new(LinkResolver::new(ipfs_clients, env_vars.cheap_clone()));
+    let servers_tls = ServersTls::load(config.tls.as_ref()).expect("failed to set up TLS");
     let mut metrics_server =
-        PrometheusMetricsServer::new(&logger_factory, prometheus_registry.clone());
+        PrometheusMetricsServer::new(&logger_factory, prometheus_registry.clone())
+            .with_tls(servers_tls.get(TlsServer::Metrics));
 
     let endpoint_metrics = Arc::new(EndpointMetrics::new(
         logger.clone(),
@@ -234,6 +286,8 @@ async fn main() {
         metrics_registry.cheap_clone(),
     ));
 
+    let eth_rpc_metrics = Arc::new(ProviderEthRpcMetrics::new(metrics_registry.clone()));
+
     // Ethereum clients; query nodes ignore all ethereum clients and never
     // connect to them directly
     let eth_networks = if query_only {
@@ -241,7 +295,7 @@ async fn main() {
     } else {
         create_all_ethereum_networks(
             logger.clone(),
-            metrics_registry.clone(),
+            eth_rpc_metrics.cheap_clone(),
             &config,
             endpoint_metrics.cheap_clone(),
         )
@@ -261,6 +315,12 @@ async fn main() {
         create_substreams_networks(logger.clone(), &config, endpoint_metrics.clone())
     };
 
+    let providers = Arc::new(NodeProviders::new(
+        &eth_networks,
+        &firehose_networks_by_kind,
+        &substreams_networks_by_kind,
+    ));
+
     let graphql_metrics_registry = metrics_registry.clone();
 
     let contention_logger = logger.clone();
@@ -283,6 +343,50 @@ async fn main() {
         let chain_head_update_listener = store_builder.chain_head_update_listener();
         let primary_pool = store_builder.primary_pool();
 
+        let ipfs_store_cache = if ENV_VARS.mappings.ipfs_store_cache_max_file_size > 0 {
+            let cache: Arc<dyn IpfsCacheTrait> =
+                Arc::new(IpfsCache::new(&logger, primary_pool.clone()));
+            Some(cache)
+        } else {
+            None
+        };
+        let ipfs_service = ipfs_service(
+            ipfs_clients.clone(),
+            ipfs_store_cache.clone(),
+            ENV_VARS.mappings.max_ipfs_file_bytes as u64,
+            ENV_VARS.mappings.ipfs_timeout,
+            ENV_VARS.mappings.ipfs_unhealthy_backoff,
+            ENV_VARS.mappings.ipfs_request_limit,
+        );
+
+        // Convert the clients into a link resolver. Since we want to get past
+        // possible temporary DNS failures, make the resolver retry
+        let mut link_resolver = LinkResolver::new(ipfs_clients, env_vars.cheap_clone());
+        if let Some(ipfs_store_cache) = ipfs_store_cache {
+            link_resolver = link_resolver.with_store_cache(ipfs_store_cache);
+        }
+        let link_resolver = Arc::new(link_resolver);
+
+        // A `--subgraph` that names a build directory or a manifest on disk
+        // is deployed from there instead of from IPFS
+        let subgraph = subgraph.map(|subgraph| {
+            let (name, target) = match subgraph.split_once(':') {
+                Some((name, target)) => (name.to_owned(), target.to_owned()),
+                None => ("cli".to_owned(), subgraph),
+            };
+            let hash = if Path::new(&target).exists() {
+                let local = LocalSubgraph::load(Path::new(&target))
+                    .unwrap_or_else(|e| panic!("Failed to load subgraph from {}: {:#}", target, e));
+                info!(logger, "Deploying subgraph from disk";
+                      "path" => &target, "deployment" => local.hash.as_str());
+                link_resolver.add_local_subgraph(&local.hash, local.manifest, local.files);
+                local.hash
+            } else {
+                DeploymentHash::new(target).expect("Subgraph hash must be a valid IPFS hash")
+            };
+            (name, hash)
+        });
+
         // To support the ethereum block ingestor, ethereum networks are referenced both by the
         // `blockchain_map` and `ethereum_chains`. Future chains should be referred to only in
         // `blockchain_map`.
@@ -299,6 +403,39 @@ async fn main() {
         let (eth_networks, ethereum_idents) =
             connect_ethereum_networks(&logger, eth_networks).await;
 
+        // Ethereum chains that only have Firehose providers are identified
+        // through Firehose. Since Firehose does not report the chain id,
+        // it is taken from the configuration
+        let (eth_firehose_networks, eth_firehose_idents) =
+            connect_firehose_networks::<ethereum::codec::HeaderOnlyBlock>(
+                &logger,
+                firehose_networks_by_kind
+                    .remove(&BlockchainKind::Ethereum)
+                    .unwrap_or_else(FirehoseNetworks::new),
+            )
+            .await;
+        let eth_firehose_idents: Vec<_> = eth_firehose_idents
+            .into_iter()
+            .filter(|(name, _)| !ethereum_idents.iter().any(|(eth_name, _)| eth_name == name))
+            .map(|(name, idents)| {
+                let chain_id = config
+                    .chains
+                    .chains
+                    .get(&name)
+                    .and_then(|chain| chain.chain_id);
+                let idents = idents
+                    .into_iter()
+                    .map(|mut ident| {
+                        if let Some(chain_id) = chain_id {
+                            ident.net_version = chain_id.to_string();
+                        }
+                        ident
+                    })
+                    .collect();
+                (name, idents)
+            })
+            .collect();
+
         let (near_networks, near_idents) =
             connect_firehose_networks::<NearFirehoseHeaderOnlyBlock>(
                 &logger,
@@ -318,6 +455,7 @@ async fn main() {
 
         let network_identifiers = ethereum_idents
             .into_iter()
+            .chain(eth_firehose_idents)
             .chain(arweave_idents)
             .chain(near_idents)
             .chain(cosmos_idents)
@@ -325,6 +463,36 @@ async fn main() {
 
         let network_store = store_builder.network_store(network_identifiers);
 
+        // Reload the configuration file on request of the admin API or
+        // when the node receives a SIGHUP
+        let config_reloader = Arc::new(Reloader::new(
+            &logger,
+            opt.config.clone(),
+            config,
+            &store_builder,
+            network_store.block_store(),
+            eth_networks.clone(),
+            eth_rpc_metrics,
+        ));
+        {
+            let config_reloader = config_reloader.cheap_clone();
+            let logger = logger.clone();
+            graph::spawn(async move {
+                let mut hangups = match signal(SignalKind::hangup()) {
+                    Ok(hangups) => hangups,
+                    Err(e) => {
+                        warn!(logger, "Failed to listen for SIGHUP";
+                              "error" => e.to_string());
+                        return;
+                    }
+                };
+                while hangups.recv().await.is_some() {
+                    // The reloader logs the outcome of the reload
+                    config_reloader.reload().await.ok();
+                }
+            });
+        }
+
         let arweave_chains = networks_as_chains::<arweave::Chain>(
             &mut blockchain_map,
             &logger,
@@ -339,7 +507,7 @@ async fn main() {
             &logger,
             node_id.clone(),
             metrics_registry.clone(),
-            firehose_networks_by_kind.get(&BlockchainKind::Ethereum),
+            Some(&eth_firehose_networks),
             substreams_networks_by_kind.get(&BlockchainKind::Ethereum),
             &eth_networks,
             network_store.as_ref(),
@@ -368,6 +536,11 @@ async fn main() {
 
         let blockchain_map = Arc::new(blockchain_map);
 
+        let notifier = Arc::new(
+            Notifier::from_env(&logger, node_id.clone(), &env_vars)
+                .expect("invalid GRAPH_NOTIFICATION_WEBHOOKS"),
+        );
+
         let load_manager = Arc::new(LoadManager::new(
             &logger,
             expensive_queries,
@@ -381,9 +554,13 @@ async fn main() {
             graphql_metrics_registry,
         ));
         let mut graphql_server =
-            GraphQLQueryServer::new(&logger_factory, graphql_runner.clone(), node_id.clone());
+            GraphQLQueryServer::new(&logger_factory, graphql_runner.clone(), node_id.clone())
+                .expect("invalid query audit log configuration")
+                .with_tls(servers_tls.get(TlsServer::Http));
         let subscription_server =
-            GraphQLSubscriptionServer::new(&logger, graphql_runner.clone(), network_store.clone());
+            GraphQLSubscriptionServer::new(&logger, graphql_runner.clone(), network_store.clone())
+                .with_tls(servers_tls.get(TlsServer::Ws));
+        let grpc_server = GrpcServer::new(&logger_factory, graphql_runner.clone());
 
         let mut index_node_server = IndexNodeServer::new(
             &logger_factory,
@@ -391,10 +568,13 @@ async fn main() {
             graphql_runner.clone(),
             network_store.clone(),
             link_resolver.clone(),
-        );
+            providers,
+        )
+        .with_tls(servers_tls.get(TlsServer::IndexNode));
 
         if !opt.disable_block_ingestor {
             let logger = logger.clone();
+            INGESTOR_HEALTH.register_metrics(&metrics_registry);
             let mut ingestors: Vec<Box<dyn BlockIngestor>> = vec![];
             collect_ingestors!(
                 ingestors,
@@ -423,9 +603,36 @@ async fn main() {
                 network_store.clone(),
                 primary_pool,
                 metrics_registry.clone(),
+                notifier.cheap_clone(),
             );
             graph::spawn_blocking(job_runner.start());
         }
+
+        // Renew this node's lease so that its deployments are not moved to
+        // other index nodes by the failover job
+        if !query_only {
+            let store = network_store.subgraph_store();
+            let node_id = node_id.clone();
+            let logger = logger.clone();
+            graph::spawn(async move {
+                let mut interval =
+                    tokio::time::interval(ENV_VARS.store.node_lease_renewal_interval);
+                loop {
+                    interval.tick().await;
+                    let store = store.cheap_clone();
+                    let node = node_id.clone();
+                    let res =
+                        graph::spawn_blocking_allow_panic(move || store.renew_node_lease(&node))
+                            .await
+                            .map_err(|e| e.to_string())
+                            .and_then(|res| res.map_err(|e| e.to_string()));
+                    if let Err(e) = res {
+                        warn!(logger, "Failed to renew node lease"; "error" => e);
+                    }
+                }
+            });
+        }
+
         let static_filters = ENV_VARS.experimental_static_filters;
 
         let sg_count = Arc::new(SubgraphCountMetric::new(metrics_registry.cheap_clone()));
@@ -439,16 +646,51 @@ async fn main() {
             metrics_registry.clone(),
             link_resolver.clone(),
             ipfs_service,
+            arweave_service,
+            object_store_service,
+            notifier.cheap_clone(),
             static_filters,
         );
 
         // Create IPFS-based subgraph provider
-        let subgraph_provider = IpfsSubgraphAssignmentProvider::new(
+        let subgraph_provider = Arc::new(IpfsSubgraphAssignmentProvider::new(
             &logger_factory,
             link_resolver.clone(),
             subgraph_instance_manager,
             sg_count,
-        );
+        ));
+
+        // On SIGTERM, stop all subgraphs at a block boundary and write
+        // what they have processed before exiting. Database connections
+        // are closed when the process exits
+        {
+            let subgraph_provider = subgraph_provider.cheap_clone();
+            let logger = logger.clone();
+            graph::spawn(async move {
+                let mut terms = match signal(SignalKind::terminate()) {
+                    Ok(terms) => terms,
+                    Err(e) => {
+                        warn!(logger, "Failed to listen for SIGTERM";
+                              "error" => e.to_string());
+                        return;
+                    }
+                };
+                if terms.recv().await.is_some() {
+                    info!(logger, "Received SIGTERM, stopping subgraphs";
+                          "timeout_secs" => ENV_VARS.shutdown_timeout.as_secs());
+                    let stop_all = subgraph_provider.stop_all();
+                    if tokio::time::timeout(ENV_VARS.shutdown_timeout, stop_all)
+                        .await
+                        .is_err()
+                    {
+                        warn!(logger, "Timed out stopping subgraphs, some changes were not written";
+                              "timeout_secs" => ENV_VARS.shutdown_timeout.as_secs());
+                    }
+                    info!(logger, "Shutting down");
+                    std::process::exit(0);
+                }
+            });
+        }
 
         // Check version switching mode environment variable
         let version_switching_mode = ENV_VARS.subgraph_version_switching_mode;
@@ -457,12 +699,13 @@ async fn main() {
         let subgraph_registrar = Arc::new(IpfsSubgraphRegistrar::new(
             &logger_factory,
             link_resolver,
-            Arc::new(subgraph_provider),
+            subgraph_provider,
             network_store.subgraph_store(),
             subscription_manager,
             blockchain_map,
             node_id.clone(),
             version_switching_mode,
+            notifier,
         ));
         graph::spawn(
             subgraph_registrar
@@ -472,12 +715,16 @@ async fn main() {
         );
 
         // Start admin JSON-RPC server.
+        let api_keys = ApiKeys::from_env(&ENV_VARS).expect("failed to load admin API keys");
         let json_rpc_server = JsonRpcServer::serve(
             json_rpc_port,
             http_port,
             ws_port,
             subgraph_registrar.clone(),
+            config_reloader,
             node_id.clone(),
+            api_keys,
+            servers_tls.get(TlsServer::Admin),
             logger.clone(),
         )
         .await
@@ -486,19 +733,21 @@ async fn main() {
         // Let the server run forever.
         std::mem::forget(json_rpc_server);
 
-        // Add the CLI subgraph with a REST request to the admin server.
-        if let Some(subgraph) = subgraph {
-            let (name, hash) = if subgraph.contains(':') {
-                let mut split = subgraph.split(':');
-                (split.next().unwrap(), split.next().unwrap().to_owned())
-            } else {
-                ("cli", subgraph)
-            };
+        // Deploy the subgraph that is being developed
+        if let Some(dev) = dev {
+            graph::spawn(dev::deploy(
+                logger.clone(),
+                subgraph_registrar.cheap_clone(),
+                ipfs_dev_client.expect("dev nodes have an IPFS node"),
+                node_id.clone(),
+                dev,
+            ));
+        }
 
+        // Add the CLI subgraph with a REST request to the admin server.
+        if let Some((name, subgraph_id)) = subgraph {
             let name = SubgraphName::new(name)
                 .expect("Subgraph name must contain only a-z, A-Z, 0-9, '-' and '_'");
-            let subgraph_id =
-                DeploymentHash::new(hash).expect("Subgraph hash must be a valid IPFS hash");
             let debug_fork = opt
                 .debug_fork
                 .map(DeploymentHash::new)
@@ -528,6 +777,7 @@ async fn main() {
                             debug_fork,
                             start_block,
                             None,
+                            false,
                         )
                         .await
                 }
@@ -546,6 +796,16 @@ async fn main() {
         // Serve GraphQL subscriptions over WebSockets
         graph::spawn(subscription_server.serve(ws_port));
 
+        // Serve entity reads over gRPC
+        if let Some(grpc_port) = grpc_port {
+            graph::spawn(async move {
+                grpc_server
+                    .serve(grpc_port)
+                    .await
+                    .expect("Failed to start gRPC server")
+            });
+        }
+
         // Run the index node server
         graph::spawn(
             index_node_server
@@ -705,10 +965,11 @@ fn ethereum_networks_as_chains(
                 chain_store.clone(),
             );
 
-            let runtime_adapter = Arc::new(RuntimeAdapter {
-                eth_adapters: Arc::new(eth_adapters.clone()),
-                call_cache: chain_store.cheap_clone(),
-            });
+            let runtime_adapter = Arc::new(RuntimeAdapter::new(
+                Arc::new(eth_adapters.clone()),
+                chain_store.cheap_clone(),
+                registry.clone(),
+            ));
 
             let chain = ethereum::Chain::new(
                 logger_factory.clone(),
@@ -1,6 +1,11 @@
+use std::cmp::Ordering;
+
+use graph::components::subgraph::{DeploymentEvent, Notifier};
 use graph::prelude::{anyhow::anyhow, Error, NodeId, StoreEvent};
 use graph_store_postgres::{
-    command_support::catalog, connection_pool::ConnectionPool, NotificationSender,
+    command_support::catalog::{self, Site},
+    connection_pool::ConnectionPool,
+    NotificationSender,
 };
 
 use crate::manager::deployment::DeploymentSearch;
@@ -26,9 +31,73 @@ pub async fn unassign(
     Ok(())
 }
 
-pub fn reassign(
+/// Pause indexing of a deployment on the node it is assigned to. The
+/// deployment stays assigned and can still be queried
+pub fn pause(
+    primary: ConnectionPool,
+    sender: &NotificationSender,
+    search: &DeploymentSearch,
+) -> Result<(), Error> {
+    let locator = search.locate_unique(&primary)?;
+
+    let conn = primary.get()?;
+    let conn = catalog::Connection::new(conn);
+
+    let site = conn
+        .locate_site(locator.clone())?
+        .ok_or_else(|| anyhow!("failed to locate site for {locator}"))?;
+
+    let changes = match conn.assignment_status(&site)? {
+        Some((_, true)) => {
+            println!("deployment {locator} is already paused");
+            vec![]
+        }
+        Some((node, false)) => {
+            println!("pausing {locator} on {node}");
+            conn.pause_subgraph(&site)?
+        }
+        None => return Err(anyhow!("deployment {locator} is not assigned to any node")),
+    };
+    conn.send_store_event(sender, &StoreEvent::new(changes))?;
+
+    Ok(())
+}
+
+/// Resume indexing of a deployment that was paused with `pause`
+pub fn resume(
+    primary: ConnectionPool,
+    sender: &NotificationSender,
+    search: &DeploymentSearch,
+) -> Result<(), Error> {
+    let locator = search.locate_unique(&primary)?;
+
+    let conn = primary.get()?;
+    let conn = catalog::Connection::new(conn);
+
+    let site = conn
+        .locate_site(locator.clone())?
+        .ok_or_else(|| anyhow!("failed to locate site for {locator}"))?;
+
+    let changes = match conn.assignment_status(&site)? {
+        Some((node, true)) => {
+            println!("resuming {locator} on {node}");
+            conn.resume_subgraph(&site)?
+        }
+        Some((_, false)) => {
+            println!("deployment {locator} is not paused");
+            vec![]
+        }
+        None => return Err(anyhow!("deployment {locator} is not assigned to any node")),
+    };
+    conn.send_store_event(sender, &StoreEvent::new(changes))?;
+
+    Ok(())
+}
+
+pub async fn reassign(
     primary: ConnectionPool,
     sender: &NotificationSender,
+    notifier: &Notifier,
     search: &DeploymentSearch,
     node: String,
 ) -> Result<(), Error> {
@@ -41,19 +110,19 @@ pub fn reassign(
     let site = conn
         .locate_site(locator.clone())?
         .ok_or_else(|| anyhow!("failed to locate site for {locator}"))?;
-    let changes = match conn.assigned_node(&site)? {
+    let (changes, from) = match conn.assigned_node(&site)? {
         Some(cur) => {
             if cur == node {
                 println!("deployment {locator} is already assigned to {cur}");
-                vec![]
+                (vec![], Some(cur))
             } else {
                 println!("reassigning {locator} to {node} (was {cur})");
-                conn.reassign_subgraph(&site, &node)?
+                (conn.reassign_subgraph(&site, &node)?, Some(cur))
             }
         }
         None => {
             println!("assigning {locator} to {node}");
-            conn.assign_subgraph(&site, &node)?
+            (conn.assign_subgraph(&site, &node)?, None)
         }
     };
     conn.send_store_event(sender, &StoreEvent::new(changes))?;
@@ -67,5 +136,129 @@ pub fn reassign(
         println!("warning: this is the only deployment assigned to {node}");
         println!("         are you sure it is spelled correctly?");
     }
+
+    if from.as_ref() != Some(&node) {
+        let event = DeploymentEvent::Reassigned { from, to: node };
+        notifier.send(&site.deployment, &event).await;
+    }
+    Ok(())
+}
+
+fn print_loads(loads: &[(NodeId, catalog::NodeLoad)]) {
+    println!(
+        "{:<30} | {:>12} | {:>13} | {:>11}",
+        "node", "handler time", "blocks behind", "deployments"
+    );
+    println!("{:-<30}-+-{:->12}-+-{:->13}-+-{:->11}", "", "", "", "");
+    for (node, load) in loads {
+        println!(
+            "{:<30} | {:>12.2} | {:>13} | {:>11}",
+            node.as_str(),
+            load.handler_time,
+            load.blocks_behind,
+            load.deployments
+        );
+    }
+}
+
+/// Move deployments between `nodes` so that the time each node spends
+/// processing blocks is about the same. Deployments are moved from the
+/// busiest node to the least busy one for as long as that narrows the gap
+/// between the two; each deployment is moved at most once. Paused
+/// deployments are never moved
+pub async fn rebalance(
+    primary: ConnectionPool,
+    sender: &NotificationSender,
+    notifier: &Notifier,
+    nodes: Vec<String>,
+    dry_run: bool,
+) -> Result<(), Error> {
+    let nodes = nodes
+        .into_iter()
+        .map(|node| NodeId::new(node.clone()).map_err(|()| anyhow!("illegal node id `{}`", node)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if nodes.len() < 2 {
+        return Err(anyhow!("rebalancing needs at least two nodes"));
+    }
+
+    let conn = primary.get()?;
+    let conn = catalog::Connection::new(conn);
+
+    let mut deployments = conn.assignment_loads(&nodes)?;
+    let mut loads = conn.node_loads(&nodes)?;
+
+    println!("Current load:\n");
+    print_loads(&loads);
+
+    let mut moves: Vec<(Site, NodeId, NodeId)> = Vec::new();
+    loop {
+        // unwrap is fine since there are at least two nodes
+        let busiest = (0..loads.len())
+            .max_by(|a, b| loads[*a].1.compare(&loads[*b].1))
+            .unwrap();
+        let idlest = (0..loads.len())
+            .min_by(|a, b| loads[*a].1.compare(&loads[*b].1))
+            .unwrap();
+        let gap = loads[busiest].1.handler_time - loads[idlest].1.handler_time;
+
+        // Moving a deployment with a handler time below the gap narrows
+        // it; the deployment that comes closest to halving it narrows it
+        // the most
+        let candidate = deployments
+            .iter_mut()
+            .filter(|(site, node, load)| {
+                node == &loads[busiest].0
+                    && load.handler_time > 0.0
+                    && load.handler_time < gap
+                    && !moves.iter().any(|(moved, _, _)| moved.id == site.id)
+            })
+            .min_by(|(_, _, a), (_, _, b)| {
+                let a = (a.handler_time - gap / 2.0).abs();
+                let b = (b.handler_time - gap / 2.0).abs();
+                a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+            });
+
+        match candidate {
+            Some((site, node, load)) => {
+                loads[busiest].1.remove(load);
+                loads[idlest].1.add(load);
+                *node = loads[idlest].0.clone();
+                moves.push((site.clone(), loads[busiest].0.clone(), node.clone()));
+            }
+            None => break,
+        }
+    }
+
+    if moves.is_empty() {
+        println!("\nThe load is already balanced, not moving any deployments");
+        return Ok(());
+    }
+
+    println!();
+    for (site, from, to) in &moves {
+        println!("move {} from {} to {}", site.deployment, from, to);
+    }
+    println!("\nLoad after moving deployments:\n");
+    print_loads(&loads);
+
+    if dry_run {
+        println!("\nDry run, not moving any deployments");
+        return Ok(());
+    }
+
+    let mut changes = Vec::new();
+    for (site, _, to) in &moves {
+        changes.extend(conn.reassign_subgraph(site, to)?);
+    }
+    conn.send_store_event(sender, &StoreEvent::new(changes))?;
+
+    for (site, from, to) in moves {
+        let event = DeploymentEvent::Reassigned {
+            from: Some(from),
+            to,
+        };
+        notifier.send(&site.deployment, &event).await;
+    }
+
     Ok(())
 }
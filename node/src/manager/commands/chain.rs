@@ -1,21 +1,38 @@
+use std::collections::HashSet;
+use std::convert::TryFrom;
 use std::sync::Arc;
+use std::time::Duration;
 
-use graph::blockchain::BlockPtr;
+use graph::blockchain::{Block, BlockPtr, BlockchainKind};
 use graph::cheap_clone::CheapClone;
+use graph::endpoint::EndpointMetrics;
+use graph::firehose;
+use graph::prelude::prost::Message;
 use graph::prelude::BlockNumber;
 use graph::prelude::ChainStore as _;
 use graph::prelude::EthereumBlock;
 use graph::prelude::LightEthereumBlockExt as _;
-use graph::prelude::{anyhow, anyhow::bail};
+use graph::prelude::SubgraphStore as _;
+use graph::prelude::{anyhow, anyhow::bail, Logger};
 use graph::{
-    components::store::BlockStore as _, prelude::anyhow::Error, prelude::serde_json as json,
+    components::store::BlockStore as _, components::store::StatusStore as _,
+    data::subgraph::status, prelude::anyhow::Error, prelude::serde_json as json,
 };
+use graph_chain_ethereum::chain::BlockFinality;
+use graph_chain_ethereum::codec;
 use graph_store_postgres::BlockStore;
 use graph_store_postgres::ChainStore;
+use graph_store_postgres::Store;
 use graph_store_postgres::{
     command_support::catalog::block_store, connection_pool::ConnectionPool,
 };
 
+use crate::chain::create_firehose_networks;
+use crate::config::Config;
+use crate::manager::commands::rewind::{pause, resume};
+use crate::manager::deployment::{Deployment, DeploymentSearch};
+use crate::manager::prompt::prompt_for_confirmation;
+
 pub async fn list(primary: ConnectionPool, store: Arc<BlockStore>) -> Result<(), Error> {
     let mut chains = {
         let conn = primary.get()?;
@@ -143,3 +160,265 @@ pub fn remove(primary: ConnectionPool, store: Arc<BlockStore>, name: String) ->
 
     Ok(())
 }
+
+/// Find the block in the block cache that the head of `chain_store` should
+/// be rewound to. Without a `hash`, there must be exactly one block with
+/// number `number` in the cache
+fn rewind_target(
+    chain_store: &ChainStore,
+    number: BlockNumber,
+    hash: Option<String>,
+) -> Result<BlockPtr, Error> {
+    let hashes = chain_store.block_hashes_by_block_number(number)?;
+    match hash {
+        Some(hash) => {
+            let ptr = BlockPtr::try_from((hash.as_str(), number as i64))
+                .map_err(|e| anyhow!("invalid block hash {}: {}", hash, e))?;
+            if !hashes.contains(&ptr.hash) {
+                bail!(
+                    "the block cache for {} has no block with number {} and hash {}",
+                    chain_store.chain,
+                    number,
+                    hash
+                );
+            }
+            Ok(ptr)
+        }
+        None => match hashes.as_slice() {
+            [] => bail!(
+                "the block cache for {} has no block with number {}",
+                chain_store.chain,
+                number
+            ),
+            [hash] => Ok(BlockPtr::new(hash.clone(), number)),
+            _ => {
+                let hashes = hashes
+                    .iter()
+                    .map(|hash| hash.hash_hex())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                bail!(
+                    "the block cache for {} has several blocks with number {}: {}\n\
+                     use --block-hash to pick one",
+                    chain_store.chain,
+                    number,
+                    hashes
+                )
+            }
+        },
+    }
+}
+
+/// Rewind the head of a chain to an earlier block, for example after a
+/// provider served blocks from a bad fork. All deployments on the chain
+/// that have indexed past that block are rewound to it, too
+pub async fn rewind(
+    primary: ConnectionPool,
+    store: Arc<Store>,
+    chain_store: Arc<ChainStore>,
+    block_number: BlockNumber,
+    block_hash: Option<String>,
+    dry_run: bool,
+    force: bool,
+    sleep: Duration,
+) -> Result<(), Error> {
+    let head = match chain_store.cheap_clone().chain_head_ptr().await? {
+        Some(head) => head,
+        None => bail!("chain {} does not have a head block", chain_store.chain),
+    };
+    if head.number <= block_number {
+        bail!(
+            "the head of chain {} is at block {} which is not after block {}",
+            chain_store.chain,
+            head.number,
+            block_number
+        );
+    }
+    let target = rewind_target(&chain_store, block_number, block_hash)?;
+
+    // Find the deployments on the chain that have indexed past the target
+    // block. A deployment can have several names; only look at it once
+    let mut seen = HashSet::new();
+    let deployments: Vec<Deployment> = DeploymentSearch::All
+        .lookup(&primary)?
+        .into_iter()
+        .filter(|deployment| deployment.chain == chain_store.chain)
+        .filter(|deployment| seen.insert(deployment.id))
+        .collect();
+    let ids = deployments.iter().map(|d| d.locator().id).collect();
+    let statuses = store.status(status::Filter::DeploymentIds(ids))?;
+
+    let mut affected = vec![];
+    let mut pruned = false;
+    for deployment in deployments {
+        let chain = statuses
+            .iter()
+            .find(|status| status.id.0 == deployment.id)
+            .and_then(|status| status.chains.first());
+        let (latest, earliest) = match chain {
+            Some(chain) => (
+                chain.latest_block.as_ref().map(|block| block.number()),
+                chain.earliest_block_number,
+            ),
+            None => continue,
+        };
+        if latest.map_or(true, |latest| latest <= target.number) {
+            continue;
+        }
+        if affected.is_empty() {
+            println!(
+                "{:<10} | {:<46} | {:>10} | {:>10} | action",
+                "namespace", "deployment", "latest", "earliest"
+            );
+            println!(
+                "{:-<10}-+-{:-<46}-+-{:-<10}-+-{:-<10}-+-{:-<10}",
+                "", "", "", "", ""
+            );
+        }
+        let action = if earliest > target.number {
+            pruned = true;
+            "can not rewind, pruned"
+        } else {
+            "rewind"
+        };
+        println!(
+            "{:<10} | {:<46} | {:>10} | {:>10} | {}",
+            deployment.namespace,
+            deployment.deployment,
+            latest.unwrap_or_default(),
+            earliest,
+            action
+        );
+        affected.push(deployment);
+    }
+    if affected.is_empty() {
+        println!(
+            "No deployments on {} are past block {}",
+            chain_store.chain, target.number
+        );
+    }
+    println!(
+        "\nRewinding chain {} from block {} to block {} ({})",
+        chain_store.chain,
+        head.number,
+        target.number,
+        target.hash_hex()
+    );
+    if pruned {
+        bail!(
+            "some deployments were pruned past block {} and can not be rewound; \
+             remove them or rewind to a later block",
+            target.number
+        );
+    }
+    if dry_run {
+        println!("Dry run, nothing was changed");
+        return Ok(());
+    }
+
+    let prompt = format!(
+        "This will remove all cached blocks after block {} for {} \
+         and rewind {} deployments.\nProceed?",
+        target.number,
+        chain_store.chain,
+        affected.len()
+    );
+    if !force && !prompt_for_confirmation(&prompt)? {
+        println!("Aborting.");
+        return Ok(());
+    }
+
+    let subgraph_store = store.subgraph_store();
+    pause(&subgraph_store, &affected, sleep)?;
+
+    let deleted = chain_store.rewind_chain_head(&target)?;
+    println!(
+        "\nRewound chain head to block {} and removed {} blocks from the block cache",
+        target.number, deleted
+    );
+
+    println!("\nRewinding deployments");
+    for deployment in &affected {
+        let loc = deployment.locator();
+        subgraph_store.rewind(loc.hash.clone(), target.clone())?;
+        println!("  ... rewound {}", loc);
+    }
+
+    resume(&subgraph_store, &affected)
+}
+
+/// How often `backfill` reports its progress, in blocks
+const BACKFILL_PROGRESS_INTERVAL: BlockNumber = 10_000;
+
+/// Fill the block cache of `chain_store` with the blocks `from..=to` from
+/// a Firehose provider of the chain. The blocks are stored with all their
+/// data, the way they are stored when they are fetched over JSON-RPC, and
+/// replace blocks that are already in the cache. The chain head is not
+/// changed
+pub async fn backfill(
+    logger: Logger,
+    config: &Config,
+    chain_store: Arc<ChainStore>,
+    from: BlockNumber,
+    to: BlockNumber,
+) -> Result<(), Error> {
+    if from < 0 || from > to {
+        bail!("invalid block range {}..={}", from, to);
+    }
+
+    let networks = create_firehose_networks(logger, config, Arc::new(EndpointMetrics::mock()));
+    let endpoint = networks
+        .get(&BlockchainKind::Ethereum)
+        .and_then(|networks| networks.networks.get(&chain_store.chain))
+        .ok_or_else(|| {
+            anyhow!(
+                "chain {} does not have a Firehose provider",
+                chain_store.chain
+            )
+        })?
+        .endpoint()?;
+
+    println!(
+        "Backfilling blocks {} to {} of chain {} from {}",
+        from, to, chain_store.chain, endpoint.provider
+    );
+    let mut stream = endpoint
+        .stream_blocks(firehose::Request {
+            start_block_num: from as i64,
+            stop_block_num: to as u64,
+            final_blocks_only: true,
+            ..Default::default()
+        })
+        .await?;
+
+    let mut count = 0;
+    let mut last = None;
+    while let Some(response) = stream.message().await? {
+        let any = match response.block {
+            Some(any) => any,
+            None => continue,
+        };
+        let block = codec::Block::decode(any.value.as_ref())?;
+        let block: BlockFinality = (&block).try_into()?;
+        let number = block.number();
+        chain_store.upsert_block(Arc::new(block)).await?;
+
+        count += 1;
+        if count % BACKFILL_PROGRESS_INTERVAL == 0 {
+            println!("  stored {} blocks, at block {}", count, number);
+        }
+        last = Some(number);
+    }
+
+    match last {
+        Some(last) if last >= to => {
+            println!("Stored {} blocks", count);
+            Ok(())
+        }
+        _ => bail!(
+            "the Firehose stream ended after {} blocks, before reaching block {}",
+            count,
+            to
+        ),
+    }
+}
@@ -11,12 +11,20 @@ use graph::{
     slog::Logger,
 };
 use graph_chain_ethereum::{NodeCapabilities, ProviderEthRpcMetrics};
-use graph_store_postgres::DeploymentPlacer;
+use graph_store_postgres::{DeploymentPlacer, DeploymentProperties};
 
 use crate::{chain::create_ethereum_networks_for_chain, config::Config};
 
-pub fn place(placer: &dyn DeploymentPlacer, name: &str, network: &str) -> Result<(), Error> {
-    match placer.place(name, network).map_err(|s| anyhow!(s))? {
+pub fn place(
+    placer: &dyn DeploymentPlacer,
+    name: &str,
+    network: &str,
+    properties: DeploymentProperties,
+) -> Result<(), Error> {
+    match placer
+        .place(name, network, &properties)
+        .map_err(|s| anyhow!(s))?
+    {
         None => {
             println!(
                 "no matching placement rule; default placement from JSON RPC call would be used"
@@ -137,6 +145,7 @@ pub async fn provider(
         caps,
         config.node.as_str(),
         adapters
+            .iter()
             .map(|adapter| adapter.provider().to_string())
             .join(", ")
     );
@@ -0,0 +1,23 @@
+use std::sync::Arc;
+
+use graph::prelude::anyhow;
+use graph_store_postgres::connection_pool::ConnectionPool;
+use graph_store_postgres::SubgraphStore;
+
+use crate::manager::deployment::DeploymentSearch;
+
+pub async fn set(
+    store: Arc<SubgraphStore>,
+    primary_pool: ConnectionPool,
+    search: &DeploymentSearch,
+    max: Option<usize>,
+) -> Result<(), anyhow::Error> {
+    let locator = search.locate_unique(&primary_pool)?;
+
+    store.set_max_dynamic_data_sources(&locator, max).await?;
+    match max {
+        Some(max) => println!("deployment {locator} may create at most {max} data sources"),
+        None => println!("deployment {locator} may create any number of data sources"),
+    }
+    Ok(())
+}
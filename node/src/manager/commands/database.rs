@@ -1,7 +1,59 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
 use std::{io::Write, time::Instant};
 
-use graph::prelude::anyhow;
-use graph_store_postgres::connection_pool::PoolCoordinator;
+use graph::prelude::anyhow::{self, anyhow, Context as _};
+use graph_store_postgres::connection_pool::{
+    ConnectionPool, PoolCoordinator, MIGRATIONS_TABLE_DDL,
+};
+use graph_store_postgres::Shard;
+
+/// Print the SQL of the migrations that are pending in each shard, or
+/// write it to `<output>/<shard>.sql` if `output` is set. Shards without
+/// pending migrations are skipped
+pub fn pending_migrations(
+    pools: HashMap<Shard, ConnectionPool>,
+    output: Option<String>,
+) -> Result<(), anyhow::Error> {
+    let mut pools: Vec<_> = pools.into_iter().collect();
+    pools.sort_by(|(shard1, _), (shard2, _)| shard1.as_str().cmp(shard2.as_str()));
+
+    if let Some(output) = &output {
+        fs::create_dir_all(output)
+            .with_context(|| format!("failed to create directory {}", output))?;
+    }
+
+    for (shard, pool) in pools {
+        let pending = pool.pending_migrations()?;
+        if pending.is_empty() {
+            println!("-- shard {}: no pending migrations", shard);
+            continue;
+        }
+
+        let mut sql = format!(
+            "-- {} pending migrations for shard {}\n{}\n\n",
+            pending.len(),
+            shard,
+            MIGRATIONS_TABLE_DDL
+        );
+        for migration in pending {
+            sql.push_str(&migration.script());
+            sql.push('\n');
+        }
+
+        match &output {
+            Some(output) => {
+                let path = Path::new(output).join(format!("{}.sql", shard));
+                fs::write(&path, sql)
+                    .with_context(|| format!("failed to write {}", path.display()))?;
+                println!("-- shard {}: wrote {}", shard, path.display());
+            }
+            None => print!("{}", sql),
+        }
+    }
+    Ok(())
+}
 
 pub async fn remap(
     coord: &PoolCoordinator,
@@ -0,0 +1,26 @@
+use std::sync::Arc;
+
+use graph::prelude::{anyhow, ENV_VARS};
+use graph_store_postgres::connection_pool::ConnectionPool;
+use graph_store_postgres::SubgraphStore;
+
+use crate::manager::deployment::DeploymentSearch;
+
+pub async fn set_size(
+    store: Arc<SubgraphStore>,
+    primary_pool: ConnectionPool,
+    search: &DeploymentSearch,
+    size_kb: Option<usize>,
+) -> Result<(), anyhow::Error> {
+    let locator = search.locate_unique(&primary_pool)?;
+
+    store.set_entity_cache_size(&locator, size_kb).await?;
+    match size_kb {
+        Some(size_kb) => println!("entity cache of deployment {locator} set to {size_kb}kB"),
+        None => println!(
+            "entity cache of deployment {locator} reset to the default of {}kB",
+            ENV_VARS.mappings.entity_cache_size / 1000
+        ),
+    }
+    Ok(())
+}
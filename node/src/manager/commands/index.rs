@@ -1,3 +1,4 @@
+use crate::manager::commands::prune::format_bytes;
 use crate::manager::{color::Terminal, deployment::DeploymentSearch, CmdResult};
 use graph::{
     components::store::DeploymentLocator,
@@ -5,7 +6,7 @@ use graph::{
     prelude::{anyhow, StoreError},
 };
 use graph_store_postgres::{
-    command_support::index::{CreateIndex, Method},
+    command_support::index::{CreateIndex, IndexUsage, Method},
     connection_pool::ConnectionPool,
     SubgraphStore,
 };
@@ -192,3 +193,131 @@ pub async fn drop(
     println!("Dropped index {index_name}");
     Ok(())
 }
+
+/// Whether an unused index can be dropped. Attribute indexes only speed up
+/// queries and manual indexes were created by an operator; all other
+/// indexes are needed by graph-node
+fn is_droppable(usage: &IndexUsage) -> bool {
+    if usage.scans > 0 {
+        return false;
+    }
+    match CreateIndex::parse(usage.defn.clone()) {
+        index @ CreateIndex::Parsed { unique: false, .. } => {
+            index.is_attribute_index() || usage.index_name.starts_with("manual_")
+        }
+        _ => false,
+    }
+}
+
+fn index_kind(usage: &IndexUsage) -> &'static str {
+    let index = CreateIndex::parse(usage.defn.clone());
+    if usage.index_name.starts_with("manual_") {
+        "manual"
+    } else if index.is_attribute_index() {
+        "attribute"
+    } else if index.is_default_index() {
+        "default"
+    } else {
+        "other"
+    }
+}
+
+pub async fn usage(
+    store: Arc<SubgraphStore>,
+    pool: ConnectionPool,
+    search: DeploymentSearch,
+    unused: bool,
+) -> Result<(), anyhow::Error> {
+    let deployment_locator = search.locate_unique(&pool)?;
+    let mut indexes = store.index_usage(&deployment_locator).await?;
+    if unused {
+        indexes.retain(is_droppable);
+    }
+
+    let mut term = Terminal::new();
+    writeln!(
+        term,
+        "{:^25} | {:^40} | {:^9} | {:^10} | {:^9}",
+        "table", "index", "kind", "size", "scans"
+    )?;
+    writeln!(
+        term,
+        "{:-^25}-+-{:-^40}-+-{:-^9}-+-{:-^10}-+-{:-^9}",
+        "", "", "", "", ""
+    )?;
+    for index in &indexes {
+        if is_droppable(index) {
+            term.dim()?;
+        }
+        writeln!(
+            term,
+            "{:<25} | {:<40} | {:<9} | {:>10} | {:>9}",
+            super::stats::abbreviate_table_name(&index.table_name, 25),
+            super::stats::abbreviate_table_name(&index.index_name, 40),
+            index_kind(index),
+            format_bytes(index.size),
+            index.scans
+        )?;
+        term.reset()?;
+    }
+    let total: i64 = indexes.iter().map(|index| index.size).sum();
+    let droppable: i64 = indexes
+        .iter()
+        .filter(|index| is_droppable(index))
+        .map(|index| index.size)
+        .sum();
+    writeln!(
+        term,
+        "{:-^25}-+-{:-^40}-+-{:-^9}-+-{:-^10}-+-{:-^9}",
+        "", "", "", "", ""
+    )?;
+    writeln!(
+        term,
+        "{} indexes with a total size of {}; {} could be freed by dropping unused indexes",
+        indexes.len(),
+        format_bytes(total),
+        format_bytes(droppable)
+    )?;
+    Ok(())
+}
+
+pub async fn drop_unused(
+    store: Arc<SubgraphStore>,
+    pool: ConnectionPool,
+    search: DeploymentSearch,
+    dry_run: bool,
+) -> Result<(), anyhow::Error> {
+    let deployment_locator = search.locate_unique(&pool)?;
+    let indexes: Vec<_> = store
+        .index_usage(&deployment_locator)
+        .await?
+        .into_iter()
+        .filter(is_droppable)
+        .collect();
+
+    if indexes.is_empty() {
+        println!("There are no unused indexes for {deployment_locator}");
+        return Ok(());
+    }
+    for index in &indexes {
+        if dry_run {
+            println!(
+                "Would drop {} on {} ({})",
+                index.index_name,
+                index.table_name,
+                format_bytes(index.size)
+            );
+        } else {
+            store
+                .drop_index_for_deployment(&deployment_locator, &index.index_name)
+                .await?;
+            println!(
+                "Dropped {} on {} ({})",
+                index.index_name,
+                index.table_name,
+                format_bytes(index.size)
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use std::sync::Arc;
+
+use graph::prelude::anyhow;
+use graph_store_postgres::connection_pool::ConnectionPool;
+use graph_store_postgres::SubgraphStore;
+
+use crate::manager::deployment::DeploymentSearch;
+
+pub async fn on(
+    store: Arc<SubgraphStore>,
+    primary_pool: ConnectionPool,
+    search: &DeploymentSearch,
+    message: String,
+) -> Result<(), anyhow::Error> {
+    let locator = search.locate_unique(&primary_pool)?;
+
+    store.set_maintenance(&locator, Some(message)).await?;
+    println!("deployment {locator} is now in maintenance mode");
+    Ok(())
+}
+
+pub async fn off(
+    store: Arc<SubgraphStore>,
+    primary_pool: ConnectionPool,
+    search: &DeploymentSearch,
+) -> Result<(), anyhow::Error> {
+    let locator = search.locate_unique(&primary_pool)?;
+
+    store.set_maintenance(&locator, None).await?;
+    println!("deployment {locator} is no longer in maintenance mode");
+    Ok(())
+}
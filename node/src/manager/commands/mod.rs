@@ -4,16 +4,24 @@ pub mod check_blocks;
 pub mod config;
 pub mod copy;
 pub mod create;
+pub mod data_source_limit;
 pub mod database;
 pub mod drop;
+pub mod entity_cache;
 pub mod index;
 pub mod info;
 pub mod listen;
+pub mod maintenance;
+pub mod poi;
 pub mod prune;
 pub mod query;
+pub mod rate_limit;
 pub mod remove;
+pub mod retry_policy;
 pub mod rewind;
 pub mod run;
 pub mod stats;
 pub mod txn_speed;
 pub mod unused_deployments;
+pub mod validate;
+pub mod webhook;
@@ -0,0 +1,161 @@
+use std::sync::Arc;
+
+use graph::components::store::{DeploymentLocator, StatusStore};
+use graph::data::subgraph::status;
+use graph::prelude::{anyhow::anyhow, hex, BlockNumber, Error};
+use graph::url::Url;
+use graph_server_index_node::{bisect, PoiComparator, Pois};
+use graph_store_postgres::{connection_pool::ConnectionPool, BlockStore, Store, SubgraphStore};
+
+use crate::manager::deployment::DeploymentSearch;
+
+pub async fn diff(
+    store: Arc<Store>,
+    primary: ConnectionPool,
+    search: &DeploymentSearch,
+    indexer: String,
+    block: BlockNumber,
+    start: Option<BlockNumber>,
+) -> Result<(), Error> {
+    let indexer =
+        Url::parse(&indexer).map_err(|e| anyhow!("invalid indexer URL `{}`: {}", indexer, e))?;
+    let locator = search.locate_unique(&primary)?;
+
+    let diff = PoiComparator::new(store, indexer.clone())
+        .diff(&locator.hash, block, start)
+        .await?;
+
+    if diff.agrees() {
+        println!("The proofs of indexing for {locator} agree at block {block}");
+        println!("  POI: 0x{}", hex::encode(diff.local_poi));
+        return Ok(());
+    }
+
+    // unwrap is fine since the proofs of indexing do not agree
+    let divergent = diff.first_divergent_block.unwrap();
+    match diff.last_agreeing_block {
+        Some(agreeing) if agreeing + 1 == divergent => {
+            println!("The proofs of indexing for {locator} first differ at block {divergent}")
+        }
+        Some(agreeing) => {
+            println!(
+                "The proofs of indexing for {locator} agree at block {agreeing} and differ at \
+                 block {divergent}"
+            );
+            println!("The blocks in between have no proofs of indexing on one of the indexers");
+        }
+        None => println!(
+            "The proofs of indexing for {locator} differ at block {divergent}, the first block \
+             that could be compared"
+        ),
+    }
+    println!("  local POI:  0x{}", hex::encode(diff.local_poi));
+    println!("  remote POI: 0x{}", hex::encode(diff.remote_poi));
+    Ok(())
+}
+
+/// Compare the proofs of indexing of the deployment `copy` with those of
+/// `control`, another copy of the same deployment. This checks that a copy
+/// that was indexed with synthetic reverts (`GRAPH_CHAOS_REVERT_DEPLOYMENTS`)
+/// ends up in the same state as one that was indexed without them
+pub async fn compare(
+    store: Arc<Store>,
+    primary: ConnectionPool,
+    copy: &DeploymentSearch,
+    control: &DeploymentSearch,
+    block: BlockNumber,
+    start: Option<BlockNumber>,
+) -> Result<(), Error> {
+    let copy = copy.locate_unique(&primary)?;
+    let control = control.locate_unique(&primary)?;
+    if copy.hash != control.hash {
+        return Err(anyhow!(
+            "{copy} and {control} are not copies of the same deployment"
+        ));
+    }
+    let start = match start {
+        Some(start) => start,
+        None => earliest_block(&store, &copy)?,
+    };
+    if start > block {
+        return Err(anyhow!("the start block {start} is after block {block}"));
+    }
+
+    let subgraph_store = store.subgraph_store();
+    let block_store = store.block_store();
+    let (subgraph_store, block_store, locators) =
+        (&subgraph_store, &block_store, (&copy, &control));
+    let fetch = move |blocks| pois(subgraph_store, block_store, locators, blocks);
+
+    let (copy_poi, control_poi) = match fetch(vec![block]).await?[0] {
+        (Some(copy_poi), Some(control_poi)) => (copy_poi, control_poi),
+        (None, _) => {
+            return Err(anyhow!(
+                "there is no proof of indexing for {copy} at block {block}"
+            ))
+        }
+        (_, None) => {
+            return Err(anyhow!(
+                "there is no proof of indexing for {control} at block {block}"
+            ))
+        }
+    };
+    if copy_poi == control_poi {
+        println!("The proofs of indexing for {copy} and {control} agree at block {block}");
+        println!("  POI: 0x{}", hex::encode(copy_poi));
+        return Ok(());
+    }
+
+    let (agreeing, divergent, (copy_poi, control_poi)) =
+        bisect(start, block, (copy_poi, control_poi), fetch).await?;
+    match agreeing {
+        Some(agreeing) if agreeing + 1 == divergent => println!(
+            "The proofs of indexing for {copy} and {control} first differ at block {divergent}"
+        ),
+        Some(agreeing) => {
+            println!(
+                "The proofs of indexing for {copy} and {control} agree at block {agreeing} and \
+                 differ at block {divergent}"
+            );
+            println!("The blocks in between have no proofs of indexing for one of them");
+        }
+        None => println!(
+            "The proofs of indexing for {copy} and {control} differ at block {divergent}, the \
+             first block that could be compared"
+        ),
+    }
+    println!("  {copy} POI: 0x{}", hex::encode(copy_poi));
+    println!("  {control} POI: 0x{}", hex::encode(control_poi));
+    Ok(())
+}
+
+fn earliest_block(store: &Store, locator: &DeploymentLocator) -> Result<BlockNumber, Error> {
+    let infos = store.status(status::Filter::DeploymentIds(vec![locator.id]))?;
+    infos
+        .first()
+        .and_then(|info| info.chains.first())
+        .map(|chain| chain.earliest_block_number)
+        .ok_or_else(|| anyhow!("deployment {locator} not found"))
+}
+
+/// The proofs of indexing of the two deployments in `locators` at `blocks`
+async fn pois(
+    subgraph_store: &SubgraphStore,
+    block_store: &Arc<BlockStore>,
+    (copy, control): (&DeploymentLocator, &DeploymentLocator),
+    blocks: Vec<BlockNumber>,
+) -> Result<Vec<Pois>, Error> {
+    let mut pois = Vec::with_capacity(blocks.len());
+    for block in blocks {
+        let copy_poi = subgraph_store
+            .public_proof_of_indexing_for(copy, block, block_store.clone())
+            .await?
+            .map(|(_, poi)| poi);
+        let control_poi = subgraph_store
+            .public_proof_of_indexing_for(control, block, block_store.clone())
+            .await?
+            .map(|(_, poi)| poi);
+        pois.push((copy_poi, control_poi));
+    }
+    Ok(pois)
+}
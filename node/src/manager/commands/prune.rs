@@ -6,7 +6,7 @@ use std::{
 };
 
 use graph::{
-    components::store::{PrunePhase, PruneRequest},
+    components::store::{PruneEstimate, PrunePhase, PruneRequest, PruningStrategy},
     env::ENV_VARS,
 };
 use graph::{
@@ -29,10 +29,11 @@ struct Progress {
     table_start: Instant,
     table_rows: usize,
     initial_analyze: bool,
+    dry_run: bool,
 }
 
 impl Progress {
-    fn new() -> Self {
+    fn new(dry_run: bool) -> Self {
         Self {
             start: Instant::now(),
             analyze_start: Instant::now(),
@@ -41,6 +42,7 @@ impl Progress {
             table_start: Instant::now(),
             table_rows: 0,
             initial_analyze: true,
+            dry_run,
         }
     }
 }
@@ -110,7 +112,7 @@ impl PruneReporter for Progress {
         show_stats(stats.as_slice(), HashSet::new()).ok();
         println!();
 
-        if self.initial_analyze {
+        if self.initial_analyze && !self.dry_run {
             // After analyzing, we start the actual work
             println!("Pruning tables");
             print_copy_header();
@@ -156,6 +158,57 @@ impl PruneReporter for Progress {
     }
 }
 
+/// Format a number of bytes in a human-readable way
+pub fn format_bytes(bytes: i64) -> String {
+    const UNITS: [&str; 5] = ["B", "kB", "MB", "GB", "TB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[unit])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+fn show_estimates(estimates: &[PruneEstimate]) {
+    println!(
+        "{:^30} | {:^8} | {:^10} | {:^10} | {:^10} | {:^11}",
+        "table", "strategy", "versions", "removed", "size", "reclaimable"
+    );
+    println!(
+        "{:-^30}-+-{:-^8}-+-{:-^10}-+-{:-^10}-+-{:-^10}-+-{:-^11}",
+        "", "", "", "", "", ""
+    );
+    for estimate in estimates {
+        let strategy = match estimate.strategy {
+            Some(PruningStrategy::Rebuild) => "rebuild",
+            Some(PruningStrategy::Delete) => "delete",
+            None => "-",
+        };
+        println!(
+            "{:<30} | {:^8} | {:>10} | {:>10} | {:>10} | {:>11}",
+            abbreviate_table_name(&estimate.tablename, 30),
+            strategy,
+            estimate.versions,
+            estimate.removed_versions,
+            format_bytes(estimate.total_bytes),
+            format_bytes(estimate.reclaimable_bytes)
+        );
+    }
+    let total: i64 = estimates.iter().map(|e| e.total_bytes).sum();
+    let reclaimable: i64 = estimates.iter().map(|e| e.reclaimable_bytes).sum();
+    println!(
+        "\nPruning would reclaim about {} of {}",
+        format_bytes(reclaimable),
+        format_bytes(total)
+    );
+}
+
 pub async fn run(
     store: Arc<Store>,
     primary_pool: ConnectionPool,
@@ -164,6 +217,7 @@ pub async fn run(
     rebuild_threshold: Option<f64>,
     delete_threshold: Option<f64>,
     once: bool,
+    dry_run: bool,
 ) -> Result<(), anyhow::Error> {
     let history = history as BlockNumber;
     let deployment = search.locate_unique(&primary_pool)?;
@@ -205,7 +259,16 @@ pub async fn run(
         req.delete_threshold = delete_threshold;
     }
 
-    let reporter = Box::new(Progress::new());
+    let reporter = Box::new(Progress::new(dry_run));
+
+    if dry_run {
+        let (_, estimates) = store
+            .subgraph_store()
+            .prune_estimate(reporter, &deployment, req)
+            .await?;
+        show_estimates(&estimates);
+        return Ok(());
+    }
 
     store
         .subgraph_store()
@@ -223,3 +286,17 @@ pub async fn run(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::format_bytes;
+
+    #[test]
+    fn formats_bytes() {
+        assert_eq!("0 B", format_bytes(0));
+        assert_eq!("1023 B", format_bytes(1023));
+        assert_eq!("1.0 kB", format_bytes(1024));
+        assert_eq!("1.5 MB", format_bytes(3 * 512 * 1024));
+        assert_eq!("2048.0 TB", format_bytes(1 << 51));
+    }
+}
@@ -0,0 +1,30 @@
+use std::sync::Arc;
+
+use graph::prelude::anyhow::{self, bail};
+use graph_store_postgres::connection_pool::ConnectionPool;
+use graph_store_postgres::SubgraphStore;
+
+use crate::manager::deployment::DeploymentSearch;
+
+pub async fn set(
+    store: Arc<SubgraphStore>,
+    primary_pool: ConnectionPool,
+    search: &DeploymentSearch,
+    blocks_per_second: Option<u32>,
+) -> Result<(), anyhow::Error> {
+    if blocks_per_second == Some(0) {
+        bail!("the number of blocks per second must be positive");
+    }
+    let locator = search.locate_unique(&primary_pool)?;
+
+    store
+        .set_max_blocks_per_second(&locator, blocks_per_second)
+        .await?;
+    match blocks_per_second {
+        Some(max) => println!(
+            "deployment {locator} processes at most {max} blocks per second while catching up"
+        ),
+        None => println!("deployment {locator} processes blocks as fast as it can"),
+    }
+    Ok(())
+}
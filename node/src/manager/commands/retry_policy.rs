@@ -0,0 +1,25 @@
+use std::sync::Arc;
+
+use graph::components::store::RetryPolicy;
+use graph::prelude::anyhow;
+use graph_store_postgres::connection_pool::ConnectionPool;
+use graph_store_postgres::SubgraphStore;
+
+use crate::manager::deployment::DeploymentSearch;
+
+pub async fn set(
+    store: Arc<SubgraphStore>,
+    primary_pool: ConnectionPool,
+    search: &DeploymentSearch,
+    policy: RetryPolicy,
+) -> Result<(), anyhow::Error> {
+    let locator = search.locate_unique(&primary_pool)?;
+
+    store.set_retry_policy(&locator, policy).await?;
+    let attempts = match policy.max_attempts {
+        Some(max_attempts) => format!("at most {max_attempts} times"),
+        None => "forever".to_string(),
+    };
+    println!("deployment {locator} will be retried {attempts} after non-deterministic errors");
+    Ok(())
+}
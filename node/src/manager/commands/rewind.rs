@@ -5,9 +5,9 @@ use std::{collections::HashSet, convert::TryFrom};
 
 use graph::anyhow::bail;
 use graph::components::store::{BlockStore as _, ChainStore as _};
-use graph::prelude::{anyhow, BlockNumber, BlockPtr, NodeId, SubgraphStore};
-use graph_store_postgres::BlockStore;
+use graph::prelude::{anyhow, BlockNumber, BlockPtr, NodeId, SubgraphStore as _};
 use graph_store_postgres::{connection_pool::ConnectionPool, Store};
+use graph_store_postgres::{BlockStore, SubgraphStore};
 
 use crate::manager::deployment::{Deployment, DeploymentSearch};
 
@@ -55,6 +55,59 @@ async fn block_ptr(
     Ok(block_ptr_to)
 }
 
+const PAUSED: &str = "paused_";
+
+/// Pause `deployments` by assigning them to a `paused_` node, and give the
+/// nodes that index them `sleep` to notice
+pub(crate) fn pause(
+    subgraph_store: &SubgraphStore,
+    deployments: &[Deployment],
+    sleep: Duration,
+) -> Result<(), anyhow::Error> {
+    println!("Pausing deployments");
+    let mut paused = false;
+    for deployment in deployments {
+        if let Some(node) = &deployment.node_id {
+            if !node.starts_with(PAUSED) {
+                let loc = deployment.locator();
+                let node =
+                    NodeId::new(format!("{}{}", PAUSED, node)).expect("paused_ node id is valid");
+                subgraph_store.reassign_subgraph(&loc, &node)?;
+                println!("  ... paused {}", loc);
+                paused = true;
+            }
+        }
+    }
+
+    if paused {
+        // There's no good way to tell that a subgraph has in fact stopped
+        // indexing. We sleep and hope for the best.
+        println!(
+            "\nWaiting {}s to make sure pausing was processed",
+            sleep.as_secs()
+        );
+        thread::sleep(sleep);
+    }
+    Ok(())
+}
+
+/// Assign `deployments` back to the nodes they were assigned to before
+/// they were paused
+pub(crate) fn resume(
+    subgraph_store: &SubgraphStore,
+    deployments: &[Deployment],
+) -> Result<(), anyhow::Error> {
+    println!("Resuming deployments");
+    for deployment in deployments {
+        if let Some(node) = &deployment.node_id {
+            let loc = deployment.locator();
+            let node = NodeId::new(node.clone()).expect("node id is valid");
+            subgraph_store.reassign_subgraph(&loc, &node)?;
+        }
+    }
+    Ok(())
+}
+
 pub async fn run(
     primary: ConnectionPool,
     store: Arc<Store>,
@@ -64,8 +117,6 @@ pub async fn run(
     force: bool,
     sleep: Duration,
 ) -> Result<(), anyhow::Error> {
-    const PAUSED: &str = "paused_";
-
     let subgraph_store = store.subgraph_store();
     let block_store = store.block_store();
 
@@ -91,27 +142,7 @@ pub async fn run(
     )
     .await?;
 
-    println!("Pausing deployments");
-    let mut paused = false;
-    for deployment in &deployments {
-        if let Some(node) = &deployment.node_id {
-            if !node.starts_with(PAUSED) {
-                let loc = deployment.locator();
-                let node =
-                    NodeId::new(format!("{}{}", PAUSED, node)).expect("paused_ node id is valid");
-                subgraph_store.reassign_subgraph(&loc, &node)?;
-                println!("  ... paused {}", loc);
-                paused = true;
-            }
-        }
-    }
-
-    if paused {
-        // There's no good way to tell that a subgraph has in fact stopped
-        // indexing. We sleep and hope for the best.
-        println!("\nWaiting 10s to make sure pausing was processed");
-        thread::sleep(sleep);
-    }
+    pause(&subgraph_store, &deployments, sleep)?;
 
     println!("\nRewinding deployments");
     for deployment in &deployments {
@@ -120,13 +151,5 @@ pub async fn run(
         println!("  ... rewound {}", loc);
     }
 
-    println!("Resuming deployments");
-    for deployment in &deployments {
-        if let Some(node) = &deployment.node_id {
-            let loc = deployment.locator();
-            let node = NodeId::new(node.clone()).expect("node id is valid");
-            subgraph_store.reassign_subgraph(&loc, &node)?;
-        }
-    }
-    Ok(())
+    resume(&subgraph_store, &deployments)
 }
@@ -16,18 +16,22 @@ use graph::anyhow::{bail, format_err};
 use graph::blockchain::client::ChainClient;
 use graph::blockchain::{BlockchainKind, BlockchainMap};
 use graph::cheap_clone::CheapClone;
-use graph::components::store::{BlockStore as _, DeploymentLocator};
+use graph::components::store::{BlockStore as _, DeploymentLocator, StatusStore};
+use graph::components::subgraph::Notifier;
+use graph::data::subgraph::status;
 use graph::endpoint::EndpointMetrics;
 use graph::env::EnvVars;
 use graph::firehose::FirehoseEndpoints;
 use graph::prelude::{
-    anyhow, tokio, BlockNumber, DeploymentHash, LoggerFactory, NodeId, SubgraphAssignmentProvider,
-    SubgraphCountMetric, SubgraphName, SubgraphRegistrar, SubgraphStore,
-    SubgraphVersionSwitchingMode, ENV_VARS,
+    anyhow, hex, reqwest, tokio, BlockNumber, DeploymentHash, LoggerFactory, NodeId,
+    SubgraphAssignmentProvider, SubgraphCountMetric, SubgraphName, SubgraphRegistrar,
+    SubgraphStore, SubgraphVersionSwitchingMode, ENV_VARS,
 };
-use graph::slog::{debug, info, Logger};
+use graph::slog::{debug, info, warn, Logger};
 use graph_chain_ethereum as ethereum;
-use graph_core::polling_monitor::ipfs_service;
+use graph_core::polling_monitor::{
+    arweave_gateways, arweave_service, ipfs_service, object_store_service, ObjectStoreEndpoints,
+};
 use graph_core::{
     LinkResolver, SubgraphAssignmentProvider as IpfsSubgraphAssignmentProvider,
     SubgraphInstanceManager, SubgraphRegistrar as IpfsSubgraphRegistrar,
@@ -64,13 +68,33 @@ pub async fn run(
 
     // FIXME: Hard-coded IPFS config, take it from config file instead?
     let ipfs_clients: Vec<_> = create_ipfs_clients(&logger, &ipfs_url);
-    let ipfs_client = ipfs_clients.first().cloned().expect("Missing IPFS client");
     let ipfs_service = ipfs_service(
-        ipfs_client,
+        ipfs_clients.clone(),
+        None,
         env_vars.mappings.max_ipfs_file_bytes as u64,
         env_vars.mappings.ipfs_timeout,
+        env_vars.mappings.ipfs_unhealthy_backoff,
         env_vars.mappings.ipfs_request_limit,
     );
+    let arweave_service = arweave_service(
+        reqwest::Client::new(),
+        arweave_gateways(&env_vars.mappings.arweave_gateways).expect("invalid Arweave gateway"),
+        env_vars.mappings.max_arweave_file_bytes as u64,
+        env_vars.mappings.arweave_timeout,
+        env_vars.mappings.arweave_request_limit,
+    );
+    let object_store_service = object_store_service(
+        reqwest::Client::new(),
+        ObjectStoreEndpoints::new(
+            &env_vars.mappings.object_store_s3_endpoint,
+            &env_vars.mappings.object_store_s3_region,
+            &env_vars.mappings.object_store_gcs_endpoint,
+        )
+        .expect("invalid object store endpoint"),
+        env_vars.mappings.max_object_store_file_bytes as u64,
+        env_vars.mappings.object_store_timeout,
+        env_vars.mappings.object_store_request_limit,
+    );
 
     let endpoint_metrics = Arc::new(EndpointMetrics::new(
         logger.clone(),
@@ -148,10 +172,11 @@ pub async fn run(
             metrics_registry.clone(),
             chain_store.cheap_clone(),
         )),
-        Arc::new(EthereumRuntimeAdapter {
-            call_cache: chain_store.cheap_clone(),
-            eth_adapters: Arc::new(eth_adapters2),
-        }),
+        Arc::new(EthereumRuntimeAdapter::new(
+            Arc::new(eth_adapters2),
+            chain_store.cheap_clone(),
+            metrics_registry.clone(),
+        )),
         graph::env::ENV_VARS.reorg_threshold,
         ethereum::ENV_VARS.ingestor_polling_interval,
         // We assume the tested chain is always ingestible for now
@@ -166,6 +191,7 @@ pub async fn run(
     let sg_metrics = Arc::new(SubgraphCountMetric::new(metrics_registry.clone()));
 
     let blockchain_map = Arc::new(blockchain_map);
+    let notifier = Arc::new(Notifier::new(&logger, node_id.clone(), vec![]));
     let subgraph_instance_manager = SubgraphInstanceManager::new(
         &logger_factory,
         env_vars.cheap_clone(),
@@ -175,6 +201,9 @@ pub async fn run(
         metrics_registry.clone(),
         link_resolver.cheap_clone(),
         ipfs_service,
+        arweave_service,
+        object_store_service,
+        notifier.cheap_clone(),
         static_filters,
     );
 
@@ -197,6 +226,7 @@ pub async fn run(
         blockchain_map,
         node_id.clone(),
         SubgraphVersionSwitchingMode::Instant,
+        notifier,
     ));
 
     let (name, hash) = if subgraph.contains(':') {
@@ -232,22 +262,38 @@ pub async fn run(
         None,
         None,
         None,
+        false,
     )
     .await?;
 
     let locator = locate(subgraph_store.as_ref(), &hash)?;
 
-    SubgraphAssignmentProvider::start(subgraph_provider.as_ref(), locator, Some(stop_block))
-        .await?;
+    SubgraphAssignmentProvider::start(
+        subgraph_provider.as_ref(),
+        locator.clone(),
+        Some(stop_block),
+    )
+    .await?;
 
-    loop {
+    let failure = loop {
         tokio::time::sleep(Duration::from_millis(1000)).await;
 
-        let block_ptr = subgraph_store
-            .least_block_ptr(&subgraph_hash)
-            .await
-            .unwrap()
-            .unwrap();
+        let info = network_store
+            .status(status::Filter::Deployments(vec![hash.clone()]))?
+            .pop()
+            .ok_or_else(|| format_err!("subgraph {} disappeared", hash))?;
+        if let Some(error) = info.fatal_error {
+            if error.deterministic {
+                break Some(error);
+            }
+            warn!(&logger, "subgraph failed with a non-deterministic error, retrying";
+                  "error" => &error.message);
+        }
+
+        let block_ptr = match subgraph_store.least_block_ptr(&subgraph_hash).await? {
+            Some(block_ptr) => block_ptr,
+            None => continue,
+        };
 
         debug!(&logger, "subgraph block: {:?}", block_ptr);
 
@@ -256,7 +302,30 @@ pub async fn run(
                 &logger,
                 "subgraph now at block {}, reached stop block {}", block_ptr.number, stop_block
             );
-            break;
+            break None;
+        }
+    };
+
+    // Report the state at the stop block, or at the last block that was
+    // processed successfully if the subgraph failed
+    let block = match &failure {
+        Some(_) => subgraph_store
+            .least_block_ptr(&subgraph_hash)
+            .await?
+            .map(|ptr| ptr.number),
+        None => Some(stop_block),
+    };
+    if let Some(block) = block {
+        match network_store
+            .get_public_proof_of_indexing(&subgraph_hash, block)
+            .await?
+        {
+            Some((_, poi)) => println!("POI at block {}: 0x{}", block, hex::encode(poi)),
+            None => println!("No POI at block {}", block),
+        }
+        println!("Entity counts at block {}:", block);
+        for (entity, count) in subgraph_store.entity_counts(&locator, block)? {
+            println!("  {:<30} | {}", entity, count);
         }
     }
 
@@ -276,5 +345,8 @@ pub async fn run(
         .await??;
     }
 
-    Ok(())
+    match failure {
+        Some(error) => bail!("subgraph {} failed: {}", subgraph_hash, error),
+        None => Ok(()),
+    }
 }
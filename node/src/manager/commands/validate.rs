@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use graph::env::EnvVars;
+use graph::prelude::{
+    anyhow::{anyhow, bail, Error},
+    DeploymentHash, Logger, SubgraphValidation, ValidationStage,
+};
+use graph_core::{validate_subgraph, LinkResolver};
+use graph_store_postgres::Store;
+
+use crate::chain::create_ipfs_clients;
+use crate::config::Config;
+
+fn print(validation: &SubgraphValidation) {
+    fn row(label: &str, value: impl std::fmt::Display) {
+        println!("{:<14} | {}", label, value);
+    }
+
+    let list = |values: &[String]| {
+        if values.is_empty() {
+            "ø".to_string()
+        } else {
+            values.join(", ")
+        }
+    };
+    let opt = |value: &Option<String>| value.clone().unwrap_or_else(|| "ø".to_string());
+
+    row("deployment", &validation.deployment);
+    row("valid", validation.valid);
+    row(
+        "kind",
+        validation
+            .kind
+            .map(|kind| kind.to_string())
+            .unwrap_or_else(|| "ø".to_string()),
+    );
+    row("network", opt(&validation.network));
+    row("spec version", opt(&validation.spec_version));
+    row("api versions", list(&validation.api_versions));
+    row("features", list(&validation.features));
+    row("capabilities", opt(&validation.required_capabilities));
+
+    if !validation.diagnostics.is_empty() {
+        println!();
+        for diagnostic in &validation.diagnostics {
+            println!("[{}] {}", diagnostic.stage, diagnostic.message);
+        }
+    }
+}
+
+/// Validate the manifest for `hash` the same way that deploying it would,
+/// without creating a deployment. The manifest's network must be one of
+/// the chains in `config`
+pub async fn run(
+    logger: Logger,
+    store: Arc<Store>,
+    config: &Config,
+    ipfs_url: Vec<String>,
+    hash: String,
+) -> Result<(), Error> {
+    let hash =
+        DeploymentHash::new(hash).map_err(|hash| anyhow!("invalid deployment hash `{}`", hash))?;
+    let env_vars = Arc::new(EnvVars::from_env()?);
+    let ipfs_clients = create_ipfs_clients(&logger, &ipfs_url);
+    let resolver: Arc<dyn graph::prelude::LinkResolver> =
+        Arc::new(LinkResolver::new(ipfs_clients, env_vars));
+
+    let mut validation =
+        validate_subgraph(&logger, store.subgraph_store(), None, &resolver, &hash).await;
+    if let Some(network) = validation.network.clone() {
+        if !config.chains.chains.contains_key(&network) {
+            validation.add(
+                ValidationStage::Network,
+                format!("network `{}` is not configured", network),
+            );
+        }
+    }
+
+    print(&validation);
+    if !validation.valid {
+        bail!("the manifest for {} is not valid", hash);
+    }
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use graph::prelude::{
+    anyhow, hex,
+    rand::{thread_rng, RngCore},
+};
+use graph_store_postgres::connection_pool::ConnectionPool;
+use graph_store_postgres::SubgraphStore;
+
+use crate::manager::deployment::DeploymentSearch;
+
+pub async fn add(
+    store: Arc<SubgraphStore>,
+    primary_pool: ConnectionPool,
+    search: &DeploymentSearch,
+    url: String,
+    entities: Vec<String>,
+    secret: Option<String>,
+) -> Result<(), anyhow::Error> {
+    let locator = search.locate_unique(&primary_pool)?;
+    graph::url::Url::parse(&url)
+        .map_err(|e| anyhow::anyhow!("invalid webhook url {}: {}", url, e))?;
+
+    let secret = secret.unwrap_or_else(|| {
+        let mut secret = [0u8; 32];
+        thread_rng().fill_bytes(&mut secret);
+        hex::encode(secret)
+    });
+    let id = store
+        .add_entity_webhook(&locator, url, entities, secret.clone())
+        .await?;
+    println!("added webhook {id} to deployment {locator}");
+    println!("requests are signed with the secret {secret}");
+    Ok(())
+}
+
+pub async fn list(
+    store: Arc<SubgraphStore>,
+    primary_pool: ConnectionPool,
+    search: &DeploymentSearch,
+) -> Result<(), anyhow::Error> {
+    let locator = search.locate_unique(&primary_pool)?;
+
+    let webhooks = store.entity_webhooks(&locator).await?;
+    if webhooks.is_empty() {
+        println!("deployment {locator} has no webhooks");
+    }
+    for webhook in webhooks {
+        println!(
+            "{:>5}  {}  {}",
+            webhook.id,
+            webhook.url,
+            webhook.entity_types.join(", ")
+        );
+    }
+    Ok(())
+}
+
+pub async fn remove(
+    store: Arc<SubgraphStore>,
+    primary_pool: ConnectionPool,
+    search: &DeploymentSearch,
+    id: i32,
+) -> Result<(), anyhow::Error> {
+    let locator = search.locate_unique(&primary_pool)?;
+
+    if !store.remove_entity_webhook(&locator, id).await? {
+        anyhow::bail!("deployment {locator} has no webhook {id}");
+    }
+    println!("removed webhook {id} from deployment {locator}");
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use std::collections::HashSet;
 use std::fmt;
 use std::str::FromStr;
+use std::time::Duration;
 
 use diesel::{dsl::sql, prelude::*};
 use diesel::{sql_types::Text, PgConnection};
@@ -205,6 +206,8 @@ impl Deployment {
                 "earliest block",
                 "latest block",
                 "chain head block",
+                "blocks/s",
+                "sync eta",
             ]);
         }
 
@@ -226,6 +229,7 @@ impl Deployment {
                 deployment.node_id.unwrap_or("---".to_string()),
             ];
             if let Some(status) = status {
+                let eta = status.sync_eta().map(human_eta);
                 let chain = &status.chains[0];
                 rows.extend(vec![
                     status.synced.to_string(),
@@ -241,6 +245,11 @@ impl Deployment {
                         .as_ref()
                         .map(|b| b.number().to_string())
                         .unwrap_or("-".to_string()),
+                    status
+                        .blocks_per_second
+                        .map(|rate| format!("{:.1}", rate))
+                        .unwrap_or("-".to_string()),
+                    eta.unwrap_or("-".to_string()),
                 ])
             }
             list.append(rows);
@@ -249,3 +258,18 @@ impl Deployment {
         list.render();
     }
 }
+
+/// Format `eta` coarsely since it is only an estimate
+fn human_eta(eta: Duration) -> String {
+    let minutes = eta.as_secs() / 60;
+    let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m", minutes)
+    } else {
+        "<1m".to_string()
+    }
+}
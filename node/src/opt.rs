@@ -1,4 +1,4 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
 use git_testament::{git_testament, render_testament};
 use lazy_static::lazy_static;
 
@@ -14,7 +14,8 @@ lazy_static! {
     name = "graph-node",
     about = "Scalable queries for a decentralized future",
     author = "Graph Protocol, Inc.",
-    version = RENDERED_TESTAMENT.as_str()
+    version = RENDERED_TESTAMENT.as_str(),
+    subcommand_negates_reqs = true
 )]
 pub struct Opt {
     #[clap(
@@ -29,9 +30,10 @@ pub struct Opt {
     pub check_config: bool,
     #[clap(
         long,
-        value_name = "[NAME:]IPFS_HASH",
+        value_name = "[NAME:]{IPFS_HASH|PATH}",
         env = "SUBGRAPH",
-        help = "name and IPFS hash of the subgraph manifest"
+        help = "name and IPFS hash of the subgraph manifest, or the path of a build \
+                directory or manifest to deploy the subgraph from disk without IPFS"
     )]
     pub subgraph: Option<String>,
 
@@ -145,6 +147,13 @@ pub struct Opt {
         help = "Port for the Prometheus metrics server"
     )]
     pub metrics_port: u16,
+    #[clap(
+        long,
+        value_name = "PORT",
+        help = "Port for the gRPC entity server, which is only started if a port is given",
+        env = "GRAPH_GRPC_PORT"
+    )]
+    pub grpc_port: Option<u16>,
     #[clap(
         long,
         default_value = "default",
@@ -222,6 +231,53 @@ pub struct Opt {
         help = "Base URL for forking subgraphs"
     )]
     pub fork_base: Option<String>,
+
+    #[clap(subcommand)]
+    pub cmd: Option<Command>,
+}
+
+#[derive(Clone, Debug, Subcommand)]
+pub enum Command {
+    /// Run a node for local subgraph development
+    ///
+    /// Postgres and IPFS are started in docker containers that are removed
+    /// when the node is stopped with Ctrl-C unless `--postgres-url` or `--config` and
+    /// `--ipfs` say where to find them. The subgraph in the build directory
+    /// is deployed, and deployed again whenever the build directory changes.
+    /// Options like `--ethereum-rpc` go before `dev`
+    Dev(DevOpt),
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct DevOpt {
+    #[clap(
+        default_value = "build",
+        help = "the directory with the `subgraph.yaml` that `graph build` writes"
+    )]
+    pub build_dir: String,
+    #[clap(
+        long,
+        default_value = "dev",
+        help = "the name to deploy the subgraph under"
+    )]
+    pub name: String,
+    #[clap(
+        long,
+        default_value = "postgres:14",
+        help = "the docker image to run Postgres from"
+    )]
+    pub postgres_image: String,
+    #[clap(
+        long,
+        default_value = "ipfs/kubo:v0.18.1",
+        help = "the docker image to run IPFS from"
+    )]
+    pub ipfs_image: String,
+    #[clap(
+        long,
+        help = "deploy the subgraph once instead of whenever the build directory changes"
+    )]
+    pub no_watch: bool,
 }
 
 impl From<Opt> for config::Opt {
@@ -0,0 +1,564 @@
+//! Reload the configuration file of a running node. Only changes that can
+//! be applied without disrupting the node are accepted: the sizes of
+//! connection pools, the deployment rules, and the JSON-RPC providers for
+//! Ethereum chains. A reload that contains any other change is rejected as
+//! a whole, and the node keeps running with its current configuration
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use graph::components::store::{BlockStore as _, ChainStore as _};
+use graph::prelude::{
+    anyhow, async_trait, info, o, serde_json, tokio::sync::Mutex, warn, Arc, CheapClone,
+    ConfigReloader, Error, Logger,
+};
+use graph_chain_ethereum::{EthereumNetworkAdapters, EthereumNetworks, ProviderEthRpcMetrics};
+use graph_store_postgres::{connection_pool::ConnectionPool, BlockStore, Shard as ShardName};
+
+use crate::chain::{connect_ethereum_networks, create_ethereum_networks_for_chain};
+use crate::config::{Config, Provider, ProviderDetails, ReloadablePlacer, Shard};
+use crate::store_builder::StoreBuilder;
+
+/// A change in the configuration that can be applied to a running node
+#[derive(Clone, Debug, PartialEq)]
+enum Change {
+    /// The size of the main and the fdw pool of a shard changed
+    MainPool {
+        shard: String,
+        from: (u32, u32),
+        to: (u32, u32),
+    },
+    /// The size of the pool for a replica changed. `index` is the position
+    /// of the replica in the configuration
+    ReplicaPool {
+        shard: String,
+        replica: String,
+        index: usize,
+        from: u32,
+        to: u32,
+    },
+    /// The JSON-RPC providers of a chain changed
+    Providers {
+        chain: String,
+        added: Vec<String>,
+        removed: Vec<String>,
+        changed: Vec<String>,
+    },
+    DeploymentRules,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::MainPool { shard, from, to } => write!(
+                f,
+                "pool size for shard {} changed from {} (fdw: {}) to {} (fdw: {})",
+                shard, from.0, from.1, to.0, to.1
+            ),
+            Change::ReplicaPool {
+                shard,
+                replica,
+                from,
+                to,
+                ..
+            } => write!(
+                f,
+                "pool size for replica {} of shard {} changed from {} to {}",
+                replica, shard, from, to
+            ),
+            Change::Providers {
+                chain,
+                added,
+                removed,
+                changed,
+            } => write!(
+                f,
+                "providers for chain {} changed: added [{}], removed [{}], changed [{}]",
+                chain,
+                added.join(", "),
+                removed.join(", "),
+                changed.join(", ")
+            ),
+            Change::DeploymentRules => write!(f, "deployment rules changed"),
+        }
+    }
+}
+
+fn json_eq<T: serde::Serialize>(a: &T, b: &T) -> bool {
+    serde_json::to_value(a).ok() == serde_json::to_value(b).ok()
+}
+
+/// The JSON-RPC providers in `providers`, keyed by their label
+fn web3_providers(providers: &[Provider]) -> BTreeMap<&str, &Provider> {
+    providers
+        .iter()
+        .filter(|p| {
+            matches!(
+                p.details,
+                ProviderDetails::Web3(_) | ProviderDetails::Web3Call(_)
+            )
+        })
+        .map(|p| (p.label.as_str(), p))
+        .collect()
+}
+
+/// All providers in `providers` that are not JSON-RPC providers
+fn other_providers(providers: &[Provider]) -> Vec<&Provider> {
+    providers
+        .iter()
+        .filter(|p| {
+            !matches!(
+                p.details,
+                ProviderDetails::Web3(_) | ProviderDetails::Web3Call(_)
+            )
+        })
+        .collect()
+}
+
+/// Compare the current configuration `old` with `new`. Returns the changes
+/// that can be applied to a running node, or, if there are changes that
+/// require a restart, a description of each of those
+fn diff(old: &Config, new: &Config) -> Result<Vec<Change>, Vec<String>> {
+    let mut changes = Vec::new();
+    let mut rejected = Vec::new();
+
+    if !json_eq(&old.general, &new.general) {
+        rejected.push("the [general] section changed".to_string());
+    }
+
+    for name in old
+        .stores
+        .keys()
+        .filter(|name| !new.stores.contains_key(*name))
+    {
+        rejected.push(format!("shard {} was removed", name));
+    }
+    for (name, shard) in &new.stores {
+        let old_shard = match old.stores.get(name) {
+            Some(old_shard) => old_shard,
+            None => {
+                rejected.push(format!("shard {} was added", name));
+                continue;
+            }
+        };
+        if old_shard.connection != shard.connection {
+            rejected.push(format!("the connection for shard {} changed", name));
+        }
+        if old_shard.weight != shard.weight {
+            rejected.push(format!("the weight of shard {} changed", name));
+        }
+
+        let size = |shard: &Shard| {
+            let pool_size = shard.pool_size.size_for(&new.node, name)?;
+            let fdw_pool_size = shard.fdw_pool_size.size_for(&new.node, name)?;
+            Ok::<_, Error>((pool_size, fdw_pool_size))
+        };
+        match (size(old_shard), size(shard)) {
+            (Ok(from), Ok(to)) if from == to => {}
+            (Ok(from), Ok(to)) if from.0 == 0 || to.0 == 0 => rejected.push(format!(
+                "the pool for shard {} was enabled or disabled",
+                name
+            )),
+            (Ok(from), Ok(to)) => changes.push(Change::MainPool {
+                shard: name.clone(),
+                from,
+                to,
+            }),
+            (_, Err(e)) | (Err(e), _) => rejected.push(e.to_string()),
+        }
+
+        if old_shard.replicas.keys().ne(shard.replicas.keys()) {
+            rejected.push(format!("the replicas of shard {} changed", name));
+            continue;
+        }
+        for (index, ((replica_name, old_replica), replica)) in old_shard
+            .replicas
+            .iter()
+            .zip(shard.replicas.values())
+            .enumerate()
+        {
+            if old_replica.connection != replica.connection || old_replica.weight != replica.weight
+            {
+                rejected.push(format!(
+                    "the connection or weight of replica {} of shard {} changed",
+                    replica_name, name
+                ));
+            }
+            let from = old_replica.pool_size.size_for(&new.node, name);
+            let to = replica.pool_size.size_for(&new.node, name);
+            match (from, to) {
+                (Ok(from), Ok(to)) if from == to => {}
+                (Ok(from), Ok(to)) if from == 0 || to == 0 => rejected.push(format!(
+                    "the pool for replica {} of shard {} was enabled or disabled",
+                    replica_name, name
+                )),
+                (Ok(from), Ok(to)) => changes.push(Change::ReplicaPool {
+                    shard: name.clone(),
+                    replica: replica_name.clone(),
+                    index,
+                    from,
+                    to,
+                }),
+                (_, Err(e)) | (Err(e), _) => rejected.push(e.to_string()),
+            }
+        }
+    }
+
+    if old.chains.ingestor != new.chains.ingestor {
+        rejected.push("the block ingestor changed".to_string());
+    }
+    for name in old
+        .chains
+        .chains
+        .keys()
+        .filter(|name| !new.chains.chains.contains_key(*name))
+    {
+        rejected.push(format!("chain {} was removed", name));
+    }
+    for (name, chain) in &new.chains.chains {
+        let old_chain = match old.chains.chains.get(name) {
+            Some(old_chain) => old_chain,
+            None => {
+                rejected.push(format!("chain {} was added", name));
+                continue;
+            }
+        };
+        if old_chain.shard != chain.shard || old_chain.protocol != chain.protocol {
+            rejected.push(format!("the shard or protocol of chain {} changed", name));
+        }
+        if other_providers(&old_chain.providers) != other_providers(&chain.providers) {
+            rejected.push(format!(
+                "the firehose or substreams providers of chain {} changed",
+                name
+            ));
+        }
+
+        let old_web3 = web3_providers(&old_chain.providers);
+        let web3 = web3_providers(&chain.providers);
+        if old_web3 == web3 {
+            continue;
+        }
+        if web3.is_empty() {
+            rejected.push(format!(
+                "all JSON-RPC providers of chain {} were removed",
+                name
+            ));
+            continue;
+        }
+        let added: Vec<_> = web3
+            .keys()
+            .filter(|label| !old_web3.contains_key(*label))
+            .map(|label| label.to_string())
+            .collect();
+        let removed: Vec<_> = old_web3
+            .keys()
+            .filter(|label| !web3.contains_key(*label))
+            .map(|label| label.to_string())
+            .collect();
+        let changed: Vec<_> = web3
+            .iter()
+            .filter(|(label, provider)| {
+                old_web3
+                    .get(*label)
+                    .map(|old| old != *provider)
+                    .unwrap_or(false)
+            })
+            .map(|(label, _)| label.to_string())
+            .collect();
+        changes.push(Change::Providers {
+            chain: name.clone(),
+            added,
+            removed,
+            changed,
+        });
+    }
+
+    if !json_eq(&old.deployment, &new.deployment) {
+        changes.push(Change::DeploymentRules);
+    }
+
+    if !json_eq(&old.tls, &new.tls) {
+        rejected.push("the [tls] section changed".to_string());
+    }
+
+    if rejected.is_empty() {
+        Ok(changes)
+    } else {
+        Err(rejected)
+    }
+}
+
+/// Reloads the configuration file and applies the changes in it to the
+/// running node
+pub struct Reloader {
+    logger: Logger,
+    /// The configuration file; `None` if the configuration was generated
+    /// from command line arguments
+    path: Option<String>,
+    /// The configuration that is currently in effect. Holding the lock
+    /// also makes sure that only one reload runs at a time
+    config: Mutex<Config>,
+    placer: Arc<ReloadablePlacer>,
+    pools: HashMap<ShardName, ConnectionPool>,
+    replica_pools: HashMap<ShardName, Vec<ConnectionPool>>,
+    /// The adapters that the chains on this node use
+    eth_networks: EthereumNetworks,
+    eth_rpc_metrics: Arc<ProviderEthRpcMetrics>,
+    block_store: Arc<BlockStore>,
+}
+
+impl Reloader {
+    pub fn new(
+        logger: &Logger,
+        path: Option<String>,
+        config: Config,
+        store_builder: &StoreBuilder,
+        block_store: Arc<BlockStore>,
+        eth_networks: EthereumNetworks,
+        eth_rpc_metrics: Arc<ProviderEthRpcMetrics>,
+    ) -> Self {
+        Reloader {
+            logger: logger.new(o!("component" => "ConfigReloader")),
+            path,
+            config: Mutex::new(config),
+            placer: store_builder.placer(),
+            pools: store_builder.pools(),
+            replica_pools: store_builder.replica_pools(),
+            eth_networks,
+            eth_rpc_metrics,
+            block_store,
+        }
+    }
+
+    /// Connect to the providers for `chain` in `config` and check that
+    /// they are for the same chain as the one this node has been using
+    async fn connect(
+        &self,
+        config: &Config,
+        chain: &str,
+    ) -> Result<EthereumNetworkAdapters, Error> {
+        let metrics = Arc::new(
+            self.eth_networks
+                .metrics
+                .with_providers(&config.chains.providers()),
+        );
+        let networks = create_ethereum_networks_for_chain(
+            &self.logger,
+            self.eth_rpc_metrics.cheap_clone(),
+            config,
+            chain,
+            metrics,
+        )
+        .await?;
+        let (mut networks, idents) = connect_ethereum_networks(&self.logger, networks).await;
+
+        let genesis = self
+            .block_store
+            .chain_store(chain)
+            .ok_or_else(|| anyhow!("chain {} is not known to the block store", chain))?
+            .genesis_block_ptr()?;
+        for ident in idents.into_iter().flat_map(|(_, idents)| idents) {
+            if ident.genesis_block_hash != genesis.hash {
+                return Err(anyhow!(
+                    "a provider for chain {} has genesis block {} but the chain has genesis block {}",
+                    chain,
+                    ident.genesis_block_hash,
+                    genesis.hash
+                ));
+            }
+        }
+
+        match networks.networks.remove(chain) {
+            Some(adapters) if !adapters.adapters().is_empty() => Ok(adapters),
+            _ => Err(anyhow!(
+                "none of the new providers for chain {} could be reached",
+                chain
+            )),
+        }
+    }
+
+    fn resize(&self, change: &Change) -> Result<(), Error> {
+        match change {
+            Change::MainPool { shard, to, .. } => {
+                let name = ShardName::new(shard.clone())?;
+                if let Some(pool) = self.pools.get(&name) {
+                    pool.resize(to.0, Some(to.1))?;
+                }
+            }
+            Change::ReplicaPool {
+                shard, index, to, ..
+            } => {
+                let name = ShardName::new(shard.clone())?;
+                if let Some(pool) = self
+                    .replica_pools
+                    .get(&name)
+                    .and_then(|pools| pools.get(*index))
+                {
+                    pool.resize(*to, None)?;
+                }
+            }
+            Change::Providers { .. } | Change::DeploymentRules => {}
+        }
+        Ok(())
+    }
+
+    async fn reload_inner(&self) -> Result<Vec<Change>, Error> {
+        let path = self.path.as_ref().ok_or_else(|| {
+            anyhow!("the configuration was generated from command line arguments and can not be reloaded")
+        })?;
+
+        let mut config = self.config.lock().await;
+        let new = Config::from_file(&self.logger, path, config.node.as_str())?;
+        let changes = diff(&config, &new).map_err(|rejected| {
+            anyhow!(
+                "the configuration was not reloaded since these changes require a restart: {}",
+                rejected.join("; ")
+            )
+        })?;
+
+        // Connect to all new providers before changing anything so that a
+        // failure leaves the node as it was
+        let mut adapters = Vec::new();
+        for change in &changes {
+            if let Change::Providers { chain, .. } = change {
+                if let Some(running) = self.eth_networks.networks.get(chain) {
+                    adapters.push((running, self.connect(&new, chain).await?));
+                }
+            }
+        }
+
+        for change in &changes {
+            info!(self.logger, "Applying configuration change"; "change" => change.to_string());
+            self.resize(change)?;
+        }
+        if changes.contains(&Change::DeploymentRules) {
+            self.placer.replace(new.deployment.clone());
+        }
+        // Subgraphs that are already running keep using the adapters they
+        // picked; the new adapters are used whenever an adapter is chosen
+        for (running, new_adapters) in adapters {
+            running.replace(&new_adapters);
+        }
+
+        *config = new;
+        Ok(changes)
+    }
+}
+
+#[async_trait]
+impl ConfigReloader for Reloader {
+    async fn reload(&self) -> Result<Vec<String>, Error> {
+        match self.reload_inner().await {
+            Ok(changes) => {
+                if changes.is_empty() {
+                    info!(self.logger, "Reloaded configuration without changes");
+                }
+                Ok(changes.iter().map(|change| change.to_string()).collect())
+            }
+            Err(e) => {
+                warn!(self.logger, "Failed to reload configuration"; "error" => e.to_string());
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, Change};
+    use crate::config::Config;
+
+    const CONFIG: &str = r#"
+        [store]
+        [store.primary]
+        connection = "postgresql://postgres:1.2.3.4:5432/graph"
+        pool_size = 10
+        [store.primary.replicas.repl1]
+        connection = "postgresql://postgres:1.2.3.5:5432/graph"
+        pool_size = 5
+
+        [chains]
+        ingestor = "default"
+        [chains.mainnet]
+        shard = "primary"
+        provider = [
+            { label = "mainnet-0", url = "http://rpc.mainnet.io", features = ["archive"] },
+            { label = "firehose", details = { type = "firehose", url = "http://localhost:9000", features = [] } },
+        ]
+
+        [deployment]
+        [[deployment.rule]]
+        indexers = [ "default" ]
+    "#;
+
+    fn config(toml: &str) -> Config {
+        Config::from_str(toml, "default").unwrap()
+    }
+
+    #[test]
+    fn accepts_safe_changes() {
+        let old = config(CONFIG);
+        assert_eq!(Ok(vec![]), diff(&old, &old));
+
+        let new = config(
+            &CONFIG
+                .replace("pool_size = 10", "pool_size = 20")
+                .replace("pool_size = 5", "pool_size = 7")
+                .replace(
+                    r#"features = ["archive"] },"#,
+                    r#"features = ["archive"] },
+            { label = "mainnet-1", url = "http://rpc2.mainnet.io", features = [] },"#,
+                )
+                .replace(r#"[ "default" ]"#, r#"[ "default", "other" ]"#),
+        );
+        assert_eq!(
+            Ok(vec![
+                Change::MainPool {
+                    shard: "primary".to_string(),
+                    from: (10, 5),
+                    to: (20, 5),
+                },
+                Change::ReplicaPool {
+                    shard: "primary".to_string(),
+                    replica: "repl1".to_string(),
+                    index: 0,
+                    from: 5,
+                    to: 7,
+                },
+                Change::Providers {
+                    chain: "mainnet".to_string(),
+                    added: vec!["mainnet-1".to_string()],
+                    removed: vec![],
+                    changed: vec![],
+                },
+                Change::DeploymentRules,
+            ]),
+            diff(&old, &new)
+        );
+    }
+
+    #[test]
+    fn rejects_unsafe_changes() {
+        let old = config(CONFIG);
+
+        let new = config(&CONFIG.replace("1.2.3.4", "1.2.3.6"));
+        assert_eq!(1, diff(&old, &new).unwrap_err().len());
+
+        let new = config(&CONFIG.replace("localhost:9000", "localhost:9001"));
+        assert_eq!(1, diff(&old, &new).unwrap_err().len());
+
+        let new = config(&CONFIG.replace(r#"ingestor = "default""#, r#"ingestor = "other""#));
+        assert_eq!(1, diff(&old, &new).unwrap_err().len());
+
+        // Removing all JSON-RPC providers is not allowed, even if the
+        // rest of the changes are fine
+        let new = config(
+            &CONFIG
+                .replace(
+                    r#"{ label = "mainnet-0", url = "http://rpc.mainnet.io", features = ["archive"] },"#,
+                    "",
+                )
+                .replace("pool_size = 10", "pool_size = 20"),
+        );
+        assert_eq!(1, diff(&old, &new).unwrap_err().len());
+    }
+}
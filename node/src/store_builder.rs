@@ -14,16 +14,18 @@ use graph_store_postgres::connection_pool::{
 };
 use graph_store_postgres::{
     BlockStore as DieselBlockStore, ChainHeadUpdateListener as PostgresChainHeadUpdateListener,
-    ChainStoreMetrics, NotificationSender, Shard as ShardName, Store as DieselStore, SubgraphStore,
-    SubscriptionManager, PRIMARY_SHARD,
+    ChainStoreMetrics, DeploymentPlacer, NotificationSender, Shard as ShardName,
+    Store as DieselStore, SubgraphStore, SubscriptionManager, PRIMARY_SHARD,
 };
 
-use crate::config::{Config, Shard};
+use crate::config::{Config, ReloadablePlacer, Shard};
 
 pub struct StoreBuilder {
     logger: Logger,
     subgraph_store: Arc<SubgraphStore>,
     pools: HashMap<ShardName, ConnectionPool>,
+    replica_pools: HashMap<ShardName, Vec<ConnectionPool>>,
+    placer: Arc<ReloadablePlacer>,
     subscription_manager: Arc<SubscriptionManager>,
     chain_head_update_listener: Arc<PostgresChainHeadUpdateListener>,
     /// Map network names to the shards where they are/should be stored
@@ -51,10 +53,12 @@ impl StoreBuilder {
             registry.clone(),
         ));
 
-        let (store, pools, coord) = Self::make_subgraph_store_and_pools(
+        let placer = Arc::new(ReloadablePlacer::new(config.deployment.clone()));
+        let (store, pools, replica_pools, coord) = Self::make_subgraph_store_and_pools(
             logger,
             node,
             config,
+            placer.cheap_clone(),
             fork_base,
             registry.cheap_clone(),
         );
@@ -81,6 +85,8 @@ impl StoreBuilder {
             logger: logger.cheap_clone(),
             subgraph_store: store,
             pools,
+            replica_pools,
+            placer,
             subscription_manager,
             chain_head_update_listener,
             chains,
@@ -89,18 +95,20 @@ impl StoreBuilder {
         }
     }
 
-    /// Make a `ShardedStore` across all configured shards, and also return
-    /// the main connection pools for each shard, but not any pools for
-    /// replicas
+    /// Make a `ShardedStore` across all configured shards that places new
+    /// deployments with `placer`, and also return the main connection pools
+    /// and the pools for the replicas of each shard
     pub fn make_subgraph_store_and_pools(
         logger: &Logger,
         node: &NodeId,
         config: &Config,
+        placer: Arc<dyn DeploymentPlacer + Send + Sync + 'static>,
         fork_base: Option<Url>,
         registry: Arc<MetricsRegistry>,
     ) -> (
         Arc<SubgraphStore>,
         HashMap<ShardName, ConnectionPool>,
+        HashMap<ShardName, Vec<ConnectionPool>>,
         Arc<PoolCoordinator>,
     ) {
         let notification_sender = Arc::new(NotificationSender::new(registry.cheap_clone()));
@@ -148,17 +156,22 @@ impl StoreBuilder {
                 .iter()
                 .map(|(name, pool, _, _)| (name.clone(), pool.clone())),
         );
+        let replica_pools: HashMap<_, _> = HashMap::from_iter(
+            shards
+                .iter()
+                .map(|(name, _, replicas, _)| (name.clone(), replicas.clone())),
+        );
 
         let store = Arc::new(SubgraphStore::new(
             logger,
             shards,
-            Arc::new(config.deployment.clone()),
+            placer,
             notification_sender,
             fork_base,
             registry,
         ));
 
-        (store, pools, coord)
+        (store, pools, replica_pools, coord)
     }
 
     pub fn make_store(
@@ -280,14 +293,14 @@ impl StoreBuilder {
 
     /// Return a store that combines both a `Store` for subgraph data
     /// and a `BlockStore` for all chain related data
-    pub fn network_store(self, networks: Vec<(String, Vec<ChainIdentifier>)>) -> Arc<DieselStore> {
+    pub fn network_store(&self, networks: Vec<(String, Vec<ChainIdentifier>)>) -> Arc<DieselStore> {
         Self::make_store(
             &self.logger,
-            self.pools,
-            self.subgraph_store,
-            self.chains,
+            self.pools.clone(),
+            self.subgraph_store.cheap_clone(),
+            self.chains.clone(),
             networks,
-            self.registry,
+            self.registry.cheap_clone(),
         )
     }
 
@@ -302,4 +315,20 @@ impl StoreBuilder {
     pub fn primary_pool(&self) -> ConnectionPool {
         self.pools.get(&*PRIMARY_SHARD).unwrap().clone()
     }
+
+    /// The main connection pools for each shard
+    pub fn pools(&self) -> HashMap<ShardName, ConnectionPool> {
+        self.pools.clone()
+    }
+
+    /// The connection pools for the replicas of each shard, in the order
+    /// in which the replicas appear in the configuration
+    pub fn replica_pools(&self) -> HashMap<ShardName, Vec<ConnectionPool>> {
+        self.replica_pools.clone()
+    }
+
+    /// The placer that the subgraph store uses for new deployments
+    pub fn placer(&self) -> Arc<ReloadablePlacer> {
+        self.placer.cheap_clone()
+    }
 }
@@ -105,6 +105,8 @@ async fn test_valid_module_and_store_with_timeout(
         ),
         host_metrics,
         timeout,
+        false,
+        ENV_VARS.max_gas_per_handler,
         experimental_features,
     )
     .unwrap();
@@ -1015,6 +1017,49 @@ async fn entity_store_v0_0_5() {
     test_entity_store(API_VERSION_0_0_5).await;
 }
 
+async fn test_store_set_validation_error(api_version: Version) {
+    let (mut module, _, _) = test_valid_module_and_store(
+        "storeSetValidationError",
+        mock_data_source(
+            &wasm_file_path("store.wasm", api_version.clone()),
+            api_version.clone(),
+        ),
+        api_version,
+    )
+    .await;
+
+    // Put an entity whose id does not match its key into the cache so that
+    // `loadAndSetUserName` loads it and tries to save it under `alex`
+    let mut bob = Entity::new();
+    bob.set("id", "bob");
+    bob.set("name", "Bob");
+    module
+        .instance_ctx_mut()
+        .ctx
+        .state
+        .entity_cache
+        .append(vec![EntityOperation::Set {
+            key: EntityKey::data("User".to_string(), "alex".to_string()),
+            data: bob,
+        }]);
+
+    let err = module
+        .invoke_export2_void("loadAndSetUserName", "alex", "Alex")
+        .unwrap_err();
+    assert!(err.to_string().contains("conflicts with ID"));
+    assert!(module.instance_ctx().deterministic_host_trap);
+}
+
+#[tokio::test]
+async fn store_set_validation_error_v0_0_4() {
+    test_store_set_validation_error(API_VERSION_0_0_4).await;
+}
+
+#[tokio::test]
+async fn store_set_validation_error_v0_0_5() {
+    test_store_set_validation_error(API_VERSION_0_0_5).await;
+}
+
 fn test_detect_contract_calls(api_version: Version) {
     let data_source_without_calls = mock_data_source(
         &wasm_file_path("abi_store_value.wasm", api_version.clone()),
@@ -245,6 +245,24 @@ async fn test_abi_ethabi_token_identity(api_version: Version) {
     let new_token: Token = module.asc_get(new_token_ptr).unwrap();
 
     assert_eq!(new_token, token_array_nested);
+
+    // Tuples and fixed arrays nested in arrays, as in event parameters of
+    // type `(uint256,(address,string[])[2])[]`
+    let inner = Token::Tuple(vec![
+        Token::Address(address),
+        Token::Array(vec![Token::String("a".into()), Token::String("b".into())]),
+    ]);
+    let token_deeply_nested = Token::Array(vec![Token::Tuple(vec![
+        Token::Uint(U256::from(7)),
+        Token::FixedArray(vec![inner.clone(), inner]),
+    ])]);
+    let new_array_obj: AscEnumArray<EthereumValueKind> =
+        module.invoke_export1("token_to_array", &token_deeply_nested);
+
+    let new_token_ptr = module.takes_ptr_returns_ptr("token_from_array", new_array_obj);
+    let new_token: Token = module.asc_get(new_token_ptr).unwrap();
+
+    assert_eq!(new_token, token_deeply_nested);
 }
 
 /// Test a roundtrip Token -> Payload -> Token identity conversion through asc,
@@ -422,6 +422,12 @@ impl From<EnumPayload> for i32 {
     }
 }
 
+impl From<EnumPayload> for i64 {
+    fn from(payload: EnumPayload) -> i64 {
+        payload.0 as i64
+    }
+}
+
 impl From<EnumPayload> for f64 {
     fn from(payload: EnumPayload) -> f64 {
         f64::from_bits(payload.0)
@@ -546,6 +552,7 @@ pub enum StoreValueKind {
     Null,
     Bytes,
     BigInt,
+    Int8,
 }
 
 impl StoreValueKind {
@@ -561,6 +568,7 @@ impl StoreValueKind {
             Value::Null => StoreValueKind::Null,
             Value::Bytes(_) => StoreValueKind::Bytes,
             Value::BigInt(_) => StoreValueKind::BigInt,
+            Value::Int8(_) => StoreValueKind::Int8,
         }
     }
 }
@@ -8,9 +8,11 @@ use futures03::channel::oneshot::channel;
 use graph::blockchain::{Blockchain, HostFn, RuntimeAdapter};
 use graph::components::store::{EnsLookup, SubgraphFork};
 use graph::components::subgraph::{MappingError, SharedProofOfIndexing};
+use graph::data::subgraph::HandlerLimits;
 use graph::data_source::{
     DataSource, DataSourceTemplate, MappingTrigger, TriggerData, TriggerWithHandler,
 };
+use graph::log::telemetry;
 use graph::prelude::{
     RuntimeHost as RuntimeHostTrait, RuntimeHostBuilder as RuntimeHostBuilderTrait, *,
 };
@@ -62,6 +64,7 @@ where
         logger: Logger,
         subgraph_id: DeploymentHash,
         metrics: Arc<HostMetrics>,
+        handler_limits: &HandlerLimits,
     ) -> Result<Sender<Self::Req>, Error> {
         let experimental_features = ExperimentalFeatures {
             allow_non_deterministic_ipfs: ENV_VARS.mappings.allow_non_deterministic_ipfs,
@@ -72,7 +75,11 @@ where
             subgraph_id,
             metrics,
             tokio::runtime::Handle::current(),
-            ENV_VARS.mappings.timeout,
+            handler_limits
+                .skip_timeout()
+                .or_else(|| handler_limits.timeout()),
+            handler_limits.skip_timeout().is_some(),
+            handler_limits.max_gas(),
             experimental_features,
         )
     }
@@ -171,6 +178,9 @@ where
             "data_source" => &self.data_source.name(),
         );
 
+        let mut span = telemetry::span("handler")
+            .attr("handler", handler.clone())
+            .attr("data_source", self.data_source.name().to_string());
         let (result_sender, result_receiver) = channel();
         let start_time = Instant::now();
         let metrics = self.metrics.clone();
@@ -201,7 +211,16 @@ where
             .context("Mapping terminated before handling trigger")?;
 
         let elapsed = start_time.elapsed();
-        metrics.observe_handler_execution_time(elapsed.as_secs_f64(), &handler);
+        let result = match result {
+            Ok((mut state, gas)) => {
+                state.observe_handler_execution_time(&metrics, elapsed.as_secs_f64(), &handler);
+                Ok((state, gas))
+            }
+            Err(e) => {
+                metrics.observe_handler_execution_time(elapsed.as_secs_f64(), &handler);
+                Err(e)
+            }
+        };
 
         // If there is an error, "gas_used" is incorrectly reported as 0.
         let gas_used = result.as_ref().map(|(_, gas)| gas).unwrap_or(&Gas::ZERO);
@@ -213,6 +232,10 @@ where
             "data_source" => &self.data_source.name(),
             "gas_used" => gas_used.to_string(),
         );
+        span.set_attr("gas_used", gas_used.to_string());
+        if let Err(MappingError::PossibleReorg(e) | MappingError::Unknown(e)) = &result {
+            span.set_error(format!("{:#}", e));
+        }
 
         // Discard the gas value
         result.map(|(block_state, _)| block_state)
@@ -264,14 +287,14 @@ impl<C: Blockchain> RuntimeHostTrait<C> for RuntimeHost<C> {
     /// trigger has been processed.
     fn done_at(&self) -> Option<BlockNumber> {
         match self.data_source() {
-            DataSource::Onchain(_) => None,
+            DataSource::Onchain(_) | DataSource::Subgraph(_) => None,
             DataSource::Offchain(ds) => ds.done_at(),
         }
     }
 
     fn set_done_at(&self, block: Option<BlockNumber>) {
         match self.data_source() {
-            DataSource::Onchain(_) => {}
+            DataSource::Onchain(_) | DataSource::Subgraph(_) => {}
             DataSource::Offchain(ds) => ds.set_done_at(block),
         }
     }
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
 use std::ops::Deref;
 use std::str::FromStr;
 use std::time::{Duration, Instant};
@@ -15,6 +16,7 @@ use graph::components::subgraph::{
     PoICausalityRegion, ProofOfIndexingEvent, SharedProofOfIndexing,
 };
 use graph::data::store;
+use graph::data_source::offchain::{self, ContentType};
 use graph::data_source::{CausalityRegion, DataSource, DataSourceTemplate, EntityTypeAccess};
 use graph::ensure;
 use graph::prelude::ethabi::param_type::Reader;
@@ -23,6 +25,7 @@ use graph::prelude::serde_json;
 use graph::prelude::{slog::b, slog::record_static, *};
 use graph::runtime::gas::{self, complexity, Gas, GasCounter};
 pub use graph::runtime::{DeterministicHostError, HostExportError};
+use graph::url::Url;
 
 use crate::module::{WasmInstance, WasmInstanceContext};
 use crate::{error::DeterminismLevel, module::IntoTrap};
@@ -39,6 +42,132 @@ fn write_poi_event(
     }
 }
 
+/// Check whether `url` can be fetched with `http.get`, which is the case for
+/// content-addressed URLs and for URLs on an allow-listed host. Returns the
+/// URL to fetch without its fragment and, for content-addressed URLs, the
+/// keccak256 hash the content must have
+fn http_get_target(
+    url: &str,
+    allowed_hosts: &[String],
+) -> Result<(Url, Option<[u8; 32]>), anyhow::Error> {
+    const HASH_PREFIX: &str = "keccak256=";
+
+    let mut url = Url::parse(url).with_context(|| format!("Invalid URL `{}`", url))?;
+    ensure!(
+        url.scheme() == "http" || url.scheme() == "https",
+        "`http.get` only supports http and https URLs, not `{}`",
+        url
+    );
+
+    let expected_hash = match url.fragment() {
+        Some(fragment) if fragment.starts_with(HASH_PREFIX) => {
+            let hash = fragment[HASH_PREFIX.len()..].trim_start_matches("0x");
+            let hash = hex::decode(hash)
+                .ok()
+                .and_then(|hash| <[u8; 32]>::try_from(hash.as_slice()).ok())
+                .ok_or_else(|| anyhow!("Invalid keccak256 hash in URL `{}`", url))?;
+            Some(hash)
+        }
+        _ => None,
+    };
+    url.set_fragment(None);
+
+    let host = url.host_str().unwrap_or_default().to_lowercase();
+    ensure!(
+        expected_hash.is_some() || allowed_hosts.contains(&host),
+        "The host of `{}` is not allowed for `http.get`; add it to \
+         GRAPH_MAPPING_HTTP_ALLOWED_HOSTS to allow it, or add the \
+         `#keccak256=<hash>` of the content to the URL",
+        url
+    );
+    Ok((url, expected_hash))
+}
+
+/// Whether `ip` is an address on the public internet. Mappings must not be
+/// able to reach the node's own network through `http.get`, even when an
+/// allowed host resolves to such an address
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // 0.0.0.0/8 and the shared address space 100.64.0.0/10
+                || a == 0
+                || (a == 100 && (b & 0xc0) == 64))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(ip));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // Unique local fc00::/7 and link-local fe80::/10 addresses
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+/// Resolve the host of `url` and check that all its addresses are public.
+/// Returns the address that the request must be sent to, so that the host
+/// can not resolve to a different address by the time we connect to it
+async fn http_get_addr(url: &Url) -> Result<SocketAddr, anyhow::Error> {
+    let host = url
+        .host_str()
+        .ok_or_else(|| anyhow!("`{}` has no host", url))?;
+    let port = url.port_or_known_default().unwrap_or(80);
+    let addrs: Vec<_> = tokio::net::lookup_host((host, port))
+        .await
+        .with_context(|| format!("Failed to resolve the host of `{}`", url))?
+        .collect();
+    ensure!(!addrs.is_empty(), "The host of `{}` has no addresses", url);
+    if let Some(addr) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+        return Err(anyhow!(
+            "The host of `{}` resolves to {}, which is not a public address",
+            url,
+            addr.ip()
+        ));
+    }
+    Ok(addrs[0])
+}
+
+/// Fetch `url` for `http.get`, enforcing the configured timeout and
+/// response size limit. Redirects are not followed since they could lead
+/// to hosts that are not allowed
+async fn http_fetch(url: &Url) -> Result<Vec<u8>, anyhow::Error> {
+    let max_size = ENV_VARS.mappings.http_max_response_size;
+    let addr = http_get_addr(url).await?;
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .resolve(url.host_str().unwrap_or_default(), addr)
+        .build()?;
+    let mut response = client
+        .get(url.as_str())
+        .timeout(ENV_VARS.mappings.http_timeout)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        ensure!(
+            bytes.len() + chunk.len() <= max_size,
+            "the response is larger than the limit of {} bytes",
+            max_size
+        );
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
 impl IntoTrap for HostExportError {
     fn determinism_level(&self) -> DeterminismLevel {
         match self {
@@ -56,6 +185,9 @@ impl IntoTrap for HostExportError {
     }
 }
 
+/// The longest name of a metric that mappings can increment
+const MAX_METRIC_NAME_LENGTH: usize = 64;
+
 pub struct HostExports<C: Blockchain> {
     pub(crate) subgraph_id: DeploymentHash,
     pub api_version: Version,
@@ -182,7 +314,14 @@ impl<C: Blockchain> HostExports<C> {
         gas.consume_host_fn(gas::STORE_SET.with_args(complexity::Linear, (&key, &data)))?;
 
         let entity = Entity::from(data);
-        state.entity_cache.set(key, entity)?;
+        state.entity_cache.set(key, entity).map_err(|e| {
+            match e.downcast::<store::EntityValidationError>() {
+                // Whether an entity matches the schema does not depend on
+                // anything outside of the subgraph
+                Ok(e) => HostExportError::Deterministic(e.into()),
+                Err(e) => HostExportError::Unknown(e),
+            }
+        })?;
 
         Ok(())
     }
@@ -239,6 +378,26 @@ impl<C: Blockchain> HostExports<C> {
         Ok(result)
     }
 
+    pub(crate) fn store_get_in_block(
+        &self,
+        state: &mut BlockState<C>,
+        entity_type: String,
+        entity_id: String,
+        gas: &GasCounter,
+    ) -> Result<Option<Entity>, anyhow::Error> {
+        let store_key = EntityKey {
+            entity_type: EntityType::new(entity_type),
+            entity_id: entity_id.into(),
+            causality_region: self.data_source_causality_region,
+        };
+        self.check_entity_type_access(&store_key.entity_type)?;
+
+        let result = state.entity_cache.get_in_block(&store_key);
+        gas.consume_host_fn(gas::STORE_GET.with_args(complexity::Linear, (&store_key, &result)))?;
+
+        Ok(result)
+    }
+
     pub(crate) fn store_load_related(
         &self,
         state: &mut BlockState<C>,
@@ -291,6 +450,63 @@ impl<C: Blockchain> HostExports<C> {
         graph::block_on(self.link_resolver.cat(logger, &Link { link }))
     }
 
+    /// Fetch `url` for `http.get`. Only URLs that are content-addressed
+    /// with a `#keccak256=<hash>` fragment or that are on an allow-listed
+    /// host can be fetched, and only if their host resolves to public
+    /// addresses. The hash of the response is written to the PoI so that indexers that were
+    /// served different responses can tell. Responses are kept for the rest
+    /// of the block, and fetching the same URL again in the block returns
+    /// the same response.
+    ///
+    /// Failing to fetch the URL is not deterministic, so that the handler
+    /// is retried rather than processed without the response.
+    pub(crate) fn http_get(
+        &self,
+        logger: &Logger,
+        state: &mut BlockState<C>,
+        proof_of_indexing: &SharedProofOfIndexing,
+        url: String,
+        gas: &GasCounter,
+    ) -> Result<Vec<u8>, HostExportError> {
+        let (url, expected_hash) = http_get_target(&url, &ENV_VARS.mappings.http_allowed_hosts)
+            .map_err(HostExportError::Deterministic)?;
+
+        let bytes = match state.http_response(url.as_str()) {
+            Some(bytes) => bytes.clone(),
+            None => {
+                let bytes = graph::block_on(http_fetch(&url)).map_err(|e| {
+                    HostExportError::Unknown(anyhow!("Failed to fetch `{}`: {}", url, e))
+                })?;
+                state.cache_http_response(url.to_string(), bytes.clone());
+                bytes
+            }
+        };
+        gas.consume_host_fn(gas::DEFAULT_GAS_OP.with_args(complexity::Size, &bytes))?;
+
+        let hash = tiny_keccak::keccak256(&bytes);
+        if let Some(expected_hash) = expected_hash {
+            if hash != expected_hash {
+                return Err(HostExportError::Unknown(anyhow!(
+                    "The content of `{}` has hash 0x{}, expected 0x{}",
+                    url,
+                    hex::encode(hash),
+                    hex::encode(expected_hash)
+                )));
+            }
+        }
+
+        write_poi_event(
+            proof_of_indexing,
+            &ProofOfIndexingEvent::HttpResponse {
+                url: url.as_str(),
+                hash: &hex::encode(hash),
+            },
+            &self.poi_causality_region,
+            logger,
+        );
+        Ok(bytes)
+    }
+
     pub(crate) fn ipfs_get_block(
         &self,
         logger: &Logger,
@@ -327,49 +543,124 @@ impl<C: Blockchain> HostExports<C> {
             "Flags must contain 'json'"
         );
 
-        let host_metrics = module.host_metrics.clone();
-        let valid_module = module.valid_module.clone();
-        let ctx = module.ctx.derive_with_empty_block_state();
-        let callback = callback.to_owned();
         // Create a base error message to avoid borrowing headaches
         let errmsg = format!(
             "ipfs_map: callback '{}' failed when processing file '{}'",
-            &*callback, &link
+            callback, &link
         );
 
+        let logger = module.ctx.logger.new(o!("ipfs_map" => link.clone()));
+
+        let result = graph::block_on(link_resolver.json_stream(&logger, &Link { link })).and_then(
+            |mut stream: JsonValueStream| {
+                let values = std::iter::from_fn(|| {
+                    graph::block_on(stream.next()).map(|sv| sv.map(|sv| sv.value))
+                });
+                Self::map_json_values(module, values, callback, &user_data, &logger)
+            },
+        );
+        result.map_err(move |e: Error| anyhow::anyhow!("{}: {}", errmsg, e.to_string()))
+    }
+
+    // Split `bytes` into JSON values, e.g., the lines of a JSON lines file,
+    // and invoke the exported function `callback` on each of them in the
+    // same way as `ipfs_map`. Since every invocation happens in its own
+    // instance of the WASM module, only one value is ever materialized in
+    // WASM memory, which makes it possible to process files that are too
+    // large for `json.fromBytes`
+    pub(crate) fn json_map_lines(
+        module: &mut WasmInstanceContext<C>,
+        bytes: &[u8],
+        callback: &str,
+        user_data: store::Value,
+        gas: &GasCounter,
+    ) -> Result<Vec<BlockState<C>>, HostExportError> {
+        // Like `ipfs_map`, this only accounts for splitting the input and
+        // not for the gas used by the callbacks
+        gas.consume_host_fn(gas::DEFAULT_GAS_OP.with_args(complexity::Size, &bytes))?;
+
+        let logger = module
+            .ctx
+            .logger
+            .new(o!("json_map_lines" => callback.to_owned()));
+
+        // Values are parsed lazily so that only one of them is held in
+        // memory at a time. Malformed input is a deterministic error, which
+        // is remembered here since parsing stops at the first error
+        let mut parse_error = None;
+        let values = serde_json::Deserializer::from_slice(bytes)
+            .into_iter::<serde_json::Value>()
+            .map_while(|value| match value {
+                Ok(value) => Some(Ok(value)),
+                Err(e) => {
+                    parse_error = Some(e);
+                    None
+                }
+            });
+
+        let states =
+            Self::map_json_values(module, values, callback, &user_data, &logger).map_err(|e| {
+                HostExportError::Unknown(anyhow!(
+                    "json.mapLines: callback '{}' failed: {}",
+                    callback,
+                    e
+                ))
+            })?;
+        match parse_error {
+            Some(e) => Err(HostExportError::Deterministic(anyhow!(
+                "json.mapLines: invalid JSON at line {}, column {}: {}",
+                e.line(),
+                e.column(),
+                e
+            ))),
+            None => Ok(states),
+        }
+    }
+
+    // Invoke the exported function `callback` on each of the `values`. Each
+    // invocation happens in its own instance of a WASM module, which is
+    // identical to `module` when it was first started, and returns the
+    // block state it produced
+    fn map_json_values(
+        module: &mut WasmInstanceContext<C>,
+        values: impl Iterator<Item = Result<serde_json::Value, Error>>,
+        callback: &str,
+        user_data: &store::Value,
+        logger: &Logger,
+    ) -> Result<Vec<BlockState<C>>, Error> {
+        let host_metrics = module.host_metrics.clone();
+        let valid_module = module.valid_module.clone();
+        let ctx = module.ctx.derive_with_empty_block_state();
+
         let start = Instant::now();
         let mut last_log = start;
-        let logger = ctx.logger.new(o!("ipfs_map" => link.clone()));
-
-        let result = {
-            let mut stream: JsonValueStream =
-                graph::block_on(link_resolver.json_stream(&logger, &Link { link }))?;
-            let mut v = Vec::new();
-            while let Some(sv) = graph::block_on(stream.next()) {
-                let sv = sv?;
-                let module = WasmInstance::from_valid_module_with_ctx(
-                    valid_module.clone(),
-                    ctx.derive_with_empty_block_state(),
-                    host_metrics.clone(),
-                    module.timeout,
-                    module.experimental_features,
-                )?;
-                let result = module.handle_json_callback(&callback, &sv.value, &user_data)?;
-                // Log progress every 15s
-                if last_log.elapsed() > Duration::from_secs(15) {
-                    debug!(
-                        logger,
-                        "Processed {} lines in {}s so far",
-                        sv.line,
-                        start.elapsed().as_secs()
-                    );
-                    last_log = Instant::now();
-                }
-                v.push(result)
+
+        let mut v = Vec::new();
+        for value in values {
+            let value = value?;
+            let module = WasmInstance::from_valid_module_with_ctx(
+                valid_module.clone(),
+                ctx.derive_with_empty_block_state(),
+                host_metrics.clone(),
+                module.timeout,
+                module.skip_on_timeout,
+                module.max_gas,
+                module.experimental_features,
+            )?;
+            let result = module.handle_json_callback(callback, &value, user_data)?;
+            v.push(result);
+            // Log progress every 15s
+            if last_log.elapsed() > Duration::from_secs(15) {
+                debug!(
+                    logger,
+                    "Processed {} lines in {}s so far",
+                    v.len(),
+                    start.elapsed().as_secs()
+                );
+                last_log = Instant::now();
             }
-            Ok(v)
-        };
-        result.map_err(move |e: Error| anyhow::anyhow!("{}: {}", errmsg, e.to_string()))
+        }
+        Ok(v)
     }
 
     /// Expects a decimal string.
@@ -434,6 +725,113 @@ impl<C: Blockchain> HostExports<C> {
         Ok(tiny_keccak::keccak256(data))
     }
 
+    pub(crate) fn crypto_sha256(
+        &self,
+        input: Vec<u8>,
+        gas: &GasCounter,
+    ) -> Result<[u8; 32], DeterministicHostError> {
+        use sha2::Digest;
+
+        gas.consume_host_fn(gas::DEFAULT_GAS_OP.with_args(complexity::Size, &input))?;
+        Ok(sha2::Sha256::digest(&input).into())
+    }
+
+    pub(crate) fn crypto_ripemd160(
+        &self,
+        input: Vec<u8>,
+        gas: &GasCounter,
+    ) -> Result<[u8; 20], DeterministicHostError> {
+        gas.consume_host_fn(gas::DEFAULT_GAS_OP.with_args(complexity::Size, &input))?;
+        Ok(ripemd160(&input))
+    }
+
+    /// The BLAKE2b hash of `input` with an output of `size` bytes, which
+    /// must be between 1 and 64
+    pub(crate) fn crypto_blake2b(
+        &self,
+        input: Vec<u8>,
+        size: u32,
+        gas: &GasCounter,
+    ) -> Result<Vec<u8>, DeterministicHostError> {
+        gas.consume_host_fn(gas::DEFAULT_GAS_OP.with_args(complexity::Size, &input))?;
+        if size == 0 || size > 64 {
+            return Err(DeterministicHostError::from(anyhow!(
+                "BLAKE2b output size must be between 1 and 64 bytes, but is {}",
+                size
+            )));
+        }
+        let hash = blake2b_simd::Params::new()
+            .hash_length(size as usize)
+            .hash(&input);
+        Ok(hash.as_bytes().to_vec())
+    }
+
+    /// Derive `length` pseudo-random bytes from the block hash, the handler
+    /// and a counter. The bytes are the same on every indexer, but they
+    /// must not be used where unpredictability matters since anybody can
+    /// compute them as soon as the block is known
+    pub(crate) fn crypto_random_bytes(
+        &self,
+        block_hash: &[u8],
+        handler: &str,
+        counter: u64,
+        length: u32,
+        gas: &GasCounter,
+    ) -> Result<Vec<u8>, DeterministicHostError> {
+        const MAX_RANDOM_BYTES: u32 = 1024 * 1024;
+
+        if length > MAX_RANDOM_BYTES {
+            return Err(DeterministicHostError::from(anyhow!(
+                "at most {} random bytes can be requested at once, but {} were requested",
+                MAX_RANDOM_BYTES,
+                length
+            )));
+        }
+        let bytes = random_bytes(block_hash, handler, counter, length);
+        gas.consume_host_fn(gas::DEFAULT_GAS_OP.with_args(complexity::Size, bytes.as_slice()))?;
+        Ok(bytes)
+    }
+
+    /// Recover the uncompressed public key that produced the 65 byte
+    /// signature `r || s || v` for the 32 byte `hash`. The recovery id `v`
+    /// can be given as 0/1 or as 27/28. Returns `None` if no public key can
+    /// be recovered from the signature
+    pub(crate) fn crypto_secp256k1_recover(
+        &self,
+        hash: Vec<u8>,
+        signature: Vec<u8>,
+        gas: &GasCounter,
+    ) -> Result<Option<[u8; 65]>, DeterministicHostError> {
+        use secp256k1::ecdsa::{RecoverableSignature, RecoveryId};
+        use secp256k1::{Message, Secp256k1};
+
+        gas.consume_host_fn(gas::SECP256K1_RECOVER)?;
+        if hash.len() != 32 {
+            return Err(DeterministicHostError::from(anyhow!(
+                "secp256k1 recovery expects a 32 byte hash, but got {} bytes",
+                hash.len()
+            )));
+        }
+        if signature.len() != 65 {
+            return Err(DeterministicHostError::from(anyhow!(
+                "secp256k1 recovery expects a 65 byte signature, but got {} bytes",
+                signature.len()
+            )));
+        }
+
+        let v = match signature[64] {
+            v @ 27..=28 => v - 27,
+            v => v,
+        };
+        let recovered = RecoveryId::from_i32(v as i32)
+            .and_then(|id| RecoverableSignature::from_compact(&signature[..64], id))
+            .and_then(|signature| {
+                let message = Message::from_slice(&hash)?;
+                Secp256k1::verification_only().recover_ecdsa(&message, &signature)
+            });
+        Ok(recovered.ok().map(|key| key.serialize_uncompressed()))
+    }
+
     pub(crate) fn big_int_plus(
         &self,
         x: BigInt,
@@ -541,6 +939,39 @@ impl<C: Blockchain> HostExports<C> {
         Ok(x & y)
     }
 
+    pub(crate) fn big_int_bit_xor(
+        &self,
+        x: BigInt,
+        y: BigInt,
+        gas: &GasCounter,
+    ) -> Result<BigInt, DeterministicHostError> {
+        gas.consume_host_fn(gas::BIG_MATH_GAS_OP.with_args(complexity::Max, (&x, &y)))?;
+        Ok(x ^ y)
+    }
+
+    pub(crate) fn big_int_bit_not(
+        &self,
+        x: BigInt,
+        gas: &GasCounter,
+    ) -> Result<BigInt, DeterministicHostError> {
+        gas.consume_host_fn(gas::BIG_MATH_GAS_OP.with_args(complexity::Size, &x))?;
+        Ok(!x)
+    }
+
+    pub(crate) fn big_int_sqrt(
+        &self,
+        x: BigInt,
+        gas: &GasCounter,
+    ) -> Result<BigInt, DeterministicHostError> {
+        gas.consume_host_fn(gas::BIG_MATH_GAS_OP.with_args(complexity::Mul, (&x, &x)))?;
+        x.sqrt().ok_or_else(|| {
+            DeterministicHostError::from(anyhow!(
+                "attempted to take the square root of negative BigInt `{}`",
+                x
+            ))
+        })
+    }
+
     pub(crate) fn big_int_left_shift(
         &self,
         x: BigInt,
@@ -618,6 +1049,50 @@ impl<C: Blockchain> HostExports<C> {
         Ok(x / y)
     }
 
+    pub(crate) fn big_decimal_sqrt(
+        &self,
+        x: BigDecimal,
+        gas: &GasCounter,
+    ) -> Result<BigDecimal, DeterministicHostError> {
+        gas.consume_host_fn(gas::BIG_MATH_GAS_OP.with_args(complexity::Mul, (&x, &x)))?;
+        x.sqrt().ok_or_else(|| {
+            DeterministicHostError::from(anyhow!(
+                "attempted to take the square root of negative BigDecimal `{}`",
+                x
+            ))
+        })
+    }
+
+    pub(crate) fn big_decimal_log10(
+        &self,
+        x: BigDecimal,
+        gas: &GasCounter,
+    ) -> Result<BigDecimal, DeterministicHostError> {
+        gas.consume_host_fn(
+            gas::BIG_MATH_GAS_OP.with_args(complexity::Mul, (&x, &x))
+                + gas::BIG_DECIMAL_STEP * BigDecimal::log10_work(),
+        )?;
+        x.log10().ok_or_else(|| {
+            DeterministicHostError::from(anyhow!(
+                "attempted to take the logarithm of non-positive BigDecimal `{}`",
+                x
+            ))
+        })
+    }
+
+    pub(crate) fn big_decimal_pow(
+        &self,
+        x: BigDecimal,
+        exp: BigDecimal,
+        gas: &GasCounter,
+    ) -> Result<BigDecimal, DeterministicHostError> {
+        gas.consume_host_fn(
+            gas::BIG_MATH_GAS_OP.with_args(complexity::Mul, (&x, &exp))
+                + gas::BIG_DECIMAL_STEP * BigDecimal::pow_work(&exp),
+        )?;
+        x.pow(&exp).map_err(DeterministicHostError::from)
+    }
+
     pub(crate) fn big_decimal_equals(
         &self,
         x: BigDecimal,
@@ -652,6 +1127,7 @@ impl<C: Blockchain> HostExports<C> {
         &self,
         logger: &Logger,
         state: &mut BlockState<C>,
+        proof_of_indexing: &SharedProofOfIndexing,
         name: String,
         params: Vec<String>,
         context: Option<DataSourceContext>,
@@ -688,6 +1164,35 @@ impl<C: Blockchain> HostExports<C> {
             .map_err(DeterministicHostError::from)?
             .clone();
 
+        // File data sources take the content type of the file as an
+        // optional second parameter
+        if let (DataSourceTemplate::Offchain(_), Some(content_type)) = (&template, params.get(1)) {
+            content_type
+                .parse::<ContentType>()
+                .with_context(|| format!("Failed to create data source from name `{}`", name))
+                .map_err(DeterministicHostError::from)?;
+        }
+
+        // Objects in cloud storage are not content-addressed. The hash
+        // that their content must have is part of the source, and is
+        // recorded here since the PoI does not cover file data sources
+        if let DataSourceTemplate::Offchain(template) = &template {
+            if let Some(Ok(offchain::Source::ObjectStore(file))) = params
+                .first()
+                .map(|source| offchain::Source::parse(&template.kind, source))
+            {
+                write_poi_event(
+                    proof_of_indexing,
+                    &ProofOfIndexingEvent::ObjectStoreFile {
+                        url: &file.url(),
+                        hash: &hex::encode(file.hash),
+                    },
+                    &self.poi_causality_region,
+                    logger,
+                );
+            }
+        }
+
         // Remember that we need to create this data source
         state.push_created_data_source(DataSourceTemplateInfo {
             template,
@@ -699,6 +1204,15 @@ impl<C: Blockchain> HostExports<C> {
         Ok(())
     }
 
+    pub(crate) fn ens_namehash(
+        &self,
+        name: String,
+        gas: &GasCounter,
+    ) -> Result<[u8; 32], DeterministicHostError> {
+        gas.consume_host_fn(gas::DEFAULT_GAS_OP.with_args(complexity::Size, &name))?;
+        Ok(graph::util::ens::namehash(&name))
+    }
+
     pub(crate) fn ens_name_by_hash(&self, hash: &str) -> Result<Option<String>, anyhow::Error> {
         Ok(self.ens_lookup.find_name(hash)?)
     }
@@ -732,6 +1246,35 @@ impl<C: Blockchain> HostExports<C> {
         Ok(())
     }
 
+    /// Increment the counter `name` that is exported to Prometheus for
+    /// the deployment once the changes of the block have been written.
+    /// Counters are not part of the subgraph's state, and only invalid
+    /// names cause an error
+    pub(crate) fn metrics_increment(
+        &self,
+        state: &mut BlockState<C>,
+        name: String,
+        value: u32,
+        gas: &GasCounter,
+    ) -> Result<(), DeterministicHostError> {
+        gas.consume_host_fn(gas::DEFAULT_GAS_OP.with_args(complexity::Size, &name))?;
+
+        if name.is_empty()
+            || name.len() > MAX_METRIC_NAME_LENGTH
+            || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            return Err(DeterministicHostError::from(anyhow!(
+                "Invalid metric name `{}`: names must consist of at most {} letters, \
+                 digits and underscores",
+                name,
+                MAX_METRIC_NAME_LENGTH
+            )));
+        }
+
+        state.increment_metric(name, value as f64);
+        Ok(())
+    }
+
     pub(crate) fn data_source_address(
         &self,
         gas: &GasCounter,
@@ -830,6 +1373,32 @@ fn string_to_h160(string: &str) -> Result<H160, DeterministicHostError> {
         .map_err(DeterministicHostError::from)
 }
 
+fn ripemd160(input: &[u8]) -> [u8; 20] {
+    use ripemd::Digest;
+
+    ripemd::Ripemd160::digest(input).into()
+}
+
+/// Derive `length` bytes from a seed made of the block hash, the handler
+/// and the counter by hashing the seed together with the index of each 32
+/// byte block of output
+fn random_bytes(block_hash: &[u8], handler: &str, counter: u64, length: u32) -> Vec<u8> {
+    let mut seed = block_hash.to_vec();
+    seed.extend_from_slice(handler.as_bytes());
+    seed.extend_from_slice(&counter.to_be_bytes());
+    let seed = tiny_keccak::keccak256(&seed);
+
+    let mut bytes = Vec::with_capacity(length as usize + 32);
+    let mut block = [0u8; 36];
+    block[..32].copy_from_slice(&seed);
+    for i in 0..(length + 31) / 32 {
+        block[32..].copy_from_slice(&i.to_be_bytes());
+        bytes.extend_from_slice(&tiny_keccak::keccak256(&block));
+    }
+    bytes.truncate(length as usize);
+    bytes
+}
+
 fn bytes_to_string(logger: &Logger, bytes: Vec<u8>) -> String {
     let s = String::from_utf8_lossy(&bytes);
 
@@ -878,3 +1447,114 @@ fn bytes_to_string_is_lossy() {
         )
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use graph::url::Url;
+
+    use super::{http_get_addr, http_get_target, is_public_ip, random_bytes, ripemd160};
+
+    #[test]
+    fn ripemd160_known_answers() {
+        // Test vectors from the RIPEMD-160 reference
+        let cases = [
+            ("", "9c1185a5c5e9fc54612808977ee8f548b2258d31"),
+            ("a", "0bdc9d2d256b3ee9daae347be6f4dc835a467ffe"),
+            ("abc", "8eb208f7e05d987a9b044a8e98c6b087f15a0bfc"),
+            ("message digest", "5d0689ef49d2fae572b881b123a85ffa21595f36"),
+            (
+                "abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                "12a053384a9c0c88e405a06c27dcf49ada62eb2b",
+            ),
+        ];
+        for (input, hash) in cases {
+            assert_eq!(
+                hash,
+                hex::encode(ripemd160(input.as_bytes())),
+                "{:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn random_bytes_are_deterministic() {
+        let block_hash = [7u8; 32];
+        let bytes = random_bytes(&block_hash, "handleTransfer", 0, 100);
+        assert_eq!(100, bytes.len());
+        assert_eq!(bytes, random_bytes(&block_hash, "handleTransfer", 0, 100));
+
+        // Shorter requests get a prefix of the same bytes
+        assert_eq!(
+            &bytes[..33],
+            random_bytes(&block_hash, "handleTransfer", 0, 33)
+        );
+        assert!(random_bytes(&block_hash, "handleTransfer", 0, 0).is_empty());
+
+        // Changing any part of the seed changes the bytes
+        assert_ne!(bytes, random_bytes(&block_hash, "handleTransfer", 1, 100));
+        assert_ne!(bytes, random_bytes(&block_hash, "handleApproval", 0, 100));
+        assert_ne!(bytes, random_bytes(&[8u8; 32], "handleTransfer", 0, 100));
+    }
+
+    #[test]
+    fn http_get_target_requires_hash_or_allowed_host() {
+        const HASH: &str = "0x0000000000000000000000000000000000000000000000000000000000000001";
+        let allowed = vec!["example.com".to_string()];
+
+        let (url, hash) = http_get_target("https://Example.com/data.json", &allowed).unwrap();
+        assert_eq!("https://example.com/data.json", url.as_str());
+        assert_eq!(None, hash);
+
+        let (url, hash) = http_get_target(
+            &format!("https://example.com/a#keccak256={}", HASH),
+            &allowed,
+        )
+        .unwrap();
+        assert_eq!("https://example.com/a", url.as_str());
+        assert_eq!(Some(1), hash.map(|hash| hash[31]));
+
+        // Content-addressed URLs can be on any host
+        let (url, hash) =
+            http_get_target(&format!("https://other.com/a#keccak256={}", HASH), &allowed).unwrap();
+        assert_eq!("https://other.com/a", url.as_str());
+        assert!(hash.is_some());
+
+        assert!(http_get_target("https://other.com/a", &allowed).is_err());
+        assert!(http_get_target("https://example.com/a#keccak256=0x12", &allowed).is_err());
+        assert!(http_get_target("file:///etc/passwd", &allowed).is_err());
+    }
+
+    #[test]
+    fn http_get_only_reaches_public_addresses() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "fe80::1",
+            "fd00::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{} is not public", ip);
+        }
+        for ip in ["1.1.1.1", "93.184.216.34", "2606:4700:4700::1111"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{} is public", ip);
+        }
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        for url in [
+            "http://localhost:8020/",
+            "http://127.0.0.1/",
+            "http://[::1]/",
+        ] {
+            let url = Url::parse(url).unwrap();
+            assert!(rt.block_on(http_get_addr(&url)).is_err(), "{}", url);
+        }
+    }
+}
@@ -16,6 +16,7 @@ pub mod host_exports;
 
 pub mod error;
 mod gas_rules;
+mod module_cache;
 
 pub use host::RuntimeHostBuilder;
 pub use host_exports::HostExports;
@@ -1,5 +1,6 @@
 use crate::gas_rules::GasRules;
 use crate::module::{ExperimentalFeatures, ToAscPtr, WasmInstance};
+use crate::module_cache;
 use futures::sync::mpsc;
 use futures03::channel::oneshot::Sender;
 use graph::blockchain::{Blockchain, HostFn};
@@ -20,12 +21,14 @@ pub fn spawn_module<C: Blockchain>(
     host_metrics: Arc<HostMetrics>,
     runtime: tokio::runtime::Handle,
     timeout: Option<Duration>,
+    skip_on_timeout: bool,
+    max_gas: u64,
     experimental_features: ExperimentalFeatures,
 ) -> Result<mpsc::Sender<MappingRequest<C>>, anyhow::Error>
 where
     <C as Blockchain>::MappingTrigger: ToAscPtr,
 {
-    let valid_module = Arc::new(ValidModule::new(&logger, raw_module)?);
+    let valid_module = module_cache::valid_module(&logger, raw_module)?;
 
     // Create channel for event handling requests
     let (mapping_request_sender, mapping_request_receiver) = mpsc::channel(100);
@@ -58,6 +61,8 @@ where
                     trigger,
                     host_metrics.cheap_clone(),
                     timeout,
+                    skip_on_timeout,
+                    max_gas,
                     experimental_features,
                 );
 
@@ -84,6 +89,8 @@ fn instantiate_module_and_handle_trigger<C: Blockchain>(
     trigger: TriggerWithHandler<MappingTrigger<C>>,
     host_metrics: Arc<HostMetrics>,
     timeout: Option<Duration>,
+    skip_on_timeout: bool,
+    max_gas: u64,
     experimental_features: ExperimentalFeatures,
 ) -> Result<(BlockState<C>, Gas), MappingError>
 where
@@ -98,6 +105,8 @@ where
         ctx,
         host_metrics.cheap_clone(),
         timeout,
+        skip_on_timeout,
+        max_gas,
         experimental_features,
     )
     .context("module instantiation failed")?;
@@ -187,6 +196,22 @@ impl ValidModule {
             .map_err(|_| anyhow!("Failed to inject gas counter"))?;
         let raw_module = parity_module.into_bytes()?;
 
+        let engine = &wasmtime::Engine::new(&Self::config())?;
+        let module = wasmtime::Module::from_binary(engine, &raw_module)?;
+
+        Ok(Self::from_module(module))
+    }
+
+    /// Load a module that was compiled earlier and serialized with
+    /// `wasmtime::Module::serialize`. Fails if the module was compiled with
+    /// a different configuration or version of wasmtime
+    pub fn deserialize(compiled_module: &[u8]) -> Result<Self, anyhow::Error> {
+        let engine = &wasmtime::Engine::new(&Self::config())?;
+        let module = wasmtime::Module::deserialize(engine, compiled_module)?;
+        Ok(Self::from_module(module))
+    }
+
+    fn config() -> wasmtime::Config {
         // We currently use Cranelift as a compilation engine. Cranelift is an optimizing compiler,
         // but that should not cause determinism issues since it adheres to the Wasm spec. Still we
         // turn off optional optimizations to be conservative.
@@ -198,10 +223,10 @@ impl ValidModule {
         config
             .max_wasm_stack(ENV_VARS.mappings.max_stack_size)
             .unwrap(); // Safe because this only panics if size passed is 0.
+        config
+    }
 
-        let engine = &wasmtime::Engine::new(&config)?;
-        let module = wasmtime::Module::from_binary(engine, &raw_module)?;
-
+    fn from_module(module: wasmtime::Module) -> Self {
         let mut import_name_to_modules: BTreeMap<String, Vec<String>> = BTreeMap::new();
 
         // Unwrap: Module linking is disabled.
@@ -215,9 +240,9 @@ impl ValidModule {
                 .push(module.to_string());
         }
 
-        Ok(ValidModule {
+        ValidModule {
             module,
             import_name_to_modules,
-        })
+        }
     }
 }
@@ -15,9 +15,10 @@ use semver::Version;
 use wasmtime::{Memory, Trap};
 
 use graph::blockchain::{Blockchain, HostFnCtx};
+use graph::components::link_resolver::is_transient_error;
 use graph::data::store;
 use graph::data::subgraph::schema::SubgraphError;
-use graph::data_source::{offchain, MappingTrigger, TriggerWithHandler};
+use graph::data_source::{offchain, subgraph, MappingTrigger, TriggerWithHandler};
 use graph::prelude::*;
 use graph::runtime::{
     asc_get, asc_new,
@@ -43,6 +44,34 @@ pub mod stopwatch;
 
 pub const TRAP_TIMEOUT: &str = "trap: interrupt";
 
+/// The size of a page of WASM linear memory
+const WASM_PAGE_SIZE: usize = 64 * 1024;
+
+/// The number of whole pages that fit into `max_memory` bytes
+fn memory_pages(max_memory: usize) -> u32 {
+    (max_memory / WASM_PAGE_SIZE).min(u32::MAX as usize) as u32
+}
+
+/// Whether a linear memory of `size` bytes can not grow by another page
+/// without exceeding `max_memory`
+fn is_memory_exhausted(size: usize, max_memory: Option<usize>) -> bool {
+    match max_memory {
+        Some(max_memory) => size + WASM_PAGE_SIZE > max_memory,
+        None => false,
+    }
+}
+
+fn memory_stats(size: usize, max_memory: Option<usize>, arena_free_size: i32) -> String {
+    let limit = match max_memory {
+        Some(max_memory) => format!("{} bytes", max_memory),
+        None => "unlimited".to_string(),
+    };
+    format!(
+        "linear memory: {} bytes, limit: {}, free in host arena: {} bytes",
+        size, limit, arena_free_size
+    )
+}
+
 pub trait IntoTrap {
     fn determinism_level(&self) -> DeterminismLevel;
     fn into_trap(self) -> Trap;
@@ -64,7 +93,30 @@ impl ToAscPtr for offchain::TriggerData {
         heap: &mut H,
         gas: &GasCounter,
     ) -> Result<AscPtr<()>, HostExportError> {
-        asc_new(heap, self.data.as_ref() as &[u8], gas).map(|ptr| ptr.erase())
+        match self.content_type {
+            offchain::ContentType::Raw => {
+                asc_new(heap, self.data.as_ref() as &[u8], gas).map(|ptr| ptr.erase())
+            }
+            // The content was checked against the content type when the
+            // trigger was matched to the data source
+            offchain::ContentType::Json | offchain::ContentType::ImageMetadata => {
+                let value = self
+                    .content_type
+                    .parse_json(&self.data)
+                    .map_err(DeterministicHostError::from)?;
+                asc_new::<AscEnum<JsonValueKind>, _, _>(heap, &value, gas).map(|ptr| ptr.erase())
+            }
+        }
+    }
+}
+
+impl ToAscPtr for subgraph::TriggerData {
+    fn to_asc_ptr<H: AscHeap>(
+        self,
+        heap: &mut H,
+        gas: &GasCounter,
+    ) -> Result<AscPtr<()>, HostExportError> {
+        asc_new::<AscEntity, _, _>(heap, &self.entity.sorted(), gas).map(|ptr| ptr.erase())
     }
 }
 
@@ -80,6 +132,7 @@ where
         match self {
             MappingTrigger::Onchain(trigger) => trigger.to_asc_ptr(heap, gas),
             MappingTrigger::Offchain(trigger) => trigger.to_asc_ptr(heap, gas),
+            MappingTrigger::Subgraph(trigger) => trigger.to_asc_ptr(heap, gas),
         }
     }
 }
@@ -150,7 +203,7 @@ fn is_trap_deterministic(trap: &Trap) -> bool {
         | Some(UnreachableCodeReached) => true,
 
         // `Interrupt`: Can be a timeout, at least as wasmtime currently implements it.
-        // `StackOverflow`: We may want to have a configurable stack size.
+        // `StackOverflow`: Depends on the configured stack size.
         // `None`: A host trap, so we need to check the `deterministic_host_trap` flag in the context.
         Some(Interrupt) | Some(StackOverflow) | None | _ => false,
     }
@@ -168,6 +221,7 @@ impl<C: Blockchain> WasmInstance<C> {
         let user_data = asc_new(self.instance_ctx_mut().deref_mut(), user_data, &gas)?;
 
         self.instance_ctx_mut().ctx.state.enter_handler();
+        self.instance_ctx_mut().handler = handler_name.to_string();
 
         // Invoke the callback
         self.instance
@@ -235,6 +289,7 @@ impl<C: Blockchain> WasmInstance<C> {
 
         // Caution: Make sure all exit paths from this function call `exit_handler`.
         self.instance_ctx_mut().ctx.state.enter_handler();
+        self.instance_ctx_mut().handler = handler.to_string();
 
         // This `match` will return early if there was a non-deterministic trap.
         let deterministic_error: Option<Error> = match func.call(arg.wasm_ptr()) {
@@ -248,14 +303,49 @@ impl<C: Blockchain> WasmInstance<C> {
                 return Err(MappingError::PossibleReorg(trap.into()));
             }
 
-            // Treat as a special case to have a better error message.
-            Err(trap) if trap.to_string().contains(TRAP_TIMEOUT) => {
+            // Running out of memory depends on the configured limit, and
+            // is therefore not deterministic.
+            Err(trap) if self.instance_ctx().is_out_of_memory() => {
+                self.instance_ctx_mut().ctx.state.exit_handler();
+                let ctx = self.instance_ctx();
+                let entity = match &ctx.last_entity {
+                    Some((entity_type, id)) => format!(" after accessing {}[{}]", entity_type, id),
+                    None => String::new(),
+                };
+                return Err(MappingError::Unknown(Error::from(trap).context(format!(
+                    "Handler '{}' ran out of memory{}; {}",
+                    handler,
+                    entity,
+                    ctx.memory_stats()
+                ))));
+            }
+
+            // The stack size is set by `GRAPH_RUNTIME_MAX_STACK_SIZE`, so
+            // this is not deterministic either.
+            Err(trap) if trap.trap_code() == Some(wasmtime::TrapCode::StackOverflow) => {
                 self.instance_ctx_mut().ctx.state.exit_handler();
                 return Err(MappingError::Unknown(Error::from(trap).context(format!(
+                    "Handler '{}' overflowed the stack of {} bytes",
+                    handler, ENV_VARS.mappings.max_stack_size
+                ))));
+            }
+
+            // Treat as a special case to have a better error message.
+            Err(trap) if trap.to_string().contains(TRAP_TIMEOUT) => {
+                let e = Error::from(trap).context(format!(
                     "Handler '{}' hit the timeout of '{}' seconds",
                     handler,
                     self.instance_ctx().timeout.unwrap().as_secs()
-                ))));
+                ));
+                // Subgraphs that prefer availability over determinism can
+                // have handlers that take too long skipped; the deployment
+                // records that as a non-fatal error
+                if self.instance_ctx().skip_on_timeout {
+                    Some(e)
+                } else {
+                    self.instance_ctx_mut().ctx.state.exit_handler();
+                    return Err(MappingError::Unknown(e));
+                }
             }
             Err(trap) => {
                 let trap_is_deterministic =
@@ -323,6 +413,10 @@ pub struct WasmInstanceContext<C: Blockchain> {
     pub valid_module: Arc<ValidModule>,
     pub host_metrics: Arc<HostMetrics>,
     pub(crate) timeout: Option<Duration>,
+    /// Whether a handler that hits the timeout is skipped like one that
+    /// fails deterministically rather than failing the block
+    pub(crate) skip_on_timeout: bool,
+    pub(crate) max_gas: u64,
 
     // Used by ipfs.map.
     pub(crate) timeout_stopwatch: Arc<std::sync::Mutex<TimeoutStopwatch>>,
@@ -339,19 +433,40 @@ pub struct WasmInstanceContext<C: Blockchain> {
     // A host export trap ocurred for a deterministic reason.
     pub deterministic_host_trap: bool,
 
+    // The type and id of the entity that the handler accessed last, for
+    // error messages.
+    pub(crate) last_entity: Option<(String, String)>,
+
+    // The name of the handler that is running, used to derive random bytes.
+    pub(crate) handler: String,
+
     pub(crate) experimental_features: ExperimentalFeatures,
 }
 
 impl<C: Blockchain> WasmInstance<C> {
-    /// Instantiates the module and sets it to be interrupted after `timeout`.
+    /// Instantiates the module and sets it to be interrupted after `timeout`
+    /// or once it has used `max_gas`. With `skip_on_timeout`, a handler that
+    /// is interrupted by the timeout is skipped and recorded as an error
     pub fn from_valid_module_with_ctx(
         valid_module: Arc<ValidModule>,
         ctx: MappingContext<C>,
         host_metrics: Arc<HostMetrics>,
         timeout: Option<Duration>,
+        skip_on_timeout: bool,
+        max_gas: u64,
         experimental_features: ExperimentalFeatures,
     ) -> Result<WasmInstance<C>, anyhow::Error> {
-        let mut linker = wasmtime::Linker::new(&wasmtime::Store::new(valid_module.module.engine()));
+        let engine = valid_module.module.engine();
+        let store = match ENV_VARS.mappings.max_memory {
+            Some(max_memory) => wasmtime::Store::new_with_limits(
+                engine,
+                wasmtime::StoreLimitsBuilder::new()
+                    .memory_pages(memory_pages(max_memory))
+                    .build(),
+            ),
+            None => wasmtime::Store::new(engine),
+        };
+        let mut linker = wasmtime::Linker::new(&store);
         let host_fns = ctx.host_fns.cheap_clone();
         let api_version = ctx.host_exports.api_version.clone();
 
@@ -388,7 +503,7 @@ impl<C: Blockchain> WasmInstance<C> {
 
         // Because `gas` and `deterministic_host_trap` need to be accessed from the gas
         // host fn, they need to be separate from the rest of the context.
-        let gas = GasCounter::default();
+        let gas = GasCounter::with_limit(max_gas);
         let deterministic_host_trap = Rc::new(AtomicBool::new(false));
 
         macro_rules! link {
@@ -426,6 +541,8 @@ impl<C: Blockchain> WasmInstance<C> {
                                     valid_module.cheap_clone(),
                                     host_metrics.cheap_clone(),
                                     timeout,
+                                    skip_on_timeout,
+                                    max_gas,
                                     timeout_stopwatch.cheap_clone(),
                                     experimental_features.clone()
                                 ).unwrap())
@@ -512,7 +629,8 @@ impl<C: Blockchain> WasmInstance<C> {
                         }
                         HostExportError::Unknown(e) => e,
                     })?;
-                    instance.host_metrics.observe_host_fn_execution_time(
+                    instance.ctx.state.observe_host_fn_execution_time(
+                        &instance.host_metrics,
                         start.elapsed().as_secs_f64(),
                         &name_for_metrics,
                     );
@@ -527,6 +645,13 @@ impl<C: Blockchain> WasmInstance<C> {
         link!("abort", abort, message_ptr, file_name_ptr, line, column);
 
         link!("store.get", store_get, "host_export_store_get", entity, id);
+        link!(
+            "store.getInBlock",
+            store_get_in_block,
+            "host_export_store_get_in_block",
+            entity,
+            id
+        );
         link!(
             "store.loadRelated",
             store_load_related,
@@ -569,6 +694,10 @@ impl<C: Blockchain> WasmInstance<C> {
             );
         }
 
+        if ENV_VARS.mappings.allow_http_get {
+            link!("http.get", http_get, "host_export_http_get", url_ptr);
+        }
+
         link!("store.remove", store_remove, entity_ptr, id_ptr);
 
         link!("typeConversion.bytesToString", bytes_to_string, ptr);
@@ -584,8 +713,26 @@ impl<C: Blockchain> WasmInstance<C> {
         link!("json.toU64", json_to_u64, ptr);
         link!("json.toF64", json_to_f64, ptr);
         link!("json.toBigInt", json_to_big_int, ptr);
+        link!(
+            "json.mapLines",
+            json_map_lines,
+            "host_export_json_map_lines",
+            bytes_ptr,
+            callback,
+            user_data
+        );
 
         link!("crypto.keccak256", crypto_keccak_256, ptr);
+        link!("crypto.sha256", crypto_sha256, ptr);
+        link!("crypto.ripemd160", crypto_ripemd160, ptr);
+        link!("crypto.blake2b", crypto_blake2b, ptr, size);
+        link!("crypto.randomBytes", crypto_random_bytes, length);
+        link!(
+            "crypto.secp256k1Recover",
+            crypto_secp256k1_recover,
+            hash_ptr,
+            signature_ptr
+        );
 
         link!("bigInt.plus", big_int_plus, x_ptr, y_ptr);
         link!("bigInt.minus", big_int_minus, x_ptr, y_ptr);
@@ -597,6 +744,9 @@ impl<C: Blockchain> WasmInstance<C> {
         link!("bigInt.fromString", big_int_from_string, ptr);
         link!("bigInt.bitOr", big_int_bit_or, x_ptr, y_ptr);
         link!("bigInt.bitAnd", big_int_bit_and, x_ptr, y_ptr);
+        link!("bigInt.bitXor", big_int_bit_xor, x_ptr, y_ptr);
+        link!("bigInt.bitNot", big_int_bit_not, x_ptr);
+        link!("bigInt.sqrt", big_int_sqrt, x_ptr);
         link!("bigInt.leftShift", big_int_left_shift, x_ptr, bits);
         link!("bigInt.rightShift", big_int_right_shift, x_ptr, bits);
 
@@ -607,6 +757,9 @@ impl<C: Blockchain> WasmInstance<C> {
         link!("bigDecimal.times", big_decimal_times, x_ptr, y_ptr);
         link!("bigDecimal.dividedBy", big_decimal_divided_by, x, y);
         link!("bigDecimal.equals", big_decimal_equals, x_ptr, y_ptr);
+        link!("bigDecimal.sqrt", big_decimal_sqrt, x_ptr);
+        link!("bigDecimal.log10", big_decimal_log10, x_ptr);
+        link!("bigDecimal.pow", big_decimal_pow, x_ptr, exp_ptr);
 
         link!("dataSource.create", data_source_create, name, params);
         link!(
@@ -621,9 +774,12 @@ impl<C: Blockchain> WasmInstance<C> {
         link!("dataSource.context", data_source_context,);
 
         link!("ens.nameByHash", ens_name_by_hash, ptr);
+        link!("ens.namehash", ens_namehash, name_ptr);
 
         link!("log.log", log_log, level, msg_ptr);
 
+        link!("metrics.increment", metrics_increment, name_ptr, value);
+
         // `arweave and `box` functionality was removed, but apiVersion <= 0.0.4 must link it.
         if api_version <= Version::new(0, 0, 4) {
             link!("arweave.transactionData", arweave_transaction_data, ptr);
@@ -658,6 +814,8 @@ impl<C: Blockchain> WasmInstance<C> {
                 valid_module,
                 host_metrics,
                 timeout,
+                skip_on_timeout,
+                max_gas,
                 timeout_stopwatch,
                 experimental_features,
             )?);
@@ -708,10 +866,18 @@ impl<C: Blockchain> AscHeap for WasmInstanceContext<C> {
             // causes at most half of memory to be wasted, which is acceptable.
             let arena_size = size.max(MIN_ARENA_SIZE);
 
-            // Unwrap: This may panic if more memory needs to be requested from the OS and that
-            // fails. This error is not deterministic since it depends on the operating conditions
-            // of the node.
-            self.arena_start_ptr = self.memory_allocate.call(arena_size).unwrap();
+            // This may panic if more memory needs to be requested from the OS or if the memory
+            // limit is reached. This error is not deterministic since it depends on the operating
+            // conditions and the configuration of the node.
+            self.arena_start_ptr = match self.memory_allocate.call(arena_size) {
+                Ok(ptr) => ptr,
+                Err(e) => panic!(
+                    "Failed to allocate {} bytes of WASM memory ({}): {}",
+                    arena_size,
+                    self.memory_stats(),
+                    e
+                ),
+            };
             self.arena_free_size = arena_size;
 
             match &self.ctx.host_exports.api_version {
@@ -808,6 +974,8 @@ impl<C: Blockchain> WasmInstanceContext<C> {
         valid_module: Arc<ValidModule>,
         host_metrics: Arc<HostMetrics>,
         timeout: Option<Duration>,
+        skip_on_timeout: bool,
+        max_gas: u64,
         timeout_stopwatch: Arc<std::sync::Mutex<TimeoutStopwatch>>,
         experimental_features: ExperimentalFeatures,
     ) -> Result<Self, anyhow::Error> {
@@ -846,11 +1014,15 @@ impl<C: Blockchain> WasmInstanceContext<C> {
             valid_module,
             host_metrics,
             timeout,
+            skip_on_timeout,
+            max_gas,
             timeout_stopwatch,
             arena_free_size: 0,
             arena_start_ptr: 0,
             possible_reorg: false,
             deterministic_host_trap: false,
+            last_entity: None,
+            handler: String::new(),
             experimental_features,
         })
     }
@@ -861,6 +1033,8 @@ impl<C: Blockchain> WasmInstanceContext<C> {
         valid_module: Arc<ValidModule>,
         host_metrics: Arc<HostMetrics>,
         timeout: Option<Duration>,
+        skip_on_timeout: bool,
+        max_gas: u64,
         timeout_stopwatch: Arc<std::sync::Mutex<TimeoutStopwatch>>,
         experimental_features: ExperimentalFeatures,
     ) -> Result<Self, anyhow::Error> {
@@ -902,16 +1076,40 @@ impl<C: Blockchain> WasmInstanceContext<C> {
             valid_module,
             host_metrics,
             timeout,
+            skip_on_timeout,
+            max_gas,
             timeout_stopwatch,
             arena_free_size: 0,
             arena_start_ptr: 0,
             possible_reorg: false,
             deterministic_host_trap: false,
+            last_entity: None,
+            handler: String::new(),
             experimental_features,
         })
     }
 }
 
+impl<C: Blockchain> WasmInstanceContext<C> {
+    /// Whether the linear memory can not grow any further because it has
+    /// reached `GRAPH_RUNTIME_MAX_MEMORY`
+    fn is_out_of_memory(&self) -> bool {
+        is_memory_exhausted(self.memory.data_size(), ENV_VARS.mappings.max_memory)
+    }
+
+    fn memory_stats(&self) -> String {
+        memory_stats(
+            self.memory.data_size(),
+            ENV_VARS.mappings.max_memory,
+            self.arena_free_size,
+        )
+    }
+
+    fn set_last_entity(&mut self, entity_type: &str, id: &str) {
+        self.last_entity = Some((entity_type.to_string(), id.to_string()));
+    }
+}
+
 // Implementation of externals.
 impl<C: Blockchain> WasmInstanceContext<C> {
     /// function abort(message?: string | null, fileName?: string | null, lineNumber?: u32, columnNumber?: u32): void
@@ -960,6 +1158,7 @@ impl<C: Blockchain> WasmInstanceContext<C> {
         let entity: String = asc_get(self, entity_ptr, gas)?;
         let id: String = asc_get(self, id_ptr, gas)?;
         let data = asc_get(self, data_ptr, gas)?;
+        self.set_last_entity(&entity, &id);
 
         if self.ctx.instrument {
             debug!(self.ctx.logger, "store_set";
@@ -990,6 +1189,7 @@ impl<C: Blockchain> WasmInstanceContext<C> {
     ) -> Result<(), HostExportError> {
         let entity: String = asc_get(self, entity_ptr, gas)?;
         let id: String = asc_get(self, id_ptr, gas)?;
+        self.set_last_entity(&entity, &id);
         if self.ctx.instrument {
             debug!(self.ctx.logger, "store_remove";
                     "type" => &entity,
@@ -1012,13 +1212,11 @@ impl<C: Blockchain> WasmInstanceContext<C> {
         entity_ptr: AscPtr<AscString>,
         id_ptr: AscPtr<AscString>,
     ) -> Result<AscPtr<AscEntity>, HostExportError> {
-        let _timer = self
-            .host_metrics
-            .cheap_clone()
-            .time_host_fn_execution_region("store_get");
+        let start = Instant::now();
 
         let entity_type: String = asc_get(self, entity_ptr, gas)?;
         let id: String = asc_get(self, id_ptr, gas)?;
+        self.set_last_entity(&entity_type, &id);
         let entity_option = self.ctx.host_exports.store_get(
             &mut self.ctx.state,
             entity_type.clone(),
@@ -1064,6 +1262,47 @@ impl<C: Blockchain> WasmInstanceContext<C> {
             },
         };
 
+        self.ctx.state.observe_host_fn_execution_time(
+            &self.host_metrics,
+            start.elapsed().as_secs_f64(),
+            "store_get",
+        );
+        Ok(ret)
+    }
+
+    /// function store.getInBlock(entity: string, id: string): Entity | null
+    pub fn store_get_in_block(
+        &mut self,
+        gas: &GasCounter,
+        entity_ptr: AscPtr<AscString>,
+        id_ptr: AscPtr<AscString>,
+    ) -> Result<AscPtr<AscEntity>, HostExportError> {
+        let start = Instant::now();
+
+        let entity_type: String = asc_get(self, entity_ptr, gas)?;
+        let id: String = asc_get(self, id_ptr, gas)?;
+        let entity_option = self.ctx.host_exports.store_get_in_block(
+            &mut self.ctx.state,
+            entity_type.clone(),
+            id.clone(),
+            gas,
+        )?;
+        if self.ctx.instrument {
+            debug!(self.ctx.logger, "store_get_in_block";
+                    "type" => &entity_type,
+                    "id" => &id,
+                    "found" => entity_option.is_some());
+        }
+        let ret = match entity_option {
+            Some(entity) => asc_new(self, &entity.sorted(), gas)?,
+            None => AscPtr::null(),
+        };
+
+        self.ctx.state.observe_host_fn_execution_time(
+            &self.host_metrics,
+            start.elapsed().as_secs_f64(),
+            "store_get_in_block",
+        );
         Ok(ret)
     }
 
@@ -1238,7 +1477,13 @@ impl<C: Blockchain> WasmInstanceContext<C> {
         match ipfs_res {
             Ok(bytes) => asc_new(self, &*bytes, gas).map_err(Into::into),
 
-            // Return null in case of error.
+            // Fail the block so that it is retried if the IPFS node could
+            // not be reached
+            Err(e) if is_transient_error(&e) => Err(HostExportError::Unknown(
+                e.context("Failed ipfs.cat because IPFS could not be reached"),
+            )),
+
+            // Return null for files that can not be found or are too large.
             Err(e) => {
                 info!(&self.ctx.logger, "Failed ipfs.cat, returning `null`";
                                     "link" => asc_get::<String, _, _>(self, link_ptr, gas)?,
@@ -1268,7 +1513,11 @@ impl<C: Blockchain> WasmInstanceContext<C> {
         match ipfs_res {
             Ok(bytes) => asc_new(self, &*bytes, gas).map_err(Into::into),
 
-            // Return null in case of error.
+            Err(e) if is_transient_error(&e) => Err(HostExportError::Unknown(
+                e.context("Failed ipfs.getBlock because IPFS could not be reached"),
+            )),
+
+            // Return null for blocks that can not be found or are too large.
             Err(e) => {
                 info!(&self.ctx.logger, "Failed ipfs.getBlock, returning `null`";
                                     "link" => asc_get::<String, _, _>(self, link_ptr, gas)?,
@@ -1278,6 +1527,23 @@ impl<C: Blockchain> WasmInstanceContext<C> {
         }
     }
 
+    /// function http.get(url: String): Bytes
+    pub fn http_get(
+        &mut self,
+        gas: &GasCounter,
+        url_ptr: AscPtr<AscString>,
+    ) -> Result<AscPtr<Uint8Array>, HostExportError> {
+        let url: String = asc_get(self, url_ptr, gas)?;
+        let bytes = self.ctx.host_exports.http_get(
+            &self.ctx.logger,
+            &mut self.ctx.state,
+            &self.ctx.proof_of_indexing,
+            url,
+            gas,
+        )?;
+        asc_new(self, &*bytes, gas).map_err(Into::into)
+    }
+
     /// function ipfs.map(link: String, callback: String, flags: String[]): void
     pub fn ipfs_map(
         &mut self,
@@ -1333,6 +1599,41 @@ impl<C: Blockchain> WasmInstanceContext<C> {
         Ok(())
     }
 
+    /// function json.mapLines(bytes: Bytes, callback: String, userData: Value): void
+    pub fn json_map_lines(
+        &mut self,
+        gas: &GasCounter,
+        bytes_ptr: AscPtr<Uint8Array>,
+        callback: AscPtr<AscString>,
+        user_data: AscPtr<AscEnum<StoreValueKind>>,
+    ) -> Result<(), HostExportError> {
+        let bytes: Vec<u8> = asc_get(self, bytes_ptr, gas)?;
+        let callback: String = asc_get(self, callback, gas)?;
+        let user_data: store::Value = asc_get(self, user_data, gas)?;
+
+        // Pause the timeout while the callbacks run, since each of them has
+        // its own timeout
+        self.timeout_stopwatch.lock().unwrap().stop();
+        let defer_stopwatch = self.timeout_stopwatch.clone();
+        let _stopwatch_guard = defer::defer(|| defer_stopwatch.lock().unwrap().start());
+
+        let start_time = Instant::now();
+        let output_states = HostExports::json_map_lines(self, &bytes, &callback, user_data, gas)?;
+
+        debug!(
+            &self.ctx.logger,
+            "Successfully processed bytes with json.mapLines";
+            "callback" => &*callback,
+            "n_calls" => output_states.len(),
+            "time" => format!("{}ms", start_time.elapsed().as_millis())
+        );
+        for output_state in output_states {
+            self.ctx.state.extend(output_state);
+        }
+
+        Ok(())
+    }
+
     /// Expects a decimal string.
     /// function json.toI64(json: String): i64
     pub fn json_to_i64(
@@ -1396,6 +1697,82 @@ impl<C: Blockchain> WasmInstanceContext<C> {
         asc_new(self, input.as_ref(), gas)
     }
 
+    /// function crypto.sha256(input: Bytes): Bytes
+    pub fn crypto_sha256(
+        &mut self,
+        gas: &GasCounter,
+        input_ptr: AscPtr<Uint8Array>,
+    ) -> Result<AscPtr<Uint8Array>, HostExportError> {
+        let hash = self
+            .ctx
+            .host_exports
+            .crypto_sha256(asc_get(self, input_ptr, gas)?, gas)?;
+        asc_new(self, hash.as_ref(), gas)
+    }
+
+    /// function crypto.ripemd160(input: Bytes): Bytes
+    pub fn crypto_ripemd160(
+        &mut self,
+        gas: &GasCounter,
+        input_ptr: AscPtr<Uint8Array>,
+    ) -> Result<AscPtr<Uint8Array>, HostExportError> {
+        let hash = self
+            .ctx
+            .host_exports
+            .crypto_ripemd160(asc_get(self, input_ptr, gas)?, gas)?;
+        asc_new(self, hash.as_ref(), gas)
+    }
+
+    /// function crypto.blake2b(input: Bytes, size: u32): Bytes
+    pub fn crypto_blake2b(
+        &mut self,
+        gas: &GasCounter,
+        input_ptr: AscPtr<Uint8Array>,
+        size: u32,
+    ) -> Result<AscPtr<Uint8Array>, HostExportError> {
+        let hash =
+            self.ctx
+                .host_exports
+                .crypto_blake2b(asc_get(self, input_ptr, gas)?, size, gas)?;
+        asc_new(self, hash.as_slice(), gas)
+    }
+
+    /// function crypto.randomBytes(length: u32): Bytes
+    pub fn crypto_random_bytes(
+        &mut self,
+        gas: &GasCounter,
+        length: u32,
+    ) -> Result<AscPtr<Uint8Array>, HostExportError> {
+        let counter = self.ctx.state.next_random_counter();
+        let bytes = self.ctx.host_exports.crypto_random_bytes(
+            self.ctx.block_ptr.hash.as_slice(),
+            &self.handler,
+            counter,
+            length,
+            gas,
+        )?;
+        asc_new(self, bytes.as_slice(), gas)
+    }
+
+    /// function crypto.secp256k1Recover(hash: Bytes, signature: Bytes): Bytes | null
+    pub fn crypto_secp256k1_recover(
+        &mut self,
+        gas: &GasCounter,
+        hash_ptr: AscPtr<Uint8Array>,
+        signature_ptr: AscPtr<Uint8Array>,
+    ) -> Result<AscPtr<Uint8Array>, HostExportError> {
+        let hash = asc_get(self, hash_ptr, gas)?;
+        let signature = asc_get(self, signature_ptr, gas)?;
+        match self
+            .ctx
+            .host_exports
+            .crypto_secp256k1_recover(hash, signature, gas)?
+        {
+            Some(key) => asc_new(self, key.as_ref(), gas),
+            None => Ok(AscPtr::null()),
+        }
+    }
+
     /// function bigInt.plus(x: BigInt, y: BigInt): BigInt
     pub fn big_int_plus(
         &mut self,
@@ -1531,6 +1908,47 @@ impl<C: Blockchain> WasmInstanceContext<C> {
         asc_new(self, &result, gas)
     }
 
+    /// function bigInt.bitXor(x: BigInt, y: BigInt): BigInt
+    pub fn big_int_bit_xor(
+        &mut self,
+        gas: &GasCounter,
+        x_ptr: AscPtr<AscBigInt>,
+        y_ptr: AscPtr<AscBigInt>,
+    ) -> Result<AscPtr<AscBigInt>, HostExportError> {
+        let result = self.ctx.host_exports.big_int_bit_xor(
+            asc_get(self, x_ptr, gas)?,
+            asc_get(self, y_ptr, gas)?,
+            gas,
+        )?;
+        asc_new(self, &result, gas)
+    }
+
+    /// function bigInt.bitNot(x: BigInt): BigInt
+    pub fn big_int_bit_not(
+        &mut self,
+        gas: &GasCounter,
+        x_ptr: AscPtr<AscBigInt>,
+    ) -> Result<AscPtr<AscBigInt>, HostExportError> {
+        let result = self
+            .ctx
+            .host_exports
+            .big_int_bit_not(asc_get(self, x_ptr, gas)?, gas)?;
+        asc_new(self, &result, gas)
+    }
+
+    /// function bigInt.sqrt(x: BigInt): BigInt
+    pub fn big_int_sqrt(
+        &mut self,
+        gas: &GasCounter,
+        x_ptr: AscPtr<AscBigInt>,
+    ) -> Result<AscPtr<AscBigInt>, HostExportError> {
+        let result = self
+            .ctx
+            .host_exports
+            .big_int_sqrt(asc_get(self, x_ptr, gas)?, gas)?;
+        asc_new(self, &result, gas)
+    }
+
     /// function bigInt.leftShift(x: BigInt, bits: u8): BigInt
     pub fn big_int_left_shift(
         &mut self,
@@ -1674,6 +2092,47 @@ impl<C: Blockchain> WasmInstanceContext<C> {
         )
     }
 
+    /// function bigDecimal.sqrt(x: BigDecimal): BigDecimal
+    pub fn big_decimal_sqrt(
+        &mut self,
+        gas: &GasCounter,
+        x_ptr: AscPtr<AscBigDecimal>,
+    ) -> Result<AscPtr<AscBigDecimal>, HostExportError> {
+        let result = self
+            .ctx
+            .host_exports
+            .big_decimal_sqrt(asc_get(self, x_ptr, gas)?, gas)?;
+        asc_new(self, &result, gas)
+    }
+
+    /// function bigDecimal.log10(x: BigDecimal): BigDecimal
+    pub fn big_decimal_log10(
+        &mut self,
+        gas: &GasCounter,
+        x_ptr: AscPtr<AscBigDecimal>,
+    ) -> Result<AscPtr<AscBigDecimal>, HostExportError> {
+        let result = self
+            .ctx
+            .host_exports
+            .big_decimal_log10(asc_get(self, x_ptr, gas)?, gas)?;
+        asc_new(self, &result, gas)
+    }
+
+    /// function bigDecimal.pow(x: BigDecimal, exp: BigDecimal): BigDecimal
+    pub fn big_decimal_pow(
+        &mut self,
+        gas: &GasCounter,
+        x_ptr: AscPtr<AscBigDecimal>,
+        exp_ptr: AscPtr<AscBigDecimal>,
+    ) -> Result<AscPtr<AscBigDecimal>, HostExportError> {
+        let result = self.ctx.host_exports.big_decimal_pow(
+            asc_get(self, x_ptr, gas)?,
+            asc_get(self, exp_ptr, gas)?,
+            gas,
+        )?;
+        asc_new(self, &result, gas)
+    }
+
     /// function dataSource.create(name: string, params: Array<string>): void
     pub fn data_source_create(
         &mut self,
@@ -1686,6 +2145,7 @@ impl<C: Blockchain> WasmInstanceContext<C> {
         self.ctx.host_exports.data_source_create(
             &self.ctx.logger,
             &mut self.ctx.state,
+            &self.ctx.proof_of_indexing,
             name,
             params,
             None,
@@ -1708,6 +2168,7 @@ impl<C: Blockchain> WasmInstanceContext<C> {
         self.ctx.host_exports.data_source_create(
             &self.ctx.logger,
             &mut self.ctx.state,
+            &self.ctx.proof_of_indexing,
             name,
             params,
             Some(context.into()),
@@ -1775,6 +2236,19 @@ impl<C: Blockchain> WasmInstanceContext<C> {
             .unwrap_or(Ok(AscPtr::null()))
     }
 
+    /// function ens.namehash(name: string): Bytes
+    pub fn ens_namehash(
+        &mut self,
+        gas: &GasCounter,
+        name_ptr: AscPtr<AscString>,
+    ) -> Result<AscPtr<Uint8Array>, HostExportError> {
+        let node = self
+            .ctx
+            .host_exports
+            .ens_namehash(asc_get(self, name_ptr, gas)?, gas)?;
+        asc_new(self, node.as_ref(), gas)
+    }
+
     pub fn log_log(
         &mut self,
         gas: &GasCounter,
@@ -1788,6 +2262,19 @@ impl<C: Blockchain> WasmInstanceContext<C> {
             .log_log(&self.ctx.mapping_logger, level, msg, gas)
     }
 
+    /// function metrics.increment(name: string, value: u32): void
+    pub fn metrics_increment(
+        &mut self,
+        gas: &GasCounter,
+        name_ptr: AscPtr<AscString>,
+        value: u32,
+    ) -> Result<(), DeterministicHostError> {
+        let name: String = asc_get(self, name_ptr, gas)?;
+        self.ctx
+            .host_exports
+            .metrics_increment(&mut self.ctx.state, name, value, gas)
+    }
+
     /// function encode(token: ethereum.Value): Bytes | null
     pub fn ethereum_encode(
         &mut self,
@@ -1845,3 +2332,30 @@ impl<C: Blockchain> WasmInstanceContext<C> {
         )))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{is_memory_exhausted, memory_pages, memory_stats, WASM_PAGE_SIZE};
+
+    #[test]
+    fn memory_limit() {
+        assert_eq!(16, memory_pages(1024 * 1024));
+        // Partial pages are not usable
+        assert_eq!(16, memory_pages(1024 * 1024 + WASM_PAGE_SIZE - 1));
+        assert_eq!(u32::MAX, memory_pages(usize::MAX));
+
+        let max_memory = Some(4 * WASM_PAGE_SIZE);
+        assert!(!is_memory_exhausted(3 * WASM_PAGE_SIZE, max_memory));
+        assert!(is_memory_exhausted(4 * WASM_PAGE_SIZE, max_memory));
+        assert!(!is_memory_exhausted(4 * WASM_PAGE_SIZE, None));
+
+        assert_eq!(
+            "linear memory: 131072 bytes, limit: 262144 bytes, free in host arena: 100 bytes",
+            memory_stats(2 * WASM_PAGE_SIZE, max_memory, 100)
+        );
+        assert_eq!(
+            "linear memory: 131072 bytes, limit: unlimited, free in host arena: 0 bytes",
+            memory_stats(2 * WASM_PAGE_SIZE, None, 0)
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex, Weak};
+
+use graph::prelude::{lazy_static, tiny_keccak, warn, Logger, ENV_VARS};
+
+use crate::mapping::ValidModule;
+
+lazy_static! {
+    /// The compiled modules that are currently in use, keyed by `cache_key`.
+    /// Data sources and subgraphs that use the same code share one compiled
+    /// module for as long as any of them is running
+    static ref MODULES: Mutex<HashMap<[u8; 32], Weak<ValidModule>>> = Mutex::new(HashMap::new());
+}
+
+/// Return the compiled module for `raw_module`. The module is only compiled
+/// if it is neither in use already nor stored in the directory set with
+/// `GRAPH_WASM_MODULE_CACHE_DIR` from an earlier compilation
+pub fn valid_module(logger: &Logger, raw_module: &[u8]) -> Result<Arc<ValidModule>, anyhow::Error> {
+    let key = cache_key(raw_module);
+    if let Some(module) = MODULES.lock().unwrap().get(&key).and_then(Weak::upgrade) {
+        return Ok(module);
+    }
+
+    // Compile without holding the lock; if another thread compiles the
+    // same module concurrently, the module that is inserted last wins
+    let module = match &ENV_VARS.mappings.module_cache_dir {
+        Some(dir) => load_or_compile(logger, dir, &key, raw_module)?,
+        None => ValidModule::new(logger, raw_module)?,
+    };
+    let module = Arc::new(module);
+
+    let mut modules = MODULES.lock().unwrap();
+    modules.retain(|_, module| module.strong_count() > 0);
+    modules.insert(key, Arc::downgrade(&module));
+    Ok(module)
+}
+
+/// The key for a module. Besides the code, it covers everything that
+/// changes how the module is compiled, since gas metering is injected into
+/// the code before compilation
+fn cache_key(raw_module: &[u8]) -> [u8; 32] {
+    let mut data = raw_module.to_vec();
+    data.extend_from_slice(env!("CARGO_PKG_VERSION").as_bytes());
+    data.extend_from_slice(&ENV_VARS.mappings.max_stack_size.to_le_bytes());
+    tiny_keccak::keccak256(&data)
+}
+
+fn load_or_compile(
+    logger: &Logger,
+    dir: &Path,
+    key: &[u8; 32],
+    raw_module: &[u8],
+) -> Result<ValidModule, anyhow::Error> {
+    let path = dir.join(format!("{}.cwasm", hex::encode(key)));
+
+    if let Ok(compiled) = fs::read(&path) {
+        match ValidModule::deserialize(&compiled) {
+            Ok(module) => return Ok(module),
+            // Artifacts from a different version of wasmtime can not be
+            // loaded; compile the module again and replace the artifact
+            Err(e) => warn!(logger, "Failed to load compiled WASM module, recompiling it";
+                            "path" => path.display().to_string(),
+                            "error" => e.to_string()),
+        }
+    }
+
+    let module = ValidModule::new(logger, raw_module)?;
+
+    // Failing to store the compiled module only costs another compilation
+    // later, and should not stop the subgraph
+    let store = || -> Result<(), anyhow::Error> {
+        fs::create_dir_all(dir)?;
+        // Write to a temporary file first so that other nodes sharing the
+        // directory never see a partially written artifact
+        let tmp = path.with_extension(format!("{}.tmp", uuid::Uuid::new_v4()));
+        fs::write(&tmp, module.module.serialize()?)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    };
+    if let Err(e) = store() {
+        warn!(logger, "Failed to store compiled WASM module";
+              "path" => path.display().to_string(),
+              "error" => e.to_string());
+    }
+
+    Ok(module)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::sync::Arc;
+
+    use graph::log::discard;
+
+    use super::{cache_key, load_or_compile, valid_module, ValidModule};
+
+    /// An empty WASM module
+    const EMPTY_MODULE: &[u8] = b"\0asm\x01\0\0\0";
+
+    #[test]
+    fn shares_modules_in_use() {
+        let logger = discard();
+        let module = valid_module(&logger, EMPTY_MODULE).unwrap();
+        let other = valid_module(&logger, EMPTY_MODULE).unwrap();
+        assert!(Arc::ptr_eq(&module, &other));
+
+        assert_ne!(
+            cache_key(EMPTY_MODULE),
+            cache_key(b"\0asm\x01\0\0\0\0\x02\x01a")
+        );
+    }
+
+    #[test]
+    fn stores_compiled_modules() {
+        let logger = discard();
+        let dir = std::env::temp_dir().join(format!("module-cache-{}", uuid::Uuid::new_v4()));
+        let key = cache_key(EMPTY_MODULE);
+        let path = dir.join(format!("{}.cwasm", hex::encode(key)));
+
+        load_or_compile(&logger, &dir, &key, EMPTY_MODULE).unwrap();
+        let compiled = fs::read(&path).unwrap();
+        assert_eq!(1, fs::read_dir(&dir).unwrap().count());
+
+        // The stored module is loaded instead of compiling it again
+        load_or_compile(&logger, &dir, &key, EMPTY_MODULE).unwrap();
+        assert_eq!(compiled, fs::read(&path).unwrap());
+
+        // An artifact that can not be loaded is replaced
+        fs::write(&path, b"garbage").unwrap();
+        load_or_compile(&logger, &dir, &key, EMPTY_MODULE).unwrap();
+        ValidModule::deserialize(&fs::read(&path).unwrap()).unwrap();
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,5 +1,6 @@
 use ethabi;
 
+use graph::data::subgraph::API_VERSION_0_0_8;
 use graph::prelude::{BigDecimal, BigInt};
 use graph::runtime::gas::GasCounter;
 use graph::runtime::{
@@ -270,6 +271,7 @@ impl FromAscObj<AscEnum<StoreValueKind>> for store::Value {
                 let array: Vec<u8> = asc_get(heap, ptr, gas)?;
                 Value::BigInt(store::scalar::BigInt::from_signed_bytes_le(&array))
             }
+            StoreValueKind::Int8 => Value::Int8(i64::from(payload)),
         })
     }
 }
@@ -282,6 +284,14 @@ impl ToAscObj<AscEnum<StoreValueKind>> for store::Value {
     ) -> Result<AscEnum<StoreValueKind>, HostExportError> {
         use self::store::Value;
 
+        // Mappings for API versions before 0.0.8 do not know about `Int8`
+        // and get these values as a `BigInt` instead
+        if let Value::Int8(n) = self {
+            if heap.api_version() < API_VERSION_0_0_8 {
+                return Value::BigInt(BigInt::from(*n)).to_asc_obj(heap, gas);
+            }
+        }
+
         let payload = match self {
             Value::String(string) => asc_new(heap, string.as_str(), gas)?.into(),
             Value::Int(n) => EnumPayload::from(*n),
@@ -298,6 +308,7 @@ impl ToAscObj<AscEnum<StoreValueKind>> for store::Value {
                     asc_new(heap, &*big_int.to_signed_bytes_le(), gas)?;
                 bytes_obj.into()
             }
+            Value::Int8(n) => EnumPayload::from(*n),
         };
 
         Ok(AscEnum {
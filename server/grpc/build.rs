@@ -0,0 +1,9 @@
+fn main() {
+    println!("cargo:rerun-if-changed=proto");
+
+    tonic_build::configure()
+        .out_dir("src/protobuf")
+        .build_client(false)
+        .compile(&["proto/entities.proto"], &["proto"])
+        .expect("Failed to compile entities proto");
+}
@@ -0,0 +1,5 @@
+mod protobuf;
+mod query;
+mod server;
+
+pub use self::server::GrpcServer;
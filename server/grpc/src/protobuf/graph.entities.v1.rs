@@ -0,0 +1,352 @@
+/// The subgraph to query
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Subgraph {
+    #[prost(oneof = "subgraph::Subgraph", tags = "1, 2")]
+    pub subgraph: ::core::option::Option<subgraph::Subgraph>,
+}
+/// Nested message and enum types in `Subgraph`.
+pub mod subgraph {
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Subgraph {
+        /// The name of the subgraph, whose current version is queried
+        #[prost(string, tag = "1")]
+        Name(::prost::alloc::string::String),
+        /// The IPFS hash of a deployment
+        #[prost(string, tag = "2")]
+        Deployment(::prost::alloc::string::String),
+    }
+}
+/// The block to query at, like the `block` argument in GraphQL. The latest
+/// block that the subgraph has indexed is used if none is given
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BlockHeight {
+    #[prost(oneof = "block_height::Block", tags = "1, 2, 3")]
+    pub block: ::core::option::Option<block_height::Block>,
+}
+/// Nested message and enum types in `BlockHeight`.
+pub mod block_height {
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Block {
+        #[prost(int32, tag = "1")]
+        Number(i32),
+        /// The block hash as a hex string with a `0x` prefix
+        #[prost(string, tag = "2")]
+        Hash(::prost::alloc::string::String),
+        /// The latest block, which has to be at least this block
+        #[prost(int32, tag = "3")]
+        NumberGte(i32),
+    }
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetRequest {
+    #[prost(message, optional, tag = "1")]
+    pub subgraph: ::core::option::Option<Subgraph>,
+    /// The name of the entity type as in the GraphQL schema, e.g., `Token`
+    #[prost(string, tag = "2")]
+    pub entity_type: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "4")]
+    pub block: ::core::option::Option<BlockHeight>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetResponse {
+    /// Not set if there is no such entity
+    #[prost(message, optional, tag = "1")]
+    pub entity: ::core::option::Option<Entity>,
+}
+/// A condition in the `where` argument of a GraphQL collection query, e.g.,
+/// the field `name_starts_with` with the value `"a"`
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Filter {
+    #[prost(string, tag = "1")]
+    pub field: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "2")]
+    pub value: ::core::option::Option<Value>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListRequest {
+    #[prost(message, optional, tag = "1")]
+    pub subgraph: ::core::option::Option<Subgraph>,
+    #[prost(string, tag = "2")]
+    pub entity_type: ::prost::alloc::string::String,
+    /// The conditions that entities have to match, which are all combined
+    /// with `and`
+    #[prost(message, repeated, tag = "3")]
+    pub filter: ::prost::alloc::vec::Vec<Filter>,
+    /// The field to order by. Entities are ordered by id if this is empty
+    #[prost(string, tag = "4")]
+    pub order_by: ::prost::alloc::string::String,
+    #[prost(enumeration = "OrderDirection", tag = "5")]
+    pub order_direction: i32,
+    /// How many entities to return. The default of GraphQL queries is used
+    /// if this is 0
+    #[prost(int32, tag = "6")]
+    pub first: i32,
+    #[prost(int32, tag = "7")]
+    pub skip: i32,
+    #[prost(message, optional, tag = "8")]
+    pub block: ::core::option::Option<BlockHeight>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub entities: ::prost::alloc::vec::Vec<Entity>,
+}
+/// The fields of an entity. Fields that are null are not included, and
+/// neither are fields that hold lists of references to other entities
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Entity {
+    #[prost(map = "string, message", tag = "1")]
+    pub fields: ::std::collections::HashMap<::prost::alloc::string::String, Value>,
+}
+/// The value of a field, whose kind follows from the type of the field in
+/// the GraphQL schema. A value without a kind is null
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Value {
+    #[prost(oneof = "value::Kind", tags = "1, 2, 3, 4, 5, 6, 7, 8")]
+    pub kind: ::core::option::Option<value::Kind>,
+}
+/// Nested message and enum types in `Value`.
+pub mod value {
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Kind {
+        /// A `String` or `ID`, or the value of an enum
+        #[prost(string, tag = "1")]
+        String(::prost::alloc::string::String),
+        /// An `Int` or `Int8`
+        #[prost(int64, tag = "2")]
+        Int(i64),
+        /// A `BigInt` as a decimal string
+        #[prost(string, tag = "3")]
+        BigInt(::prost::alloc::string::String),
+        /// A `BigDecimal` as a decimal string
+        #[prost(string, tag = "4")]
+        BigDecimal(::prost::alloc::string::String),
+        #[prost(bytes, tag = "5")]
+        Bytes(::prost::alloc::vec::Vec<u8>),
+        #[prost(bool, tag = "6")]
+        Bool(bool),
+        #[prost(message, tag = "7")]
+        List(super::ValueList),
+        /// The id of the entity that a field references
+        #[prost(string, tag = "8")]
+        Reference(::prost::alloc::string::String),
+    }
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ValueList {
+    #[prost(message, repeated, tag = "1")]
+    pub values: ::prost::alloc::vec::Vec<Value>,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum OrderDirection {
+    Asc = 0,
+    Desc = 1,
+}
+impl OrderDirection {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            OrderDirection::Asc => "ASC",
+            OrderDirection::Desc => "DESC",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "ASC" => Some(Self::Asc),
+            "DESC" => Some(Self::Desc),
+            _ => None,
+        }
+    }
+}
+/// Generated server implementations.
+pub mod entities_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with EntitiesServer.
+    #[async_trait]
+    pub trait Entities: Send + Sync + 'static {
+        /// Get the entity of a type with a given id
+        async fn get(
+            &self,
+            request: tonic::Request<super::GetRequest>,
+        ) -> Result<tonic::Response<super::GetResponse>, tonic::Status>;
+        /// List the entities of a type that match a filter
+        async fn list(
+            &self,
+            request: tonic::Request<super::ListRequest>,
+        ) -> Result<tonic::Response<super::ListResponse>, tonic::Status>;
+    }
+    /// Reads the entities of subgraphs. Requests are answered by running the
+    /// equivalent GraphQL query, so the same limits, block constraints and
+    /// caching apply as for GraphQL queries, but clients do not have to
+    /// generate GraphQL or parse JSON responses.
+    #[derive(Debug)]
+    pub struct EntitiesServer<T: Entities> {
+        inner: _Inner<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+    }
+    struct _Inner<T>(Arc<T>);
+    impl<T: Entities> EntitiesServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            let inner = _Inner(inner);
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+            }
+        }
+        pub fn with_interceptor<F>(inner: T, interceptor: F) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for EntitiesServer<T>
+    where
+        T: Entities,
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            let inner = self.inner.clone();
+            match req.uri().path() {
+                "/graph.entities.v1.Entities/Get" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetSvc<T: Entities>(pub Arc<T>);
+                    impl<T: Entities> tonic::server::UnaryService<super::GetRequest> for GetSvc<T> {
+                        type Response = super::GetResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).get(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/graph.entities.v1.Entities/List" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListSvc<T: Entities>(pub Arc<T>);
+                    impl<T: Entities> tonic::server::UnaryService<super::ListRequest> for ListSvc<T> {
+                        type Response = super::ListResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).list(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ListSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => Box::pin(async move {
+                    Ok(http::Response::builder()
+                        .status(200)
+                        .header("grpc-status", "12")
+                        .header("content-type", "application/grpc")
+                        .body(empty_body())
+                        .unwrap())
+                }),
+            }
+        }
+    }
+    impl<T: Entities> Clone for EntitiesServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self {
+                inner,
+                accept_compression_encodings: self.accept_compression_encodings,
+                send_compression_encodings: self.send_compression_encodings,
+            }
+        }
+    }
+    impl<T: Entities> Clone for _Inner<T> {
+        fn clone(&self) -> Self {
+            Self(self.0.clone())
+        }
+    }
+    impl<T: std::fmt::Debug> std::fmt::Debug for _Inner<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self.0)
+        }
+    }
+    impl<T: Entities> tonic::server::NamedService for EntitiesServer<T> {
+        const NAME: &'static str = "graph.entities.v1.Entities";
+    }
+}
@@ -0,0 +1,5 @@
+#[rustfmt::skip]
+#[path = "graph.entities.v1.rs"]
+mod pbentities;
+
+pub use pbentities::*;
@@ -0,0 +1,413 @@
+//! Translating requests to the gRPC API into GraphQL queries, and the
+//! results of these queries back into entities. Like the REST facade of
+//! the HTTP server, the entity type is introspected first, so that the
+//! selection can be generated from its fields and the values of the fields
+//! can be converted to the kinds that their types call for.
+use std::collections::HashMap;
+
+use graph::prelude::serde_json::{self, json, Map, Value as JsonValue};
+use graph::prelude::{hex, Query};
+use inflector::Inflector;
+use tonic::Status;
+
+use crate::protobuf::{
+    block_height::Block, value::Kind, BlockHeight, Entity, Filter, OrderDirection, Value, ValueList,
+};
+
+/// The type of a field of an entity type, without `NON_NULL` wrappers
+#[derive(Clone, Debug, PartialEq)]
+enum FieldType {
+    /// A scalar with the given name, like `BigInt`
+    Scalar(String),
+    Enum,
+    /// A reference to another entity, of which only the id is selected
+    Reference,
+    List(Box<FieldType>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Field {
+    name: String,
+    ty: FieldType,
+}
+
+/// The fields of an entity type that requests select
+#[derive(Debug)]
+pub(crate) struct EntityType {
+    name: String,
+    fields: Vec<Field>,
+}
+
+/// A query that introspects the fields of `entity_type`
+pub(crate) fn type_query(entity_type: &str) -> Result<Query, Status> {
+    if !is_name(entity_type) {
+        return Err(Status::invalid_argument(format!(
+            "invalid entity type `{}`",
+            entity_type
+        )));
+    }
+    let text = format!(
+        "{{ __type(name: \"{}\") {{ kind fields {{ name type {{ ...TypeRef }} }} }} }} \
+         fragment TypeRef on __Type {{ kind name ofType {{ kind name ofType {{ \
+         kind name ofType {{ kind name }} }} }} }}",
+        entity_type
+    );
+    let document = graphql_parser::parse_query(&text)
+        .expect("the type query is valid")
+        .into_static();
+    Ok(Query::new(document, None, false))
+}
+
+impl EntityType {
+    /// The entity type from the result of running `type_query`. Returns
+    /// `None` if there is no such entity type
+    pub(crate) fn from_type_data(name: &str, type_data: &JsonValue) -> Option<Self> {
+        let ty = &type_data["__type"];
+        if ty["kind"] != "OBJECT" && ty["kind"] != "INTERFACE" {
+            return None;
+        }
+        let fields = ty["fields"]
+            .as_array()
+            .map(|fields| fields.iter().filter_map(field).collect())
+            .unwrap_or_default();
+        Some(EntityType {
+            name: name.to_string(),
+            fields,
+        })
+    }
+
+    fn selection(&self) -> String {
+        self.fields
+            .iter()
+            .map(|field| match innermost(&field.ty) {
+                FieldType::Reference => format!("{} {{ id }}", field.name),
+                _ => field.name.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The name of the field on `Query` for a single entity
+    pub(crate) fn single_field(&self) -> String {
+        self.name.to_camel_case()
+    }
+
+    /// The name of the collection field on `Query`
+    pub(crate) fn collection_field(&self) -> String {
+        self.name.to_plural().to_camel_case()
+    }
+
+    /// The query for the entity with `id`
+    pub(crate) fn get_query(&self, id: &str, block: Option<&BlockHeight>) -> Result<Query, Status> {
+        let mut variables = Map::new();
+        variables.insert("id".to_string(), json!(id));
+        if let Some(block) = block_height(block) {
+            variables.insert("block".to_string(), block);
+        }
+        self.query(&self.single_field(), variables)
+    }
+
+    /// The collection query for entities that match `filter`
+    pub(crate) fn list_query(
+        &self,
+        filter: &[Filter],
+        order_by: &str,
+        order_direction: i32,
+        first: i32,
+        skip: i32,
+        block: Option<&BlockHeight>,
+    ) -> Result<Query, Status> {
+        let mut variables = Map::new();
+        if !filter.is_empty() {
+            let filter = filter
+                .iter()
+                .map(|filter| (filter.field.clone(), json_value(filter.value.as_ref())))
+                .collect();
+            variables.insert("where".to_string(), JsonValue::Object(filter));
+        }
+        if !order_by.is_empty() {
+            variables.insert("orderBy".to_string(), json!(order_by));
+            let direction = OrderDirection::from_i32(order_direction)
+                .ok_or_else(|| Status::invalid_argument("invalid order direction"))?;
+            variables.insert(
+                "orderDirection".to_string(),
+                json!(direction.as_str_name().to_lowercase()),
+            );
+        }
+        if first != 0 {
+            variables.insert("first".to_string(), json!(first));
+        }
+        if skip != 0 {
+            variables.insert("skip".to_string(), json!(skip));
+        }
+        if let Some(block) = block_height(block) {
+            variables.insert("block".to_string(), block);
+        }
+        self.query(&self.collection_field(), variables)
+    }
+
+    fn query(&self, field: &str, variables: Map<String, JsonValue>) -> Result<Query, Status> {
+        let (params, args): (Vec<_>, Vec<_>) = variables
+            .keys()
+            .map(|name| {
+                let ty = match name.as_str() {
+                    "id" => "ID!".to_string(),
+                    "where" => format!("{}_filter", self.name),
+                    "orderBy" => format!("{}_orderBy", self.name),
+                    "orderDirection" => "OrderDirection".to_string(),
+                    "block" => "Block_height".to_string(),
+                    _ => "Int".to_string(),
+                };
+                (format!("${}: {}", name, ty), format!("{}: ${}", name, name))
+            })
+            .unzip();
+        let text = if params.is_empty() {
+            format!("{{ {} {{ {} }} }}", field, self.selection())
+        } else {
+            format!(
+                "query({}) {{ {}({}) {{ {} }} }}",
+                params.join(", "),
+                field,
+                args.join(", "),
+                self.selection()
+            )
+        };
+
+        let document = graphql_parser::parse_query(&text)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?
+            .into_static();
+        let variables = serde_json::from_value(JsonValue::Object(variables))
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        Ok(Query::new(document, Some(variables), false))
+    }
+
+    /// Convert an entity in the result of a query into an `Entity`
+    pub(crate) fn entity(&self, data: &JsonValue) -> Entity {
+        let fields: HashMap<_, _> = self
+            .fields
+            .iter()
+            .filter_map(|field| {
+                let value = convert(&field.ty, &data[field.name.as_str()])?;
+                Some((field.name.clone(), value))
+            })
+            .collect();
+        Entity { fields }
+    }
+}
+
+/// The `block` argument of a query
+fn block_height(block: Option<&BlockHeight>) -> Option<JsonValue> {
+    match block?.block.as_ref()? {
+        Block::Number(number) => Some(json!({ "number": number })),
+        Block::Hash(hash) => Some(json!({ "hash": hash })),
+        Block::NumberGte(number) => Some(json!({ "number_gte": number })),
+    }
+}
+
+/// The value of `value` as a GraphQL variable
+fn json_value(value: Option<&Value>) -> JsonValue {
+    match value.and_then(|value| value.kind.as_ref()) {
+        None => JsonValue::Null,
+        Some(Kind::String(s))
+        | Some(Kind::BigInt(s))
+        | Some(Kind::BigDecimal(s))
+        | Some(Kind::Reference(s)) => json!(s),
+        Some(Kind::Int(i)) => json!(i),
+        Some(Kind::Bytes(bytes)) => json!(format!("0x{}", hex::encode(bytes))),
+        Some(Kind::Bool(b)) => json!(b),
+        Some(Kind::List(list)) => {
+            JsonValue::Array(list.values.iter().map(|v| json_value(Some(v))).collect())
+        }
+    }
+}
+
+/// Convert `value` from a query result to a `Value` of the kind that `ty`
+/// calls for. Returns `None` for nulls
+fn convert(ty: &FieldType, value: &JsonValue) -> Option<Value> {
+    let kind = match (ty, value) {
+        (_, JsonValue::Null) => return None,
+        (FieldType::List(ty), JsonValue::Array(values)) => Kind::List(ValueList {
+            values: values
+                .iter()
+                .map(|value| convert(ty, value).unwrap_or(Value { kind: None }))
+                .collect(),
+        }),
+        (FieldType::Reference, value) => Kind::Reference(value["id"].as_str()?.to_string()),
+        (FieldType::Scalar(name), value) => match (name.as_str(), value) {
+            ("BigInt", value) => Kind::BigInt(scalar_string(value)),
+            ("BigDecimal", value) => Kind::BigDecimal(scalar_string(value)),
+            ("Bytes", JsonValue::String(s)) => {
+                Kind::Bytes(hex::decode(s.trim_start_matches("0x")).ok()?)
+            }
+            (_, JsonValue::Bool(b)) => Kind::Bool(*b),
+            (_, JsonValue::Number(n)) if n.is_i64() => Kind::Int(n.as_i64()?),
+            // `Int8` is serialized as a string
+            ("Int8", JsonValue::String(s)) => Kind::Int(s.parse().ok()?),
+            (_, value) => Kind::String(scalar_string(value)),
+        },
+        (_, value) => Kind::String(scalar_string(value)),
+    };
+    Some(Value { kind: Some(kind) })
+}
+
+fn scalar_string(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn innermost(ty: &FieldType) -> &FieldType {
+    match ty {
+        FieldType::List(ty) => innermost(ty),
+        ty => ty,
+    }
+}
+
+fn is_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .map(|c| c.is_ascii_alphabetic() || c == '_')
+        .unwrap_or(false)
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// The field of the entity type that `field` from the type query
+/// describes. Lists of references to other entities are left out since they
+/// can be arbitrarily large
+fn field(field: &JsonValue) -> Option<Field> {
+    let name = field["name"].as_str()?;
+    if name.starts_with("__") {
+        return None;
+    }
+
+    fn field_type(ty: &JsonValue, list: bool) -> Option<FieldType> {
+        match ty["kind"].as_str()? {
+            "NON_NULL" => field_type(&ty["ofType"], list),
+            "LIST" => Some(FieldType::List(Box::new(field_type(&ty["ofType"], true)?))),
+            "SCALAR" => Some(FieldType::Scalar(ty["name"].as_str()?.to_string())),
+            "ENUM" => Some(FieldType::Enum),
+            "OBJECT" | "INTERFACE" if !list => Some(FieldType::Reference),
+            _ => None,
+        }
+    }
+
+    Some(Field {
+        name: name.to_string(),
+        ty: field_type(&field["type"], false)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use graph::prelude::serde_json::json;
+
+    use crate::protobuf::{
+        block_height::Block, value::Kind, BlockHeight, Filter, OrderDirection, Value, ValueList,
+    };
+
+    use super::EntityType;
+
+    fn token_type() -> EntityType {
+        let non_null = |kind: &str, name: &str| json!({ "kind": "NON_NULL", "name": null, "ofType": { "kind": kind, "name": name }});
+        let type_data = json!({ "__type": { "kind": "OBJECT", "fields": [
+            { "name": "id", "type": non_null("SCALAR", "ID") },
+            { "name": "supply", "type": non_null("SCALAR", "BigInt") },
+            { "name": "hash", "type": { "kind": "SCALAR", "name": "Bytes" }},
+            { "name": "decimals", "type": non_null("SCALAR", "Int") },
+            { "name": "owner", "type": { "kind": "OBJECT", "name": "Account" }},
+            { "name": "tags", "type": { "kind": "LIST", "name": null,
+                                        "ofType": non_null("SCALAR", "String") }},
+            { "name": "holders", "type": { "kind": "LIST", "name": null,
+                                           "ofType": non_null("OBJECT", "Account") }},
+        ]}});
+        EntityType::from_type_data("Token", &type_data).unwrap()
+    }
+
+    fn value(kind: Kind) -> Value {
+        Value { kind: Some(kind) }
+    }
+
+    #[test]
+    fn generates_queries() {
+        let token = token_type();
+        assert!(EntityType::from_type_data("Token", &json!({ "__type": null })).is_none());
+
+        let query = token.get_query("0x1", None).unwrap();
+        let expected = graphql_parser::parse_query::<String>(
+            "query($id: ID!) { token(id: $id) { id supply hash decimals owner { id } tags } }",
+        )
+        .unwrap();
+        assert_eq!(expected.to_string(), query.document.to_string());
+
+        let block = BlockHeight {
+            block: Some(Block::Number(17)),
+        };
+        let filter = vec![Filter {
+            field: "decimals_gt".to_string(),
+            value: Some(value(Kind::Int(6))),
+        }];
+        let query = token
+            .list_query(
+                &filter,
+                "supply",
+                OrderDirection::Desc as i32,
+                5,
+                0,
+                Some(&block),
+            )
+            .unwrap();
+        let expected = graphql_parser::parse_query::<String>(
+            "query($block: Block_height, $first: Int, $orderBy: Token_orderBy, \
+             $orderDirection: OrderDirection, $where: Token_filter) { \
+             tokens(block: $block, first: $first, orderBy: $orderBy, \
+             orderDirection: $orderDirection, where: $where) { \
+             id supply hash decimals owner { id } tags } }",
+        )
+        .unwrap();
+        assert_eq!(expected.to_string(), query.document.to_string());
+    }
+
+    #[test]
+    fn converts_entities() {
+        let token = token_type();
+        let entity = token.entity(&json!({
+            "id": "0x1",
+            "supply": "1000000000000000000000",
+            "hash": "0xdeadbeef",
+            "decimals": 18,
+            "owner": { "id": "0x2" },
+            "tags": ["a", "b"],
+        }));
+
+        assert_eq!(6, entity.fields.len());
+        assert_eq!(value(Kind::String("0x1".to_string())), entity.fields["id"]);
+        assert_eq!(
+            value(Kind::BigInt("1000000000000000000000".to_string())),
+            entity.fields["supply"]
+        );
+        assert_eq!(
+            value(Kind::Bytes(vec![0xde, 0xad, 0xbe, 0xef])),
+            entity.fields["hash"]
+        );
+        assert_eq!(value(Kind::Int(18)), entity.fields["decimals"]);
+        assert_eq!(
+            value(Kind::Reference("0x2".to_string())),
+            entity.fields["owner"]
+        );
+        assert_eq!(
+            value(Kind::List(ValueList {
+                values: vec![
+                    value(Kind::String("a".to_string())),
+                    value(Kind::String("b".to_string()))
+                ]
+            })),
+            entity.fields["tags"]
+        );
+
+        // Nulls are left out
+        let entity = token.entity(&json!({ "id": "0x1", "owner": null }));
+        assert_eq!(1, entity.fields.len());
+    }
+}
@@ -0,0 +1,208 @@
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::sync::Arc;
+use std::time::Instant;
+
+use graph::data::query::{QueryResults, QueryTarget};
+use graph::prelude::serde_json::{self, Value as JsonValue};
+use graph::prelude::{
+    info, ApiVersion, CheapClone, ComponentLoggerConfig, DeploymentHash,
+    ElasticComponentLoggerConfig, GraphQlRunner, Logger, LoggerFactory, Query, SubgraphName,
+    ENV_VARS,
+};
+use graph_server_http::{QueryLimits, QueryPermit};
+use tonic::codec::CompressionEncoding;
+use tonic::{Request, Response, Status};
+
+use crate::protobuf::entities_server::{Entities, EntitiesServer};
+use crate::protobuf::{subgraph, GetRequest, GetResponse, ListRequest, ListResponse, Subgraph};
+use crate::query::{type_query, EntityType};
+
+/// A gRPC server for reading entities
+pub struct GrpcServer<Q> {
+    logger: Logger,
+    graphql_runner: Arc<Q>,
+    limits: Arc<QueryLimits>,
+}
+
+impl<Q> GrpcServer<Q>
+where
+    Q: GraphQlRunner,
+{
+    pub fn new(logger_factory: &LoggerFactory, graphql_runner: Arc<Q>) -> Self {
+        let logger = logger_factory.component_logger(
+            "GrpcServer",
+            Some(ComponentLoggerConfig {
+                elastic: Some(ElasticComponentLoggerConfig {
+                    index: String::from("grpc-server-logs"),
+                }),
+            }),
+        );
+        GrpcServer {
+            logger,
+            graphql_runner,
+            limits: Arc::new(QueryLimits::from_env(&ENV_VARS)),
+        }
+    }
+
+    pub async fn serve(self, port: u16) -> Result<(), tonic::transport::Error> {
+        info!(
+            self.logger,
+            "Starting gRPC entity server at: http://localhost:{}", port
+        );
+
+        let addr = SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), port);
+        let service = EntitiesService {
+            graphql_runner: self.graphql_runner,
+            limits: self.limits,
+        };
+        tonic::transport::Server::builder()
+            .add_service(
+                EntitiesServer::new(service)
+                    .accept_compressed(CompressionEncoding::Gzip)
+                    .send_compressed(CompressionEncoding::Gzip),
+            )
+            .serve(addr.into())
+            .await
+    }
+}
+
+struct EntitiesService<Q> {
+    graphql_runner: Arc<Q>,
+    limits: Arc<QueryLimits>,
+}
+
+impl<Q> EntitiesService<Q>
+where
+    Q: GraphQlRunner,
+{
+    /// Check the limits for queries against `target` sent with the API key
+    /// in the metadata of `request`. The returned permit must be held
+    /// until the query has finished running
+    async fn admit<T>(
+        &self,
+        request: &Request<T>,
+        target: &QueryTarget,
+    ) -> Result<QueryPermit, Status> {
+        let api_key = request
+            .metadata()
+            .get(ENV_VARS.graphql.api_key_header.to_lowercase().as_str())
+            .and_then(|v| v.to_str().ok());
+        self.limits
+            .admit_target(&*self.graphql_runner, target, api_key)
+            .await
+            .map_err(|e| Status::resource_exhausted(e.message()))
+    }
+
+    /// Introspect the entity type `name`
+    async fn entity_type(&self, target: &QueryTarget, name: &str) -> Result<EntityType, Status> {
+        let results = self
+            .graphql_runner
+            .cheap_clone()
+            .run_query(type_query(name)?, target.clone())
+            .await;
+        let type_data = results_data(&results)?;
+        EntityType::from_type_data(name, &type_data)
+            .ok_or_else(|| Status::not_found(format!("unknown entity type `{}`", name)))
+    }
+
+    /// Run `query` and return the data of its result
+    async fn run(
+        &self,
+        target: QueryTarget,
+        query: Query,
+        start: Instant,
+    ) -> Result<JsonValue, Status> {
+        let results = self
+            .graphql_runner
+            .cheap_clone()
+            .run_query(query, target)
+            .await;
+        self.graphql_runner
+            .metrics()
+            .observe_query_execution(start.elapsed(), &results);
+        results_data(&results)
+    }
+}
+
+#[tonic::async_trait]
+impl<Q> Entities for EntitiesService<Q>
+where
+    Q: GraphQlRunner,
+{
+    async fn get(&self, request: Request<GetRequest>) -> Result<Response<GetResponse>, Status> {
+        let target = query_target(request.get_ref().subgraph.as_ref())?;
+        let _permit = self.admit(&request, &target).await?;
+        let request = request.into_inner();
+
+        let start = Instant::now();
+        let entity_type = self.entity_type(&target, &request.entity_type).await?;
+        let query = entity_type.get_query(&request.id, request.block.as_ref())?;
+        let data = self.run(target, query, start).await?;
+        let entity = match &data[entity_type.single_field().as_str()] {
+            JsonValue::Null => None,
+            entity => Some(entity_type.entity(entity)),
+        };
+        Ok(Response::new(GetResponse { entity }))
+    }
+
+    async fn list(&self, request: Request<ListRequest>) -> Result<Response<ListResponse>, Status> {
+        let target = query_target(request.get_ref().subgraph.as_ref())?;
+        let _permit = self.admit(&request, &target).await?;
+        let request = request.into_inner();
+
+        let start = Instant::now();
+        let entity_type = self.entity_type(&target, &request.entity_type).await?;
+        let query = entity_type.list_query(
+            &request.filter,
+            &request.order_by,
+            request.order_direction,
+            request.first,
+            request.skip,
+            request.block.as_ref(),
+        )?;
+        let data = self.run(target, query, start).await?;
+        let entities = data[entity_type.collection_field().as_str()]
+            .as_array()
+            .map(|entities| {
+                entities
+                    .iter()
+                    .map(|entity| entity_type.entity(entity))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(Response::new(ListResponse { entities }))
+    }
+}
+
+fn query_target(subgraph: Option<&Subgraph>) -> Result<QueryTarget, Status> {
+    let version = ApiVersion::default();
+    match subgraph.and_then(|subgraph| subgraph.subgraph.as_ref()) {
+        Some(subgraph::Subgraph::Name(name)) => SubgraphName::new(name.as_str())
+            .map(|name| QueryTarget::Name(name, version))
+            .map_err(|()| Status::invalid_argument(format!("invalid subgraph name `{}`", name))),
+        Some(subgraph::Subgraph::Deployment(id)) => DeploymentHash::new(id.as_str())
+            .map(|id| QueryTarget::Deployment(id, version))
+            .map_err(|id| Status::invalid_argument(format!("invalid deployment `{}`", id))),
+        None => Err(Status::invalid_argument("the subgraph to query is missing")),
+    }
+}
+
+/// The `data` of successful query results as JSON
+fn results_data(results: &QueryResults) -> Result<JsonValue, Status> {
+    if results.has_errors() {
+        let message = results
+            .errors()
+            .iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+            .join("; ");
+        return if results.not_found() {
+            Err(Status::not_found(message))
+        } else {
+            Err(Status::invalid_argument(message))
+        };
+    }
+    serde_json::to_value(results)
+        .map_err(|e| Status::internal(e.to_string()))
+        .map(|mut value| value["data"].take())
+}
@@ -0,0 +1,322 @@
+//! The query audit log records one JSON object for every GraphQL query
+//! that the server answers, or for a sample of them. Records are appended
+//! to a file that is rotated once it gets too big, and/or sent to a Kafka
+//! topic, keyed by deployment
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use graph::data::query::QueryResults;
+use graph::env::EnvVars;
+use graph::prelude::chrono::{SecondsFormat, Utc};
+use graph::prelude::rand::{thread_rng, Rng};
+use graph::prelude::*;
+use rdkafka::config::ClientConfig;
+use rdkafka::producer::{BaseRecord, DefaultProducerContext, ThreadedProducer};
+use serde::Serialize;
+
+/// A record in the audit log
+#[derive(Debug, Serialize)]
+struct AuditRecord<'a> {
+    timestamp: String,
+    /// The subgraph name or deployment hash the query was sent to
+    target: String,
+    /// The deployment that answered the query, if the query got far
+    /// enough to determine that
+    deployment: Option<&'a str>,
+    query_hash: String,
+    variables_hash: String,
+    duration_ms: u64,
+    result_size: usize,
+    api_key: Option<&'a str>,
+    errors: bool,
+}
+
+/// What is known about a query before it runs
+pub struct AuditQuery {
+    query_hash: u64,
+    variables_hash: u64,
+}
+
+impl AuditQuery {
+    pub fn new(query: &Query) -> Self {
+        AuditQuery {
+            query_hash: query.shape_hash,
+            variables_hash: variables_hash(query.variables.as_ref()),
+        }
+    }
+}
+
+/// Hash the variables of a query so that queries with the same variables
+/// can be told apart from others without recording the variables. The
+/// hash does not depend on the order of the variables
+fn variables_hash(variables: Option<&QueryVariables>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    if let Some(variables) = variables {
+        let mut variables: Vec<_> = variables.iter().collect();
+        variables.sort_by_key(|(name, _)| *name);
+        for (name, value) in variables {
+            name.hash(&mut hasher);
+            value.to_string().hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+/// A file that is rotated once it reaches `max_size` bytes. On rotation,
+/// `path` is renamed to `path.1`, `path.1` to `path.2` and so on, keeping
+/// at most `keep` old files
+struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    keep: usize,
+    size: u64,
+    out: BufWriter<File>,
+}
+
+impl RotatingFile {
+    fn open(path: &Path, max_size: u64, keep: usize) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(RotatingFile {
+            path: path.to_path_buf(),
+            max_size,
+            keep,
+            size,
+            out: BufWriter::new(file),
+        })
+    }
+
+    fn rotated_path(&self, n: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", n));
+        PathBuf::from(path)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.out.flush()?;
+        if self.keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            for n in (1..self.keep).rev() {
+                let from = self.rotated_path(n);
+                if from.exists() {
+                    fs::rename(from, self.rotated_path(n + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.out = BufWriter::new(file);
+        self.size = 0;
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let len = line.len() as u64 + 1;
+        if self.size > 0 && self.size + len > self.max_size {
+            self.rotate()?;
+        }
+        self.out.write_all(line.as_bytes())?;
+        self.out.write_all(b"\n")?;
+        self.out.flush()?;
+        self.size += len;
+        Ok(())
+    }
+}
+
+struct KafkaSink {
+    producer: ThreadedProducer<DefaultProducerContext>,
+    topic: String,
+}
+
+/// The query audit log. Records are written on a best-effort basis:
+/// failures to write them are logged and otherwise ignored
+pub struct QueryAuditLog {
+    logger: Logger,
+    file: Option<Mutex<RotatingFile>>,
+    kafka: Option<KafkaSink>,
+    sample_rate: f64,
+    slow_query: Option<Duration>,
+}
+
+impl QueryAuditLog {
+    /// An audit log that does not record anything
+    pub fn disabled() -> Self {
+        QueryAuditLog {
+            logger: Logger::root(slog::Discard, o!()),
+            file: None,
+            kafka: None,
+            sample_rate: 0.0,
+            slow_query: None,
+        }
+    }
+
+    /// The audit log configured with the `GRAPH_GRAPHQL_AUDIT_LOG_*`
+    /// environment variables
+    pub fn from_env(logger: &Logger, env: &EnvVars) -> Result<Self, Error> {
+        let env = &env.graphql;
+        let file = env
+            .audit_log_file
+            .as_ref()
+            .map(|path| {
+                RotatingFile::open(
+                    Path::new(path),
+                    env.audit_log_file_max_size,
+                    env.audit_log_file_keep,
+                )
+                .map(Mutex::new)
+                .map_err(|e| anyhow!("failed to open audit log file {}: {}", path, e))
+            })
+            .transpose()?;
+        let kafka = env
+            .audit_log_kafka_brokers
+            .as_ref()
+            .map(|brokers| {
+                ClientConfig::new()
+                    .set("bootstrap.servers", brokers)
+                    .create()
+                    .map(|producer| KafkaSink {
+                        producer,
+                        topic: env.audit_log_kafka_topic.clone(),
+                    })
+                    .map_err(|e| anyhow!("failed to connect to Kafka at {}: {}", brokers, e))
+            })
+            .transpose()?;
+        Ok(QueryAuditLog {
+            logger: logger.new(o!("component" => "QueryAuditLog")),
+            file,
+            kafka,
+            sample_rate: env.audit_log_sample_rate,
+            slow_query: env.audit_log_slow_query,
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.file.is_some() || self.kafka.is_some()
+    }
+
+    /// Whether a query that took `duration` should be recorded
+    fn sampled(&self, duration: Duration) -> bool {
+        if self.slow_query.map_or(false, |slow| duration >= slow) {
+            return true;
+        }
+        self.sample_rate >= 1.0
+            || (self.sample_rate > 0.0 && thread_rng().gen_bool(self.sample_rate))
+    }
+
+    /// Record a query that was sent to `target` and answered with
+    /// `results`, which took `result_size` bytes. If the query could not
+    /// be parsed, `query` is `None`, and the query is recorded with zero
+    /// hashes
+    pub fn record(
+        &self,
+        target: &QueryTarget,
+        query: Option<&AuditQuery>,
+        results: &QueryResults,
+        result_size: usize,
+        duration: Duration,
+        api_key: Option<&str>,
+    ) {
+        if !self.is_enabled() || !self.sampled(duration) {
+            return;
+        }
+
+        let deployment = results
+            .first()
+            .and_then(|result| result.deployment.as_ref())
+            .map(|deployment| deployment.as_str());
+        let target = match target {
+            QueryTarget::Name(name, _) => name.to_string(),
+            QueryTarget::Deployment(id, _) => id.to_string(),
+        };
+        let record = AuditRecord {
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            target,
+            deployment,
+            query_hash: format!("{:016x}", query.map_or(0, |q| q.query_hash)),
+            variables_hash: format!("{:016x}", query.map_or(0, |q| q.variables_hash)),
+            duration_ms: duration.as_millis() as u64,
+            result_size,
+            api_key,
+            errors: results.has_errors(),
+        };
+        let line = match serde_json::to_string(&record) {
+            Ok(line) => line,
+            Err(e) => {
+                error!(self.logger, "Failed to serialize audit record"; "error" => e.to_string());
+                return;
+            }
+        };
+
+        if let Some(file) = &self.file {
+            if let Err(e) = file.lock().unwrap().write_line(&line) {
+                error!(self.logger, "Failed to write audit log file"; "error" => e.to_string());
+            }
+        }
+        if let Some(kafka) = &self.kafka {
+            let record = BaseRecord::to(&kafka.topic)
+                .key(deployment.unwrap_or(""))
+                .payload(&line);
+            if let Err((e, _)) = kafka.producer.send(record) {
+                error!(self.logger, "Failed to send audit record to Kafka"; "error" => e.to_string());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+
+    use graph::prelude::{r, QueryVariables};
+
+    use super::{variables_hash, RotatingFile};
+
+    #[test]
+    fn variables_hash_ignores_order() {
+        let vars = |pairs: Vec<(&str, i32)>| {
+            QueryVariables::new(
+                pairs
+                    .into_iter()
+                    .map(|(name, value)| (name.to_string(), r::Value::Int(value.into())))
+                    .collect::<HashMap<_, _>>(),
+            )
+        };
+        let a = vars(vec![("first", 10), ("skip", 20)]);
+        let b = vars(vec![("skip", 20), ("first", 10)]);
+        let c = vars(vec![("first", 20), ("skip", 10)]);
+        assert_eq!(variables_hash(Some(&a)), variables_hash(Some(&b)));
+        assert_ne!(variables_hash(Some(&a)), variables_hash(Some(&c)));
+    }
+
+    #[test]
+    fn rotates_files() {
+        let dir = std::env::temp_dir().join(format!("audit-log-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("queries.log");
+
+        // Each line is 10 bytes including the newline, so that every file
+        // holds two lines
+        let mut file = RotatingFile::open(&path, 20, 2).unwrap();
+        for i in 0..7 {
+            file.write_line(&format!("line {:04}", i)).unwrap();
+        }
+
+        let read = |name: &str| fs::read_to_string(dir.join(name)).unwrap();
+        assert_eq!("line 0006\n", read("queries.log"));
+        assert_eq!("line 0004\nline 0005\n", read("queries.log.1"));
+        assert_eq!("line 0002\nline 0003\n", read("queries.log.2"));
+        assert!(!dir.join("queries.log.3").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
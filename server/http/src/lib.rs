@@ -6,10 +6,15 @@ extern crate http;
 extern crate hyper;
 extern crate serde;
 
+mod audit;
+mod limits;
 mod request;
+mod rest;
 mod server;
 mod service;
 
+pub use self::audit::QueryAuditLog;
+pub use self::limits::{QueryLimits, QueryPermit};
 pub use self::server::GraphQLServer;
 pub use self::service::{GraphQLService, GraphQLServiceResponse};
 
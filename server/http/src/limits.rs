@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use graph::data::query::QueryTarget;
+use graph::env::EnvVars;
+use graph::prelude::{DeploymentHash, GraphQlRunner};
+
+/// The length of the window over which query rates are measured
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Once a rate table has more than this many entries, entries for windows
+/// that have already expired are removed
+const RATE_TABLE_PRUNE_SIZE: usize = 1024;
+
+/// The reason why a query was rejected by the `QueryLimits`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LimitExceeded {
+    /// Too many queries are running against the deployment at the same time
+    Concurrency,
+    /// Too many queries have been sent in the current window; clients
+    /// should retry after the given duration
+    Rate(Duration),
+}
+
+impl LimitExceeded {
+    /// The value for the `Retry-After` header, in seconds. It is always at
+    /// least 1 since the header does not allow fractional values
+    pub fn retry_after_secs(&self) -> u64 {
+        match self {
+            LimitExceeded::Concurrency => 1,
+            LimitExceeded::Rate(wait) => (wait.as_secs() + (wait.subsec_nanos() > 0) as u64).max(1),
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            LimitExceeded::Concurrency => {
+                "Too many concurrent queries for this subgraph, please retry later"
+            }
+            LimitExceeded::Rate(_) => "Query rate limit exceeded, please retry later",
+        }
+    }
+}
+
+struct Window {
+    start: Instant,
+    count: u32,
+}
+
+/// Counts queries per key in fixed windows of `RATE_WINDOW`
+#[derive(Default)]
+struct RateTable {
+    windows: HashMap<String, Window>,
+}
+
+impl RateTable {
+    /// Check whether another query for `key` fits into the current window
+    /// without counting it
+    fn check(&mut self, key: &str, max: u32, now: Instant) -> Result<(), Duration> {
+        if self.windows.len() > RATE_TABLE_PRUNE_SIZE {
+            self.windows
+                .retain(|_, window| now.duration_since(window.start) < RATE_WINDOW);
+        }
+
+        let window = self.windows.entry(key.to_string()).or_insert(Window {
+            start: now,
+            count: 0,
+        });
+        let elapsed = now.duration_since(window.start);
+        if elapsed >= RATE_WINDOW {
+            window.start = now;
+            window.count = 0;
+        }
+        if window.count >= max {
+            return Err(RATE_WINDOW - now.duration_since(window.start));
+        }
+        Ok(())
+    }
+
+    /// Count a query for `key`; must only be called after `check` passed
+    fn count(&mut self, key: &str) {
+        if let Some(window) = self.windows.get_mut(key) {
+            window.count += 1;
+        }
+    }
+}
+
+#[derive(Default)]
+struct LimitState {
+    running: HashMap<String, usize>,
+    deployment_rates: RateTable,
+    api_key_rates: RateTable,
+}
+
+/// Limits on the queries the HTTP server accepts, both per deployment and
+/// per API key. The limits protect the database from a single hot subgraph
+/// during traffic spikes.
+///
+/// Deployments are identified by their hash, so that queries sent to a
+/// subgraph name count against the deployment that the name currently
+/// resolves to. A query is only counted once it passed all limits, so that
+/// a query that is rejected does not use up any of them.
+pub struct QueryLimits {
+    max_concurrent_per_deployment: Option<usize>,
+    max_rate_per_deployment: Option<u32>,
+    max_rate_per_api_key: Option<u32>,
+    state: Mutex<LimitState>,
+}
+
+impl QueryLimits {
+    pub fn new(
+        max_concurrent_per_deployment: Option<usize>,
+        max_rate_per_deployment: Option<u32>,
+        max_rate_per_api_key: Option<u32>,
+    ) -> Self {
+        QueryLimits {
+            max_concurrent_per_deployment,
+            max_rate_per_deployment,
+            max_rate_per_api_key,
+            state: Mutex::new(LimitState::default()),
+        }
+    }
+
+    pub fn from_env(env: &EnvVars) -> Self {
+        Self::new(
+            env.graphql.max_concurrent_queries_per_deployment,
+            env.graphql.max_query_rate_per_deployment,
+            env.graphql.max_query_rate_per_api_key,
+        )
+    }
+
+    /// A `QueryLimits` that never rejects a query
+    pub fn unlimited() -> Self {
+        Self::new(None, None, None)
+    }
+
+    fn limits_deployments(&self) -> bool {
+        self.max_concurrent_per_deployment.is_some() || self.max_rate_per_deployment.is_some()
+    }
+
+    /// Like `admit`, but first use `runner` to find the deployment that
+    /// `target` refers to. If the target can not be resolved, for example
+    /// because the subgraph name does not exist, only the limit for the API
+    /// key applies, and running the query will report the problem
+    pub async fn admit_target<Q: GraphQlRunner>(
+        self: &Arc<Self>,
+        runner: &Q,
+        target: &QueryTarget,
+        api_key: Option<&str>,
+    ) -> Result<QueryPermit, LimitExceeded> {
+        let deployment = match target {
+            QueryTarget::Deployment(id, _) => Some(id.clone()),
+            QueryTarget::Name(_, _) if self.limits_deployments() => {
+                runner.deployment_for(target).await.ok()
+            }
+            QueryTarget::Name(_, _) => None,
+        };
+        self.admit(deployment.as_ref(), api_key)
+    }
+
+    /// Check whether a query against `deployment` sent with `api_key` can
+    /// run. If it can, the returned `QueryPermit` must be held for as long
+    /// as the query is running.
+    pub fn admit(
+        self: &Arc<Self>,
+        deployment: Option<&DeploymentHash>,
+        api_key: Option<&str>,
+    ) -> Result<QueryPermit, LimitExceeded> {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        let api_key = self.max_rate_per_api_key.zip(api_key);
+        let deployment_rate = self.max_rate_per_deployment.zip(deployment);
+        let concurrency = self.max_concurrent_per_deployment.zip(deployment);
+
+        if let Some((max, api_key)) = api_key {
+            state
+                .api_key_rates
+                .check(api_key, max, now)
+                .map_err(LimitExceeded::Rate)?;
+        }
+        if let Some((max, deployment)) = deployment_rate {
+            state
+                .deployment_rates
+                .check(deployment.as_str(), max, now)
+                .map_err(LimitExceeded::Rate)?;
+        }
+        if let Some((max, deployment)) = concurrency {
+            let running = state.running.get(deployment.as_str()).copied();
+            if running.unwrap_or(0) >= max {
+                return Err(LimitExceeded::Concurrency);
+            }
+        }
+
+        // The query passed all limits; only now count it against them
+        if let Some((_, api_key)) = api_key {
+            state.api_key_rates.count(api_key);
+        }
+        if let Some((_, deployment)) = deployment_rate {
+            state.deployment_rates.count(deployment.as_str());
+        }
+        let tracked = concurrency.map(|(_, deployment)| {
+            *state.running.entry(deployment.to_string()).or_insert(0) += 1;
+            deployment.to_string()
+        });
+
+        Ok(QueryPermit {
+            limits: self.clone(),
+            tracked,
+        })
+    }
+
+    fn release(&self, key: &str) {
+        let running = &mut self.state.lock().unwrap().running;
+        if let Some(count) = running.get_mut(key) {
+            *count -= 1;
+            if *count == 0 {
+                running.remove(key);
+            }
+        }
+    }
+}
+
+/// Proof that a query was admitted by `QueryLimits`. Dropping the permit
+/// marks the query as finished.
+pub struct QueryPermit {
+    limits: Arc<QueryLimits>,
+    /// The deployment whose running queries this query counts against
+    tracked: Option<String>,
+}
+
+impl Drop for QueryPermit {
+    fn drop(&mut self) {
+        if let Some(key) = &self.tracked {
+            self.limits.release(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use graph::prelude::DeploymentHash;
+    use std::sync::Arc;
+
+    use super::{LimitExceeded, QueryLimits};
+
+    fn hash(id: &str) -> DeploymentHash {
+        DeploymentHash::new(id).unwrap()
+    }
+
+    #[test]
+    fn concurrency_limit() {
+        let limits = Arc::new(QueryLimits::new(Some(2), None, None));
+        let (a, b) = (hash("a"), hash("b"));
+
+        let p1 = limits.admit(Some(&a), None).unwrap();
+        let _p2 = limits.admit(Some(&a), None).unwrap();
+        assert_eq!(
+            Some(LimitExceeded::Concurrency),
+            limits.admit(Some(&a), None).err()
+        );
+        // Other deployments are not affected
+        let _p3 = limits.admit(Some(&b), None).unwrap();
+
+        drop(p1);
+        assert!(limits.admit(Some(&a), None).is_ok());
+    }
+
+    #[test]
+    fn rate_limit() {
+        let limits = Arc::new(QueryLimits::new(None, Some(2), None));
+        let (a, b) = (hash("a"), hash("b"));
+
+        assert!(limits.admit(Some(&a), None).is_ok());
+        assert!(limits.admit(Some(&a), None).is_ok());
+        match limits.admit(Some(&a), None) {
+            Err(err @ LimitExceeded::Rate(_)) => assert_eq!(1, err.retry_after_secs()),
+            other => panic!("expected rate limit error, got {:?}", other.err()),
+        }
+        assert!(limits.admit(Some(&b), None).is_ok());
+        // Queries whose deployment is not known are not limited
+        assert!(limits.admit(None, None).is_ok());
+    }
+
+    #[test]
+    fn api_key_rate_limit() {
+        let limits = Arc::new(QueryLimits::new(None, None, Some(1)));
+        let (a, b) = (hash("a"), hash("b"));
+
+        assert!(limits.admit(Some(&a), Some("key1")).is_ok());
+        // The key limit applies across deployments
+        assert!(limits.admit(Some(&b), Some("key1")).is_err());
+        assert!(limits.admit(Some(&b), Some("key2")).is_ok());
+        // Requests without a key are not limited
+        assert!(limits.admit(Some(&a), None).is_ok());
+        assert!(limits.admit(Some(&a), None).is_ok());
+    }
+
+    #[test]
+    fn rejected_queries_are_not_counted() {
+        let limits = Arc::new(QueryLimits::new(Some(1), Some(2), Some(2)));
+        let (a, b) = (hash("a"), hash("b"));
+
+        let permit = limits.admit(Some(&a), Some("key")).unwrap();
+        // Rejected by the concurrency limit, which must not use up the
+        // rate limits for the key or the deployment
+        assert_eq!(
+            Some(LimitExceeded::Concurrency),
+            limits.admit(Some(&a), Some("key")).err()
+        );
+        drop(permit);
+        assert!(limits.admit(Some(&a), None).is_ok());
+        assert!(limits.admit(Some(&b), Some("key")).is_ok());
+
+        // Both rate limits are now used up
+        assert!(limits.admit(Some(&a), None).is_err());
+        assert!(limits.admit(Some(&b), Some("key")).is_err());
+    }
+}
@@ -0,0 +1,263 @@
+use graph::components::server::query::GraphQLServerError;
+use graph::prelude::serde_json::{self, json, Map, Value};
+use graph::prelude::*;
+use graph::url::form_urlencoded;
+use inflector::Inflector;
+
+/// A request to the REST facade, i.e., a `GET` request to
+/// `/subgraphs/.../rest/<entity>`. The request is answered by translating
+/// it into a GraphQL query for the collection field of the entity type
+#[derive(Debug, PartialEq)]
+pub struct RestQuery {
+    entity: String,
+    variables: Map<String, Value>,
+}
+
+impl RestQuery {
+    /// Parse the entity type from the path and the arguments of the
+    /// collection query from the query string of the request
+    pub fn parse(entity: &str, query_string: Option<&str>) -> Result<Self, GraphQLServerError> {
+        if !is_name(entity) {
+            return Err(GraphQLServerError::ClientError(format!(
+                "Invalid entity type `{}`",
+                entity
+            )));
+        }
+
+        let mut variables = Map::new();
+        let pairs = query_string
+            .map(|qs| form_urlencoded::parse(qs.as_bytes()).collect::<Vec<_>>())
+            .unwrap_or_default();
+        for (key, value) in pairs {
+            let value = match key.as_ref() {
+                "where" => {
+                    let filter: Value = serde_json::from_str(&value).map_err(|e| {
+                        GraphQLServerError::ClientError(format!("Invalid `where`: {}", e))
+                    })?;
+                    if !filter.is_object() {
+                        return Err(GraphQLServerError::ClientError(
+                            "Invalid `where`: the filter must be a JSON object".to_string(),
+                        ));
+                    }
+                    filter
+                }
+                "first" | "skip" => json!(parse_int(&key, &value)?),
+                "orderBy" | "orderDirection" => Value::String(value.to_string()),
+                "block" if value.starts_with("0x") => json!({ "hash": value }),
+                "block" => json!({ "number": parse_int(&key, &value)? }),
+                // Handled by `resolve_api_version`
+                "api-version" => continue,
+                _ => {
+                    return Err(GraphQLServerError::ClientError(format!(
+                        "Unknown query parameter `{}`",
+                        key
+                    )))
+                }
+            };
+            variables.insert(key.into_owned(), value);
+        }
+
+        Ok(RestQuery {
+            entity: entity.to_string(),
+            variables,
+        })
+    }
+
+    /// The name of the collection field on `Query` for the entity type
+    pub fn collection_field(&self) -> String {
+        self.entity.to_plural().to_camel_case()
+    }
+
+    /// A query that introspects the fields of the entity type so that the
+    /// selection set for the collection query can be generated from it
+    pub fn type_query(&self) -> Query {
+        let text = format!(
+            "{{ __type(name: \"{}\") {{ kind fields {{ name type {{ ...TypeRef }} }} }} }} \
+             fragment TypeRef on __Type {{ kind name ofType {{ kind name ofType {{ \
+             kind name ofType {{ kind name }} }} }} }}",
+            self.entity
+        );
+        let document = graphql_parser::parse_query(&text)
+            .expect("the type query is valid")
+            .into_static();
+        Query::new(document, None, false)
+    }
+
+    /// Generate the collection query for the entity type from the result
+    /// of running `type_query`. Returns `None` if the entity type does not
+    /// exist
+    pub fn collection_query(&self, type_data: &Value) -> Result<Option<Query>, GraphQLServerError> {
+        let ty = &type_data["__type"];
+        if ty["kind"] != "OBJECT" && ty["kind"] != "INTERFACE" {
+            return Ok(None);
+        }
+        let selection = ty["fields"]
+            .as_array()
+            .map(|fields| {
+                fields
+                    .iter()
+                    .filter_map(field_selection)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        let (params, args): (Vec<_>, Vec<_>) = self
+            .variables
+            .keys()
+            .map(|name| {
+                let ty = match name.as_str() {
+                    "where" => format!("{}_filter", self.entity),
+                    "orderBy" => format!("{}_orderBy", self.entity),
+                    "orderDirection" => "OrderDirection".to_string(),
+                    "block" => "Block_height".to_string(),
+                    _ => "Int".to_string(),
+                };
+                (format!("${}: {}", name, ty), format!("{}: ${}", name, name))
+            })
+            .unzip();
+        let text = if params.is_empty() {
+            format!(
+                "{{ {} {{ {} }} }}",
+                self.collection_field(),
+                selection.join(" ")
+            )
+        } else {
+            format!(
+                "query({}) {{ {}({}) {{ {} }} }}",
+                params.join(", "),
+                self.collection_field(),
+                args.join(", "),
+                selection.join(" ")
+            )
+        };
+
+        let document = graphql_parser::parse_query(&text)
+            .map_err(|e| GraphQLServerError::from(QueryError::ParseError(Arc::new(e.into()))))?
+            .into_static();
+        let variables = serde_json::from_value(Value::Object(self.variables.clone()))
+            .map_err(|e| GraphQLServerError::ClientError(e.to_string()))?;
+        Ok(Some(Query::new(document, Some(variables), false)))
+    }
+}
+
+fn parse_int(key: &str, value: &str) -> Result<i64, GraphQLServerError> {
+    value.parse::<i64>().map_err(|_| {
+        GraphQLServerError::ClientError(format!("Invalid `{}`: {} is not an integer", key, value))
+    })
+}
+
+fn is_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .map(|c| c.is_ascii_alphabetic() || c == '_')
+        .unwrap_or(false)
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// The selection for a field of the entity type: scalars are selected as
+/// is, references to other entities only with their `id`, and lists of
+/// references are left out since they can be arbitrarily large
+fn field_selection(field: &Value) -> Option<String> {
+    let name = field["name"].as_str()?;
+    if name.starts_with("__") {
+        return None;
+    }
+
+    let mut ty = &field["type"];
+    let mut list = false;
+    loop {
+        match ty["kind"].as_str()? {
+            "NON_NULL" => ty = &ty["ofType"],
+            "LIST" => {
+                list = true;
+                ty = &ty["ofType"];
+            }
+            "SCALAR" | "ENUM" => return Some(name.to_string()),
+            "OBJECT" | "INTERFACE" if !list => return Some(format!("{} {{ id }}", name)),
+            _ => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use graph::prelude::serde_json::{self, json};
+
+    use super::{field_selection, RestQuery};
+
+    fn field(name: &str, ty: serde_json::Value) -> serde_json::Value {
+        json!({ "name": name, "type": ty })
+    }
+
+    #[test]
+    fn parse_query_string() {
+        let query = RestQuery::parse(
+            "Token",
+            Some("where=%7B%22name_starts_with%22%3A%22a%22%7D&first=5&orderBy=name&api-version=0.0.6"),
+        )
+        .unwrap();
+        assert_eq!("tokens", query.collection_field());
+        assert_eq!(
+            json!({ "where": { "name_starts_with": "a" }, "first": 5, "orderBy": "name" }),
+            serde_json::Value::Object(query.variables)
+        );
+
+        let query = RestQuery::parse("Token", Some("block=17")).unwrap();
+        assert_eq!(
+            json!({ "block": { "number": 17 } }),
+            serde_json::Value::Object(query.variables)
+        );
+
+        assert!(RestQuery::parse("Token", Some("where=[1]")).is_err());
+        assert!(RestQuery::parse("Token", Some("first=many")).is_err());
+        assert!(RestQuery::parse("Token", Some("unknown=1")).is_err());
+        assert!(RestQuery::parse("Token { id }", None).is_err());
+    }
+
+    #[test]
+    fn field_selections() {
+        let id = json!({ "kind": "NON_NULL", "name": null, "ofType": { "kind": "SCALAR", "name": "ID" }});
+        let owner = json!({ "kind": "OBJECT", "name": "Account" });
+        let holders = json!({ "kind": "NON_NULL", "name": null, "ofType": {
+            "kind": "LIST", "name": null, "ofType": { "kind": "OBJECT", "name": "Account" }}});
+        let tags = json!({ "kind": "LIST", "name": null, "ofType": { "kind": "SCALAR", "name": "String" }});
+
+        assert_eq!(Some("id".to_string()), field_selection(&field("id", id)));
+        assert_eq!(
+            Some("owner { id }".to_string()),
+            field_selection(&field("owner", owner))
+        );
+        assert_eq!(None, field_selection(&field("holders", holders)));
+        assert_eq!(
+            Some("tags".to_string()),
+            field_selection(&field("tags", tags))
+        );
+    }
+
+    #[test]
+    fn generate_collection_query() {
+        let type_data = json!({ "__type": { "kind": "OBJECT", "fields": [
+            { "name": "id", "type": { "kind": "NON_NULL", "name": null, "ofType": { "kind": "SCALAR", "name": "ID" }}},
+            { "name": "owner", "type": { "kind": "OBJECT", "name": "Account" }},
+        ]}});
+
+        let query = RestQuery::parse("Token", Some("first=5&where=%7B%7D"))
+            .unwrap()
+            .collection_query(&type_data)
+            .unwrap()
+            .unwrap();
+        let expected = graphql_parser::parse_query::<String>(
+            "query($first: Int, $where: Token_filter) { tokens(first: $first, where: $where) { id owner { id } } }",
+        )
+        .unwrap();
+        assert_eq!(expected.to_string(), query.document.to_string());
+
+        // Unknown types
+        let query = RestQuery::parse("Token", None)
+            .unwrap()
+            .collection_query(&json!({ "__type": null }))
+            .unwrap();
+        assert!(query.is_none());
+    }
+}
@@ -3,6 +3,10 @@ use std::net::{Ipv4Addr, SocketAddrV4};
 use hyper::service::make_service_fn;
 use hyper::Server;
 
+use graph::components::server::tls::{self, ServerTls};
+
+use crate::audit::QueryAuditLog;
+use crate::limits::QueryLimits;
 use crate::service::GraphQLService;
 use graph::prelude::{GraphQLServer as GraphQLServerTrait, *};
 use thiserror::Error;
@@ -11,7 +15,7 @@ use thiserror::Error;
 #[derive(Debug, Error)]
 pub enum GraphQLServeError {
     #[error("Bind error: {0}")]
-    BindError(#[from] hyper::Error),
+    BindError(#[from] std::io::Error),
 }
 
 /// A GraphQL server based on Hyper.
@@ -19,11 +23,19 @@ pub struct GraphQLServer<Q> {
     logger: Logger,
     graphql_runner: Arc<Q>,
     node_id: NodeId,
+    limits: Arc<QueryLimits>,
+    audit: Arc<QueryAuditLog>,
+    tls: Option<Arc<ServerTls>>,
 }
 
 impl<Q> GraphQLServer<Q> {
-    /// Creates a new GraphQL server.
-    pub fn new(logger_factory: &LoggerFactory, graphql_runner: Arc<Q>, node_id: NodeId) -> Self {
+    /// Creates a new GraphQL server. Fails if the query audit log can not be
+    /// set up.
+    pub fn new(
+        logger_factory: &LoggerFactory,
+        graphql_runner: Arc<Q>,
+        node_id: NodeId,
+    ) -> Result<Self, Error> {
         let logger = logger_factory.component_logger(
             "GraphQLServer",
             Some(ComponentLoggerConfig {
@@ -32,11 +44,21 @@ impl<Q> GraphQLServer<Q> {
                 }),
             }),
         );
-        GraphQLServer {
+        let audit = Arc::new(QueryAuditLog::from_env(&logger, &ENV_VARS)?);
+        Ok(GraphQLServer {
             logger,
             graphql_runner,
             node_id,
-        }
+            limits: Arc::new(QueryLimits::from_env(&ENV_VARS)),
+            audit,
+            tls: None,
+        })
+    }
+
+    /// Serve queries over TLS with `tls`
+    pub fn with_tls(mut self, tls: Option<Arc<ServerTls>>) -> Self {
+        self.tls = tls;
+        self
     }
 }
 
@@ -55,7 +77,9 @@ where
 
         info!(
             logger,
-            "Starting GraphQL HTTP server at: http://localhost:{}", port
+            "Starting GraphQL HTTP server at: {}://localhost:{}",
+            tls::scheme(&self.tls, "http"),
+            port
         );
 
         let addr = SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), port);
@@ -65,17 +89,23 @@ where
         let logger_for_service = self.logger.clone();
         let graphql_runner = self.graphql_runner.clone();
         let node_id = self.node_id.clone();
+        let limits = self.limits.clone();
+        let audit = self.audit.clone();
         let new_service = make_service_fn(move |_| {
             futures03::future::ok::<_, Error>(GraphQLService::new(
                 logger_for_service.clone(),
                 graphql_runner.clone(),
                 ws_port,
                 node_id.clone(),
+                limits.clone(),
+                audit.clone(),
             ))
         });
 
         // Create a task to run the server and handle HTTP requests
-        let task = Server::try_bind(&addr.into())?
+        let incoming = tls::incoming(self.logger.clone(), addr.into(), self.tls.clone())?;
+        let task = Server::builder(incoming)
+            .http1_keepalive(ENV_VARS.graphql.http_keep_alive)
             .serve(new_service)
             .map_err(move |e| error!(logger, "Server error"; "error" => format!("{}", e)));
 
@@ -4,18 +4,27 @@ use std::task::Context;
 use std::task::Poll;
 use std::time::Instant;
 
+use graph::env::{ContentEncoding, CorsOrigins};
 use graph::prelude::*;
 use graph::semver::VersionReq;
 use graph::{components::server::query::GraphQLServerError, data::query::QueryTarget};
 use http::header;
 use http::header::{
-    ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN,
-    CONTENT_TYPE, LOCATION,
+    ACCEPT_ENCODING, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+    ACCESS_CONTROL_ALLOW_ORIGIN, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, LOCATION, ORIGIN,
+    RETRY_AFTER, VARY,
 };
+use hyper::body::{Bytes, HttpBody};
 use hyper::service::Service;
 use hyper::{Body, Method, Request, Response, StatusCode};
 
+use crate::audit::{AuditQuery, QueryAuditLog};
+use crate::limits::{LimitExceeded, QueryLimits};
 use crate::request::parse_graphql_request;
+use crate::rest::RestQuery;
+
+/// The query used to preload the schema of a subgraph into GraphiQL
+const INTROSPECTION_QUERY: &str = include_str!("../assets/introspection.graphql");
 
 pub type GraphQLServiceResult = Result<Response<Body>, GraphQLServerError>;
 /// An asynchronous response to a GraphQL request.
@@ -29,6 +38,8 @@ pub struct GraphQLService<Q> {
     graphql_runner: Arc<Q>,
     ws_port: u16,
     node_id: NodeId,
+    limits: Arc<QueryLimits>,
+    audit: Arc<QueryAuditLog>,
 }
 
 impl<Q> Clone for GraphQLService<Q> {
@@ -38,6 +49,8 @@ impl<Q> Clone for GraphQLService<Q> {
             graphql_runner: self.graphql_runner.clone(),
             ws_port: self.ws_port,
             node_id: self.node_id.clone(),
+            limits: self.limits.clone(),
+            audit: self.audit.clone(),
         }
     }
 }
@@ -47,18 +60,33 @@ where
     Q: GraphQlRunner,
 {
     /// Creates a new GraphQL service.
-    pub fn new(logger: Logger, graphql_runner: Arc<Q>, ws_port: u16, node_id: NodeId) -> Self {
+    pub fn new(
+        logger: Logger,
+        graphql_runner: Arc<Q>,
+        ws_port: u16,
+        node_id: NodeId,
+        limits: Arc<QueryLimits>,
+        audit: Arc<QueryAuditLog>,
+    ) -> Self {
         GraphQLService {
             logger,
             graphql_runner,
             ws_port,
             node_id,
+            limits,
+            audit,
         }
     }
 
-    fn graphiql_html(&self) -> String {
+    fn graphiql_html(&self, introspection: Option<serde_json::Value>) -> String {
+        // Escape `</` so that the JSON can not terminate the script tag it
+        // is embedded in
+        let introspection = introspection
+            .map(|value| value.to_string().replace("</", "<\\/"))
+            .unwrap_or_else(|| "null".to_string());
         include_str!("../assets/index.html")
             .replace("__WS_PORT__", format!("{}", self.ws_port).as_str())
+            .replace("__INTROSPECTION__", &introspection)
     }
 
     async fn index(self) -> GraphQLServiceResult {
@@ -73,21 +101,82 @@ where
             .unwrap())
     }
 
-    /// Serves a dynamically created file.
-    fn serve_dynamic_file(&self, contents: String) -> GraphQLServiceResponse {
-        async {
-            Ok(Response::builder()
-                .status(200)
-                .header(ACCESS_CONTROL_ALLOW_ORIGIN, "*")
-                .header(CONTENT_TYPE, "text/html")
-                .body(Body::from(contents))
-                .unwrap())
+    async fn handle_graphiql(
+        self,
+        target: Option<QueryTarget>,
+        api_key: Option<String>,
+    ) -> GraphQLServiceResult {
+        if ENV_VARS.graphql.disable_graphiql {
+            return self.handle_not_found().await;
         }
-        .boxed()
+
+        let introspection = match target {
+            Some(target) => self.introspect(target, api_key.as_deref()).await,
+            None => None,
+        };
+
+        Ok(Response::builder()
+            .status(200)
+            .header(ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+            .header(CONTENT_TYPE, "text/html")
+            .body(Body::from(self.graphiql_html(introspection)))
+            .unwrap())
+    }
+
+    /// Run an introspection query against `target` so that GraphiQL can be
+    /// served with the schema preloaded. The query counts against the
+    /// `QueryLimits` like any other query. Returns `None` if the query is
+    /// not admitted or fails, in which case GraphiQL introspects the schema
+    /// itself
+    async fn introspect(
+        &self,
+        target: QueryTarget,
+        api_key: Option<&str>,
+    ) -> Option<serde_json::Value> {
+        // Hold on to the permit until the query has finished running
+        let _permit = self
+            .limits
+            .admit_target(&*self.graphql_runner, &target, api_key)
+            .await
+            .ok()?;
+        let document = graphql_parser::parse_query(INTROSPECTION_QUERY)
+            .expect("the introspection query is valid")
+            .into_static();
+        let results = self
+            .graphql_runner
+            .cheap_clone()
+            .run_query(Query::new(document, None, false), target)
+            .await;
+        if results.has_errors() {
+            return None;
+        }
+        serde_json::to_value(&results)
+            .ok()
+            .and_then(|mut value| value.get_mut("data").map(serde_json::Value::take))
     }
 
-    fn handle_graphiql(&self) -> GraphQLServiceResponse {
-        self.serve_dynamic_file(self.graphiql_html())
+    /// The subgraph whose schema the GraphiQL page at `path` is served with
+    fn graphiql_target(&self, request: &Request<Body>, path: &[&str]) -> Option<QueryTarget> {
+        let version = self.resolve_api_version(request).ok()?;
+        match path {
+            ["subgraphs", "id", id, "graphql"] => DeploymentHash::new(*id)
+                .ok()
+                .map(|id| QueryTarget::Deployment(id, version)),
+            ["subgraphs", "name", name, "graphql"] => SubgraphName::new(*name)
+                .ok()
+                .map(|name| QueryTarget::Name(name, version)),
+            ["subgraphs", "name", part1, part2, "graphql"] => {
+                SubgraphName::new(format!("{}/{}", part1, part2))
+                    .ok()
+                    .map(|name| QueryTarget::Name(name, version))
+            }
+            ["subgraphs", "network", part1, part2, "graphql"] => {
+                SubgraphName::new(format!("network/{}/{}", part1, part2))
+                    .ok()
+                    .map(|name| QueryTarget::Name(name, version))
+            }
+            _ => None,
+        }
     }
 
     fn resolve_api_version(
@@ -172,14 +261,35 @@ where
                     })
                     .unwrap_or(false)
         };
-        let body = hyper::body::to_bytes(request.into_body())
-            .map_err(|_| GraphQLServerError::InternalError("Failed to read request body".into()))
-            .await?;
+        // Hold on to the permit until the query has finished running
+        let _permit = match self
+            .limits
+            .admit_target(&*self.graphql_runner, &target, api_key(&request))
+            .await
+        {
+            Ok(permit) => permit,
+            Err(e) => return Ok(self.handle_too_many_requests(e)),
+        };
+        let api_key = api_key(&request).map(str::to_string);
+        let max_body_size = ENV_VARS.graphql.http_max_body_size;
+        let body = match read_body(request.into_body(), max_body_size).await? {
+            Some(body) => body,
+            None => return Ok(self.handle_payload_too_large()),
+        };
         let query = parse_graphql_request(&body, trace);
         let query_parsing_time = start.elapsed();
 
+        let audit_query = match &query {
+            Ok(query) if self.audit.is_enabled() => Some(AuditQuery::new(query)),
+            _ => None,
+        };
         let result = match query {
-            Ok(query) => service.graphql_runner.run_query(query, target).await,
+            Ok(query) => {
+                service
+                    .graphql_runner
+                    .run_query(query, target.clone())
+                    .await
+            }
             Err(GraphQLServerError::QueryError(e)) => QueryResult::from(e).into(),
             Err(e) => return Err(e),
         };
@@ -191,16 +301,109 @@ where
             .metrics()
             .observe_query_execution(start.elapsed(), &result);
 
-        Ok(result.as_http_response())
+        let response: Response<String> = result.as_http_response();
+        self.audit.record(
+            &target,
+            audit_query.as_ref(),
+            &result,
+            response.body().len(),
+            start.elapsed(),
+            api_key.as_deref(),
+        );
+        Ok(response.map(Body::from))
+    }
+
+    /// The target of a request to the REST facade at `path`
+    fn rest_target(&self, request: &Request<Body>, path: &[&str]) -> Option<QueryTarget> {
+        let version = self.resolve_api_version(request).ok()?;
+        match path {
+            ["subgraphs", "id", id, "rest", _] => DeploymentHash::new(*id)
+                .ok()
+                .map(|id| QueryTarget::Deployment(id, version)),
+            ["subgraphs", "name", name, "rest", _] => SubgraphName::new(*name)
+                .ok()
+                .map(|name| QueryTarget::Name(name, version)),
+            ["subgraphs", "name", part1, part2, "rest", _] => {
+                SubgraphName::new(format!("{}/{}", part1, part2))
+                    .ok()
+                    .map(|name| QueryTarget::Name(name, version))
+            }
+            _ => None,
+        }
+    }
+
+    /// Answers a request to the REST facade by running the collection
+    /// query for `entity` and returning the entities as a JSON array
+    async fn handle_rest_query(
+        self,
+        target: Option<QueryTarget>,
+        entity: String,
+        request: Request<Body>,
+    ) -> GraphQLServiceResult {
+        let target = match (ENV_VARS.graphql.enable_rest, target) {
+            (true, Some(target)) => target,
+            _ => return self.handle_not_found().await,
+        };
+        let _permit = match self
+            .limits
+            .admit_target(&*self.graphql_runner, &target, api_key(&request))
+            .await
+        {
+            Ok(permit) => permit,
+            Err(e) => return Ok(self.handle_too_many_requests(e)),
+        };
+
+        let start = Instant::now();
+        let rest = RestQuery::parse(&entity, request.uri().query())?;
+        let results = self
+            .graphql_runner
+            .cheap_clone()
+            .run_query(rest.type_query(), target.clone())
+            .await;
+        if results.has_errors() {
+            return Ok(results.as_http_response());
+        }
+        let type_data = results_data(&results)?;
+        let query = match rest.collection_query(&type_data)? {
+            Some(query) => query,
+            None => return self.handle_not_found().await,
+        };
+
+        let results = self
+            .graphql_runner
+            .cheap_clone()
+            .run_query(query, target)
+            .await;
+        self.graphql_runner
+            .metrics()
+            .observe_query_execution(start.elapsed(), &results);
+        if results.has_errors() {
+            return Ok(results.as_http_response());
+        }
+        let entities = results_data(&results)?
+            .get_mut(rest.collection_field())
+            .map(serde_json::Value::take)
+            .unwrap_or_default();
+
+        Ok(Response::builder()
+            .status(200)
+            .header(ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(entities.to_string()))
+            .unwrap())
     }
 
     // Handles OPTIONS requests
     fn handle_graphql_options(&self, _request: Request<Body>) -> GraphQLServiceResponse {
-        async {
+        let allow_headers = format!(
+            "Content-Type, User-Agent, {}",
+            ENV_VARS.graphql.api_key_header
+        );
+        async move {
             Ok(Response::builder()
                 .status(200)
                 .header(ACCESS_CONTROL_ALLOW_ORIGIN, "*")
-                .header(ACCESS_CONTROL_ALLOW_HEADERS, "Content-Type, User-Agent")
+                .header(ACCESS_CONTROL_ALLOW_HEADERS, allow_headers)
                 .header(ACCESS_CONTROL_ALLOW_METHODS, "GET, OPTIONS, POST")
                 .header(CONTENT_TYPE, "text/html")
                 .body(Body::from(""))
@@ -226,6 +429,30 @@ where
             })
     }
 
+    /// Handles queries rejected by the query limits with a 429
+    fn handle_too_many_requests(&self, e: LimitExceeded) -> Response<Body> {
+        Response::builder()
+            .status(StatusCode::TOO_MANY_REQUESTS)
+            .header(CONTENT_TYPE, "text/plain")
+            .header(ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+            .header(RETRY_AFTER, e.retry_after_secs().to_string())
+            .body(Body::from(e.message()))
+            .unwrap()
+    }
+
+    /// Handles requests whose body exceeds the configured maximum size
+    fn handle_payload_too_large(&self) -> Response<Body> {
+        Response::builder()
+            .status(StatusCode::PAYLOAD_TOO_LARGE)
+            .header(CONTENT_TYPE, "text/plain")
+            .header(ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+            .body(Body::from(format!(
+                "Request body exceeds the maximum size of {} bytes",
+                ENV_VARS.graphql.http_max_body_size
+            )))
+            .unwrap()
+    }
+
     /// Handles 404s.
     fn handle_not_found(&self) -> GraphQLServiceResponse {
         async {
@@ -254,11 +481,23 @@ where
 
         match (method, path_segments.as_slice()) {
             (Method::GET, [""]) => self.index().boxed(),
-            (Method::GET, &["subgraphs", "id", _, "graphql"])
-            | (Method::GET, &["subgraphs", "name", _, "graphql"])
-            | (Method::GET, &["subgraphs", "name", _, _, "graphql"])
-            | (Method::GET, &["subgraphs", "network", _, _, "graphql"])
-            | (Method::GET, &["subgraphs", "graphql"]) => self.handle_graphiql(),
+            (Method::GET, path @ ["subgraphs", "id", _, "graphql"])
+            | (Method::GET, path @ ["subgraphs", "name", _, "graphql"])
+            | (Method::GET, path @ ["subgraphs", "name", _, _, "graphql"])
+            | (Method::GET, path @ ["subgraphs", "network", _, _, "graphql"])
+            | (Method::GET, path @ ["subgraphs", "graphql"]) => {
+                let target = self.graphiql_target(&req, path);
+                let api_key = api_key(&req).map(str::to_string);
+                self.handle_graphiql(target, api_key).boxed()
+            }
+
+            (Method::GET, path @ ["subgraphs", "id", _, "rest", entity])
+            | (Method::GET, path @ ["subgraphs", "name", _, "rest", entity])
+            | (Method::GET, path @ ["subgraphs", "name", _, _, "rest", entity]) => {
+                let target = self.rest_target(&req, path);
+                let entity = entity.to_string();
+                self.handle_rest_query(target, entity, req).boxed()
+            }
 
             (Method::GET, path @ ["subgraphs", "id", _])
             | (Method::GET, path @ ["subgraphs", "name", _])
@@ -311,12 +550,14 @@ where
     fn call(&mut self, req: Request<Body>) -> Self::Future {
         let logger = self.logger.clone();
         let service = self.clone();
+        let origin = req.headers().get(ORIGIN).cloned();
+        let encoding = negotiate_encoding(req.headers(), &ENV_VARS.graphql.http_compression.0);
 
         // Returning Err here will prevent the client from receiving any response.
         // Instead, we generate a Response with an error code and return Ok
         Box::pin(async move {
             let result = service.handle_call(req).await;
-            match result {
+            let response: GraphQLServiceResult = match result {
                 Ok(response) => Ok(response),
                 Err(err @ GraphQLServerError::ClientError(_)) => Ok(Response::builder()
                     .status(400)
@@ -344,9 +585,174 @@ where
                         .body(Body::from(format!("Internal server error: {}", err)))
                         .unwrap())
                 }
+            };
+            let mut response = response?;
+            set_cors_headers(&mut response, origin.as_ref());
+            if let Some(encoding) = encoding {
+                response = compress(response, encoding).await?;
+            }
+            Ok(response)
+        })
+    }
+}
+
+/// The API key a request was sent with
+fn api_key(request: &Request<Body>) -> Option<&str> {
+    request
+        .headers()
+        .get(ENV_VARS.graphql.api_key_header.as_str())
+        .and_then(|v| v.to_str().ok())
+}
+
+/// The `data` of successful query results as JSON
+fn results_data(results: &QueryResults) -> Result<serde_json::Value, GraphQLServerError> {
+    serde_json::to_value(results)
+        .map_err(|e| GraphQLServerError::InternalError(e.to_string()))
+        .map(|mut value| value["data"].take())
+}
+
+/// Read a request body, returning `None` if it is larger than `max_size`
+/// bytes. The body is read incrementally so that oversized requests are
+/// rejected without buffering them completely.
+async fn read_body(mut body: Body, max_size: usize) -> Result<Option<Bytes>, GraphQLServerError> {
+    let mut buf = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk
+            .map_err(|_| GraphQLServerError::InternalError("Failed to read request body".into()))?;
+        if buf.len() + chunk.len() > max_size {
+            return Ok(None);
+        }
+        buf.extend_from_slice(&chunk);
+    }
+    Ok(Some(Bytes::from(buf)))
+}
+
+/// Replace the permissive `Access-Control-Allow-Origin` header that handlers
+/// set with one that reflects the configured CORS origins
+fn set_cors_headers(response: &mut Response<Body>, origin: Option<&header::HeaderValue>) {
+    let origins = &ENV_VARS.graphql.http_cors_origins;
+    if !response.headers().contains_key(ACCESS_CONTROL_ALLOW_ORIGIN) || *origins == CorsOrigins::Any
+    {
+        return;
+    }
+
+    let headers = response.headers_mut();
+    headers.remove(ACCESS_CONTROL_ALLOW_ORIGIN);
+    headers.append(VARY, header::HeaderValue::from_static("Origin"));
+    if let Some(origin) = origin {
+        if origin.to_str().map(|o| origins.allows(o)).unwrap_or(false) {
+            headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone());
+        }
+    }
+}
+
+/// Responses smaller than this are not worth compressing
+const MIN_COMPRESSION_SIZE: usize = 1024;
+
+/// The quality and window size for Brotli; a medium quality compresses
+/// almost as well as the highest one at a fraction of the cost
+const BROTLI_QUALITY: u32 = 5;
+const BROTLI_WINDOW: u32 = 22;
+
+/// The quality values that the `Accept-Encoding` headers in `headers` give
+/// to each encoding, `*` included. Entries whose quality can not be parsed
+/// are ignored
+fn accepted_encodings(headers: &header::HeaderMap) -> Vec<(String, f32)> {
+    headers
+        .get_all(ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|entry| {
+            let mut parts = entry.split(';');
+            let name = parts.next()?.trim().to_ascii_lowercase();
+            if name.is_empty() {
+                return None;
+            }
+            let mut q = 1.0;
+            for param in parts {
+                match param.split_once('=') {
+                    Some((key, value)) if key.trim().eq_ignore_ascii_case("q") => {
+                        q = value.trim().parse::<f32>().ok()?;
+                    }
+                    _ => {}
+                }
             }
+            (0.0..=1.0).contains(&q).then_some((name, q))
         })
+        .collect()
+}
+
+/// The encoding from `available` that the client that sent `headers`
+/// prefers. An encoding that is not named explicitly gets the quality of
+/// `*`, and a quality of 0 means the client does not accept the encoding.
+/// Ties are broken by the order of `available`
+fn negotiate_encoding(
+    headers: &header::HeaderMap,
+    available: &[ContentEncoding],
+) -> Option<ContentEncoding> {
+    if available.is_empty() {
+        return None;
+    }
+    let accepted = accepted_encodings(headers);
+    let quality = |name: &str| {
+        accepted
+            .iter()
+            .find(|(accepted, _)| accepted == name)
+            .map(|(_, q)| *q)
+    };
+
+    let mut best: Option<(ContentEncoding, f32)> = None;
+    for encoding in available {
+        let q = quality(encoding.as_str())
+            .or_else(|| quality("*"))
+            .unwrap_or(0.0);
+        if q > 0.0 && best.map(|(_, best)| q > best).unwrap_or(true) {
+            best = Some((*encoding, q));
+        }
+    }
+    best.map(|(encoding, _)| encoding)
+}
+
+async fn compress(response: Response<Body>, encoding: ContentEncoding) -> GraphQLServiceResult {
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    if response.headers().contains_key(CONTENT_ENCODING) {
+        return Ok(response);
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let body = hyper::body::to_bytes(body)
+        .await
+        .map_err(|_| GraphQLServerError::InternalError("Failed to read response body".into()))?;
+    if body.len() < MIN_COMPRESSION_SIZE {
+        return Ok(Response::from_parts(parts, Body::from(body)));
+    }
+
+    let buf = Vec::with_capacity(body.len() / 4);
+    let compressed = match encoding {
+        ContentEncoding::Gzip => {
+            let mut encoder = GzEncoder::new(buf, Compression::default());
+            encoder.write_all(&body).and_then(|()| encoder.finish())
+        }
+        ContentEncoding::Brotli => {
+            let mut encoder =
+                brotli::CompressorWriter::new(buf, 4096, BROTLI_QUALITY, BROTLI_WINDOW);
+            encoder.write_all(&body).map(|()| encoder.into_inner())
+        }
     }
+    .map_err(|e| GraphQLServerError::InternalError(format!("Failed to compress: {}", e)))?;
+
+    parts.headers.remove(CONTENT_LENGTH);
+    parts.headers.insert(
+        CONTENT_ENCODING,
+        header::HeaderValue::from_static(encoding.as_str()),
+    );
+    parts
+        .headers
+        .append(VARY, header::HeaderValue::from_static("Accept-Encoding"));
+    Ok(Response::from_parts(parts, Body::from(compressed)))
 }
 
 #[cfg(test)]
@@ -362,9 +768,11 @@ mod tests {
     };
     use graph::prelude::*;
 
+    use crate::audit::QueryAuditLog;
+    use crate::limits::QueryLimits;
     use crate::test_utils;
 
-    use super::GraphQLService;
+    use super::{negotiate_encoding, read_body, ContentEncoding, GraphQLService};
 
     /// A simple stupid query runner for testing.
     pub struct TestGraphQlRunner;
@@ -414,6 +822,17 @@ mod tests {
             unreachable!();
         }
 
+        /// Every subgraph name refers to the deployment `USERS`
+        async fn deployment_for(
+            &self,
+            target: &QueryTarget,
+        ) -> Result<DeploymentHash, QueryExecutionError> {
+            match target {
+                QueryTarget::Deployment(id, _) => Ok(id.clone()),
+                QueryTarget::Name(_, _) => Ok(USERS.clone()),
+            }
+        }
+
         fn load_manager(&self) -> Arc<LoadManager> {
             unimplemented!()
         }
@@ -430,7 +849,9 @@ mod tests {
         let graphql_runner = Arc::new(TestGraphQlRunner);
 
         let node_id = NodeId::new("test").unwrap();
-        let mut service = GraphQLService::new(logger, graphql_runner, 8001, node_id);
+        let limits = Arc::new(QueryLimits::unlimited());
+        let audit = Arc::new(QueryAuditLog::disabled());
+        let mut service = GraphQLService::new(logger, graphql_runner, 8001, node_id, limits, audit);
 
         let request = Request::builder()
             .method(Method::POST)
@@ -460,7 +881,9 @@ mod tests {
         let graphql_runner = Arc::new(TestGraphQlRunner);
 
         let node_id = NodeId::new("test").unwrap();
-        let mut service = GraphQLService::new(logger, graphql_runner, 8001, node_id);
+        let limits = Arc::new(QueryLimits::unlimited());
+        let audit = Arc::new(QueryAuditLog::disabled());
+        let mut service = GraphQLService::new(logger, graphql_runner, 8001, node_id, limits, audit);
 
         let request = Request::builder()
             .method(Method::POST)
@@ -486,4 +909,174 @@ mod tests {
             .expect("Query result field \"name\" is not a string");
         assert_eq!(name, "Jordi".to_string());
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn queries_over_the_rate_limit_are_rejected() {
+        let logger = Logger::root(slog::Discard, o!());
+        let graphql_runner = Arc::new(TestGraphQlRunner);
+
+        let node_id = NodeId::new("test").unwrap();
+        let limits = Arc::new(QueryLimits::new(None, Some(1), None));
+        let audit = Arc::new(QueryAuditLog::disabled());
+        let mut service = GraphQLService::new(logger, graphql_runner, 8001, node_id, limits, audit);
+
+        let request = || {
+            Request::builder()
+                .method(Method::POST)
+                .uri(format!("http://localhost:8000/subgraphs/id/{}", *USERS))
+                .body(Body::from("{\"query\": \"{ name }\"}"))
+                .unwrap()
+        };
+
+        let response = service.call(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = service.call(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(
+            response
+                .headers()
+                .get(http::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok()),
+            Some("1")
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn names_and_hashes_share_the_rate_limit() {
+        let logger = Logger::root(slog::Discard, o!());
+        let graphql_runner = Arc::new(TestGraphQlRunner);
+
+        let node_id = NodeId::new("test").unwrap();
+        let limits = Arc::new(QueryLimits::new(None, Some(1), None));
+        let audit = Arc::new(QueryAuditLog::disabled());
+        let mut service = GraphQLService::new(logger, graphql_runner, 8001, node_id, limits, audit);
+
+        let request = |path: String| {
+            Request::builder()
+                .method(Method::POST)
+                .uri(format!("http://localhost:8000/subgraphs/{}", path))
+                .body(Body::from("{\"query\": \"{ name }\"}"))
+                .unwrap()
+        };
+
+        let response = service
+            .call(request("name/users".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // The name resolves to `USERS`, whose limit is now used up
+        let response = service
+            .call(request(format!("id/{}", *USERS)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn graphiql_is_served_with_preloaded_schema() {
+        let logger = Logger::root(slog::Discard, o!());
+        let graphql_runner = Arc::new(TestGraphQlRunner);
+
+        let node_id = NodeId::new("test").unwrap();
+        let limits = Arc::new(QueryLimits::unlimited());
+        let audit = Arc::new(QueryAuditLog::disabled());
+        let mut service = GraphQLService::new(logger, graphql_runner, 8001, node_id, limits, audit);
+
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(format!(
+                "http://localhost:8000/subgraphs/id/{}/graphql",
+                *USERS
+            ))
+            .body(Body::empty())
+            .unwrap();
+
+        let response = service.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        // The test runner answers every query with `{ name: "Jordi" }`
+        assert!(html.contains(r#"var introspection = {"name":"Jordi"};"#));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn graphiql_introspection_counts_against_the_limits() {
+        let logger = Logger::root(slog::Discard, o!());
+        let graphql_runner = Arc::new(TestGraphQlRunner);
+
+        let node_id = NodeId::new("test").unwrap();
+        let limits = Arc::new(QueryLimits::new(None, Some(1), None));
+        let audit = Arc::new(QueryAuditLog::disabled());
+        let mut service = GraphQLService::new(logger, graphql_runner, 8001, node_id, limits, audit);
+
+        let request = || {
+            Request::builder()
+                .method(Method::GET)
+                .uri(format!(
+                    "http://localhost:8000/subgraphs/id/{}/graphql",
+                    *USERS
+                ))
+                .body(Body::empty())
+                .unwrap()
+        };
+        let html = |response: hyper::Response<Body>| async move {
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            String::from_utf8(body.to_vec()).unwrap()
+        };
+
+        let response = service.call(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(html(response)
+            .await
+            .contains(r#"var introspection = {"name":"Jordi"};"#));
+
+        // The page is still served once the limit is used up, but without
+        // the schema
+        let response = service.call(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(html(response).await.contains("var introspection = null;"));
+    }
+
+    #[test]
+    fn accept_encoding() {
+        use graph::env::ContentEncoding::{Brotli, Gzip};
+
+        fn negotiate(value: &str, available: &[ContentEncoding]) -> Option<ContentEncoding> {
+            let mut headers = http::HeaderMap::new();
+            headers.insert(http::header::ACCEPT_ENCODING, value.parse().unwrap());
+            negotiate_encoding(&headers, available)
+        }
+
+        assert_eq!(Some(Gzip), negotiate("gzip", &[Gzip]));
+        assert_eq!(Some(Gzip), negotiate("deflate, gzip;q=1.0, br", &[Gzip]));
+        assert_eq!(Some(Gzip), negotiate("*", &[Gzip]));
+        assert_eq!(None, negotiate("br", &[Gzip]));
+        assert_eq!(None, negotiate("gzip;q=0", &[Gzip]));
+        assert_eq!(None, negotiate("gzip;q=0.0", &[Gzip]));
+        assert_eq!(None, negotiate("gzip; q=0.000", &[Gzip]));
+        assert_eq!(None, negotiate("gzip;q=nope", &[Gzip]));
+        assert_eq!(None, negotiate("*, gzip;q=0", &[Gzip]));
+        assert_eq!(None, negotiate("gzip", &[]));
+        assert_eq!(None, negotiate_encoding(&http::HeaderMap::new(), &[Gzip]));
+
+        // The client's preference wins, then the order of the encodings
+        assert_eq!(Some(Brotli), negotiate("gzip;q=0.5, br", &[Gzip, Brotli]));
+        assert_eq!(
+            Some(Gzip),
+            negotiate("GZIP;Q=0.9, br;q=0.1", &[Brotli, Gzip])
+        );
+        assert_eq!(Some(Brotli), negotiate("gzip, br", &[Brotli, Gzip]));
+        assert_eq!(Some(Gzip), negotiate("*;q=0.5, br;q=0.2", &[Brotli, Gzip]));
+    }
+
+    #[tokio::test]
+    async fn request_body_size_limit() {
+        let body = read_body(Body::from("0123456789"), 10).await.unwrap();
+        assert_eq!(Some(&b"0123456789"[..]), body.as_deref());
+
+        let body = read_body(Body::from("0123456789"), 9).await.unwrap();
+        assert_eq!(None, body);
+    }
 }
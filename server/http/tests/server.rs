@@ -78,6 +78,16 @@ impl GraphQlRunner for TestGraphQlRunner {
         unreachable!();
     }
 
+    async fn deployment_for(
+        &self,
+        target: &QueryTarget,
+    ) -> Result<DeploymentHash, QueryExecutionError> {
+        match target {
+            QueryTarget::Deployment(id, _) => Ok(id.clone()),
+            QueryTarget::Name(_, _) => unimplemented!(),
+        }
+    }
+
     fn load_manager(&self) -> Arc<LoadManager> {
         unimplemented!()
     }
@@ -105,7 +115,8 @@ mod test {
                 let id = USERS.clone();
                 let query_runner = Arc::new(TestGraphQlRunner);
                 let node_id = NodeId::new("test").unwrap();
-                let mut server = HyperGraphQLServer::new(&logger_factory, query_runner, node_id);
+                let mut server =
+                    HyperGraphQLServer::new(&logger_factory, query_runner, node_id).unwrap();
                 let http_server = server
                     .serve(8007, 8008)
                     .expect("Failed to start GraphQL server");
@@ -147,7 +158,8 @@ mod test {
             let id = USERS.clone();
             let query_runner = Arc::new(TestGraphQlRunner);
             let node_id = NodeId::new("test").unwrap();
-            let mut server = HyperGraphQLServer::new(&logger_factory, query_runner, node_id);
+            let mut server =
+                HyperGraphQLServer::new(&logger_factory, query_runner, node_id).unwrap();
             let http_server = server
                 .serve(8002, 8003)
                 .expect("Failed to start GraphQL server");
@@ -228,7 +240,8 @@ mod test {
             let id = USERS.clone();
             let query_runner = Arc::new(TestGraphQlRunner);
             let node_id = NodeId::new("test").unwrap();
-            let mut server = HyperGraphQLServer::new(&logger_factory, query_runner, node_id);
+            let mut server =
+                HyperGraphQLServer::new(&logger_factory, query_runner, node_id).unwrap();
             let http_server = server
                 .serve(8003, 8004)
                 .expect("Failed to start GraphQL server");
@@ -274,7 +287,8 @@ mod test {
             let id = USERS.clone();
             let query_runner = Arc::new(TestGraphQlRunner);
             let node_id = NodeId::new("test").unwrap();
-            let mut server = HyperGraphQLServer::new(&logger_factory, query_runner, node_id);
+            let mut server =
+                HyperGraphQLServer::new(&logger_factory, query_runner, node_id).unwrap();
             let http_server = server
                 .serve(8005, 8006)
                 .expect("Failed to start GraphQL server");
@@ -1,11 +1,14 @@
 mod auth;
 mod explorer;
+mod manifest;
+mod poi;
 mod resolver;
 mod schema;
 mod server;
 mod service;
 
 pub use self::auth::PoiProtection;
+pub use self::poi::{bisect, Poi, PoiComparator, PoiDiff, Pois};
 pub use self::server::IndexNodeServer;
 pub use self::service::{IndexNodeService, IndexNodeServiceResponse};
 
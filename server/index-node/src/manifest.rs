@@ -0,0 +1,187 @@
+//! The data sources of a deployment, as its manifest lists them and as the
+//! deployment created them from templates. The manifest is read with a
+//! generic YAML parser rather than the manifest types of the chains so that
+//! listing data sources works the same for every chain and does not need
+//! to resolve any files from IPFS
+use graph::components::store::StoredDynamicDataSource;
+use graph::data::graphql::{object, IntoValue};
+use graph::prelude::{hex, r, serde_yaml, BlockNumber};
+use serde_yaml::Value;
+
+/// A data source of a deployment
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataSourceInfo {
+    pub kind: String,
+    pub name: String,
+    pub network: Option<String>,
+    /// The address of the contract, or the file for file data sources
+    pub address: Option<String>,
+    /// The block at which the data source starts. For data sources created
+    /// from templates, the block at which they were created
+    pub start_block: Option<BlockNumber>,
+    /// Whether the data source was created from a template
+    pub dynamic: bool,
+}
+
+impl DataSourceInfo {
+    fn from_yaml(value: &Value) -> Self {
+        let str_field = |value: &Value, key: &str| value.get(key)?.as_str().map(str::to_string);
+        let source = value.get("source");
+        DataSourceInfo {
+            kind: str_field(value, "kind").unwrap_or_default(),
+            name: str_field(value, "name").unwrap_or_default(),
+            network: str_field(value, "network"),
+            address: source.and_then(|source| str_field(source, "address")),
+            start_block: source
+                .and_then(|source| source.get("startBlock")?.as_i64())
+                .and_then(|block| BlockNumber::try_from(block).ok()),
+            dynamic: false,
+        }
+    }
+}
+
+impl IntoValue for DataSourceInfo {
+    fn into_value(self) -> r::Value {
+        object! {
+            __typename: "DataSourceInfo",
+            kind: self.kind,
+            name: self.name,
+            network: self.network,
+            address: self.address,
+            startBlock: self.start_block,
+            dynamic: self.dynamic,
+        }
+    }
+}
+
+/// The data sources and templates of a manifest
+#[derive(Debug)]
+pub struct ManifestDataSources {
+    data_sources: Vec<DataSourceInfo>,
+    templates: Vec<DataSourceInfo>,
+}
+
+impl ManifestDataSources {
+    pub fn parse(manifest: &str) -> Result<Self, serde_yaml::Error> {
+        let raw: Value = serde_yaml::from_str(manifest)?;
+        let entries = |key: &str| -> Vec<DataSourceInfo> {
+            raw.get(key)
+                .and_then(Value::as_sequence)
+                .map(|entries| entries.iter().map(DataSourceInfo::from_yaml).collect())
+                .unwrap_or_default()
+        };
+        Ok(ManifestDataSources {
+            data_sources: entries("dataSources"),
+            templates: entries("templates"),
+        })
+    }
+
+    /// The index and name of each template in the manifest. Like
+    /// `SubgraphManifest::template_idx_and_name`, templates are counted
+    /// after the data sources
+    pub fn template_idx_and_name(&self) -> Vec<(u32, String)> {
+        let ds_len = self.data_sources.len() as u32;
+        self.templates
+            .iter()
+            .enumerate()
+            .map(|(idx, template)| (ds_len + idx as u32, template.name.clone()))
+            .collect()
+    }
+
+    /// The data sources of the manifest followed by the data sources in
+    /// `dynamic` that the deployment created from templates
+    pub fn with_dynamic(self, dynamic: Vec<StoredDynamicDataSource>) -> Vec<DataSourceInfo> {
+        let ds_len = self.data_sources.len();
+        let templates = self.templates;
+        let mut data_sources = self.data_sources;
+        data_sources.extend(dynamic.into_iter().filter_map(|stored| {
+            let template = templates.get((stored.manifest_idx as usize).checked_sub(ds_len)?)?;
+            let address = stored
+                .param
+                .map(|param| match template.kind.starts_with("file/") {
+                    true => String::from_utf8_lossy(param.as_slice()).into_owned(),
+                    false => format!("0x{}", hex::encode(param.as_slice())),
+                });
+            Some(DataSourceInfo {
+                kind: template.kind.clone(),
+                name: template.name.clone(),
+                network: template.network.clone(),
+                address,
+                start_block: stored.creation_block,
+                dynamic: true,
+            })
+        }));
+        data_sources
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use graph::data_source::CausalityRegion;
+
+    use super::*;
+
+    const MANIFEST: &str = r#"
+specVersion: 0.0.5
+schema:
+  file:
+    /: /ipfs/QmSchema
+dataSources:
+  - kind: ethereum/contract
+    name: Factory
+    network: mainnet
+    source:
+      address: "0x0000000000000000000000000000000000000001"
+      abi: Factory
+      startBlock: 100
+templates:
+  - kind: ethereum/contract
+    name: Pool
+    network: mainnet
+  - kind: file/ipfs
+    name: Metadata
+"#;
+
+    fn stored(manifest_idx: u32, param: &[u8], block: BlockNumber) -> StoredDynamicDataSource {
+        StoredDynamicDataSource {
+            manifest_idx,
+            param: Some(param.to_vec().into()),
+            context: None,
+            creation_block: Some(block),
+            done_at: None,
+            causality_region: CausalityRegion::ONCHAIN,
+        }
+    }
+
+    #[test]
+    fn data_sources() {
+        let manifest = ManifestDataSources::parse(MANIFEST).unwrap();
+        assert_eq!(
+            vec![(1, "Pool".to_string()), (2, "Metadata".to_string())],
+            manifest.template_idx_and_name()
+        );
+
+        let dynamic = vec![stored(1, &[0xab; 20], 150), stored(2, b"QmFile", 160)];
+        let data_sources = manifest.with_dynamic(dynamic);
+        assert_eq!(3, data_sources.len());
+
+        let factory = &data_sources[0];
+        assert_eq!("Factory", factory.name);
+        assert_eq!(Some(100), factory.start_block);
+        assert!(!factory.dynamic);
+        assert_eq!(
+            Some("0x0000000000000000000000000000000000000001"),
+            factory.address.as_deref()
+        );
+
+        let pool = &data_sources[1];
+        assert_eq!("Pool", pool.name);
+        assert_eq!(Some("mainnet"), pool.network.as_deref());
+        assert_eq!(Some(format!("0x{}", "ab".repeat(20))), pool.address);
+        assert_eq!(Some(150), pool.start_block);
+        assert!(pool.dynamic);
+
+        assert_eq!("file/ipfs", data_sources[2].kind);
+        assert_eq!(Some("QmFile"), data_sources[2].address.as_deref());
+    }
+}
@@ -0,0 +1,375 @@
+//! Compare the public proofs of indexing of a deployment with those of
+//! another indexer to find the block at which the two started to diverge
+use std::convert::TryInto;
+use std::time::Duration;
+
+use futures::Future;
+use graph::components::store::StatusStore;
+use graph::data::graphql::{object, IntoValue};
+use graph::data::subgraph::status;
+use graph::prelude::serde_json::{json, Value};
+use graph::prelude::*;
+use graph::url::Url;
+
+/// The maximum number of blocks that `publicProofsOfIndexing` accepts in
+/// one request
+const MAX_REQUESTS: usize = 10;
+
+/// How long to wait for the other indexer to respond
+const REMOTE_TIMEOUT: Duration = Duration::from_secs(60);
+
+const REMOTE_QUERY: &str = "query pois($requests: [PublicProofOfIndexingRequest!]!) {
+  publicProofsOfIndexing(requests: $requests) { proofOfIndexing }
+}";
+
+pub type Poi = [u8; 32];
+
+/// The local and remote proof of indexing at a block, if they are known
+pub type Pois = (Option<Poi>, Option<Poi>);
+
+/// The result of comparing the proofs of indexing of a deployment on this
+/// indexer with those of another indexer
+#[derive(Clone, Debug, PartialEq)]
+pub struct PoiDiff {
+    pub deployment: DeploymentHash,
+    /// The block at which the comparison was requested
+    pub block: BlockNumber,
+    /// The last block at which both indexers have the same proof of
+    /// indexing, if there is such a block
+    pub last_agreeing_block: Option<BlockNumber>,
+    /// The first block at which the proofs of indexing differ, or `None`
+    /// if they agree at `block`. If the proofs of indexing for the blocks
+    /// after `last_agreeing_block` are not available, this is not
+    /// necessarily the block at which the indexers started to diverge, and
+    /// the divergence happened somewhere in the range between the two
+    pub first_divergent_block: Option<BlockNumber>,
+    /// Our proof of indexing at `first_divergent_block`, or at `block` if
+    /// the proofs of indexing agree
+    pub local_poi: Poi,
+    /// The other indexer's proof of indexing at the same block as
+    /// `local_poi`
+    pub remote_poi: Poi,
+}
+
+impl PoiDiff {
+    pub fn agrees(&self) -> bool {
+        self.first_divergent_block.is_none()
+    }
+}
+
+impl IntoValue for PoiDiff {
+    fn into_value(self) -> r::Value {
+        object! {
+            deployment: self.deployment.to_string(),
+            blockNumber: self.block,
+            agrees: self.agrees(),
+            lastAgreeingBlock: self.last_agreeing_block,
+            firstDivergentBlock: self.first_divergent_block,
+            localProofOfIndexing: format!("0x{}", hex::encode(self.local_poi)),
+            remoteProofOfIndexing: format!("0x{}", hex::encode(self.remote_poi)),
+        }
+    }
+}
+
+/// Compares our public proofs of indexing with those that another indexer
+/// serves from its index node status API at `indexer`
+pub struct PoiComparator<S> {
+    store: Arc<S>,
+    client: reqwest::Client,
+    indexer: Url,
+}
+
+impl<S: StatusStore> PoiComparator<S> {
+    pub fn new(store: Arc<S>, indexer: Url) -> Self {
+        PoiComparator {
+            store,
+            client: reqwest::Client::new(),
+            indexer,
+        }
+    }
+
+    /// Compare the proofs of indexing for `deployment` at `block`, and if
+    /// they differ, find the first block at or after `start` at which they
+    /// differ. If `start` is not given, the earliest block of the
+    /// deployment is used
+    pub async fn diff(
+        &self,
+        deployment: &DeploymentHash,
+        block: BlockNumber,
+        start: Option<BlockNumber>,
+    ) -> Result<PoiDiff, Error> {
+        let start = match start {
+            Some(start) => start,
+            None => self.earliest_block(deployment)?,
+        };
+        if start > block {
+            return Err(anyhow!(
+                "the start block {} is after block {}",
+                start,
+                block
+            ));
+        }
+
+        let (local, remote) = self.pois(deployment, vec![block]).await?[0];
+        let (local_poi, remote_poi) = match (local, remote) {
+            (Some(local), Some(remote)) => (local, remote),
+            (None, _) => {
+                return Err(anyhow!(
+                    "there is no proof of indexing for {} at block {} on this indexer",
+                    deployment,
+                    block
+                ))
+            }
+            (_, None) => {
+                return Err(anyhow!(
+                    "there is no proof of indexing for {} at block {} on {}",
+                    deployment,
+                    block,
+                    self.indexer
+                ))
+            }
+        };
+
+        if local_poi == remote_poi {
+            return Ok(PoiDiff {
+                deployment: deployment.clone(),
+                block,
+                last_agreeing_block: Some(block),
+                first_divergent_block: None,
+                local_poi,
+                remote_poi,
+            });
+        }
+
+        let (last_agreeing_block, first_divergent_block, (local_poi, remote_poi)) =
+            bisect(start, block, (local_poi, remote_poi), |blocks| {
+                self.pois(deployment, blocks)
+            })
+            .await?;
+        Ok(PoiDiff {
+            deployment: deployment.clone(),
+            block,
+            last_agreeing_block,
+            first_divergent_block: Some(first_divergent_block),
+            local_poi,
+            remote_poi,
+        })
+    }
+
+    fn earliest_block(&self, deployment: &DeploymentHash) -> Result<BlockNumber, Error> {
+        let infos = self
+            .store
+            .status(status::Filter::Deployments(vec![deployment.to_string()]))?;
+        infos
+            .first()
+            .and_then(|info| info.chains.first())
+            .map(|chain| chain.earliest_block_number)
+            .ok_or_else(|| anyhow!("deployment {} not found", deployment))
+    }
+
+    /// Our and the other indexer's proofs of indexing for `blocks`
+    async fn pois(
+        &self,
+        deployment: &DeploymentHash,
+        blocks: Vec<BlockNumber>,
+    ) -> Result<Vec<Pois>, Error> {
+        let remote = self.remote_pois(deployment, &blocks).await?;
+        let mut pois = Vec::with_capacity(blocks.len());
+        for (block, remote) in blocks.into_iter().zip(remote) {
+            let local = self
+                .store
+                .get_public_proof_of_indexing(deployment, block)
+                .await?
+                .map(|(_, poi)| poi);
+            pois.push((local, remote));
+        }
+        Ok(pois)
+    }
+
+    async fn remote_pois(
+        &self,
+        deployment: &DeploymentHash,
+        blocks: &[BlockNumber],
+    ) -> Result<Vec<Option<Poi>>, Error> {
+        let requests: Vec<_> = blocks
+            .iter()
+            .map(|block| {
+                json!({
+                    "deployment": deployment.as_str(),
+                    "blockNumber": block.to_string(),
+                })
+            })
+            .collect();
+        let body = json!({ "query": REMOTE_QUERY, "variables": { "requests": requests } });
+
+        let res: Value = self
+            .client
+            .post(self.indexer.clone())
+            .json(&body)
+            .timeout(REMOTE_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        if let Some(errors) = res.get("errors") {
+            return Err(anyhow!(
+                "{} failed to return proofs of indexing: {}",
+                self.indexer,
+                errors
+            ));
+        }
+        let results = res["data"]["publicProofsOfIndexing"]
+            .as_array()
+            .filter(|results| results.len() == blocks.len())
+            .ok_or_else(|| anyhow!("{} returned an invalid response: {}", self.indexer, res))?;
+        results
+            .iter()
+            .map(|result| match result["proofOfIndexing"].as_str() {
+                Some(poi) => parse_poi(poi).map(Some),
+                None => Ok(None),
+            })
+            .collect()
+    }
+}
+
+fn parse_poi(poi: &str) -> Result<Poi, Error> {
+    let bytes = hex::decode(poi.trim_start_matches("0x"))?;
+    bytes
+        .try_into()
+        .map_err(|_| anyhow!("invalid proof of indexing {}", poi))
+}
+
+/// Find the first block in `start..=hi` at which the proofs of indexing
+/// differ, knowing that they differ at `hi`. Since a proof of indexing
+/// covers all blocks up to the one it is for, the proofs of indexing agree
+/// up to some block and differ after it. Each round checks up to
+/// `MAX_REQUESTS` blocks spread evenly across the remaining range with
+/// `fetch`. Blocks for which one of the indexers has no proof of indexing
+/// are skipped, and the search stops when none of the blocks checked in a
+/// round could be compared.
+///
+/// Returns the last block known to agree, the first block known to
+/// differ, and the proofs of indexing at that block
+pub async fn bisect<F, Fut>(
+    start: BlockNumber,
+    mut hi: BlockNumber,
+    mut hi_pois: (Poi, Poi),
+    fetch: F,
+) -> Result<(Option<BlockNumber>, BlockNumber, (Poi, Poi)), Error>
+where
+    F: Fn(Vec<BlockNumber>) -> Fut,
+    Fut: Future<Output = Result<Vec<Pois>, Error>>,
+{
+    // The search covers the blocks strictly between `lo` and `hi`
+    let mut lo = start - 1;
+    let mut agreeing = None;
+
+    while hi - lo > 1 {
+        let count = ((hi - lo - 1) as usize).min(MAX_REQUESTS);
+        let span = (hi - lo) as i64;
+        let mut blocks: Vec<_> = (1..=count as i64)
+            .map(|i| lo + (span * i / (count as i64 + 1)) as BlockNumber)
+            .collect();
+        blocks.dedup();
+
+        let pois = fetch(blocks.clone()).await?;
+        let mut narrowed = false;
+        for (block, pois) in blocks.into_iter().zip(pois) {
+            match pois {
+                (Some(local), Some(remote)) if local == remote => {
+                    lo = block;
+                    agreeing = Some(block);
+                    narrowed = true;
+                }
+                (Some(local), Some(remote)) => {
+                    hi = block;
+                    hi_pois = (local, remote);
+                    narrowed = true;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        if !narrowed {
+            break;
+        }
+    }
+    Ok((agreeing, hi, hi_pois))
+}
+
+#[cfg(test)]
+mod tests {
+    use graph::prelude::{BlockNumber, Error};
+
+    use super::{bisect, Poi, Pois};
+
+    fn poi(n: u8) -> Poi {
+        [n; 32]
+    }
+
+    /// Fetch proofs of indexing for two indexers that diverge at block
+    /// `divergent` and that have no proofs of indexing for `missing`
+    fn fetch(
+        divergent: BlockNumber,
+        missing: Vec<BlockNumber>,
+    ) -> impl Fn(Vec<BlockNumber>) -> futures::future::Ready<Result<Vec<Pois>, Error>> {
+        move |blocks| {
+            let pois = blocks
+                .into_iter()
+                .map(|block| {
+                    if missing.contains(&block) {
+                        (Some(poi(0)), None)
+                    } else if block < divergent {
+                        (Some(poi(1)), Some(poi(1)))
+                    } else {
+                        (Some(poi(2)), Some(poi(3)))
+                    }
+                })
+                .collect();
+            futures::future::ready(Ok(pois))
+        }
+    }
+
+    fn run(
+        start: BlockNumber,
+        hi: BlockNumber,
+        divergent: BlockNumber,
+        missing: Vec<BlockNumber>,
+    ) -> (Option<BlockNumber>, BlockNumber) {
+        let (agreeing, divergent, pois) = futures::executor::block_on(bisect(
+            start,
+            hi,
+            (poi(2), poi(3)),
+            fetch(divergent, missing),
+        ))
+        .unwrap();
+        assert_eq!((poi(2), poi(3)), pois);
+        (agreeing, divergent)
+    }
+
+    #[test]
+    fn finds_first_divergent_block() {
+        assert_eq!((Some(12_344), 12_345), run(0, 1_000_000, 12_345, vec![]));
+        assert_eq!((Some(99), 100), run(0, 100, 100, vec![]));
+        assert_eq!((Some(10), 11), run(10, 1_000, 11, vec![]));
+        // Diverged right at the start
+        assert_eq!((None, 10), run(10, 1_000, 5, vec![]));
+        assert_eq!((None, 7), run(7, 7, 7, vec![]));
+    }
+
+    #[test]
+    fn missing_pois_leave_a_range() {
+        // The search can not get closer to the divergence than the blocks
+        // around the missing ones
+        let missing = (40..60).collect();
+        let (agreeing, divergent) = run(0, 1_000, 50, missing);
+        assert!(agreeing.unwrap() < 40);
+        assert!(divergent >= 60);
+
+        // Every block except the end is missing
+        let missing = (0..100).collect();
+        assert_eq!((None, 100), run(0, 100, 50, missing));
+    }
+}
@@ -1,21 +1,32 @@
 use std::collections::BTreeMap;
 use std::convert::TryInto;
+use std::time::SystemTime;
 
 use either::Either;
 use graph::data::query::Trace;
 use web3::types::Address;
 
 use graph::blockchain::{Blockchain, BlockchainKind, BlockchainMap};
-use graph::components::store::{BlockStore, EntityType, Store};
+use graph::components::metrics::ingestors::{IngestorStatus, INGESTOR_HEALTH};
+use graph::components::metrics::resources::{ResourceUsage, RESOURCE_USAGE};
+use graph::components::store::{BlockStore, EntityChangeCursor, EntityType, Store};
+use graph::components::subgraph::{ProofOfIndexingVersion, OFFCHAIN_STATUS};
 use graph::components::versions::VERSIONS;
 use graph::data::graphql::{object, IntoValue, ObjectOrInterface, ValueMap};
 use graph::data::subgraph::features::detect_features;
-use graph::data::subgraph::status;
+use graph::data::subgraph::{status, ManifestSupport};
 use graph::data::value::{Object, Word};
+use graph::endpoint::{ProviderInfo, ProviderInventory};
 use graph::prelude::*;
+use graph::url::Url;
 use graph_graphql::prelude::{a, ExecutionContext, Resolver};
 
 use crate::auth::PoiProtection;
+use crate::manifest::ManifestDataSources;
+use crate::poi::PoiComparator;
+
+/// The most changes that one page of `entityChangesBetween` can contain
+const MAX_ENTITY_CHANGES_PAGE: usize = 1000;
 
 #[derive(Clone, Debug)]
 struct PublicProofOfIndexingRequest {
@@ -67,6 +78,7 @@ pub struct IndexNodeResolver<S: Store> {
     store: Arc<S>,
     link_resolver: Arc<dyn LinkResolver>,
     bearer_token: Option<String>,
+    providers: Arc<dyn ProviderInventory>,
 }
 
 impl<S: Store> IndexNodeResolver<S> {
@@ -76,6 +88,7 @@ impl<S: Store> IndexNodeResolver<S> {
         link_resolver: Arc<dyn LinkResolver>,
         bearer_token: Option<String>,
         blockchain_map: Arc<BlockchainMap>,
+        providers: Arc<dyn ProviderInventory>,
     ) -> Self {
         let logger = logger.new(o!("component" => "IndexNodeResolver"));
 
@@ -85,6 +98,7 @@ impl<S: Store> IndexNodeResolver<S> {
             store,
             link_resolver,
             bearer_token,
+            providers,
         }
     }
 
@@ -103,9 +117,10 @@ impl<S: Store> IndexNodeResolver<S> {
             })
             .unwrap_or_else(Vec::new);
 
-        let infos = self
+        let mut infos = self
             .store
             .status(status::Filter::Deployments(deployments))?;
+        add_offchain_status(&mut infos);
         Ok(infos.into_value())
     }
 
@@ -126,9 +141,10 @@ impl<S: Store> IndexNodeResolver<S> {
             "name" => &subgraph_name
         );
 
-        let infos = self
+        let mut infos = self
             .store
             .status(status::Filter::SubgraphName(subgraph_name))?;
+        add_offchain_status(&mut infos);
 
         Ok(infos.into_value())
     }
@@ -153,6 +169,118 @@ impl<S: Store> IndexNodeResolver<S> {
         Ok(entity_changes_to_graphql(entity_changes))
     }
 
+    fn resolve_entity_changes_between(
+        &self,
+        field: &a::Field,
+    ) -> Result<r::Value, QueryExecutionError> {
+        let subgraph_id = field
+            .get_required::<DeploymentHash>("subgraphId")
+            .expect("Valid subgraphId required");
+        let entity_type = field
+            .get_required::<String>("entityType")
+            .expect("Valid entityType required");
+        let from_block = field
+            .get_required::<BlockNumber>("fromBlock")
+            .expect("Valid fromBlock required");
+        let to_block = field
+            .get_required::<BlockNumber>("toBlock")
+            .expect("Valid toBlock required");
+        let first = field
+            .get_optional::<i32>("first")
+            .expect("Invalid first")
+            .unwrap_or(MAX_ENTITY_CHANGES_PAGE as i32);
+        let after = field
+            .get_optional::<String>("after")
+            .expect("Invalid after");
+
+        if from_block < 0 || from_block > to_block {
+            return Err(QueryExecutionError::ValidationError(
+                None,
+                format!(
+                    "invalid block range: fromBlock {} must be between 0 and toBlock {}",
+                    from_block, to_block
+                ),
+            ));
+        }
+
+        if first < 1 || first as usize > MAX_ENTITY_CHANGES_PAGE {
+            return Err(QueryExecutionError::ValidationError(
+                None,
+                format!(
+                    "first must be between 1 and {}, but is {}",
+                    MAX_ENTITY_CHANGES_PAGE, first
+                ),
+            ));
+        }
+        let after = after
+            .map(|after| after.parse::<EntityChangeCursor>())
+            .transpose()
+            .map_err(|e| QueryExecutionError::ValidationError(None, e.to_string()))?;
+
+        let (entity_changes, next) = self.store.subgraph_store().entity_changes_between(
+            &subgraph_id,
+            &EntityType::new(entity_type),
+            from_block,
+            to_block,
+            after.as_ref(),
+            first as usize,
+        )?;
+
+        Ok(object! {
+            changes: entity_changes_to_graphql(entity_changes),
+            cursor: match next {
+                Some(next) => r::Value::String(next.to_string()),
+                None => r::Value::Null,
+            },
+        })
+    }
+
+    fn resolve_entity_versions(&self, field: &a::Field) -> Result<r::Value, QueryExecutionError> {
+        let deployment = field
+            .get_required::<DeploymentHash>("deployment")
+            .expect("Valid deployment required");
+        let entity_type = field
+            .get_required::<String>("entityType")
+            .expect("Valid entityType required");
+        let id = field
+            .get_required::<String>("id")
+            .expect("Valid id required");
+        let block_number = field
+            .get_optional::<BlockNumber>("blockNumber")
+            .expect("Invalid blockNumber");
+
+        // Raw versions show everything a deployment ever wrote, including
+        // its proofs of indexing; only allow that for trusted callers
+        let poi_protection = PoiProtection::from_env(&ENV_VARS);
+        if !poi_protection.validate_access_token(self.bearer_token.as_deref()) {
+            return Err(QueryExecutionError::ValidationError(
+                None,
+                "listing entity versions requires a valid access token".to_string(),
+            ));
+        }
+
+        let versions = self.store.subgraph_store().entity_versions(
+            &deployment,
+            &EntityType::new(entity_type),
+            &id,
+            block_number,
+        )?;
+        Ok(r::Value::List(
+            versions
+                .into_iter()
+                .map(|version| {
+                    object! {
+                        __typename: "EntityVersion",
+                        vid: format!("{}", version.vid),
+                        startBlock: version.start,
+                        endBlock: version.end,
+                        data: r::Value::from(version.data),
+                    }
+                })
+                .collect(),
+        ))
+    }
+
     fn resolve_block_data(&self, field: &a::Field) -> Result<r::Value, QueryExecutionError> {
         let network = field
             .get_required::<String>("network")
@@ -378,6 +506,19 @@ impl<S: Store> IndexNodeResolver<S> {
             .get_optional::<Address>("indexer")
             .expect("Invalid indexer");
 
+        let version = match field
+            .get_optional::<i32>("version")
+            .expect("Invalid version")
+        {
+            Some(number) => Some(ProofOfIndexingVersion::from_number(number).ok_or_else(|| {
+                QueryExecutionError::ValidationError(
+                    None,
+                    format!("unknown proof of indexing version {}", number),
+                )
+            })?),
+            None => None,
+        };
+
         let poi_protection = PoiProtection::from_env(&ENV_VARS);
         if !poi_protection.validate_access_token(self.bearer_token.as_deref()) {
             // Let's sign the POI with a zero'd address when the access token is
@@ -385,9 +526,9 @@ impl<S: Store> IndexNodeResolver<S> {
             indexer = Some(Address::zero());
         }
 
-        let poi_fut = self
-            .store
-            .get_proof_of_indexing(&deployment_id, &indexer, block.clone());
+        let poi_fut =
+            self.store
+                .get_proof_of_indexing(&deployment_id, &indexer, block.clone(), version);
         let poi = match futures::executor::block_on(poi_fut) {
             Ok(Some(poi)) => r::Value::String(format!("0x{}", hex::encode(poi))),
             Ok(None) => r::Value::Null,
@@ -458,6 +599,48 @@ impl<S: Store> IndexNodeResolver<S> {
         ))
     }
 
+    async fn resolve_proof_of_indexing_diff(
+        &self,
+        field: &a::Field,
+    ) -> Result<r::Value, QueryExecutionError> {
+        let deployment = field
+            .get_required::<DeploymentHash>("deployment")
+            .expect("Valid deployment required");
+        let block_number = field
+            .get_required::<BlockNumber>("blockNumber")
+            .expect("Valid blockNumber required");
+        let indexer = field
+            .get_required::<String>("indexer")
+            .expect("Valid indexer required");
+        let start_block = field
+            .get_optional::<BlockNumber>("startBlock")
+            .expect("Invalid startBlock");
+
+        // Comparing makes this node send requests to whatever URL it is
+        // given; only allow that for callers that may see private POIs
+        let poi_protection = PoiProtection::from_env(&ENV_VARS);
+        if !poi_protection.validate_access_token(self.bearer_token.as_deref()) {
+            return Err(QueryExecutionError::ValidationError(
+                None,
+                "comparing proofs of indexing requires a valid access token".to_string(),
+            ));
+        }
+
+        let indexer = Url::parse(&indexer).map_err(|e| {
+            QueryExecutionError::ValidationError(None, format!("invalid indexer URL: {}", e))
+        })?;
+        let diff = PoiComparator::new(self.store.cheap_clone(), indexer)
+            .diff(&deployment, block_number, start_block)
+            .await
+            .map_err(|e| {
+                QueryExecutionError::ValidationError(
+                    None,
+                    format!("failed to compare proofs of indexing: {:#}", e),
+                )
+            })?;
+        Ok(diff.into_value())
+    }
+
     fn resolve_indexing_status_for_version(
         &self,
         field: &a::Field,
@@ -475,10 +658,11 @@ impl<S: Store> IndexNodeResolver<S> {
             "current_version" => current_version,
         );
 
-        let infos = self.store.status(status::Filter::SubgraphVersion(
+        let mut infos = self.store.status(status::Filter::SubgraphVersion(
             subgraph_name,
             current_version,
         ))?;
+        add_offchain_status(&mut infos);
 
         Ok(infos
             .into_iter()
@@ -615,6 +799,101 @@ impl<S: Store> IndexNodeResolver<S> {
         Ok(r::Value::Object(response))
     }
 
+    async fn resolve_subgraph_manifest(
+        &self,
+        field: &a::Field,
+    ) -> Result<r::Value, QueryExecutionError> {
+        let deployment = field
+            .get_required::<DeploymentHash>("subgraphId")
+            .expect("Valid subgraphId required");
+
+        let raw = match self.store.raw_manifest(&deployment)? {
+            Some(raw) => raw,
+            None => return Ok(r::Value::Null),
+        };
+        let data_sources = match raw.manifest.as_deref() {
+            Some(manifest) => {
+                let manifest = ManifestDataSources::parse(manifest).map_err(|e| {
+                    QueryExecutionError::StoreError(
+                        anyhow!("the manifest of {} is invalid: {}", deployment, e).into(),
+                    )
+                })?;
+                let dynamic = self
+                    .store
+                    .dynamic_data_sources(&deployment, manifest.template_idx_and_name())
+                    .await?;
+                manifest.with_dynamic(dynamic)
+            }
+            None => vec![],
+        };
+
+        Ok(object! {
+            __typename: "SubgraphManifest",
+            deployment: deployment.to_string(),
+            schema: raw.schema,
+            manifest: raw.manifest,
+            dataSources: data_sources,
+        })
+    }
+
+    fn resolve_providers(&self, field: &a::Field) -> Result<r::Value, QueryExecutionError> {
+        let chain = field
+            .get_optional::<String>("chain")
+            .expect("Invalid chain");
+
+        Ok(r::Value::List(
+            self.providers
+                .providers()
+                .into_iter()
+                .filter(|info| chain.as_ref().map_or(true, |chain| &info.chain == chain))
+                .map(provider_to_graphql)
+                .collect(),
+        ))
+    }
+
+    fn resolve_block_ingestors(&self, field: &a::Field) -> Result<r::Value, QueryExecutionError> {
+        let chain = field
+            .get_optional::<String>("chain")
+            .expect("Invalid chain");
+
+        Ok(r::Value::List(
+            INGESTOR_HEALTH
+                .all()
+                .into_iter()
+                .filter(|status| chain.as_ref().map_or(true, |chain| &status.chain == chain))
+                .map(ingestor_status_to_graphql)
+                .collect(),
+        ))
+    }
+
+    fn resolve_resource_usage(&self, field: &a::Field) -> Result<r::Value, QueryExecutionError> {
+        let deployments = field
+            .get_optional::<Vec<String>>("deployments")
+            .expect("Invalid deployments");
+
+        Ok(r::Value::List(
+            RESOURCE_USAGE
+                .all()
+                .into_iter()
+                .filter(|(deployment, _)| {
+                    deployments
+                        .as_ref()
+                        .map_or(true, |deployments| deployments.contains(deployment))
+                })
+                .map(|(deployment, resources)| {
+                    let last_five_minutes = resources.usage(Duration::from_secs(5 * 60));
+                    let last_hour = resources.usage(Duration::from_secs(60 * 60));
+                    object! {
+                        deployment: deployment,
+                        lastFiveMinutes: resource_usage_to_graphql(last_five_minutes),
+                        lastHour: resource_usage_to_graphql(last_hour),
+                        sinceStart: resource_usage_to_graphql(resources.total()),
+                    }
+                })
+                .collect(),
+        ))
+    }
+
     fn resolve_api_versions(&self, _field: &a::Field) -> Result<r::Value, QueryExecutionError> {
         Ok(r::Value::List(
             VERSIONS
@@ -630,6 +909,94 @@ impl<S: Store> IndexNodeResolver<S> {
     }
 }
 
+/// Add the state of the file data sources that this node monitors to the
+/// statuses that the store returned
+fn add_offchain_status(infos: &mut [status::Info]) {
+    for info in infos {
+        info.offchain_data_sources = OFFCHAIN_STATUS.summary(&info.subgraph);
+    }
+}
+
+fn manifest_support_to_graphql(support: ManifestSupport) -> r::Value {
+    object! {
+        __typename: "ManifestSupport",
+        minSpecVersion: support.min_spec_version.to_string(),
+        maxSpecVersion: support.max_spec_version.to_string(),
+        maxApiVersion: support.max_api_version.to_string(),
+        features: support
+            .features
+            .iter()
+            .map(|feature| r::Value::String(feature.to_string()))
+            .collect::<Vec<_>>(),
+    }
+}
+
+fn resource_usage_to_graphql(usage: ResourceUsage) -> r::Value {
+    object! {
+        wasmSeconds: usage.wasm.as_secs_f64(),
+        storeWriteSeconds: usage.store_write.as_secs_f64(),
+        rpcSeconds: usage.rpc.as_secs_f64(),
+        bytesWritten: usage.bytes_written,
+    }
+}
+
+fn ingestor_status_to_graphql(status: IngestorStatus) -> r::Value {
+    let head_lag = status.head_lag();
+    let secs_since = |time: SystemTime| {
+        SystemTime::now()
+            .duration_since(time)
+            .unwrap_or_default()
+            .as_secs_f64()
+    };
+    let IngestorStatus {
+        chain,
+        provider,
+        latest_block,
+        provider_head: _,
+        last_error,
+    } = status;
+    let (latest_block, latest_block_time) = latest_block.unzip();
+    let (last_error, last_error_time) = last_error.unzip();
+
+    object! {
+        chain: chain,
+        provider: provider,
+        latestBlock: latest_block.map(|ptr| object! {
+            hash: ptr.hash.hash_hex(),
+            number: ptr.number,
+        }),
+        secondsSinceLatestBlock: latest_block_time.map(secs_since),
+        headLag: head_lag,
+        lastError: last_error,
+        secondsSinceLastError: last_error_time.map(secs_since),
+    }
+}
+
+fn provider_to_graphql(info: ProviderInfo) -> r::Value {
+    let ProviderInfo {
+        chain,
+        provider,
+        conn_type,
+        capabilities,
+        health,
+    } = info;
+    let ms = |latency: Option<Duration>| latency.map(|latency| latency.as_secs_f64() * 1000.0);
+    let kind: &str = (&conn_type).into();
+
+    object! {
+        chain: chain,
+        name: provider.to_string(),
+        kind: r::Value::Enum(kind.to_string()),
+        capabilities: capabilities,
+        requests: health.requests as i32,
+        consecutiveErrors: health.consecutive_errors as i32,
+        errorRate: health.error_rate,
+        latencyP50Ms: ms(health.latency_p50),
+        latencyP90Ms: ms(health.latency_p90),
+        latencyP99Ms: ms(health.latency_p99),
+    }
+}
+
 struct ValidationPostProcessResult {
     features: r::Value,
     errors: r::Value,
@@ -841,6 +1208,12 @@ impl<S: Store> Resolver for IndexNodeResolver<S> {
             (None, "SubgraphIndexingStatus", "indexingStatusesForSubgraphName") => {
                 self.resolve_indexing_statuses_for_subgraph_name(field)
             }
+            (None, "Provider", "providers") => self.resolve_providers(field),
+            (None, "DeploymentResourceUsage", "resourceUsage") => {
+                self.resolve_resource_usage(field)
+            }
+            (None, "BlockIngestorStatus", "blockIngestors") => self.resolve_block_ingestors(field),
+            (None, "EntityVersion", "entityVersions") => self.resolve_entity_versions(field),
             (None, "CachedEthereumCall", "cachedEthereumCalls") => {
                 self.resolve_cached_ethereum_calls(field).await
             }
@@ -871,9 +1244,15 @@ impl<S: Store> Resolver for IndexNodeResolver<S> {
                 self.resolve_indexing_status_for_version(field, false)
             }
             (None, "subgraphFeatures") => self.resolve_subgraph_features(field).await,
+            (None, "subgraphManifest") => self.resolve_subgraph_manifest(field).await,
             (None, "entityChangesInBlock") => self.resolve_entity_changes_in_block(field),
+            (None, "entityChangesBetween") => self.resolve_entity_changes_between(field),
+            (None, "proofOfIndexingDiff") => self.resolve_proof_of_indexing_diff(field).await,
             // The top-level `subgraphVersions` field
             (None, "apiVersions") => self.resolve_api_versions(field),
+            (None, "manifestSupport") => {
+                Ok(manifest_support_to_graphql(ManifestSupport::current()))
+            }
 
             // Resolve fields of `Object` values (e.g. the `latestBlock` field of `EthereumBlock`)
             (value, _) => Ok(value.unwrap_or(r::Value::Null)),
@@ -4,7 +4,9 @@ use std::net::{Ipv4Addr, SocketAddrV4};
 
 use graph::{
     blockchain::BlockchainMap,
+    components::server::tls::{self, ServerTls},
     components::store::Store,
+    endpoint::ProviderInventory,
     prelude::{IndexNodeServer as IndexNodeServerTrait, *},
 };
 
@@ -15,7 +17,7 @@ use thiserror::Error;
 #[derive(Debug, Error)]
 pub enum IndexNodeServeError {
     #[error("Bind error: {0}")]
-    BindError(#[from] hyper::Error),
+    BindError(#[from] std::io::Error),
 }
 
 /// A GraphQL server based on Hyper.
@@ -25,6 +27,8 @@ pub struct IndexNodeServer<Q, S> {
     graphql_runner: Arc<Q>,
     store: Arc<S>,
     link_resolver: Arc<dyn LinkResolver>,
+    providers: Arc<dyn ProviderInventory>,
+    tls: Option<Arc<ServerTls>>,
 }
 
 impl<Q, S> IndexNodeServer<Q, S> {
@@ -35,6 +39,7 @@ impl<Q, S> IndexNodeServer<Q, S> {
         graphql_runner: Arc<Q>,
         store: Arc<S>,
         link_resolver: Arc<dyn LinkResolver>,
+        providers: Arc<dyn ProviderInventory>,
     ) -> Self {
         let logger = logger_factory.component_logger(
             "IndexNodeServer",
@@ -51,8 +56,16 @@ impl<Q, S> IndexNodeServer<Q, S> {
             graphql_runner,
             store,
             link_resolver,
+            providers,
+            tls: None,
         }
     }
+
+    /// Serve requests over TLS with `tls`
+    pub fn with_tls(mut self, tls: Option<Arc<ServerTls>>) -> Self {
+        self.tls = tls;
+        self
+    }
 }
 
 impl<Q, S> IndexNodeServerTrait for IndexNodeServer<Q, S>
@@ -70,7 +83,9 @@ where
 
         info!(
             logger,
-            "Starting index node server at: http://localhost:{}", port
+            "Starting index node server at: {}://localhost:{}",
+            tls::scheme(&self.tls, "http"),
+            port
         );
 
         let addr = SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), port);
@@ -86,12 +101,14 @@ where
             graphql_runner,
             store,
             self.link_resolver.clone(),
+            self.providers.clone(),
         );
         let new_service =
             make_service_fn(move |_| futures03::future::ok::<_, Error>(service.clone()));
 
         // Create a task to run the server and handle HTTP requests
-        let task = Server::try_bind(&addr.into())?
+        let incoming = tls::incoming(self.logger.clone(), addr.into(), self.tls.clone())?;
+        let task = Server::builder(incoming)
             .serve(new_service)
             .map_err(move |e| error!(logger, "Server error"; "error" => format!("{}", e)));
 
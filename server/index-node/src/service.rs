@@ -12,6 +12,7 @@ use std::task::Poll;
 
 use graph::components::{server::query::GraphQLServerError, store::Store};
 use graph::data::query::QueryResults;
+use graph::endpoint::ProviderInventory;
 use graph::prelude::*;
 use graph_graphql::prelude::{execute_query, Query as PreparedQuery, QueryExecutionOptions};
 
@@ -42,6 +43,7 @@ pub struct IndexNodeService<Q, S> {
     store: Arc<S>,
     explorer: Arc<Explorer<S>>,
     link_resolver: Arc<dyn LinkResolver>,
+    providers: Arc<dyn ProviderInventory>,
 }
 
 impl<Q, S> Clone for IndexNodeService<Q, S> {
@@ -53,6 +55,7 @@ impl<Q, S> Clone for IndexNodeService<Q, S> {
             store: self.store.clone(),
             explorer: self.explorer.clone(),
             link_resolver: self.link_resolver.clone(),
+            providers: self.providers.clone(),
         }
     }
 }
@@ -71,6 +74,7 @@ where
         graphql_runner: Arc<Q>,
         store: Arc<S>,
         link_resolver: Arc<dyn LinkResolver>,
+        providers: Arc<dyn ProviderInventory>,
     ) -> Self {
         let explorer = Arc::new(Explorer::new(store.clone()));
 
@@ -81,6 +85,7 @@ where
             store,
             explorer,
             link_resolver,
+            providers,
         }
     }
 
@@ -153,6 +158,7 @@ where
                 self.link_resolver.clone(),
                 validated.bearer_token,
                 self.blockchain_map.clone(),
+                self.providers.clone(),
             );
             let options = QueryExecutionOptions {
                 resolver,
@@ -161,6 +167,7 @@ where
                 max_skip: std::u32::MAX,
                 load_manager,
                 trace: false,
+                max_result_bytes: None,
             };
             let result = execute_query(query_clone.cheap_clone(), None, None, options).await;
             query_clone.log_execution(0);
@@ -0,0 +1,356 @@
+//! API keys for the admin JSON-RPC server. Without API keys, everybody
+//! who can reach the admin port can call every method. With API keys, each
+//! request has to carry a key in an `Authorization: Bearer <token>` header,
+//! and the key has to have a role that allows the methods it calls.
+//!
+//! The keys are read from a JSON file that contains a list of keys:
+//!
+//! ```json
+//! [
+//!   { "name": "ops", "token": "...", "roles": ["admin"] },
+//!   {
+//!     "name": "team-a-ci",
+//!     "token": "...",
+//!     "roles": ["deploy", "read-status"],
+//!     "subgraphs": ["team-a/*"]
+//!   }
+//! ]
+//! ```
+//!
+//! A key that lists `subgraphs` or `deployments` can only be used for
+//! those. Subgraph names can end in `*` to match all names with that
+//! prefix.
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use graph::env::EnvVars;
+use graph::prelude::anyhow::Context as _;
+use graph::prelude::{anyhow, serde_json, Error};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+
+/// What an API key is allowed to do
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Role {
+    /// Call every method
+    Admin,
+    /// Create subgraph names and deploy new versions
+    Deploy,
+    /// Reassign, pause and resume deployments
+    Reassign,
+    /// Rewind deployments
+    Rewind,
+    /// Call methods that do not change anything
+    ReadStatus,
+}
+
+impl Role {
+    /// The roles other than `Admin` that allow calling `method`
+    fn for_method(method: &str) -> &'static [Role] {
+        use Role::*;
+
+        match method {
+            "subgraph_create" | "subgraph_deploy" | "subgraph_deploy_batch" => &[Deploy],
+            "subgraph_validate" => &[ReadStatus, Deploy],
+            "subgraph_reassign" | "subgraph_pause" | "subgraph_resume" => &[Reassign],
+            "subgraph_rewind" => &[Rewind],
+            _ => &[],
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ApiKeyConfig {
+    name: String,
+    token: String,
+    roles: Vec<Role>,
+    #[serde(default)]
+    subgraphs: Vec<String>,
+    #[serde(default)]
+    deployments: Vec<String>,
+}
+
+/// An API key from the API keys file
+#[derive(Debug)]
+pub struct ApiKey {
+    name: String,
+    roles: HashSet<Role>,
+    subgraphs: Vec<String>,
+    deployments: Vec<String>,
+}
+
+impl ApiKey {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Check that this key may make all the calls in `request`, a
+    /// JSON-RPC request or batch of requests
+    pub fn authorize(&self, request: &JsonValue) -> Result<(), AuthError> {
+        match request {
+            JsonValue::Array(calls) => calls.iter().try_for_each(|call| self.authorize_call(call)),
+            call => self.authorize_call(call),
+        }
+    }
+
+    fn is_scoped(&self) -> bool {
+        !self.subgraphs.is_empty() || !self.deployments.is_empty()
+    }
+
+    fn allows_subgraph(&self, name: &str) -> bool {
+        self.subgraphs
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => name == pattern,
+            })
+    }
+
+    fn allows_deployment(&self, deployment: &str) -> bool {
+        self.deployments.iter().any(|d| d == deployment)
+    }
+
+    /// Check that this key may make the call `call`, a JSON-RPC request
+    /// object
+    fn authorize_call(&self, call: &JsonValue) -> Result<(), AuthError> {
+        let method = call.get("method").and_then(JsonValue::as_str).unwrap_or("");
+        let permitted = self.roles.contains(&Role::Admin)
+            || Role::for_method(method)
+                .iter()
+                .any(|role| self.roles.contains(role));
+        if !permitted {
+            return Err(AuthError::Forbidden(format!(
+                "API key `{}` is not allowed to call `{}`",
+                self.name, method
+            )));
+        }
+        if !self.is_scoped() {
+            return Ok(());
+        }
+
+        let params = match call.get("params") {
+            Some(JsonValue::Object(params)) => params,
+            _ => {
+                return Err(AuthError::Forbidden(format!(
+                    "API key `{}` can only be used with named parameters",
+                    self.name
+                )))
+            }
+        };
+        let str_param = |name: &str| params.get(name).and_then(JsonValue::as_str);
+        let (names, deployments): (Vec<&str>, Vec<&str>) = match method {
+            "subgraph_create" | "subgraph_deploy" | "subgraph_remove" => {
+                (str_param("name").into_iter().collect(), vec![])
+            }
+            "subgraph_deploy_batch" => {
+                let names = params
+                    .get("subgraphs")
+                    .and_then(JsonValue::as_array)
+                    .map(|entries| {
+                        entries
+                            .iter()
+                            .filter_map(|entry| entry.get("name").and_then(JsonValue::as_str))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                (names, vec![])
+            }
+            "subgraph_reassign" => (vec![], str_param("ipfs_hash").into_iter().collect()),
+            "subgraph_pause" | "subgraph_resume" | "subgraph_rewind" => {
+                (vec![], str_param("deployment").into_iter().collect())
+            }
+            _ => (vec![], vec![]),
+        };
+        if let Some(name) = names.iter().find(|name| !self.allows_subgraph(name)) {
+            return Err(AuthError::Forbidden(format!(
+                "API key `{}` can not be used for subgraph `{}`",
+                self.name, name
+            )));
+        }
+        if let Some(deployment) = deployments.iter().find(|d| !self.allows_deployment(d)) {
+            return Err(AuthError::Forbidden(format!(
+                "API key `{}` can not be used for deployment `{}`",
+                self.name, deployment
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// The reason why a request was rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthError {
+    /// The request did not carry a known API key
+    Unauthorized,
+    /// The API key is not allowed to make the request
+    Forbidden(String),
+}
+
+impl AuthError {
+    pub fn message(&self) -> String {
+        match self {
+            AuthError::Unauthorized => "a valid API key is required".to_string(),
+            AuthError::Forbidden(msg) => msg.clone(),
+        }
+    }
+}
+
+/// The API keys that the admin JSON-RPC server accepts
+#[derive(Debug)]
+pub struct ApiKeys {
+    /// The keys by the BLAKE3 hash of their token so that looking up a
+    /// token does not leak anything about the tokens through timing
+    keys: HashMap<[u8; 32], ApiKey>,
+}
+
+impl ApiKeys {
+    /// Read the API keys from the file in `GRAPH_ADMIN_API_KEYS_FILE`.
+    /// Returns `None` if the variable is not set
+    pub fn from_env(env: &EnvVars) -> Result<Option<Self>, Error> {
+        env.admin_api_keys_file
+            .as_ref()
+            .map(|path| Self::load(Path::new(path)))
+            .transpose()
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read API keys from {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("invalid API keys in {}", path.display()))
+    }
+
+    pub(crate) fn parse(text: &str) -> Result<Self, Error> {
+        let configs: Vec<ApiKeyConfig> = serde_json::from_str(text)?;
+        let mut keys = HashMap::new();
+        for config in configs {
+            if config.token.is_empty() {
+                return Err(anyhow!("API key `{}` has an empty token", config.name));
+            }
+            let key = ApiKey {
+                name: config.name,
+                roles: config.roles.into_iter().collect(),
+                subgraphs: config.subgraphs,
+                deployments: config.deployments,
+            };
+            let hash = *blake3::hash(config.token.as_bytes()).as_bytes();
+            if let Some(other) = keys.insert(hash, key) {
+                return Err(anyhow!(
+                    "API key `{}` has the same token as another key",
+                    other.name
+                ));
+            }
+        }
+        Ok(ApiKeys { keys })
+    }
+
+    /// The API key with `token`. Requests can be checked with this before
+    /// their body has been read
+    pub fn authenticate(&self, token: Option<&[u8]>) -> Result<&ApiKey, AuthError> {
+        token
+            .and_then(|token| self.keys.get(blake3::hash(token).as_bytes()))
+            .ok_or(AuthError::Unauthorized)
+    }
+
+    /// Check that the API key with `token` may make all the calls in
+    /// `request`, a JSON-RPC request or batch of requests. Returns the
+    /// name of the key
+    pub fn authorize(&self, token: Option<&[u8]>, request: &JsonValue) -> Result<&str, AuthError> {
+        let key = self.authenticate(token)?;
+        key.authorize(request)?;
+        Ok(key.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const KEYS: &str = r#"[
+        { "name": "ops", "token": "ops-token", "roles": ["admin"] },
+        {
+            "name": "ci",
+            "token": "ci-token",
+            "roles": ["deploy", "read-status"],
+            "subgraphs": ["team-a/*", "shared"]
+        },
+        {
+            "name": "oncall",
+            "token": "oncall-token",
+            "roles": ["reassign", "rewind"],
+            "deployments": ["QmA"]
+        }
+    ]"#;
+
+    fn call(method: &str, params: JsonValue) -> JsonValue {
+        json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params })
+    }
+
+    #[test]
+    fn tokens() {
+        let keys = ApiKeys::parse(KEYS).unwrap();
+        let reload = call("config_reload", json!({}));
+
+        assert_eq!(Ok("ops"), keys.authorize(Some(b"ops-token"), &reload));
+        assert_eq!(
+            Err(AuthError::Unauthorized),
+            keys.authorize(Some(b"unknown"), &reload)
+        );
+        assert_eq!(Err(AuthError::Unauthorized), keys.authorize(None, &reload));
+        assert_eq!("ci", keys.authenticate(Some(b"ci-token")).unwrap().name());
+        assert!(keys.authenticate(Some(b"unknown")).is_err());
+        assert!(keys.authorize(Some(b"ci-token"), &reload).is_err());
+
+        assert!(ApiKeys::parse(r#"[{ "name": "a", "token": "", "roles": [] }]"#).is_err());
+        assert!(ApiKeys::parse(
+            r#"[{ "name": "a", "token": "t", "roles": [] },
+                { "name": "b", "token": "t", "roles": [] }]"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn roles_and_scopes() {
+        let keys = ApiKeys::parse(KEYS).unwrap();
+        let ci = |request: &JsonValue| keys.authorize(Some(b"ci-token"), request).is_ok();
+        let oncall = |request: &JsonValue| keys.authorize(Some(b"oncall-token"), request).is_ok();
+
+        assert!(ci(&call("subgraph_deploy", json!({ "name": "team-a/x" }))));
+        assert!(ci(&call("subgraph_create", json!({ "name": "shared" }))));
+        assert!(!ci(&call("subgraph_deploy", json!({ "name": "team-b/x" }))));
+        assert!(!ci(&call("subgraph_deploy", json!(["team-a/x", "QmA"]))));
+        assert!(!ci(&call("subgraph_remove", json!({ "name": "team-a/x" }))));
+        assert!(!ci(&call(
+            "subgraph_deploy_batch",
+            json!({ "subgraphs": [{ "name": "team-a/x" }, { "name": "team-b/x" }] })
+        )));
+        assert!(ci(&json!([
+            call("subgraph_validate", json!({ "ipfs_hash": "QmB" })),
+            call("subgraph_deploy", json!({ "name": "team-a/x" })),
+        ])));
+        assert!(!ci(&json!([
+            call("subgraph_validate", json!({ "ipfs_hash": "QmB" })),
+            call("subgraph_pause", json!({ "deployment": "QmA" })),
+        ])));
+
+        assert!(oncall(&call(
+            "subgraph_pause",
+            json!({ "deployment": "QmA" })
+        )));
+        assert!(oncall(&call(
+            "subgraph_reassign",
+            json!({ "ipfs_hash": "QmA", "node_id": "n" })
+        )));
+        assert!(!oncall(&call(
+            "subgraph_rewind",
+            json!({ "deployment": "QmB" })
+        )));
+        assert!(!oncall(&call(
+            "subgraph_deploy",
+            json!({ "name": "shared" })
+        )));
+    }
+}
@@ -1,4 +1,11 @@
+use graph::components::server::tls::{self, ServerTls};
+use graph::components::store::SubgraphVersions;
 use graph::prelude::{Value as GraphValue, *};
+use hyper::body::HttpBody as _;
+use hyper::header::{AUTHORIZATION, CONTENT_TYPE};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use jsonrpsee::core::server::rpc_module::Methods;
 use jsonrpsee::core::Error as JsonRpcError;
 use jsonrpsee::http_server::{HttpServerBuilder, HttpServerHandle};
 use jsonrpsee::types::error::CallError;
@@ -6,26 +13,45 @@ use jsonrpsee::types::ErrorObject;
 use jsonrpsee::RpcModule;
 use serde_json::{self, Value as JsonValue};
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
+use std::convert::Infallible;
 use std::net::{Ipv4Addr, SocketAddr};
 
+mod auth;
+
+pub use auth::{ApiKey, ApiKeys, AuthError, Role};
+
 type JsonRpcResult<T> = Result<T, jsonrpsee::core::Error>;
 
+/// The error codes for requests that the API keys reject
+const UNAUTHORIZED_ERROR: i64 = 9;
+const FORBIDDEN_ERROR: i64 = 10;
+
+/// The largest request body that the admin server accepts, the same as
+/// the default of the `jsonrpsee` HTTP server
+const MAX_REQUEST_BODY_SIZE: usize = 10 * 1024 * 1024;
+
 pub struct JsonRpcServer {
     // TODO: in the future we might want to have some sort of async drop to stop
     // the server. For now, we're just letting it run it forever.
-    _handle: HttpServerHandle,
+    _handle: Option<HttpServerHandle>,
 }
 
 impl JsonRpcServer {
+    /// Serve the admin API on `port`. With `api_keys` or `tls`, the API is
+    /// served by our own HTTP server, which only calls the methods of the
+    /// API for requests that carry an API key that allows them
     pub async fn serve<R>(
         port: u16,
         http_port: u16,
         ws_port: u16,
         registrar: Arc<R>,
+        config_reloader: Arc<dyn ConfigReloader>,
         node_id: NodeId,
+        api_keys: Option<ApiKeys>,
+        tls: Option<Arc<ServerTls>>,
         logger: Logger,
-    ) -> JsonRpcResult<Self>
+    ) -> Result<Self, Error>
     where
         R: SubgraphRegistrar,
     {
@@ -33,20 +59,21 @@ impl JsonRpcServer {
 
         info!(
             logger,
-            "Starting JSON-RPC admin server at: http://localhost:{}", port
+            "Starting JSON-RPC admin server at: {}://localhost:{}",
+            tls::scheme(&tls, "http"),
+            port
         );
 
+        let socket_addr: SocketAddr = (Ipv4Addr::new(0, 0, 0, 0), port).into();
         let state = ServerState {
             registrar,
+            config_reloader,
             http_port,
             ws_port,
             node_id,
-            logger,
+            logger: logger.clone(),
         };
 
-        let socket_addr: SocketAddr = (Ipv4Addr::new(0, 0, 0, 0), port).into();
-        let http_server = HttpServerBuilder::default().build(socket_addr).await?;
-
         let mut rpc_module = RpcModule::new(state);
         rpc_module
             .register_async_method("subgraph_create", |params, state| async move {
@@ -58,6 +85,16 @@ impl JsonRpcServer {
                 state.deploy_handler(params.parse()?).await
             })
             .unwrap();
+        rpc_module
+            .register_async_method("subgraph_deploy_batch", |params, state| async move {
+                state.deploy_batch_handler(params.parse()?).await
+            })
+            .unwrap();
+        rpc_module
+            .register_async_method("subgraph_validate", |params, state| async move {
+                state.validate_handler(params.parse()?).await
+            })
+            .unwrap();
         rpc_module
             .register_async_method("subgraph_remove", |params, state| async move {
                 state.remove_handler(params.parse()?).await
@@ -68,14 +105,215 @@ impl JsonRpcServer {
                 state.reassign_handler(params.parse()?).await
             })
             .unwrap();
+        rpc_module
+            .register_async_method("subgraph_pause", |params, state| async move {
+                state.pause_handler(params.parse()?).await
+            })
+            .unwrap();
+        rpc_module
+            .register_async_method("subgraph_resume", |params, state| async move {
+                state.resume_handler(params.parse()?).await
+            })
+            .unwrap();
+        rpc_module
+            .register_async_method("subgraph_rewind", |params, state| async move {
+                state.rewind_handler(params.parse()?).await
+            })
+            .unwrap();
+        rpc_module
+            .register_async_method("config_reload", |_, state| async move {
+                state.reload_handler().await
+            })
+            .unwrap();
 
-        let _handle = http_server.start(rpc_module)?;
+        let _handle = if api_keys.is_some() || tls.is_some() {
+            if api_keys.is_some() {
+                info!(logger, "Requiring API keys for the JSON-RPC admin server");
+            }
+            serve_authorized(
+                socket_addr,
+                rpc_module.into(),
+                api_keys.map(Arc::new),
+                tls,
+                logger,
+            )?;
+            None
+        } else {
+            let http_server = HttpServerBuilder::default().build(socket_addr).await?;
+            Some(http_server.start(rpc_module)?)
+        };
         Ok(Self { _handle })
     }
 }
 
+/// Accept requests on `addr`, with TLS if `tls` is set, and call the
+/// `methods` for them. With `api_keys`, only requests that the API keys
+/// allow are answered
+fn serve_authorized(
+    addr: SocketAddr,
+    methods: Methods,
+    api_keys: Option<Arc<ApiKeys>>,
+    tls: Option<Arc<ServerTls>>,
+    logger: Logger,
+) -> Result<(), Error> {
+    let incoming = tls::incoming(logger.clone(), addr, tls)?;
+    let make_service = make_service_fn(move |_| {
+        let api_keys = api_keys.clone();
+        let methods = methods.clone();
+        let logger = logger.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                authorize_and_call(api_keys.clone(), methods.clone(), logger.clone(), req)
+            }))
+        }
+    });
+    let server = hyper::Server::builder(incoming).serve(make_service);
+    graph::spawn(async move {
+        if let Err(e) = server.await {
+            panic!("JSON-RPC admin server failed: {}", e);
+        }
+    });
+    Ok(())
+}
+
+async fn authorize_and_call(
+    api_keys: Option<Arc<ApiKeys>>,
+    methods: Methods,
+    logger: Logger,
+    req: Request<Body>,
+) -> Result<Response<Body>, hyper::Error> {
+    if req.method() != Method::POST {
+        return Ok(error_response(
+            StatusCode::METHOD_NOT_ALLOWED,
+            -32600,
+            "only POST requests are accepted".to_string(),
+        ));
+    }
+
+    // Check the API key before reading the body so that requests without
+    // a valid key can not make us buffer anything
+    let key = match &api_keys {
+        Some(api_keys) => {
+            let token = req
+                .headers()
+                .get(AUTHORIZATION)
+                .and_then(|header| header.as_bytes().strip_prefix(b"Bearer "));
+            match api_keys.authenticate(token) {
+                Ok(key) => Some(key),
+                Err(e) => return Ok(rejected(&logger, e)),
+            }
+        }
+        None => None,
+    };
+
+    let body = match read_body(req.into_body(), MAX_REQUEST_BODY_SIZE).await? {
+        Some(body) => body,
+        None => {
+            return Ok(error_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                -32600,
+                format!("the request is larger than {} bytes", MAX_REQUEST_BODY_SIZE),
+            ))
+        }
+    };
+    // Requests that are not valid JSON are rejected here since we can not
+    // tell which methods they call
+    let request: JsonValue = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(e) => {
+            return Ok(error_response(
+                StatusCode::BAD_REQUEST,
+                -32700,
+                format!("invalid JSON: {}", e),
+            ))
+        }
+    };
+    if let Some(key) = key {
+        match key.authorize(&request) {
+            Ok(()) => debug!(logger, "Authorized admin request"; "api_key" => key.name()),
+            Err(e) => return Ok(rejected(&logger, e)),
+        }
+    }
+
+    let response = match request {
+        JsonValue::Array(calls) if calls.is_empty() => {
+            return Ok(error_response(
+                StatusCode::BAD_REQUEST,
+                -32600,
+                "empty batch request".to_string(),
+            ))
+        }
+        JsonValue::Array(calls) => {
+            let mut responses = Vec::with_capacity(calls.len());
+            for call in &calls {
+                responses.push(call_method(&methods, call).await);
+            }
+            format!("[{}]", responses.join(","))
+        }
+        call => call_method(&methods, &call).await,
+    };
+    Ok(Response::builder()
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(response))
+        .unwrap())
+}
+
+/// Read `body` unless it is larger than `limit` bytes, in which case
+/// reading stops and `None` is returned
+async fn read_body(mut body: Body, limit: usize) -> Result<Option<Vec<u8>>, hyper::Error> {
+    if body.size_hint().lower() > limit as u64 {
+        return Ok(None);
+    }
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+        if bytes.len() + chunk.len() > limit {
+            return Ok(None);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(Some(bytes))
+}
+
+/// Call the method for `call`, a JSON-RPC request object, and return the
+/// serialized response
+async fn call_method(methods: &Methods, call: &JsonValue) -> String {
+    match methods.raw_json_request(&call.to_string()).await {
+        Ok((response, _)) => response.result,
+        Err(e) => serde_json::json!({
+            "jsonrpc": "2.0",
+            "error": { "code": -32600, "message": format!("invalid request: {}", e) },
+            "id": call.get("id").cloned().unwrap_or(JsonValue::Null),
+        })
+        .to_string(),
+    }
+}
+
+fn rejected(logger: &Logger, e: AuthError) -> Response<Body> {
+    warn!(logger, "Rejected admin request"; "error" => e.message());
+    let (status, code) = match e {
+        AuthError::Unauthorized => (StatusCode::UNAUTHORIZED, UNAUTHORIZED_ERROR),
+        AuthError::Forbidden(_) => (StatusCode::FORBIDDEN, FORBIDDEN_ERROR),
+    };
+    error_response(status, code, e.message())
+}
+
+fn error_response(status: StatusCode, code: i64, message: String) -> Response<Body> {
+    let body = serde_json::json!({
+        "jsonrpc": "2.0",
+        "error": { "code": code, "message": message },
+        "id": JsonValue::Null,
+    });
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
 struct ServerState<R> {
     registrar: Arc<R>,
+    config_reloader: Arc<dyn ConfigReloader>,
     http_port: u16,
     ws_port: u16,
     node_id: NodeId,
@@ -87,6 +325,11 @@ impl<R: SubgraphRegistrar> ServerState<R> {
     const REMOVE_ERROR: i64 = 1;
     const CREATE_ERROR: i64 = 2;
     const REASSIGN_ERROR: i64 = 3;
+    const PAUSE_ERROR: i64 = 4;
+    const RESUME_ERROR: i64 = 5;
+    const REWIND_ERROR: i64 = 6;
+    const RELOAD_ERROR: i64 = 7;
+    const DEPLOY_BATCH_ERROR: i64 = 8;
 
     /// Handler for the `subgraph_create` endpoint.
     async fn create_handler(&self, params: SubgraphCreateParams) -> JsonRpcResult<JsonValue> {
@@ -123,6 +366,7 @@ impl<R: SubgraphRegistrar> ServerState<R> {
                 // startBlock, we'll use the one from the manifest.
                 None,
                 None,
+                params.graft_at_earliest_block.unwrap_or(false),
             )
             .await
         {
@@ -137,6 +381,173 @@ impl<R: SubgraphRegistrar> ServerState<R> {
         }
     }
 
+    /// Handler for the `subgraph_deploy_batch` endpoint.
+    ///
+    /// Validates the manifests of all subgraphs in the batch before
+    /// deploying any of them, and then creates their names if needed and
+    /// deploys them one after the other. If any of them fails, the batch is
+    /// rolled back: names that the batch created are removed again, which
+    /// also unassigns their deployments, and subgraphs that existed before
+    /// get back the versions they had
+    async fn deploy_batch_handler(
+        &self,
+        params: SubgraphDeployBatchParams,
+    ) -> JsonRpcResult<JsonValue> {
+        info!(&self.logger, "Received subgraph_deploy_batch request";
+              "count" => params.subgraphs.len());
+
+        let mut names = HashSet::new();
+        if let Some(dup) = params
+            .subgraphs
+            .iter()
+            .find(|entry| !names.insert(entry.name.as_str()))
+        {
+            return Err(self.batch_error(
+                format!(
+                    "subgraph `{}` appears more than once in the batch",
+                    dup.name
+                ),
+                None,
+            ));
+        }
+
+        let mut invalid = Vec::new();
+        for entry in &params.subgraphs {
+            let validation = self.registrar.validate_subgraph(&entry.ipfs_hash).await;
+            if !validation.valid {
+                invalid.push(validation);
+            }
+        }
+        if !invalid.is_empty() {
+            let message = format!(
+                "{} of the subgraphs in the batch are not valid, nothing was deployed",
+                invalid.len()
+            );
+            return Err(self.batch_error(message, Some(serde_json::json!({ "invalid": invalid }))));
+        }
+
+        let mut deployed: Vec<BatchDeployed> = Vec::new();
+        let mut undo: Vec<BatchUndo> = Vec::new();
+        for entry in &params.subgraphs {
+            let node_id = entry.node_id.clone().unwrap_or(self.node_id.clone());
+            match self
+                .deploy_batch_entry(entry, node_id.clone(), &mut undo)
+                .await
+            {
+                Ok(created) => deployed.push(BatchDeployed {
+                    name: entry.name.to_string(),
+                    ipfs_hash: entry.ipfs_hash.to_string(),
+                    node_id: node_id.to_string(),
+                    created,
+                    routes: subgraph_routes(&entry.name, self.http_port, self.ws_port),
+                }),
+                Err(e) => {
+                    let message = format!("deploying subgraph `{}` failed: {}", entry.name, e);
+                    let report = self.rollback_batch(undo, entry).await;
+                    return Err(self.batch_error(message, Some(report)));
+                }
+            }
+        }
+
+        info!(&self.logger, "subgraph_deploy_batch succeeded"; "count" => deployed.len());
+        Ok(serde_json::json!({ "deployed": deployed }))
+    }
+
+    /// Create the name for `entry` if it does not exist yet and deploy it,
+    /// and remember in `undo` how to undo that, also when deploying fails.
+    /// Return whether the name was created
+    async fn deploy_batch_entry(
+        &self,
+        entry: &SubgraphDeployBatchEntry,
+        node_id: NodeId,
+        undo: &mut Vec<BatchUndo>,
+    ) -> Result<bool, SubgraphRegistrarError> {
+        let created = match self.registrar.subgraph_versions(&entry.name).await? {
+            Some(versions) => {
+                undo.push(BatchUndo::Restore(entry.name.clone(), versions));
+                false
+            }
+            None => {
+                self.registrar.create_subgraph(entry.name.clone()).await?;
+                undo.push(BatchUndo::Remove(entry.name.clone()));
+                true
+            }
+        };
+        self.registrar
+            .create_subgraph_version(
+                entry.name.clone(),
+                entry.ipfs_hash.clone(),
+                node_id,
+                None,
+                None,
+                None,
+                false,
+            )
+            .await?;
+        Ok(created)
+    }
+
+    /// Undo what a batch deployment did before `failed` failed, in reverse
+    /// order
+    async fn rollback_batch(
+        &self,
+        undo: Vec<BatchUndo>,
+        failed: &SubgraphDeployBatchEntry,
+    ) -> JsonValue {
+        let mut rolled_back = Vec::new();
+        let mut not_rolled_back = Vec::new();
+        for step in undo.into_iter().rev() {
+            let (name, res) = match step {
+                BatchUndo::Remove(name) => {
+                    (name.to_string(), self.registrar.remove_subgraph(name).await)
+                }
+                BatchUndo::Restore(name, versions) => (
+                    name.to_string(),
+                    self.registrar
+                        .restore_subgraph_versions(name, versions)
+                        .await,
+                ),
+            };
+            match res {
+                Ok(()) => rolled_back.push(name),
+                Err(e) => {
+                    error!(&self.logger, "Failed to roll back subgraph";
+                           "subgraph_name" => &name, "error" => e.to_string());
+                    not_rolled_back.push(name)
+                }
+            }
+        }
+
+        serde_json::json!({
+            "failed": failed.name.to_string(),
+            "rolled_back": rolled_back,
+            "not_rolled_back": not_rolled_back,
+        })
+    }
+
+    fn batch_error(&self, message: String, report: Option<JsonValue>) -> JsonRpcError {
+        error!(&self.logger, "subgraph_deploy_batch failed";
+            "error" => &message,
+            "report" => report.as_ref().map(|report| report.to_string()));
+
+        JsonRpcError::Call(CallError::Custom(ErrorObject::owned(
+            Self::DEPLOY_BATCH_ERROR as _,
+            message,
+            report,
+        )))
+    }
+
+    /// Handler for the `subgraph_validate` endpoint.
+    ///
+    /// Problems with the manifest are not errors but are reported in the
+    /// `diagnostics` of the result
+    async fn validate_handler(&self, params: SubgraphValidateParams) -> JsonRpcResult<JsonValue> {
+        info!(&self.logger, "Received subgraph_validate request"; "params" => format!("{:?}", params));
+
+        let validation = self.registrar.validate_subgraph(&params.ipfs_hash).await;
+        Ok(serde_json::to_value(validation).expect("invalid subgraph validation result"))
+    }
+
     /// Handler for the `subgraph_remove` endpoint.
     async fn remove_handler(&self, params: SubgraphRemoveParams) -> JsonRpcResult<GraphValue> {
         info!(&self.logger, "Received subgraph_remove request"; "params" => format!("{:?}", params));
@@ -172,6 +583,92 @@ impl<R: SubgraphRegistrar> ServerState<R> {
             )),
         }
     }
+
+    /// Handler for the `subgraph_pause` endpoint.
+    async fn pause_handler(&self, params: SubgraphPauseParams) -> JsonRpcResult<GraphValue> {
+        info!(&self.logger, "Received subgraph_pause request"; "params" => format!("{:?}", params));
+
+        match self.registrar.pause_subgraph(&params.deployment).await {
+            Ok(_) => Ok(Value::Null),
+            Err(e) => Err(json_rpc_error(
+                &self.logger,
+                "subgraph_pause",
+                e,
+                Self::PAUSE_ERROR,
+                params,
+            )),
+        }
+    }
+
+    /// Handler for the `subgraph_resume` endpoint.
+    async fn resume_handler(&self, params: SubgraphPauseParams) -> JsonRpcResult<GraphValue> {
+        info!(&self.logger, "Received subgraph_resume request"; "params" => format!("{:?}", params));
+
+        match self.registrar.resume_subgraph(&params.deployment).await {
+            Ok(_) => Ok(Value::Null),
+            Err(e) => Err(json_rpc_error(
+                &self.logger,
+                "subgraph_resume",
+                e,
+                Self::RESUME_ERROR,
+                params,
+            )),
+        }
+    }
+
+    /// Handler for the `subgraph_rewind` endpoint.
+    async fn rewind_handler(&self, params: SubgraphRewindParams) -> JsonRpcResult<GraphValue> {
+        info!(&self.logger, "Received subgraph_rewind request"; "params" => format!("{:?}", params));
+
+        let block_ptr_to =
+            match BlockPtr::try_from((params.block_hash.as_str(), params.block_number as i64)) {
+                Ok(ptr) => ptr,
+                Err(e) => {
+                    return Err(json_rpc_error(
+                        &self.logger,
+                        "subgraph_rewind",
+                        SubgraphRegistrarError::InvalidRewind(format!(
+                            "invalid block hash `{}`: {}",
+                            params.block_hash, e
+                        )),
+                        Self::REWIND_ERROR,
+                        params,
+                    ))
+                }
+            };
+
+        match self
+            .registrar
+            .rewind_subgraph(&params.deployment, block_ptr_to)
+            .await
+        {
+            Ok(_) => Ok(Value::Null),
+            Err(e) => Err(json_rpc_error(
+                &self.logger,
+                "subgraph_rewind",
+                e,
+                Self::REWIND_ERROR,
+                params,
+            )),
+        }
+    }
+
+    /// Handler for the `config_reload` endpoint.
+    async fn reload_handler(&self) -> JsonRpcResult<JsonValue> {
+        info!(&self.logger, "Received config_reload request");
+
+        match self.config_reloader.reload().await {
+            Ok(changes) => Ok(serde_json::to_value(changes).expect("invalid config changes")),
+            Err(e) => {
+                error!(&self.logger, "config_reload failed"; "error" => e.to_string());
+                Err(JsonRpcError::Call(CallError::Custom(ErrorObject::owned(
+                    Self::RELOAD_ERROR as _,
+                    e.to_string(),
+                    None::<String>,
+                ))))
+            }
+        }
+    }
 }
 
 fn json_rpc_error(
@@ -191,10 +688,17 @@ fn json_rpc_error(
         e.to_string()
     };
 
+    // Deploy tooling can read what the manifest needs that this node does
+    // not support from the data of the error
+    let data = match &e {
+        SubgraphRegistrarError::ResolveError(SubgraphManifestResolveError::Unsupported(
+            unsupported,
+        )) => serde_json::to_value(unsupported).ok(),
+        _ => None,
+    };
+
     JsonRpcError::Call(CallError::Custom(ErrorObject::owned(
-        code as _,
-        message,
-        None::<String>,
+        code as _, message, data,
     )))
 }
 
@@ -236,6 +740,45 @@ struct SubgraphDeployParams {
     ipfs_hash: DeploymentHash,
     node_id: Option<NodeId>,
     debug_fork: Option<DeploymentHash>,
+    /// Graft at the earliest block of the graft base if it has been pruned
+    /// past the graft block of the manifest
+    graft_at_earliest_block: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubgraphDeployBatchEntry {
+    name: SubgraphName,
+    ipfs_hash: DeploymentHash,
+    node_id: Option<NodeId>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubgraphDeployBatchParams {
+    subgraphs: Vec<SubgraphDeployBatchEntry>,
+}
+
+/// How to undo deploying one subgraph of a batch
+enum BatchUndo {
+    /// Remove the name that the batch created
+    Remove(SubgraphName),
+    /// Give the subgraph back the versions it had before the batch
+    Restore(SubgraphName, SubgraphVersions),
+}
+
+/// A subgraph that was deployed as part of a batch
+#[derive(Debug, Serialize)]
+struct BatchDeployed {
+    name: String,
+    ipfs_hash: String,
+    node_id: String,
+    /// Whether the batch created the subgraph name
+    created: bool,
+    routes: JsonValue,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubgraphValidateParams {
+    ipfs_hash: DeploymentHash,
 }
 
 #[derive(Debug, Deserialize)]
@@ -248,3 +791,354 @@ struct SubgraphReassignParams {
     ipfs_hash: DeploymentHash,
     node_id: NodeId,
 }
+
+#[derive(Debug, Deserialize)]
+struct SubgraphPauseParams {
+    deployment: DeploymentHash,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubgraphRewindParams {
+    deployment: DeploymentHash,
+    block_hash: String,
+    block_number: BlockNumber,
+}
+
+#[cfg(test)]
+mod tests {
+    use graph::components::store::{DeploymentId, DeploymentLocator};
+
+    use super::*;
+
+    const KEYS: &str = r#"[
+        { "name": "ops", "token": "ops-token", "roles": ["admin"] },
+        { "name": "ci", "token": "ci-token", "roles": ["deploy"] }
+    ]"#;
+
+    fn methods() -> Methods {
+        let mut module = RpcModule::new(());
+        module
+            .register_method("config_reload", |_, _| Ok("reloaded"))
+            .unwrap();
+        module.into()
+    }
+
+    async fn request(token: Option<&str>, body: Body) -> (StatusCode, JsonValue) {
+        let api_keys = Arc::new(ApiKeys::parse(KEYS).unwrap());
+        let mut req = Request::post("/");
+        if let Some(token) = token {
+            req = req.header(AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let logger = Logger::root(slog::Discard, o!());
+        let res = authorize_and_call(Some(api_keys), methods(), logger, req.body(body).unwrap())
+            .await
+            .unwrap();
+        let status = res.status();
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    fn reload() -> JsonValue {
+        serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": "config_reload" })
+    }
+
+    #[tokio::test]
+    async fn requests_without_api_key_are_not_read() {
+        // Reading this body fails, so the request has to be rejected
+        // before its body is read
+        let (sender, body) = Body::channel();
+        sender.abort();
+        let (status, _) = request(None, body).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+
+        let (sender, body) = Body::channel();
+        sender.abort();
+        let (status, _) = request(Some("unknown"), body).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+    }
+
+    #[tokio::test]
+    async fn authorized_requests_call_methods() {
+        let (status, res) = request(Some("ops-token"), Body::from(reload().to_string())).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(serde_json::json!("reloaded"), res["result"]);
+
+        let batch = JsonValue::Array(vec![reload(), reload()]);
+        let (status, res) = request(Some("ops-token"), Body::from(batch.to_string())).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(2, res.as_array().unwrap().len());
+
+        let (status, _) = request(Some("ci-token"), Body::from(reload().to_string())).await;
+        assert_eq!(StatusCode::FORBIDDEN, status);
+    }
+
+    #[tokio::test]
+    async fn large_requests_are_rejected() {
+        let body = vec![b' '; MAX_REQUEST_BODY_SIZE + 1];
+        let (status, _) = request(Some("ops-token"), Body::from(body)).await;
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, status);
+
+        let (mut sender, body) = Body::channel();
+        graph::spawn(async move {
+            let chunk = vec![b' '; MAX_REQUEST_BODY_SIZE / 2 + 1];
+            while sender.send_data(chunk.clone().into()).await.is_ok() {}
+        });
+        let (status, _) = request(Some("ops-token"), body).await;
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, status);
+    }
+
+    /// A registrar that keeps the current deployment of each subgraph and
+    /// the rewinds it was asked to do in memory. Deploying `QmFail` fails,
+    /// and `QmInvalid` is not valid
+    #[derive(Default)]
+    struct MockRegistrar {
+        subgraphs: std::sync::Mutex<BTreeMap<String, Option<String>>>,
+        rewinds: std::sync::Mutex<Vec<(String, BlockPtr)>>,
+    }
+
+    impl MockRegistrar {
+        fn subgraphs(&self) -> Vec<(String, Option<String>)> {
+            self.subgraphs.lock().unwrap().clone().into_iter().collect()
+        }
+    }
+
+    #[async_trait]
+    impl SubgraphRegistrar for MockRegistrar {
+        async fn create_subgraph(
+            &self,
+            name: SubgraphName,
+        ) -> Result<CreateSubgraphResult, SubgraphRegistrarError> {
+            let mut subgraphs = self.subgraphs.lock().unwrap();
+            subgraphs.entry(name.to_string()).or_default();
+            Ok(CreateSubgraphResult {
+                id: name.to_string(),
+            })
+        }
+
+        async fn create_subgraph_version(
+            &self,
+            name: SubgraphName,
+            hash: DeploymentHash,
+            _: NodeId,
+            _: Option<DeploymentHash>,
+            _: Option<BlockPtr>,
+            _: Option<BlockPtr>,
+            _: bool,
+        ) -> Result<DeploymentLocator, SubgraphRegistrarError> {
+            if hash.as_str() == "QmFail" {
+                return Err(anyhow!("deployment failed").into());
+            }
+            let mut subgraphs = self.subgraphs.lock().unwrap();
+            subgraphs.insert(name.to_string(), Some(hash.to_string()));
+            Ok(DeploymentLocator::new(DeploymentId(1), hash))
+        }
+
+        async fn validate_subgraph(&self, hash: &DeploymentHash) -> SubgraphValidation {
+            let mut validation = SubgraphValidation::new(hash);
+            if hash.as_str() == "QmInvalid" {
+                validation.add(ValidationStage::Resolve, "invalid manifest");
+            }
+            validation
+        }
+
+        async fn remove_subgraph(&self, name: SubgraphName) -> Result<(), SubgraphRegistrarError> {
+            self.subgraphs.lock().unwrap().remove(name.as_str());
+            Ok(())
+        }
+
+        async fn subgraph_versions(
+            &self,
+            name: &SubgraphName,
+        ) -> Result<Option<SubgraphVersions>, SubgraphRegistrarError> {
+            let subgraphs = self.subgraphs.lock().unwrap();
+            Ok(subgraphs
+                .get(name.as_str())
+                .map(|current| SubgraphVersions {
+                    current: current.clone(),
+                    pending: None,
+                    assignments: vec![],
+                }))
+        }
+
+        async fn restore_subgraph_versions(
+            &self,
+            name: SubgraphName,
+            versions: SubgraphVersions,
+        ) -> Result<(), SubgraphRegistrarError> {
+            let mut subgraphs = self.subgraphs.lock().unwrap();
+            subgraphs.insert(name.to_string(), versions.current);
+            Ok(())
+        }
+
+        async fn pause_subgraph(&self, _: &DeploymentHash) -> Result<(), SubgraphRegistrarError> {
+            unimplemented!()
+        }
+
+        async fn resume_subgraph(&self, _: &DeploymentHash) -> Result<(), SubgraphRegistrarError> {
+            unimplemented!()
+        }
+
+        async fn reassign_subgraph(
+            &self,
+            _: &DeploymentHash,
+            _: &NodeId,
+        ) -> Result<(), SubgraphRegistrarError> {
+            unimplemented!()
+        }
+
+        async fn rewind_subgraph(
+            &self,
+            hash: &DeploymentHash,
+            block_ptr_to: BlockPtr,
+        ) -> Result<(), SubgraphRegistrarError> {
+            let subgraphs = self.subgraphs.lock().unwrap();
+            if !subgraphs
+                .values()
+                .any(|current| current.as_deref() == Some(hash.as_str()))
+            {
+                return Err(SubgraphRegistrarError::DeploymentNotFound(hash.to_string()));
+            }
+            let mut rewinds = self.rewinds.lock().unwrap();
+            rewinds.push((hash.to_string(), block_ptr_to));
+            Ok(())
+        }
+    }
+
+    struct NoReload;
+
+    #[async_trait]
+    impl ConfigReloader for NoReload {
+        async fn reload(&self) -> Result<Vec<String>, Error> {
+            Ok(vec![])
+        }
+    }
+
+    fn server_state(subgraphs: &[(&str, &str)]) -> ServerState<MockRegistrar> {
+        let registrar = MockRegistrar::default();
+        for (name, hash) in subgraphs {
+            let mut subgraphs = registrar.subgraphs.lock().unwrap();
+            subgraphs.insert(name.to_string(), Some(hash.to_string()));
+        }
+        ServerState {
+            registrar: Arc::new(registrar),
+            config_reloader: Arc::new(NoReload),
+            http_port: 8000,
+            ws_port: 8001,
+            node_id: NodeId::new("default").unwrap(),
+            logger: Logger::root(slog::Discard, o!()),
+        }
+    }
+
+    fn batch(subgraphs: &[(&str, &str)]) -> SubgraphDeployBatchParams {
+        let subgraphs: Vec<_> = subgraphs
+            .iter()
+            .map(|(name, hash)| serde_json::json!({ "name": name, "ipfs_hash": hash }))
+            .collect();
+        serde_json::from_value(serde_json::json!({ "subgraphs": subgraphs })).unwrap()
+    }
+
+    fn current(subgraphs: &[(&str, &str)]) -> Vec<(String, Option<String>)> {
+        subgraphs
+            .iter()
+            .map(|(name, hash)| (name.to_string(), Some(hash.to_string())))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn deploy_batch() {
+        let state = server_state(&[("a", "QmA1")]);
+        let res = state
+            .deploy_batch_handler(batch(&[("a", "QmA2"), ("b", "QmB")]))
+            .await
+            .unwrap();
+        assert_eq!(false, res["deployed"][0]["created"]);
+        assert_eq!(true, res["deployed"][1]["created"]);
+        assert_eq!(
+            current(&[("a", "QmA2"), ("b", "QmB")]),
+            state.registrar.subgraphs()
+        );
+    }
+
+    #[tokio::test]
+    async fn deploy_batch_validates_everything_first() {
+        let state = server_state(&[("a", "QmA1")]);
+        let res = state
+            .deploy_batch_handler(batch(&[("a", "QmA2"), ("b", "QmInvalid")]))
+            .await;
+        assert!(res.is_err());
+        assert_eq!(current(&[("a", "QmA1")]), state.registrar.subgraphs());
+    }
+
+    #[tokio::test]
+    async fn deploy_batch_rolls_back() {
+        let state = server_state(&[("a", "QmA1"), ("c", "QmC1")]);
+        let res = state
+            .deploy_batch_handler(batch(&[("a", "QmA2"), ("b", "QmB"), ("c", "QmFail")]))
+            .await;
+        match &res {
+            Err(JsonRpcError::Call(CallError::Custom(e))) => {
+                let report: JsonValue = serde_json::from_str(e.data().unwrap().get()).unwrap();
+                assert_eq!(
+                    serde_json::json!({
+                        "failed": "c",
+                        "rolled_back": ["c", "b", "a"],
+                        "not_rolled_back": [],
+                    }),
+                    report
+                );
+            }
+            _ => panic!("expected the batch to fail, but got {:?}", res),
+        }
+        // `b` was created by the batch and is gone again, and `a` and `c`
+        // have the versions they had before
+        assert_eq!(
+            current(&[("a", "QmA1"), ("c", "QmC1")]),
+            state.registrar.subgraphs()
+        );
+    }
+
+    fn rewind(deployment: &str, block_hash: &str, block_number: i32) -> SubgraphRewindParams {
+        serde_json::from_value(serde_json::json!({
+            "deployment": deployment,
+            "block_hash": block_hash,
+            "block_number": block_number,
+        }))
+        .unwrap()
+    }
+
+    fn error_code<T: std::fmt::Debug>(res: JsonRpcResult<T>) -> i32 {
+        match res {
+            Err(JsonRpcError::Call(CallError::Custom(e))) => e.code(),
+            _ => panic!("expected an error, but got {:?}", res),
+        }
+    }
+
+    #[tokio::test]
+    async fn rewind_deployment() {
+        const HASH: &str = "0x8511fa04b64657581e3f00e14543c1d522d5d7e771b54aa3060b662ade47da13";
+
+        let state = server_state(&[("a", "QmA")]);
+        state.rewind_handler(rewind("QmA", HASH, 10)).await.unwrap();
+        assert_eq!(
+            vec![(
+                "QmA".to_string(),
+                BlockPtr::try_from((HASH, 10i64)).unwrap()
+            )],
+            *state.registrar.rewinds.lock().unwrap()
+        );
+
+        // Malformed block hashes are rejected before asking the registrar
+        let res = state.rewind_handler(rewind("QmA", "0xnothex", 10)).await;
+        assert_eq!(
+            ServerState::<MockRegistrar>::REWIND_ERROR as i32,
+            error_code(res)
+        );
+
+        let res = state.rewind_handler(rewind("QmB", HASH, 10)).await;
+        assert_eq!(
+            ServerState::<MockRegistrar>::REWIND_ERROR as i32,
+            error_code(res)
+        );
+        assert_eq!(1, state.registrar.rewinds.lock().unwrap().len());
+    }
+}
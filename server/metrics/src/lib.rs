@@ -7,6 +7,7 @@ use hyper::service::{make_service_fn, service_fn};
 use hyper::{Body, Response, Server};
 use thiserror::Error;
 
+use graph::components::server::tls::{self, ServerTls};
 use graph::prelude::*;
 use graph::prometheus::{Encoder, Registry, TextEncoder};
 
@@ -14,13 +15,14 @@ use graph::prometheus::{Encoder, Registry, TextEncoder};
 #[derive(Debug, Error)]
 pub enum PrometheusMetricsServeError {
     #[error("Bind error: {0}")]
-    BindError(#[from] hyper::Error),
+    BindError(#[from] std::io::Error),
 }
 
 #[derive(Clone)]
 pub struct PrometheusMetricsServer {
     logger: Logger,
     registry: Arc<Registry>,
+    tls: Option<Arc<ServerTls>>,
 }
 
 impl PrometheusMetricsServer {
@@ -28,9 +30,16 @@ impl PrometheusMetricsServer {
         PrometheusMetricsServer {
             logger: logger_factory.component_logger("MetricsServer", None),
             registry,
+            tls: None,
         }
     }
 
+    /// Serve metrics over TLS with `tls`
+    pub fn with_tls(mut self, tls: Option<Arc<ServerTls>>) -> Self {
+        self.tls = tls;
+        self
+    }
+
     /// Creates a new Tokio task that, when spawned, brings up the index node server.
     pub async fn serve(
         &mut self,
@@ -40,7 +49,9 @@ impl PrometheusMetricsServer {
 
         info!(
             logger,
-            "Starting metrics server at: http://localhost:{}", port,
+            "Starting metrics server at: {}://localhost:{}",
+            tls::scheme(&self.tls, "http"),
+            port,
         );
 
         let addr = SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), port);
@@ -66,7 +77,8 @@ impl PrometheusMetricsServer {
             }
         });
 
-        let task = Server::try_bind(&addr.into())?
+        let incoming = tls::incoming(self.logger.clone(), addr.into(), self.tls.clone())?;
+        let task = Server::builder(incoming)
             .serve(new_service)
             .map_err(move |e| error!(logger, "Metrics server error"; "error" => format!("{}", e)));
 
@@ -1,4 +1,5 @@
 use graph::{
+    components::server::tls::{self, ServerTls},
     data::query::QueryTarget,
     prelude::{SubscriptionServer as SubscriptionServerTrait, *},
 };
@@ -17,6 +18,7 @@ pub struct SubscriptionServer<Q, S> {
     logger: Logger,
     graphql_runner: Arc<Q>,
     store: Arc<S>,
+    tls: Option<Arc<ServerTls>>,
 }
 
 impl<Q, S> SubscriptionServer<Q, S>
@@ -29,9 +31,16 @@ where
             logger: logger.new(o!("component" => "SubscriptionServer")),
             graphql_runner,
             store,
+            tls: None,
         }
     }
 
+    /// Accept connections over TLS with `tls`
+    pub fn with_tls(mut self, tls: Option<Arc<ServerTls>>) -> Self {
+        self.tls = tls;
+        self
+    }
+
     async fn subgraph_id_from_url_path(
         store: Arc<S>,
         path: &str,
@@ -102,7 +111,9 @@ where
     async fn serve(self, port: u16) {
         info!(
             self.logger,
-            "Starting GraphQL WebSocket server at: ws://localhost:{}", port
+            "Starting GraphQL WebSocket server at: {}://localhost:{}",
+            tls::scheme(&self.tls, "ws"),
+            port
         );
 
         let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), port);
@@ -110,14 +121,8 @@ where
             .await
             .expect("Failed to bind WebSocket port");
 
-        loop {
-            let stream = match socket.accept().await {
-                Ok((stream, _)) => stream,
-                Err(e) => {
-                    trace!(self.logger, "Connection error: {}", e);
-                    continue;
-                }
-            };
+        let mut connections = tls::connections(self.logger.clone(), socket, self.tls.clone());
+        while let Some(stream) = connections.next().await {
             let logger = self.logger.clone();
             let logger2 = self.logger.clone();
             let graphql_runner = self.graphql_runner.clone();
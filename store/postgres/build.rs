@@ -0,0 +1,39 @@
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// Write the list of migrations with their SQL to `migrations.rs` in
+/// `OUT_DIR` so that `graphman` can show and export pending migrations.
+/// `embed_migrations!` does the same, but does not give access to the SQL
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+
+    let dir = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("migrations");
+    let mut names: Vec<_> = fs::read_dir(&dir)
+        .expect("failed to read migrations")
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| dir.join(name).join("up.sql").exists())
+        .collect();
+    names.sort();
+
+    let mut out = String::from("&[\n");
+    for name in names {
+        // Diesel uses the part of the name before the first `_` without
+        // dashes as the version of a migration
+        let version = name.split('_').next().unwrap().replace('-', "");
+        let up = dir.join(&name).join("up.sql");
+        writeln!(
+            out,
+            "    Migration {{ version: {:?}, name: {:?}, sql: include_str!({:?}) }},",
+            version,
+            name,
+            up.display().to_string()
+        )
+        .unwrap();
+    }
+    out.push(']');
+
+    let dest = Path::new(&env::var("OUT_DIR").unwrap()).join("migrations.rs");
+    fs::write(dest, out).expect("failed to write migrations.rs");
+}
@@ -0,0 +1,3 @@
+alter table subgraphs.subgraph_deployment_assignment
+  drop column blocks_behind,
+  drop column handler_time;
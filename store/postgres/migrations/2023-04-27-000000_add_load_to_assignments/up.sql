@@ -0,0 +1,3 @@
+alter table subgraphs.subgraph_deployment_assignment
+  add column blocks_behind int4 not null default 0,
+  add column handler_time  float8 not null default 0;
@@ -0,0 +1 @@
+drop table subgraphs.node_lease;
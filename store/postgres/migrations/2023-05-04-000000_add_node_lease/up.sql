@@ -0,0 +1,4 @@
+create table subgraphs.node_lease (
+  node_id    text primary key,
+  renewed_at timestamptz not null
+);
@@ -0,0 +1,5 @@
+alter table subgraphs.subgraph_deployment
+      drop column retry_max_attempts,
+      drop column retry_base_secs,
+      drop column retry_ceiling_secs,
+      drop column retry_count;
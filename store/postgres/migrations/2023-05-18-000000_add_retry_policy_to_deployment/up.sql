@@ -0,0 +1,5 @@
+alter table subgraphs.subgraph_deployment
+  add column retry_max_attempts int4,
+  add column retry_base_secs int4,
+  add column retry_ceiling_secs int4,
+  add column retry_count int4 not null default 0;
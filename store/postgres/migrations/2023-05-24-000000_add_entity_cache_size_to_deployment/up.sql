@@ -0,0 +1,2 @@
+alter table subgraphs.subgraph_deployment
+  add column entity_cache_size_kb int4;
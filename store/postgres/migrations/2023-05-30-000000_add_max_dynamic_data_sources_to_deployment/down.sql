@@ -0,0 +1,2 @@
+alter table subgraphs.subgraph_deployment
+      drop column max_dynamic_data_sources;
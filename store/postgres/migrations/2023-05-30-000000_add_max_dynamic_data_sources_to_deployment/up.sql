@@ -0,0 +1,2 @@
+alter table subgraphs.subgraph_deployment
+  add column max_dynamic_data_sources int4;
@@ -0,0 +1,2 @@
+alter table subgraphs.subgraph_deployment
+  add column max_blocks_per_second int4;
@@ -0,0 +1 @@
+drop table ipfs_cache;
@@ -0,0 +1,6 @@
+create table ipfs_cache (
+    path        text primary key,
+    data        bytea not null,
+    created_at  timestamptz not null default now(),
+    accessed_at timestamptz not null default now()
+);
@@ -0,0 +1,2 @@
+drop table subgraphs.entity_webhook_delivery;
+drop table subgraphs.entity_webhook;
@@ -0,0 +1,29 @@
+-- Webhooks and their pending deliveries live in the same shard as the
+-- deployment whose entity changes they are for
+create table subgraphs.entity_webhook(
+    id           serial primary key,
+    deployment   int not null
+                     references subgraphs.subgraph_deployment(id)
+                         on delete cascade,
+    url          text not null,
+    entity_types text[] not null,
+    secret       text not null,
+    created_at   timestamptz not null default now()
+);
+
+create index entity_webhook_deployment
+    on subgraphs.entity_webhook(deployment);
+
+create table subgraphs.entity_webhook_delivery(
+    id           bigserial primary key,
+    webhook      int not null
+                     references subgraphs.entity_webhook(id)
+                         on delete cascade,
+    block_number int not null,
+    payload      jsonb not null,
+    attempts     int not null default 0,
+    next_attempt timestamptz not null default now()
+);
+
+create index entity_webhook_delivery_next_attempt
+    on subgraphs.entity_webhook_delivery(next_attempt);
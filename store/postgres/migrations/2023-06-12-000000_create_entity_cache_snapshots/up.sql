@@ -0,0 +1,12 @@
+-- The keys of the entities that a deployment used most often when it last
+-- stopped, stored as parallel arrays. Only keys are stored; the entities
+-- are loaded from the deployment when it starts again
+create table subgraphs.entity_cache_snapshot(
+    deployment        int primary key
+                          references subgraphs.subgraph_deployment(id)
+                              on delete cascade,
+    entity_types      text[] not null,
+    entity_ids        text[] not null,
+    causality_regions int[] not null,
+    created_at        timestamptz not null default now()
+);
@@ -0,0 +1,2 @@
+alter table subgraphs.subgraph_deployment
+  add column poi_transition_version int4;
@@ -0,0 +1,2 @@
+alter table subgraphs.subgraph_deployment_assignment
+  add column blocks_per_second float8 not null default 0;
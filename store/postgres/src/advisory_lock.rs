@@ -13,8 +13,10 @@
 //!           shard
 //!   * 2, n: to lock the deployment with id n to make sure only one write
 //!           happens to it
+//!   * 4, n: to lock the entity webhook with id n while a node claims its
+//!           deliveries
 
-use diesel::sql_types::Bool;
+use diesel::sql_types::{Array, Bool, Integer};
 use diesel::{sql_query, PgConnection, RunQueryDsl};
 use graph::prelude::StoreError;
 
@@ -68,6 +70,7 @@ impl Scope {
 const COPY: Scope = Scope { id: 1 };
 const WRITE: Scope = Scope { id: 2 };
 const PRUNE: Scope = Scope { id: 3 };
+const WEBHOOK: Scope = Scope { id: 4 };
 
 /// Get a lock for running migrations. Blocks until we get the lock.
 pub(crate) fn lock_migration(conn: &PgConnection) -> Result<(), StoreError> {
@@ -121,3 +124,23 @@ pub(crate) fn try_lock_pruning(conn: &PgConnection, site: &Site) -> Result<bool,
 pub(crate) fn unlock_pruning(conn: &PgConnection, site: &Site) -> Result<(), StoreError> {
     PRUNE.unlock(conn, site.id)
 }
+
+/// Try to lock the entity webhooks `ids` until the end of the current
+/// transaction. Return the ids of the webhooks that we got the lock for
+pub(crate) fn try_lock_webhooks(conn: &PgConnection, ids: &[i32]) -> Result<Vec<i32>, StoreError> {
+    #[derive(QueryableByName)]
+    struct Locked {
+        #[sql_type = "Integer"]
+        id: i32,
+    }
+
+    sql_query(format!(
+        "select id from unnest($1::int4[]) as id \
+          where pg_try_advisory_xact_lock({}, id)",
+        WEBHOOK.id
+    ))
+    .bind::<Array<Integer>, _>(ids)
+    .load::<Locked>(conn)
+    .map(|locked| locked.into_iter().map(|locked| locked.id).collect())
+    .map_err(StoreError::from)
+}
@@ -287,6 +287,32 @@ impl<'a> BlockRangeColumn<'a> {
         }
     }
 
+    /// Output an expression for the block at which the row was written
+    pub(crate) fn changed_at(&self, out: &mut AstPass<Pg>) -> QueryResult<()> {
+        match self {
+            BlockRangeColumn::Mutable { .. } => {
+                out.push_sql("lower(");
+                out.push_identifier(BLOCK_RANGE_COLUMN)?;
+                out.push_sql(")");
+                Ok(())
+            }
+            BlockRangeColumn::Immutable { .. } => out.push_identifier(BLOCK_COLUMN),
+        }
+    }
+
+    /// Output an expression that matches all rows that have been changed
+    /// before `block` (exclusive)
+    pub(crate) fn changed_before(&self, out: &mut AstPass<Pg>) -> QueryResult<()> {
+        let block = match self {
+            BlockRangeColumn::Mutable { block, .. } | BlockRangeColumn::Immutable { block, .. } => {
+                block
+            }
+        };
+        self.changed_at(out)?;
+        out.push_sql(" < ");
+        out.push_bind_param::<Integer, _>(block)
+    }
+
     /// Output an expression that matches all rows that have been changed
     /// after `block` (inclusive)
     pub(crate) fn changed_since(&self, out: &mut AstPass<Pg>) -> QueryResult<()> {
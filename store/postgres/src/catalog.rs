@@ -1,4 +1,4 @@
-use diesel::sql_types::{Bool, Integer};
+use diesel::sql_types::{BigInt, Bool, Integer};
 use diesel::{connection::SimpleConnection, prelude::RunQueryDsl, select};
 use diesel::{insert_into, OptionalExtension};
 use diesel::{pg::PgConnection, sql_query};
@@ -400,6 +400,21 @@ pub fn migration_count(conn: &PgConnection) -> Result<i64, StoreError> {
     m::table.count().get_result(conn).map_err(StoreError::from)
 }
 
+/// The versions of all migrations that have been applied
+pub fn applied_migrations(conn: &PgConnection) -> Result<HashSet<String>, StoreError> {
+    use __diesel_schema_migrations as m;
+
+    if !table_exists(conn, NAMESPACE_PUBLIC, &MIGRATIONS_TABLE)? {
+        return Ok(HashSet::new());
+    }
+
+    m::table
+        .select(m::version)
+        .load::<String>(conn)
+        .map(|versions| versions.into_iter().collect())
+        .map_err(StoreError::from)
+}
+
 pub fn account_like(conn: &PgConnection, site: &Site) -> Result<HashSet<String>, StoreError> {
     use table_stats as ts;
     let names = ts::table
@@ -654,6 +669,48 @@ pub(crate) fn indexes_for_table(
 
     Ok(results.into_iter().map(|i| i.def).collect())
 }
+/// Size and usage statistics for an index
+#[derive(Debug, Queryable, QueryableByName)]
+pub struct IndexUsage {
+    #[sql_type = "Text"]
+    pub table_name: String,
+    #[sql_type = "Text"]
+    pub index_name: String,
+    /// The definition of the index as it appears in `pg_indexes.indexdef`
+    #[sql_type = "Text"]
+    pub defn: String,
+    /// The size of the index in bytes
+    #[sql_type = "BigInt"]
+    pub size: i64,
+    /// How often the index was used in a scan since statistics were last
+    /// reset. This only counts scans on the database we are connected to
+    /// and not on any of its replicas
+    #[sql_type = "BigInt"]
+    pub scans: i64,
+}
+
+pub(crate) fn index_usage(
+    conn: &PgConnection,
+    schema_name: &str,
+) -> Result<Vec<IndexUsage>, StoreError> {
+    let query = "
+        select
+            s.relname as table_name,
+            s.indexrelname as index_name,
+            pg_get_indexdef(s.indexrelid) as defn,
+            pg_relation_size(s.indexrelid) as size,
+            s.idx_scan as scans
+        from
+            pg_stat_user_indexes s
+        where
+            s.schemaname = $1
+        order by s.relname, s.indexrelname";
+    sql_query(query)
+        .bind::<Text, _>(schema_name)
+        .load::<IndexUsage>(conn)
+        .map_err(Into::into)
+}
+
 pub(crate) fn drop_index(
     conn: &PgConnection,
     schema_name: &str,
@@ -661,8 +718,6 @@ pub(crate) fn drop_index(
 ) -> Result<(), StoreError> {
     let query = format!("drop index concurrently {schema_name}.{index_name}");
     sql_query(query)
-        .bind::<Text, _>(schema_name)
-        .bind::<Text, _>(index_name)
         .execute(conn)
         .map_err::<StoreError, _>(Into::into)?;
     Ok(())
@@ -859,3 +914,33 @@ pub(crate) fn needs_autoanalyze(
 
     Ok(tables)
 }
+
+/// Return the total size in bytes, including indexes and TOAST data, of
+/// each table in `namespace`, keyed by the name of the table
+pub(crate) fn table_sizes(
+    conn: &PgConnection,
+    namespace: &Namespace,
+) -> Result<HashMap<String, i64>, StoreError> {
+    const QUERY: &str = "select c.relname as tablename, \
+                                pg_total_relation_size(c.oid)::int8 as total_bytes \
+                           from pg_class c, pg_namespace n \
+                          where c.relnamespace = n.oid \
+                            and c.relkind = 'r' \
+                            and n.nspname = $1";
+
+    #[derive(QueryableByName)]
+    struct TableSize {
+        #[sql_type = "Text"]
+        tablename: String,
+        #[sql_type = "BigInt"]
+        total_bytes: i64,
+    }
+
+    let sizes = sql_query(QUERY)
+        .bind::<Text, _>(namespace.as_str())
+        .get_results::<TableSize>(conn)?
+        .into_iter()
+        .map(|size| (size.tablename, size.total_bytes))
+        .collect();
+    Ok(sizes)
+}
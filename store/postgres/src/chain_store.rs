@@ -922,6 +922,33 @@ mod data {
             }
         }
 
+        /// Delete all blocks with a number greater than `block`
+        pub(super) fn delete_blocks_after(
+            &self,
+            conn: &PgConnection,
+            chain: &str,
+            block: i64,
+        ) -> Result<usize, Error> {
+            match self {
+                Storage::Shared => {
+                    use public::ethereum_blocks as b;
+
+                    diesel::delete(b::table)
+                        .filter(b::network_name.eq(chain))
+                        .filter(b::number.gt(block))
+                        .execute(conn)
+                        .map_err(Error::from)
+                }
+                Storage::Private(Schema { blocks, .. }) => {
+                    let query = format!("delete from {} where number > $1", blocks.qname);
+                    sql_query(query)
+                        .bind::<BigInt, _>(block)
+                        .execute(conn)
+                        .map_err(Error::from)
+                }
+            }
+        }
+
         pub(super) fn delete_blocks_by_hash(
             &self,
             conn: &PgConnection,
@@ -1551,6 +1578,34 @@ impl ChainStore {
             .delete_blocks_by_hash(&conn, &self.chain, block_hashes)
     }
 
+    /// Move the chain head back to `ptr` and remove all blocks after it
+    /// from the block cache, for example, because a provider served blocks
+    /// from a bad fork. The head cursor is cleared so that block ingestion
+    /// starts again from the new head. Returns the number of blocks that
+    /// were removed
+    pub fn rewind_chain_head(&self, ptr: &BlockPtr) -> Result<usize, Error> {
+        use public::ethereum_networks as n;
+
+        let conn = self.get_conn()?;
+        let hash = ptr.hash_hex();
+        let number = ptr.number as i64;
+        let deleted = conn.transaction(|| -> Result<usize, Error> {
+            let deleted = self
+                .storage
+                .delete_blocks_after(&conn, &self.chain, number)?;
+            update(n::table.filter(n::name.eq(&self.chain)))
+                .set((
+                    n::head_block_hash.eq(&hash),
+                    n::head_block_number.eq(number),
+                    n::head_block_cursor.eq(None::<String>),
+                ))
+                .execute(&conn)?;
+            Ok(deleted)
+        })?;
+        self.chain_head_update_sender.send(&hash, number)?;
+        Ok(deleted)
+    }
+
     pub fn truncate_block_cache(&self) -> Result<(), StoreError> {
         let conn = self.get_conn()?;
         self.storage.truncate_block_cache(&conn)?;
@@ -255,6 +255,7 @@ pub struct ConnectionPool {
     logger: Logger,
     pub shard: Shard,
     state_tracker: PoolStateTracker,
+    coord: Arc<PoolCoordinator>,
 }
 
 impl fmt::Debug for ConnectionPool {
@@ -346,7 +347,7 @@ impl ConnectionPool {
                 if pool_name.is_replica() {
                     PoolState::Ready(Arc::new(pool))
                 } else {
-                    PoolState::Created(Arc::new(pool), coord)
+                    PoolState::Created(Arc::new(pool), coord.cheap_clone())
                 }
             }
         };
@@ -355,9 +356,46 @@ impl ConnectionPool {
             logger: logger.clone(),
             shard,
             state_tracker,
+            coord,
         }
     }
 
+    /// Change the number of connections in this pool. Since the size of a
+    /// pool is fixed when it is created, this replaces the pool with a new
+    /// one. Connections that are in use at that point are closed when they
+    /// are returned. Pools can not be enabled or disabled this way
+    pub fn resize(&self, pool_size: u32, fdw_pool_size: Option<u32>) -> Result<(), StoreError> {
+        if pool_size == 0 {
+            return Err(constraint_violation!(
+                "the pool for shard {} can not be disabled while it is in use",
+                self.shard
+            ));
+        }
+
+        let mut guard = self.inner.lock(&self.logger);
+        let old = match &*guard {
+            PoolState::Created(pool, _) | PoolState::Ready(pool) => pool.clone(),
+            PoolState::Disabled => {
+                return Err(constraint_violation!(
+                    "the pool for shard {} is disabled and can not be resized",
+                    self.shard
+                ))
+            }
+        };
+        let new = Arc::new(old.resize(
+            &self.logger,
+            pool_size,
+            fdw_pool_size,
+            self.state_tracker.clone(),
+        ));
+        match &mut *guard {
+            PoolState::Created(pool, _) | PoolState::Ready(pool) => *pool = new.clone(),
+            PoolState::Disabled => unreachable!("we checked that the pool is not disabled"),
+        }
+        self.coord.replace_pool(&old, new);
+        Ok(())
+    }
+
     /// This is only used for `graphman` to ensure it doesn't run migrations
     /// or other setup steps
     pub fn skip_setup(&self) {
@@ -475,6 +513,21 @@ impl ConnectionPool {
         ForeignServer::new(pool.shard.clone(), &pool.postgres_url).map_err(|e| e.into())
     }
 
+    /// The migrations that have not been applied to this pool's database.
+    /// This uses a connection of its own rather than one from the pool,
+    /// since getting a connection from the pool sets it up and with that
+    /// runs all pending migrations
+    pub fn pending_migrations(&self) -> Result<Vec<&'static Migration>, StoreError> {
+        let postgres_url = match &*self.inner.lock(&self.logger) {
+            PoolState::Created(pool, _) | PoolState::Ready(pool) => pool.postgres_url.clone(),
+            PoolState::Disabled => return Err(StoreError::DatabaseDisabled),
+        };
+        let conn = PgConnection::establish(&postgres_url).map_err(|e| {
+            StoreError::Unknown(anyhow!("could not connect to shard {}: {}", self.shard, e))
+        })?;
+        pending_migrations(&conn)
+    }
+
     /// Check that we can connect to the database
     pub fn check(&self) -> bool {
         true
@@ -691,6 +744,8 @@ impl HandleEvent for EventHandler {
 pub struct PoolInner {
     logger: Logger,
     pub shard: Shard,
+    pool_name: String,
+    registry: Arc<MetricsRegistry>,
     pool: Pool<ConnectionManager<PgConnection>>,
     // A separate pool for connections that will use foreign data wrappers.
     // Once such a connection accesses a foreign table, Postgres keeps a
@@ -713,7 +768,7 @@ pub struct PoolInner {
     // is sized acording to the DB connection pool size.
     query_semaphore: Arc<tokio::sync::Semaphore>,
     semaphore_wait_stats: Arc<RwLock<MovingStats>>,
-    semaphore_wait_gauge: Box<Gauge>,
+    semaphore_wait_gauge: Gauge,
 }
 
 impl PoolInner {
@@ -795,7 +850,7 @@ impl PoolInner {
         info!(logger_store, "Pool successfully connected to Postgres");
 
         let semaphore_wait_gauge = registry
-            .new_gauge(
+            .global_gauge(
                 "query_semaphore_wait_ms",
                 "Moving average of time spent on waiting for postgres query semaphore",
                 const_labels,
@@ -806,6 +861,8 @@ impl PoolInner {
         PoolInner {
             logger: logger_pool,
             shard,
+            pool_name: pool_name.to_string(),
+            registry,
             postgres_url,
             pool,
             fdw_pool,
@@ -817,6 +874,27 @@ impl PoolInner {
         }
     }
 
+    /// A pool like this one, but with different sizes
+    fn resize(
+        &self,
+        logger: &Logger,
+        pool_size: u32,
+        fdw_pool_size: Option<u32>,
+        state_tracker: PoolStateTracker,
+    ) -> PoolInner {
+        info!(self.logger, "Resizing connection pool"; "conn_pool_size" => pool_size);
+        PoolInner::create(
+            self.shard.clone(),
+            &self.pool_name,
+            self.postgres_url.clone(),
+            pool_size,
+            fdw_pool_size,
+            logger,
+            self.registry.cheap_clone(),
+            state_tracker,
+        )
+    }
+
     /// Execute a closure with a connection to the database.
     ///
     /// # API
@@ -1010,7 +1088,13 @@ impl PoolInner {
         // in the database instead of just in memory
         let result = pool
             .configure_fdw(coord.servers.as_ref())
-            .and_then(|()| migrate_schema(&pool.logger, &conn))
+            .and_then(|()| {
+                if ENV_VARS.store.migrations_externally_managed {
+                    check_migrations(&pool.logger, &conn).map(|()| false)
+                } else {
+                    migrate_schema(&pool.logger, &conn)
+                }
+            })
             .and_then(|had_migrations| {
                 if had_migrations {
                     coord.propagate_schema_change(&self.shard)
@@ -1107,6 +1191,71 @@ impl PoolInner {
 
 embed_migrations!("./migrations");
 
+/// A schema migration and the SQL that applies it
+pub struct Migration {
+    /// The version under which Diesel records that the migration ran
+    pub version: &'static str,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+impl Migration {
+    /// The SQL to apply this migration out of band. Like Diesel, it runs
+    /// the migration in a transaction and records it as applied
+    pub fn script(&self) -> String {
+        format!(
+            "-- {name}\n\
+             begin;\n\
+             {sql}\n\
+             insert into public.__diesel_schema_migrations(version) values ('{version}');\n\
+             commit;\n",
+            name = self.name,
+            sql = self.sql.trim_end(),
+            version = self.version
+        )
+    }
+}
+
+/// All migrations, in the order in which they have to be applied. The list
+/// is generated by `build.rs` from the same directory as
+/// `embedded_migrations`
+const MIGRATIONS: &[Migration] = include!(concat!(env!("OUT_DIR"), "/migrations.rs"));
+
+/// The SQL that creates the table in which Diesel records migrations, for
+/// databases that have not had any migrations yet
+pub const MIGRATIONS_TABLE_DDL: &str = "create table if not exists \
+     public.__diesel_schema_migrations(\
+     version varchar(50) primary key not null, \
+     run_on timestamp not null default current_timestamp);";
+
+fn pending_migrations(conn: &PgConnection) -> Result<Vec<&'static Migration>, StoreError> {
+    let applied = catalog::applied_migrations(conn)?;
+    Ok(MIGRATIONS
+        .iter()
+        .filter(|migration| !applied.contains(migration.version))
+        .collect())
+}
+
+/// Check that all migrations have been applied when migrations are
+/// managed outside of `graph-node`
+fn check_migrations(logger: &Logger, conn: &PgConnection) -> Result<(), StoreError> {
+    let pending = pending_migrations(conn)?;
+    if pending.is_empty() {
+        info!(
+            logger,
+            "Migrations are managed externally and none are pending"
+        );
+        return Ok(());
+    }
+    let names: Vec<_> = pending.iter().map(|migration| migration.name).collect();
+    Err(StoreError::Unknown(anyhow!(
+        "migrations are managed externally (GRAPH_MIGRATIONS_EXTERNALLY_MANAGED) but {} \
+         migrations are pending: {}. Use `graphman database migrate --output` to get their SQL",
+        pending.len(),
+        names.join(", ")
+    )))
+}
+
 /// Run all schema migrations.
 ///
 /// When multiple `graph-node` processes start up at the same time, we ensure
@@ -1224,6 +1373,17 @@ impl PoolCoordinator {
         Ok(())
     }
 
+    /// Use `new` instead of `old` when propagating schema changes if we
+    /// track `old`, i.e., if `old` is the main pool of its shard
+    fn replace_pool(&self, old: &Arc<PoolInner>, new: Arc<PoolInner>) {
+        let mut pools = self.pools.lock().unwrap();
+        if let Some(pool) = pools.get_mut(&new.shard) {
+            if Arc::ptr_eq(pool, old) {
+                *pool = new;
+            }
+        }
+    }
+
     pub fn pools(&self) -> Vec<Arc<PoolInner>> {
         self.pools.lock().unwrap().values().cloned().collect()
     }
@@ -1,11 +1,11 @@
 //! Utilities for dealing with deployment metadata. Any connection passed
 //! into these methods must be for the shard that holds the actual
 //! deployment data and metadata
-use crate::{advisory_lock, detail::GraphNodeVersion, primary::DeploymentId};
+use crate::{advisory_lock, detail::GraphNodeVersion, primary::DeploymentId, DeploymentProperties};
 use diesel::{
     connection::SimpleConnection,
     dsl::{count, delete, insert_into, select, sql, update},
-    sql_types::Integer,
+    sql_types::{Array, Integer},
 };
 use diesel::{expression::SqlLiteral, pg::PgConnection, sql_types::Numeric};
 use diesel::{
@@ -15,7 +15,9 @@ use diesel::{
 };
 use graph::{blockchain::block_stream::FirehoseCursor, data::subgraph::schema::SubgraphError};
 use graph::{
-    components::store::EntityType,
+    components::store::{EntityKey, EntityType, RetryPolicy},
+    components::subgraph::ProofOfIndexingVersion,
+    data_source::CausalityRegion,
     prelude::{
         anyhow, bigdecimal::ToPrimitive, hex, web3::types::H256, BigDecimal, BlockNumber, BlockPtr,
         DeploymentHash, DeploymentState, Schema, StoreError,
@@ -24,8 +26,10 @@ use graph::{
 use graph::{
     data::subgraph::{
         schema::{DeploymentCreate, SubgraphManifestEntity},
+        status::RawManifest,
         SubgraphFeature,
     },
+    semver::Version,
     util::backoff::ExponentialBackoff,
 };
 use stable_hash_legacy::crypto::SetHasher;
@@ -144,6 +148,15 @@ table! {
         current_reorg_depth -> Integer,
         max_reorg_depth -> Integer,
         firehose_cursor -> Nullable<Text>,
+        maintenance_message -> Nullable<Text>,
+        retry_max_attempts -> Nullable<Integer>,
+        retry_base_secs -> Nullable<Integer>,
+        retry_ceiling_secs -> Nullable<Integer>,
+        retry_count -> Integer,
+        entity_cache_size_kb -> Nullable<Integer>,
+        max_dynamic_data_sources -> Nullable<Integer>,
+        max_blocks_per_second -> Nullable<Integer>,
+        poi_transition_version -> Nullable<Integer>,
     }
 }
 
@@ -349,6 +362,34 @@ impl ManifestInfo {
     }
 }
 
+/// Return what deployment rules can match on for the deployment `site`
+pub fn properties(conn: &PgConnection, site: &Site) -> Result<DeploymentProperties, StoreError> {
+    use subgraph_manifest as sm;
+
+    let (schema, features, raw_yaml): (String, Vec<String>, Option<String>) = sm::table
+        .select((sm::schema, sm::features, sm::raw_yaml))
+        .filter(sm::id.eq(site.id))
+        .first(conn)?;
+    let schema = Schema::parse(schema.as_str(), site.deployment.clone())?;
+    Ok(DeploymentProperties::new(
+        &features,
+        raw_yaml.as_deref(),
+        &schema,
+    ))
+}
+
+/// Return the GraphQL schema and the manifest of the deployment `site` as
+/// they were deployed
+pub fn raw_manifest(conn: &PgConnection, site: &Site) -> Result<RawManifest, StoreError> {
+    use subgraph_manifest as sm;
+
+    let (schema, manifest) = sm::table
+        .select((sm::schema, sm::raw_yaml))
+        .filter(sm::id.eq(site.id))
+        .first::<(String, Option<String>)>(conn)?;
+    Ok(RawManifest { schema, manifest })
+}
+
 // Return how many blocks of history this subgraph should keep
 pub fn history_blocks(conn: &PgConnection, site: &Site) -> Result<BlockNumber, StoreError> {
     use subgraph_manifest as sm;
@@ -638,6 +679,7 @@ pub fn state(conn: &PgConnection, id: DeploymentHash) -> Result<DeploymentState,
             d::latest_ethereum_block_number,
             d::latest_ethereum_block_hash,
             d::earliest_block_number,
+            d::maintenance_message,
         ))
         .first::<(
             String,
@@ -646,6 +688,7 @@ pub fn state(conn: &PgConnection, id: DeploymentHash) -> Result<DeploymentState,
             Option<BigDecimal>,
             Option<Vec<u8>>,
             BlockNumber,
+            Option<String>,
         )>(conn)
         .optional()?
     {
@@ -660,6 +703,7 @@ pub fn state(conn: &PgConnection, id: DeploymentHash) -> Result<DeploymentState,
             latest_block_number,
             latest_block_hash,
             earliest_block_number,
+            maintenance,
         )) => {
             let reorg_count = convert_to_u32(Some(reorg_count), "reorg_count", id.as_str())?;
             let max_reorg_depth =
@@ -684,11 +728,279 @@ pub fn state(conn: &PgConnection, id: DeploymentHash) -> Result<DeploymentState,
                 max_reorg_depth,
                 latest_block,
                 earliest_block_number,
+                maintenance,
             })
         }
     }
 }
 
+/// Put the deployment into maintenance mode with the given `message` shown
+/// to queries, or take it out of maintenance mode if `message` is `None`
+pub fn set_maintenance(
+    conn: &PgConnection,
+    site: &Site,
+    message: Option<&str>,
+) -> Result<(), StoreError> {
+    use subgraph_deployment as d;
+
+    update(d::table.filter(d::id.eq(site.id)))
+        .set(d::maintenance_message.eq(message))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(StoreError::from)
+}
+
+/// Return the policy for retrying the deployment after non-deterministic
+/// errors
+pub fn retry_policy(conn: &PgConnection, site: &Site) -> Result<RetryPolicy, StoreError> {
+    use subgraph_deployment as d;
+
+    let (max_attempts, base, ceiling) = d::table
+        .filter(d::id.eq(site.id))
+        .select((
+            d::retry_max_attempts,
+            d::retry_base_secs,
+            d::retry_ceiling_secs,
+        ))
+        .first::<(Option<i32>, Option<i32>, Option<i32>)>(conn)?;
+    let secs = |secs: Option<i32>| secs.map(|secs| Duration::from_secs(secs.max(0) as u64));
+    Ok(RetryPolicy {
+        max_attempts: max_attempts.map(|n| n.max(0) as u32),
+        base: secs(base),
+        ceiling: secs(ceiling),
+    })
+}
+
+/// Set the policy for retrying the deployment after non-deterministic
+/// errors. Fields of `policy` that are `None` revert to the defaults
+pub fn set_retry_policy(
+    conn: &PgConnection,
+    site: &Site,
+    policy: &RetryPolicy,
+) -> Result<(), StoreError> {
+    use subgraph_deployment as d;
+
+    let secs = |duration: Option<Duration>| duration.map(|duration| duration.as_secs() as i32);
+    update(d::table.filter(d::id.eq(site.id)))
+        .set((
+            d::retry_max_attempts.eq(policy.max_attempts.map(|n| n as i32)),
+            d::retry_base_secs.eq(secs(policy.base)),
+            d::retry_ceiling_secs.eq(secs(policy.ceiling)),
+        ))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(StoreError::from)
+}
+
+/// Record how many times the deployment has been retried since it last
+/// failed with a non-deterministic error
+pub fn set_retry_count(conn: &PgConnection, site: &Site, count: u32) -> Result<(), StoreError> {
+    use subgraph_deployment as d;
+
+    update(d::table.filter(d::id.eq(site.id)))
+        .set(d::retry_count.eq(count as i32))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(StoreError::from)
+}
+
+/// Return how many times the deployment has been retried since it last
+/// failed with a non-deterministic error
+pub fn retry_count(conn: &PgConnection, site: &Site) -> Result<u32, StoreError> {
+    use subgraph_deployment as d;
+
+    let count = d::table
+        .filter(d::id.eq(site.id))
+        .select(d::retry_count)
+        .first::<i32>(conn)?;
+    Ok(count.max(0) as u32)
+}
+
+/// Return the size limit of the entity cache of the deployment in
+/// kilobytes, or `None` if it uses the default from
+/// `GRAPH_ENTITY_CACHE_SIZE`
+pub fn entity_cache_size(conn: &PgConnection, site: &Site) -> Result<Option<usize>, StoreError> {
+    use subgraph_deployment as d;
+
+    d::table
+        .filter(d::id.eq(site.id))
+        .select(d::entity_cache_size_kb)
+        .first::<Option<i32>>(conn)
+        .map(|size| size.map(|size| size.max(0) as usize))
+        .map_err(StoreError::from)
+}
+
+/// Set the size limit of the entity cache of the deployment in kilobytes.
+/// Passing `None` reverts to the default
+pub fn set_entity_cache_size(
+    conn: &PgConnection,
+    site: &Site,
+    size_kb: Option<usize>,
+) -> Result<(), StoreError> {
+    use subgraph_deployment as d;
+
+    update(d::table.filter(d::id.eq(site.id)))
+        .set(d::entity_cache_size_kb.eq(size_kb.map(|size| size as i32)))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(StoreError::from)
+}
+
+/// Remember `keys` as the entities that the deployment used most often,
+/// replacing any earlier snapshot
+pub fn save_entity_cache_snapshot(
+    conn: &PgConnection,
+    site: &Site,
+    keys: &[EntityKey],
+) -> Result<(), StoreError> {
+    let entity_types: Vec<_> = keys
+        .iter()
+        .map(|key| key.entity_type.as_str().to_string())
+        .collect();
+    let entity_ids: Vec<_> = keys
+        .iter()
+        .map(|key| key.entity_id.as_str().to_string())
+        .collect();
+    let causality_regions: Vec<_> = keys.iter().map(|key| key.causality_region).collect();
+
+    sql_query(
+        "insert into subgraphs.entity_cache_snapshot
+                (deployment, entity_types, entity_ids, causality_regions)
+         values ($1, $2, $3, $4)
+         on conflict(deployment) do update
+            set entity_types = excluded.entity_types,
+                entity_ids = excluded.entity_ids,
+                causality_regions = excluded.causality_regions,
+                created_at = now()",
+    )
+    .bind::<Integer, _>(site.id)
+    .bind::<Array<Text>, _>(entity_types)
+    .bind::<Array<Text>, _>(entity_ids)
+    .bind::<Array<Integer>, _>(causality_regions)
+    .execute(conn)
+    .map(|_| ())
+    .map_err(StoreError::from)
+}
+
+/// The keys of the entities that the deployment used most often when it
+/// last stopped, most frequently used first
+pub fn entity_cache_snapshot(
+    conn: &PgConnection,
+    site: &Site,
+) -> Result<Vec<EntityKey>, StoreError> {
+    #[derive(QueryableByName)]
+    struct Snapshot {
+        #[sql_type = "Array<Text>"]
+        entity_types: Vec<String>,
+        #[sql_type = "Array<Text>"]
+        entity_ids: Vec<String>,
+        #[sql_type = "Array<Integer>"]
+        causality_regions: Vec<CausalityRegion>,
+    }
+
+    let snapshot = sql_query(
+        "select entity_types, entity_ids, causality_regions
+           from subgraphs.entity_cache_snapshot
+          where deployment = $1",
+    )
+    .bind::<Integer, _>(site.id)
+    .get_result::<Snapshot>(conn)
+    .optional()?;
+
+    Ok(snapshot
+        .map(|snapshot| {
+            snapshot
+                .entity_types
+                .into_iter()
+                .zip(snapshot.entity_ids)
+                .zip(snapshot.causality_regions)
+                .map(|((entity_type, entity_id), causality_region)| EntityKey {
+                    entity_type: EntityType::new(entity_type),
+                    entity_id: entity_id.into(),
+                    causality_region,
+                })
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Return how many dynamic data sources the deployment may create, or
+/// `None` if there is no limit for the deployment
+pub fn max_dynamic_data_sources(
+    conn: &PgConnection,
+    site: &Site,
+) -> Result<Option<usize>, StoreError> {
+    use subgraph_deployment as d;
+
+    d::table
+        .filter(d::id.eq(site.id))
+        .select(d::max_dynamic_data_sources)
+        .first::<Option<i32>>(conn)
+        .map(|max| max.map(|max| max.max(0) as usize))
+        .map_err(StoreError::from)
+}
+
+/// Set how many dynamic data sources the deployment may create. Passing
+/// `None` removes the limit
+pub fn set_max_dynamic_data_sources(
+    conn: &PgConnection,
+    site: &Site,
+    max: Option<usize>,
+) -> Result<(), StoreError> {
+    use subgraph_deployment as d;
+
+    update(d::table.filter(d::id.eq(site.id)))
+        .set(d::max_dynamic_data_sources.eq(max.map(|max| max as i32)))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(StoreError::from)
+}
+
+/// Return how many blocks per second the deployment may process while it
+/// is catching up, or `None` if that is not limited
+pub fn max_blocks_per_second(conn: &PgConnection, site: &Site) -> Result<Option<u32>, StoreError> {
+    use subgraph_deployment as d;
+
+    d::table
+        .filter(d::id.eq(site.id))
+        .select(d::max_blocks_per_second)
+        .first::<Option<i32>>(conn)
+        .map(|max| max.filter(|max| *max > 0).map(|max| max as u32))
+        .map_err(StoreError::from)
+}
+
+/// Return the version of the POI that the deployment computes in addition
+/// to its own version during a transition, if any
+pub fn poi_transition_version(
+    conn: &PgConnection,
+    site: &Site,
+) -> Result<Option<ProofOfIndexingVersion>, StoreError> {
+    use subgraph_deployment as d;
+
+    d::table
+        .filter(d::id.eq(site.id))
+        .select(d::poi_transition_version)
+        .first::<Option<i32>>(conn)
+        .map(|version| version.and_then(ProofOfIndexingVersion::from_number))
+        .map_err(StoreError::from)
+}
+
+/// Set how many blocks per second the deployment may process while it is
+/// catching up. Passing `None` removes the limit
+pub fn set_max_blocks_per_second(
+    conn: &PgConnection,
+    site: &Site,
+    max: Option<u32>,
+) -> Result<(), StoreError> {
+    use subgraph_deployment as d;
+
+    update(d::table.filter(d::id.eq(site.id)))
+        .set(d::max_blocks_per_second.eq(max.map(|max| max as i32)))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(StoreError::from)
+}
+
 /// Mark the deployment `id` as synced
 pub fn set_synced(conn: &PgConnection, id: &DeploymentHash) -> Result<(), StoreError> {
     use subgraph_deployment as d;
@@ -1051,6 +1363,13 @@ pub fn create_deployment(
         debug_fork,
     } = deployment;
     let earliest_block_number = start_block.as_ref().map(|ptr| ptr.number).unwrap_or(0);
+    // Only deployments that are created during a transition compute the
+    // latest POI version, so that it covers all of their blocks
+    let poi_transition_version = Version::parse(&spec_version)
+        .map(|version| ProofOfIndexingVersion::for_spec_version(&version))
+        .map_err(|e| StoreError::Unknown(anyhow!("invalid spec version {}: {}", spec_version, e)))?
+        .transition()
+        .map(ProofOfIndexingVersion::number);
     let entities_with_causality_region = Vec::from_iter(entities_with_causality_region.into_iter());
 
     let deployment_values = (
@@ -1069,6 +1388,7 @@ pub fn create_deployment(
         d::graft_block_hash.eq(b(&graft_block)),
         d::graft_block_number.eq(n(&graft_block)),
         d::debug_fork.eq(debug_fork.as_ref().map(|s| s.as_str())),
+        d::poi_transition_version.eq(poi_transition_version),
     );
 
     let graph_node_version_id = GraphNodeVersion::create_or_get(conn)?;
@@ -1183,6 +1503,23 @@ pub fn copy_earliest_block(conn: &PgConnection, src: &Site, dst: &Site) -> Resul
     Ok(())
 }
 
+/// Return the `earliest_block` of `src`. Like for `copy_earliest_block`,
+/// `conn` must be for the shard for `dst`
+pub fn foreign_earliest_block(
+    conn: &PgConnection,
+    src: &Site,
+    dst: &Site,
+) -> Result<BlockNumber, StoreError> {
+    let src_nsp = ForeignServer::metadata_schema_in(&src.shard, &dst.shard);
+
+    let query = format!(
+        "(select earliest_block_number from {src_nsp}.subgraph_deployment where id = {})",
+        src.id
+    );
+
+    Ok(select(sql::<Integer>(&query)).get_result::<BlockNumber>(conn)?)
+}
+
 pub fn on_sync(conn: &PgConnection, id: impl Into<DeploymentId>) -> Result<OnSync, StoreError> {
     use subgraph_manifest as m;
 
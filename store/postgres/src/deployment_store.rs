@@ -6,12 +6,13 @@ use diesel::r2d2::{ConnectionManager, PooledConnection};
 use graph::anyhow::Context;
 use graph::blockchain::block_stream::FirehoseCursor;
 use graph::components::store::{
-    DerivedEntityQuery, EntityKey, EntityType, PrunePhase, PruneReporter, PruneRequest,
-    PruningStrategy, StoredDynamicDataSource, VersionStats,
+    DerivedEntityQuery, EntityChangeCursor, EntityKey, EntityType, EntityVersion, PruneEstimate,
+    PrunePhase, PruneReporter, PruneRequest, PruningStrategy, RetryPolicy, StoredDynamicDataSource,
+    VersionStats,
 };
 use graph::components::versions::VERSIONS;
 use graph::data::query::Trace;
-use graph::data::subgraph::{status, SPEC_VERSION_0_0_6};
+use graph::data::subgraph::status;
 use graph::data_source::CausalityRegion;
 use graph::prelude::futures03::FutureExt;
 use graph::prelude::{
@@ -38,7 +39,7 @@ use graph::components::subgraph::{ProofOfIndexingFinisher, ProofOfIndexingVersio
 use graph::constraint_violation;
 use graph::data::subgraph::schema::{DeploymentCreate, SubgraphError, POI_OBJECT};
 use graph::prelude::{
-    anyhow, debug, info, o, warn, web3, ApiSchema, AttributeNames, BlockNumber, BlockPtr,
+    anyhow, debug, info, o, reqwest, warn, web3, ApiSchema, AttributeNames, BlockNumber, BlockPtr,
     CheapClone, DeploymentHash, DeploymentState, Entity, EntityModification, EntityQuery, Error,
     Logger, QueryExecutionError, Schema, StopwatchMetrics, StoreError, StoreEvent, UnfailOutcome,
     Value, ENV_VARS,
@@ -47,6 +48,7 @@ use graph_graphql::prelude::api_schema;
 use web3::types::Address;
 
 use crate::block_range::{block_number, BLOCK_COLUMN, BLOCK_RANGE_COLUMN};
+use crate::catalog::IndexUsage;
 use crate::deployment::{self, OnSync};
 use crate::detail::ErrorDetail;
 use crate::dynds::DataSourcesTable;
@@ -54,9 +56,9 @@ use crate::primary::DeploymentId;
 use crate::relational::index::{CreateIndex, Method};
 use crate::relational::{Layout, LayoutCache, SqlName, Table};
 use crate::relational_queries::FromEntityData;
-use crate::{advisory_lock, catalog, retry};
+use crate::{advisory_lock, catalog, retry, DeploymentProperties};
 use crate::{connection_pool::ConnectionPool, detail};
-use crate::{dynds, primary::Site};
+use crate::{dynds, primary::Site, webhooks};
 
 /// When connected to read replicas, this allows choosing which DB server to use for an operation.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -587,11 +589,7 @@ impl DeploymentStore {
 
         let spec_version =
             Version::from_str(&manifest_info.spec_version).map_err(anyhow::Error::from)?;
-        let poi_version = if spec_version.ge(&SPEC_VERSION_0_0_6) {
-            ProofOfIndexingVersion::Fast
-        } else {
-            ProofOfIndexingVersion::Legacy
-        };
+        let poi_version = ProofOfIndexingVersion::for_spec_version(&spec_version);
 
         let info = SubgraphInfo {
             input: Arc::new(manifest_info.input_schema),
@@ -641,7 +639,16 @@ impl DeploymentStore {
     ) -> Result<Vec<status::Info>, StoreError> {
         let conn = self.get_conn()?;
         conn.transaction(|| -> Result<Vec<status::Info>, StoreError> {
-            detail::deployment_statuses(&conn, sites)
+            let mut infos = detail::deployment_statuses(&conn, sites)?;
+            for info in &mut infos {
+                if let Some(site) = sites
+                    .iter()
+                    .find(|site| site.deployment.as_str() == info.subgraph)
+                {
+                    info.dynamic_data_source_count = dynds::count(&conn, site)? as u64;
+                }
+            }
+            Ok(infos)
         })
     }
 
@@ -728,6 +735,44 @@ impl DeploymentStore {
         Ok(())
     }
 
+    /// Count the entities of each type in `site` that exist at `block`.
+    /// The counts do not include the proof of indexing
+    pub(crate) fn entity_counts(
+        &self,
+        site: Arc<Site>,
+        block: BlockNumber,
+    ) -> Result<BTreeMap<String, i64>, StoreError> {
+        #[derive(QueryableByName)]
+        struct Count {
+            #[sql_type = "diesel::sql_types::BigInt"]
+            count: i64,
+        }
+
+        let conn = self.get_conn()?;
+        let layout = self.layout(&conn, site)?;
+        layout
+            .tables
+            .values()
+            .filter(|table| !table.object.is_poi())
+            .map(|table| {
+                let filter = if table.immutable {
+                    format!("{} <= $1", BLOCK_COLUMN)
+                } else {
+                    format!("{} @> $1", BLOCK_RANGE_COLUMN)
+                };
+                let query = format!(
+                    "select count(*) as count from {} where {}",
+                    table.qualified_name, filter
+                );
+                let count = diesel::sql_query(query)
+                    .bind::<diesel::sql_types::Integer, _>(block)
+                    .get_result::<Count>(&conn)?
+                    .count;
+                Ok((table.object.to_string(), count))
+            })
+            .collect()
+    }
+
     pub(crate) fn stats_targets(
         &self,
         site: Arc<Site>,
@@ -844,6 +889,15 @@ impl DeploymentStore {
         .await
     }
 
+    /// Returns the size and usage statistics of all indexes of the
+    /// deployment.
+    pub(crate) async fn index_usage(&self, site: Arc<Site>) -> Result<Vec<IndexUsage>, StoreError> {
+        self.with_conn(move |conn, _| {
+            catalog::index_usage(conn, site.namespace.as_str()).map_err(Into::into)
+        })
+        .await
+    }
+
     /// Drops an index for a given deployment, concurrently.
     pub(crate) async fn drop_index(
         &self,
@@ -858,6 +912,129 @@ impl DeploymentStore {
         .await
     }
 
+    pub(crate) async fn set_maintenance(
+        &self,
+        site: Arc<Site>,
+        message: Option<String>,
+    ) -> Result<(), StoreError> {
+        self.with_conn(move |conn, _| {
+            deployment::set_maintenance(conn, &site, message.as_deref()).map_err(Into::into)
+        })
+        .await
+    }
+
+    pub(crate) fn retry_policy(&self, site: &Site) -> Result<RetryPolicy, StoreError> {
+        let conn = self.get_conn()?;
+        deployment::retry_policy(&conn, site)
+    }
+
+    pub(crate) fn retry_count(&self, site: &Site) -> Result<u32, StoreError> {
+        let conn = self.get_conn()?;
+        deployment::retry_count(&conn, site)
+    }
+
+    pub(crate) async fn set_retry_policy(
+        &self,
+        site: Arc<Site>,
+        policy: RetryPolicy,
+    ) -> Result<(), StoreError> {
+        self.with_conn(move |conn, _| {
+            deployment::set_retry_policy(conn, &site, &policy).map_err(Into::into)
+        })
+        .await
+    }
+
+    pub(crate) fn entity_cache_size(&self, site: &Site) -> Result<Option<usize>, StoreError> {
+        let conn = self.get_conn()?;
+        deployment::entity_cache_size(&conn, site)
+    }
+
+    pub(crate) fn max_dynamic_data_sources(
+        &self,
+        site: &Site,
+    ) -> Result<Option<usize>, StoreError> {
+        let conn = self.get_conn()?;
+        deployment::max_dynamic_data_sources(&conn, site)
+    }
+
+    pub(crate) async fn set_max_dynamic_data_sources(
+        &self,
+        site: Arc<Site>,
+        max: Option<usize>,
+    ) -> Result<(), StoreError> {
+        self.with_conn(move |conn, _| {
+            deployment::set_max_dynamic_data_sources(conn, &site, max).map_err(Into::into)
+        })
+        .await
+    }
+
+    pub(crate) fn max_blocks_per_second(&self, site: &Site) -> Result<Option<u32>, StoreError> {
+        let conn = self.get_conn()?;
+        deployment::max_blocks_per_second(&conn, site)
+    }
+
+    pub(crate) fn poi_transition_version(
+        &self,
+        site: &Site,
+    ) -> Result<Option<ProofOfIndexingVersion>, StoreError> {
+        let conn = self.get_conn()?;
+        deployment::poi_transition_version(&conn, site)
+    }
+
+    pub(crate) async fn set_max_blocks_per_second(
+        &self,
+        site: Arc<Site>,
+        max: Option<u32>,
+    ) -> Result<(), StoreError> {
+        self.with_conn(move |conn, _| {
+            deployment::set_max_blocks_per_second(conn, &site, max).map_err(Into::into)
+        })
+        .await
+    }
+
+    pub(crate) async fn set_entity_cache_size(
+        &self,
+        site: Arc<Site>,
+        size_kb: Option<usize>,
+    ) -> Result<(), StoreError> {
+        self.with_conn(move |conn, _| {
+            deployment::set_entity_cache_size(conn, &site, size_kb).map_err(Into::into)
+        })
+        .await
+    }
+
+    pub(crate) async fn set_retry_count(
+        &self,
+        site: Arc<Site>,
+        count: u32,
+    ) -> Result<(), StoreError> {
+        self.with_conn(move |conn, _| {
+            deployment::set_retry_count(conn, &site, count).map_err(Into::into)
+        })
+        .await
+    }
+
+    pub(crate) async fn save_entity_cache_snapshot(
+        &self,
+        site: Arc<Site>,
+        keys: Vec<EntityKey>,
+    ) -> Result<(), StoreError> {
+        self.with_conn(move |conn, _| {
+            deployment::save_entity_cache_snapshot(conn, &site, &keys).map_err(Into::into)
+        })
+        .await
+    }
+
+    pub(crate) async fn entity_cache_snapshot(
+        &self,
+        site: Arc<Site>,
+    ) -> Result<Vec<EntityKey>, StoreError> {
+        self.with_conn(move |conn, _| {
+            deployment::entity_cache_snapshot(conn, &site).map_err(Into::into)
+        })
+        .await
+    }
+
     pub(crate) async fn set_account_like(
         &self,
         site: Arc<Site>,
@@ -951,6 +1128,22 @@ impl DeploymentStore {
         })
         .await
     }
+
+    pub(crate) async fn prune_estimate(
+        self: &Arc<Self>,
+        mut reporter: Box<dyn PruneReporter>,
+        site: Arc<Site>,
+        req: PruneRequest,
+    ) -> Result<(Box<dyn PruneReporter>, Vec<PruneEstimate>), StoreError> {
+        let store = self.clone();
+        self.with_conn(move |conn, cancel| {
+            let layout = store.layout(conn, site)?;
+            cancel.check_cancel()?;
+            let estimates = layout.prune_estimate(conn, reporter.as_mut(), &req, cancel)?;
+            Ok((reporter, estimates))
+        })
+        .await
+    }
 }
 
 /// Methods that back the trait `graph::components::Store`, but have small
@@ -999,6 +1192,7 @@ impl DeploymentStore {
         site: Arc<Site>,
         indexer: &Option<Address>,
         block: BlockPtr,
+        version: Option<ProofOfIndexingVersion>,
     ) -> Result<Option<[u8; 32]>, StoreError> {
         let indexer = *indexer;
         let site3 = site.cheap_clone();
@@ -1062,10 +1256,19 @@ impl DeploymentStore {
             return Ok(None);
         };
 
+        let info = self.subgraph_info(&site5).map_err(anyhow::Error::from)?;
+
+        // The digests for the version of the deployment are in the entities
+        // whose id is the causality region, and the digests for the version
+        // the deployment computes during a transition in the entities whose
+        // id also contains that version
+        let version = version.unwrap_or(info.poi_version);
+        let transition = (version != info.poi_version).then_some(version);
+
         let mut by_causality_region = entities
             .into_iter()
             .map(|e| {
-                let causality_region = e.id()?;
+                let id = e.id()?;
                 let digest = match e.get("digest") {
                     Some(Value::Bytes(b)) => Ok(b.clone()),
                     other => Err(anyhow::anyhow!(
@@ -1074,14 +1277,23 @@ impl DeploymentStore {
                     )),
                 }?;
 
-                Ok((causality_region, digest))
+                match ProofOfIndexingVersion::parse_id(&id) {
+                    (entity_version, causality_region) if entity_version == transition => {
+                        Ok(Some((causality_region.to_string(), digest)))
+                    }
+                    _ => Ok(None),
+                }
             })
+            .filter_map(|res| res.transpose())
             .collect::<Result<HashMap<_, _>, anyhow::Error>>()?;
 
-        let info = self.subgraph_info(&site5).map_err(anyhow::Error::from)?;
+        if transition.is_some() && by_causality_region.is_empty() {
+            // The deployment does not compute this version
+            return Ok(None);
+        }
 
         let mut finisher =
-            ProofOfIndexingFinisher::new(&block2, &site3.deployment, &indexer, info.poi_version);
+            ProofOfIndexingFinisher::new(&block2, &site3.deployment, &indexer, version);
         for (name, region) in by_causality_region.drain() {
             finisher.add_causality_region(&name, &region);
         }
@@ -1143,6 +1355,67 @@ impl DeploymentStore {
         Ok(changes)
     }
 
+    pub(crate) fn get_changes_between(
+        &self,
+        site: Arc<Site>,
+        entity_type: &EntityType,
+        from: BlockNumber,
+        to: BlockNumber,
+        after: Option<&EntityChangeCursor>,
+        first: usize,
+    ) -> Result<(Vec<EntityOperation>, Option<EntityChangeCursor>), StoreError> {
+        let conn = self.get_conn()?;
+        // Entities that the deployment writes after its current head could
+        // still change the result, and callers would miss those changes
+        // when they continue from `to`
+        let head = Self::block_ptr_with_conn(&conn, site.cheap_clone())?;
+        match head {
+            Some(head) if to <= head.number => {}
+            _ => {
+                return Err(StoreError::QueryExecutionError(format!(
+                    "block {} is after the latest block of deployment {}",
+                    to, site.deployment
+                )))
+            }
+        }
+        // Pruning removes the versions that ended before the earliest
+        // block, and with them the deletions before that block
+        let state = deployment::state(&conn, site.deployment.clone())?;
+        if from < state.earliest_block_number {
+            return Err(StoreError::QueryExecutionError(format!(
+                "block {} is before the earliest block {} of deployment {}",
+                from, state.earliest_block_number, site.deployment
+            )));
+        }
+        let layout = self.layout(&conn, site)?;
+        layout.find_changes_between(&conn, entity_type, from, to, after, first)
+    }
+
+    pub(crate) fn deployment_properties(
+        &self,
+        site: &Site,
+    ) -> Result<DeploymentProperties, StoreError> {
+        let conn = self.get_conn()?;
+        deployment::properties(&conn, site)
+    }
+
+    pub(crate) fn raw_manifest(&self, site: &Site) -> Result<status::RawManifest, StoreError> {
+        let conn = self.get_conn()?;
+        deployment::raw_manifest(&conn, site)
+    }
+
+    pub(crate) fn entity_versions(
+        &self,
+        site: Arc<Site>,
+        entity_type: &EntityType,
+        entity_id: &str,
+        block: BlockNumber,
+    ) -> Result<Vec<EntityVersion>, StoreError> {
+        let conn = self.get_conn()?;
+        let layout = self.layout(&conn, site)?;
+        layout.find_versions(&conn, entity_type, entity_id, block)
+    }
+
     // Only used by tests
     #[cfg(debug_assertions)]
     pub(crate) fn find(
@@ -1204,6 +1477,8 @@ impl DeploymentStore {
 
                 dynds::update_offchain_status(&conn, &site, processed_data_sources)?;
 
+                webhooks::enqueue(&conn, &site, block_ptr_to, mods)?;
+
                 if !deterministic_errors.is_empty() {
                     deployment::insert_subgraph_errors(
                         &conn,
@@ -1367,6 +1642,7 @@ impl DeploymentStore {
                 Layout::revert_metadata(conn, &site, block)?;
 
                 deployment::update_entity_count(conn, site.as_ref(), count)?;
+                webhooks::enqueue_revert(conn, &site, &block_ptr_to)?;
                 Ok(event)
             })
         })?;
@@ -1386,11 +1662,25 @@ impl DeploymentStore {
 
         // Sanity check on block numbers
         if block_ptr_from.number <= block_ptr_to.number {
-            constraint_violation!(
+            return Err(constraint_violation!(
                 "rewind must go backwards, but would go from block {} to block {}",
                 block_ptr_from.number,
                 block_ptr_to.number
-            );
+            ));
+        }
+
+        // Pruning has removed the data needed to get back to blocks before
+        // the earliest block
+        let state = deployment::state(&conn, site.deployment.clone())?;
+        if block_ptr_to.number < state.earliest_block_number {
+            return Err(anyhow!(
+                "Can not rewind subgraph `{}` to block {} since its history only \
+                 goes back to block {}",
+                site.deployment,
+                block_ptr_to.number,
+                state.earliest_block_number
+            )
+            .into());
         }
 
         // When rewinding, we reset the firehose cursor. That way, on resume, Firehose will start
@@ -1508,6 +1798,28 @@ impl DeploymentStore {
     /// to the graph point, so that calling this needlessly with `Some(..)`
     /// will remove any progress that might have been made since the last
     /// time the deployment was started.
+    /// Check that `src` still has all the data at `block` that grafting
+    /// onto it at `block` needs, i.e., that it has not been pruned past
+    /// `block`
+    fn check_graft_block(
+        conn: &PgConnection,
+        src: &Site,
+        dst: &Site,
+        block: &BlockPtr,
+    ) -> Result<(), StoreError> {
+        let earliest_block = deployment::foreign_earliest_block(conn, src, dst)?;
+        if earliest_block > block.number {
+            return Err(StoreError::Unknown(anyhow!(
+                "failed to graft onto `{}` at block {} since it has been pruned and only has \
+                 data starting at block {}",
+                src.deployment,
+                block.number,
+                earliest_block
+            )));
+        }
+        Ok(())
+    }
+
     pub(crate) fn start_subgraph(
         &self,
         logger: &Logger,
@@ -1525,6 +1837,8 @@ impl DeploymentStore {
                 dst.catalog.site.namespace
             );
 
+            Self::check_graft_block(&self.get_conn()?, &src.site, &dst.site, &block)?;
+
             let src_manifest_idx_and_name = src_deployment.manifest.template_idx_and_name()?;
             let dst_manifest_idx_and_name = self
                 .load_deployment(&dst.site)?
@@ -1606,7 +1920,9 @@ impl DeploymentStore {
                 // we truly do have all the data starting at
                 // `earliest_block` and do not inadvertently expose data
                 // that might be incomplete because a prune on the source
-                // removed data just before we copied it
+                // removed data just before we copied it. If that prune
+                // removed data at the graft point, the copy is incomplete
+                Self::check_graft_block(&conn, &src.site, &dst.site, &block)?;
                 deployment::copy_earliest_block(&conn, &src.site, &dst.site)?;
 
                 // Set the block ptr to the graft point to signal that we successfully
@@ -1840,6 +2156,46 @@ impl DeploymentStore {
         });
     }
 
+    pub(crate) async fn deliver_webhooks(&self, logger: &Logger, client: &reqwest::Client) {
+        webhooks::deliver(logger, &self.pool, client)
+            .await
+            .unwrap_or_else(|e| {
+                warn!(logger, "Delivering entity webhooks failed";
+                      "error" => e.to_string(),
+                      "shard" => self.pool.shard.as_str())
+            });
+    }
+
+    pub(crate) async fn add_entity_webhook(
+        &self,
+        site: Arc<Site>,
+        url: String,
+        entity_types: Vec<String>,
+        secret: String,
+    ) -> Result<i32, StoreError> {
+        self.with_conn(move |conn, _| {
+            webhooks::create(conn, &site, &url, &entity_types, &secret).map_err(Into::into)
+        })
+        .await
+    }
+
+    pub(crate) async fn entity_webhooks(
+        &self,
+        site: Arc<Site>,
+    ) -> Result<Vec<webhooks::EntityWebhook>, StoreError> {
+        self.with_conn(move |conn, _| webhooks::list(conn, &site).map_err(Into::into))
+            .await
+    }
+
+    pub(crate) async fn remove_entity_webhook(
+        &self,
+        site: Arc<Site>,
+        id: i32,
+    ) -> Result<bool, StoreError> {
+        self.with_conn(move |conn, _| webhooks::remove(conn, &site, id).map_err(Into::into))
+            .await
+    }
+
     pub(crate) async fn health(
         &self,
         site: &Site,
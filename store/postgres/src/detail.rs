@@ -65,6 +65,15 @@ pub struct DeploymentDetail {
     current_reorg_depth: i32,
     max_reorg_depth: i32,
     firehose_cursor: Option<String>,
+    maintenance_message: Option<String>,
+    retry_max_attempts: Option<i32>,
+    retry_base_secs: Option<i32>,
+    retry_ceiling_secs: Option<i32>,
+    retry_count: i32,
+    entity_cache_size_kb: Option<i32>,
+    max_dynamic_data_sources: Option<i32>,
+    max_blocks_per_second: Option<i32>,
+    poi_transition_version: Option<i32>,
 }
 
 #[derive(Queryable, QueryableByName)]
@@ -198,6 +207,8 @@ pub(crate) fn info_from_details(
         graft_base: _,
         graft_block_hash: _,
         graft_block_number: _,
+        retry_count,
+        max_dynamic_data_sources,
         ..
     } = detail;
 
@@ -244,7 +255,16 @@ pub(crate) fn info_from_details(
         non_fatal_errors,
         chains: vec![chain],
         entity_count,
+        retry_count: retry_count.max(0) as u32,
+        // 'dynamic_data_source_count' is filled in later since it needs
+        // to query the data sources table of the deployment
+        dynamic_data_source_count: 0,
+        max_dynamic_data_sources: max_dynamic_data_sources.map(|max| max.max(0) as u32),
+        // Only the node that indexes the deployment knows the state of its
+        // file data sources, and the index node server fills that in
+        offchain_data_sources: None,
         node: None,
+        blocks_per_second: None,
     })
 }
 
@@ -25,6 +25,14 @@ pub fn load(
     }
 }
 
+/// The number of dynamic data sources that the deployment has created
+pub(crate) fn count(conn: &PgConnection, site: &Site) -> Result<usize, StoreError> {
+    match site.schema_version.private_data_sources() {
+        true => DataSourcesTable::new(site.namespace.clone()).count(conn),
+        false => shared::count(conn, &site.deployment),
+    }
+}
+
 pub(crate) fn insert(
     conn: &PgConnection,
     site: &Site,
@@ -319,6 +319,10 @@ impl DataSourcesTable {
         Ok(())
     }
 
+    pub(super) fn count(&self, conn: &PgConnection) -> Result<usize, StoreError> {
+        Ok(self.table.clone().count().get_result::<i64>(conn)? as usize)
+    }
+
     /// The current causality sequence according to the store, which is infered to be the maximum
     /// value existing in the table.
     pub(super) fn causality_region_curr_val(
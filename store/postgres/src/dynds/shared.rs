@@ -228,6 +228,17 @@ pub(super) fn revert(
     Ok(())
 }
 
+pub(super) fn count(conn: &PgConnection, id: &DeploymentHash) -> Result<usize, StoreError> {
+    use dynamic_ethereum_contract_data_source as decds;
+
+    decds::table
+        .filter(decds::deployment.eq(id.as_str()))
+        .count()
+        .get_result::<i64>(conn)
+        .map(|count| count as usize)
+        .map_err(|e| e.into())
+}
+
 pub(crate) fn drop(conn: &PgConnection, id: &DeploymentHash) -> Result<usize, StoreError> {
     use dynamic_ethereum_contract_data_source as decds;
 
@@ -0,0 +1,112 @@
+//! A cache of IPFS files in the primary. Entries that have not been used
+//! for a while are removed by a job so that the cache does not grow
+//! without bounds
+use std::time::Duration;
+
+use async_trait::async_trait;
+use diesel::sql_types::{Binary, Text};
+use diesel::{sql_query, OptionalExtension, RunQueryDsl};
+
+use graph::components::link_resolver::IpfsCache as IpfsCacheTrait;
+use graph::prelude::{o, warn, Logger, StoreError, ENV_VARS};
+
+use crate::connection_pool::ConnectionPool;
+
+/// Entries that have not been used for this long are removed
+const IPFS_CACHE_RETENTION: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+pub struct IpfsCache {
+    logger: Logger,
+    primary: ConnectionPool,
+}
+
+impl IpfsCache {
+    pub fn new(logger: &Logger, primary: ConnectionPool) -> Self {
+        IpfsCache {
+            logger: logger.new(o!("component" => "IpfsCache")),
+            primary,
+        }
+    }
+
+    async fn find(&self, path: String) -> Result<Option<Vec<u8>>, StoreError> {
+        #[derive(QueryableByName)]
+        struct Data {
+            #[sql_type = "Binary"]
+            data: Vec<u8>,
+        }
+
+        self.primary
+            .with_conn(move |conn, _| {
+                let data = sql_query("select data from ipfs_cache where path = $1")
+                    .bind::<Text, _>(&path)
+                    .get_result::<Data>(conn)
+                    .optional()?;
+                if data.is_some() {
+                    // Only record the use once a day to avoid writing on
+                    // every read
+                    sql_query(
+                        "update ipfs_cache set accessed_at = now() \
+                          where path = $1 and accessed_at < now() - interval '1 day'",
+                    )
+                    .bind::<Text, _>(&path)
+                    .execute(conn)?;
+                }
+                Ok(data.map(|data| data.data))
+            })
+            .await
+    }
+
+    async fn store(&self, path: String, data: Vec<u8>) -> Result<(), StoreError> {
+        self.primary
+            .with_conn(move |conn, _| {
+                sql_query(
+                    "insert into ipfs_cache(path, data) values($1, $2) \
+                     on conflict(path) do nothing",
+                )
+                .bind::<Text, _>(&path)
+                .bind::<Binary, _>(&data)
+                .execute(conn)?;
+                Ok(())
+            })
+            .await
+    }
+}
+
+#[async_trait]
+impl IpfsCacheTrait for IpfsCache {
+    async fn get(&self, path: &str) -> Option<Vec<u8>> {
+        match self.find(path.to_string()).await {
+            Ok(data) => data,
+            Err(e) => {
+                warn!(self.logger, "Failed to read IPFS file from the cache";
+                      "path" => path, "error" => e.to_string());
+                None
+            }
+        }
+    }
+
+    async fn insert(&self, path: String, data: Vec<u8>) {
+        if data.len() > ENV_VARS.mappings.ipfs_store_cache_max_file_size {
+            return;
+        }
+        if let Err(e) = self.store(path.clone(), data).await {
+            warn!(self.logger, "Failed to add IPFS file to the cache";
+                  "path" => path, "error" => e.to_string());
+        }
+    }
+}
+
+/// Remove the entries that were not used within `IPFS_CACHE_RETENTION`
+/// and return how many were removed
+pub(crate) async fn remove_unused(primary: &ConnectionPool) -> Result<usize, StoreError> {
+    let retention = format!("{} seconds", IPFS_CACHE_RETENTION.as_secs());
+    primary
+        .with_conn(move |conn, _| {
+            let removed =
+                sql_query("delete from ipfs_cache where accessed_at < now() - $1::interval")
+                    .bind::<Text, _>(&retention)
+                    .execute(conn)?;
+            Ok(removed)
+        })
+        .await
+}
@@ -6,11 +6,13 @@ use std::time::{Duration, Instant};
 use async_trait::async_trait;
 use diesel::{prelude::RunQueryDsl, sql_query, sql_types::Double};
 
-use graph::prelude::{error, Logger, MetricsRegistry, StoreError, ENV_VARS};
-use graph::prometheus::Gauge;
+use graph::components::subgraph::{DeploymentEvent, Notifier};
+use graph::prelude::{error, info, reqwest, warn, Logger, MetricsRegistry, StoreError, ENV_VARS};
+use graph::prometheus::{Counter, Gauge};
 use graph::util::jobs::{Job, Runner};
 
 use crate::connection_pool::ConnectionPool;
+use crate::ipfs_cache;
 use crate::{unused, Store, SubgraphStore};
 
 pub fn register(
@@ -18,6 +20,7 @@ pub fn register(
     store: Arc<Store>,
     primary_pool: ConnectionPool,
     registry: Arc<MetricsRegistry>,
+    notifier: Arc<Notifier>,
 ) {
     const ONE_MINUTE: Duration = Duration::from_secs(60);
     const ONE_HOUR: Duration = Duration::from_secs(60 * 60);
@@ -28,10 +31,29 @@ pub fn register(
     );
 
     runner.register(
-        Arc::new(NotificationQueueUsage::new(primary_pool, registry)),
+        Arc::new(NotificationQueueUsage::new(
+            primary_pool.clone(),
+            registry.clone(),
+        )),
         ONE_MINUTE,
     );
 
+    if ENV_VARS.mappings.ipfs_store_cache_max_file_size > 0 {
+        runner.register(Arc::new(IpfsCacheJob::new(primary_pool)), 24 * ONE_HOUR);
+    }
+
+    if let Some(grace) = ENV_VARS.store.node_failover_grace_period {
+        runner.register(
+            Arc::new(FailoverJob::new(
+                store.subgraph_store(),
+                grace,
+                registry,
+                notifier,
+            )),
+            ENV_VARS.store.node_lease_renewal_interval,
+        );
+    }
+
     runner.register(
         Arc::new(MirrorPrimary::new(store.subgraph_store())),
         15 * ONE_MINUTE,
@@ -47,6 +69,11 @@ pub fn register(
         Arc::new(RefreshMaterializedView::new(store.subgraph_store())),
         6 * ONE_HOUR,
     );
+
+    runner.register(
+        Arc::new(EntityWebhookJob::new(store.subgraph_store())),
+        Duration::from_secs(10),
+    );
 }
 
 /// A job that vacuums `subgraphs.subgraph_deployment`. With a large number
@@ -135,6 +162,38 @@ impl Job for NotificationQueueUsage {
     }
 }
 
+/// A job that removes files from the IPFS cache in the primary that have
+/// not been used in a while
+struct IpfsCacheJob {
+    primary: ConnectionPool,
+}
+
+impl IpfsCacheJob {
+    fn new(primary: ConnectionPool) -> Self {
+        Self { primary }
+    }
+}
+
+#[async_trait]
+impl Job for IpfsCacheJob {
+    fn name(&self) -> &str {
+        "Remove unused files from the IPFS cache"
+    }
+
+    async fn run(&self, logger: &Logger) {
+        match ipfs_cache::remove_unused(&self.primary).await {
+            Ok(removed) if removed > 0 => {
+                info!(logger, "Removed unused files from the IPFS cache"; "count" => removed)
+            }
+            Ok(_) => {}
+            Err(e) => error!(
+                logger,
+                "Failed to remove unused files from the IPFS cache: {}", e
+            ),
+        }
+    }
+}
+
 struct MirrorPrimary {
     store: Arc<SubgraphStore>,
 }
@@ -177,6 +236,33 @@ impl Job for RefreshMaterializedView {
     }
 }
 
+/// A job that posts entity changes to the webhooks that are waiting for
+/// them
+struct EntityWebhookJob {
+    store: Arc<SubgraphStore>,
+    client: reqwest::Client,
+}
+
+impl EntityWebhookJob {
+    fn new(store: Arc<SubgraphStore>) -> Self {
+        Self {
+            store,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Job for EntityWebhookJob {
+    fn name(&self) -> &str {
+        "Deliver entity webhooks"
+    }
+
+    async fn run(&self, logger: &Logger) {
+        self.store.deliver_webhooks(logger, &self.client).await;
+    }
+}
+
 struct UnusedJob {
     store: Arc<SubgraphStore>,
 }
@@ -236,3 +322,65 @@ impl Job for UnusedJob {
         }
     }
 }
+
+/// A job that moves the deployments of index nodes that stopped renewing
+/// their lease to index nodes that are alive
+struct FailoverJob {
+    store: Arc<SubgraphStore>,
+    grace: Duration,
+    failovers: Box<Counter>,
+    notifier: Arc<Notifier>,
+}
+
+impl FailoverJob {
+    fn new(
+        store: Arc<SubgraphStore>,
+        grace: Duration,
+        registry: Arc<MetricsRegistry>,
+        notifier: Arc<Notifier>,
+    ) -> Self {
+        let failovers = registry
+            .new_counter(
+                "deployment_failover_count",
+                "Counts deployments that were moved off unresponsive index nodes",
+            )
+            .expect("Can register the deployment_failover_count counter");
+        FailoverJob {
+            store,
+            grace,
+            failovers,
+            notifier,
+        }
+    }
+}
+
+#[async_trait]
+impl Job for FailoverJob {
+    fn name(&self) -> &str {
+        "Fail over deployments from unresponsive index nodes"
+    }
+
+    async fn run(&self, logger: &Logger) {
+        match self.store.fail_over_dead_nodes(logger, self.grace) {
+            Ok(failovers) => {
+                for failover in failovers {
+                    warn!(logger, "Reassigned deployment from unresponsive index node";
+                          "deployment" => failover.deployment.as_str(),
+                          "from" => failover.from.as_str(),
+                          "to" => failover.to.as_str());
+                    self.failovers.inc();
+                    self.notifier.notify(
+                        &failover.deployment,
+                        DeploymentEvent::Reassigned {
+                            from: Some(failover.from),
+                            to: failover.to,
+                        },
+                    );
+                }
+            }
+            Err(e) => {
+                error!(logger, "failed to fail over deployments"; "error" => e.to_string());
+            }
+        }
+    }
+}
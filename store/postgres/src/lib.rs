@@ -23,8 +23,10 @@ mod deployment;
 mod deployment_store;
 mod detail;
 mod dynds;
+mod entity_sink;
 mod fork;
 mod functions;
+mod ipfs_cache;
 mod jobs;
 mod jsonb;
 mod notification_listener;
@@ -38,6 +40,7 @@ mod store;
 mod store_events;
 mod subgraph_store;
 pub mod transaction_receipt;
+mod webhooks;
 mod writable;
 
 #[cfg(debug_assertions)]
@@ -58,12 +61,17 @@ pub use self::block_store::BlockStore;
 pub use self::chain_head_listener::ChainHeadUpdateListener;
 pub use self::chain_store::{ChainStore, ChainStoreMetrics};
 pub use self::detail::DeploymentDetail;
+pub use self::ipfs_cache::IpfsCache;
 pub use self::jobs::register as register_jobs;
 pub use self::notification_listener::NotificationSender;
 pub use self::primary::{db_version, UnusedDeployment};
 pub use self::store::Store;
 pub use self::store_events::SubscriptionManager;
-pub use self::subgraph_store::{unused, DeploymentPlacer, Shard, SubgraphStore, PRIMARY_SHARD};
+pub use self::subgraph_store::{
+    unused, DeploymentPlacer, DeploymentProperties, Failover, Scheduler, Shard, SubgraphStore,
+    PRIMARY_SHARD,
+};
+pub use self::webhooks::EntityWebhook;
 
 /// This module is only meant to support command line tooling. It must not
 /// be used in 'normal' graph-node code
@@ -76,9 +84,10 @@ pub mod command_support {
             active_copies, deployment_schemas, ens_names, subgraph, subgraph_deployment_assignment,
             subgraph_version, Site,
         };
-        pub use crate::primary::{Connection, Mirror};
+        pub use crate::primary::{Connection, Mirror, NodeLoad};
     }
     pub mod index {
+        pub use crate::catalog::IndexUsage;
         pub use crate::relational::index::{CreateIndex, Method};
     }
     pub use crate::deployment::{on_sync, OnSync};
@@ -7,7 +7,7 @@ use diesel::{
     dsl::{any, exists, not, select},
     pg::Pg,
     serialize::Output,
-    sql_types::{Array, Integer, Text},
+    sql_types::{Array, Bool, Integer, Text},
     types::{FromSql, ToSql},
 };
 use diesel::{
@@ -32,7 +32,10 @@ use graph::{
     },
 };
 use graph::{
-    components::store::{DeploymentId as GraphDeploymentId, DeploymentSchemaVersion},
+    components::store::{
+        DeploymentId as GraphDeploymentId, DeploymentLoad, DeploymentSchemaVersion,
+        SubgraphVersions,
+    },
     prelude::{chrono, CancelHandle, CancelToken},
 };
 use graph::{data::subgraph::schema::generate_entity_id, prelude::StoreEvent};
@@ -40,12 +43,13 @@ use itertools::Itertools;
 use maybe_owned::MaybeOwned;
 use std::{
     borrow::Borrow,
+    cmp::Ordering,
     collections::HashMap,
     convert::TryFrom,
     convert::TryInto,
     fmt,
     io::Write,
-    time::{SystemTime, UNIX_EPOCH},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use crate::{
@@ -93,6 +97,10 @@ table! {
     subgraphs.subgraph_deployment_assignment {
         id -> Integer,
         node_id -> Text,
+        paused_at -> Nullable<Timestamptz>,
+        blocks_behind -> Integer,
+        handler_time -> Double,
+        blocks_per_second -> Double,
     }
 }
 
@@ -162,6 +170,15 @@ table! {
     }
 }
 
+// Index nodes renew their lease periodically; a node whose lease has not
+// been renewed for a while is considered dead
+table! {
+    subgraphs.node_lease(node_id) {
+        node_id -> Text,
+        renewed_at -> Timestamptz,
+    }
+}
+
 table! {
     public.db_version(version) {
         #[sql_name = "db_version"]
@@ -214,6 +231,41 @@ pub struct UnusedDeployment {
     pub synced: bool,
 }
 
+/// The combined load of all deployments that are assigned to an index
+/// node and not paused
+#[derive(Clone, Debug, Default)]
+pub struct NodeLoad {
+    pub handler_time: f64,
+    pub blocks_behind: i64,
+    pub deployments: usize,
+}
+
+impl NodeLoad {
+    pub fn add(&mut self, load: &DeploymentLoad) {
+        self.handler_time += load.handler_time;
+        self.blocks_behind += load.blocks_behind as i64;
+        self.deployments += 1;
+    }
+
+    pub fn remove(&mut self, load: &DeploymentLoad) {
+        self.handler_time -= load.handler_time;
+        self.blocks_behind -= load.blocks_behind as i64;
+        self.deployments -= 1;
+    }
+
+    /// Compare loads by the time spent processing blocks first since that
+    /// is what limits how many deployments a node can index. How far
+    /// behind deployments are and how many there are only break ties,
+    /// which matters mostly for nodes that have not reported any load yet
+    pub fn compare(&self, other: &NodeLoad) -> Ordering {
+        self.handler_time
+            .partial_cmp(&other.handler_time)
+            .unwrap_or(Ordering::Equal)
+            .then(self.blocks_behind.cmp(&other.blocks_behind))
+            .then(self.deployments.cmp(&other.deployments))
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash, AsExpression, FromSqlRow)]
 #[sql_type = "diesel::sql_types::Text"]
 /// A namespace (schema) in the database
@@ -578,6 +630,7 @@ mod queries {
         ds::table
             .inner_join(a::table.on(a::id.eq(ds::id)))
             .filter(a::node_id.eq(node.as_str()))
+            .filter(a::paused_at.is_null())
             .select(ds::all_columns)
             .load::<Schema>(conn)?
             .into_iter()
@@ -593,12 +646,16 @@ mod queries {
         let nodes: HashMap<_, _> = a::table
             .inner_join(ds::table.on(ds::id.eq(a::id)))
             .filter(ds::subgraph.eq(any(ids)))
-            .select((ds::subgraph, a::node_id))
-            .load::<(String, String)>(conn)?
+            .select((ds::subgraph, a::node_id, a::blocks_per_second))
+            .load::<(String, String, f64)>(conn)?
             .into_iter()
+            .map(|(subgraph, node, blocks_per_second)| (subgraph, (node, blocks_per_second)))
             .collect();
         for mut info in infos {
-            info.node = nodes.get(&info.subgraph).cloned()
+            if let Some((node, blocks_per_second)) = nodes.get(&info.subgraph) {
+                info.node = Some(node.clone());
+                info.blocks_per_second = Some(*blocks_per_second);
+            }
         }
         Ok(())
     }
@@ -624,6 +681,29 @@ mod queries {
             .transpose()
     }
 
+    /// Return the node `site` is assigned to and whether indexing of it is
+    /// paused, or `None` if `site` is not assigned to any node
+    pub(super) fn assignment_status(
+        conn: &PgConnection,
+        site: &Site,
+    ) -> Result<Option<(NodeId, bool)>, StoreError> {
+        a::table
+            .filter(a::id.eq(site.id))
+            .select((a::node_id, a::paused_at.is_not_null()))
+            .first::<(String, bool)>(conn)
+            .optional()?
+            .map(|(node, paused)| {
+                NodeId::new(&node).map(|node| (node, paused)).map_err(|()| {
+                    constraint_violation!(
+                        "invalid node id `{}` in assignment for `{}`",
+                        node,
+                        site.deployment
+                    )
+                })
+            })
+            .transpose()
+    }
+
     pub(super) fn version_info(
         conn: &PgConnection,
         version: &str,
@@ -979,6 +1059,100 @@ impl<'a> Connection<'a> {
         }
     }
 
+    /// Return the current and the pending version of the subgraph `name`,
+    /// and the nodes that their deployments are assigned to, or `None` if
+    /// there is no subgraph with that name
+    pub fn subgraph_versions(
+        &self,
+        name: &SubgraphName,
+    ) -> Result<Option<SubgraphVersions>, StoreError> {
+        use deployment_schemas as ds;
+        use subgraph as s;
+        use subgraph_deployment_assignment as a;
+        use subgraph_version as v;
+
+        let conn = self.conn.as_ref();
+        let versions = s::table
+            .filter(s::name.eq(name.as_str()))
+            .select((s::current_version.nullable(), s::pending_version.nullable()))
+            .first::<(Option<String>, Option<String>)>(conn)
+            .optional()?;
+        let (current, pending) = match versions {
+            Some(versions) => versions,
+            None => return Ok(None),
+        };
+
+        let version_ids: Vec<_> = current.iter().chain(pending.iter()).collect();
+        let assignments = v::table
+            .inner_join(ds::table.on(v::deployment.eq(ds::subgraph)))
+            .inner_join(a::table.on(a::id.eq(ds::id)))
+            .filter(v::id.eq_any(version_ids))
+            .filter(ds::active)
+            .select((ds::id, ds::subgraph, a::node_id))
+            .load::<(DeploymentId, String, String)>(conn)?
+            .into_iter()
+            .map(|(id, hash, node)| {
+                let hash = DeploymentHash::new(hash)
+                    .map_err(|hash| constraint_violation!("illegal deployment id: {}", hash))?;
+                let node = NodeId::new(node)
+                    .map_err(|node| constraint_violation!("illegal node id: {}", node))?;
+                Ok((DeploymentLocator::new(id.into(), hash), node))
+            })
+            .collect::<Result<_, StoreError>>()?;
+
+        Ok(Some(SubgraphVersions {
+            current,
+            pending,
+            assignments,
+        }))
+    }
+
+    /// Give the subgraph `name` back the `versions` that `subgraph_versions`
+    /// returned, assign their deployments again if they have been
+    /// unassigned since, and remove the assignments of deployments that are
+    /// not used anymore
+    pub fn restore_subgraph_versions(
+        &self,
+        name: &SubgraphName,
+        versions: &SubgraphVersions,
+    ) -> Result<Vec<EntityChange>, StoreError> {
+        use subgraph as s;
+        use subgraph_deployment_assignment as a;
+
+        let conn = self.conn.as_ref();
+        let updated = update(s::table.filter(s::name.eq(name.as_str())))
+            .set((
+                s::current_version.eq(&versions.current),
+                s::pending_version.eq(&versions.pending),
+            ))
+            .execute(conn)?;
+        if updated == 0 {
+            return Err(StoreError::DeploymentNotFound(name.to_string()));
+        }
+
+        let mut changes = Vec::new();
+        for (locator, node) in &versions.assignments {
+            let id = DeploymentId::from(locator.id);
+            let assigned = a::table
+                .filter(a::id.eq(id))
+                .select(a::id)
+                .first::<i32>(conn)
+                .optional()?
+                .is_some();
+            if !assigned {
+                insert_into(a::table)
+                    .values((a::id.eq(id), a::node_id.eq(node.as_str())))
+                    .execute(conn)?;
+                changes.push(EntityChange::for_assignment(
+                    locator.clone(),
+                    EntityChangeOperation::Set,
+                ));
+            }
+        }
+        changes.extend(self.remove_unused_assignments()?);
+        Ok(changes)
+    }
+
     pub fn reassign_subgraph(
         &self,
         site: &Site,
@@ -1020,6 +1194,36 @@ impl<'a> Connection<'a> {
         Ok(vec![change])
     }
 
+    /// Pause indexing of `site` on the node it is assigned to without
+    /// removing the assignment. Pausing a site that is already paused has
+    /// no effect
+    pub fn pause_subgraph(&self, site: &Site) -> Result<Vec<EntityChange>, StoreError> {
+        self.set_paused(site, true)
+    }
+
+    /// Resume indexing of a `site` that was paused with `pause_subgraph`
+    pub fn resume_subgraph(&self, site: &Site) -> Result<Vec<EntityChange>, StoreError> {
+        self.set_paused(site, false)
+    }
+
+    fn set_paused(&self, site: &Site, paused: bool) -> Result<Vec<EntityChange>, StoreError> {
+        use subgraph_deployment_assignment as a;
+
+        let conn = self.conn.as_ref();
+        match queries::assignment_status(conn, site)? {
+            None => Err(StoreError::DeploymentNotFound(site.deployment.to_string())),
+            Some((_, cur)) if cur == paused => Ok(vec![]),
+            Some(_) => {
+                let paused_at = if paused { "now()" } else { "null" };
+                update(a::table.filter(a::id.eq(site.id)))
+                    .set(a::paused_at.eq(sql(paused_at)))
+                    .execute(conn)?;
+                let change = EntityChange::for_assignment(site.into(), EntityChangeOperation::Set);
+                Ok(vec![change])
+            }
+        }
+    }
+
     pub fn unassign_subgraph(&self, site: &Site) -> Result<Vec<EntityChange>, StoreError> {
         use subgraph_deployment_assignment as a;
 
@@ -1108,6 +1312,10 @@ impl<'a> Connection<'a> {
         queries::assigned_node(self.conn.as_ref(), site)
     }
 
+    pub fn assignment_status(&self, site: &Site) -> Result<Option<(NodeId, bool)>, StoreError> {
+        queries::assignment_status(self.conn.as_ref(), site)
+    }
+
     /// Create a copy of the site `src` in the shard `shard`, but mark it as
     /// not active. If there already is a site in `shard`, return that
     /// instead.
@@ -1255,6 +1463,175 @@ impl<'a> Connection<'a> {
             })
     }
 
+    /// Record the load that indexing `site` currently puts on the node it
+    /// is assigned to. Since the load is only used for placing
+    /// deployments, this does not send a store event
+    pub fn report_load(&self, site: &Site, load: &DeploymentLoad) -> Result<(), StoreError> {
+        use subgraph_deployment_assignment as a;
+
+        update(a::table.filter(a::id.eq(site.id)))
+            .set((
+                a::blocks_behind.eq(load.blocks_behind),
+                a::handler_time.eq(load.handler_time),
+                a::blocks_per_second.eq(load.blocks_per_second),
+            ))
+            .execute(self.conn.as_ref())?;
+        Ok(())
+    }
+
+    /// Return the deployments that are assigned to one of `nodes` and not
+    /// paused together with the node they are assigned to and their last
+    /// reported load
+    pub fn assignment_loads(
+        &self,
+        nodes: &[NodeId],
+    ) -> Result<Vec<(Site, NodeId, DeploymentLoad)>, StoreError> {
+        use deployment_schemas as ds;
+        use subgraph_deployment_assignment as a;
+
+        let nodes: Vec<_> = nodes.iter().map(|n| n.as_str()).collect();
+
+        ds::table
+            .inner_join(a::table.on(a::id.eq(ds::id)))
+            .filter(a::node_id.eq(any(&nodes)))
+            .filter(a::paused_at.is_null())
+            .select((
+                ds::all_columns,
+                a::node_id,
+                a::blocks_behind,
+                a::handler_time,
+                a::blocks_per_second,
+            ))
+            .load::<(Schema, String, i32, f64, f64)>(self.conn.as_ref())?
+            .into_iter()
+            .map(
+                |(schema, node, blocks_behind, handler_time, blocks_per_second)| {
+                    let node = NodeId::new(&node).map_err(|()| {
+                        constraint_violation!(
+                            "database has assignment for illegal node name {:?}",
+                            node
+                        )
+                    })?;
+                    let load = DeploymentLoad {
+                        blocks_behind,
+                        handler_time,
+                        blocks_per_second,
+                    };
+                    Ok((Site::try_from(schema)?, node, load))
+                },
+            )
+            .collect()
+    }
+
+    /// Return the total load of each of `nodes`. Nodes without any
+    /// assignments have no load
+    pub fn node_loads(&self, nodes: &[NodeId]) -> Result<Vec<(NodeId, NodeLoad)>, StoreError> {
+        let mut loads: Vec<_> = nodes
+            .iter()
+            .map(|node| (node.clone(), NodeLoad::default()))
+            .collect();
+        for (_, node, load) in self.assignment_loads(nodes)? {
+            if let Some((_, total)) = loads.iter_mut().find(|(n, _)| n == &node) {
+                total.add(&load);
+            }
+        }
+        Ok(loads)
+    }
+
+    /// Return the node with the least load out of the given `nodes`. If
+    /// `nodes` is empty, return `None`
+    pub fn least_loaded_node(&self, nodes: &[NodeId]) -> Result<Option<NodeId>, StoreError> {
+        Ok(self
+            .node_loads(nodes)?
+            .into_iter()
+            .min_by(|(_, a), (_, b)| a.compare(b))
+            .map(|(node, _)| node))
+    }
+
+    /// Renew the lease of `node`, creating it if `node` does not have one
+    /// yet
+    pub fn renew_node_lease(&self, node: &NodeId) -> Result<(), StoreError> {
+        use node_lease as l;
+
+        insert_into(l::table)
+            .values((l::node_id.eq(node.as_str()), l::renewed_at.eq(sql("now()"))))
+            .on_conflict(l::node_id)
+            .do_update()
+            .set(l::renewed_at.eq(sql("now()")))
+            .execute(self.conn.as_ref())?;
+        Ok(())
+    }
+
+    /// Return the nodes whose lease was last renewed more than `grace` ago
+    /// if `expired` is `true`, and the nodes whose lease is current
+    /// otherwise
+    pub fn nodes_by_lease(
+        &self,
+        grace: Duration,
+        expired: bool,
+    ) -> Result<Vec<NodeId>, StoreError> {
+        use node_lease as l;
+
+        let op = if expired { "<" } else { ">=" };
+        l::table
+            .filter(sql::<Bool>(&format!(
+                "renewed_at {} now() - interval '{} seconds'",
+                op,
+                grace.as_secs()
+            )))
+            .select(l::node_id)
+            .load::<String>(self.conn.as_ref())?
+            .into_iter()
+            .map(|node| {
+                NodeId::new(&node)
+                    .map_err(|()| constraint_violation!("illegal node name {:?} in lease", node))
+            })
+            .collect()
+    }
+
+    /// Lock the lease of `node` for the rest of the current transaction if
+    /// it was last renewed more than `grace` ago. Return `false` if the
+    /// lease is current or `node` has no lease. This keeps several nodes
+    /// from failing over the same node at the same time
+    pub fn lock_expired_lease(&self, node: &NodeId, grace: Duration) -> Result<bool, StoreError> {
+        use node_lease as l;
+
+        let locked = l::table
+            .filter(l::node_id.eq(node.as_str()))
+            .filter(sql::<Bool>(&format!(
+                "renewed_at < now() - interval '{} seconds'",
+                grace.as_secs()
+            )))
+            .select(l::node_id)
+            .for_update()
+            .first::<String>(self.conn.as_ref())
+            .optional()?;
+        Ok(locked.is_some())
+    }
+
+    pub fn remove_node_lease(&self, node: &NodeId) -> Result<(), StoreError> {
+        use node_lease as l;
+
+        delete(l::table.filter(l::node_id.eq(node.as_str()))).execute(self.conn.as_ref())?;
+        Ok(())
+    }
+
+    /// Return all deployments assigned to `node`, including the ones that
+    /// are paused
+    pub fn sites_assigned_to(&self, node: &NodeId) -> Result<Vec<Site>, StoreError> {
+        use deployment_schemas as ds;
+        use subgraph_deployment_assignment as a;
+
+        ds::table
+            .inner_join(a::table.on(a::id.eq(ds::id)))
+            .filter(a::node_id.eq(node.as_str()))
+            .select(ds::all_columns)
+            .load::<Schema>(self.conn.as_ref())?
+            .into_iter()
+            .map(Site::try_from)
+            .collect()
+    }
+
     /// Return the shard that has the fewest deployments out of the given
     /// `shards`. If `shards` is empty, return `None`
     ///
@@ -1721,6 +2098,10 @@ impl Mirror {
         self.read(|conn| queries::assigned_node(conn, site))
     }
 
+    pub fn assignment_status(&self, site: &Site) -> Result<Option<(NodeId, bool)>, StoreError> {
+        self.read(|conn| queries::assignment_status(conn, site))
+    }
+
     pub fn find_active_site(&self, subgraph: &DeploymentHash) -> Result<Option<Site>, StoreError> {
         self.read(|conn| queries::find_active_site(conn, subgraph))
     }
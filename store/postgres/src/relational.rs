@@ -19,7 +19,7 @@ mod prune;
 
 use diesel::pg::Pg;
 use diesel::serialize::Output;
-use diesel::sql_types::Text;
+use diesel::sql_types::{BigInt, Integer, Jsonb, Nullable, Text};
 use diesel::types::{FromSql, ToSql};
 use diesel::{connection::SimpleConnection, Connection};
 use diesel::{debug_query, OptionalExtension, PgConnection, RunQueryDsl};
@@ -29,7 +29,7 @@ use graph::data::graphql::TypeExt as _;
 use graph::data::query::Trace;
 use graph::data::value::Word;
 use graph::data_source::CausalityRegion;
-use graph::prelude::{q, s, EntityQuery, StopwatchMetrics, ENV_VARS};
+use graph::prelude::{q, s, serde_json, EntityQuery, StopwatchMetrics, ENV_VARS};
 use graph::slog::warn;
 use inflector::Inflector;
 use lazy_static::lazy_static;
@@ -41,7 +41,10 @@ use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
-use crate::relational_queries::{FindChangesQuery, FindDerivedQuery, FindPossibleDeletionsQuery};
+use crate::relational_queries::{
+    EntityChange, FindChangesBetweenQuery, FindChangesQuery, FindDerivedQuery,
+    FindPossibleDeletionsQuery,
+};
 use crate::{
     primary::{Namespace, Site},
     relational_queries::{
@@ -49,7 +52,9 @@ use crate::{
         FilterQuery, FindManyQuery, FindQuery, InsertQuery, RevertClampQuery, RevertRemoveQuery,
     },
 };
-use graph::components::store::{DerivedEntityQuery, EntityKey, EntityType};
+use graph::components::store::{
+    DerivedEntityQuery, EntityChangeCursor, EntityKey, EntityType, EntityVersion,
+};
 use graph::data::graphql::ext::{DirectiveFinder, DocumentExt, ObjectTypeExt};
 use graph::data::schema::{FulltextConfig, FulltextDefinition, Schema, SCHEMA_TYPE_NAME};
 use graph::data::store::BYTES_SCALAR;
@@ -642,6 +647,117 @@ impl Layout {
         Ok(changes)
     }
 
+    /// Return the changes to entities of type `entity_type` in the blocks
+    /// `from + 1..=to`: the entities that were inserted or updated, with
+    /// their data at block `to`, and the entities that were deleted and do
+    /// not exist at block `to`. At most `first` changes after `after` are
+    /// returned; if that many were found, there might be more, and the
+    /// cursor of the last one is returned to continue from
+    pub fn find_changes_between(
+        &self,
+        conn: &PgConnection,
+        entity_type: &EntityType,
+        from: BlockNumber,
+        to: BlockNumber,
+        after: Option<&EntityChangeCursor>,
+        first: usize,
+    ) -> Result<(Vec<EntityOperation>, Option<EntityChangeCursor>), StoreError> {
+        let table = self.table_for_entity(entity_type)?;
+
+        let changes = FindChangesBetweenQuery::new(table, from, to, after, first as i64)
+            .load::<EntityChange>(conn)?;
+        let next = match changes.last() {
+            Some(last) if changes.len() == first => Some(EntityChangeCursor {
+                block: last.block(),
+                id: last.id().to_string(),
+            }),
+            _ => None,
+        };
+
+        let changes = changes
+            .into_iter()
+            .map(|change| {
+                let key = EntityKey {
+                    entity_type: change.entity_type(),
+                    entity_id: Word::from(change.id()),
+                    causality_region: change.causality_region(),
+                };
+                match change.into_data() {
+                    Some(data) => Ok(EntityOperation::Set {
+                        key,
+                        data: data.deserialize_with_layout(self, None, true)?,
+                    }),
+                    None => Ok(EntityOperation::Remove { key }),
+                }
+            })
+            .collect::<Result<_, StoreError>>()?;
+        Ok((changes, next))
+    }
+
+    /// Return all stored versions of the entity `entity_type` with id
+    /// `entity_id` that were written at or before `block`
+    pub fn find_versions(
+        &self,
+        conn: &PgConnection,
+        entity_type: &EntityType,
+        entity_id: &str,
+        block: BlockNumber,
+    ) -> Result<Vec<EntityVersion>, StoreError> {
+        #[derive(QueryableByName)]
+        struct Version {
+            #[sql_type = "BigInt"]
+            vid: i64,
+            #[sql_type = "Integer"]
+            start: i32,
+            #[sql_type = "Nullable<Integer>"]
+            end: Option<i32>,
+            #[sql_type = "Jsonb"]
+            data: serde_json::Value,
+        }
+
+        let table = self.table_for_entity(entity_type)?;
+        let (start, end) = if table.immutable {
+            (format!("\"{}\"", BLOCK_COLUMN), "null::int4".to_string())
+        } else {
+            (
+                format!("lower(\"{}\")", BLOCK_RANGE_COLUMN),
+                format!("upper(\"{}\")", BLOCK_RANGE_COLUMN),
+            )
+        };
+        let (id_cond, entity_id) = match table.primary_key().column_type.id_type() {
+            IdType::String => ("e.id = $1", entity_id),
+            IdType::Bytes => (
+                "e.id = decode($1, 'hex')",
+                entity_id.trim_start_matches("0x"),
+            ),
+        };
+        let query = format!(
+            "select e.vid, {start} as start, {end} as \"end\", \
+                    to_jsonb(e) - 'vid' - '{block_column}' as data \
+               from {table} e \
+              where {id_cond} and {start} <= $2 \
+              order by {start}",
+            start = start,
+            end = end,
+            block_column = table.block_column().as_str(),
+            table = table.qualified_name,
+            id_cond = id_cond,
+        );
+        let versions = diesel::sql_query(query)
+            .bind::<Text, _>(entity_id)
+            .bind::<Integer, _>(block)
+            .load::<Version>(conn)?
+            .into_iter()
+            .map(|version| EntityVersion {
+                vid: version.vid,
+                start: version.start,
+                end: version.end,
+                data: version.data,
+            })
+            .collect();
+        Ok(versions)
+    }
+
     pub fn insert<'a>(
         &'a self,
         conn: &PgConnection,
@@ -1010,6 +1126,7 @@ pub enum ColumnType {
     BigInt,
     Bytes,
     Int,
+    Int8,
     String,
     TSVector(FulltextConfig),
     Enum(EnumType),
@@ -1067,6 +1184,7 @@ impl ColumnType {
             ValueType::BigInt => Ok(ColumnType::BigInt),
             ValueType::Bytes => Ok(ColumnType::Bytes),
             ValueType::Int => Ok(ColumnType::Int),
+            ValueType::Int8 => Ok(ColumnType::Int8),
             ValueType::String => Ok(ColumnType::String),
         }
     }
@@ -1078,6 +1196,7 @@ impl ColumnType {
             ColumnType::BigInt => "numeric",
             ColumnType::Bytes => "bytea",
             ColumnType::Int => "integer",
+            ColumnType::Int8 => "int8",
             ColumnType::String => "text",
             ColumnType::TSVector(_) => "tsvector",
             ColumnType::Enum(enum_type) => enum_type.name.as_str(),
@@ -7,7 +7,9 @@ use diesel::{
     Connection, PgConnection, RunQueryDsl,
 };
 use graph::{
-    components::store::{PrunePhase, PruneReporter, PruneRequest, PruningStrategy, VersionStats},
+    components::store::{
+        PruneEstimate, PrunePhase, PruneReporter, PruneRequest, PruningStrategy, VersionStats,
+    },
     prelude::{
         BlockNumber, CancelHandle, CancelToken, CancelableError, CheapClone, StoreError,
         BLOCK_NUMBER_MAX,
@@ -343,6 +345,38 @@ impl Layout {
         prunable_tables
     }
 
+    /// Estimate for each mutable table how much space pruning according to
+    /// `req` would reclaim, without changing any data. All tables are
+    /// analyzed first so that the estimates are based on current
+    /// statistics.
+    pub fn prune_estimate(
+        &self,
+        conn: &PgConnection,
+        reporter: &mut dyn PruneReporter,
+        req: &PruneRequest,
+        cancel: &CancelHandle,
+    ) -> Result<Vec<PruneEstimate>, CancelableError<StoreError>> {
+        let stats = self.version_stats(conn, reporter, true, cancel)?;
+        let sizes = catalog::table_sizes(conn, &self.site.namespace)?;
+
+        let mut estimates: Vec<_> = self
+            .tables
+            .values()
+            .filter(|table| !table.immutable)
+            .filter_map(|table| {
+                stats
+                    .iter()
+                    .find(|stats| stats.tablename == table.name.as_str())
+            })
+            .map(|stats| {
+                let total_bytes = sizes.get(&stats.tablename).copied().unwrap_or(0);
+                PruneEstimate::new(req, stats, total_bytes)
+            })
+            .collect();
+        estimates.sort_by(|a, b| a.tablename.cmp(&b.tablename));
+        Ok(estimates)
+    }
+
     /// Remove all data from the underlying deployment that is not needed to
     /// respond to queries before block `earliest_block`. The `req` is used
     /// to determine which strategy should be used for pruning, rebuild or
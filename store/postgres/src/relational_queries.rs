@@ -9,10 +9,10 @@ use diesel::pg::{Pg, PgConnection};
 use diesel::query_builder::{AstPass, QueryFragment, QueryId};
 use diesel::query_dsl::{LoadQuery, RunQueryDsl};
 use diesel::result::{Error as DieselError, QueryResult};
-use diesel::sql_types::{Array, BigInt, Binary, Bool, Integer, Jsonb, Text};
+use diesel::sql_types::{Array, BigInt, Binary, Bool, Integer, Jsonb, Nullable, Text};
 use diesel::Connection;
 
-use graph::components::store::{DerivedEntityQuery, EntityKey};
+use graph::components::store::{DerivedEntityQuery, EntityChangeCursor, EntityKey};
 use graph::data::value::Word;
 use graph::data_source::CausalityRegion;
 use graph::prelude::{
@@ -285,6 +285,8 @@ pub trait FromColumnValue: Sized + std::fmt::Debug {
 
     fn from_i32(i: i32) -> Self;
 
+    fn from_i64(i: i64) -> Self;
+
     fn from_big_decimal(d: scalar::BigDecimal) -> Self;
 
     fn from_big_int(i: serde_json::Number) -> Result<Self, StoreError>;
@@ -314,6 +316,13 @@ pub trait FromColumnValue: Sized + std::fmt::Debug {
                     number
                 ))),
             },
+            (j::Number(number), ColumnType::Int8) => match number.as_i64() {
+                Some(i) => Ok(Self::from_i64(i)),
+                None => Err(StoreError::Unknown(anyhow!(
+                    "failed to convert {} to Int8",
+                    number
+                ))),
+            },
             (j::Number(number), ColumnType::BigDecimal) => {
                 let s = number.to_string();
                 scalar::BigDecimal::from_str(s.as_str())
@@ -375,6 +384,10 @@ impl FromColumnValue for r::Value {
         r::Value::Int(i.into())
     }
 
+    fn from_i64(i: i64) -> Self {
+        r::Value::String(i.to_string())
+    }
+
     fn from_big_decimal(d: scalar::BigDecimal) -> Self {
         r::Value::String(d.to_string())
     }
@@ -420,6 +433,10 @@ impl FromColumnValue for graph::prelude::Value {
         graph::prelude::Value::Int(i)
     }
 
+    fn from_i64(i: i64) -> Self {
+        graph::prelude::Value::Int8(i)
+    }
+
     fn from_big_decimal(d: scalar::BigDecimal) -> Self {
         graph::prelude::Value::BigDecimal(d)
     }
@@ -467,6 +484,46 @@ impl EntityDeletion {
     }
 }
 
+/// A change that `FindChangesBetweenQuery` found. Entities that were
+/// inserted or updated come with their data, deleted entities without
+#[derive(QueryableByName, Debug)]
+pub struct EntityChange {
+    #[sql_type = "Text"]
+    entity: String,
+    #[sql_type = "Integer"]
+    block: BlockNumber,
+    #[sql_type = "Text"]
+    id: String,
+    #[sql_type = "Integer"]
+    causality_region: CausalityRegion,
+    #[sql_type = "Nullable<Jsonb>"]
+    data: Option<serde_json::Value>,
+}
+
+impl EntityChange {
+    pub fn entity_type(&self) -> EntityType {
+        EntityType::new(self.entity.clone())
+    }
+
+    pub fn block(&self) -> BlockNumber {
+        self.block
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn causality_region(&self) -> CausalityRegion {
+        self.causality_region
+    }
+
+    /// The data of the entity, or `None` if it was deleted
+    pub fn into_data(self) -> Option<EntityData> {
+        let entity = self.entity;
+        self.data.map(|data| EntityData { entity, data })
+    }
+}
+
 /// Helper struct for retrieving entities from the database. With diesel, we
 /// can only run queries that return columns whose number and type are known
 /// at compile time. Because of that, we retrieve the actual data for an
@@ -572,6 +629,7 @@ impl<'a> QueryFragment<Pg> for QueryValue<'a> {
                 ),
             },
             Value::Int(i) => out.push_bind_param::<Integer, _>(i),
+            Value::Int8(i) => out.push_bind_param::<BigInt, _>(i),
             Value::BigDecimal(d) => {
                 out.push_bind_param::<Text, _>(&d.to_string())?;
                 out.push_sql("::numeric");
@@ -590,6 +648,7 @@ impl<'a> QueryFragment<Pg> for QueryValue<'a> {
                     ColumnType::Boolean => out.push_bind_param::<Array<Bool>, _>(&sql_values),
                     ColumnType::Bytes => out.push_bind_param::<Array<Binary>, _>(&sql_values),
                     ColumnType::Int => out.push_bind_param::<Array<Integer>, _>(&sql_values),
+                    ColumnType::Int8 => out.push_bind_param::<Array<BigInt>, _>(&sql_values),
                     ColumnType::String => out.push_bind_param::<Array<Text>, _>(&sql_values),
                     ColumnType::Enum(enum_type) => {
                         out.push_bind_param::<Array<Text>, _>(&sql_values)?;
@@ -948,7 +1007,7 @@ impl<'a> QueryFilter<'a> {
             }
             // This is a special case since we want to allow passing "block" column filter, but we dont
             // want to fail/error when this is passed here, since this column is not really an entity column.
-            ChangeBlockGte(..) => {}
+            ChangeBlockGte(..) | ChangeBlockLt(..) => {}
             Contains(attr, _)
             | ContainsNoCase(attr, _)
             | NotContains(attr, _)
@@ -1168,6 +1227,7 @@ impl<'a> QueryFilter<'a> {
             Value::Null
             | Value::BigDecimal(_)
             | Value::Int(_)
+            | Value::Int8(_)
             | Value::Bool(_)
             | Value::BigInt(_) => {
                 let filter = match negated {
@@ -1238,6 +1298,7 @@ impl<'a> QueryFilter<'a> {
                 | Value::Bytes(_)
                 | Value::BigDecimal(_)
                 | Value::Int(_)
+                | Value::Int8(_)
                 | Value::String(_) => QueryValue(value, &column.column_type).walk_ast(out)?,
                 Value::Bool(_) | Value::List(_) | Value::Null => {
                     return Err(UnsupportedFilter {
@@ -1350,6 +1411,14 @@ impl<'a> QueryFilter<'a> {
         BlockRangeColumn::new(self.table, "c.", *block_number_gte).changed_since(&mut out)
     }
 
+    fn filter_block_lt(
+        &self,
+        block_number_lt: &BlockNumber,
+        mut out: AstPass<Pg>,
+    ) -> QueryResult<()> {
+        BlockRangeColumn::new(self.table, "c.", *block_number_lt).changed_before(&mut out)
+    }
+
     fn starts_or_ends_with(
         &self,
         attribute: &Attribute,
@@ -1360,6 +1429,18 @@ impl<'a> QueryFilter<'a> {
     ) -> QueryResult<()> {
         let column = self.column(attribute);
 
+        if column.column_type == ColumnType::Bytes {
+            return self.bytes_starts_or_ends_with(column, value, op, starts_with, out);
+        }
+        if let (true, true, Value::String(prefix)) = (starts_with, column.is_primary_key(), value) {
+            // Case-insensitive matching still needs `ilike`
+            match op.trim() {
+                "like" => return self.id_starts_with(column, prefix, false, out),
+                "not like" => return self.id_starts_with(column, prefix, true, out),
+                _ => {}
+            }
+        }
+
         out.push_sql(self.table_prefix);
         out.push_identifier(column.name.as_str())?;
         out.push_sql(op);
@@ -1377,6 +1458,7 @@ impl<'a> QueryFilter<'a> {
             | Value::Bytes(_)
             | Value::BigDecimal(_)
             | Value::Int(_)
+            | Value::Int8(_)
             | Value::List(_)
             | Value::Null => {
                 return Err(UnsupportedFilter {
@@ -1388,6 +1470,160 @@ impl<'a> QueryFilter<'a> {
         }
         Ok(())
     }
+
+    /// Check whether the `String` primary key `column` starts with
+    /// `prefix` by translating that into the range
+    /// `prefix <= column < successor(prefix)`. Unlike `like`, Postgres can
+    /// answer that from the primary key index. Ranges of strings match
+    /// prefixes because the database uses the `C` collation
+    fn id_starts_with(
+        &self,
+        column: &Column,
+        prefix: &str,
+        negated: bool,
+        mut out: AstPass<Pg>,
+    ) -> QueryResult<()> {
+        if negated {
+            out.push_sql("not ");
+        }
+        out.push_sql("(");
+        out.push_sql(self.table_prefix);
+        out.push_identifier(column.name.as_str())?;
+        out.push_sql(" >= ");
+        out.push_bind_param::<Text, _>(&prefix)?;
+        if let Some(successor) = string_prefix_successor(prefix) {
+            out.push_sql(" and ");
+            out.push_sql(self.table_prefix);
+            out.push_identifier(column.name.as_str())?;
+            out.push_sql(" < ");
+            out.push_bind_param::<Text, _>(&successor)?;
+        }
+        out.push_sql(")");
+        Ok(())
+    }
+
+    /// Check whether a `Bytes` column starts or ends with `value`. Since
+    /// `like` does not work for `bytea`, a prefix check is translated into
+    /// the range `value <= column < successor(value)` which Postgres can
+    /// answer from the btree index on the column; a suffix check compares
+    /// the trailing bytes of the column with `value`.
+    ///
+    /// The `op` is one of the `like` operators that `starts_or_ends_with`
+    /// takes; case-insensitive matching is not supported for bytes
+    fn bytes_starts_or_ends_with(
+        &self,
+        column: &Column,
+        value: &Value,
+        op: &str,
+        starts_with: bool,
+        mut out: AstPass<Pg>,
+    ) -> QueryResult<()> {
+        let unsupported = || {
+            let filter = match (starts_with, op.trim()) {
+                (true, "like") => "starts_with",
+                (true, "not like") => "not_starts_with",
+                (false, "like") => "ends_with",
+                (false, "not like") => "not_ends_with",
+                (_, op) => op,
+            };
+            UnsupportedFilter {
+                filter: filter.to_owned(),
+                value: value.clone(),
+            }
+        };
+
+        let negated = match op.trim() {
+            "like" => false,
+            "not like" => true,
+            _ => return Err(unsupported().into()),
+        };
+        let bytes = match value {
+            Value::Bytes(b) => b.clone(),
+            Value::String(s) => scalar::Bytes::from_str(s).map_err(|_| unsupported())?,
+            _ => return Err(unsupported().into()),
+        };
+
+        if negated {
+            out.push_sql("not ");
+        }
+        out.push_sql("(");
+        if starts_with {
+            // The prefix of the column that is indexed is only usable if
+            // the value we are looking for fits into it
+            let use_prefix =
+                column.use_prefix_comparison && bytes.as_slice().len() <= BYTE_ARRAY_PREFIX_SIZE;
+
+            self.push_bytes_column(column, use_prefix, &mut out)?;
+            out.push_sql(" >= ");
+            out.push_bind_param::<Binary, _>(&bytes.as_slice())?;
+            if let Some(successor) = bytes_prefix_successor(bytes.as_slice()) {
+                out.push_sql(" and ");
+                self.push_bytes_column(column, use_prefix, &mut out)?;
+                out.push_sql(" < ");
+                out.push_bind_param::<Binary, _>(&successor.as_slice())?;
+            }
+        } else {
+            out.push_sql("substring(");
+            out.push_sql(self.table_prefix);
+            out.push_identifier(column.name.as_str())?;
+            out.push_sql(", octet_length(");
+            out.push_sql(self.table_prefix);
+            out.push_identifier(column.name.as_str())?;
+            out.push_sql(") - ");
+            out.push_sql(&bytes.as_slice().len().to_string());
+            out.push_sql(" + 1) = ");
+            out.push_bind_param::<Binary, _>(&bytes.as_slice())?;
+        }
+        out.push_sql(")");
+        Ok(())
+    }
+
+    fn push_bytes_column(
+        &self,
+        column: &Column,
+        use_prefix: bool,
+        out: &mut AstPass<Pg>,
+    ) -> QueryResult<()> {
+        if use_prefix {
+            PrefixType::new(column)?.push_column_prefix(out)
+        } else {
+            out.push_sql(self.table_prefix);
+            out.push_identifier(column.name.as_str())
+        }
+    }
+}
+
+/// The smallest string that is larger than every string that starts with
+/// `prefix` when strings are compared by code points, or `None` if there
+/// is no such string because `prefix` consists only of `char::MAX`
+fn string_prefix_successor(prefix: &str) -> Option<String> {
+    let mut successor = prefix.to_string();
+    while let Some(last) = successor.pop() {
+        // Skip over the surrogates, which are not valid chars
+        let next = match last {
+            '\u{d7ff}' => Some('\u{e000}'),
+            c => char::from_u32(c as u32 + 1),
+        };
+        if let Some(next) = next {
+            successor.push(next);
+            return Some(successor);
+        }
+    }
+    None
+}
+
+/// The smallest byte string that is larger than every byte string that
+/// starts with `prefix`, or `None` if there is no such byte string because
+/// `prefix` consists only of `0xff` bytes
+fn bytes_prefix_successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut successor = prefix.to_vec();
+    while let Some(last) = successor.pop() {
+        if last < u8::MAX {
+            successor.push(last + 1);
+            return Some(successor);
+        }
+    }
+    None
 }
 
 impl<'a> QueryFragment<Pg> for QueryFilter<'a> {
@@ -1441,6 +1677,7 @@ impl<'a> QueryFragment<Pg> for QueryFilter<'a> {
                 self.starts_or_ends_with(attr, value, " not ilike ", false, out)?
             }
             ChangeBlockGte(block_number) => self.filter_block_gte(block_number, out)?,
+            ChangeBlockLt(block_number) => self.filter_block_lt(block_number, out)?,
             Child(child) => self.child(
                 &child.attr,
                 &child.entity_type,
@@ -1607,6 +1844,130 @@ impl<'a> LoadQuery<PgConnection, EntityDeletion> for FindPossibleDeletionsQuery<
 
 impl<'a, Conn> RunQueryDsl<Conn> for FindPossibleDeletionsQuery<'a> {}
 
+/// Builds a query that finds the changes to the entities in `table` in the
+/// blocks `from + 1..=to`: the entities whose version at block `to` was
+/// written in those blocks, and the entities that were deleted in them and
+/// that do not exist at block `to`. Changes are ordered by the block at
+/// which they happened and the id of the entity, and only the first
+/// `first` changes after `after` are returned
+#[derive(Debug, Clone, Constructor)]
+pub struct FindChangesBetweenQuery<'a> {
+    pub(crate) table: &'a Table,
+    pub(crate) from: BlockNumber,
+    pub(crate) to: BlockNumber,
+    pub(crate) after: Option<&'a EntityChangeCursor>,
+    pub(crate) first: i64,
+}
+
+impl<'a> FindChangesBetweenQuery<'a> {
+    fn id_and_causality_region(&self, out: &mut AstPass<Pg>) {
+        match self.table.primary_key().column_type.id_type() {
+            IdType::String => out.push_sql("e.id, "),
+            IdType::Bytes => out.push_sql("'0x' || encode(e.id, 'hex') as id, "),
+        }
+        if self.table.has_causality_region {
+            out.push_sql("e.causality_region");
+        } else {
+            out.push_sql("0 as causality_region");
+        }
+    }
+}
+
+impl<'a> QueryFragment<Pg> for FindChangesBetweenQuery<'a> {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        // Generate
+        //    select $object as entity, c.*
+        //      from (select lower(e.block_range) as block, e.id,
+        //                   e.causality_region, to_jsonb(e.*) as data
+        //              from schema.<table> e
+        //             where {e.block_range @> $to}
+        //               and {lower(e.block_range) > $from}
+        //            union all
+        //            select max(upper(e.block_range)), e.id,
+        //                   e.causality_region, null
+        //              from schema.<table> e
+        //             where upper(e.block_range) > $from
+        //               and upper(e.block_range) <= $to
+        //               and not exists (select 1 from schema.<table> d
+        //                                where d.id = e.id and {d.block_range @> $to})
+        //             group by e.id, e.causality_region) c
+        //     where (c.block, c.id) > ($after.block, $after.id)
+        //     order by c.block, c.id
+        //     limit $first
+        //
+        // Immutable entities can not be deleted, and the part of the query
+        // that finds deletions is left out for them
+        out.push_sql("select ");
+        out.push_bind_param::<Text, _>(&self.table.object.as_str())?;
+        out.push_sql(" as entity, c.*\n");
+        out.push_sql("  from (select ");
+        let block_range = BlockRangeColumn::new(self.table, "e.", self.to);
+        block_range.changed_at(&mut out)?;
+        out.push_sql(" as block, ");
+        self.id_and_causality_region(&mut out);
+        out.push_sql(", to_jsonb(e.*) as data\n");
+        out.push_sql("          from ");
+        out.push_sql(self.table.qualified_name.as_str());
+        out.push_sql(" e\n         where ");
+        block_range.contains(&mut out)?;
+        out.push_sql(" and ");
+        BlockRangeColumn::new(self.table, "e.", self.from + 1).changed_since(&mut out)?;
+        if !self.table.immutable {
+            out.push_sql("\n        union all\n");
+            out.push_sql("        select max(upper(e.");
+            out.push_sql(BLOCK_RANGE_COLUMN);
+            out.push_sql(")), ");
+            self.id_and_causality_region(&mut out);
+            out.push_sql(", null::jsonb\n");
+            out.push_sql("          from ");
+            out.push_sql(self.table.qualified_name.as_str());
+            out.push_sql(" e\n         where upper(e.");
+            out.push_sql(BLOCK_RANGE_COLUMN);
+            out.push_sql(") > ");
+            out.push_bind_param::<Integer, _>(&self.from)?;
+            out.push_sql(" and upper(e.");
+            out.push_sql(BLOCK_RANGE_COLUMN);
+            out.push_sql(") <= ");
+            out.push_bind_param::<Integer, _>(&self.to)?;
+            out.push_sql("\n           and not exists (select 1 from ");
+            out.push_sql(self.table.qualified_name.as_str());
+            out.push_sql(" d where d.id = e.id and ");
+            BlockRangeColumn::new(self.table, "d.", self.to).contains(&mut out)?;
+            out.push_sql(")\n         group by e.id");
+            if self.table.has_causality_region {
+                out.push_sql(", e.causality_region");
+            }
+        }
+        out.push_sql(") c\n");
+        if let Some(after) = self.after {
+            out.push_sql(" where (c.block, c.id) > (");
+            out.push_bind_param::<Integer, _>(&after.block)?;
+            out.push_sql(", ");
+            out.push_bind_param::<Text, _>(&after.id)?;
+            out.push_sql(")\n");
+        }
+        out.push_sql(" order by c.block, c.id\n");
+        out.push_sql(" limit ");
+        out.push_bind_param::<BigInt, _>(&self.first)
+    }
+}
+
+impl<'a> QueryId for FindChangesBetweenQuery<'a> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+impl<'a> LoadQuery<PgConnection, EntityChange> for FindChangesBetweenQuery<'a> {
+    fn internal_load(self, conn: &PgConnection) -> QueryResult<Vec<EntityChange>> {
+        conn.query_by_name(&self)
+    }
+}
+
+impl<'a, Conn> RunQueryDsl<Conn> for FindChangesBetweenQuery<'a> {}
+
 #[derive(Debug, Clone, Constructor)]
 pub struct FindManyQuery<'a> {
     pub(crate) _namespace: &'a Namespace,
@@ -2721,6 +3082,12 @@ pub enum SortKey<'a> {
     },
     /// Order by some other column; `column` will never be `id`
     ChildKey(ChildKey<'a>),
+    /// Order by the block at which the rows were written, then by `id`.
+    /// Only possible for queries of a single entity type
+    ChangeBlock {
+        br_column: BlockRangeColumn<'a>,
+        direction: &'static str,
+    },
 }
 
 /// String representation that is useful for debugging when `walk_ast` fails
@@ -2842,6 +3209,17 @@ impl<'a> fmt::Display for SortKey<'a> {
                     )
                 }
             },
+            SortKey::ChangeBlock {
+                br_column,
+                direction,
+            } => write!(
+                f,
+                "{} {}, {} {}",
+                br_column.column_name(),
+                direction,
+                PRIMARY_KEY_COLUMN,
+                direction
+            ),
         }
     }
 }
@@ -3143,6 +3521,14 @@ impl<'a> SortKey<'a> {
             EntityOrder::Descending(attr, _) => with_key(table, attr, filter, DESC, br_column),
             EntityOrder::Default => Ok(SortKey::IdAsc(br_column)),
             EntityOrder::Unordered => Ok(SortKey::None),
+            EntityOrder::ChangeBlockAscending => Ok(SortKey::ChangeBlock {
+                br_column: BlockRangeColumn::new(table, "c.", block),
+                direction: ASC,
+            }),
+            EntityOrder::ChangeBlockDescending => Ok(SortKey::ChangeBlock {
+                br_column: BlockRangeColumn::new(table, "c.", block),
+                direction: DESC,
+            }),
             EntityOrder::ChildAscending(kind) => match kind {
                 EntityOrderByChild::Object(child, entity_type) => with_child_object_key(
                     table,
@@ -3242,6 +3628,9 @@ impl<'a> SortKey<'a> {
 
                 Ok(())
             }
+            SortKey::ChangeBlock { .. } => Err(diesel::result::Error::QueryBuilderError(
+                "SortKey::ChangeBlock can only be used for queries of a single entity type".into(),
+            )),
         }
     }
 
@@ -3341,6 +3730,13 @@ impl<'a> SortKey<'a> {
                     }
                 }
             }
+            SortKey::ChangeBlock {
+                br_column,
+                direction,
+            } => {
+                out.push_sql("order by ");
+                SortKey::change_block_expr(br_column, direction, out)
+            }
         }
     }
 
@@ -3370,9 +3766,33 @@ impl<'a> SortKey<'a> {
             SortKey::ChildKey(_) => Err(diesel::result::Error::QueryBuilderError(
                 "SortKey::ChildKey cannot be used for parent ordering (yet)".into(),
             )),
+            SortKey::ChangeBlock {
+                br_column,
+                direction,
+            } => {
+                out.push_sql("order by g$parent_id, ");
+                SortKey::change_block_expr(br_column, direction, out)
+            }
         }
     }
 
+    /// Generate
+    ///   {block} direction, id direction
+    fn change_block_expr(
+        br_column: &BlockRangeColumn,
+        direction: &str,
+        out: &mut AstPass<Pg>,
+    ) -> QueryResult<()> {
+        br_column.changed_at(out)?;
+        out.push_sql(" ");
+        out.push_sql(direction);
+        out.push_sql(", ");
+        out.push_identifier(PRIMARY_KEY_COLUMN)?;
+        out.push_sql(" ");
+        out.push_sql(direction);
+        Ok(())
+    }
+
     /// Generate
     ///   [name direction,] id
     fn sort_expr(
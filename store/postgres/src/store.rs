@@ -6,10 +6,12 @@ use graph::{
         server::index_node::VersionInfo,
         store::{
             BlockStore as BlockStoreTrait, QueryStoreManager, StatusStore, Store as StoreTrait,
+            StoredDynamicDataSource,
         },
+        subgraph::ProofOfIndexingVersion,
     },
     constraint_violation,
-    data::subgraph::status,
+    data::{query::QueryTarget, subgraph::status},
     prelude::{
         tokio, web3::types::Address, BlockNumber, BlockPtr, CheapClone, DeploymentHash,
         PartialBlockPtr, QueryExecutionError, StoreError,
@@ -68,7 +70,7 @@ impl StoreTrait for Store {
 impl QueryStoreManager for Store {
     async fn query_store(
         &self,
-        target: graph::data::query::QueryTarget,
+        target: QueryTarget,
         for_subscription: bool,
     ) -> Result<
         Arc<dyn graph::prelude::QueryStore + Send + Sync>,
@@ -102,6 +104,25 @@ impl QueryStoreManager for Store {
             Arc::new(api_version.clone()),
         )))
     }
+
+    async fn deployment_for(
+        &self,
+        target: &QueryTarget,
+    ) -> Result<DeploymentHash, QueryExecutionError> {
+        if let QueryTarget::Deployment(id, _) = target {
+            return Ok(id.clone());
+        }
+        let store = self.subgraph_store.cheap_clone();
+        let target = target.clone();
+        graph::spawn_blocking_allow_panic(move || {
+            store
+                .deployment_for_query(&target)
+                .map_err(QueryExecutionError::from)
+        })
+        .await
+        .map_err(|e| QueryExecutionError::Panic(e.to_string()))
+        .and_then(|x| x)
+    }
 }
 
 #[async_trait]
@@ -145,9 +166,10 @@ impl StatusStore for Store {
         subgraph_id: &DeploymentHash,
         indexer: &Option<Address>,
         block: BlockPtr,
+        version: Option<ProofOfIndexingVersion>,
     ) -> Result<Option<[u8; 32]>, StoreError> {
         self.subgraph_store
-            .get_proof_of_indexing(subgraph_id, indexer, block)
+            .get_proof_of_indexing(subgraph_id, indexer, block, version)
             .await
     }
 
@@ -161,6 +183,23 @@ impl StatusStore for Store {
             .await
     }
 
+    fn raw_manifest(
+        &self,
+        subgraph_id: &DeploymentHash,
+    ) -> Result<Option<status::RawManifest>, StoreError> {
+        self.subgraph_store.raw_manifest(subgraph_id)
+    }
+
+    async fn dynamic_data_sources(
+        &self,
+        subgraph_id: &DeploymentHash,
+        manifest_idx_and_name: Vec<(u32, String)>,
+    ) -> Result<Vec<StoredDynamicDataSource>, StoreError> {
+        self.subgraph_store
+            .dynamic_data_sources(subgraph_id, manifest_idx_and_name)
+            .await
+    }
+
     async fn query_permit(&self) -> Result<tokio::sync::OwnedSemaphorePermit, StoreError> {
         // Status queries go to the primary shard.
         Ok(self.block_store.query_permit_primary().await)
@@ -17,23 +17,35 @@ use graph::{
         server::index_node::VersionInfo,
         store::{
             self, BlockStore, DeploymentLocator, DeploymentSchemaVersion,
-            EnsLookup as EnsLookupTrait, PruneReporter, PruneRequest, SubgraphFork,
+            EnsLookup as EnsLookupTrait, EntityChangeCursor, EntityType, EntityVersion,
+            PruneEstimate, PruneReporter, PruneRequest, RetryPolicy, SubgraphFork,
+            SubgraphVersions,
         },
+        subgraph::ProofOfIndexingVersion,
     },
     constraint_violation,
+    data::graphql::ext::DocumentExt,
     data::query::QueryTarget,
+    data::schema::SCHEMA_TYPE_NAME,
     data::subgraph::{schema::DeploymentCreate, status},
     prelude::StoreEvent,
     prelude::{
-        anyhow, futures03::future::join_all, lazy_static, o, web3::types::Address, ApiSchema,
-        ApiVersion, BlockNumber, BlockPtr, ChainStore, DeploymentHash, EntityOperation, Logger,
-        MetricsRegistry, NodeId, PartialBlockPtr, Schema, StoreError, SubgraphDeploymentEntity,
-        SubgraphName, SubgraphStore as SubgraphStoreTrait, SubgraphVersionSwitchingMode,
+        anyhow, error,
+        futures03::future::join_all,
+        lazy_static, o, reqwest,
+        serde::{Deserialize, Serialize},
+        serde_yaml,
+        web3::types::Address,
+        ApiSchema, ApiVersion, BlockNumber, BlockPtr, ChainStore, DeploymentHash, EntityOperation,
+        Logger, MetricsRegistry, NodeId, PartialBlockPtr, Schema, StoreError,
+        SubgraphDeploymentEntity, SubgraphName, SubgraphStore as SubgraphStoreTrait,
+        SubgraphVersionSwitchingMode, BLOCK_NUMBER_MAX, ENV_VARS,
     },
     url::Url,
     util::timed_cache::TimedCache,
 };
 
+use crate::{catalog::IndexUsage, fork, relational::index::CreateIndex, relational::SqlName};
 use crate::{
     connection_pool::ConnectionPool,
     deployment::{OnSync, SubgraphHealth},
@@ -46,9 +58,10 @@ use crate::{
 use crate::{
     deployment_store::{DeploymentStore, ReplicaId},
     detail::DeploymentDetail,
+    entity_sink::EntitySink,
     primary::UnusedDeployment,
+    webhooks::EntityWebhook,
 };
-use crate::{fork, relational::index::CreateIndex, relational::SqlName};
 
 /// The name of a database shard; valid names must match `[a-z0-9_]+`
 #[derive(Clone, Debug, Eq, PartialEq, Hash, AsExpression, FromSqlRow)]
@@ -111,15 +124,109 @@ impl ToSql<Text, Pg> for Shard {
     }
 }
 
+/// A deployment that was moved from an index node that stopped renewing
+/// its lease to a node that is alive
+#[derive(Clone, Debug)]
+pub struct Failover {
+    pub deployment: DeploymentHash,
+    pub from: NodeId,
+    pub to: NodeId,
+}
+
 /// Decide where a new deployment should be placed based on the subgraph
-/// name and the network it is indexing. If the deployment can be placed,
-/// returns a list of eligible database shards for the deployment and the
-/// names of the indexers that should index it. The deployment should then
-/// be assigned to one of the returned indexers and placed into one of the
-/// shards.
+/// name, the network it is indexing and its `DeploymentProperties`. If the
+/// deployment can be placed, returns a list of eligible database shards for
+/// the deployment and the names of the indexers that should index it. The
+/// deployment should then be assigned to one of the returned indexers and
+/// placed into one of the shards.
 pub trait DeploymentPlacer {
-    fn place(&self, name: &str, network: &str)
-        -> Result<Option<(Vec<Shard>, Vec<NodeId>)>, String>;
+    fn place(
+        &self,
+        name: &str,
+        network: &str,
+        properties: &DeploymentProperties,
+    ) -> Result<Option<(Vec<Shard>, Vec<NodeId>)>, String>;
+
+    /// How to choose among the indexers that `place` returns
+    fn scheduler(&self) -> Scheduler;
+}
+
+/// How a new deployment is assigned to one of the indexers that the
+/// deployment rules allow for it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scheduler {
+    /// Use the indexer with the fewest deployments
+    #[default]
+    Rules,
+    /// Use the indexer with the least load as reported by the indexers
+    /// for the deployments they are indexing
+    Load,
+}
+
+/// What deployment rules can match on besides the subgraph name and the
+/// network of a deployment
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeploymentProperties {
+    /// The features that the manifest declares, and `fileDataSources` if
+    /// the manifest has templates for file data sources
+    pub features: Vec<String>,
+    /// The number of data sources in the manifest, not counting templates
+    pub data_sources: usize,
+    /// The number of entity types in the schema
+    pub entity_types: usize,
+}
+
+impl DeploymentProperties {
+    /// The pseudo-feature of deployments that use file data sources, which
+    /// manifests do not declare
+    pub const FILE_DATA_SOURCES: &'static str = "fileDataSources";
+
+    /// The properties of a deployment whose manifest declares `features`
+    /// and whose schema is `schema`. Data sources are counted in
+    /// `raw_yaml`; deployments that were created before the raw manifest
+    /// was stored have no data sources
+    pub fn new(features: &[String], raw_yaml: Option<&str>, schema: &Schema) -> Self {
+        #[derive(Deserialize)]
+        struct MinimalDs {
+            #[serde(default)]
+            kind: String,
+        }
+        #[derive(Default, Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct MinimalManifest {
+            #[serde(default)]
+            data_sources: Vec<MinimalDs>,
+            #[serde(default)]
+            templates: Vec<MinimalDs>,
+        }
+
+        let manifest: MinimalManifest = raw_yaml
+            .and_then(|raw_yaml| serde_yaml::from_str(raw_yaml).ok())
+            .unwrap_or_default();
+
+        let mut features = features.to_vec();
+        if manifest
+            .data_sources
+            .iter()
+            .chain(manifest.templates.iter())
+            .any(|ds| ds.kind.starts_with("file/"))
+        {
+            features.push(Self::FILE_DATA_SOURCES.to_string());
+        }
+        let entity_types = schema
+            .document
+            .get_object_type_definitions()
+            .iter()
+            .filter(|obj_type| obj_type.name != SCHEMA_TYPE_NAME)
+            .count();
+
+        DeploymentProperties {
+            features,
+            data_sources: manifest.data_sources.len(),
+            entity_types,
+        }
+    }
 }
 
 /// Tools for managing unused deployments
@@ -233,8 +340,11 @@ impl SubgraphStore {
         id: &DeploymentHash,
         indexer: &Option<Address>,
         block: BlockPtr,
+        version: Option<ProofOfIndexingVersion>,
     ) -> Result<Option<[u8; 32]>, StoreError> {
-        self.inner.get_proof_of_indexing(id, indexer, block).await
+        self.inner
+            .get_proof_of_indexing(id, indexer, block, version)
+            .await
     }
 
     pub(crate) async fn get_public_proof_of_indexing(
@@ -275,6 +385,8 @@ pub struct SubgraphStoreInner {
     sender: Arc<NotificationSender>,
     writables: Mutex<HashMap<DeploymentId, Arc<WritableStore>>>,
     registry: Arc<MetricsRegistry>,
+    /// Where entity changes are published to, if `GRAPH_ENTITY_SINK` is set
+    entity_sink: Option<Arc<EntitySink>>,
 }
 
 impl SubgraphStoreInner {
@@ -323,6 +435,17 @@ impl SubgraphStoreInner {
             },
         ));
         let sites = TimedCache::new(SITES_CACHE_TTL);
+        let entity_sink = ENV_VARS.store.entity_sink.as_deref().map(|target| {
+            let sink = EntitySink::new(
+                logger,
+                target,
+                &ENV_VARS.store.entity_sink_topic,
+                ENV_VARS.store.entity_sink_queue_size,
+                &registry,
+            )
+            .expect("invalid GRAPH_ENTITY_SINK");
+            Arc::new(sink)
+        });
         SubgraphStoreInner {
             mirror,
             stores,
@@ -331,6 +454,7 @@ impl SubgraphStoreInner {
             sender,
             writables: Mutex::new(HashMap::new()),
             registry,
+            entity_sink,
         }
     }
 
@@ -438,12 +562,25 @@ impl SubgraphStoreInner {
                 let conn = self.primary_conn()?;
 
                 // unwrap is fine since nodes is not empty
-                let node = conn.least_assigned_node(&nodes)?.unwrap();
+                let node = self.pick_node(&conn, &nodes)?.unwrap();
                 Ok(node)
             }
         }
     }
 
+    /// Pick one of `nodes` according to the configured scheduler. Return
+    /// `None` if `nodes` is empty
+    fn pick_node(
+        &self,
+        conn: &primary::Connection,
+        nodes: &[NodeId],
+    ) -> Result<Option<NodeId>, StoreError> {
+        match self.placer.scheduler() {
+            Scheduler::Rules => conn.least_assigned_node(nodes),
+            Scheduler::Load => conn.least_loaded_node(nodes),
+        }
+    }
+
     fn place_in_shard(&self, mut shards: Vec<Shard>) -> Result<Shard, StoreError> {
         match shards.len() {
             0 => Ok(PRIMARY_SHARD.clone()),
@@ -462,6 +599,7 @@ impl SubgraphStoreInner {
         &self,
         name: &SubgraphName,
         network_name: &str,
+        properties: &DeploymentProperties,
         default_node: NodeId,
     ) -> Result<(Shard, NodeId), StoreError> {
         // We try to place the deployment according to the configured rules.
@@ -471,7 +609,7 @@ impl SubgraphStoreInner {
         // uses the legacy command-line options as configuration
         let placement = self
             .placer
-            .place(name.as_str(), network_name)
+            .place(name.as_str(), network_name, properties)
             .map_err(|msg| {
                 constraint_violation!("illegal indexer name in deployment rule: {}", msg)
             })?;
@@ -531,7 +669,12 @@ impl SubgraphStoreInner {
             //       In that case, we need to use the shard and node
             //       assignment that we used last time to avoid creating
             //       the same deployment in another shard
-            let (shard, node_id) = self.place(&name, &network_name, node_id)?;
+            let properties = DeploymentProperties::new(
+                &deployment.manifest.features,
+                deployment.manifest.raw_yaml.as_deref(),
+                schema,
+            );
+            let (shard, node_id) = self.place(&name, &network_name, &properties, node_id)?;
             let schema_version = match &graft_base {
                 None => DeploymentSchemaVersion::LATEST,
                 Some(src_layout) => src_layout.site.schema_version,
@@ -694,6 +837,10 @@ impl SubgraphStoreInner {
         conn.send_store_event(&self.sender, event)
     }
 
+    pub(crate) fn entity_sink(&self) -> Option<Arc<EntitySink>> {
+        self.entity_sink.clone()
+    }
+
     /// Get a connection to the primary shard. Code must never hold one of these
     /// connections while also accessing a `DeploymentStore`, since both
     /// might draw connections from the same pool, and trying to get two
@@ -705,15 +852,23 @@ impl SubgraphStoreInner {
         Ok(primary::Connection::new(conn))
     }
 
+    /// The deployment that queries against `target` go to
+    pub(crate) fn deployment_for_query(
+        &self,
+        target: &QueryTarget,
+    ) -> Result<DeploymentHash, StoreError> {
+        match target {
+            QueryTarget::Name(name, _) => self.mirror.current_deployment_for_subgraph(name),
+            QueryTarget::Deployment(id, _) => Ok(id.clone()),
+        }
+    }
+
     pub(crate) fn replica_for_query(
         &self,
         target: QueryTarget,
         for_subscription: bool,
     ) -> Result<(Arc<DeploymentStore>, Arc<Site>, ReplicaId), StoreError> {
-        let id = match target {
-            QueryTarget::Name(name, _) => self.mirror.current_deployment_for_subgraph(&name)?,
-            QueryTarget::Deployment(id, _) => id,
-        };
+        let id = self.deployment_for_query(&target)?;
 
         let (store, site) = self.store(&id)?;
         let replica = store.replica_for_query(for_subscription)?;
@@ -762,6 +917,105 @@ impl SubgraphStoreInner {
         Ok(by_shard)
     }
 
+    /// Renew the lease of `node` so that other nodes know that it is alive
+    pub fn renew_node_lease(&self, node: &NodeId) -> Result<(), StoreError> {
+        self.primary_conn()?.renew_node_lease(node)
+    }
+
+    /// Move the deployments of index nodes whose lease was last renewed
+    /// more than `grace` ago to nodes whose lease is current. The new node
+    /// for a deployment is chosen from the indexers that the deployment
+    /// rules allow for it in the same way as for a new deployment.
+    /// Deployments for which none of these indexers is alive stay where
+    /// they are, and moving them is tried again the next time this is
+    /// called
+    pub fn fail_over_dead_nodes(
+        &self,
+        logger: &Logger,
+        grace: Duration,
+    ) -> Result<Vec<Failover>, StoreError> {
+        let pconn = self.primary_conn()?;
+        let dead = pconn.nodes_by_lease(grace, true)?;
+        if dead.is_empty() {
+            return Ok(Vec::new());
+        }
+        let live = pconn.nodes_by_lease(grace, false)?;
+
+        let mut failovers = Vec::new();
+        for node in dead {
+            let moved = pconn.transaction(|| -> Result<_, StoreError> {
+                if !pconn.lock_expired_lease(&node, grace)? {
+                    // The node renewed its lease in the meantime, or
+                    // another node already failed it over
+                    return Ok(Vec::new());
+                }
+
+                let mut moved = Vec::new();
+                let mut changes = Vec::new();
+                let mut stranded = false;
+                for site in pconn.sites_assigned_to(&node)? {
+                    match self.failover_node(&pconn, &site, &live)? {
+                        Some(to) => {
+                            changes.extend(pconn.reassign_subgraph(&site, &to)?);
+                            moved.push(Failover {
+                                deployment: site.deployment.clone(),
+                                from: node.clone(),
+                                to,
+                            });
+                        }
+                        None => {
+                            stranded = true;
+                            error!(logger, "No live index node can take over deployment";
+                                   "deployment" => site.deployment.as_str(),
+                                   "node" => node.as_str());
+                        }
+                    }
+                }
+                if !stranded {
+                    pconn.remove_node_lease(&node)?;
+                }
+                if !changes.is_empty() {
+                    pconn.send_store_event(&self.sender, &StoreEvent::new(changes))?;
+                }
+                Ok(moved)
+            })?;
+            failovers.extend(moved);
+        }
+        Ok(failovers)
+    }
+
+    /// The node out of `live` that should take over `site`, or `None` if
+    /// the deployment rules do not allow any of them to index it
+    fn failover_node(
+        &self,
+        conn: &primary::Connection,
+        site: &Site,
+        live: &[NodeId],
+    ) -> Result<Option<NodeId>, StoreError> {
+        // Rules can match on the subgraph name; deployments without a name
+        // can only match rules that do not look at the name
+        let name = conn
+            .subgraphs_using_deployment(site)?
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+        let properties = self.for_site(site)?.deployment_properties(site)?;
+        let placement = self
+            .placer
+            .place(&name, &site.network, &properties)
+            .map_err(|msg| {
+                constraint_violation!("illegal indexer name in deployment rule: {}", msg)
+            })?;
+        let nodes: Vec<_> = match placement {
+            Some((_, nodes)) => nodes
+                .into_iter()
+                .filter(|node| live.contains(node))
+                .collect(),
+            None => live.to_vec(),
+        };
+        self.pick_node(conn, &nodes)
+    }
+
     /// Look for new unused deployments and add them to the `unused_deployments`
     /// table
     pub fn record_unused_deployments(&self) -> Result<Vec<DeploymentDetail>, StoreError> {
@@ -940,20 +1194,17 @@ impl SubgraphStoreInner {
         join_all(self.stores.values().map(|store| store.vacuum())).await
     }
 
-    pub fn rewind(&self, id: DeploymentHash, block_ptr_to: BlockPtr) -> Result<(), StoreError> {
-        let (store, site) = self.store(&id)?;
-        let event = store.rewind(site, block_ptr_to)?;
-        self.send_store_event(&event)
-    }
-
     pub(crate) async fn get_proof_of_indexing(
         &self,
         id: &DeploymentHash,
         indexer: &Option<Address>,
         block: BlockPtr,
+        version: Option<ProofOfIndexingVersion>,
     ) -> Result<Option<[u8; 32]>, StoreError> {
         let (store, site) = self.store(id)?;
-        store.get_proof_of_indexing(site, indexer, block).await
+        store
+            .get_proof_of_indexing(site, indexer, block, version)
+            .await
     }
 
     pub(crate) async fn get_public_proof_of_indexing(
@@ -963,7 +1214,30 @@ impl SubgraphStoreInner {
         block_store: Arc<impl BlockStore>,
     ) -> Result<Option<(PartialBlockPtr, [u8; 32])>, StoreError> {
         let (store, site) = self.store(id)?;
+        Self::public_proof_of_indexing(store, site, block_number, block_store).await
+    }
 
+    /// Like `get_public_proof_of_indexing`, but for the deployment
+    /// `locator`, which does not need to be the active deployment for its
+    /// hash. That makes it possible to compare the proofs of indexing of a
+    /// copy with those of the original deployment
+    pub async fn public_proof_of_indexing_for(
+        &self,
+        locator: &DeploymentLocator,
+        block_number: BlockNumber,
+        block_store: Arc<impl BlockStore>,
+    ) -> Result<Option<(PartialBlockPtr, [u8; 32])>, StoreError> {
+        let site = self.find_site(locator.id)?;
+        let store = self.for_site(&site)?;
+        Self::public_proof_of_indexing(store, site, block_number, block_store).await
+    }
+
+    async fn public_proof_of_indexing(
+        store: &Arc<DeploymentStore>,
+        site: Arc<Site>,
+        block_number: BlockNumber,
+        block_store: Arc<impl BlockStore>,
+    ) -> Result<Option<(PartialBlockPtr, [u8; 32])>, StoreError> {
         let chain_store = match block_store.chain_store(&site.network) {
             Some(chain_store) => chain_store,
             None => return Ok(None),
@@ -983,7 +1257,7 @@ impl SubgraphStoreInner {
         let block_for_poi_query = BlockPtr::new(block_hash.clone(), block_number);
         let indexer = Some(Address::zero());
         let poi = store
-            .get_proof_of_indexing(site, &indexer, block_for_poi_query)
+            .get_proof_of_indexing(site, &indexer, block_for_poi_query, None)
             .await?;
 
         Ok(poi.map(|poi| {
@@ -997,6 +1271,29 @@ impl SubgraphStoreInner {
         }))
     }
 
+    pub(crate) fn raw_manifest(
+        &self,
+        id: &DeploymentHash,
+    ) -> Result<Option<status::RawManifest>, StoreError> {
+        let (store, site) = match self.store(id) {
+            Ok(found) => found,
+            Err(StoreError::DeploymentNotFound(_)) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        store.raw_manifest(&site).map(Some)
+    }
+
+    pub(crate) async fn dynamic_data_sources(
+        &self,
+        id: &DeploymentHash,
+        manifest_idx_and_name: Vec<(u32, String)>,
+    ) -> Result<Vec<store::StoredDynamicDataSource>, StoreError> {
+        let (store, site) = self.store(id)?;
+        store
+            .load_dynamic_data_sources(site, BLOCK_NUMBER_MAX, manifest_idx_and_name)
+            .await
+    }
+
     // Only used by tests
     #[cfg(debug_assertions)]
     pub fn find(
@@ -1037,6 +1334,51 @@ impl SubgraphStoreInner {
         .await;
     }
 
+    /// Deliver the entity changes that webhooks are waiting for in each
+    /// shard
+    pub(crate) async fn deliver_webhooks(&self, logger: &Logger, client: &reqwest::Client) {
+        join_all(
+            self.stores
+                .values()
+                .map(|store| store.deliver_webhooks(logger, client)),
+        )
+        .await;
+    }
+
+    /// Call `url` whenever entities of one of `entity_types` in
+    /// `deployment` change. Returns the id of the new webhook
+    pub async fn add_entity_webhook(
+        &self,
+        deployment: &DeploymentLocator,
+        url: String,
+        entity_types: Vec<String>,
+        secret: String,
+    ) -> Result<i32, StoreError> {
+        let (store, site) = self.store(&deployment.hash)?;
+        store
+            .add_entity_webhook(site, url, entity_types, secret)
+            .await
+    }
+
+    pub async fn entity_webhooks(
+        &self,
+        deployment: &DeploymentLocator,
+    ) -> Result<Vec<EntityWebhook>, StoreError> {
+        let (store, site) = self.store(&deployment.hash)?;
+        store.entity_webhooks(site).await
+    }
+
+    /// Remove the webhook `id` of `deployment`. Returns `false` if
+    /// `deployment` has no such webhook
+    pub async fn remove_entity_webhook(
+        &self,
+        deployment: &DeploymentLocator,
+        id: i32,
+    ) -> Result<bool, StoreError> {
+        let (store, site) = self.store(&deployment.hash)?;
+        store.remove_entity_webhook(site, id).await
+    }
+
     pub fn analyze(
         &self,
         deployment: &DeploymentLocator,
@@ -1046,6 +1388,16 @@ impl SubgraphStoreInner {
         store.analyze(site, entity_name)
     }
 
+    /// Count the entities of each type in `deployment` at `block`
+    pub fn entity_counts(
+        &self,
+        deployment: &DeploymentLocator,
+        block: BlockNumber,
+    ) -> Result<BTreeMap<String, i64>, StoreError> {
+        let (store, site) = self.store(&deployment.hash)?;
+        store.entity_counts(site, block)
+    }
+
     /// Return the statistics targets for all tables of `deployment`. The
     /// first return value is the default target, and the second value maps
     /// the name of each table to a map of column name to its statistics
@@ -1094,6 +1446,14 @@ impl SubgraphStoreInner {
         store.indexes_for_entity(site, entity_name).await
     }
 
+    pub async fn index_usage(
+        &self,
+        deployment: &DeploymentLocator,
+    ) -> Result<Vec<IndexUsage>, StoreError> {
+        let (store, site) = self.store(&deployment.hash)?;
+        store.index_usage(site).await
+    }
+
     pub async fn drop_index_for_deployment(
         &self,
         deployment: &DeploymentLocator,
@@ -1103,6 +1463,65 @@ impl SubgraphStoreInner {
         store.drop_index(site, index_name).await
     }
 
+    /// Put `deployment` into maintenance mode so that queries against it
+    /// fail with `message`, or take it out of maintenance mode if `message`
+    /// is `None`
+    pub async fn set_maintenance(
+        &self,
+        deployment: &DeploymentLocator,
+        message: Option<String>,
+    ) -> Result<(), StoreError> {
+        let (store, site) = self.store(&deployment.hash)?;
+        store.set_maintenance(site, message).await
+    }
+
+    /// Set how `deployment` is retried after non-deterministic errors. The
+    /// new policy takes effect when the deployment is next started
+    pub async fn set_retry_policy(
+        &self,
+        deployment: &DeploymentLocator,
+        policy: RetryPolicy,
+    ) -> Result<(), StoreError> {
+        let (store, site) = self.store(&deployment.hash)?;
+        store.set_retry_policy(site, policy).await
+    }
+
+    /// Set the size limit of the entity cache of `deployment` in
+    /// kilobytes, or revert to the default if `size_kb` is `None`. The new
+    /// size takes effect when the deployment is next started
+    pub async fn set_entity_cache_size(
+        &self,
+        deployment: &DeploymentLocator,
+        size_kb: Option<usize>,
+    ) -> Result<(), StoreError> {
+        let (store, site) = self.store(&deployment.hash)?;
+        store.set_entity_cache_size(site, size_kb).await
+    }
+
+    /// Limit how many dynamic data sources `deployment` may create, or
+    /// remove the limit if `max` is `None`. The new limit takes effect when
+    /// the deployment is next started
+    pub async fn set_max_dynamic_data_sources(
+        &self,
+        deployment: &DeploymentLocator,
+        max: Option<usize>,
+    ) -> Result<(), StoreError> {
+        let (store, site) = self.store(&deployment.hash)?;
+        store.set_max_dynamic_data_sources(site, max).await
+    }
+
+    /// Limit how many blocks per second `deployment` may process while it
+    /// is catching up, or remove the limit if `max` is `None`. The new
+    /// limit takes effect when the deployment is next started
+    pub async fn set_max_blocks_per_second(
+        &self,
+        deployment: &DeploymentLocator,
+        max: Option<u32>,
+    ) -> Result<(), StoreError> {
+        let (store, site) = self.store(&deployment.hash)?;
+        store.set_max_blocks_per_second(site, max).await
+    }
+
     pub async fn set_account_like(
         &self,
         deployment: &DeploymentLocator,
@@ -1134,6 +1553,20 @@ impl SubgraphStoreInner {
         store.prune(reporter, site, req).await
     }
 
+    /// Estimate how much space pruning according to `req` would reclaim
+    /// for each table of the deployment without pruning anything
+    pub async fn prune_estimate(
+        &self,
+        reporter: Box<dyn PruneReporter>,
+        deployment: &DeploymentLocator,
+        req: PruneRequest,
+    ) -> Result<(Box<dyn PruneReporter>, Vec<PruneEstimate>), StoreError> {
+        let site = self.find_site(deployment.id.into())?;
+        let store = self.for_site(&site)?;
+
+        store.prune_estimate(reporter, site, req).await
+    }
+
     pub fn set_history_blocks(
         &self,
         deployment: &DeploymentLocator,
@@ -1249,6 +1682,26 @@ impl SubgraphStoreTrait for SubgraphStore {
         })
     }
 
+    fn subgraph_versions(
+        &self,
+        name: &SubgraphName,
+    ) -> Result<Option<SubgraphVersions>, StoreError> {
+        let pconn = self.primary_conn()?;
+        pconn.subgraph_versions(name)
+    }
+
+    fn restore_subgraph_versions(
+        &self,
+        name: &SubgraphName,
+        versions: &SubgraphVersions,
+    ) -> Result<(), StoreError> {
+        let pconn = self.primary_conn()?;
+        pconn.transaction(|| -> Result<_, StoreError> {
+            let changes = pconn.restore_subgraph_versions(name, versions)?;
+            pconn.send_store_event(&self.sender, &StoreEvent::new(changes))
+        })
+    }
+
     fn reassign_subgraph(
         &self,
         deployment: &DeploymentLocator,
@@ -1262,11 +1715,48 @@ impl SubgraphStoreTrait for SubgraphStore {
         })
     }
 
+    fn pause_subgraph(&self, deployment: &DeploymentLocator) -> Result<(), StoreError> {
+        let site = self.find_site(deployment.id.into())?;
+        let pconn = self.primary_conn()?;
+        pconn.transaction(|| -> Result<_, StoreError> {
+            let changes = pconn.pause_subgraph(site.as_ref())?;
+            pconn.send_store_event(&self.sender, &StoreEvent::new(changes))
+        })
+    }
+
+    fn resume_subgraph(&self, deployment: &DeploymentLocator) -> Result<(), StoreError> {
+        let site = self.find_site(deployment.id.into())?;
+        let pconn = self.primary_conn()?;
+        pconn.transaction(|| -> Result<_, StoreError> {
+            let changes = pconn.resume_subgraph(site.as_ref())?;
+            pconn.send_store_event(&self.sender, &StoreEvent::new(changes))
+        })
+    }
+
+    fn rewind(&self, id: DeploymentHash, block_ptr_to: BlockPtr) -> Result<(), StoreError> {
+        let (store, site) = self.store(&id)?;
+        let event = store.rewind(site, block_ptr_to)?;
+        self.send_store_event(&event)
+    }
+
+    fn network_name(&self, deployment: &DeploymentLocator) -> Result<String, StoreError> {
+        let site = self.find_site(deployment.id.into())?;
+        Ok(site.network.clone())
+    }
+
     fn assigned_node(&self, deployment: &DeploymentLocator) -> Result<Option<NodeId>, StoreError> {
         let site = self.find_site(deployment.id.into())?;
         self.mirror.assigned_node(site.as_ref())
     }
 
+    fn assignment_status(
+        &self,
+        deployment: &DeploymentLocator,
+    ) -> Result<Option<(NodeId, bool)>, StoreError> {
+        let site = self.find_site(deployment.id.into())?;
+        self.mirror.assignment_status(site.as_ref())
+    }
+
     fn assignments(&self, node: &NodeId) -> Result<Vec<DeploymentLocator>, StoreError> {
         self.mirror
             .assignments(node)
@@ -1287,6 +1777,35 @@ impl SubgraphStoreTrait for SubgraphStore {
         Ok(changes)
     }
 
+    fn entity_changes_between(
+        &self,
+        subgraph_id: &DeploymentHash,
+        entity_type: &EntityType,
+        from: BlockNumber,
+        to: BlockNumber,
+        after: Option<&EntityChangeCursor>,
+        first: usize,
+    ) -> Result<(Vec<EntityOperation>, Option<EntityChangeCursor>), StoreError> {
+        let (store, site) = self.store(subgraph_id)?;
+        store.get_changes_between(site, entity_type, from, to, after, first)
+    }
+
+    fn entity_versions(
+        &self,
+        subgraph_id: &DeploymentHash,
+        entity_type: &EntityType,
+        entity_id: &str,
+        block: Option<BlockNumber>,
+    ) -> Result<Vec<EntityVersion>, StoreError> {
+        let (store, site) = self.store(subgraph_id)?;
+        store.entity_versions(
+            site,
+            entity_type,
+            entity_id,
+            block.unwrap_or(BLOCK_NUMBER_MAX),
+        )
+    }
+
     fn input_schema(&self, id: &DeploymentHash) -> Result<Arc<Schema>, StoreError> {
         let (store, site) = self.store(id)?;
         let info = store.subgraph_info(&site)?;
@@ -1372,6 +1891,29 @@ impl SubgraphStoreTrait for SubgraphStore {
         }
     }
 
+    async fn stop_all_subgraphs(&self) -> Result<(), StoreError> {
+        let writables: Vec<_> = self
+            .writables
+            .lock()
+            .unwrap()
+            .drain()
+            .map(|(_, writable)| writable)
+            .collect();
+
+        // Wait for each writable to write what it has queued before
+        // stopping it. Keep going if one of them fails so that the others
+        // still get a chance to write their changes
+        let results = join_all(writables.into_iter().map(|writable| async move {
+            store::WritableStore::flush(writable.as_ref()).await?;
+            writable.stop().await
+        }))
+        .await;
+        results
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .map(|_| ())
+    }
+
     fn is_deployed(&self, id: &DeploymentHash) -> Result<bool, StoreError> {
         match self.site(id) {
             Ok(_) => Ok(true),
@@ -1385,6 +1927,12 @@ impl SubgraphStoreTrait for SubgraphStore {
         store.block_ptr(site.cheap_clone()).await
     }
 
+    async fn earliest_block(&self, id: &DeploymentHash) -> Result<BlockNumber, StoreError> {
+        let (store, _) = self.store(id)?;
+        let state = store.deployment_state_from_id(id.clone()).await?;
+        Ok(state.earliest_block_number)
+    }
+
     async fn is_healthy(&self, id: &DeploymentHash) -> Result<bool, StoreError> {
         let (store, site) = self.store(id)?;
         let health = store.health(&site).await?;
@@ -1428,4 +1976,58 @@ impl SubgraphStoreTrait for SubgraphStore {
         let info = store.subgraph_info(&site)?;
         Ok(info.instrument)
     }
+
+    fn retry_policy(&self, deployment: &DeploymentLocator) -> Result<RetryPolicy, StoreError> {
+        let site = self.find_site(deployment.id.into())?;
+        let store = self.for_site(&site)?;
+
+        store.retry_policy(&site)
+    }
+
+    fn retry_count(&self, deployment: &DeploymentLocator) -> Result<u32, StoreError> {
+        let site = self.find_site(deployment.id.into())?;
+        let store = self.for_site(&site)?;
+
+        store.retry_count(&site)
+    }
+
+    fn entity_cache_size(&self, deployment: &DeploymentLocator) -> Result<usize, StoreError> {
+        let site = self.find_site(deployment.id.into())?;
+        let store = self.for_site(&site)?;
+
+        Ok(store
+            .entity_cache_size(&site)?
+            .map(|size_kb| size_kb * 1000)
+            .unwrap_or(ENV_VARS.mappings.entity_cache_size))
+    }
+
+    fn max_dynamic_data_sources(
+        &self,
+        deployment: &DeploymentLocator,
+    ) -> Result<Option<usize>, StoreError> {
+        let site = self.find_site(deployment.id.into())?;
+        let store = self.for_site(&site)?;
+
+        store.max_dynamic_data_sources(&site)
+    }
+
+    fn max_blocks_per_second(
+        &self,
+        deployment: &DeploymentLocator,
+    ) -> Result<Option<u32>, StoreError> {
+        let site = self.find_site(deployment.id.into())?;
+        let store = self.for_site(&site)?;
+
+        store.max_blocks_per_second(&site)
+    }
+
+    fn poi_transition_version(
+        &self,
+        deployment: &DeploymentLocator,
+    ) -> Result<Option<ProofOfIndexingVersion>, StoreError> {
+        let site = self.find_site(deployment.id.into())?;
+        let store = self.for_site(&site)?;
+
+        store.poi_transition_version(&site)
+    }
 }
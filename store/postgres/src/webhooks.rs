@@ -0,0 +1,373 @@
+//! Webhooks that are called when entities of certain types of a deployment
+//! change. Webhooks live in the shard of their deployment. The changes a
+//! webhook is interested in are written to an outbox,
+//! `subgraphs.entity_webhook_delivery`, in the same transaction as the
+//! changes themselves, and a job posts them from there, batched per block.
+//! Deliveries are only removed once the receiver acknowledges them with a
+//! `2xx` response, so that every change is delivered at least once even if
+//! the receiver is down for a while or the node restarts. Receivers should
+//! use the delivery id to discard duplicates.
+//!
+//! Each request carries the signature `sha256=<hex>` of the HMAC-SHA256 of
+//! the body with the secret of the webhook in the `X-Graph-Signature`
+//! header.
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use diesel::pg::PgConnection;
+use diesel::sql_types::{Array, BigInt, Integer, Jsonb, Text};
+use diesel::{sql_query, Connection, RunQueryDsl};
+
+use graph::prelude::{
+    error, reqwest, serde_json, serde_json::json, warn, BlockPtr, EntityModification, Logger,
+    StoreError,
+};
+use graph::util::hmac::hmac_sha256;
+
+use crate::advisory_lock;
+use crate::connection_pool::ConnectionPool;
+use crate::primary::Site;
+
+/// How many deliveries are claimed at a time
+const BATCH_SIZE: i64 = 100;
+/// How long a claimed delivery is reserved for the node that claimed it. A
+/// node that dies while delivering leaves its deliveries to other nodes
+/// after this time
+const CLAIM_LEASE: Duration = Duration::from_secs(300);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long one run of delivering keeps posting deliveries. Together with
+/// `REQUEST_TIMEOUT`, this must be well below `CLAIM_LEASE` so that no
+/// other node claims deliveries that are still being posted
+const DELIVERY_DEADLINE: Duration = Duration::from_secs(60);
+/// Deliveries are retried with exponential backoff up to this delay
+const MAX_BACKOFF: Duration = Duration::from_secs(3600);
+/// Deliveries that failed this often are dropped. With `MAX_BACKOFF`, this
+/// keeps them for about a day
+const MAX_ATTEMPTS: i32 = 35;
+
+/// A webhook for the entity changes of a deployment
+#[derive(Clone, Debug, QueryableByName)]
+pub struct EntityWebhook {
+    #[sql_type = "Integer"]
+    pub id: i32,
+    #[sql_type = "Text"]
+    pub url: String,
+    #[sql_type = "Array<Text>"]
+    pub entity_types: Vec<String>,
+}
+
+pub(crate) fn create(
+    conn: &PgConnection,
+    site: &Site,
+    url: &str,
+    entity_types: &[String],
+    secret: &str,
+) -> Result<i32, StoreError> {
+    #[derive(QueryableByName)]
+    struct Id {
+        #[sql_type = "Integer"]
+        id: i32,
+    }
+
+    let id = sql_query(
+        "insert into subgraphs.entity_webhook(deployment, url, entity_types, secret) \
+         values($1, $2, $3, $4) returning id",
+    )
+    .bind::<Integer, _>(site.id)
+    .bind::<Text, _>(url)
+    .bind::<Array<Text>, _>(entity_types)
+    .bind::<Text, _>(secret)
+    .get_result::<Id>(conn)?;
+    Ok(id.id)
+}
+
+pub(crate) fn list(conn: &PgConnection, site: &Site) -> Result<Vec<EntityWebhook>, StoreError> {
+    Ok(sql_query(
+        "select id, url, entity_types from subgraphs.entity_webhook \
+          where deployment = $1 order by id",
+    )
+    .bind::<Integer, _>(site.id)
+    .load::<EntityWebhook>(conn)?)
+}
+
+/// Remove the webhook `id` of `site` and its pending deliveries. Returns
+/// `false` if there is no such webhook
+pub(crate) fn remove(conn: &PgConnection, site: &Site, id: i32) -> Result<bool, StoreError> {
+    let count = sql_query("delete from subgraphs.entity_webhook where deployment = $1 and id = $2")
+        .bind::<Integer, _>(site.id)
+        .bind::<Integer, _>(id)
+        .execute(conn)?;
+    Ok(count > 0)
+}
+
+fn insert_delivery(
+    conn: &PgConnection,
+    webhook: i32,
+    block: i32,
+    payload: serde_json::Value,
+) -> Result<(), StoreError> {
+    sql_query(
+        "insert into subgraphs.entity_webhook_delivery(webhook, block_number, payload) \
+         values($1, $2, $3)",
+    )
+    .bind::<Integer, _>(webhook)
+    .bind::<Integer, _>(block)
+    .bind::<Jsonb, _>(payload)
+    .execute(conn)?;
+    Ok(())
+}
+
+/// Queue deliveries of the changes `mods` that `site` made in `block_ptr`
+/// for each webhook that is interested in them. Must be called in the
+/// transaction that writes the changes
+pub(crate) fn enqueue(
+    conn: &PgConnection,
+    site: &Site,
+    block_ptr: &BlockPtr,
+    mods: &[EntityModification],
+) -> Result<(), StoreError> {
+    for webhook in list(conn, site)? {
+        let entity_types: HashSet<_> = webhook.entity_types.iter().map(String::as_str).collect();
+        let changes: Vec<_> = mods
+            .iter()
+            .filter(|m| entity_types.contains(m.entity_ref().entity_type.as_str()))
+            .map(|m| {
+                let key = m.entity_ref();
+                let op = match m {
+                    EntityModification::Insert { .. } => "create",
+                    EntityModification::Overwrite { .. } => "update",
+                    EntityModification::Remove { .. } => "delete",
+                };
+                json!({
+                    "op": op,
+                    "entity_type": key.entity_type.as_str(),
+                    "id": key.entity_id.as_str(),
+                    "data": m.entity(),
+                })
+            })
+            .collect();
+        if changes.is_empty() {
+            continue;
+        }
+        let payload = json!({
+            "deployment": site.deployment.as_str(),
+            "block": { "number": block_ptr.number, "hash": block_ptr.hash_hex() },
+            "changes": changes,
+        });
+        insert_delivery(conn, webhook.id, block_ptr.number, payload)?;
+    }
+    Ok(())
+}
+
+/// Queue a delivery telling every webhook of `site` that all changes after
+/// `block_ptr` were reverted. Must be called in the transaction that
+/// reverts the changes
+pub(crate) fn enqueue_revert(
+    conn: &PgConnection,
+    site: &Site,
+    block_ptr: &BlockPtr,
+) -> Result<(), StoreError> {
+    for webhook in list(conn, site)? {
+        let payload = json!({
+            "deployment": site.deployment.as_str(),
+            "block": { "number": block_ptr.number, "hash": block_ptr.hash_hex() },
+            "reverted": true,
+            "changes": [],
+        });
+        insert_delivery(conn, webhook.id, block_ptr.number, payload)?;
+    }
+    Ok(())
+}
+
+#[derive(QueryableByName)]
+struct Delivery {
+    #[sql_type = "BigInt"]
+    id: i64,
+    #[sql_type = "Integer"]
+    webhook: i32,
+    #[sql_type = "Integer"]
+    attempts: i32,
+    #[sql_type = "Text"]
+    url: String,
+    #[sql_type = "Text"]
+    secret: String,
+    #[sql_type = "Text"]
+    payload: String,
+}
+
+/// Claim deliveries that are due, oldest first. The deliveries of a webhook
+/// are only claimed while no earlier delivery of it waits for a retry or is
+/// claimed by another node, and only one node at a time claims deliveries
+/// of a webhook, so that they are posted in order
+fn claim(conn: &PgConnection) -> Result<Vec<Delivery>, StoreError> {
+    #[derive(QueryableByName)]
+    struct Webhook {
+        #[sql_type = "Integer"]
+        webhook: i32,
+    }
+
+    conn.transaction::<_, StoreError, _>(|| {
+        let webhooks: Vec<_> = sql_query(
+            "select distinct webhook from subgraphs.entity_webhook_delivery \
+              where next_attempt <= now()",
+        )
+        .load::<Webhook>(conn)?
+        .into_iter()
+        .map(|webhook| webhook.webhook)
+        .collect();
+        if webhooks.is_empty() {
+            return Ok(Vec::new());
+        }
+        // Claiming in a new statement once we have the locks makes sure
+        // that we see what another node claimed while it held them
+        let webhooks = advisory_lock::try_lock_webhooks(conn, &webhooks)?;
+
+        let mut deliveries = sql_query(
+            "update subgraphs.entity_webhook_delivery d
+                set attempts = d.attempts + 1,
+                    next_attempt = now() + $2 * interval '1 second'
+               from subgraphs.entity_webhook w
+              where w.id = d.webhook
+                and d.id in (select id from subgraphs.entity_webhook_delivery e
+                              where e.webhook = any($3)
+                                and e.next_attempt <= now()
+                                and not exists (select 1 from subgraphs.entity_webhook_delivery p
+                                                 where p.webhook = e.webhook
+                                                   and p.id < e.id
+                                                   and p.next_attempt > now())
+                              order by id
+                              limit $1
+                                for update)
+             returning d.id, d.webhook, d.attempts, w.url, w.secret, d.payload::text as payload",
+        )
+        .bind::<BigInt, _>(BATCH_SIZE)
+        .bind::<BigInt, _>(CLAIM_LEASE.as_secs() as i64)
+        .bind::<Array<Integer>, _>(&webhooks)
+        .load::<Delivery>(conn)?;
+        deliveries.sort_by_key(|delivery| delivery.id);
+        Ok(deliveries)
+    })
+}
+
+fn remove_deliveries(conn: &PgConnection, ids: &[i64]) -> Result<(), StoreError> {
+    sql_query("delete from subgraphs.entity_webhook_delivery where id = any($1)")
+        .bind::<Array<BigInt>, _>(ids)
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Retry the deliveries `ids` in `delay`. Deliveries that were claimed but
+/// not attempted do not count as an attempt
+fn reschedule(
+    conn: &PgConnection,
+    ids: &[i64],
+    delay: Duration,
+    attempted: bool,
+) -> Result<(), StoreError> {
+    sql_query(
+        "update subgraphs.entity_webhook_delivery
+            set next_attempt = now() + $2 * interval '1 second',
+                attempts = attempts - $3
+          where id = any($1)",
+    )
+    .bind::<Array<BigInt>, _>(ids)
+    .bind::<BigInt, _>(delay.as_secs() as i64)
+    .bind::<Integer, _>(if attempted { 0 } else { 1 })
+    .execute(conn)?;
+    Ok(())
+}
+
+fn backoff(attempts: i32) -> Duration {
+    Duration::from_secs(1u64 << attempts.clamp(0, 12)).min(MAX_BACKOFF)
+}
+
+async fn post(client: &reqwest::Client, delivery: &Delivery) -> Result<(), reqwest::Error> {
+    let signature = hex::encode(hmac_sha256(
+        delivery.secret.as_bytes(),
+        delivery.payload.as_bytes(),
+    ));
+    client
+        .post(&delivery.url)
+        .timeout(REQUEST_TIMEOUT)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header("X-Graph-Webhook", delivery.webhook.to_string())
+        .header("X-Graph-Delivery", delivery.id.to_string())
+        .header("X-Graph-Signature", format!("sha256={}", signature))
+        .body(delivery.payload.clone())
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// Deliver everything that is due in the shard of `pool`, but stop
+/// posting after `DELIVERY_DEADLINE` so that other jobs get to run. The
+/// deliveries of each webhook are attempted in block order, and the
+/// deliveries after one that fails are held back until it succeeds
+pub(crate) async fn deliver(
+    logger: &Logger,
+    pool: &ConnectionPool,
+    client: &reqwest::Client,
+) -> Result<(), StoreError> {
+    let start = Instant::now();
+    loop {
+        let deliveries = pool.with_conn(|conn, _| Ok(claim(conn)?)).await?;
+        let claimed = deliveries.len() as i64;
+
+        let mut done = Vec::new();
+        // Deliveries that were claimed after the deadline had passed
+        let mut released = Vec::new();
+        // The webhooks whose deliveries failed, with the failed delivery,
+        // how often it was attempted, and the deliveries held back after it
+        let mut failed: Vec<(i32, i64, i32, Vec<i64>)> = Vec::new();
+        for delivery in &deliveries {
+            if let Some((_, _, _, held_back)) = failed
+                .iter_mut()
+                .find(|(webhook, _, _, _)| *webhook == delivery.webhook)
+            {
+                held_back.push(delivery.id);
+                continue;
+            }
+            if start.elapsed() > DELIVERY_DEADLINE {
+                released.push(delivery.id);
+                continue;
+            }
+            match post(client, delivery).await {
+                Ok(()) => done.push(delivery.id),
+                Err(e) if delivery.attempts >= MAX_ATTEMPTS => {
+                    error!(logger, "Dropping webhook delivery after too many attempts";
+                           "webhook" => delivery.webhook,
+                           "delivery" => delivery.id,
+                           "attempts" => delivery.attempts,
+                           "error" => e.to_string());
+                    done.push(delivery.id);
+                }
+                Err(e) => {
+                    warn!(logger, "Webhook delivery failed, retrying";
+                          "webhook" => delivery.webhook,
+                          "delivery" => delivery.id,
+                          "attempts" => delivery.attempts,
+                          "error" => e.to_string());
+                    failed.push((delivery.webhook, delivery.id, delivery.attempts, vec![]));
+                }
+            }
+        }
+
+        let deadline_passed = !released.is_empty() || start.elapsed() > DELIVERY_DEADLINE;
+        pool.with_conn(move |conn, _| {
+            remove_deliveries(conn, &done)?;
+            for (_, id, attempts, held_back) in failed {
+                let delay = backoff(attempts);
+                reschedule(conn, &[id], delay, true)?;
+                reschedule(conn, &held_back, delay, false)?;
+            }
+            reschedule(conn, &released, Duration::ZERO, false)?;
+            Ok(())
+        })
+        .await?;
+
+        if claimed < BATCH_SIZE || deadline_passed {
+            return Ok(());
+        }
+    }
+}
@@ -1,10 +1,14 @@
 use std::collections::BTreeSet;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
+use std::time::Instant;
 use std::{collections::BTreeMap, sync::Arc};
 
 use graph::blockchain::block_stream::FirehoseCursor;
-use graph::components::store::{DeploymentCursorTracker, DerivedEntityQuery, EntityKey, ReadStore};
+use graph::components::metrics::resources::{DeploymentResources, RESOURCE_USAGE};
+use graph::components::store::{
+    DeploymentCursorTracker, DeploymentLoad, DerivedEntityQuery, EntityKey, ReadStore,
+};
 use graph::data::subgraph::schema;
 use graph::data_source::CausalityRegion;
 use graph::prelude::{
@@ -13,6 +17,7 @@ use graph::prelude::{
 };
 use graph::slog::info;
 use graph::util::bounded_queue::BoundedQueue;
+use graph::util::cache_weight::CacheWeight;
 use graph::{
     cheap_clone::CheapClone,
     components::store::{self, EntityType, WritableStore as WritableStoreTrait},
@@ -26,6 +31,7 @@ use graph::{
 use store::StoredDynamicDataSource;
 
 use crate::deployment_store::DeploymentStore;
+use crate::entity_sink::EntitySink;
 use crate::primary::DeploymentId;
 use crate::retry;
 use crate::{primary, primary::Site, relational::Layout, SubgraphStore};
@@ -68,6 +74,8 @@ struct SyncStore {
     writable: Arc<DeploymentStore>,
     site: Arc<Site>,
     input_schema: Arc<Schema>,
+    resources: Arc<DeploymentResources>,
+    entity_sink: Option<Arc<EntitySink>>,
 }
 
 impl SyncStore {
@@ -79,12 +87,16 @@ impl SyncStore {
         let store = WritableSubgraphStore(subgraph_store.clone());
         let writable = subgraph_store.for_site(site.as_ref())?.clone();
         let input_schema = subgraph_store.input_schema(&site.deployment)?;
+        let resources = RESOURCE_USAGE.deployment(site.deployment.as_str());
+        let entity_sink = subgraph_store.entity_sink();
         Ok(Self {
             logger,
             store,
             writable,
             site,
             input_schema,
+            resources,
+            entity_sink,
         })
     }
 
@@ -146,6 +158,9 @@ impl SyncStore {
                 block_ptr_to.clone(),
                 firehose_cursor,
             )?;
+            if let Some(sink) = &self.entity_sink {
+                sink.revert(&self.logger, &self.site.deployment, &block_ptr_to);
+            }
 
             self.try_send_store_event(event)
         })
@@ -212,6 +227,7 @@ impl SyncStore {
         processed_data_sources: &[StoredDynamicDataSource],
     ) -> Result<(), StoreError> {
         retry::forever(&self.logger, "transact_block_operations", move || {
+            let start = Instant::now();
             let event = self.writable.transact_block_operations(
                 &self.logger,
                 self.site.clone(),
@@ -224,6 +240,16 @@ impl SyncStore {
                 manifest_idx_and_name,
                 processed_data_sources,
             )?;
+            let bytes_written = mods
+                .iter()
+                .filter_map(|m| m.entity())
+                .map(|entity| entity.weight() as u64)
+                .sum();
+            self.resources
+                .add_store_write(start.elapsed(), bytes_written);
+            if let Some(sink) = &self.entity_sink {
+                sink.changes(&self.logger, &self.site.deployment, block_ptr_to, mods);
+            }
 
             let _section = stopwatch.start_section("send_store_event");
             self.try_send_store_event(event)?;
@@ -281,6 +307,35 @@ impl SyncStore {
         })
     }
 
+    fn report_load(&self, load: DeploymentLoad) -> Result<(), StoreError> {
+        // Load reports are only advisory; there is no need to retry since
+        // the next report will replace this one anyway
+        self.store.primary_conn()?.report_load(&self.site, &load)
+    }
+
+    async fn set_retry_count(&self, count: u32) -> Result<(), StoreError> {
+        retry::forever_async(&self.logger, "set_retry_count", || async {
+            self.writable
+                .set_retry_count(self.site.cheap_clone(), count)
+                .await
+        })
+        .await
+    }
+
+    // Snapshots only make restarts faster; there is no need to retry, and
+    // retrying would hold up stopping the deployment
+    async fn save_entity_cache_snapshot(&self, keys: Vec<EntityKey>) -> Result<(), StoreError> {
+        self.writable
+            .save_entity_cache_snapshot(self.site.cheap_clone(), keys)
+            .await
+    }
+
+    async fn entity_cache_snapshot(&self) -> Result<Vec<EntityKey>, StoreError> {
+        self.writable
+            .entity_cache_snapshot(self.site.cheap_clone())
+            .await
+    }
+
     async fn load_dynamic_data_sources(
         &self,
         block: BlockNumber,
@@ -1205,6 +1260,22 @@ impl WritableStoreTrait for WritableStore {
         self.store.unassign_subgraph(&self.store.site)
     }
 
+    fn report_load(&self, load: DeploymentLoad) -> Result<(), StoreError> {
+        self.store.report_load(load)
+    }
+
+    async fn set_retry_count(&self, count: u32) -> Result<(), StoreError> {
+        self.store.set_retry_count(count).await
+    }
+
+    async fn save_entity_cache_snapshot(&self, keys: Vec<EntityKey>) -> Result<(), StoreError> {
+        self.store.save_entity_cache_snapshot(keys).await
+    }
+
+    async fn entity_cache_snapshot(&self) -> Result<Vec<EntityKey>, StoreError> {
+        self.store.entity_cache_snapshot().await
+    }
+
     async fn load_dynamic_data_sources(
         &self,
         manifest_idx_and_name: Vec<(u32, String)>,
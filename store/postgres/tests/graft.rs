@@ -142,6 +142,7 @@ async fn insert_test_data(store: Arc<DieselSubgraphStore>) -> DeploymentLocator
         data_sources: vec![],
         graft: None,
         templates: vec![],
+        handler_limits: Default::default(),
         chain: PhantomData,
     };
 
@@ -647,6 +648,29 @@ fn prune() {
             for block in 2..=5 {
                 check_at_block(&store, &src, strategy, block, vec!["1", "2", "3"]);
             }
+
+            // Grafting onto the pruned deployment only works from its
+            // earliest block on
+            assert_eq!(3, store.earliest_block(&src.hash).await?);
+            let err = create_grafted_subgraph(
+                &DeploymentHash::new("grafted_pruned").unwrap(),
+                GRAFT_GQL,
+                src.hash.as_str(),
+                BLOCKS[2].clone(),
+            )
+            .await
+            .expect_err("grafting onto block 2 fails");
+            assert!(err
+                .to_string()
+                .contains("only has data starting at block 3"));
+            create_grafted_subgraph(
+                &DeploymentHash::new("grafted_earliest").unwrap(),
+                GRAFT_GQL,
+                src.hash.as_str(),
+                BLOCKS[3].clone(),
+            )
+            .await
+            .expect("grafting onto block 3 works");
             Ok(())
         })
     }
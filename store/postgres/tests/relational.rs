@@ -1,12 +1,13 @@
 //! Test mapping of GraphQL schema to a relational schema
 use diesel::connection::SimpleConnection as _;
 use diesel::pg::PgConnection;
-use graph::components::store::EntityKey;
+use graph::components::store::{EntityChangeCursor, EntityKey};
 use graph::data::store::scalar;
 use graph::entity;
 use graph::prelude::{
     o, slog, tokio, web3::types::H256, DeploymentHash, Entity, EntityCollection, EntityFilter,
-    EntityOrder, EntityQuery, Logger, Schema, StopwatchMetrics, Value, ValueType, BLOCK_NUMBER_MAX,
+    EntityOperation, EntityOrder, EntityQuery, Logger, Schema, StopwatchMetrics, Value, ValueType,
+    BLOCK_NUMBER_MAX,
 };
 use graph::prelude::{BlockNumber, MetricsRegistry};
 use graph_store_postgres::layout_for_tests::set_account_like;
@@ -1129,10 +1130,97 @@ fn check_block_finds() {
             .check(
                 vec!["1"],
                 user_query().filter(EntityFilter::ChangeBlockGte(1)),
+            )
+            // Before the update, we should get the entities that did not change
+            .check(
+                vec!["2", "3"],
+                user_query().filter(EntityFilter::ChangeBlockLt(1)),
+            )
+            // Order by the block at which entities changed, then by id
+            .check(
+                vec!["2", "3", "1"],
+                user_query().order(EntityOrder::ChangeBlockAscending),
+            )
+            .check(
+                vec!["1", "3", "2"],
+                user_query().order(EntityOrder::ChangeBlockDescending),
             );
     });
 }
 
+#[test]
+fn find_changes_between() {
+    fn changes(
+        conn: &PgConnection,
+        layout: &Layout,
+        from: BlockNumber,
+        to: BlockNumber,
+    ) -> Vec<String> {
+        page(conn, layout, from, to, None, 100).0
+    }
+
+    fn page(
+        conn: &PgConnection,
+        layout: &Layout,
+        from: BlockNumber,
+        to: BlockNumber,
+        after: Option<&EntityChangeCursor>,
+        first: usize,
+    ) -> (Vec<String>, Option<EntityChangeCursor>) {
+        let (changes, next) = layout
+            .find_changes_between(conn, &EntityType::from("User"), from, to, after, first)
+            .expect("find_changes_between failed");
+        let changes = changes
+            .into_iter()
+            .map(|op| match op {
+                EntityOperation::Set { key, .. } => format!("set {}", key.entity_id),
+                EntityOperation::Remove { key } => format!("remove {}", key.entity_id),
+            })
+            .collect();
+        (changes, next)
+    }
+
+    run_test(move |conn, layout| {
+        insert_users(conn, layout);
+        update_user_entity(
+            conn,
+            layout,
+            "1",
+            "User",
+            "Johnton",
+            "tonofjohn@email.com",
+            67_i32,
+            184.4,
+            false,
+            Some("yellow"),
+            None,
+            1,
+        );
+        layout
+            .delete(conn, &EntityType::from("User"), &["2"], 2, &MOCK_STOPWATCH)
+            .expect("Failed to delete");
+
+        // Changes are ordered by block and id
+        assert_eq!(vec!["set 1", "remove 2"], changes(conn, layout, 0, 2));
+        assert_eq!(vec!["set 1"], changes(conn, layout, 0, 1));
+        assert_eq!(vec!["remove 2"], changes(conn, layout, 1, 2));
+        assert!(changes(conn, layout, 2, 2).is_empty());
+
+        // Page through the changes one at a time
+        let (changes, next) = page(conn, layout, 0, 2, None, 1);
+        assert_eq!(vec!["set 1"], changes);
+        let next = next.expect("there are more changes");
+        assert_eq!("1/1", next.to_string());
+        let (changes, next) = page(conn, layout, 0, 2, Some(&next), 1);
+        assert_eq!(vec!["remove 2"], changes);
+        let next = next.expect("the page is full");
+        assert_eq!("2/2", next.to_string());
+        let (changes, next) = page(conn, layout, 0, 2, Some(&next), 1);
+        assert!(changes.is_empty());
+        assert_eq!(None, next);
+    });
+}
+
 #[test]
 fn check_find() {
     run_test(move |conn, layout| {
@@ -1781,6 +1869,19 @@ fn check_filters() {
             .check(vec!["a2b", "a3"], filter_not_in(vec![&a1, &a2]))
             .check(vec!["a2", "a2b"], filter_not_in(vec![&a1, &a3]));
 
+        // Prefixes of ids are matched literally
+        let id_starts_with =
+            |prefix: &str| EntityFilter::StartsWith("id".to_owned(), prefix.into());
+        let id_not_starts_with =
+            |prefix: &str| EntityFilter::NotStartsWith("id".to_owned(), prefix.into());
+        checker
+            .check(vec!["a2", "a2b"], id_starts_with("a2"))
+            .check(vec!["a1", "a2", "a2b", "a3"], id_starts_with("a"))
+            .check(vec![], id_starts_with("a_"))
+            .check(vec![], id_starts_with("b"))
+            .check(vec!["a1", "a3"], id_not_starts_with("a2"))
+            .check(vec!["a1", "a2", "a2b", "a3"], id_not_starts_with("a%"));
+
         update_entity_at(
             conn,
             layout,
@@ -14,8 +14,8 @@ use std::{collections::BTreeMap, sync::Arc};
 
 use graph::prelude::{
     o, slog, web3::types::H256, AttributeNames, ChildMultiplicity, DeploymentHash, Entity,
-    EntityCollection, EntityLink, EntityWindow, Logger, ParentLink, Schema, StopwatchMetrics,
-    Value, WindowAttribute, BLOCK_NUMBER_MAX,
+    EntityCollection, EntityFilter, EntityLink, EntityWindow, Logger, ParentLink, Schema,
+    StopwatchMetrics, Value, WindowAttribute, BLOCK_NUMBER_MAX,
 };
 use graph::{
     components::store::EntityType,
@@ -536,3 +536,72 @@ fn query() {
         assert_eq!(vec![ROOT, ROOT], things);
     });
 }
+
+#[test]
+fn bytes_starts_and_ends_with() {
+    fn fetch(conn: &PgConnection, layout: &Layout, filter: EntityFilter) -> Vec<String> {
+        let id = DeploymentHash::new("QmXW3qvxV7zXnwRntpj7yoK8HZVtaraZ67uMqaLRvXdxha").unwrap();
+        let coll = EntityCollection::All(vec![(THING.clone(), AttributeNames::All)]);
+        let query = EntityQuery::new(id, BLOCK_NUMBER_MAX, coll)
+            .filter(filter)
+            .first(10);
+        layout
+            .query::<Entity>(&LOGGER, conn, query)
+            .map(|(entities, _)| entities)
+            .expect("the query succeeds")
+            .into_iter()
+            .map(|e| e.id().expect("entities have an id"))
+            .collect::<Vec<_>>()
+    }
+
+    fn bytes(s: &str) -> Value {
+        Value::Bytes(scalar::Bytes::from_str(s).unwrap())
+    }
+
+    run_test(|conn, layout| {
+        make_thing_tree(conn, layout);
+
+        let things = fetch(
+            conn,
+            layout,
+            EntityFilter::StartsWith("id".to_string(), bytes("0xbabe")),
+        );
+        assert_eq!(vec![CHILD1, CHILD2], things);
+
+        let things = fetch(
+            conn,
+            layout,
+            EntityFilter::StartsWith("id".to_string(), bytes("0xfafa02")),
+        );
+        assert_eq!(vec![GRANDCHILD2], things);
+
+        let things = fetch(
+            conn,
+            layout,
+            EntityFilter::NotStartsWith("id".to_string(), bytes("0xfa")),
+        );
+        assert_eq!(vec![CHILD1, CHILD2, ROOT], things);
+
+        let things = fetch(
+            conn,
+            layout,
+            EntityFilter::EndsWith("id".to_string(), bytes("0x02")),
+        );
+        assert_eq!(vec![CHILD2, GRANDCHILD2], things);
+
+        let things = fetch(
+            conn,
+            layout,
+            EntityFilter::NotEndsWith("id".to_string(), bytes("0x01")),
+        );
+        assert_eq!(vec![CHILD2, ROOT, GRANDCHILD2], things);
+
+        // Bytes can not be matched case-insensitively
+        let id = DeploymentHash::new("QmXW3qvxV7zXnwRntpj7yoK8HZVtaraZ67uMqaLRvXdxha").unwrap();
+        let coll = EntityCollection::All(vec![(THING.clone(), AttributeNames::All)]);
+        let query = EntityQuery::new(id, BLOCK_NUMBER_MAX, coll).filter(
+            EntityFilter::StartsWithNoCase("id".to_string(), bytes("0xbabe")),
+        );
+        assert!(layout.query::<Entity>(&LOGGER, conn, query).is_err());
+    });
+}
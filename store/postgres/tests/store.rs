@@ -1,3 +1,4 @@
+use diesel::connection::SimpleConnection as _;
 use graph::blockchain::block_stream::FirehoseCursor;
 use graph::data::graphql::ext::TypeDefinitionExt;
 use graph::data::query::QueryTarget;
@@ -165,6 +166,7 @@ async fn insert_test_data(store: Arc<DieselSubgraphStore>) -> DeploymentLocator
         data_sources: vec![],
         graft: None,
         templates: vec![],
+        handler_limits: Default::default(),
         chain: PhantomData,
     };
 
@@ -1280,6 +1282,7 @@ fn entity_changes_are_fired_and_forwarded_to_subscriptions() {
             data_sources: vec![],
             graft: None,
             templates: vec![],
+            handler_limits: Default::default(),
             chain: PhantomData,
         };
 
@@ -2164,3 +2167,36 @@ fn reorg_tracking() {
         check_state!(store, 5, 3, 2);
     })
 }
+
+#[test]
+fn pending_migrations_does_not_migrate() {
+    run_test_with_conn(|conn| {
+        let pool = unready_primary_pool();
+        assert!(pool.pending_migrations().unwrap().is_empty());
+
+        // Pretend that the last migration has not run yet
+        conn.batch_execute(
+            "delete from __diesel_schema_migrations
+              where version = (select max(version) from __diesel_schema_migrations)",
+        )
+        .unwrap();
+
+        let pending = pool.pending_migrations().unwrap();
+        assert_eq!(1, pending.len());
+        let version = pending[0].version;
+
+        // Listing the pending migrations must not have applied them
+        let pending = pool.pending_migrations().unwrap();
+        assert_eq!(
+            vec![version],
+            pending.iter().map(|m| m.version).collect::<Vec<_>>()
+        );
+
+        conn.batch_execute(&format!(
+            "insert into __diesel_schema_migrations(version) values ('{}')",
+            version
+        ))
+        .unwrap();
+        assert!(pool.pending_migrations().unwrap().is_empty());
+    })
+}
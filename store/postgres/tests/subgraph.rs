@@ -1,7 +1,7 @@
 use graph::{
     components::{
         server::index_node::VersionInfo,
-        store::{DeploymentId, DeploymentLocator, StatusStore},
+        store::{DeploymentId, DeploymentLoad, DeploymentLocator, StatusStore},
     },
     data::query::QueryTarget,
     data::subgraph::schema::SubgraphHealth,
@@ -21,7 +21,7 @@ use graph::{
 };
 use graph_store_postgres::layout_for_tests::Connection as Primary;
 use graph_store_postgres::SubgraphStore;
-use std::{collections::HashSet, marker::PhantomData, sync::Arc};
+use std::{collections::HashSet, marker::PhantomData, sync::Arc, time::Duration};
 use test_store::*;
 
 const SUBGRAPH_GQL: &str = "
@@ -107,6 +107,151 @@ fn reassign_subgraph() {
     })
 }
 
+#[test]
+fn pause_and_resume_subgraph() {
+    run_test_sequentially(|store| async move {
+        let id = DeploymentHash::new("pauseSubgraph").unwrap();
+        remove_subgraphs();
+        let deployment = create_test_subgraph(&id, SUBGRAPH_GQL).await;
+        let store = store.subgraph_store();
+
+        let node = store.assigned_node(&deployment).unwrap().unwrap();
+        assert!(store.assignments(&node).unwrap().contains(&deployment));
+
+        // Pausing keeps the assignment, but the deployment is not among the
+        // deployments the node should index anymore
+        let expected = vec![StoreEvent::new(vec![assigned(&deployment)])];
+        let (_, events) = tap_store_events(|| store.pause_subgraph(&deployment).unwrap());
+        assert_eq!(expected, events);
+        assert_eq!(
+            Some((node.clone(), true)),
+            store.assignment_status(&deployment).unwrap()
+        );
+        assert_eq!(
+            Some(node.clone()),
+            store.assigned_node(&deployment).unwrap()
+        );
+        assert!(!store.assignments(&node).unwrap().contains(&deployment));
+
+        // Pausing a paused deployment does nothing
+        let (_, events) = tap_store_events(|| store.pause_subgraph(&deployment).unwrap());
+        assert!(events.is_empty());
+
+        let (_, events) = tap_store_events(|| store.resume_subgraph(&deployment).unwrap());
+        assert_eq!(expected, events);
+        assert_eq!(
+            Some((node.clone(), false)),
+            store.assignment_status(&deployment).unwrap()
+        );
+        assert!(store.assignments(&node).unwrap().contains(&deployment));
+    })
+}
+
+#[test]
+fn least_loaded_node() {
+    run_test_sequentially(|store| async move {
+        let id = DeploymentHash::new("loadedSubgraph").unwrap();
+        remove_subgraphs();
+        let deployment = create_test_subgraph(&id, SUBGRAPH_GQL).await;
+        let store = store.subgraph_store();
+        let primary = primary_connection();
+
+        let busy = NodeId::new("busy").unwrap();
+        let idle = NodeId::new("idle").unwrap();
+        let nodes = vec![busy.clone(), idle.clone()];
+        store.reassign_subgraph(&deployment, &busy).unwrap();
+
+        // Without any reports, nodes with fewer deployments have less load
+        assert_eq!(
+            Some(idle.clone()),
+            primary.least_loaded_node(&nodes).unwrap()
+        );
+
+        let load = DeploymentLoad {
+            blocks_behind: 10,
+            handler_time: 0.5,
+            blocks_per_second: 2.0,
+        };
+        store
+            .cheap_clone()
+            .writable(LOGGER.clone(), deployment.id)
+            .await
+            .expect("can get writable")
+            .report_load(load)
+            .unwrap();
+
+        let loads = primary.node_loads(&nodes).unwrap();
+        assert_eq!(busy, loads[0].0);
+        assert_eq!(0.5, loads[0].1.handler_time);
+        assert_eq!(10, loads[0].1.blocks_behind);
+        assert_eq!(1, loads[0].1.deployments);
+        assert_eq!(0, loads[1].1.deployments);
+        assert_eq!(
+            Some(idle.clone()),
+            primary.least_loaded_node(&nodes).unwrap()
+        );
+
+        // Paused deployments do not put any load on their node
+        store.pause_subgraph(&deployment).unwrap();
+        let loads = primary.node_loads(&nodes).unwrap();
+        assert_eq!(0, loads[0].1.deployments);
+        assert_eq!(0.0, loads[0].1.handler_time);
+    })
+}
+
+#[test]
+fn fail_over_dead_nodes() {
+    run_test_sequentially(|store| async move {
+        let id = DeploymentHash::new("failoverSubgraph").unwrap();
+        remove_subgraphs();
+        let deployment = create_test_subgraph(&id, SUBGRAPH_GQL).await;
+        let store = store.subgraph_store();
+        let primary = primary_connection();
+
+        // The test configuration only allows the `default` node to index
+        let dead = NodeId::new("dead").unwrap();
+        let live = NodeId::new("default").unwrap();
+        primary.remove_node_lease(&dead).unwrap();
+        primary.remove_node_lease(&live).unwrap();
+        store.reassign_subgraph(&deployment, &dead).unwrap();
+
+        // Nodes that never took out a lease are not failed over
+        let grace = Duration::from_secs(1);
+        assert!(store
+            .fail_over_dead_nodes(&LOGGER, grace)
+            .unwrap()
+            .is_empty());
+
+        store.renew_node_lease(&dead).unwrap();
+        std::thread::sleep(Duration::from_millis(1500));
+        store.renew_node_lease(&live).unwrap();
+        assert_eq!(
+            vec![dead.clone()],
+            primary.nodes_by_lease(grace, true).unwrap()
+        );
+        assert_eq!(
+            vec![live.clone()],
+            primary.nodes_by_lease(grace, false).unwrap()
+        );
+
+        let (failovers, events) =
+            tap_store_events(|| store.fail_over_dead_nodes(&LOGGER, grace).unwrap());
+        assert_eq!(1, failovers.len());
+        assert_eq!(id, failovers[0].deployment);
+        assert_eq!(dead, failovers[0].from);
+        assert_eq!(live, failovers[0].to);
+        assert_eq!(vec![StoreEvent::new(vec![assigned(&deployment)])], events);
+        assert_eq!(
+            Some(live.clone()),
+            store.assigned_node(&deployment).unwrap()
+        );
+
+        // The lease of the dead node is gone once all its deployments moved
+        assert!(primary.nodes_by_lease(grace, true).unwrap().is_empty());
+        primary.remove_node_lease(&live).unwrap();
+    })
+}
+
 #[test]
 fn create_subgraph() {
     const SUBGRAPH_NAME: &str = "create/subgraph";
@@ -145,6 +290,7 @@ fn create_subgraph() {
             data_sources: vec![],
             graft: None,
             templates: vec![],
+            handler_limits: Default::default(),
             chain: PhantomData,
         };
         let deployment = DeploymentCreate::new(String::new(), &manifest, None);
@@ -43,6 +43,7 @@ async fn insert_test_data(store: Arc<DieselSubgraphStore>) -> DeploymentLocator
         data_sources: vec![],
         graft: None,
         templates: vec![],
+        handler_limits: Default::default(),
         chain: PhantomData,
     };
 
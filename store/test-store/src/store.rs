@@ -19,12 +19,13 @@ use graph_graphql::prelude::{
 use graph_graphql::test_support::GraphQLMetrics;
 use graph_node::config::{Config, Opt};
 use graph_node::store_builder::StoreBuilder;
+use graph_store_postgres::connection_pool::{ConnectionPool, PoolCoordinator, PoolName};
 use graph_store_postgres::layout_for_tests::FAKE_NETWORK_SHARED;
-use graph_store_postgres::{connection_pool::ConnectionPool, Shard, SubscriptionManager};
 use graph_store_postgres::{
-    BlockStore as DieselBlockStore, DeploymentPlacer, SubgraphStore as DieselSubgraphStore,
-    PRIMARY_SHARD,
+    BlockStore as DieselBlockStore, DeploymentPlacer, DeploymentProperties,
+    SubgraphStore as DieselSubgraphStore, PRIMARY_SHARD,
 };
+use graph_store_postgres::{Shard, SubscriptionManager};
 use hex_literal::hex;
 use lazy_static::lazy_static;
 use std::collections::BTreeSet;
@@ -143,7 +144,9 @@ pub fn remove_subgraphs() {
 }
 
 pub fn place(name: &str) -> Result<Option<(Vec<Shard>, Vec<NodeId>)>, String> {
-    CONFIG.deployment.place(name, NETWORK_NAME)
+    CONFIG
+        .deployment
+        .place(name, NETWORK_NAME, &DeploymentProperties::default())
 }
 
 pub async fn create_subgraph(
@@ -163,6 +166,7 @@ pub async fn create_subgraph(
         data_sources: vec![],
         graft: None,
         templates: vec![],
+        handler_limits: Default::default(),
         chain: PhantomData,
     };
 
@@ -401,7 +405,7 @@ where
 
 /// Run a GraphQL query against the `STORE`
 pub async fn execute_subgraph_query(query: Query, target: QueryTarget) -> QueryResults {
-    execute_subgraph_query_internal(query, target, None, None).await
+    execute_subgraph_query_internal(query, target, None, None, None).await
 }
 
 pub async fn execute_subgraph_query_with_deadline(
@@ -409,7 +413,15 @@ pub async fn execute_subgraph_query_with_deadline(
     target: QueryTarget,
     deadline: Option<Instant>,
 ) -> QueryResults {
-    execute_subgraph_query_internal(query, target, None, deadline).await
+    execute_subgraph_query_internal(query, target, None, deadline, None).await
+}
+
+pub async fn execute_subgraph_query_with_max_result_bytes(
+    query: Query,
+    target: QueryTarget,
+    max_result_bytes: usize,
+) -> QueryResults {
+    execute_subgraph_query_internal(query, target, None, None, Some(max_result_bytes)).await
 }
 
 /// Like `try!`, but we return the contents of an `Err`, not the
@@ -433,6 +445,7 @@ async fn execute_subgraph_query_internal(
     target: QueryTarget,
     max_complexity: Option<u64>,
     deadline: Option<Instant>,
+    max_result_bytes: Option<usize>,
 ) -> QueryResults {
     let logger = Logger::root(slog::Discard, o!());
     let (id, version) = match target {
@@ -491,6 +504,7 @@ async fn execute_subgraph_query_internal(
                     max_first: std::u32::MAX,
                     max_skip: std::u32::MAX,
                     trace,
+                    max_result_bytes,
                 },
             )
             .await,
@@ -577,6 +591,25 @@ pub fn primary_connection() -> graph_store_postgres::layout_for_tests::Connectio
     graph_store_postgres::layout_for_tests::Connection::new(conn)
 }
 
+/// A new pool for the primary that has not been set up, and therefore has
+/// not run any migrations yet
+pub fn unready_primary_pool() -> ConnectionPool {
+    let shard = CONFIG
+        .stores
+        .get(PRIMARY_SHARD.as_str())
+        .expect("the primary shard is configured");
+    let coord = Arc::new(PoolCoordinator::new(Arc::new(vec![])));
+    coord.create_pool(
+        &LOGGER,
+        PRIMARY_SHARD.as_str(),
+        PoolName::Main,
+        shard.connection.clone(),
+        1,
+        None,
+        METRICS_REGISTRY.clone(),
+    )
+}
+
 pub fn primary_mirror() -> graph_store_postgres::layout_for_tests::Mirror {
     let pool = PRIMARY_POOL.clone();
     let map = HashMap::from_iter(Some((PRIMARY_SHARD.clone(), pool)));
@@ -6,6 +6,7 @@ use super::{
     test_ptr, MutexBlockStreamBuilder, NoopAdapterSelector, NoopRuntimeAdapter,
     StaticBlockRefetcher, StaticStreamBuilder, Stores, TestChain, NODE_ID,
 };
+use graph::blockchain::block_stream::BlockStreamBuilder;
 use graph::blockchain::client::ChainClient;
 use graph::blockchain::{BlockPtr, TriggersAdapterSelector};
 use graph::cheap_clone::CheapClone;
@@ -32,6 +33,16 @@ pub async fn chain(
         triggers_in_block_sleep: Duration::ZERO,
         x: PhantomData,
     }));
+    let static_block_stream = Arc::new(StaticStreamBuilder { chain: blocks });
+    chain_with_stream(static_block_stream, stores, triggers_adapter).await
+}
+
+/// A chain whose blocks come from `block_stream`
+pub(crate) async fn chain_with_stream(
+    block_stream: Arc<dyn BlockStreamBuilder<Chain>>,
+    stores: &Stores,
+    triggers_adapter: Arc<dyn TriggersAdapterSelector<Chain>>,
+) -> TestChain<Chain> {
     let logger = graph::log::logger(true);
     let mock_registry = Arc::new(MetricsRegistry::mock());
     let logger_factory = LoggerFactory::new(logger.cheap_clone(), None, mock_registry.clone());
@@ -54,8 +65,7 @@ pub async fn chain(
 
     let client = Arc::new(ChainClient::<Chain>::new_firehose(firehose_endpoints));
 
-    let static_block_stream = Arc::new(StaticStreamBuilder { chain: blocks });
-    let block_stream_builder = Arc::new(MutexBlockStreamBuilder(Mutex::new(static_block_stream)));
+    let block_stream_builder = Arc::new(MutexBlockStreamBuilder(Mutex::new(block_stream)));
 
     let chain = Chain::new(
         logger_factory,
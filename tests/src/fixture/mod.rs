@@ -1,4 +1,5 @@
 pub mod ethereum;
+pub mod replay;
 
 use std::marker::PhantomData;
 use std::sync::Mutex;
@@ -18,25 +19,30 @@ use graph::blockchain::{
 use graph::cheap_clone::CheapClone;
 use graph::components::metrics::MetricsRegistry;
 use graph::components::store::{BlockStore, DeploymentLocator};
+use graph::components::subgraph::Notifier;
 use graph::data::graphql::effort::LoadManager;
 use graph::data::query::{Query, QueryTarget};
 use graph::data::subgraph::schema::{SubgraphError, SubgraphHealth};
+use graph::endpoint::ProviderInfo;
 use graph::env::EnvVars;
 use graph::ipfs_client::IpfsClient;
 use graph::prelude::ethabi::ethereum_types::H256;
 use graph::prelude::serde_json::{self, json};
 use graph::prelude::{
-    async_trait, r, ApiVersion, BigInt, BlockNumber, DeploymentHash, GraphQlRunner as _,
+    async_trait, r, reqwest, ApiVersion, BigInt, BlockNumber, DeploymentHash, GraphQlRunner as _,
     LoggerFactory, NodeId, QueryError, SubgraphAssignmentProvider, SubgraphCountMetric,
     SubgraphName, SubgraphRegistrar, SubgraphStore as _, SubgraphVersionSwitchingMode,
     TriggerProcessor,
 };
 use graph::slog::crit;
-use graph_core::polling_monitor::ipfs_service;
+use graph_core::polling_monitor::{
+    arweave_gateways, arweave_service, ipfs_service, object_store_service, ObjectStoreEndpoints,
+};
 use graph_core::{
     LinkResolver, SubgraphAssignmentProvider as IpfsSubgraphAssignmentProvider,
     SubgraphInstanceManager, SubgraphRegistrar as IpfsSubgraphRegistrar, SubgraphTriggerProcessor,
 };
+use graph_node::dev::LocalSubgraph;
 use graph_node::manager::PanicSubscriptionManager;
 use graph_node::{config::Config, store_builder::StoreBuilder};
 use graph_runtime_wasm::RuntimeHostBuilder;
@@ -272,10 +278,16 @@ graph::prelude::lazy_static! {
 }
 
 pub async fn stores(store_config_path: &str) -> Stores {
+    let config = read_to_string(store_config_path).await.unwrap();
+    stores_with_config(&config).await
+}
+
+/// Like `stores`, but with the store configuration `config` itself rather
+/// than the path of a file with it
+pub async fn stores_with_config(config: &str) -> Stores {
     let _mutex_guard = STORE_MUTEX.lock().unwrap();
 
     let config = {
-        let config = read_to_string(store_config_path).await.unwrap();
         let db_url = match std::env::var("THEGRAPH_STORE_POSTGRES_DIESEL_URL") {
             Ok(url) => url,
             Err(VarError::NotPresent) => panic!(
@@ -324,6 +336,49 @@ pub async fn setup<C: Blockchain>(
     chain: &TestChain<C>,
     graft_block: Option<BlockPtr>,
     env_vars: Option<EnvVars>,
+) -> TestContext {
+    setup_inner(
+        subgraph_name,
+        hash,
+        None,
+        stores,
+        chain,
+        graft_block,
+        env_vars,
+    )
+    .await
+}
+
+/// Like `setup`, but for a subgraph that is loaded from disk rather than
+/// from IPFS
+pub async fn setup_local<C: Blockchain>(
+    subgraph_name: SubgraphName,
+    subgraph: LocalSubgraph,
+    stores: &Stores,
+    chain: &TestChain<C>,
+    env_vars: Option<EnvVars>,
+) -> TestContext {
+    let hash = subgraph.hash.clone();
+    setup_inner(
+        subgraph_name,
+        &hash,
+        Some(subgraph),
+        stores,
+        chain,
+        None,
+        env_vars,
+    )
+    .await
+}
+
+async fn setup_inner<C: Blockchain>(
+    subgraph_name: SubgraphName,
+    hash: &DeploymentHash,
+    local: Option<LocalSubgraph>,
+    stores: &Stores,
+    chain: &TestChain<C>,
+    graft_block: Option<BlockPtr>,
+    env_vars: Option<EnvVars>,
 ) -> TestContext {
     let env_vars = Arc::new(match env_vars {
         Some(ev) => ev,
@@ -349,15 +404,40 @@ pub async fn setup<C: Blockchain>(
         vec![ipfs.cheap_clone()],
         Default::default(),
     ));
+    if let Some(local) = local {
+        link_resolver.add_local_subgraph(&local.hash, local.manifest, local.files);
+    }
     let ipfs_service = ipfs_service(
-        ipfs.cheap_clone(),
+        vec![ipfs.cheap_clone()],
+        None,
         env_vars.mappings.max_ipfs_file_bytes as u64,
         env_vars.mappings.ipfs_timeout,
+        env_vars.mappings.ipfs_unhealthy_backoff,
         env_vars.mappings.ipfs_request_limit,
     );
+    let arweave_service = arweave_service(
+        reqwest::Client::new(),
+        arweave_gateways(&env_vars.mappings.arweave_gateways).expect("invalid Arweave gateway"),
+        env_vars.mappings.max_arweave_file_bytes as u64,
+        env_vars.mappings.arweave_timeout,
+        env_vars.mappings.arweave_request_limit,
+    );
+    let object_store_service = object_store_service(
+        reqwest::Client::new(),
+        ObjectStoreEndpoints::new(
+            &env_vars.mappings.object_store_s3_endpoint,
+            &env_vars.mappings.object_store_s3_region,
+            &env_vars.mappings.object_store_gcs_endpoint,
+        )
+        .expect("invalid object store endpoint"),
+        env_vars.mappings.max_object_store_file_bytes as u64,
+        env_vars.mappings.object_store_timeout,
+        env_vars.mappings.object_store_request_limit,
+    );
     let sg_count = Arc::new(SubgraphCountMetric::new(mock_registry.cheap_clone()));
 
     let blockchain_map = Arc::new(blockchain_map);
+    let notifier = Arc::new(Notifier::new(&logger, node_id.clone(), vec![]));
     let subgraph_instance_manager = SubgraphInstanceManager::new(
         &logger_factory,
         env_vars.cheap_clone(),
@@ -367,6 +447,9 @@ pub async fn setup<C: Blockchain>(
         mock_registry.clone(),
         link_resolver.cheap_clone(),
         ipfs_service,
+        arweave_service,
+        object_store_service,
+        notifier.cheap_clone(),
         static_filters,
     );
 
@@ -387,6 +470,7 @@ pub async fn setup<C: Blockchain>(
         graphql_runner.cheap_clone(),
         stores.network_store.cheap_clone(),
         link_resolver.cheap_clone(),
+        Arc::new(Vec::<ProviderInfo>::new()),
     ));
 
     // Create IPFS-based subgraph provider
@@ -408,6 +492,7 @@ pub async fn setup<C: Blockchain>(
         blockchain_map.clone(),
         node_id.clone(),
         SubgraphVersionSwitchingMode::Instant,
+        notifier,
     ));
 
     SubgraphRegistrar::create_subgraph(subgraph_registrar.as_ref(), subgraph_name.clone())
@@ -422,6 +507,7 @@ pub async fn setup<C: Blockchain>(
         None,
         None,
         graft_block,
+        false,
     )
     .await
     .expect("failed to create subgraph version");
@@ -576,17 +662,7 @@ where
         _filter: Arc<C::TriggerFilter>,
         _unified_api_version: graph::data::subgraph::UnifiedMappingApiVersion,
     ) -> anyhow::Result<Box<dyn BlockStream<C>>> {
-        let current_idx = current_block.map(|current_block| {
-            self.chain
-                .iter()
-                .enumerate()
-                .find(|(_, b)| b.ptr() == current_block)
-                .unwrap()
-                .0
-        });
-        Ok(Box::new(StaticStream {
-            stream: Box::pin(stream_events(self.chain.clone(), current_idx)),
-        }))
+        Ok(static_stream(self.chain.clone(), current_block))
     }
 
     async fn build_polling(
@@ -602,6 +678,27 @@ where
     }
 }
 
+/// A stream of the blocks in `chain` that starts after `current_block`
+fn static_stream<C: Blockchain>(
+    chain: Vec<BlockWithTriggers<C>>,
+    current_block: Option<BlockPtr>,
+) -> Box<dyn BlockStream<C>>
+where
+    C::TriggerData: Clone,
+{
+    let current_idx = current_block.map(|current_block| {
+        chain
+            .iter()
+            .enumerate()
+            .find(|(_, b)| b.ptr() == current_block)
+            .unwrap()
+            .0
+    });
+    Box::new(StaticStream {
+        stream: Box::pin(stream_events(chain, current_idx)),
+    })
+}
+
 struct StaticStream<C: Blockchain> {
     stream: Pin<Box<dyn Stream<Item = Result<BlockStreamEvent<C>, Error>> + Send>>,
 }
@@ -631,7 +728,9 @@ where
         let skip = current_idx.map(|idx| idx + 1).unwrap_or(0);
         let mut blocks_iter = blocks.iter().skip(skip).peekable();
         while let Some(&block) = blocks_iter.peek() {
-            if block.parent_ptr() == current_ptr {
+            // The first block does not need to be the genesis block, which
+            // lets recorded chains start anywhere
+            if current_ptr.is_none() || block.parent_ptr() == current_ptr {
                 current_ptr = Some(block.ptr());
                 current_parent_ptr = block.parent_ptr();
                 blocks_iter.next(); // Block consumed, advance the iterator.
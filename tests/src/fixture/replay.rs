@@ -0,0 +1,219 @@
+//! Chains that replay blocks recorded from a real network. They make it
+//! possible to reproduce indexing bugs from production exactly, and to run
+//! regression tests on real block data.
+//!
+//! Recordings are the files in a directory, replayed in the order of their
+//! names. A file with the extension `.json` holds a block, or an array of
+//! blocks, in the JSON form that the chain store keeps blocks in. A file
+//! with the extension `.pb` holds a block the way Firehose sends it, i.e.,
+//! an encoded `sf.ethereum.type.v2.Block`. Other files are ignored. Only
+//! Firehose blocks contain the calls of the block; call handlers never run
+//! on blocks from JSON files.
+//!
+//! Like the blocks of `ethereum::chain`, a block that is not a child of the
+//! block before it reverts the chain to its parent first. The triggers of
+//! each block are the ones that the filter of the subgraph selects, just
+//! like on a real chain.
+use std::convert::TryInto;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context as _, Error};
+use graph::blockchain::block_stream::{
+    BlockStream, BlockStreamBuilder, BlockWithTriggers, FirehoseCursor,
+};
+use graph::blockchain::{BlockPtr, TriggersAdapter, TriggersAdapterSelector};
+use graph::cheap_clone::CheapClone;
+use graph::components::store::DeploymentLocator;
+use graph::data::subgraph::UnifiedMappingApiVersion;
+use graph::prelude::prost::Message;
+use graph::prelude::{
+    async_trait, serde_json, BlockNumber, EthereumBlock, EthereumBlockWithCalls,
+    LightEthereumBlockExt,
+};
+use graph_chain_ethereum::chain::{parse_triggers, BlockFinality};
+use graph_chain_ethereum::{codec, Chain, NodeCapabilities, TriggerFilter};
+use slog::{o, Discard, Logger};
+
+use super::{ethereum::chain_with_stream, static_stream, Stores, TestChain};
+
+/// Load the blocks recorded in the files in `dir`
+pub fn load_blocks(dir: impl AsRef<Path>) -> Result<Vec<EthereumBlockWithCalls>, Error> {
+    let dir = dir.as_ref();
+    let mut paths = fs::read_dir(dir)
+        .with_context(|| format!("failed to read recorded blocks from {}", dir.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.sort();
+
+    let mut blocks = Vec::new();
+    for path in paths {
+        let read = || fs::read(&path).with_context(|| format!("failed to read {}", path.display()));
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => {
+                let value: serde_json::Value = serde_json::from_slice(&read()?)
+                    .with_context(|| format!("{} is not valid JSON", path.display()))?;
+                let values = match value {
+                    serde_json::Value::Array(values) => values,
+                    value => vec![value],
+                };
+                for value in values {
+                    let block: EthereumBlock =
+                        serde_json::from_value(value).with_context(|| {
+                            format!("{} does not contain Ethereum blocks", path.display())
+                        })?;
+                    blocks.push(EthereumBlockWithCalls {
+                        ethereum_block: block,
+                        calls: None,
+                    });
+                }
+            }
+            Some("pb") => {
+                let block = codec::Block::decode(read()?.as_slice())
+                    .with_context(|| format!("{} is not a Firehose block", path.display()))?;
+                blocks.push((&block).try_into()?);
+            }
+            _ => {}
+        }
+    }
+    Ok(blocks)
+}
+
+/// A chain that replays `blocks`, usually the result of `load_blocks`
+pub async fn replay_chain(
+    blocks: Vec<EthereumBlockWithCalls>,
+    stores: &Stores,
+) -> TestChain<Chain> {
+    let blocks = Arc::new(blocks);
+    let block_stream = Arc::new(ReplayStreamBuilder {
+        blocks: blocks.cheap_clone(),
+    });
+    let triggers_adapter = Arc::new(ReplayAdapterSelector { blocks });
+    chain_with_stream(block_stream, stores, triggers_adapter).await
+}
+
+/// The pointer to `block`, which is the block the subgraph has to sync to
+/// if `block` is the last recorded block
+pub fn block_ptr(block: &EthereumBlockWithCalls) -> BlockPtr {
+    block.ethereum_block.block.block_ptr()
+}
+
+/// Streams the recorded blocks with the triggers that the filter of the
+/// subgraph selects
+struct ReplayStreamBuilder {
+    blocks: Arc<Vec<EthereumBlockWithCalls>>,
+}
+
+#[async_trait]
+impl BlockStreamBuilder<Chain> for ReplayStreamBuilder {
+    async fn build_firehose(
+        &self,
+        _chain: &Chain,
+        _deployment: DeploymentLocator,
+        _block_cursor: FirehoseCursor,
+        _start_blocks: Vec<BlockNumber>,
+        current_block: Option<BlockPtr>,
+        filter: Arc<TriggerFilter>,
+        _unified_api_version: UnifiedMappingApiVersion,
+    ) -> anyhow::Result<Box<dyn BlockStream<Chain>>> {
+        let logger = Logger::root(Discard, o!());
+        let chain = self
+            .blocks
+            .iter()
+            .map(|block| parse_triggers(&logger, block.clone(), &filter))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(static_stream(chain, current_block))
+    }
+
+    async fn build_polling(
+        &self,
+        _chain: &Chain,
+        _deployment: DeploymentLocator,
+        _start_blocks: Vec<BlockNumber>,
+        _subgraph_current_block: Option<BlockPtr>,
+        _filter: Arc<TriggerFilter>,
+        _unified_api_version: UnifiedMappingApiVersion,
+    ) -> anyhow::Result<Box<dyn BlockStream<Chain>>> {
+        unimplemented!("only firehose mode should be used for tests")
+    }
+}
+
+/// Finds triggers in recorded blocks, which is needed for data sources that
+/// are created while a block is processed
+struct ReplayAdapterSelector {
+    blocks: Arc<Vec<EthereumBlockWithCalls>>,
+}
+
+impl TriggersAdapterSelector<Chain> for ReplayAdapterSelector {
+    fn triggers_adapter(
+        &self,
+        _loc: &DeploymentLocator,
+        _capabilities: &NodeCapabilities,
+        _unified_api_version: UnifiedMappingApiVersion,
+    ) -> Result<Arc<dyn TriggersAdapter<Chain>>, Error> {
+        Ok(Arc::new(ReplayTriggersAdapter {
+            blocks: self.blocks.cheap_clone(),
+        }))
+    }
+}
+
+struct ReplayTriggersAdapter {
+    blocks: Arc<Vec<EthereumBlockWithCalls>>,
+}
+
+impl ReplayTriggersAdapter {
+    fn block(&self, ptr: &BlockPtr) -> Option<&EthereumBlockWithCalls> {
+        self.blocks.iter().find(|block| &block_ptr(block) == ptr)
+    }
+}
+
+#[async_trait]
+impl TriggersAdapter<Chain> for ReplayTriggersAdapter {
+    async fn ancestor_block(
+        &self,
+        ptr: BlockPtr,
+        offset: BlockNumber,
+    ) -> Result<Option<BlockFinality>, Error> {
+        let mut block = self.block(&ptr);
+        for _ in 0..offset {
+            block = block
+                .and_then(|block| block.ethereum_block.block.parent_ptr())
+                .and_then(|parent| self.block(&parent));
+        }
+        Ok(block.map(|block| BlockFinality::NonFinal(block.clone())))
+    }
+
+    async fn scan_triggers(
+        &self,
+        _from: BlockNumber,
+        _to: BlockNumber,
+        _filter: &TriggerFilter,
+    ) -> Result<Vec<BlockWithTriggers<Chain>>, Error> {
+        unimplemented!("replayed blocks are only streamed")
+    }
+
+    async fn triggers_in_block(
+        &self,
+        logger: &Logger,
+        block: BlockFinality,
+        filter: &TriggerFilter,
+    ) -> Result<BlockWithTriggers<Chain>, Error> {
+        match block {
+            BlockFinality::NonFinal(block) => parse_triggers(logger, block, filter),
+            BlockFinality::Final(block) => {
+                Err(anyhow!("block {} was not replayed", block.block_ptr()))
+            }
+        }
+    }
+
+    async fn is_on_main_chain(&self, _ptr: BlockPtr) -> Result<bool, Error> {
+        unimplemented!("replayed blocks are only streamed")
+    }
+
+    async fn parent_ptr(&self, ptr: &BlockPtr) -> Result<Option<BlockPtr>, Error> {
+        self.block(ptr)
+            .map(|block| block.ethereum_block.block.parent_ptr())
+            .ok_or_else(|| anyhow!("block {} was not replayed", ptr))
+    }
+}
@@ -0,0 +1,206 @@
+//! A harness for testing subgraphs with the Rust test framework. It indexes
+//! the compiled subgraph in a build directory on a mock chain that produces
+//! blocks the test scripts, and lets the test check the entities and proofs
+//! of indexing that result.
+//!
+//! Instead of scripted blocks, a test can replay blocks that were recorded
+//! from a real network with `SubgraphTest::replay`.
+//!
+//! The subgraph is loaded from disk and does not need IPFS. Its data
+//! sources have to use the network `test`. The store uses the Postgres
+//! database in `THEGRAPH_STORE_POSTGRES_DIESEL_URL`, whose contents for the
+//! subgraph are replaced by every test.
+//!
+//! ```no_run
+//! use graph_tests::fixture::ethereum::{empty_block, genesis, push_test_log};
+//! use graph_tests::fixture::test_ptr;
+//! use graph_tests::harness::SubgraphTest;
+//!
+//! # async fn example() {
+//! let block0 = genesis();
+//! let mut block1 = empty_block(block0.ptr(), test_ptr(1));
+//! push_test_log(&mut block1, "hello");
+//!
+//! let run = SubgraphTest::new("my-subgraph", "./build")
+//!     .blocks(vec![block0, block1])
+//!     .run()
+//!     .await
+//!     .unwrap();
+//! assert!(run.entity("Greeting", "hello").await.is_some());
+//! assert!(run.poi(test_ptr(1)).await.is_some());
+//! # }
+//! ```
+use std::path::{Path, PathBuf};
+
+use graph::blockchain::block_stream::BlockWithTriggers;
+use graph::blockchain::BlockPtr;
+use graph::components::store::{EntityKey, ReadStore as _};
+use graph::data::subgraph::schema::SubgraphError;
+use graph::env::EnvVars;
+use graph::prelude::{
+    r, Entity, EthereumBlockWithCalls, QueryError, SubgraphName, SubgraphStore as _,
+};
+use graph_chain_ethereum::Chain;
+use graph_node::dev::LocalSubgraph;
+
+use crate::fixture::replay::{block_ptr, load_blocks, replay_chain};
+use crate::fixture::{self, ethereum::chain, stores_with_config, TestContext};
+
+/// The store configuration of the tests. It uses one database for
+/// everything and has a chain `test` without providers that are ever used
+const STORE_CONFIG: &str = r#"
+[store]
+[store.primary]
+connection = "$THEGRAPH_STORE_POSTGRES_DIESEL_URL"
+pool_size = 10
+
+[deployment]
+[[deployment.rule]]
+store = "primary"
+indexers = [ "default" ]
+
+[chains]
+ingestor = "default"
+
+[chains.test]
+shard = "primary"
+provider = [
+  { label = "test", url = "http://localhost:1/", features = [] }
+]
+"#;
+
+/// A test of the subgraph in a build directory against scripted blocks
+pub struct SubgraphTest {
+    name: String,
+    build_dir: PathBuf,
+    blocks: Vec<BlockWithTriggers<Chain>>,
+    recorded: Vec<EthereumBlockWithCalls>,
+    env_vars: Option<EnvVars>,
+}
+
+impl SubgraphTest {
+    /// Test the subgraph that `graph build` wrote to `build_dir`, which is
+    /// deployed under `name`
+    pub fn new(name: &str, build_dir: impl Into<PathBuf>) -> Self {
+        SubgraphTest {
+            name: name.to_string(),
+            build_dir: build_dir.into(),
+            blocks: vec![],
+            recorded: vec![],
+            env_vars: None,
+        }
+    }
+
+    /// Add `block` to the chain. A block that is not a child of the block
+    /// before it makes the chain revert to its parent first
+    pub fn block(mut self, block: BlockWithTriggers<Chain>) -> Self {
+        self.blocks.push(block);
+        self
+    }
+
+    pub fn blocks(mut self, blocks: impl IntoIterator<Item = BlockWithTriggers<Chain>>) -> Self {
+        self.blocks.extend(blocks);
+        self
+    }
+
+    /// Replay the blocks recorded in the files in `dir` instead of scripted
+    /// blocks. See `fixture::replay` for the format of the recordings
+    pub fn replay(mut self, dir: impl AsRef<Path>) -> Self {
+        let dir = dir.as_ref();
+        let blocks = load_blocks(dir)
+            .unwrap_or_else(|e| panic!("failed to load blocks from {}: {:#}", dir.display(), e));
+        self.recorded.extend(blocks);
+        self
+    }
+
+    /// Index with `env_vars` instead of the settings from the environment
+    pub fn env_vars(mut self, env_vars: EnvVars) -> Self {
+        self.env_vars = Some(env_vars);
+        self
+    }
+
+    /// Index all blocks of the chain. Fails with the error of the subgraph
+    /// if it fails on the last block
+    pub async fn run(self) -> Result<SubgraphTestRun, SubgraphError> {
+        let name = SubgraphName::new(self.name.as_str())
+            .expect("Subgraph name must contain only a-z, A-Z, 0-9, '-' and '_'");
+        let subgraph = LocalSubgraph::load(&self.build_dir).unwrap_or_else(|e| {
+            panic!(
+                "failed to load subgraph from {}: {:#}",
+                self.build_dir.display(),
+                e
+            )
+        });
+        assert!(
+            self.blocks.is_empty() || self.recorded.is_empty(),
+            "a subgraph test can not mix scripted and recorded blocks"
+        );
+
+        let stores = stores_with_config(STORE_CONFIG).await;
+        let (chain, stop_block) = match self.recorded.last() {
+            Some(last) => {
+                let stop_block = block_ptr(last);
+                (replay_chain(self.recorded, &stores).await, stop_block)
+            }
+            None => {
+                let stop_block = self
+                    .blocks
+                    .last()
+                    .expect("a subgraph test needs at least one block")
+                    .ptr();
+                (chain(self.blocks, &stores, None).await, stop_block)
+            }
+        };
+        let ctx = fixture::setup_local(name, subgraph, &stores, &chain, self.env_vars).await;
+
+        ctx.provider
+            .start(ctx.deployment.clone(), Some(stop_block.number))
+            .await
+            .expect("unable to start subgraph");
+        fixture::wait_for_sync(&ctx.logger, &ctx.store, &ctx.deployment, stop_block).await?;
+
+        Ok(SubgraphTestRun { ctx })
+    }
+}
+
+/// The state of a subgraph after a `SubgraphTest` indexed it
+pub struct SubgraphTestRun {
+    ctx: TestContext,
+}
+
+impl SubgraphTestRun {
+    /// The context of the test for checks that the run does not support
+    /// directly
+    pub fn context(&self) -> &TestContext {
+        &self.ctx
+    }
+
+    /// The entity of type `entity_type` with `id` at the last block
+    pub async fn entity(&self, entity_type: &str, id: &str) -> Option<Entity> {
+        let writable = self
+            .ctx
+            .store
+            .clone()
+            .writable(self.ctx.logger.clone(), self.ctx.deployment.id)
+            .await
+            .expect("failed to get writable store");
+        writable
+            .get(&EntityKey::data(entity_type, id))
+            .expect("failed to load entity")
+    }
+
+    /// Run the GraphQL `query` against the subgraph
+    pub async fn query(&self, query: &str) -> Result<Option<r::Value>, Vec<QueryError>> {
+        self.ctx.query(query).await
+    }
+
+    /// The proof of indexing of the subgraph at `block` without an indexer
+    /// address
+    pub async fn poi(&self, block: BlockPtr) -> Option<[u8; 32]> {
+        self.ctx
+            .store
+            .get_proof_of_indexing(&self.ctx.deployment.hash, &None, block, None)
+            .await
+            .expect("failed to compute proof of indexing")
+    }
+}
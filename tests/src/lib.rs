@@ -1,3 +1,4 @@
 pub mod docker_utils;
 pub mod fixture;
+pub mod harness;
 pub mod helpers;
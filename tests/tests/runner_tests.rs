@@ -333,7 +333,7 @@ async fn template_static_filters_false_positives() {
 
     let poi = ctx
         .store
-        .get_proof_of_indexing(&ctx.deployment.hash, &None, test_ptr(1))
+        .get_proof_of_indexing(&ctx.deployment.hash, &None, test_ptr(1), None)
         .await
         .unwrap();
 
@@ -413,6 +413,51 @@ async fn retry_create_ds() {
     assert_eq!(runner.context().instance().hosts().len(), 2);
 }
 
+#[tokio::test]
+async fn dynamic_data_source_limit() {
+    let RunnerTestRecipe {
+        stores,
+        subgraph_name,
+        hash,
+    } = RunnerTestRecipe::new("data-source-limit").await;
+
+    let blocks = {
+        let block_0 = genesis();
+        let block_1 = empty_block(block_0.ptr(), test_ptr(1));
+        let block_2 = empty_block(block_1.ptr(), test_ptr(2));
+        let block_3 = empty_block(block_2.ptr(), test_ptr(3));
+        vec![block_0, block_1, block_2, block_3]
+    };
+    let stop_block = blocks.last().unwrap().block.ptr();
+
+    let chain = chain(blocks, &stores, None).await;
+    let ctx = fixture::setup(subgraph_name.clone(), &hash, &stores, &chain, None, None).await;
+    ctx.store
+        .set_max_dynamic_data_sources(&ctx.deployment, Some(1))
+        .await
+        .unwrap();
+
+    // The subgraph creates a data source in every block and stops on the
+    // first restart after exceeding the limit
+    ctx.runner(stop_block)
+        .await
+        .run_for_test(true)
+        .await
+        .unwrap();
+
+    // The limit is specific to this node, so the error must be
+    // non-deterministic
+    let err = ctx
+        .store
+        .status_for_id(ctx.deployment.id)
+        .fatal_error
+        .expect("the subgraph failed");
+    assert!(!err.deterministic);
+    assert!(err
+        .message
+        .contains("limit of 1 dynamic data sources exceeded"));
+}
+
 #[tokio::test]
 async fn fatal_error() -> anyhow::Result<()> {
     let RunnerTestRecipe {